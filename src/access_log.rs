@@ -0,0 +1,59 @@
+// Combined Log Format output, as written by Apache and nginx, for existing log tooling
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::app::HttpLog;
+use crate::http;
+
+pub struct AccessLog {
+    file: File,
+}
+
+/// `"value"` with quotes and backslashes escaped the way nginx does, or `"-"`
+fn quoted(value: Option<&str>) -> String {
+    match value.filter(|v| !v.is_empty()) {
+        Some(v) => format!("\"{}\"", v.replace('\\', "\\x5C").replace('"', "\\x22")),
+        None => "\"-\"".to_string(),
+    }
+}
+
+/// One line for an exchange; None for tunnels and other entries without a request
+pub fn line(log: &HttpLog) -> Option<String> {
+    let raw = log.request_raw.bytes();
+    let head = http::RequestHead::parse(&raw)?;
+    let text = String::from_utf8_lossy(http::split_raw(&raw).0).to_string();
+    let version = text.lines().next().and_then(|l| l.rsplit(' ').next()).unwrap_or("HTTP/1.1");
+    let t = log.started?;
+    let month = &t.month().to_string()[..3];
+    let time = format!("{:02}/{}/{}:{:02}:{:02}:{:02} +0000", t.day(), month, t.year(), t.hour(), t.minute(), t.second());
+    let status = log.status().map_or("-".to_string(), |s| s.to_string());
+    let bytes = match http::split_raw(&log.response_raw.bytes()).1.len() {
+        0 => "-".to_string(),
+        n => n.to_string(),
+    };
+    Some(format!(
+        "{} - - [{}] {} {} {} {} {}",
+        log.client.map_or("-".to_string(), |ip| ip.to_string()),
+        time,
+        quoted(Some(&format!("{} {} {}", head.method, log.full_url(), version))),
+        status,
+        bytes,
+        quoted(head.header_str("referer")),
+        quoted(head.header_str("user-agent")),
+    ))
+}
+
+impl AccessLog {
+    /// Append to `path`, creating it if needed
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Ok(Self { file: OpenOptions::new().create(true).append(true).open(path)? })
+    }
+
+    pub fn write(&mut self, log: &HttpLog) {
+        if let Some(line) = line(log) {
+            let _ = writeln!(self.file, "{}", line);
+        }
+    }
+}
@@ -0,0 +1,326 @@
+// `belch --headless`: the proxy without the terminal UI, driven over a small HTTP API so CI jobs
+// and scripts can list what was captured, fetch bodies, replay, clear and export HAR, or follow
+// traffic live over a WebSocket. Flows are numbered from 1 in capture order, as in
+// `:triage export`; everything served goes through `--redact`. Calls need the token printed at
+// startup and are refused from web pages and through host names other than this machine's.
+
+use std::error::Error;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+
+use serde_json::{json, Value};
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{broadcast, mpsc};
+use tracing::{debug, info, warn};
+
+use crate::app::{App, Origin};
+use crate::export;
+use crate::http;
+use crate::macros;
+use crate::proxy::read_request;
+use crate::random;
+use crate::redact;
+use crate::send;
+use crate::websocket;
+
+pub const DEFAULT_ADDR: &str = "127.0.0.1:1336";
+
+const ENDPOINTS: &str = "GET /flows[?select=TERMS], GET /flows/ID, GET /flows/ID/request, GET /flows/ID/response, POST /flows/ID/replay, DELETE /flows, GET /har[?select=TERMS], GET /events (WebSocket)";
+
+/// Events a slow `/events` subscriber may fall behind by before it misses some
+const BACKLOG: usize = 1024;
+
+struct Reply {
+    status: &'static str,
+    content_type: String,
+    /// Further response headers
+    headers: Vec<(&'static str, String)>,
+    body: Vec<u8>,
+}
+
+impl Reply {
+    fn json(status: &'static str, value: Value) -> Self {
+        let body = serde_json::to_vec_pretty(&value).unwrap_or_default();
+        Reply { status, content_type: "application/json".to_string(), headers: Vec::new(), body }
+    }
+
+    fn error(status: &'static str, message: impl Into<String>) -> Self {
+        Reply::json(status, json!({ "error": message.into() }))
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        let mut head = format!("HTTP/1.1 {}\r\nContent-Type: {}\r\n", self.status, self.content_type);
+        for (name, value) in &self.headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str(&format!("Content-Length: {}\r\nConnection: close\r\n\r\n", self.body.len()));
+        let mut out = head.into_bytes();
+        out.extend_from_slice(&self.body);
+        out
+    }
+}
+
+/// What the flow list shows of entry `index`
+fn summary(app: &App, index: usize) -> Value {
+    let log = redact::outgoing(app, &app.logs[index]);
+    json!({
+        "id": index + 1,
+        "method": log.method(),
+        "url": log.full_url(),
+        "status": log.status(),
+        "duration_ms": log.duration.as_millis() as u64,
+        "request_size": log.request_size,
+        "response_size": log.response_size,
+        "response_sha256": log.response_sha256,
+        "tags": log.tags,
+        "origin": log.origin.name(),
+        "listener": log.listener,
+    })
+}
+
+/// `GET /events` message for entry `index`: the `:export jsonl` line with its flow ID
+pub fn event(app: &App, index: usize) -> String {
+    let mut value = export::exchange(&redact::outgoing(app, &app.logs[index]));
+    value["id"] = json!(index + 1);
+    value["origin"] = json!(app.logs[index].origin.name());
+    value.to_string()
+}
+
+/// Index into `logs` of flow `id`
+fn index(app: &App, id: &str) -> Result<usize, Reply> {
+    id.parse::<usize>().ok().filter(|n| *n > 0 && *n <= app.logs.len()).map(|n| n - 1)
+        .ok_or_else(|| Reply::error("404 Not Found", format!("no flow {}", id)))
+}
+
+/// The entries `select=TERMS` in the query picks, all of them without it
+fn selection(app: &App, query: &[(String, String)]) -> Result<Vec<usize>, Reply> {
+    let terms = query.iter().find(|(k, _)| k == "select").map_or("", |(_, v)| v.as_str());
+    export::Filter::parse(terms).and_then(|f| f.select(app)).map_err(|e| Reply::error("400 Bad Request", e))
+}
+
+/// A request or response body, decoded, as a download: captured HTML or script must not run with
+/// the API's origin. Its own Content-Type goes in `X-Belch-Content-Type`.
+fn body(app: &App, index: usize, request: bool) -> Reply {
+    let log = redact::outgoing(app, &app.logs[index]);
+    let raw = if request { log.request_raw.bytes() } else { log.response_raw.bytes() };
+    let (head, body) = http::split_raw(&raw);
+    let head = String::from_utf8_lossy(head);
+    let mut headers = vec![
+        ("X-Content-Type-Options", "nosniff".to_string()),
+        ("Content-Disposition", format!("attachment; filename=\"flow-{}-{}\"", index + 1, if request { "request" } else { "response" })),
+        ("Content-Security-Policy", "sandbox".to_string()),
+    ];
+    if let Some(original) = http::header_value(&head, "Content-Type") {
+        headers.push(("X-Belch-Content-Type", original.to_string()));
+    }
+    Reply { status: "200 OK", content_type: "application/octet-stream".to_string(), headers, body: http::decode_body(&head, body) }
+}
+
+/// Who may call the API
+struct Access {
+    /// Bearer token printed at startup
+    token: String,
+    bound: SocketAddr,
+}
+
+impl Access {
+    /// Refuse browsers, which send `Origin` and can't leave it out, Host names other than the bound
+    /// address or localhost, which a DNS-rebound page would carry, and calls without the token.
+    /// `query_token` is a `?token=` value, taken where clients can't set headers.
+    fn check(&self, head: &str, query_token: Option<&str>) -> Result<(), Reply> {
+        if http::header_value(head, "Origin").is_some() {
+            return Err(Reply::error("403 Forbidden", "requests from web pages are refused"));
+        }
+        let host = http::header_value(head, "Host").unwrap_or("");
+        if !self.host_allowed(host) {
+            return Err(Reply::error("403 Forbidden", format!("Host `{}` is not this API's address", host)));
+        }
+        let given = http::header_value(head, "Authorization").and_then(|v| v.strip_prefix("Bearer ")).map(str::trim).or(query_token);
+        match given.is_some_and(|t| self.token_matches(t)) {
+            true => Ok(()),
+            false => Err(Reply::error("401 Unauthorized", "send `Authorization: Bearer TOKEN` with the token belch printed at startup")),
+        }
+    }
+
+    fn host_allowed(&self, host: &str) -> bool {
+        let (name, port) = http::split_authority(host);
+        if port.and_then(|p| p.parse::<u16>().ok()) != Some(self.bound.port()) {
+            return false;
+        }
+        let bound = self.bound.ip();
+        if name.eq_ignore_ascii_case("localhost") {
+            return bound.is_loopback() || bound.is_unspecified();
+        }
+        // Only addresses, never other names: those are what rebinding needs
+        match name.parse::<IpAddr>() {
+            Ok(ip) => ip == bound || bound.is_unspecified() || (ip.is_loopback() && bound.is_loopback()),
+            Err(_) => false,
+        }
+    }
+
+    /// Compared in constant time
+    fn token_matches(&self, given: &str) -> bool {
+        given.len() == self.token.len() && given.bytes().zip(self.token.bytes()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+    }
+}
+
+/// Answer one API call; only replay waits on the network
+async fn route(app: &Arc<Mutex<App>>, method: &str, target: &str) -> Reply {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let query = http::parse_query(query);
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    if let (["flows", id, "replay"], "POST") = (segments.as_slice(), method) {
+        return replay(app, id).await;
+    }
+    let mut guard = app.lock().unwrap();
+    let result = match (method, segments.as_slice()) {
+        ("GET", ["flows"]) => selection(&guard, &query).map(|selected| {
+            Reply::json("200 OK", Value::Array(selected.into_iter().map(|i| summary(&guard, i)).collect()))
+        }),
+        ("GET", ["flows", id]) => index(&guard, id).map(|i| {
+            let log = redact::outgoing(&guard, &guard.logs[i]);
+            let mut value = summary(&guard, i);
+            value["request_head"] = json!(String::from_utf8_lossy(http::split_raw(&log.request_raw.bytes()).0));
+            value["response_head"] = json!(log.response_head());
+            value["note"] = json!(log.note);
+            value["triage"] = json!(log.triage.map(|s| s.name()));
+            Reply::json("200 OK", value)
+        }),
+        ("GET", ["flows", id, "request"]) => index(&guard, id).map(|i| body(&guard, i, true)),
+        ("GET", ["flows", id, "response"]) => index(&guard, id).map(|i| body(&guard, i, false)),
+        ("DELETE", ["flows"]) => {
+            let count = guard.logs.len();
+            guard.clear_logs();
+            info!("api: cleared {} flows", count);
+            Ok(Reply::json("200 OK", json!({ "cleared": count })))
+        }
+        ("GET", ["har"]) => selection(&guard, &query).map(|selected| {
+            let logs: Vec<_> = selected.iter().map(|i| redact::outgoing(&guard, &guard.logs[*i])).collect();
+            Reply::json("200 OK", export::har(logs.iter().map(|l| l.as_ref())))
+        }),
+        _ => Err(Reply::error("404 Not Found", format!("no endpoint {} {}; try {}", method, path, ENDPOINTS))),
+    };
+    result.unwrap_or_else(|e| e)
+}
+
+/// Send flow `id` again, with the session macros keep, and answer with the new flow
+async fn replay(app: &Arc<Mutex<App>>, id: &str) -> Reply {
+    let (log, before) = {
+        let guard = app.lock().unwrap();
+        if guard.read_only {
+            return Reply::error("403 Forbidden", "read-only mode: replay is off");
+        }
+        match index(&guard, id) {
+            Ok(i) if guard.read_only_for(&guard.logs[i].host) => {
+                return Reply::error("403 Forbidden", format!("{} is read-only: replay is off for it", guard.logs[i].host));
+            }
+            Ok(i) => (guard.logs[i].clone(), guard.logs.len()),
+            Err(reply) => return reply,
+        }
+    };
+    if log.request_raw.is_empty() {
+        return Reply::error("400 Bad Request", format!("flow {} has no request to replay", id));
+    }
+    let result = macros::send_with_session(app, &log, &send::replayable(&log), Origin::Replay).await;
+    let guard = app.lock().unwrap();
+    // Other traffic may have come in meanwhile
+    let sent = (before..guard.logs.len()).find(|i| guard.logs[*i].origin == Origin::Replay && guard.logs[*i].host == log.host);
+    match (result, sent) {
+        (Ok(_), Some(i)) => Reply::json("201 Created", summary(&guard, i)),
+        (Err((_, reason)), Some(i)) => Reply::json("502 Bad Gateway", json!({ "error": reason, "id": i + 1 })),
+        (Ok(_), None) => Reply::error("500 Internal Server Error", "the replayed flow was not logged"),
+        (Err((_, reason)), None) => Reply::error("502 Bad Gateway", reason),
+    }
+}
+
+/// Send each exchange captured from now on as a text message, until the subscriber closes
+async fn events(app: Arc<Mutex<App>>, client: TcpStream, key: &str) {
+    let Some(mut flows) = app.lock().unwrap().flow_stream.as_ref().map(|s| s.subscribe()) else { return };
+    let (mut reader, mut writer) = client.into_split();
+    let handshake = format!(
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        websocket::accept_key(key)
+    );
+    if writer.write_all(handshake.as_bytes()).await.is_err() {
+        return;
+    }
+    info!("api: /events subscriber connected");
+    // Frames are read on their own task: a read cut short by an event would lose its place
+    let (control, mut received) = mpsc::channel(8);
+    let read = tokio::spawn(async move {
+        while let Ok(message) = websocket::read_message(&mut reader).await {
+            if control.send(message).await.is_err() {
+                break;
+            }
+        }
+    });
+    loop {
+        let frame = tokio::select! {
+            flow = flows.recv() => match flow {
+                Ok(text) => websocket::frame(1, text.as_bytes()),
+                Err(broadcast::error::RecvError::Lagged(n)) => websocket::frame(1, json!({ "skipped": n }).to_string().as_bytes()),
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            message = received.recv() => match message {
+                Some((9, payload)) => websocket::frame(10, &payload),
+                Some((8, _)) | None => {
+                    let _ = writer.write_all(&websocket::frame(8, &[])).await;
+                    break;
+                }
+                Some(_) => continue,
+            },
+        };
+        if writer.write_all(&frame).await.is_err() {
+            break;
+        }
+    }
+    read.abort();
+    debug!("api: /events subscriber gone");
+}
+
+async fn handle(app: Arc<Mutex<App>>, mut client: TcpStream, access: Arc<Access>) {
+    let Some(request) = read_request(&mut client).await else { return };
+    let text = String::from_utf8_lossy(http::split_raw(&request).0).to_string();
+    let reply = match http::RequestHead::parse(&request) {
+        Some(head) if head.target.split('?').next() == Some("/events") => {
+            let query = http::parse_query(head.target.split_once('?').map_or("", |(_, q)| q));
+            let token = query.iter().find(|(k, _)| k == "token").map(|(_, v)| v.as_str());
+            match (access.check(&text, token), http::header_value(&text, "Sec-WebSocket-Key").filter(|_| websocket::is_upgrade(&head))) {
+                (Err(reply), _) => reply,
+                (Ok(()), Some(key)) => return events(app, client, key).await,
+                (Ok(()), None) => Reply::error("426 Upgrade Required", "/events is a WebSocket"),
+            }
+        }
+        Some(head) => match access.check(&text, None) {
+            Ok(()) => route(&app, head.method, head.target).await,
+            Err(reply) => reply,
+        },
+        None => Reply::error("400 Bad Request", "not an HTTP request"),
+    };
+    let _ = client.write_all(&reply.bytes()).await;
+}
+
+/// Serve the API on `addr` until Ctrl+C
+pub async fn run(app: Arc<Mutex<App>>, addr: &str) -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind(addr).await.map_err(|e| format!("--api {}: {}", addr, e))?;
+    app.lock().unwrap().flow_stream = Some(broadcast::channel(BACKLOG).0);
+    let access = Arc::new(Access { token: random::hex(16), bound: listener.local_addr()? });
+    println!("API on http://{} ({})", access.bound, ENDPOINTS);
+    println!("API token: {}", access.token);
+    let serve = async {
+        loop {
+            match listener.accept().await {
+                Ok((client, _)) => {
+                    tokio::spawn(handle(app.clone(), client, access.clone()));
+                }
+                Err(e) => warn!("api: accept failed: {}", e),
+            }
+        }
+    };
+    tokio::select! {
+        _ = serve => {}
+        result = tokio::signal::ctrl_c() => result?,
+    }
+    Ok(())
+}
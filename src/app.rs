@@ -0,0 +1,1348 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use time::OffsetDateTime;
+use tokio::sync::broadcast;
+
+use crate::access_log::AccessLog;
+use crate::api;
+use crate::authz::AuthMatrix;
+use crate::certs::Decoded;
+use crate::config::{self, CorsDev, Passthrough, Scope};
+use crate::cookies::{CookieJar, CookieOverrides};
+use crate::creds::{self, Credential};
+use crate::csrf::CsrfRules;
+use crate::diagnose::Diagnosis;
+use crate::diff::DiffView;
+use crate::editor::{External, TextArea};
+use crate::events::EventLog;
+use crate::filters::SavedFilters;
+use crate::fuzzer::FuzzRun;
+use crate::fuzzy::Finder;
+use crate::history::History;
+use crate::hosts::{self, HostSort};
+use crate::intercept::Paused;
+use crate::issues::Issue;
+use crate::jsonpath::{self, Query};
+use crate::listeners::Listener;
+use crate::macros::{Macro, Session, Step};
+use crate::markers::{Marker, Window};
+use crate::mirror::Mirror;
+use crate::rewrite::Rule;
+use crate::otel::Exporter;
+use crate::palette::Palette;
+use crate::pinning::PinCheck;
+use crate::recon::Found;
+use crate::redact::Redactions;
+use crate::redirects::Chains;
+use crate::repeat::RepeatRun;
+use crate::repeater::Repeater;
+use crate::selection::Selection;
+use crate::send_to::Menu;
+use crate::sitemap::{self, Source};
+use crate::smuggle::SmuggleRun;
+use crate::sniff::NonHttp;
+use crate::detail::Detail;
+use crate::store::Stored;
+use crate::tags::TagRules;
+use crate::trace::Dependency;
+use crate::transform::{Pipeline, Transform};
+use crate::{cache_bust, config_file, cookies, correlation, cors, csp, csrf, dns, docker, downgrade, export, faults, filters, forms, host_tls, http, listeners, markers, mirror, normalize, payloads, pinning, plugins, project, random, redact, reflect, rewrite, serialized, throttle, triage, vhost};
+use crate::upstream::Routes;
+use crate::websocket::{WsFilter, WsMessage};
+
+/// Narrowest and widest the Requests list may be, as a percentage, so both panes stay usable
+pub const MIN_SPLIT: u16 = 10;
+pub const MAX_SPLIT: u16 = 90;
+
+/// What made an entry's request
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub enum Origin {
+    /// A client through the proxy
+    #[default]
+    Live,
+    Repeater,
+    Fuzzer,
+    Spider,
+    /// A `:repeat` run, or a replay asked for over the `--headless` API
+    Replay,
+    /// The proxied half of a `:diagnose` comparison
+    Diagnose,
+    /// A copy of a live request sent to the `:mirror` upstream
+    Mirror,
+}
+
+impl Origin {
+    pub const ALL: [Origin; 7] = [Origin::Live, Origin::Repeater, Origin::Fuzzer, Origin::Spider, Origin::Replay, Origin::Diagnose, Origin::Mirror];
+    /// Marks requests belch's own tools send through the proxy; taken off before forwarding
+    pub const HEADER: &'static str = "X-Belch-Origin";
+
+    /// `HEADER` value for requests this tool sends: its name and a token only this process knows,
+    /// so a client can't pass its own traffic off as belch's
+    pub fn mark(self) -> String {
+        format!("{} {}", self.name(), mark_token())
+    }
+
+    /// The origin a `HEADER` value names, if it carries this process's token
+    pub fn from_mark(value: &str) -> Option<Origin> {
+        let (name, token) = value.split_once(' ')?;
+        (token == mark_token()).then(|| Origin::parse(name)).flatten()
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Origin::Live => "live",
+            Origin::Repeater => "repeater",
+            Origin::Fuzzer => "fuzzer",
+            Origin::Spider => "spider",
+            Origin::Replay => "replay",
+            Origin::Diagnose => "diagnose",
+            Origin::Mirror => "mirror",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Origin> {
+        Origin::ALL.into_iter().find(|o| o.name() == name.to_lowercase())
+    }
+}
+
+/// Random per run, so a `Origin::HEADER` from the wire never matches
+fn mark_token() -> &'static str {
+    static TOKEN: OnceLock<String> = OnceLock::new();
+    TOKEN.get_or_init(|| random::hex(16))
+}
+
+#[derive(Clone, Default)]
+pub struct HttpLog {
+    pub url: String,
+    pub host: String,
+    pub port: u16,
+    /// Request as shown in the detail pane
+    pub request: Stored,
+    /// Response as shown, when that isn't the raw response (e.g. a tunnel's byte counts); empty
+    /// otherwise, see `response_text`
+    pub response: Stored,
+    /// Request exactly as sent by the client
+    pub request_raw: Stored,
+    /// Response exactly as received from upstream
+    pub response_raw: Stored,
+    /// Intercepted from a CONNECT tunnel rather than sent as plain HTTP
+    pub tls: bool,
+    /// Server name the client sent in its TLS ClientHello
+    pub sni: Option<String>,
+    /// Address the client connected from
+    pub client: Option<IpAddr>,
+    /// Client connection it arrived on, numbered from 1; 0 when not known
+    pub conn: u64,
+    /// When the exchange started, set on capture
+    pub started: Option<OffsetDateTime>,
+    /// Time from upstream connect to the end of the response
+    pub duration: Duration,
+    /// Request body size as sent
+    pub request_size: usize,
+    /// Decoded response body size
+    pub response_size: usize,
+    /// SHA-256 of the decoded response body; empty when there was no response
+    pub response_sha256: String,
+    /// Set by the tag rules on capture
+    pub tags: Vec<String>,
+    /// Upgraded to a WebSocket; its messages are in `App::ws_messages`
+    pub websocket: bool,
+    /// Correlation and trace IDs as `(header, id)`, set on capture
+    pub correlation: Vec<(String, String)>,
+    pub origin: Origin,
+    /// Label of the listener it arrived on; empty for requests belch's tools sent themselves
+    pub listener: String,
+    /// Address the host resolved to, for direct connections
+    pub resolved: Option<dns::Lookup>,
+    /// Entry this one mirrors, for answers from the `:mirror` upstream
+    pub mirror_of: Option<usize>,
+    /// Triage note, exported with `:triage export`
+    pub note: String,
+    pub triage: Option<triage::Status>,
+}
+
+impl HttpLog {
+    /// Entry for a request a tool sent to `target`'s host itself, with the answer or why there was none
+    pub fn sent(target: &HttpLog, request: &[u8], response: Result<&[u8], &str>, duration: Duration, origin: Origin) -> HttpLog {
+        let (meth, path) = http::RequestHead::parse(request).map_or(("?".to_string(), "/".to_string()), |h| (h.method.to_string(), h.target.to_string()));
+        let url = format!("{} {} [{}: {}]{}", meth, path, if target.tls { "TLS" } else { "Host" }, target.host, if response.is_err() { " ✗" } else { "" });
+        let (shown, raw) = match response {
+            Ok(raw) => (String::new(), raw.to_vec()),
+            Err(reason) => (format!("[{}]", reason), Vec::new()),
+        };
+        HttpLog {
+            url,
+            host: target.host.clone(),
+            port: target.port,
+            tls: target.tls,
+            sni: target.sni.clone(),
+            request: String::from_utf8_lossy(request).replace("\r\n", "\n").into(),
+            response: shown.into(),
+            request_raw: request.to_vec().into(),
+            response_raw: raw.into(),
+            duration,
+            origin,
+            ..Default::default()
+        }
+    }
+    pub fn method(&self) -> String {
+        http::RequestHead::parse(&self.request_raw.head()).map_or(String::new(), |h| h.method.to_string())
+    }
+    /// Absolute URL, rebuilding the scheme and host for origin-form targets
+    pub fn full_url(&self) -> String {
+        let raw = self.request_raw.head();
+        let target = http::RequestHead::parse(&raw).map_or("/", |h| h.target);
+        if target.contains("://") {
+            target.to_string()
+        } else {
+            let scheme = if self.tls { "https" } else { "http" };
+            match self.port {
+                0 | 80 | 443 => format!("{}://{}{}", scheme, http::url_host(&self.host), target),
+                port => format!("{}://{}:{}{}", scheme, http::url_host(&self.host), port, target),
+            }
+        }
+    }
+    pub fn status(&self) -> Option<u16> {
+        http::status_code(&self.response_head())
+    }
+    /// Response head as text
+    pub fn response_head(&self) -> String {
+        String::from_utf8_lossy(http::split_raw(&self.response_raw.head()).0).to_string()
+    }
+    /// The response as shown in the detail pane
+    pub fn response_text(&self) -> String {
+        match self.response.is_empty() {
+            true => String::from_utf8_lossy(&self.response_raw.bytes()).replace("\r\n", "\n"),
+            false => self.response.text(),
+        }
+    }
+    /// Response body with transfer- and content-encoding removed
+    pub fn response_body(&self) -> Vec<u8> {
+        let raw = self.response_raw.bytes();
+        let (head, body) = http::split_raw(&raw);
+        http::decode_body(&String::from_utf8_lossy(head), body)
+    }
+    /// Set the body sizes and response hash, and find passive issues; needs nothing but the
+    /// entry, so capture does it before taking the App lock
+    pub fn analyze(&mut self) -> Vec<Issue> {
+        self.request_size = http::split_raw(&self.request_raw.bytes()).1.len();
+        let mut found = Vec::new();
+        if !self.response_raw.is_empty() {
+            let raw = self.response_raw.bytes();
+            let (head, body) = http::split_raw(&raw);
+            let (body, too_large) = http::decode_body_bounded(&String::from_utf8_lossy(head), body);
+            if too_large {
+                self.tags.push("decoded body too large".to_string());
+            }
+            self.response_size = body.len();
+            self.response_sha256 = http::sha256_hex(&body);
+            found.extend(reflect::check(self, &body).into_iter().chain(cors::passive(self)).chain(csp::passive(self)));
+        }
+        found.extend(vhost::passive(self).into_iter().chain(serialized::passive(self)));
+        found
+    }
+}
+
+/// Analyze an entry outside the lock, then add it; returns the session generation and its index
+pub fn capture(app: &Mutex<App>, mut entry: HttpLog) -> (u64, usize) {
+    let found = entry.analyze();
+    let mut guard = app.lock().unwrap();
+    guard.push_analyzed(entry, found);
+    (guard.generation, guard.logs.len() - 1)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum Tab {
+    #[default]
+    Requests,
+    Hosts,
+    Sitemap,
+    Credentials,
+    Issues,
+    Events,
+    Decoder,
+}
+
+impl Tab {
+    pub const ALL: [Tab; 7] = [Tab::Requests, Tab::Hosts, Tab::Sitemap, Tab::Credentials, Tab::Issues, Tab::Events, Tab::Decoder];
+    pub fn title(self) -> &'static str {
+        match self {
+            Tab::Requests => "Requests",
+            Tab::Hosts => "Hosts",
+            Tab::Sitemap => "Sitemap",
+            Tab::Credentials => "Credentials",
+            Tab::Issues => "Issues",
+            Tab::Events => "Events",
+            Tab::Decoder => "Decoder",
+        }
+    }
+    pub fn next(self) -> Tab {
+        let i = Tab::ALL.iter().position(|t| *t == self).unwrap_or(0);
+        Tab::ALL[(i + 1) % Tab::ALL.len()]
+    }
+}
+
+/// Ordering of the Requests list; capture order in `App::logs` is never changed
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortColumn {
+    #[default]
+    Time,
+    Duration,
+    RequestSize,
+    ResponseSize,
+    Status,
+    Host,
+    Hash,
+    Connection,
+}
+
+impl SortColumn {
+    pub const ALL: [SortColumn; 8] = [
+        SortColumn::Time,
+        SortColumn::Duration,
+        SortColumn::RequestSize,
+        SortColumn::ResponseSize,
+        SortColumn::Status,
+        SortColumn::Host,
+        SortColumn::Hash,
+        SortColumn::Connection,
+    ];
+    pub fn title(self) -> &'static str {
+        match self {
+            SortColumn::Time => "time",
+            SortColumn::Duration => "duration",
+            SortColumn::RequestSize => "request size",
+            SortColumn::ResponseSize => "response size",
+            SortColumn::Status => "status",
+            SortColumn::Host => "host",
+            SortColumn::Hash => "hash",
+            SortColumn::Connection => "connection",
+        }
+    }
+    /// Column named in a `:sort` command
+    pub fn parse(name: &str) -> Option<SortColumn> {
+        Some(match name {
+            "time" => SortColumn::Time,
+            "duration" | "dur" => SortColumn::Duration,
+            "req" | "reqsize" | "request" => SortColumn::RequestSize,
+            "size" | "resp" | "response" => SortColumn::ResponseSize,
+            "status" => SortColumn::Status,
+            "host" => SortColumn::Host,
+            "hash" => SortColumn::Hash,
+            "conn" | "connection" => SortColumn::Connection,
+            _ => return None,
+        })
+    }
+    pub fn next(self) -> SortColumn {
+        let i = SortColumn::ALL.iter().position(|c| *c == self).unwrap_or(0);
+        SortColumn::ALL[(i + 1) % SortColumn::ALL.len()]
+    }
+}
+
+/// Passthrough list editor popup
+#[derive(Default)]
+pub struct PassthroughEditor {
+    pub selected: usize,
+    pub input: Option<String>,
+}
+
+/// Recon popup: URLs, domains and IPs seen in responses
+#[derive(Default)]
+pub struct ReconView {
+    pub found: Vec<Found>,
+    pub selected: usize,
+}
+
+/// Certificate decoder popup
+#[derive(Default)]
+pub struct DecoderView {
+    /// What was decoded: an entry's URL or pasted text
+    pub source: String,
+    pub decoded: Vec<Decoded>,
+    pub scroll: usize,
+}
+
+/// Payload list manager popup
+pub struct PayloadsView {
+    pub lists: Vec<payloads::List>,
+    pub selected: usize,
+    pub scroll: usize,
+}
+
+impl PayloadsView {
+    /// Reread the lists, selecting `name` if given
+    pub fn load(name: Option<&str>) -> Self {
+        let lists = payloads::all();
+        let selected = name.and_then(|n| lists.iter().position(|l| l.name == n)).unwrap_or(0);
+        PayloadsView { lists, selected, scroll: 0 }
+    }
+}
+
+/// Device onboarding popup
+#[derive(Default)]
+pub struct DeviceSetup {
+    pub url: String,
+    pub qr: Vec<String>,
+    pub ios: bool,
+}
+
+#[derive(Default)]
+pub struct App {
+    pub logs: VecDeque<HttpLog>,
+    /// Bumped whenever `logs` is emptied: an index kept across an await is only good with the
+    /// generation it was taken in
+    pub generation: u64,
+    /// Position in `view()`, not in `logs`
+    pub selected: usize,
+    /// `G` was pressed and the newest entry is still selected, so new ones get selected as they arrive
+    pub following: bool,
+    /// Entries captured when the newest was last selected; None until the list is drawn
+    pub seen: Option<usize>,
+    pub sort: SortColumn,
+    pub sort_desc: bool,
+    /// Only show entries whose response has this hash
+    pub identical_to: Option<String>,
+    /// Only show entries with this tag
+    pub tag_filter: Option<String>,
+    /// Filters from `filters.txt` with the entries each matches
+    pub saved_filters: SavedFilters,
+    /// Only show entries matching the saved filter of this name
+    pub saved_filter: Option<String>,
+    /// The Requests tab's sidebar of saved filters and tags is shown
+    pub sidebar: bool,
+    /// Only show entries from this origin
+    pub origin_filter: Option<Origin>,
+    /// Only show entries that arrived on the listener with this label
+    pub listener_filter: Option<String>,
+    /// Named moments of the capture, oldest first
+    pub markers: Vec<Marker>,
+    /// Only show entries started in this window
+    pub window: Option<Window>,
+    /// Entries marked with `m` to go in a report, by index into `logs`
+    pub evidence: HashSet<usize>,
+    /// Only show entries from this client connection
+    pub conn_filter: Option<u64>,
+    /// Lowercased; only entries with a correlation ID containing it are shown
+    pub corr_filter: Option<String>,
+    pub tag_rules: TagRules,
+    /// Masking rules from `redact.txt`, applied to exports and project saves while `redact` is set
+    pub redactions: Redactions,
+    pub redact: bool,
+    /// Where state-changing resends get a fresh CSRF token
+    pub csrf: CsrfRules,
+    /// Lowercased `:find` text; only entries containing it are shown
+    pub search: Option<String>,
+    /// Indices into `logs` matching `search`, kept current as traffic arrives
+    search_hits: HashSet<usize>,
+    /// `:where` query; only entries whose JSON response passes it are shown
+    pub where_filter: Option<Query>,
+    /// Indices into `logs` passing `where_filter`, kept current as traffic arrives
+    where_hits: HashSet<usize>,
+    /// `:jq` query, evaluated on the selected entry's JSON response in the detail pane
+    pub body_query: Option<Query>,
+    /// Messages of every WebSocket connection, in arrival order
+    pub ws_messages: Vec<WsMessage>,
+    /// Which WebSocket messages the detail pane lists
+    pub ws_filter: WsFilter,
+    /// Event type the server-sent events list is narrowed to by `:sse`
+    pub sse_filter: Option<String>,
+    pub redirects: Chains,
+    /// First entries of redirect chains listed in full rather than as one row
+    pub expanded: HashSet<usize>,
+    /// Entry traced by `:trace` and where its values came from
+    pub trace: Option<(usize, Vec<Dependency>)>,
+    /// First visible line of the detail pane
+    pub detail_scroll: usize,
+    /// Show the selected response body untruncated
+    pub full_body: bool,
+    /// Width of the Requests list, as a percentage of the screen
+    pub split: u16,
+    /// Detail pane takes the whole Requests tab
+    pub zoomed: bool,
+    /// Detail pane shows request and response headers side by side
+    pub compare_headers: bool,
+    /// Detail pane shows HTML responses as readable text
+    pub html_preview: bool,
+    /// Text selected in the detail pane, while selecting
+    pub selection: Option<Selection>,
+    /// Hold in-scope requests for editing before they are forwarded
+    pub intercepting: bool,
+    /// Held requests, oldest first; the first is the one being edited
+    pub paused: VecDeque<Paused>,
+    pub passthrough: Passthrough,
+    pub scope: Scope,
+    /// Hosts whose responses are made CORS-permissive on the way to the client
+    pub cors_dev: CorsDev,
+    /// Upstream proxy per host
+    pub routes: Routes,
+    /// Forward plain HTTP as a bare request line and `Host` instead of the client's bytes
+    pub minimal_forward: bool,
+    /// Capture only: traffic passes unchanged and nothing is sent on belch's own initiative
+    pub read_only: bool,
+    /// Host patterns read-only as above while the rest of the traffic isn't, set with `:read-only HOST`
+    pub read_only_hosts: Vec<String>,
+    pub non_http: NonHttp,
+    /// `--access-log` file, appended to as traffic is captured
+    pub access_log: Option<AccessLog>,
+    /// Set by `--otlp`: a span is exported for every captured exchange
+    pub otel: Option<Exporter>,
+    /// Set by `--headless`: every captured exchange goes out as JSON to `GET /events` subscribers
+    pub flow_stream: Option<broadcast::Sender<String>>,
+    pub passthrough_editor: Option<PassthroughEditor>,
+    pub finder: Option<Finder>,
+    pub palette: Option<Palette>,
+    /// The detail pane's decoders' findings for the selected entry
+    pub detail: Detail,
+    pub show_ca: bool,
+    pub recon: Option<ReconView>,
+    pub decoder: Option<DecoderView>,
+    pub repeat: Option<RepeatRun>,
+    /// The repeat chart popup is open; hiding it leaves the run going
+    pub show_repeat: bool,
+    pub authz: Option<AuthMatrix>,
+    pub show_authz: bool,
+    pub smuggle: Option<SmuggleRun>,
+    pub show_smuggle: bool,
+    /// Kept while hidden, so `:repeater` brings back the edits and the last answer
+    pub repeater: Option<Repeater>,
+    pub show_repeater: bool,
+    /// Buffer to open in `$EDITOR` before the next frame
+    pub external_edit: Option<External>,
+    pub fuzz: Option<FuzzRun>,
+    /// Certificate pinning check waiting on the client's next connections
+    pub pin_check: Option<PinCheck>,
+    /// Direct vs proxied comparison shown in a popup
+    pub diagnosis: Option<Diagnosis>,
+    /// Cookies pinned for outbound requests, and the jar popup listing what was seen
+    pub cookie_overrides: CookieOverrides,
+    pub cookie_jar: Option<CookieJar>,
+    /// Strip conditional and caching headers for in-scope hosts
+    pub no_cache: bool,
+    /// Started as `belch docker`: entries get their Engine API operation and pretty JSON
+    pub docker: bool,
+    /// Second upstream that requests are copied to
+    pub mirror: Option<Mirror>,
+    /// Match/replace rules, in order
+    pub rewrites: Vec<Rule>,
+    /// config.toml, or the file --config named
+    pub config_path: PathBuf,
+    /// The `[export]` table, written back by `:config save`
+    pub export_defaults: config_file::Export,
+    /// The `s` menu on an entry
+    pub send_to: Option<Menu>,
+    /// Entry picked as the first side of a diff, and the diff once the second is picked
+    pub diff_base: Option<usize>,
+    pub diff: Option<DiffView>,
+    pub payloads: Option<PayloadsView>,
+    /// Decoder tab input and stages
+    pub pipeline: Pipeline,
+    pub show_fuzz: bool,
+    /// Macro collecting captured requests as steps, until `:macro stop`
+    pub macro_recording: Option<Macro>,
+    /// Macro replayed when a repeat send finds the session expired
+    pub auto_macro: Option<String>,
+    /// Cookies and values from the last macro run
+    pub macro_session: Option<Session>,
+    pub macro_running: bool,
+    /// Address of the first listener, which belch's own tools send through
+    pub listen: String,
+    pub listeners: Vec<Listener>,
+    /// Label of the listener each client connection arrived on
+    pub conn_listeners: HashMap<u64, String>,
+    pub device_setup: Option<DeviceSetup>,
+    pub setup_url: Option<String>,
+    /// Capture times within the last minute, for the footer sparkline
+    pub request_times: VecDeque<Instant>,
+    pub active_connections: usize,
+    /// Client connections accepted so far, which is how they are numbered
+    pub connections_seen: u64,
+    /// Memory saved by compressing stored messages
+    pub stored_saved: usize,
+    pub tab: Tab,
+    pub events: EventLog,
+    /// `:` command being typed in the footer
+    pub command: Option<String>,
+    pub history: History,
+    /// Transient footer message and when it was set
+    pub status: Option<(String, Instant)>,
+    pub credentials: Vec<Credential>,
+    pub cred_selected: usize,
+    /// Indices into `credentials` shown unmasked
+    pub cred_revealed: Vec<usize>,
+    pub issues: Vec<Issue>,
+    pub issue_selected: usize,
+    pub host_sort: HostSort,
+    pub host_sort_reversed: bool,
+    pub host_selected: usize,
+    pub sitemap_selected: usize,
+    /// URLs listed by robots.txt and sitemaps, whether or not they were requested since
+    pub seeded: BTreeMap<String, Source>,
+    pub sitemap_tree: sitemap::Cache,
+    /// Stop flag of the running spider
+    pub spider: Option<Arc<AtomicBool>>,
+}
+
+impl App {
+    pub fn new(passthrough: Passthrough, scope: Scope, listen: String, events: EventLog) -> Self {
+        Self { passthrough, scope, listen, events, split: config::load_split().unwrap_or(50).clamp(MIN_SPLIT, MAX_SPLIT), ..Default::default() }
+    }
+    /// Whether `host` is in scope for traffic on `listener`: the listener's own scope when it has one
+    pub fn in_scope(&self, listener: &str, host: &str) -> bool {
+        self.listeners.iter().find(|l| l.label == listener).and_then(|l| l.covers(host)).unwrap_or_else(|| self.scope.contains(host))
+    }
+    pub fn set_status(&mut self, msg: impl Into<String>) {
+        self.status = Some((msg.into(), Instant::now()));
+    }
+    pub fn push_log(&mut self, mut entry: HttpLog) {
+        let found = entry.analyze();
+        self.push_analyzed(entry, found);
+    }
+    /// `push_log` for an entry `analyze` already ran on, with the issues it found
+    pub fn push_analyzed(&mut self, mut entry: HttpLog, found: Vec<Issue>) {
+        let now = Instant::now();
+        self.request_times.push_back(now);
+        while self.request_times.front().is_some_and(|t| now.duration_since(*t) > Duration::from_secs(60)) {
+            self.request_times.pop_front();
+        }
+        entry.started = Some(OffsetDateTime::now_utc() - entry.duration);
+        if let Some(label) = self.conn_listeners.get(&entry.conn).filter(|_| entry.listener.is_empty()) {
+            entry.listener = label.clone();
+        }
+        self.ingest(entry, found);
+        if let (Some(file), Some(log)) = (self.access_log.as_mut(), self.logs.back()) {
+            file.write(log);
+        }
+        if let (Some(otel), Some(log)) = (&self.otel, self.logs.back()) {
+            otel.record(log);
+        }
+        if let Some(stream) = self.flow_stream.as_ref().filter(|s| s.receiver_count() > 0) {
+            let _ = stream.send(api::event(self, self.logs.len() - 1));
+        }
+        // A macro is what the browser did, not what the tools sent meanwhile
+        if let (Some(m), Some(log)) = (self.macro_recording.as_mut(), self.logs.back().filter(|l| l.origin == Origin::Live)) {
+            m.steps.extend(Step::from_log(log));
+        }
+    }
+    /// Add an entry `analyze` ran on, with the issues it found, deriving credentials and tags
+    pub fn ingest(&mut self, mut entry: HttpLog, found: Vec<Issue>) {
+        for cred in creds::extract(&entry.request_raw.bytes(), &entry.host) {
+            if !self.credentials.contains(&cred) {
+                self.credentials.push(cred);
+                // Keep grouped by host
+                self.credentials.sort_by(|a, b| a.host.cmp(&b.host));
+            }
+        }
+        let raw = entry.request_raw.head();
+        if let Some(origin) = http::header_value(&String::from_utf8_lossy(http::split_raw(&raw).0), Origin::HEADER).and_then(Origin::from_mark) {
+            entry.origin = origin;
+        }
+        entry.correlation = correlation::extract(&entry);
+        if self.docker {
+            docker::label(&mut entry);
+        }
+        self.stored_saved += [&entry.request, &entry.response, &entry.request_raw, &entry.response_raw]
+            .iter().map(|s| s.saved()).sum::<usize>();
+        for issue in found {
+            self.add_issue(issue);
+        }
+        for tag in self.tag_rules.apply(&entry) {
+            if !entry.tags.contains(&tag) {
+                entry.tags.push(tag);
+            }
+        }
+        let index = self.logs.len();
+        self.redirects.add(index, &entry);
+        self.saved_filters.ingest(index, &entry);
+        self.logs.push_back(entry);
+        if self.search.as_ref().is_some_and(|s| self.entry_contains(index, s)) {
+            self.search_hits.insert(index);
+        }
+        if self.where_filter.as_ref().is_some_and(|q| jsonpath::response_json(&self.logs[index]).is_some_and(|json| q.matches(&json))) {
+            self.where_hits.insert(index);
+        }
+    }
+    /// Drop every entry and what was derived from them
+    pub fn clear_logs(&mut self) {
+        self.logs.clear();
+        self.generation += 1;
+        self.issues.clear();
+        self.credentials.clear();
+        self.ws_messages.clear();
+        self.redirects = Default::default();
+        self.expanded.clear();
+        self.evidence.clear();
+        self.saved_filters.clear_hits();
+        self.trace = None;
+        self.detail = Detail::default();
+        self.sitemap_tree = Default::default();
+        self.search = None;
+        self.search_hits.clear();
+        self.where_hits.clear();
+        self.diff_base = None;
+        self.cred_selected = 0;
+        self.cred_revealed.clear();
+        self.issue_selected = 0;
+        self.host_selected = 0;
+        self.sitemap_selected = 0;
+        self.stored_saved = 0;
+        self.identical_to = None;
+        self.conn_filter = None;
+        self.corr_filter = None;
+        self.selected = 0;
+        self.seen = None;
+    }
+    /// Add what a streamed response received since the last call, unless `:open` replaced its
+    /// entry; the body is decoded and hashed once, when the stream is `done`
+    pub fn extend_response(&mut self, index: usize, url: &str, more: &[u8], duration: Duration, done: bool) {
+        let Some(log) = self.logs.get_mut(index).filter(|l| l.url == url) else { return };
+        log.response_raw.append(more);
+        log.duration = duration;
+        if !done {
+            log.response_size += more.len();
+            return;
+        }
+        log.response_raw = log.response_raw.bytes().into();
+        let body = log.response_body();
+        log.response_size = body.len();
+        log.response_sha256 = http::sha256_hex(&body);
+    }
+    /// Record a WebSocket message, unless its connection was dropped by `:open`
+    pub fn push_ws(&mut self, message: WsMessage) {
+        if !self.logs.get(message.entry).is_some_and(|l| l.websocket) {
+            return;
+        }
+        if self.search.as_ref().is_some_and(|s| payload_contains(&message.payload, s)) {
+            self.search_hits.insert(message.entry);
+        }
+        self.ws_messages.push(message);
+    }
+    /// WebSocket messages of entry `index` passing the `:ws` filter and the search
+    pub fn ws_shown(&self, index: usize) -> Vec<&WsMessage> {
+        self.ws_messages.iter()
+            .filter(|m| m.entry == index && self.ws_filter.matches(m))
+            .filter(|m| self.search.as_ref().is_none_or(|s| payload_contains(&m.payload, s)))
+            .collect()
+    }
+    /// Request, decoded response body or any WebSocket payload contains `needle` (lowercase)
+    fn entry_contains(&self, index: usize, needle: &str) -> bool {
+        let log = &self.logs[index];
+        log.request.text().to_lowercase().contains(needle)
+            || payload_contains(&log.response_body(), needle)
+            || self.ws_messages.iter().any(|m| m.entry == index && payload_contains(&m.payload, needle))
+    }
+    /// Indices into `logs` in display order
+    pub fn view(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.logs.len())
+            .filter(|i| self.identical_to.as_ref().is_none_or(|h| self.logs[*i].response_sha256 == *h))
+            .filter(|i| self.tag_filter.as_ref().is_none_or(|t| self.logs[*i].tags.contains(t)))
+            .filter(|i| self.saved_filter.as_ref().is_none_or(|f| self.saved_filters.contains(f, *i)))
+            .filter(|i| self.origin_filter.is_none_or(|o| self.logs[*i].origin == o))
+            .filter(|i| self.listener_filter.as_ref().is_none_or(|l| self.logs[*i].listener == *l))
+            .filter(|i| self.window.as_ref().is_none_or(|w| w.contains(&self.markers, self.logs[*i].started)))
+            .filter(|i| self.conn_filter.is_none_or(|c| self.logs[*i].conn == c))
+            .filter(|i| self.corr_filter.as_ref().is_none_or(|c| self.logs[*i].correlation.iter().any(|(_, id)| id.to_lowercase().contains(c))))
+            .filter(|i| self.search.is_none() || self.search_hits.contains(i))
+            .filter(|i| self.where_filter.is_none() || self.where_hits.contains(i))
+            .collect();
+        // Stable sorts keep capture order among equal keys
+        match self.sort {
+            SortColumn::Time => {}
+            SortColumn::Duration => order.sort_by_key(|i| self.logs[*i].duration),
+            SortColumn::RequestSize => order.sort_by_key(|i| self.logs[*i].request_size),
+            SortColumn::ResponseSize => order.sort_by_key(|i| self.logs[*i].response_size),
+            SortColumn::Status => order.sort_by_key(|i| self.logs[*i].status()),
+            SortColumn::Host => order.sort_by(|a, b| self.logs[*a].host.cmp(&self.logs[*b].host)),
+            SortColumn::Hash => order.sort_by(|a, b| self.logs[*a].response_sha256.cmp(&self.logs[*b].response_sha256)),
+            SortColumn::Connection => order.sort_by_key(|i| self.logs[*i].conn),
+        }
+        if self.sort_desc {
+            order.reverse();
+        }
+        // Redirect chains fold into their first request unless expanded
+        let shown: HashSet<usize> = order.iter().copied().collect();
+        let mut grouped = Vec::with_capacity(order.len());
+        for i in order {
+            if self.redirects.prev(i).is_some_and(|p| shown.contains(&p)) {
+                continue;
+            }
+            grouped.push(i);
+            if self.expanded.contains(&i) {
+                grouped.extend(self.redirects.chain(i).into_iter().skip(1).take_while(|n| shown.contains(n)));
+            }
+        }
+        grouped
+    }
+    /// Mark the selected entry as report evidence, or unmark it; `:export FORMAT marked` writes the marked ones
+    pub fn toggle_evidence(&mut self) {
+        let Some(index) = self.view().get(self.selected).copied() else { return };
+        let marked = self.evidence.insert(index);
+        if !marked {
+            self.evidence.remove(&index);
+        }
+        let count = self.evidence.len();
+        self.set_status(format!("{} as evidence ({} marked; :export md marked writes them)", if marked { "Marked" } else { "Unmarked" }, count));
+    }
+    /// Open the selected entry's response body, or its request body, in `$EDITOR` to read
+    pub fn edit_body(&mut self, response: bool) {
+        let Some(index) = self.view().get(self.selected).copied() else { return };
+        let log = &self.logs[index];
+        let raw = if response { log.response_raw.bytes() } else { log.request_raw.bytes() };
+        if http::split_raw(&raw).1.is_empty() {
+            return self.set_status(format!("The {} has no body", if response { "response" } else { "request" }));
+        }
+        self.external_edit = Some(External::Entry { index, response });
+    }
+    /// Expand the selected redirect chain, or fold the one it belongs to
+    pub fn toggle_chain(&mut self) {
+        let Some(index) = self.view().get(self.selected).copied() else { return };
+        let mut at = Some(index);
+        while let Some(i) = at {
+            if self.expanded.remove(&i) {
+                // The fold hides the selected hop; select the chain instead
+                self.selected = self.view().iter().position(|v| *v == i).unwrap_or(0);
+                return;
+            }
+            at = self.redirects.prev(i);
+        }
+        if self.redirects.chain(index).len() > 1 {
+            self.keep_selection(|a| { a.expanded.insert(index); });
+        }
+    }
+    /// While following (`G` starts it, moving away from the newest entry stops it) select each new
+    /// entry of `view`, the list as drawn; otherwise leave the selection alone and return how many
+    /// visible entries arrived since the newest was last selected
+    pub fn follow(&mut self, view: &[usize]) -> usize {
+        let len = self.logs.len();
+        let seen = *self.seen.get_or_insert(len);
+        let Some(newest) = view.iter().copied().max() else {
+            self.seen = Some(len);
+            return 0;
+        };
+        if self.following && newest >= seen {
+            self.selected = view.iter().position(|i| *i == newest).unwrap_or(0);
+            self.reset_detail();
+        }
+        let at_newest = view.get(self.selected) == Some(&newest);
+        self.following &= at_newest;
+        if at_newest {
+            self.seen = Some(len);
+            return 0;
+        }
+        view.iter().filter(|i| **i >= seen).count()
+    }
+    /// Select the newest visible entry and follow new ones
+    pub fn jump_newest(&mut self) {
+        let view = self.view();
+        if let Some(p) = view.iter().copied().max().and_then(|newest| view.iter().position(|i| *i == newest)) {
+            self.selected = p;
+            self.following = true;
+            self.reset_detail();
+        }
+    }
+    /// Apply a view change, keeping the selected entry selected while it stays visible
+    pub fn keep_selection(&mut self, change: impl FnOnce(&mut Self)) {
+        let current = self.view().get(self.selected).copied();
+        change(self);
+        self.selected = current.and_then(|c| self.view().iter().position(|i| *i == c)).unwrap_or(0);
+    }
+    pub fn cycle_sort(&mut self) {
+        self.keep_selection(|a| a.sort = a.sort.next());
+    }
+    pub fn reverse_sort(&mut self) {
+        self.keep_selection(|a| a.sort_desc = !a.sort_desc);
+    }
+    /// Run a `:` command line
+    pub fn run_command(&mut self, line: &str) {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            None => {}
+            Some("sort") => {
+                let column = words.next().unwrap_or("time");
+                let Some(sort) = SortColumn::parse(column) else {
+                    return self.set_status(format!("Unknown sort column `{}`: time, duration, req, size, status, host, hash", column));
+                };
+                let desc = match words.next() {
+                    None | Some("asc") => false,
+                    Some("desc") => true,
+                    Some(other) => return self.set_status(format!("Unknown sort order `{}`: asc or desc", other)),
+                };
+                self.keep_selection(|a| {
+                    a.sort = sort;
+                    a.sort_desc = desc;
+                });
+            }
+            Some("save") => {
+                let result = match words.next() {
+                    Some(file) => project::save(self, Path::new(file)).map(|_| PathBuf::from(file)),
+                    None => project::to_bytes(self)
+                        .and_then(|data| export::write_stamped(Path::new("."), "belch", "belch", data).map_err(|e| e.to_string())),
+                };
+                match result {
+                    Ok(path) => self.set_status(format!("Project saved to {}", path.display())),
+                    Err(e) => self.set_status(format!("Save failed: {}", e)),
+                }
+            }
+            Some("export") => {
+                let Some(name) = words.next() else { return self.set_status(format!("usage: :export har|jsonl|xml|md|reqwest|pytest [{}] | :export PLUGIN", export::Filter::USAGE)) };
+                if let Some(format) = export::Format::parse(name) {
+                    let text = words.collect::<Vec<_>>().join(" ");
+                    // Without terms, what the Requests list shows
+                    let filter = export::Filter::parse(if text.is_empty() { "view" } else { &text });
+                    let result = filter.and_then(|f| export::session(self, Path::new("."), format, &f));
+                    return match result {
+                        Ok((path, n)) => self.set_status(format!("Exported {} entries to {}", n, path.display())),
+                        Err(e) => self.set_status(format!("Export failed: {}", e)),
+                    };
+                }
+                let Some(plugin) = plugins::all().iter().find(|p| p.name == name) else {
+                    return self.set_status(format!("No plugin `{}` loaded", name));
+                };
+                let logs: Vec<_> = self.view().into_iter().map(|i| redact::outgoing(self, &self.logs[i])).collect();
+                let jsonl = export::jsonl(logs.iter().map(|l| l.as_ref()));
+                let result = match plugin.export(jsonl.as_bytes()) {
+                    Some(data) => export::write_stamped(Path::new("."), name, "out", data).map_err(|e| e.to_string()),
+                    None => Err(format!("{} has no exporter or produced nothing", name)),
+                };
+                match result {
+                    Ok(path) => self.set_status(format!("Exported to {}", path.display())),
+                    Err(e) => self.set_status(format!("Export failed: {}", e)),
+                }
+            }
+            Some("open") => {
+                let Some(file) = words.next() else { return self.set_status("usage: :open FILE") };
+                match project::open(self, Path::new(file)) {
+                    Ok(n) => self.set_status(format!("Opened {} ({} entries)", file, n)),
+                    Err(e) => self.set_status(format!("Could not open {}: {}", file, e)),
+                }
+            }
+            Some("tag") => {
+                let tag = words.next().map(str::to_string);
+                self.keep_selection(|a| a.tag_filter = tag);
+            }
+            Some("jq") => {
+                let text = words.collect::<Vec<_>>().join(" ");
+                match (text.is_empty(), Query::parse(&text)) {
+                    (true, _) => self.body_query = None,
+                    (false, Ok(query)) => self.body_query = Some(query),
+                    (false, Err(e)) => self.set_status(format!("jq: {}", e)),
+                }
+            }
+            Some("where") => {
+                let text = words.collect::<Vec<_>>().join(" ");
+                let query = match (text.is_empty(), Query::parse(&text)) {
+                    (true, _) => None,
+                    (false, Ok(query)) => Some(query),
+                    (false, Err(e)) => return self.set_status(format!("where: {}", e)),
+                };
+                let hits = match &query {
+                    Some(q) => (0..self.logs.len()).filter(|i| jsonpath::response_json(&self.logs[*i]).is_some_and(|json| q.matches(&json))).collect(),
+                    None => HashSet::new(),
+                };
+                self.keep_selection(|a| {
+                    a.where_filter = query;
+                    a.where_hits = hits;
+                });
+            }
+            Some("filter") => filters::command(self, &words.collect::<Vec<_>>()),
+            Some("listener") => listeners::command(self, &words.collect::<Vec<_>>()),
+            Some("mark") => markers::mark(self, &words.collect::<Vec<_>>()),
+            Some("marks") => markers::list(self),
+            Some("pin") => pinning::command(self, &words.collect::<Vec<_>>()),
+            Some("redact") => redact::command(self, &words.collect::<Vec<_>>()),
+            Some("window") => markers::window(self, &words.collect::<Vec<_>>()),
+            Some("origin") => match words.next() {
+                None | Some("all") => self.keep_selection(|a| a.origin_filter = None),
+                Some(name) => match Origin::parse(name) {
+                    Some(origin) => self.keep_selection(|a| a.origin_filter = Some(origin)),
+                    None => {
+                        let names: Vec<&str> = Origin::ALL.iter().map(|o| o.name()).collect();
+                        self.set_status(format!("usage: :origin {}|all", names.join("|")));
+                    }
+                },
+            },
+            Some("find") => {
+                let text = words.collect::<Vec<_>>().join(" ").to_lowercase();
+                let search = (!text.is_empty()).then_some(text);
+                let hits = match &search {
+                    Some(s) => (0..self.logs.len()).filter(|i| self.entry_contains(*i, s)).collect(),
+                    None => HashSet::new(),
+                };
+                self.keep_selection(|a| {
+                    a.search = search;
+                    a.search_hits = hits;
+                });
+            }
+            Some("cors-dev") => {
+                let host = words.next().map(str::to_string).or_else(|| self.selected_log().map(|l| l.host.clone()));
+                let Some(host) = host.filter(|h| !h.is_empty()) else { return self.set_status("usage: :cors-dev [HOST]") };
+                let msg = match self.cors_dev.toggle(&host) {
+                    true => format!("CORS dev mode on for {}", host),
+                    false => format!("CORS dev mode off for {}", host),
+                };
+                self.set_status(msg);
+            }
+            Some("ws") => {
+                let words: Vec<&str> = words.collect();
+                match self.ws_filter.update(&words) {
+                    Ok(()) => self.set_status(format!("WebSocket messages: {}", self.ws_filter.label())),
+                    Err(e) => self.set_status(e),
+                }
+            }
+            Some("sse") => match words.next() {
+                None | Some("all") => {
+                    self.sse_filter = None;
+                    self.set_status("Server-sent events: all types");
+                }
+                Some(kind) => {
+                    self.sse_filter = Some(kind.to_string());
+                    self.set_status(format!("Server-sent events: {} only", kind));
+                }
+            },
+            Some("corr") => {
+                let id = match words.next() {
+                    Some(id) => Some(id.to_lowercase()),
+                    None if self.corr_filter.is_some() => None,
+                    None => match self.selected_log().and_then(|l| l.correlation.first()) {
+                        Some((_, id)) => Some(id.to_lowercase()),
+                        None => return self.set_status("The selected entry has no correlation ID"),
+                    },
+                };
+                self.keep_selection(|a| a.corr_filter = id);
+            }
+            Some("payloads") => match (words.next(), words.next(), words.next()) {
+                (None, _, _) => self.payloads = Some(PayloadsView::load(None)),
+                (Some("import"), Some(file), name) => match payloads::import(file, name) {
+                    Ok(name) => {
+                        self.set_status(format!("Imported {} as `{}`: :fuzz {} uses it", file, name, name));
+                        self.payloads = Some(PayloadsView::load(Some(&name)));
+                    }
+                    Err(e) => self.set_status(e),
+                },
+                _ => self.set_status("usage: :payloads [import FILE [NAME]]"),
+            },
+            Some("csrf") => csrf::command(self, &words.collect::<Vec<_>>()),
+            Some("read-only") => match words.next() {
+                None => self.set_read_only(!self.read_only),
+                Some("on") => self.set_read_only(true),
+                Some("off") => self.set_read_only(false),
+                Some("list") if self.read_only_hosts.is_empty() => self.set_status("No read-only hosts; :read-only HOST adds one"),
+                Some("list") => self.set_status(format!("Read-only hosts: {}", self.read_only_hosts.join(" "))),
+                Some(host) => self.toggle_read_only_host(host),
+            },
+            Some("form") => match words.next().map_or(Ok(1), str::parse::<usize>) {
+                Ok(n) => self.open_form(n),
+                Err(_) => self.set_status("usage: :form [N]"),
+            },
+            Some("tls") => host_tls::command(self, &words.collect::<Vec<_>>()),
+            Some("normalize") => normalize::command(self, &words.collect::<Vec<_>>()),
+            Some("fault") => faults::command(self, &words.collect::<Vec<_>>()),
+            Some("cookie") => cookies::command(self, &words.collect::<Vec<_>>()),
+            Some("cookies") => self.cookie_jar = Some(CookieJar::collect(self.logs.iter(), &self.cookie_overrides)),
+            Some("plain-http") => downgrade::command(self, &words.collect::<Vec<_>>()),
+            Some("no-cache") => cache_bust::command(self, &words.collect::<Vec<_>>()),
+            Some("dns") => dns::command(self, &words.collect::<Vec<_>>()),
+            Some("mirror") => mirror::command(self, &words.collect::<Vec<_>>()),
+            Some("rewrite") => rewrite::command(self, &words.collect::<Vec<_>>()),
+            Some("config") => config_file::command(self, &words.collect::<Vec<_>>()),
+            Some("note") => triage::note(self, &words.collect::<Vec<_>>().join(" ")),
+            Some("triage") => triage::command(self, &words.collect::<Vec<_>>()),
+            Some("edit") => match words.next() {
+                None | Some("response") => self.edit_body(true),
+                Some("request") => self.edit_body(false),
+                Some(_) => self.set_status("usage: :edit [request|response]"),
+            },
+            Some("throttle") => {
+                let spec: Vec<&str> = words.collect();
+                let limits = match spec.as_slice() {
+                    [] => return self.set_status(format!("Throttle: {}", throttle::limits().label())),
+                    ["off"] => Ok(throttle::Limits::default()),
+                    [rate] => throttle::Limits::parse(rate),
+                    [rate, concurrency] => throttle::Limits::parse(&format!("{},{}", rate, concurrency)),
+                    _ => Err("usage: :throttle RATE [CONCURRENCY] | off".to_string()),
+                };
+                match limits {
+                    Ok(limits) => {
+                        throttle::set(limits);
+                        self.set_status(format!("Throttle: {}", limits.label()));
+                    }
+                    Err(e) => self.set_status(e),
+                }
+            }
+            Some("decode") => {
+                let mut stages = Vec::new();
+                for name in words {
+                    let Some(stage) = Transform::parse(name) else {
+                        let names: Vec<&str> = Transform::ALL.iter().map(|t| t.name()).collect();
+                        return self.set_status(format!("Unknown transform `{}`: {}", name, names.join(", ")));
+                    };
+                    stages.push(stage);
+                }
+                self.pipeline.stages = stages;
+                self.pipeline.scroll = 0;
+                self.tab = Tab::Decoder;
+            }
+            Some("repeater") if self.repeater.is_some() => self.show_repeater = true,
+            Some("repeater") => self.set_status("Nothing in the Repeater yet: press R on an entry"),
+            Some(other) => self.set_status(format!("Unknown command `{}`", other)),
+        }
+    }
+    /// Jump to the newest entry for the selected Sitemap path
+    pub fn open_sitemap_node(&mut self) {
+        sitemap::refresh(self);
+        match self.sitemap_tree.nodes.get(self.sitemap_selected).map(|n| (n.entries.last(), n.unvisited())) {
+            Some((Some(index), _)) => self.jump_to(*index),
+            Some((None, true)) => self.set_status("Listed by discovery; not requested yet"),
+            _ => {}
+        }
+    }
+    /// Load the selected entry into the Repeater, replacing what was there
+    pub fn open_repeater(&mut self) {
+        if self.refuse_selected("the Repeater") {
+            return;
+        }
+        let Some(log) = self.selected_log().filter(|l| !l.request_raw.is_empty() && !l.websocket) else {
+            return self.set_status("Select a captured request to repeat");
+        };
+        self.repeater = Some(Repeater::open(log));
+        self.show_repeater = true;
+    }
+    /// Repeater on a submit of the selected response's form `n`, its fields filled in
+    pub fn open_form(&mut self, n: usize) {
+        if self.refuse_selected("the Repeater") {
+            return;
+        }
+        let Some(log) = self.selected_log().cloned() else {
+            return self.set_status("Select an entry with an HTML form");
+        };
+        let found = forms::of(&log);
+        let Some(form) = n.checked_sub(1).and_then(|i| found.get(i)) else {
+            return self.set_status(format!("The selected response has {} form(s)", found.len()));
+        };
+        let Some((target, request)) = forms::request(form, &log) else {
+            return self.set_status(format!("Can't submit to {}", form.action));
+        };
+        let mut repeater = Repeater::open(&target);
+        repeater.editor = TextArea::new(&String::from_utf8_lossy(&request));
+        self.repeater = Some(repeater);
+        self.show_repeater = true;
+    }
+    /// Select entry `index` in the Requests tab, clearing filters and unfolding its chain if they hide it
+    pub fn jump_to(&mut self, index: usize) {
+        if !self.view().contains(&index) {
+            self.tag_filter = None;
+            self.origin_filter = None;
+            self.window = None;
+            self.search = None;
+            self.search_hits.clear();
+            self.where_filter = None;
+            self.where_hits.clear();
+            self.identical_to = None;
+            self.conn_filter = None;
+            self.corr_filter = None;
+            self.expanded.insert(self.redirects.head(index));
+        }
+        self.tab = Tab::Requests;
+        self.selected = self.view().iter().position(|i| *i == index).unwrap_or(0);
+        self.detail_scroll = 0;
+    }
+    /// Turn interception on, or off and let every held request through unchanged
+    pub fn toggle_intercept(&mut self) {
+        if !self.intercepting && self.refuse("intercept") {
+            return;
+        }
+        self.intercepting = !self.intercepting;
+        let released = self.paused.len();
+        if !self.intercepting {
+            // Dropping the reply channels forwards the originals
+            self.paused.clear();
+        }
+        let msg = match (self.intercepting, released) {
+            (true, _) => "Intercept on: in-scope requests are held for editing".to_string(),
+            (false, 0) => "Intercept off".to_string(),
+            (false, n) => format!("Intercept off; {} held request(s) forwarded unchanged", n),
+        };
+        self.set_status(msg);
+    }
+    /// In read-only mode, say that `what` is off; true when it is
+    pub fn refuse(&mut self, what: &str) -> bool {
+        if self.read_only {
+            self.set_status(format!("Read-only mode: {} is off (:read-only off allows it)", what));
+        }
+        self.read_only
+    }
+    /// As `refuse`, and also when `host` is a read-only host
+    pub fn refuse_for(&mut self, what: &str, host: &str) -> bool {
+        if self.refuse(what) {
+            return true;
+        }
+        let Some(pattern) = self.read_only_hosts.iter().find(|p| config::host_matches(std::slice::from_ref(p), host)).cloned() else { return false };
+        self.set_status(format!("{} is read-only: {} is off for it (:read-only {} allows it)", host, what, pattern));
+        true
+    }
+    /// `refuse_for` the selected entry's host
+    pub fn refuse_selected(&mut self, what: &str) -> bool {
+        let host = self.selected_log().map(|l| l.host.clone()).unwrap_or_default();
+        self.refuse_for(what, &host)
+    }
+    /// Whether traffic to `host` must pass unchanged: read-only mode, or a read-only host
+    pub fn read_only_for(&self, host: &str) -> bool {
+        self.read_only || config::host_matches(&self.read_only_hosts, host)
+    }
+    /// Make traffic to the host pattern `host` read-only while the rest isn't, or undo that
+    fn toggle_read_only_host(&mut self, host: &str) {
+        let host = host.to_lowercase();
+        match self.read_only_hosts.iter().position(|h| *h == host) {
+            Some(i) => {
+                self.read_only_hosts.remove(i);
+                self.set_status(format!("{} is no longer read-only", host));
+            }
+            None => {
+                self.set_status(format!("{} is read-only: its traffic passes unchanged and tools won't send to it", host));
+                self.read_only_hosts.push(host);
+            }
+        }
+    }
+    /// Switch read-only mode; turning it on releases held requests and stops running tools
+    pub fn set_read_only(&mut self, on: bool) {
+        self.read_only = on;
+        if !on {
+            return self.set_status("Read-only mode off");
+        }
+        if self.intercepting {
+            self.intercepting = false;
+            self.paused.clear();
+        }
+        faults::clear();
+        if let Some(run) = &self.fuzz {
+            run.stop();
+        }
+        if let Some(run) = &self.repeat {
+            run.stop();
+        }
+        if let Some(stop) = self.spider.take() {
+            stop.store(true, Ordering::Relaxed);
+        }
+        self.set_status("Read-only mode: traffic passes unchanged and active tools are off");
+    }
+    /// Show only entries with the same response body as the selected one, or clear that filter
+    pub fn toggle_identical(&mut self) {
+        let hash = self.selected_log().map(|l| l.response_sha256.clone()).filter(|h| !h.is_empty());
+        self.keep_selection(|a| a.identical_to = if a.identical_to.is_some() { None } else { hash });
+    }
+    /// Show only entries from the selected one's client connection, or clear that filter
+    pub fn toggle_connection(&mut self) {
+        let conn = self.selected_log().map(|l| l.conn).filter(|c| *c != 0);
+        self.keep_selection(|a| a.conn_filter = if a.conn_filter.is_some() { None } else { conn });
+    }
+    /// Record a finding unless the same title was already reported for that URL, ignoring the query
+    pub fn add_issue(&mut self, issue: Issue) {
+        let path = |url: &str| url.split('?').next().unwrap_or("").to_string();
+        if !self.issues.iter().any(|i| i.title == issue.title && path(&i.url) == path(&issue.url)) {
+            self.issues.push(issue);
+            // Most severe first
+            self.issues.sort_by_key(|i| std::cmp::Reverse(i.severity));
+        }
+    }
+    /// Requests per second over the last `secs` seconds, oldest first
+    pub fn request_rate(&self, secs: usize) -> Vec<u64> {
+        let now = Instant::now();
+        let mut buckets = vec![0u64; secs];
+        for t in &self.request_times {
+            let age = now.duration_since(*t).as_secs() as usize;
+            if age < secs {
+                buckets[secs - 1 - age] += 1;
+            }
+        }
+        buckets
+    }
+    /// Selected row and length of the current tab's list
+    fn cursor(&mut self) -> (&mut usize, usize) {
+        match self.tab {
+            Tab::Credentials => (&mut self.cred_selected, self.credentials.len()),
+            Tab::Issues => (&mut self.issue_selected, self.issues.len()),
+            Tab::Hosts => {
+                let len = hosts::summarize(&self.logs, self.host_sort, self.host_sort_reversed).len();
+                (&mut self.host_selected, len)
+            }
+            Tab::Sitemap => {
+                sitemap::refresh(self);
+                (&mut self.sitemap_selected, self.sitemap_tree.nodes.len())
+            }
+            _ => {
+                let len = self.view().len();
+                (&mut self.selected, len)
+            }
+        }
+    }
+    pub fn next(&mut self) {
+        let (selected, len) = self.cursor();
+        if *selected + 1 < len {
+            *selected += 1;
+        }
+        self.reset_detail();
+    }
+    pub fn previous(&mut self) {
+        let (selected, _) = self.cursor();
+        *selected = selected.saturating_sub(1);
+        self.reset_detail();
+    }
+    /// Set the list width, keeping both panes usable, and remember it
+    pub fn set_split(&mut self, pct: u16) {
+        self.split = pct.clamp(MIN_SPLIT, MAX_SPLIT);
+        config::save_split(self.split);
+    }
+    pub fn resize_split(&mut self, delta: i16) {
+        self.set_split(self.split.saturating_add_signed(delta));
+    }
+    /// Select a row of the current tab's list, ignoring positions past its end
+    pub fn select(&mut self, index: usize) {
+        let (selected, len) = self.cursor();
+        if index < len && index != *selected {
+            *selected = index;
+            self.reset_detail();
+        }
+    }
+    /// Back to the top of a truncated body, for a newly selected entry
+    fn reset_detail(&mut self) {
+        self.detail_scroll = 0;
+        self.full_body = false;
+        self.selection = None;
+    }
+    /// Toggle masking of the selected credential's password
+    pub fn toggle_reveal(&mut self) {
+        match self.cred_revealed.iter().position(|i| *i == self.cred_selected) {
+            Some(p) => { self.cred_revealed.remove(p); }
+            None => self.cred_revealed.push(self.cred_selected),
+        }
+    }
+    pub fn selected_log(&self) -> Option<&HttpLog> {
+        self.view().get(self.selected).and_then(|i| self.logs.get(*i))
+    }
+    /// Add or remove the selected entry's host from the scope
+    pub fn toggle_selected_scope(&mut self) {
+        let Some(host) = self.selected_log().map(|l| l.host.clone()).filter(|h| !h.is_empty()) else { return };
+        let mut msg = if self.scope.toggle(&host) { format!("Added {} to scope", host) } else { format!("Removed {} from scope", host) };
+        if !self.scope.saves() {
+            msg.push_str(" for this session (:config save keeps it)");
+        }
+        self.set_status(msg);
+    }
+    /// Add or remove the selected entry's host from the passthrough list
+    pub fn toggle_selected_passthrough(&mut self) {
+        if let Some(host) = self.selected_log().map(|l| l.host.clone()) {
+            if !host.is_empty() {
+                self.passthrough.toggle(&host);
+            }
+        }
+    }
+}
+
+/// Case-insensitive substring match on a payload; `needle` is lowercase
+fn payload_contains(payload: &[u8], needle: &str) -> bool {
+    String::from_utf8_lossy(payload).to_lowercase().contains(needle)
+}
@@ -0,0 +1,187 @@
+// Auth matrix: replay captured requests under other identities to find missing access control
+
+use std::fs;
+use std::sync::{Arc, Mutex};
+
+use tracing::info;
+
+use crate::app::{App, HttpLog};
+use crate::config;
+use crate::http;
+use crate::issues::{Issue, Severity};
+use crate::send;
+use crate::throttle;
+
+/// Most requests replayed in one run
+const MAX_REQUESTS: usize = 100;
+
+/// Headers that carry a session; removed before an identity's own are added
+const SESSION_HEADERS: [&str; 2] = ["cookie", "authorization"];
+
+pub struct Identity {
+    pub name: String,
+    pub headers: Vec<(String, String)>,
+}
+
+/// `identities.txt`: one `NAME HEADER: VALUE` per line; lines with the same name add up
+pub fn load_identities() -> Result<Vec<Identity>, String> {
+    let path = config::config_dir().join("identities.txt");
+    let text = fs::read_to_string(&path).unwrap_or_default();
+    let mut identities: Vec<Identity> = Vec::new();
+    for (n, line) in text.lines().enumerate().map(|(n, l)| (n + 1, l.trim())) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (name, header) = line.split_once(char::is_whitespace)
+            .and_then(|(name, rest)| Some((name, rest.trim().split_once(':')?)))
+            .ok_or_else(|| format!("{} line {}: expected `NAME HEADER: VALUE`", path.display(), n))?;
+        let header = (header.0.trim().to_string(), header.1.trim().to_string());
+        match identities.iter_mut().find(|i| i.name == name) {
+            Some(identity) => identity.headers.push(header),
+            None => identities.push(Identity { name: name.to_string(), headers: vec![header] }),
+        }
+    }
+    Ok(identities)
+}
+
+/// `request` with its session headers swapped for the identity's; None means no session at all
+fn as_identity(request: &[u8], identity: Option<&Identity>) -> Vec<u8> {
+    let mut out = SESSION_HEADERS.iter().fold(request.to_vec(), |r, h| http::remove_header(&r, h));
+    for (name, value) in identity.map_or(&[][..], |i| &i.headers[..]) {
+        out = http::set_header(&out, name, value);
+    }
+    out
+}
+
+#[derive(Clone, Copy)]
+pub struct Cell {
+    /// None when the request failed outright
+    pub status: Option<u16>,
+    /// Decoded response body size
+    pub len: usize,
+}
+
+impl Cell {
+    /// Same answer as `other`: equal status and a body within 5% of its size
+    pub fn same_as(&self, other: &Cell) -> bool {
+        self.status.is_some() && self.status == other.status && self.len.abs_diff(other.len) * 20 <= other.len.max(1)
+    }
+}
+
+pub struct MatrixRow {
+    pub method: String,
+    pub url: String,
+    /// The captured request carried a session; without one there is nothing to enforce
+    pub authenticated: bool,
+    /// One per column; None until sent
+    pub cells: Vec<Option<Cell>>,
+}
+
+pub struct AuthMatrix {
+    /// `captured` first, then `anonymous`, then the configured identities
+    pub columns: Vec<String>,
+    pub rows: Vec<MatrixRow>,
+    pub running: bool,
+    /// First row shown in the popup
+    pub scroll: usize,
+    /// Identifies the run, so a replaced one stops writing
+    run: Arc<()>,
+}
+
+impl AuthMatrix {
+    /// The captured session succeeded and this column, with another or no session, got the same answer
+    pub fn suspicious(&self, row: &MatrixRow, column: usize) -> bool {
+        match (column, row.cells.first().copied().flatten(), row.cells.get(column).copied().flatten()) {
+            (1.., Some(base), Some(cell)) => row.authenticated && base.status.is_some_and(|s| (200..300).contains(&s)) && cell.same_as(&base),
+            _ => false,
+        }
+    }
+}
+
+async fn replay(app: &Arc<Mutex<App>>, log: &HttpLog, request: &[u8]) -> Cell {
+    let via = app.lock().unwrap().routes.route(&log.host).cloned();
+    let port = send::target_port(log);
+    let _permit = throttle::acquire(&log.host).await;
+    match send::send(&log.host, port, log.tls, request, via.as_ref()).await {
+        Ok(raw) => {
+            let (head, body) = http::split_raw(&raw);
+            let head = String::from_utf8_lossy(head);
+            Cell { status: http::status_code(&head), len: http::decode_body(&head, body).len() }
+        }
+        Err(_) => Cell { status: None, len: 0 },
+    }
+}
+
+/// `:authz [show|stop]`: replay every listed request (up to `MAX_REQUESTS`) as each identity
+pub fn command(guard: &mut App, app: Arc<Mutex<App>>, args: &[&str]) {
+    match args {
+        [] => {}
+        ["show"] => return guard.show_authz = guard.authz.is_some(),
+        ["stop"] => {
+            if let Some(matrix) = guard.authz.as_mut() {
+                matrix.running = false;
+            }
+            return;
+        }
+        _ => return guard.set_status("usage: :authz [show|stop]"),
+    }
+    let identities = match load_identities() {
+        Ok(i) => i,
+        Err(e) => return guard.set_status(e),
+    };
+    let logs: Vec<HttpLog> = guard.view().into_iter()
+        .map(|i| guard.logs[i].clone())
+        .filter(|l| !l.request_raw.is_empty() && !l.websocket)
+        .take(MAX_REQUESTS)
+        .collect();
+    if logs.is_empty() {
+        return guard.set_status("No requests listed; filter the list down to the ones to test");
+    }
+    let mut columns = vec!["captured".to_string(), "anonymous".to_string()];
+    columns.extend(identities.iter().map(|i| i.name.clone()));
+    let rows = logs.iter().map(|l| {
+        let raw = l.request_raw.bytes();
+        let authenticated = http::RequestHead::parse(&raw).is_some_and(|h| SESSION_HEADERS.iter().any(|s| h.header(s).is_some()));
+        MatrixRow { method: l.method(), url: l.full_url(), authenticated, cells: vec![None; columns.len()] }
+    }).collect();
+    let run = Arc::new(());
+    guard.authz = Some(AuthMatrix { columns: columns.clone(), rows, running: true, scroll: 0, run: run.clone() });
+    guard.show_authz = true;
+    if identities.is_empty() {
+        guard.set_status("Only captured vs anonymous: add `NAME HEADER: VALUE` lines to identities.txt for more");
+    }
+    tokio::spawn(async move {
+        let mut found = 0;
+        for (row, log) in logs.iter().enumerate() {
+            let base = send::replayable(log);
+            let variants = std::iter::once(base.clone())
+                .chain(std::iter::once(as_identity(&base, None)))
+                .chain(identities.iter().map(|i| as_identity(&base, Some(i))));
+            for (column, request) in variants.enumerate() {
+                let cell = replay(&app, log, &request).await;
+                let mut guard = app.lock().unwrap();
+                // Stopped, or replaced by a newer run
+                let Some(matrix) = guard.authz.as_mut().filter(|m| m.running && Arc::ptr_eq(&m.run, &run)) else { return };
+                matrix.rows[row].cells[column] = Some(cell);
+                if !matrix.suspicious(&matrix.rows[row], column) {
+                    continue;
+                }
+                found += 1;
+                let detail = format!("{} got {} with a {} byte body, like the captured session", columns[column], cell.status.unwrap_or(0), cell.len);
+                guard.add_issue(Issue {
+                    severity: Severity::Medium,
+                    host: log.host.clone(),
+                    url: log.full_url(),
+                    title: format!("Access control: {} gets the captured response", columns[column]),
+                    detail,
+                });
+            }
+        }
+        let mut guard = app.lock().unwrap();
+        if let Some(matrix) = guard.authz.as_mut().filter(|m| Arc::ptr_eq(&m.run, &run)) {
+            matrix.running = false;
+        }
+        info!("auth matrix finished: {} requests, {} suspicious answers", logs.len(), found);
+        guard.set_status(format!("Auth matrix: {} suspicious answer(s)", found));
+    });
+}
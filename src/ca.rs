@@ -0,0 +1,219 @@
+// Root CA used to mint per-host leaf certificates for MITM
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+
+use rcgen::{
+    BasicConstraints, Certificate, CertificateParams, DnType, IsCa, KeyPair, KeyUsagePurpose,
+};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::ServerConfig;
+use sha2::{Digest, Sha256};
+use time::{Duration, OffsetDateTime};
+
+use crate::pinning::Trial;
+
+pub struct CertAuthority {
+    cert: Certificate,
+    key: KeyPair,
+    leaves: Mutex<HashMap<String, Arc<ServerConfig>>>,
+    pub cert_path: PathBuf,
+    pub cert_pem: String,
+    /// DER of the certificate as stored on disk (what clients trust)
+    pub cert_der: Vec<u8>,
+    pub not_before: OffsetDateTime,
+    pub not_after: OffsetDateTime,
+}
+
+impl CertAuthority {
+    /// Load `ca.pem`/`ca.key` from `dir`, generating a fresh CA on first run
+    pub fn load_or_create(dir: &Path) -> Result<Self, Box<dyn Error>> {
+        let cert_path = dir.join("ca.pem");
+        let key_path = dir.join("ca.key");
+        if let (Ok(cert_pem), Ok(key_pem)) =
+            (fs::read_to_string(&cert_path), fs::read_to_string(&key_path))
+        {
+            let key = KeyPair::from_pem(&key_pem)?;
+            let params = CertificateParams::from_ca_cert_pem(&cert_pem)?;
+            let (not_before, not_after) = (params.not_before, params.not_after);
+            let cert = params.self_signed(&key)?;
+            let cert_der = CertificateDer::from_pem_slice(cert_pem.as_bytes())?.to_vec();
+            return Ok(Self {
+                cert, key, leaves: Mutex::new(HashMap::new()),
+                cert_path, cert_pem, cert_der, not_before, not_after,
+            });
+        }
+
+        let key = KeyPair::generate()?;
+        let mut params = CertificateParams::default();
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        params.key_usages = vec![
+            KeyUsagePurpose::KeyCertSign,
+            KeyUsagePurpose::CrlSign,
+            KeyUsagePurpose::DigitalSignature,
+        ];
+        params.distinguished_name.push(DnType::CommonName, "Belch Proxy CA");
+        params.distinguished_name.push(DnType::OrganizationName, "Belch");
+        let now = OffsetDateTime::now_utc();
+        params.not_before = now - Duration::days(1);
+        params.not_after = now + Duration::days(3650);
+        let (not_before, not_after) = (params.not_before, params.not_after);
+        let cert = params.self_signed(&key)?;
+        let cert_pem = cert.pem();
+        let cert_der = cert.der().to_vec();
+
+        fs::create_dir_all(dir)?;
+        fs::write(&cert_path, &cert_pem)?;
+        fs::write(&key_path, key.serialize_pem())?;
+        Ok(Self {
+            cert, key, leaves: Mutex::new(HashMap::new()),
+            cert_path, cert_pem, cert_der, not_before, not_after,
+        })
+    }
+
+    /// Discard the existing CA and mint a new one
+    pub fn regenerate(dir: &Path) -> Result<Self, Box<dyn Error>> {
+        let _ = fs::remove_file(dir.join("ca.pem"));
+        let _ = fs::remove_file(dir.join("ca.key"));
+        Self::load_or_create(dir)
+    }
+
+    /// SHA-256 fingerprint as colon-separated hex
+    pub fn fingerprint(&self) -> String {
+        Sha256::digest(&self.cert_der).iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(":")
+    }
+
+    /// Whole days until the certificate expires (negative once expired)
+    pub fn days_left(&self) -> i64 {
+        (self.not_after - OffsetDateTime::now_utc()).whole_days()
+    }
+
+    /// Add the CA to the OS trust store, plus the NSS db used by Firefox/Chromium on Linux
+    pub fn install(&self) -> Result<(), Box<dyn Error>> {
+        let path = self.cert_path.to_string_lossy().to_string();
+        if cfg!(target_os = "macos") {
+            run("security", &["add-trusted-cert", "-d", "-r", "trustRoot", "-k", "/Library/Keychains/System.keychain", &path])?;
+        } else if cfg!(target_os = "windows") {
+            run("certutil", &["-addstore", "-user", "Root", &path])?;
+        } else if Path::new("/usr/local/share/ca-certificates").is_dir() {
+            // Debian/Ubuntu
+            fs::copy(&self.cert_path, "/usr/local/share/ca-certificates/belch-ca.crt")?;
+            run("update-ca-certificates", &[])?;
+        } else if Path::new("/etc/pki/ca-trust/source/anchors").is_dir() {
+            // Fedora/RHEL
+            fs::copy(&self.cert_path, "/etc/pki/ca-trust/source/anchors/belch-ca.pem")?;
+            run("update-ca-trust", &["extract"])?;
+        } else {
+            return Err("no supported trust store found; import ca.pem manually".into());
+        }
+        if cfg!(target_os = "linux") {
+            if let Some(home) = std::env::var_os("HOME") {
+                let nssdb = format!("sql:{}/.pki/nssdb", home.to_string_lossy());
+                if run("certutil", &["-d", &nssdb, "-A", "-t", "C,,", "-n", "Belch Proxy CA", "-i", &path]).is_ok() {
+                    println!("Added to NSS database {}", nssdb);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// TLS server config presenting a leaf certificate for `host`, cached per host
+    pub fn server_config(&self, host: &str) -> Result<Arc<ServerConfig>, Box<dyn Error + Send + Sync>> {
+        if let Some(cfg) = self.leaves.lock().unwrap().get(host) {
+            return Ok(cfg.clone());
+        }
+        let key = KeyPair::generate()?;
+        let mut params = CertificateParams::new(vec![host.to_string()])?;
+        params.distinguished_name.push(DnType::CommonName, host);
+        let now = OffsetDateTime::now_utc();
+        params.not_before = now - Duration::days(1);
+        params.not_after = now + Duration::days(365);
+        let leaf = params.signed_by(&key, &self.cert, &self.key)?;
+        let cfg = config(vec![leaf.der().clone(), self.cert.der().clone()], &key)?;
+        self.leaves.lock().unwrap().insert(host.to_string(), cfg.clone());
+        Ok(cfg)
+    }
+
+    /// Server config for the pinning check, its certificate wrong in the way `trial` says; not cached
+    pub fn trial_config(&self, host: &str, trial: Trial) -> Result<Arc<ServerConfig>, Box<dyn Error + Send + Sync>> {
+        if trial == Trial::Valid {
+            return self.server_config(host);
+        }
+        let key = KeyPair::generate()?;
+        let name = if trial == Trial::WrongHost { "wrong-host.belch.invalid" } else { host };
+        let mut params = CertificateParams::new(vec![name.to_string()])?;
+        params.distinguished_name.push(DnType::CommonName, name);
+        let now = OffsetDateTime::now_utc();
+        (params.not_before, params.not_after) = match trial {
+            Trial::Expired => (now - Duration::days(30), now - Duration::days(1)),
+            _ => (now - Duration::days(1), now + Duration::days(365)),
+        };
+        let chain = match trial {
+            Trial::SelfSigned => vec![params.self_signed(&key)?.der().clone()],
+            _ => vec![params.signed_by(&key, &self.cert, &self.key)?.der().clone(), self.cert.der().clone()],
+        };
+        config(chain, &key)
+    }
+}
+
+/// Config serving `chain`, leaf first, with `key`
+fn config(chain: Vec<CertificateDer<'static>>, key: &KeyPair) -> Result<Arc<ServerConfig>, Box<dyn Error + Send + Sync>> {
+    let mut cfg = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(chain, PrivateKeyDer::Pkcs8(key.serialize_der().into()))?;
+    cfg.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(Arc::new(cfg))
+}
+
+fn run(cmd: &str, args: &[&str]) -> Result<(), Box<dyn Error>> {
+    let status = Command::new(cmd).args(args).status()
+        .map_err(|e| format!("{}: {}", cmd, e))?;
+    if !status.success() {
+        return Err(format!("{} exited with {}", cmd, status).into());
+    }
+    Ok(())
+}
+
+/// `belch ca <export|regenerate|install|info>`
+pub fn command(dir: &Path, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let usage = "usage: belch ca export [--der] [FILE] | regenerate | install | info";
+    match args.first().map(String::as_str) {
+        Some("export") => {
+            let ca = CertAuthority::load_or_create(dir)?;
+            let der = args.iter().any(|a| a == "--der");
+            let bytes = if der { ca.cert_der.clone() } else { ca.cert_pem.clone().into_bytes() };
+            match args[1..].iter().find(|a| !a.starts_with("--")) {
+                Some(file) => {
+                    fs::write(file, bytes)?;
+                    println!("Wrote {}", file);
+                }
+                None => std::io::Write::write_all(&mut std::io::stdout(), &bytes)?,
+            }
+        }
+        Some("regenerate") => {
+            let ca = CertAuthority::regenerate(dir)?;
+            println!("New CA written to {}", ca.cert_path.display());
+            println!("SHA-256 {}", ca.fingerprint());
+            println!("Clients trusting the previous CA must re-import it.");
+        }
+        Some("install") => {
+            let ca = CertAuthority::load_or_create(dir)?;
+            ca.install()?;
+            println!("Installed {} ({})", ca.cert_path.display(), ca.fingerprint());
+        }
+        Some("info") => {
+            let ca = CertAuthority::load_or_create(dir)?;
+            println!("Path        {}", ca.cert_path.display());
+            println!("SHA-256     {}", ca.fingerprint());
+            println!("Not before  {}", ca.not_before.date());
+            println!("Not after   {} ({} days left)", ca.not_after.date(), ca.days_left());
+        }
+        _ => return Err(usage.into()),
+    }
+    Ok(())
+}
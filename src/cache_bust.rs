@@ -0,0 +1,55 @@
+// Cache-busting mode: in-scope requests lose their conditional headers and the responses their
+// caching headers, so every load is fresh and no 304 or stale copy gets in the way of testing
+
+use std::sync::{Arc, Mutex};
+
+use crate::app::App;
+use crate::http;
+
+/// Make the server answer in full
+const CONDITIONAL: [&str; 2] = ["if-none-match", "if-modified-since"];
+
+/// Let the browser keep nothing to revalidate or reuse
+const CACHING: [&str; 6] = ["cache-control", "expires", "etag", "last-modified", "age", "pragma"];
+
+/// Whether requests to `host` on connection `conn` are cache-busted
+pub fn applies(app: &Arc<Mutex<App>>, conn: u64, host: &str) -> bool {
+    let guard = app.lock().unwrap();
+    let listener = guard.conn_listeners.get(&conn).cloned().unwrap_or_default();
+    guard.no_cache && !guard.read_only_for(host) && guard.in_scope(&listener, host)
+}
+
+fn strip(message: Vec<u8>, names: &[&str]) -> Vec<u8> {
+    let head = http::split_raw(&message).0;
+    let found = http::header_lines(head).iter().any(|l| names.iter().any(|n| http::header_line_name(l).eq_ignore_ascii_case(n.as_bytes())));
+    if !found {
+        return message;
+    }
+    names.iter().fold(message, |m, name| http::remove_header(&m, name))
+}
+
+pub fn request(request: Vec<u8>) -> Vec<u8> {
+    strip(request, &CONDITIONAL)
+}
+
+pub fn response(response: Vec<u8>) -> Vec<u8> {
+    strip(response, &CACHING)
+}
+
+/// `:no-cache [on|off]`
+pub fn command(app: &mut App, args: &[&str]) {
+    let on = match args {
+        [] => !app.no_cache,
+        ["on"] => true,
+        ["off"] => false,
+        _ => return app.set_status("usage: :no-cache [on|off]"),
+    };
+    if on && app.refuse("cache busting") {
+        return;
+    }
+    app.no_cache = on;
+    app.set_status(match on {
+        true => "Cache busting on: in-scope requests go without If-None-Match/If-Modified-Since, responses without caching headers",
+        false => "Cache busting off",
+    });
+}
@@ -0,0 +1,171 @@
+// Decoding certificates, CSRs and public keys found in traffic or pasted in
+
+use std::sync::OnceLock;
+
+use base64::Engine;
+use regex::Regex;
+use x509_parser::der_parser::oid::Oid;
+use x509_parser::prelude::*;
+use x509_parser::public_key::PublicKey;
+
+use crate::http;
+
+/// One decoded blob: a heading and its fields
+pub struct Decoded {
+    pub label: String,
+    pub fields: Vec<(String, String)>,
+}
+
+struct Patterns {
+    pem: Regex,
+    /// Bare base64 DER certificates, as in JWKS `x5c` arrays
+    der: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| Patterns {
+        pem: Regex::new(r"(?s)-----BEGIN ([A-Z0-9 ]+)-----(.*?)-----END [A-Z0-9 ]+-----").unwrap(),
+        der: Regex::new(r"MII[A-Za-z0-9+/]{100,}={0,2}").unwrap(),
+    })
+}
+
+fn oid_name(oid: &Oid) -> String {
+    oid2sn(oid, oid_registry()).map_or_else(|_| oid.to_id_string(), str::to_string)
+}
+
+fn key_fields(spki: &SubjectPublicKeyInfo, fields: &mut Vec<(String, String)>) {
+    let mut key = oid_name(&spki.algorithm.algorithm);
+    if let Some(curve) = spki.algorithm.parameters.as_ref().and_then(|p| p.as_oid().ok()) {
+        key.push_str(&format!(" ({})", oid_name(&curve)));
+    }
+    if let Ok(parsed) = spki.parsed() {
+        if parsed.key_size() > 0 {
+            key.push_str(&format!(", {} bits", parsed.key_size()));
+        }
+        if let PublicKey::RSA(rsa) = parsed {
+            if let Ok(e) = rsa.try_exponent() {
+                key.push_str(&format!(", e={}", e));
+            }
+        }
+    }
+    fields.push(("Public key".into(), key));
+}
+
+fn certificate(der: &[u8]) -> Option<Decoded> {
+    let (_, cert) = X509Certificate::from_der(der).ok()?;
+    let mut fields = vec![
+        ("Subject".into(), cert.subject().to_string()),
+        ("Issuer".into(), cert.issuer().to_string()),
+        ("Serial".into(), cert.raw_serial_as_string()),
+        ("Not before".into(), cert.validity().not_before.to_string()),
+    ];
+    let expired = if cert.validity().is_valid() { "" } else { "  [not currently valid]" };
+    fields.push(("Not after".into(), format!("{}{}", cert.validity().not_after, expired)));
+    key_fields(cert.public_key(), &mut fields);
+    fields.push(("Signature".into(), oid_name(&cert.signature_algorithm.algorithm)));
+    if let Ok(Some(san)) = cert.subject_alternative_name() {
+        let names: Vec<String> = san.value.general_names.iter().map(|n| n.to_string()).collect();
+        fields.push(("Alt names".into(), names.join(", ")));
+    }
+    if let Ok(Some(bc)) = cert.basic_constraints() {
+        let ca = match bc.value.path_len_constraint {
+            Some(n) if bc.value.ca => format!("yes, path length {}", n),
+            _ if bc.value.ca => "yes".into(),
+            _ => "no".into(),
+        };
+        fields.push(("CA".into(), ca));
+    }
+    if let Ok(Some(ku)) = cert.key_usage() {
+        fields.push(("Key usage".into(), ku.value.to_string()));
+    }
+    if let Ok(Some(eku)) = cert.extended_key_usage() {
+        let e = eku.value;
+        let mut uses: Vec<String> = [
+            (e.any, "any"),
+            (e.server_auth, "serverAuth"),
+            (e.client_auth, "clientAuth"),
+            (e.code_signing, "codeSigning"),
+            (e.email_protection, "emailProtection"),
+            (e.time_stamping, "timeStamping"),
+            (e.ocsp_signing, "OCSPSigning"),
+        ].iter().filter(|(set, _)| *set).map(|(_, name)| name.to_string()).collect();
+        uses.extend(e.other.iter().map(oid_name));
+        fields.push(("Extended usage".into(), uses.join(", ")));
+    }
+    fields.push(("SHA-256".into(), http::sha256_hex(der)));
+    Some(Decoded { label: "Certificate".into(), fields })
+}
+
+fn csr(der: &[u8]) -> Option<Decoded> {
+    let (_, req) = X509CertificationRequest::from_der(der).ok()?;
+    let info = &req.certification_request_info;
+    let mut fields = vec![("Subject".into(), info.subject.to_string())];
+    key_fields(&info.subject_pki, &mut fields);
+    fields.push(("Signature".into(), oid_name(&req.signature_algorithm.algorithm)));
+    for ext in req.requested_extensions().into_iter().flatten() {
+        if let ParsedExtension::SubjectAlternativeName(san) = ext {
+            let names: Vec<String> = san.general_names.iter().map(|n| n.to_string()).collect();
+            fields.push(("Alt names".into(), names.join(", ")));
+        }
+    }
+    let valid = if req.verify_signature().is_ok() { "valid" } else { "INVALID" };
+    fields.push(("Self-signature".into(), valid.into()));
+    Some(Decoded { label: "Certificate request".into(), fields })
+}
+
+fn public_key(der: &[u8]) -> Option<Decoded> {
+    let (_, spki) = SubjectPublicKeyInfo::from_der(der).ok()?;
+    let mut fields = Vec::new();
+    key_fields(&spki, &mut fields);
+    fields.push(("SHA-256".into(), http::sha256_hex(der)));
+    Some(Decoded { label: "Public key".into(), fields })
+}
+
+fn decode(label: &str, der: &[u8]) -> Decoded {
+    let parsed = match label {
+        "CERTIFICATE" | "X509 CERTIFICATE" | "TRUSTED CERTIFICATE" => certificate(der),
+        "CERTIFICATE REQUEST" | "NEW CERTIFICATE REQUEST" => csr(der),
+        "PUBLIC KEY" => public_key(der),
+        _ => None,
+    };
+    parsed.unwrap_or_else(|| Decoded {
+        label: label.to_lowercase(),
+        fields: vec![("Error".into(), format!("could not parse {} bytes of DER", der.len()))],
+    })
+}
+
+/// Every PEM block and bare base64 certificate in `text`, including JSON-escaped ones
+pub fn find(text: &str) -> Vec<Decoded> {
+    let text = text.replace("\\r", "").replace("\\n", "\n").replace("\\/", "/");
+    let b64 = base64::engine::general_purpose::STANDARD;
+    let p = patterns();
+    let mut seen: Vec<Vec<u8>> = Vec::new();
+    let mut out = Vec::new();
+    for c in p.pem.captures_iter(&text) {
+        if c[1].ends_with("PRIVATE KEY") {
+            out.push(Decoded {
+                label: c[1].to_lowercase(),
+                fields: vec![("Note".into(), "private key material, not decoded".into())],
+            });
+            continue;
+        }
+        let body: String = c[2].chars().filter(|c| !c.is_whitespace()).collect();
+        let Ok(der) = b64.decode(body) else { continue };
+        if !seen.contains(&der) {
+            out.push(decode(&c[1], &der));
+            seen.push(der);
+        }
+    }
+    for m in p.der.find_iter(&text) {
+        let Ok(der) = b64.decode(m.as_str()) else { continue };
+        if seen.contains(&der) {
+            continue;
+        }
+        if let Some(cert) = certificate(&der) {
+            out.push(cert);
+            seen.push(der);
+        }
+    }
+    out
+}
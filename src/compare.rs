@@ -0,0 +1,87 @@
+// Request and response headers side by side, related ones on the same row
+
+/// Request header and the response header that answers it
+const RELATED: [(&str, &str); 12] = [
+    ("accept", "content-type"),
+    ("accept-encoding", "content-encoding"),
+    ("accept-language", "content-language"),
+    ("cookie", "set-cookie"),
+    ("origin", "access-control-allow-origin"),
+    ("if-none-match", "etag"),
+    ("if-modified-since", "last-modified"),
+    ("range", "content-range"),
+    ("authorization", "www-authenticate"),
+    ("cache-control", "cache-control"),
+    ("content-length", "content-length"),
+    ("connection", "connection"),
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Note {
+    /// A response header worth having is absent
+    Missing,
+    /// The response ignores what the request asked for
+    Unanswered,
+}
+
+pub struct Row {
+    pub request: Option<(String, String)>,
+    pub response: Option<(String, String)>,
+    pub note: Option<(Note, &'static str)>,
+}
+
+fn headers(head: &str) -> Vec<(String, String)> {
+    head.lines().skip(1)
+        .filter_map(|l| l.split_once(':'))
+        .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+        .collect()
+}
+
+/// Rows for the two heads: related pairs first, then the rest in order, then notable absences
+pub fn rows(request_head: &str, response_head: &str, tls: bool) -> Vec<Row> {
+    let mut req = headers(request_head);
+    let mut resp = headers(response_head);
+    let take = |list: &mut Vec<(String, String)>, name: &str| {
+        list.iter().position(|(k, _)| k.eq_ignore_ascii_case(name)).map(|i| list.remove(i))
+    };
+    let has = |list: &[(String, String)], name: &str| list.iter().any(|(k, _)| k.eq_ignore_ascii_case(name));
+    let mut rows = Vec::new();
+    for (asked, answer) in RELATED {
+        let request = take(&mut req, asked);
+        let response = take(&mut resp, answer);
+        if request.is_none() && response.is_none() {
+            continue;
+        }
+        let note = match (&request, &response, answer) {
+            (Some(_), None, "content-encoding") => Some((Note::Unanswered, "not compressed")),
+            (Some(_), None, "access-control-allow-origin") => Some((Note::Unanswered, "no CORS grant")),
+            (Some(_), None, "cache-control") => Some((Note::Missing, "no Cache-Control: caches and proxies may store it")),
+            _ => None,
+        };
+        rows.push(Row { request, response, note });
+    }
+    let (mut req, mut resp) = (req.into_iter(), resp.into_iter());
+    loop {
+        let (request, response) = (req.next(), resp.next());
+        if request.is_none() && response.is_none() {
+            break;
+        }
+        rows.push(Row { request, response, note: None });
+    }
+    if response_head.is_empty() {
+        return rows;
+    }
+    let all_resp = headers(response_head);
+    let mut missing = vec![("cache-control", "no Cache-Control: caches and proxies may store it")];
+    if tls {
+        missing.push(("strict-transport-security", "no Strict-Transport-Security"));
+    }
+    missing.push(("x-content-type-options", "no X-Content-Type-Options: nosniff"));
+    for (name, why) in missing {
+        // Cache-Control already has a row when the request sent one
+        if !has(&all_resp, name) && !rows.iter().any(|r| r.note.is_some_and(|(_, w)| w == why)) {
+            rows.push(Row { request: None, response: None, note: Some((Note::Missing, why)) });
+        }
+    }
+    rows
+}
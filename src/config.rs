@@ -0,0 +1,173 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// `$XDG_CONFIG_HOME/belch`, falling back to `~/.config/belch`
+pub fn config_dir() -> PathBuf {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("belch")
+}
+
+/// Whether `host` matches any exact or `*.suffix` pattern
+pub fn host_matches(patterns: &[String], host: &str) -> bool {
+    let host = host.to_lowercase();
+    patterns.iter().any(|h| match h.strip_prefix("*.") {
+        Some(suffix) => host == suffix || host.ends_with(&format!(".{}", suffix)),
+        None => *h == host,
+    })
+}
+
+fn read_hosts(path: &Path) -> Vec<String> {
+    fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(str::to_lowercase)
+        .collect()
+}
+
+fn write_hosts(path: &Path, hosts: &[String]) {
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    let _ = fs::write(path, hosts.join("\n") + "\n");
+}
+
+/// Saved width of the Requests list, as a percentage, from `split.txt`
+pub fn load_split() -> Option<u16> {
+    fs::read_to_string(config_dir().join("split.txt")).ok()?.trim().parse().ok()
+}
+
+pub fn save_split(pct: u16) {
+    let dir = config_dir();
+    let _ = fs::create_dir_all(&dir);
+    let _ = fs::write(dir.join("split.txt"), format!("{}\n", pct));
+}
+
+/// Hosts that are always tunneled blindly instead of intercepted.
+/// Entries are exact hostnames or `*.example.com` wildcards.
+#[derive(Default)]
+pub struct Passthrough {
+    pub hosts: Vec<String>,
+    path: Option<PathBuf>,
+}
+
+impl Passthrough {
+    /// Load from `passthrough.txt` in the config dir (one host per line, `#` comments)
+    pub fn load() -> Self {
+        let path = config_dir().join("passthrough.txt");
+        Self { hosts: read_hosts(&path), path: Some(path) }
+    }
+
+    pub fn matches(&self, host: &str) -> bool {
+        host_matches(&self.hosts, host)
+    }
+
+    pub fn add(&mut self, host: &str) {
+        let host = host.trim().to_lowercase();
+        if !host.is_empty() && !self.hosts.contains(&host) {
+            self.hosts.push(host);
+            self.save();
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index < self.hosts.len() {
+            self.hosts.remove(index);
+            self.save();
+        }
+    }
+
+    pub fn toggle(&mut self, host: &str) {
+        let host = host.to_lowercase();
+        match self.hosts.iter().position(|h| *h == host) {
+            Some(i) => self.remove(i),
+            None => self.add(&host),
+        }
+    }
+
+    fn save(&self) {
+        if let Some(path) = &self.path {
+            write_hosts(path, &self.hosts);
+        }
+    }
+}
+
+/// Target scope for exports; an empty list means everything is in scope.
+/// Same pattern syntax as the passthrough list.
+#[derive(Default)]
+pub struct Scope {
+    pub hosts: Vec<String>,
+    path: Option<PathBuf>,
+}
+
+impl Scope {
+    /// Load from `scope.txt` in the config dir
+    pub fn load() -> Self {
+        let path = config_dir().join("scope.txt");
+        Self { hosts: read_hosts(&path), path: Some(path) }
+    }
+
+    /// Hosts from config.toml; changes last until `:config save`
+    pub fn with_hosts(hosts: &[String]) -> Self {
+        Self { hosts: hosts.iter().map(|h| h.to_lowercase()).collect(), path: None }
+    }
+
+    pub fn contains(&self, host: &str) -> bool {
+        self.hosts.is_empty() || host_matches(&self.hosts, host)
+    }
+
+    /// Whether `toggle` writes `scope.txt`, rather than leaving the change for `:config save`
+    pub fn saves(&self) -> bool {
+        self.path.is_some()
+    }
+
+    /// Add or remove an exact host; returns whether it is now listed
+    pub fn toggle(&mut self, host: &str) -> bool {
+        let host = host.to_lowercase();
+        let added = match self.hosts.iter().position(|h| *h == host) {
+            Some(i) => { self.hosts.remove(i); false }
+            None => { self.hosts.push(host); true }
+        };
+        if let Some(path) = &self.path {
+            write_hosts(path, &self.hosts);
+        }
+        added
+    }
+}
+
+/// Hosts whose responses get permissive CORS headers, for frontend development
+/// against APIs that send none. Same pattern syntax as the passthrough list.
+#[derive(Default)]
+pub struct CorsDev {
+    pub hosts: Vec<String>,
+    path: Option<PathBuf>,
+}
+
+impl CorsDev {
+    /// Load from `cors-dev.txt` in the config dir
+    pub fn load() -> Self {
+        let path = config_dir().join("cors-dev.txt");
+        Self { hosts: read_hosts(&path), path: Some(path) }
+    }
+
+    pub fn matches(&self, host: &str) -> bool {
+        host_matches(&self.hosts, host)
+    }
+
+    /// Add or remove an exact host; returns whether it is now listed
+    pub fn toggle(&mut self, host: &str) -> bool {
+        let host = host.to_lowercase();
+        let added = match self.hosts.iter().position(|h| *h == host) {
+            Some(i) => { self.hosts.remove(i); false }
+            None => { self.hosts.push(host); true }
+        };
+        if let Some(path) = &self.path {
+            write_hosts(path, &self.hosts);
+        }
+        added
+    }
+}
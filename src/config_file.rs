@@ -0,0 +1,155 @@
+// `config.toml` in the config dir, or the file --config names: listeners, scope, match/replace
+// rules, theme, keybindings and export defaults, read at startup and written back with
+// `:config save`. Command-line flags win over the file.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::App;
+use crate::config;
+use crate::keys;
+use crate::rewrite::Rule;
+use crate::theme::{self, Theme};
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Listeners {
+    /// `--listen` specs, used when none is given on the command line
+    pub listen: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScopeHosts {
+    /// Replaces scope.txt when not empty
+    pub hosts: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Colors {
+    pub selection_fg: Option<String>,
+    pub selection_bg: Option<String>,
+    pub tag: Option<String>,
+    pub status: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Export {
+    /// `--auto-export` directory
+    pub dir: Option<String>,
+    pub format: Option<String>,
+    pub select: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleEntry {
+    pub part: String,
+    #[serde(rename = "match")]
+    pub pattern: String,
+    #[serde(default)]
+    pub replace: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hosts: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    pub listeners: Listeners,
+    pub scope: ScopeHosts,
+    pub theme: Colors,
+    /// Action name to key
+    pub keys: BTreeMap<String, String>,
+    pub export: Export,
+    pub match_replace: Vec<RuleEntry>,
+}
+
+impl ConfigFile {
+    /// `path`, or config.toml in the config dir; a missing default file is an empty config
+    pub fn load(path: Option<&Path>) -> Result<(PathBuf, Self), String> {
+        let file = path.map_or_else(|| config::config_dir().join("config.toml"), Path::to_path_buf);
+        let text = match fs::read_to_string(&file) {
+            Ok(text) => text,
+            Err(_) if path.is_none() && !file.exists() => return Ok((file, ConfigFile::default())),
+            Err(e) => return Err(format!("{}: {}", file.display(), e)),
+        };
+        let parsed = toml::from_str(&text).map_err(|e| format!("{}: {}", file.display(), e))?;
+        Ok((file, parsed))
+    }
+
+    pub fn theme(&self) -> Result<Theme, String> {
+        let mut t = Theme::default();
+        let colors = [
+            (&self.theme.selection_fg, &mut t.selection_fg),
+            (&self.theme.selection_bg, &mut t.selection_bg),
+            (&self.theme.tag, &mut t.tag),
+            (&self.theme.status, &mut t.status),
+        ];
+        for (name, color) in colors {
+            if let Some(name) = name {
+                *color = theme::parse_color(name).map_err(|e| format!("[theme]: {}", e))?;
+            }
+        }
+        Ok(t)
+    }
+
+    pub fn rules(&self) -> Result<Vec<Rule>, String> {
+        self.match_replace.iter()
+            .map(|r| Rule::new(&r.part, &r.pattern, &r.replace, r.hosts.iter().map(|h| h.to_lowercase()).collect()).map_err(|e| format!("[[match_replace]]: {}", e)))
+            .collect()
+    }
+
+    /// The session's settings as they are now
+    fn current(app: &App) -> Self {
+        let t = theme::get();
+        ConfigFile {
+            listeners: Listeners { listen: app.listeners.iter().map(|l| l.spec()).collect() },
+            scope: ScopeHosts { hosts: app.scope.hosts.clone() },
+            theme: Colors {
+                selection_fg: Some(theme::color_name(t.selection_fg)),
+                selection_bg: Some(theme::color_name(t.selection_bg)),
+                tag: Some(theme::color_name(t.tag)),
+                status: Some(theme::color_name(t.status)),
+            },
+            keys: keys::get(),
+            export: app.export_defaults.clone(),
+            match_replace: app.rewrites.iter().map(|r| RuleEntry {
+                part: r.part.name().to_string(),
+                pattern: r.source().to_string(),
+                replace: r.replace.clone(),
+                hosts: r.hosts.clone(),
+            }).collect(),
+        }
+    }
+}
+
+/// `:config` shows which file was read; `:config save [FILE]` writes the current listeners, scope,
+/// match/replace rules, theme, keys and export defaults to it, or to FILE
+pub fn command(app: &mut App, args: &[&str]) {
+    match args {
+        [] => {
+            let state = if app.config_path.exists() { "read at startup" } else { "not present" };
+            app.set_status(format!("Config: {} ({}; :config save [FILE] writes the current settings)", app.config_path.display(), state));
+        }
+        ["save", file @ ..] if file.len() <= 1 => {
+            let path = file.first().map_or_else(|| app.config_path.clone(), PathBuf::from);
+            let result = toml::to_string_pretty(&ConfigFile::current(app)).map_err(|e| e.to_string()).and_then(|text| {
+                if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+                    let _ = fs::create_dir_all(dir);
+                }
+                fs::write(&path, text).map_err(|e| e.to_string())
+            });
+            match result {
+                Ok(()) => app.set_status(format!("Settings written to {}", path.display())),
+                Err(e) => app.set_status(format!("Could not write {}: {}", path.display(), e)),
+            }
+        }
+        _ => app.set_status("usage: :config | :config save [FILE]"),
+    }
+}
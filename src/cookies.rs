@@ -0,0 +1,196 @@
+// Cookie overrides: pinned (domain, name, value) cookies put into matching outbound requests, in
+// place of what the browser sends or added when it sends none, e.g. to hold a session ID steady
+// while the browser keeps rotating it. The jar panel lists the cookies seen so far to pin from.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::app::{App, HttpLog};
+use crate::config;
+use crate::http;
+
+#[derive(Clone)]
+pub struct Override {
+    /// Exact host or `*.suffix`
+    pub domain: String,
+    pub name: String,
+    pub value: String,
+}
+
+impl Override {
+    fn line(&self) -> String {
+        format!("{} {} {}", self.domain, self.name, self.value)
+    }
+}
+
+/// Overrides from `cookies.txt` in the config dir: `DOMAIN NAME VALUE` per line, `#` comments
+#[derive(Default)]
+pub struct CookieOverrides {
+    pub overrides: Vec<Override>,
+    path: Option<PathBuf>,
+}
+
+impl CookieOverrides {
+    pub fn load() -> Result<Self, String> {
+        let path = config::config_dir().join("cookies.txt");
+        let text = fs::read_to_string(&path).unwrap_or_default();
+        let mut overrides = Vec::new();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut parts = line.splitn(3, char::is_whitespace);
+            let (Some(domain), Some(name), Some(value)) = (parts.next(), parts.next(), parts.next()) else {
+                return Err(format!("{}:{}: expected `DOMAIN NAME VALUE`", path.display(), n + 1));
+            };
+            overrides.push(Override { domain: domain.to_lowercase(), name: name.to_string(), value: value.trim().to_string() });
+        }
+        Ok(CookieOverrides { overrides, path: Some(path) })
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.path else { return Ok(()) };
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        let lines: Vec<String> = self.overrides.iter().map(Override::line).collect();
+        fs::write(path, lines.join("\n") + "\n").map_err(|e| format!("Could not write {}: {}", path.display(), e))
+    }
+
+    /// Pin `name` to `value` for `domain`, replacing an earlier pin
+    pub fn set(&mut self, domain: &str, name: &str, value: &str) -> Result<(), String> {
+        let domain = domain.to_lowercase();
+        self.overrides.retain(|o| !(o.domain == domain && o.name == name));
+        self.overrides.push(Override { domain, name: name.to_string(), value: value.to_string() });
+        self.save()
+    }
+
+    /// Whether there was a pin to remove
+    pub fn remove(&mut self, domain: &str, name: &str) -> Result<bool, String> {
+        let before = self.overrides.len();
+        self.overrides.retain(|o| !(o.domain.eq_ignore_ascii_case(domain) && o.name == name));
+        if self.overrides.len() == before {
+            return Ok(false);
+        }
+        self.save().map(|_| true)
+    }
+
+    pub fn get(&self, domain: &str, name: &str) -> Option<&Override> {
+        self.overrides.iter().find(|o| o.domain.eq_ignore_ascii_case(domain) && o.name == name)
+    }
+
+    /// Pins that apply to `host`, one per name, the first listed winning
+    fn for_host(&self, host: &str) -> Vec<(String, String)> {
+        let mut pins: Vec<(String, String)> = Vec::new();
+        for o in self.overrides.iter().filter(|o| config::host_matches(std::slice::from_ref(&o.domain), host)) {
+            if !pins.iter().any(|(n, _)| *n == o.name) {
+                pins.push((o.name.clone(), o.value.clone()));
+            }
+        }
+        pins
+    }
+}
+
+/// `request` with its Cookie header carrying the pinned values for `host`; true when any applied
+pub fn apply(app: &Arc<Mutex<App>>, host: &str, request: Vec<u8>) -> (Vec<u8>, bool) {
+    let pins = {
+        let guard = app.lock().unwrap();
+        if guard.read_only_for(host) {
+            return (request, false);
+        }
+        guard.cookie_overrides.for_host(host)
+    };
+    if pins.is_empty() {
+        return (request, false);
+    }
+    let head = String::from_utf8_lossy(http::split_raw(&request).0).into_owned();
+    // Every Cookie line, as some clients send several
+    let mut pairs: Vec<(String, String)> = head.lines().skip(1)
+        .filter_map(|l| l.split_once(':').filter(|(k, _)| k.trim().eq_ignore_ascii_case("cookie")))
+        .flat_map(|(_, v)| v.split(';').map(str::trim).filter(|p| !p.is_empty()).map(|p| {
+            let (name, value) = p.split_once('=').unwrap_or((p, ""));
+            (name.to_string(), value.to_string())
+        }).collect::<Vec<_>>())
+        .collect();
+    for (name, value) in &pins {
+        match pairs.iter_mut().find(|(n, _)| n == name) {
+            Some(pair) => pair.1 = value.clone(),
+            None => pairs.push((name.clone(), value.clone())),
+        }
+    }
+    let cookie = pairs.iter().map(|(n, v)| format!("{}={}", n, v)).collect::<Vec<_>>().join("; ");
+    (http::set_header(&request, "Cookie", &cookie), true)
+}
+
+/// A cookie seen in the traffic, with its pin if it has one
+pub struct JarRow {
+    pub domain: String,
+    pub name: String,
+    pub value: String,
+    pub pinned: Option<String>,
+}
+
+pub struct CookieJar {
+    pub rows: Vec<JarRow>,
+    pub selected: usize,
+}
+
+impl CookieJar {
+    /// Latest value of each cookie set by a response or sent by a request, and every pin
+    pub fn collect<'a>(logs: impl Iterator<Item = &'a HttpLog>, overrides: &CookieOverrides) -> Self {
+        let mut seen: BTreeMap<(String, String), String> = BTreeMap::new();
+        for log in logs.filter(|l| !l.host.is_empty()) {
+            let request = log.request_raw.bytes();
+            let request = String::from_utf8_lossy(http::split_raw(&request).0).into_owned();
+            for (_, value) in request.lines().skip(1).filter_map(|l| l.split_once(':')).filter(|(k, _)| k.trim().eq_ignore_ascii_case("cookie")) {
+                for pair in value.split(';').map(str::trim).filter(|p| !p.is_empty()) {
+                    let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+                    seen.insert((log.host.to_lowercase(), name.to_string()), value.to_string());
+                }
+            }
+            let response = log.response_raw.bytes();
+            let response = String::from_utf8_lossy(http::split_raw(&response).0).into_owned();
+            for (_, value) in response.lines().skip(1).filter_map(|l| l.split_once(':')).filter(|(k, _)| k.trim().eq_ignore_ascii_case("set-cookie")) {
+                let mut attributes = value.split(';').map(str::trim);
+                let Some((name, value)) = attributes.next().and_then(|p| p.split_once('=')) else { continue };
+                let domain = attributes.find_map(|a| a.split_once('=').filter(|(k, _)| k.eq_ignore_ascii_case("domain")).map(|(_, d)| d.trim_start_matches('.').to_lowercase()));
+                seen.insert((domain.unwrap_or_else(|| log.host.to_lowercase()), name.to_string()), value.to_string());
+            }
+        }
+        for o in &overrides.overrides {
+            seen.entry((o.domain.clone(), o.name.clone())).or_default();
+        }
+        let rows = seen.into_iter().map(|((domain, name), value)| {
+            let pinned = overrides.get(&domain, &name).map(|o| o.value.clone());
+            JarRow { domain, name, value, pinned }
+        }).collect();
+        CookieJar { rows, selected: 0 }
+    }
+}
+
+/// `:cookie set DOMAIN NAME VALUE` pins a cookie, `:cookie unset DOMAIN NAME` lets it go and
+/// `:cookie list` shows the pins
+pub fn command(app: &mut App, args: &[&str]) {
+    const USAGE: &str = "usage: :cookie list | set DOMAIN NAME VALUE | unset DOMAIN NAME   (:cookies opens the jar)";
+    let result = match args {
+        [] | ["list"] => Ok(match app.cookie_overrides.overrides.is_empty() {
+            true => "No cookie overrides; pin one from the jar (:cookies) or with :cookie set DOMAIN NAME VALUE".to_string(),
+            false => app.cookie_overrides.overrides.iter().map(Override::line).collect::<Vec<_>>().join("   "),
+        }),
+        ["set", domain, name, value @ ..] if !value.is_empty() => {
+            let value = value.join(" ");
+            app.cookie_overrides.set(domain, name, &value).map(|_| format!("Requests to {} send {}={}", domain, name, value))
+        }
+        ["unset", domain, name] => app.cookie_overrides.remove(domain, name).map(|removed| match removed {
+            true => format!("{} on {} is the browser's again", name, domain),
+            false => format!("No override for {} on {}", name, domain),
+        }),
+        _ => Err(USAGE.to_string()),
+    };
+    match result {
+        Ok(msg) | Err(msg) => app.set_status(msg),
+    }
+}
@@ -0,0 +1,51 @@
+// Correlation and trace IDs, for finding the entry behind a server-side log line or trace
+
+use crate::app::HttpLog;
+use crate::http;
+
+/// Looked for in the request first, then the response, in this order
+const HEADERS: [&str; 7] = [
+    "x-request-id",
+    "x-correlation-id",
+    "traceparent",
+    "x-amzn-trace-id",
+    "x-b3-traceid",
+    "b3",
+    "x-cloud-trace-context",
+];
+
+/// The ID a header carries: the trace ID of a trace context, the whole value otherwise
+fn id_of(header: &str, value: &str) -> Option<String> {
+    let value = value.trim();
+    let id = match header {
+        // version-traceid-parentid-flags
+        "traceparent" => value.split('-').nth(1)?,
+        // Root=1-5759e988-bd862e3fe1be46a994272793;Parent=…;Sampled=1
+        "x-amzn-trace-id" => value.split(';').find_map(|p| p.trim().strip_prefix("Root="))?,
+        // traceid-spanid-sampled-parentid
+        "b3" => value.split('-').next()?,
+        // TRACE_ID/SPAN_ID;o=1
+        "x-cloud-trace-context" => value.split('/').next()?,
+        _ => value,
+    };
+    (!id.is_empty()).then(|| id.to_string())
+}
+
+/// `(header, id)` pairs found in an exchange, request side first; one per header name
+pub fn extract(log: &HttpLog) -> Vec<(String, String)> {
+    let raw = log.request_raw.bytes();
+    let request = String::from_utf8_lossy(http::split_raw(&raw).0).to_string();
+    let response = log.response_head();
+    let mut found: Vec<(String, String)> = Vec::new();
+    for head in [&request, &response] {
+        for name in HEADERS {
+            if found.iter().any(|(n, _)| n == name) {
+                continue;
+            }
+            if let Some(id) = http::header_value(head, name).and_then(|v| id_of(name, v)) {
+                found.push((name.to_string(), id));
+            }
+        }
+    }
+    found
+}
@@ -0,0 +1,133 @@
+// CORS misconfiguration checks, passive on captured traffic and active with Origin probes,
+// plus the development mode that adds permissive CORS headers for chosen hosts
+
+use std::sync::{Arc, Mutex};
+
+use tracing::info;
+
+use crate::app::{App, HttpLog};
+use crate::http;
+use crate::issues::{Issue, Severity};
+use crate::send;
+use crate::throttle;
+
+/// Classify an Access-Control-Allow-Origin answer to a request sent with `origin`
+fn classify(origin: Option<&str>, host: &str, acao: &str, credentials: bool) -> Option<(Severity, String, String)> {
+    let creds = if credentials { " with credentials" } else { "" };
+    let severity = |base: Severity| if credentials { Severity::High } else { base };
+    if acao == "*" {
+        // Browsers refuse `*` with credentials, but it shows the intent to allow everything
+        return credentials.then(|| (Severity::Medium, "CORS wildcard with credentials".to_string(), "Access-Control-Allow-Origin: * and Allow-Credentials: true".to_string()));
+    }
+    if acao == "null" {
+        return Some((severity(Severity::Medium), "CORS allows null origin".to_string(), format!("null origin accepted{}", creds)));
+    }
+    let origin = origin?;
+    let origin_host = origin.split_once("://").map_or(origin, |(_, h)| h).split(':').next().unwrap_or("");
+    if acao == origin && origin_host != host && !origin_host.ends_with(&format!(".{}", host)) {
+        return Some((severity(Severity::Low), "CORS origin reflection".to_string(), format!("{} reflected{}", origin, creds)));
+    }
+    None
+}
+
+fn response_cors(response_head: &str) -> Option<(String, bool)> {
+    let acao = http::header_value(response_head, "access-control-allow-origin")?.to_string();
+    let credentials = http::header_value(response_head, "access-control-allow-credentials").is_some_and(|v| v.eq_ignore_ascii_case("true"));
+    Some((acao, credentials))
+}
+
+fn issue(log: &HttpLog, (severity, title, detail): (Severity, String, String)) -> Issue {
+    Issue { severity, host: log.host.clone(), url: log.full_url(), title, detail }
+}
+
+/// Issues from the CORS headers of a captured exchange
+pub fn passive(log: &HttpLog) -> Vec<Issue> {
+    let Some((acao, credentials)) = response_cors(&log.response_head()) else { return Vec::new() };
+    let request = String::from_utf8_lossy(http::split_raw(&log.request_raw.bytes()).0).to_string();
+    let origin = http::header_value(&request, "origin");
+    classify(origin, &log.host, &acao, credentials).map(|f| issue(log, f)).into_iter().collect()
+}
+
+/// Resend the entry with hostile Origin values in the background
+pub fn probe(app: Arc<Mutex<App>>, log: HttpLog) {
+    tokio::spawn(async move {
+        let base = send::replayable(&log);
+        let via = app.lock().unwrap().routes.route(&log.host).cloned();
+        let port = send::target_port(&log);
+        let scheme = if log.tls { "https" } else { "http" };
+        let origins = [
+            "https://belch-probe.example".to_string(),
+            "null".to_string(),
+            format!("{}://{}.belch-probe.example", scheme, log.host),
+            format!("{}://belch-probe{}", scheme, log.host),
+        ];
+        let mut found = 0;
+        for origin in &origins {
+            let permit = throttle::acquire(&log.host).await;
+            let sent = send::send(&log.host, port, log.tls, &http::set_header(&base, "Origin", origin), via.as_ref()).await;
+            drop(permit);
+            let Ok(resp) = sent else { continue };
+            let head = String::from_utf8_lossy(http::split_raw(&resp).0).to_string();
+            let Some((acao, credentials)) = response_cors(&head) else { continue };
+            if let Some(finding) = classify(Some(origin), &log.host, &acao, credentials) {
+                found += 1;
+                app.lock().unwrap().add_issue(issue(&log, finding));
+            }
+        }
+        info!("CORS probe of {} finished: {} findings", log.full_url(), found);
+        app.lock().unwrap().set_status(format!("CORS probe {}: {} finding(s)", log.host, found));
+    });
+}
+
+/// Headers granting the request's origin (or any origin) everything it asks for
+fn dev_headers(request: &[u8]) -> Vec<(&'static str, String)> {
+    let head = http::RequestHead::parse(request);
+    let ask = |name: &str| head.as_ref().and_then(|h| h.header_str(name)).map(str::to_string);
+    let mut headers = Vec::new();
+    match ask("origin") {
+        // A concrete origin is needed for credentialed requests
+        Some(origin) => {
+            headers.push(("Access-Control-Allow-Origin", origin));
+            headers.push(("Access-Control-Allow-Credentials", "true".to_string()));
+            headers.push(("Vary", "Origin".to_string()));
+        }
+        None => headers.push(("Access-Control-Allow-Origin", "*".to_string())),
+    }
+    headers.push(("Access-Control-Allow-Methods", ask("access-control-request-method")
+        .map_or("GET, POST, PUT, PATCH, DELETE, OPTIONS, HEAD".to_string(), |m| format!("{}, OPTIONS", m))));
+    headers.push(("Access-Control-Allow-Headers", ask("access-control-request-headers").unwrap_or_else(|| "*".to_string())));
+    headers.push(("Access-Control-Expose-Headers", "*".to_string()));
+    headers.push(("Access-Control-Max-Age", "600".to_string()));
+    headers
+}
+
+/// Answer for a CORS preflight, which the origin server may not understand
+pub fn dev_preflight(request: &[u8]) -> Option<Vec<u8>> {
+    let head = http::RequestHead::parse(request)?;
+    if !head.method.eq_ignore_ascii_case("OPTIONS") || head.header("access-control-request-method").is_none() {
+        return None;
+    }
+    let mut out = String::from("HTTP/1.1 204 No Content\r\n");
+    for (name, value) in dev_headers(request) {
+        out.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    out.push_str("Content-Length: 0\r\nConnection: close\r\n\r\n");
+    Some(out.into_bytes())
+}
+
+/// Response with its own CORS headers replaced by permissive ones
+pub fn dev_unblock(request: &[u8], response: &[u8]) -> Vec<u8> {
+    let (head, body) = http::split_raw(response);
+    let head = String::from_utf8_lossy(head);
+    let mut out: String = head.split("\r\n")
+        .filter(|l| !l.to_lowercase().starts_with("access-control-"))
+        .map(|l| format!("{}\r\n", l))
+        .collect();
+    for (name, value) in dev_headers(request) {
+        out.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    out.push_str("\r\n");
+    let mut bytes = out.into_bytes();
+    bytes.extend_from_slice(body);
+    bytes
+}
@@ -0,0 +1,106 @@
+// Passive detection of submitted credentials
+
+use base64::Engine;
+use serde_json::Value;
+
+use crate::http;
+
+const USER_FIELDS: &[&str] = &[
+    "user", "username", "user_name", "userid", "user_id", "login", "email", "mail", "uname",
+    "j_username", "account", "log",
+];
+const PASS_FIELDS: &[&str] = &["pass", "password", "passwd", "pwd", "j_password", "secret", "passphrase"];
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct Credential {
+    pub host: String,
+    pub source: &'static str,
+    pub username: String,
+    pub password: String,
+    pub url: String,
+}
+
+fn field_kind(name: &str) -> Option<bool> {
+    // Match the last bracketed/dotted component, e.g. `login[password]`
+    let name = name.to_lowercase();
+    let key = name.trim_end_matches(']').rsplit(['[', '.']).next().unwrap_or("");
+    if PASS_FIELDS.contains(&key) {
+        Some(true)
+    } else if USER_FIELDS.contains(&key) {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+fn from_pairs<'a>(pairs: impl Iterator<Item = (String, String)> + 'a) -> Option<(String, String)> {
+    let (mut user, mut pass) = (None, None);
+    for (k, v) in pairs {
+        match field_kind(&k) {
+            Some(true) if pass.is_none() => pass = Some(v),
+            Some(false) if user.is_none() => user = Some(v),
+            _ => {}
+        }
+    }
+    pass.map(|p| (user.unwrap_or_default(), p))
+}
+
+pub fn json_pairs(value: &Value, out: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map {
+                match v {
+                    Value::String(s) => out.push((k.clone(), s.clone())),
+                    Value::Number(n) => out.push((k.clone(), n.to_string())),
+                    _ => json_pairs(v, out),
+                }
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|v| json_pairs(v, out)),
+        _ => {}
+    }
+}
+
+/// Credentials in a raw client request: Basic auth headers, form or JSON bodies
+pub fn extract(request: &[u8], host: &str) -> Vec<Credential> {
+    let Some(parsed) = http::RequestHead::parse(request) else { return Vec::new() };
+    let ctype = parsed.header_str("content-type").unwrap_or("").to_lowercase();
+    let has_auth = parsed.header("authorization").is_some() || parsed.header("proxy-authorization").is_some();
+    // Most requests carry nothing of interest; skip the copy
+    if !has_auth && !ctype.contains("json") && !ctype.contains("x-www-form-urlencoded") {
+        return Vec::new();
+    }
+    let text = String::from_utf8_lossy(request);
+    let (head, body) = http::split_message(&text);
+    let url = parsed.target.to_string();
+    let found = |source, (username, password)| Credential {
+        host: host.to_string(), source, username, password, url: url.clone(),
+    };
+    let mut creds = Vec::new();
+
+    for name in ["authorization", "proxy-authorization"] {
+        let basic = http::header_value(head, name)
+            .and_then(|v| v.strip_prefix("Basic ").or_else(|| v.strip_prefix("basic ")))
+            .and_then(|b| base64::engine::general_purpose::STANDARD.decode(b.trim()).ok())
+            .map(|d| String::from_utf8_lossy(&d).to_string());
+        if let Some((user, pass)) = basic.as_deref().and_then(|d| d.split_once(':')) {
+            creds.push(found("basic", (user.to_string(), pass.to_string())));
+        }
+    }
+
+    let pair = if ctype.contains("json") {
+        serde_json::from_str::<Value>(body).ok().and_then(|v| {
+            let mut pairs = Vec::new();
+            json_pairs(&v, &mut pairs);
+            from_pairs(pairs.into_iter())
+        })
+    } else if ctype.contains("x-www-form-urlencoded") {
+        from_pairs(http::parse_query(body).into_iter())
+    } else {
+        None
+    };
+    if let Some(pair) = pair {
+        creds.push(found(if ctype.contains("json") { "json" } else { "form" }, pair));
+    }
+    creds
+}
@@ -0,0 +1,112 @@
+// Content-Security-Policy analysis: weak sources and missing directives, one report per host
+
+use crate::app::HttpLog;
+use crate::http;
+use crate::issues::{Issue, Severity};
+
+/// Hosts known to serve JSONP endpoints or script gadgets that defeat an allowlist
+const BYPASS_HOSTS: [&str; 6] = [
+    "ajax.googleapis.com",
+    "cdnjs.cloudflare.com",
+    "cdn.jsdelivr.net",
+    "unpkg.com",
+    "www.google.com",
+    "accounts.google.com",
+];
+
+/// Directives and their sources, in policy order
+pub fn parse(policy: &str) -> Vec<(String, Vec<String>)> {
+    policy.split(';')
+        .filter_map(|d| {
+            let mut words = d.split_whitespace();
+            let name = words.next()?.to_lowercase();
+            Some((name, words.map(str::to_string).collect()))
+        })
+        .collect()
+}
+
+/// One directive per line with its sources aligned after it
+pub fn pretty(policy: &str) -> String {
+    let directives = parse(policy);
+    let width = directives.iter().map(|(n, _)| n.len()).max().unwrap_or(0);
+    directives.iter()
+        .map(|(name, sources)| format!("  {:<width$}  {}", name, sources.join(" "), width = width))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Weaknesses of a policy, most severe first
+pub fn evaluate(policy: &str) -> Vec<(Severity, String)> {
+    let directives = parse(policy);
+    let get = |name: &str| directives.iter().find(|(n, _)| n == name).map(|(_, s)| s.as_slice());
+    let default = get("default-src");
+    let mut found = Vec::new();
+    match get("script-src").or(default) {
+        None => found.push((Severity::Medium, "No script-src or default-src: scripts are unrestricted".to_string())),
+        Some(scripts) => {
+            let has = |s: &str| scripts.iter().any(|x| x.eq_ignore_ascii_case(s));
+            // Nonces and hashes make browsers ignore 'unsafe-inline'
+            let pinned = scripts.iter().any(|s| s.starts_with("'nonce-") || s.starts_with("'sha"));
+            let directive = if get("script-src").is_some() { "script-src" } else { "default-src" };
+            if has("'unsafe-inline'") && !pinned {
+                found.push((Severity::Medium, format!("{} allows 'unsafe-inline'", directive)));
+            }
+            if has("'unsafe-eval'") {
+                found.push((Severity::Low, format!("{} allows 'unsafe-eval'", directive)));
+            }
+            for wide in ["*", "http:", "https:", "data:", "blob:"] {
+                if has(wide) && !has("'strict-dynamic'") {
+                    found.push((Severity::Medium, format!("{} allows any source via {}", directive, wide)));
+                }
+            }
+            for host in BYPASS_HOSTS {
+                if scripts.iter().any(|s| s.trim_start_matches("https://").trim_start_matches("*.") == host) {
+                    found.push((Severity::Low, format!("{} allows {}, which hosts known bypasses", directive, host)));
+                }
+            }
+        }
+    }
+    if get("object-src").or(default).is_none_or(|s| !s.iter().any(|x| x == "'none'")) {
+        found.push((Severity::Low, "object-src is not 'none': plugins can load script".to_string()));
+    }
+    if get("base-uri").is_none() {
+        found.push((Severity::Low, "No base-uri: an injected <base> can redirect relative scripts".to_string()));
+    }
+    if get("frame-ancestors").is_none() {
+        found.push((Severity::Info, "No frame-ancestors: framing is left to X-Frame-Options".to_string()));
+    }
+    found.sort_by_key(|(s, _)| std::cmp::Reverse(*s));
+    found
+}
+
+/// A report for an HTML response carrying a policy, filed against the host's origin
+pub fn passive(log: &HttpLog) -> Option<Issue> {
+    let head = log.response_head();
+    if !http::header_value(&head, "content-type").is_some_and(|t| t.to_lowercase().contains("html")) {
+        return None;
+    }
+    let (policy, report_only) = match http::header_value(&head, "content-security-policy") {
+        Some(p) => (p, false),
+        None => (http::header_value(&head, "content-security-policy-report-only")?, true),
+    };
+    let mut weaknesses = evaluate(policy);
+    if report_only {
+        weaknesses.insert(0, (Severity::Info, "Report-only: nothing is enforced".to_string()));
+    }
+    let severity = weaknesses.iter().map(|(s, _)| *s).max().unwrap_or(Severity::Info);
+    let list: Vec<String> = weaknesses.iter().map(|(s, w)| format!("[{}] {}", s.label(), w)).collect();
+    let summary = match weaknesses.first() {
+        Some((_, w)) => format!("{} weakness(es), worst: {}", weaknesses.len(), w),
+        None => "No known weaknesses".to_string(),
+    };
+    let url = log.full_url();
+    let origin = url.split('/').take(3).collect::<Vec<_>>().join("/");
+    // The fixed title and origin URL make it one report per host
+    Some(Issue {
+        severity,
+        host: log.host.clone(),
+        url: format!("{}/", origin),
+        title: "Content-Security-Policy report".to_string(),
+        detail: format!("{}\n\n{}\n\nPolicy as seen on {}:\n{}", summary, list.join("\n"), url, pretty(policy)),
+    })
+}
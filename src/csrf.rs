@@ -0,0 +1,237 @@
+// CSRF token refresh: before a state-changing request is resent, GET a page of the same site,
+// pull a fresh token out of it and put it into the request
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use regex::Regex;
+use tracing::info;
+
+use crate::app::{App, HttpLog};
+use crate::config;
+use crate::http;
+use crate::send;
+use crate::upstream::Upstream;
+
+#[derive(Clone)]
+pub struct Rule {
+    /// Exact host or `*.suffix`
+    pub host: String,
+    /// Path fetched for the token, on the request's own origin
+    pub page: String,
+    /// Form or query parameter, JSON key, or a header when written `Name:`
+    pub param: String,
+    /// First group, or the whole match, is the token
+    pub pattern: Regex,
+}
+
+impl Rule {
+    fn line(&self) -> String {
+        format!("{} {} {} {}", self.host, self.page, self.param, self.pattern.as_str())
+    }
+}
+
+/// Rules from `csrf.txt` in the config dir: `HOST PAGE PARAM REGEX` per line, `#` comments
+#[derive(Default)]
+pub struct CsrfRules {
+    pub rules: Vec<Rule>,
+    path: Option<PathBuf>,
+}
+
+impl CsrfRules {
+    pub fn load() -> Result<Self, String> {
+        let path = config::config_dir().join("csrf.txt");
+        let text = fs::read_to_string(&path).unwrap_or_default();
+        let mut rules = Vec::new();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            rules.push(parse(line).map_err(|e| format!("{}:{}: {}", path.display(), n + 1, e))?);
+        }
+        Ok(CsrfRules { rules, path: Some(path) })
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.path else { return Ok(()) };
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        let lines: Vec<String> = self.rules.iter().map(Rule::line).collect();
+        fs::write(path, lines.join("\n") + "\n").map_err(|e| format!("Could not write {}: {}", path.display(), e))
+    }
+
+    fn find(&self, host: &str) -> Option<&Rule> {
+        self.rules.iter().find(|r| config::host_matches(std::slice::from_ref(&r.host), host))
+    }
+}
+
+fn parse(line: &str) -> Result<Rule, String> {
+    let mut parts = line.splitn(4, char::is_whitespace);
+    let (Some(host), Some(page), Some(param), Some(pattern)) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+        return Err("expected `HOST PAGE PARAM REGEX`".into());
+    };
+    if !page.starts_with('/') {
+        return Err(format!("page `{}` must be a path starting with /", page));
+    }
+    let pattern = Regex::new(pattern.trim()).map_err(|e| format!("bad regex: {}", e))?;
+    Ok(Rule { host: host.to_lowercase(), page: page.to_string(), param: param.to_string(), pattern })
+}
+
+/// `a=1&b=2` with `name`'s value replaced; None when it has no such parameter
+fn replace_param(pairs: &str, name: &str, value: &str) -> Option<String> {
+    let mut found = false;
+    let out: Vec<String> = pairs.split('&').map(|pair| {
+        let key = pair.split('=').next().unwrap_or("");
+        if http::url_decode(key) == name {
+            found = true;
+            format!("{}={}", key, http::url_encode(value))
+        } else {
+            pair.to_string()
+        }
+    }).collect();
+    found.then(|| out.join("&"))
+}
+
+/// The request with the token in place: as a header, a JSON key, or a form or query parameter,
+/// added to the form body or the query when the request doesn't have it yet
+fn inject(request: &[u8], param: &str, token: &str) -> Vec<u8> {
+    if let Some(header) = param.strip_suffix(':') {
+        return http::set_header(request, header, token);
+    }
+    let (head, body) = http::split_raw(request);
+    let head = String::from_utf8_lossy(head).to_string();
+    let (first, headers) = head.split_once("\r\n").unwrap_or((&head, ""));
+    let mut parts = first.splitn(3, ' ');
+    let (method, target, version) = (parts.next().unwrap_or(""), parts.next().unwrap_or("/"), parts.next().unwrap_or("HTTP/1.1"));
+    let (path, query) = target.split_once('?').map_or((target, None), |(p, q)| (p, Some(q)));
+    let content_type = http::header_value(&head, "content-type").unwrap_or("").to_lowercase();
+    let form = content_type.contains("x-www-form-urlencoded");
+    let text = String::from_utf8_lossy(body).to_string();
+    let mut new_body = None;
+    let mut new_query = query.and_then(|q| replace_param(q, param, token));
+    if form {
+        new_body = replace_param(&text, param, token);
+    } else if content_type.contains("json") {
+        if let Ok(serde_json::Value::Object(mut object)) = serde_json::from_str::<serde_json::Value>(&text) {
+            if object.contains_key(param) {
+                object.insert(param.to_string(), serde_json::Value::String(token.to_string()));
+                new_body = Some(serde_json::Value::Object(object).to_string());
+            }
+        }
+    }
+    if new_body.is_none() && new_query.is_none() {
+        let pair = format!("{}={}", http::url_encode(param), http::url_encode(token));
+        if form {
+            new_body = Some(if text.is_empty() { pair } else { format!("{}&{}", text, pair) });
+        } else {
+            new_query = Some(match query {
+                Some(q) if !q.is_empty() => format!("{}&{}", q, pair),
+                _ => pair,
+            });
+        }
+    }
+    let target = match new_query.as_deref().or(query) {
+        Some(q) => format!("{}?{}", path, q),
+        None => path.to_string(),
+    };
+    let body = new_body.map_or(body.to_vec(), String::into_bytes);
+    let mut out = format!("{} {} {}\r\n{}\r\n\r\n", method, target, version, headers).into_bytes();
+    out.extend_from_slice(&body);
+    if !body.is_empty() || http::header_value(&head, "content-length").is_some() {
+        out = http::set_header(&out, "Content-Length", &body.len().to_string());
+    }
+    out
+}
+
+/// Fresh token from the rule's page, and cookies the page set, fetched with the request's cookies
+async fn fetch(rule: &Rule, log: &HttpLog, port: u16, request: &[u8], via: Option<&Upstream>) -> Result<(String, Vec<(String, String)>), String> {
+    let (head, _) = http::split_raw(request);
+    let head = String::from_utf8_lossy(head).to_string();
+    let mut get = format!("GET {} HTTP/1.1\r\nHost: {}\r\n", rule.page, http::header_value(&head, "host").unwrap_or(&log.host));
+    for name in ["Cookie", "Authorization", "User-Agent"] {
+        if let Some(value) = http::header_value(&head, name) {
+            get.push_str(&format!("{}: {}\r\n", name, value));
+        }
+    }
+    get.push_str("Accept: text/html,*/*\r\nConnection: close\r\n\r\n");
+    let raw = send::send(&log.host, port, log.tls, get.as_bytes(), via).await.map_err(|(_, e)| e)?;
+    let (head, body) = http::split_raw(&raw);
+    let head = String::from_utf8_lossy(head).to_string();
+    let page = format!("{}\r\n\r\n{}", head, String::from_utf8_lossy(&http::decode_body(&head, body)));
+    let caps = rule.pattern.captures(&page).ok_or(format!("no token matches in {}", rule.page))?;
+    let token = caps.get(1).or_else(|| caps.get(0)).map_or("", |m| m.as_str()).to_string();
+    let cookies = head.lines().skip(1)
+        .filter_map(|l| l.split_once(':').filter(|(k, _)| k.trim().eq_ignore_ascii_case("set-cookie")))
+        .filter_map(|(_, v)| v.trim().split(';').next()?.split_once('=').map(|(k, v)| (k.trim().to_string(), v.trim().to_string())))
+        .collect();
+    Ok((token, cookies))
+}
+
+/// A state-changing request with a fresh token from its host's rule; unchanged for other
+/// requests, hosts without a rule, or when the token can't be fetched
+pub async fn refresh(app: &Arc<Mutex<App>>, log: &HttpLog, port: u16, request: Vec<u8>, via: Option<&Upstream>) -> Vec<u8> {
+    let method = http::RequestHead::parse(&request).map(|h| h.method.to_uppercase()).unwrap_or_default();
+    if matches!(method.as_str(), "GET" | "HEAD" | "OPTIONS" | "") {
+        return request;
+    }
+    let Some(rule) = app.lock().unwrap().csrf.find(&log.host).cloned() else { return request };
+    match fetch(&rule, log, port, &request, via).await {
+        Ok((token, cookies)) => {
+            // Double-submit schemes want the cookie that came with the token
+            let mut request = inject(&request, &rule.param, &token);
+            if !cookies.is_empty() {
+                let head = String::from_utf8_lossy(http::split_raw(&request).0).to_string();
+                let mut pairs: Vec<(String, String)> = http::header_value(&head, "cookie").unwrap_or("").split(';')
+                    .filter_map(|p| p.trim().split_once('='))
+                    .filter(|(k, _)| !cookies.iter().any(|(n, _)| n == k))
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect();
+                pairs.extend(cookies);
+                let header: Vec<String> = pairs.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+                request = http::set_header(&request, "Cookie", &header.join("; "));
+            }
+            info!("CSRF token for {} refreshed from {}", log.host, rule.page);
+            request
+        }
+        Err(e) => {
+            app.lock().unwrap().set_status(format!("CSRF refresh from {} failed: {}; sent as is", rule.page, e));
+            request
+        }
+    }
+}
+
+/// `:csrf list|add PAGE PARAM REGEX|delete N`; `add` makes a rule for the selected entry's host
+pub fn command(app: &mut App, args: &[&str]) {
+    const USAGE: &str = "usage: :csrf list | add PAGE PARAM REGEX | delete N";
+    let result = match args {
+        [] | ["list"] => Ok(match app.csrf.rules.is_empty() {
+            true => "No CSRF rules; :csrf add PAGE PARAM REGEX for the selected entry's host".to_string(),
+            false => app.csrf.rules.iter().enumerate().map(|(i, r)| format!("{}: {}", i + 1, r.line())).collect::<Vec<_>>().join("   "),
+        }),
+        ["add", page, param, pattern @ ..] if !pattern.is_empty() => {
+            match app.selected_log().map(|l| l.host.clone()).filter(|h| !h.is_empty()) {
+                Some(host) => parse(&format!("{} {} {} {}", host, page, param, pattern.join(" "))).and_then(|rule| {
+                    let msg = format!("State-changing resends to {} get a fresh {} from {}", host, rule.param, rule.page);
+                    app.csrf.rules.retain(|r| r.host != rule.host);
+                    app.csrf.rules.push(rule);
+                    app.csrf.save().map(|_| msg)
+                }),
+                None => Err("Select an entry of the host the rule is for".to_string()),
+            }
+        }
+        ["delete", n] => match n.parse::<usize>().ok().filter(|n| (1..=app.csrf.rules.len()).contains(n)) {
+            Some(n) => {
+                let rule = app.csrf.rules.remove(n - 1);
+                app.csrf.save().map(|_| format!("Deleted the CSRF rule for {}", rule.host))
+            }
+            None => Err(format!("No CSRF rule {}", n)),
+        },
+        _ => Err(USAGE.to_string()),
+    };
+    match result {
+        Ok(msg) | Err(msg) => app.set_status(msg),
+    }
+}
@@ -0,0 +1,104 @@
+// What the detail pane decodes out of the selected entry, worked out when the selection or the
+// entry changes rather than on every frame
+
+use std::cell::OnceCell;
+
+use serde_json::Value;
+
+use crate::app::{App, HttpLog};
+use crate::forms;
+use crate::grpc;
+use crate::http;
+use crate::jsonpath;
+use crate::packed;
+use crate::plugins;
+use crate::saml;
+use crate::serialized;
+use crate::sse;
+
+/// Response body bytes shown until `L` loads the rest
+pub const PREVIEW_BYTES: usize = 64 * 1024;
+
+/// The entry a `Detail` was worked out from, with its sizes then (a streaming response grows),
+/// and whether the whole body was asked for
+type Key = (usize, usize, usize, bool);
+
+#[derive(Default)]
+pub struct Detail {
+    key: Option<Key>,
+    /// Request text as shown
+    pub request: String,
+    /// Response text as shown: the head and the first `PREVIEW_BYTES` of the body, or all of it after `L`
+    pub response: String,
+    /// Length of the whole response body, which `response` may stop short of
+    pub response_body_len: usize,
+    /// HTML forms of the response page
+    pub forms: Vec<forms::Form>,
+    /// SAML messages in the request or the response form
+    pub saml: Vec<saml::Saml>,
+    /// ViewState and Java serialized objects
+    pub serialized: Vec<serialized::Blob>,
+    /// gRPC, gRPC-Web and Connect messages
+    pub grpc: Vec<grpc::Decoded>,
+    /// MessagePack, CBOR and AMF bodies
+    pub packed: Vec<packed::Decoded>,
+    /// Each plugin's `belch_describe` text, with the plugin's name
+    pub plugins: Vec<(String, String)>,
+    /// Server-sent events received so far, parsed again only when the stream grows
+    pub events: Vec<sse::Event>,
+    /// The response parsed as JSON, once a `:jq` query first asks for it
+    json: OnceCell<Option<Value>>,
+}
+
+impl Detail {
+    fn of(key: Key, log: &HttpLog) -> Self {
+        let (request, response) = (log.request_raw.bytes(), log.response_raw.bytes());
+        let (shown, response_body_len) = preview(log.response_text(), (!key.3).then_some(PREVIEW_BYTES));
+        Detail {
+            key: Some(key),
+            request: log.request.text(),
+            response: shown,
+            response_body_len,
+            forms: forms::of(log),
+            saml: saml::find(log),
+            serialized: serialized::find(log),
+            grpc: grpc::find(log),
+            packed: packed::find(log),
+            plugins: plugins::all().iter().filter_map(|p| Some((p.name.clone(), p.describe(&request, &response)?))).collect(),
+            json: OnceCell::new(),
+            events: match sse::is_stream(&log.response_head()) {
+                true => sse::parse(&String::from_utf8_lossy(&log.response_body())),
+                false => Vec::new(),
+            },
+        }
+    }
+}
+
+impl Detail {
+    /// The response of `log`, the entry this was worked out from, as JSON
+    pub fn json(&self, log: &HttpLog) -> Option<&Value> {
+        self.json.get_or_init(|| jsonpath::response_json(log)).as_ref()
+    }
+}
+
+/// `text` with its body cut to at most `limit` bytes on a character boundary, and the whole body's length
+fn preview(mut text: String, limit: Option<usize>) -> (String, usize) {
+    let body = http::split_message(&text).1;
+    let full = body.len();
+    if let Some(mut cut) = limit.filter(|l| *l < full) {
+        while !body.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        // The body is the tail of the text
+        text.truncate(text.len() - full + cut);
+    }
+    (text, full)
+}
+
+/// Bring `app.detail` up to date with entry `index`
+pub fn update(app: &mut App, index: Option<usize>) {
+    let key = index.and_then(|i| app.logs.get(i).map(|l| (i, l.request_raw.len(), l.response_raw.len(), app.full_body)));
+    if app.detail.key != key {
+        app.detail = key.map_or_else(Detail::default, |k| Detail::of(k, &app.logs[k.0]));
+    }
+}
@@ -0,0 +1,174 @@
+// "Did the proxy break this?": fetch a URL directly and through belch, then diff the answers. A
+// second direct fetch tells what varies on its own (dates, tokens) from what belch changed.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use tracing::info;
+
+use crate::app::{App, Origin};
+use crate::{http, send, spider, throttle};
+
+/// Header values shown up to this many characters
+const SHOWN: usize = 60;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Info,
+    Same,
+    /// Differs between the two direct fetches too
+    Noise,
+    /// Only differs through belch
+    Changed,
+}
+
+pub struct Diagnosis {
+    pub url: String,
+    pub running: bool,
+    pub lines: Vec<(Kind, String)>,
+    pub scroll: usize,
+}
+
+impl Diagnosis {
+    pub fn changes(&self) -> usize {
+        self.lines.iter().filter(|(k, _)| *k == Kind::Changed).count()
+    }
+}
+
+type Fetched = Result<Vec<u8>, String>;
+
+/// Response head split into its status line and headers by lowercase name
+fn parse(raw: &[u8]) -> (String, BTreeMap<String, Vec<String>>, Vec<u8>) {
+    let (head, body) = http::split_raw(raw);
+    let head = String::from_utf8_lossy(head).into_owned();
+    let mut headers: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for line in head.lines().skip(1) {
+        if let Some((name, value)) = line.split_once(':') {
+            headers.entry(name.trim().to_lowercase()).or_default().push(value.trim().to_string());
+        }
+    }
+    let status = head.lines().next().unwrap_or("").to_string();
+    let body = http::decode_body(&head, body);
+    (status, headers, body)
+}
+
+fn shown(values: &[String]) -> String {
+    let text = values.join(", ");
+    match text.char_indices().nth(SHOWN) {
+        Some((i, _)) => format!("{}…", &text[..i]),
+        None => text,
+    }
+}
+
+/// A few bytes of `body` around `at`, printable
+fn around(body: &[u8], at: usize) -> String {
+    let start = at.saturating_sub(12);
+    let end = (at + 24).min(body.len());
+    String::from_utf8_lossy(&body[start..end]).chars().map(|c| if c.is_control() { '·' } else { c }).collect()
+}
+
+/// The report: what differs through belch, with differences the direct fetches share marked as noise
+fn compare(direct: &Fetched, proxied: &Fetched, again: &Fetched) -> Vec<(Kind, String)> {
+    let (direct, proxied, again) = match (direct, proxied, again) {
+        (Ok(d), Ok(p), Ok(a)) => (parse(d), parse(p), parse(a)),
+        (Err(d), Err(p), _) => return vec![(Kind::Info, format!("Direct fetch failed: {}", d)), (Kind::Info, format!("Through belch failed too: {}", p))],
+        (Ok(d), Err(p), _) => return vec![(Kind::Changed, format!("Direct: {}; through belch it failed: {}", parse(d).0, p))],
+        (Err(d), Ok(p), _) => return vec![(Kind::Info, format!("Direct fetch failed ({}), through belch: {}; belch may route this host through an upstream proxy", d, parse(p).0))],
+        (Ok(d), Ok(p), Err(_)) => (parse(d), parse(p), parse(d)),
+    };
+    let mut lines = Vec::new();
+    // Matching either direct fetch counts as the same
+    let kind = |same: bool, noise: bool| match (same, noise) {
+        (true, _) => Kind::Same,
+        (false, true) => Kind::Noise,
+        (false, false) => Kind::Changed,
+    };
+    lines.push(match kind(direct.0 == proxied.0 || again.0 == proxied.0, direct.0 != again.0) {
+        Kind::Same => (Kind::Same, format!("Status: {}", proxied.0)),
+        k => (k, format!("Status: {} direct, {} through belch", direct.0, proxied.0)),
+    });
+    let empty = Vec::new();
+    let names: Vec<&String> = {
+        let mut names: Vec<&String> = direct.1.keys().chain(proxied.1.keys()).collect();
+        names.sort();
+        names.dedup();
+        names
+    };
+    let mut same = 0;
+    for name in names {
+        let d = direct.1.get(name).unwrap_or(&empty);
+        let p = proxied.1.get(name).unwrap_or(&empty);
+        let a = again.1.get(name).unwrap_or(&empty);
+        match kind(d == p || a == p, d != a) {
+            Kind::Same => same += 1,
+            k if p.is_empty() => lines.push((k, format!("{}: removed (was {})", name, shown(d)))),
+            k if d.is_empty() => lines.push((k, format!("{}: added {}", name, shown(p)))),
+            k => lines.push((k, format!("{}: {} → {}", name, shown(d), shown(p)))),
+        }
+    }
+    lines.push((Kind::Same, format!("{} header(s) identical", same)));
+    let (d, p, a) = (&direct.2, &proxied.2, &again.2);
+    if d == p || a == p {
+        lines.push((Kind::Same, format!("Body identical ({} bytes decoded)", p.len())));
+    } else {
+        let at = d.iter().zip(p.iter()).position(|(x, y)| x != y).unwrap_or(d.len().min(p.len()));
+        let k = if d != a { Kind::Noise } else { Kind::Changed };
+        lines.push((k, format!("Body: {} bytes direct, {} through belch, first difference at byte {}", d.len(), p.len(), at)));
+        lines.push((Kind::Info, format!("  direct:  {}", around(d, at))));
+        lines.push((Kind::Info, format!("  belch:   {}", around(p, at))));
+    }
+    let changed = lines.iter().filter(|(k, _)| *k == Kind::Changed).count();
+    let noise = lines.iter().filter(|(k, _)| *k == Kind::Noise).count();
+    let verdict = match changed {
+        0 => "belch changed nothing that the server doesn't change by itself".to_string(),
+        n => format!("{} difference(s) only through belch", n),
+    };
+    let mut report = vec![(if changed == 0 { Kind::Same } else { Kind::Changed }, verdict)];
+    if noise > 0 {
+        report.push((Kind::Noise, format!("{} difference(s) that also vary between direct fetches (grey)", noise)));
+    }
+    report.push((Kind::Info, String::new()));
+    report.extend(lines);
+    report
+}
+
+/// `:diagnose [URL]` fetches URL, or the selected entry's URL, directly and through belch and
+/// shows what differs
+pub fn command(guard: &mut App, app: Arc<Mutex<App>>, ca_der: Vec<u8>, args: &[&str]) {
+    let url = match args {
+        [url] => url.to_string(),
+        [] => match guard.selected_log() {
+            Some(log) => log.full_url(),
+            None => return guard.set_status("usage: :diagnose [URL]"),
+        },
+        _ => return guard.set_status("usage: :diagnose [URL]"),
+    };
+    let Some((tls, host, port, path)) = spider::target(&url) else {
+        return guard.set_status(format!("diagnose: not an http(s) URL: {}", url));
+    };
+    guard.diagnosis = Some(Diagnosis { url: url.clone(), running: true, lines: Vec::new(), scroll: 0 });
+    let listen = guard.listen.clone();
+    tokio::spawn(async move {
+        let headers = format!("Host: {}\r\nUser-Agent: belch diagnose\r\nAccept: */*\r\nConnection: close\r\n", spider::host_header(&host, port));
+        let request = format!("GET {} HTTP/1.1\r\n{}\r\n", path, headers);
+        let config = spider::client_config(&ca_der);
+        let direct = || async {
+            let _permit = throttle::acquire(&host).await;
+            send::send(&host, port, tls, request.as_bytes(), None).await.map_err(|(_, e)| e)
+        };
+        let first = direct().await;
+        let proxied = {
+            let _permit = throttle::acquire(&host).await;
+            let headers = format!("{}{}: {}\r\n\r\n", headers, Origin::HEADER, Origin::Diagnose.mark());
+            spider::through_proxy(&url, &headers, &listen, &config).await.map_err(|(_, e)| e)
+        };
+        let again = direct().await;
+        let lines = compare(&first, &proxied, &again);
+        let mut guard = app.lock().unwrap();
+        let Some(diagnosis) = guard.diagnosis.as_mut().filter(|d| d.url == url && d.running) else { return };
+        diagnosis.lines = lines;
+        diagnosis.running = false;
+        let changes = diagnosis.changes();
+        info!("diagnose {}: {} difference(s) through belch", url, changes);
+    });
+}
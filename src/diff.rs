@@ -0,0 +1,71 @@
+// Two entries line by line, request and response, picked with "Diff with…" in the `s` menu
+
+use crate::app::HttpLog;
+
+/// Lines compared per side once the common start and end are set aside; past that the
+/// middle shows as all removed, then all added
+const MAX_LINES: usize = 1500;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Same,
+    /// Only in the first entry
+    Removed,
+    /// Only in the second entry
+    Added,
+}
+
+pub struct DiffView {
+    pub title: String,
+    pub lines: Vec<(Change, String)>,
+    pub scroll: usize,
+}
+
+impl DiffView {
+    pub fn changes(&self) -> usize {
+        self.lines.iter().filter(|(c, _)| *c != Change::Same).count()
+    }
+}
+
+/// Longest common subsequence of lines, as the edits that turn `a` into `b`
+pub fn lines(a: &str, b: &str) -> Vec<(Change, String)> {
+    let (a, b): (Vec<&str>, Vec<&str>) = (a.lines().collect(), b.lines().collect());
+    let start = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let end = a[start..].iter().rev().zip(b[start..].iter().rev()).take_while(|(x, y)| x == y).count();
+    let (middle_a, middle_b) = (&a[start..a.len() - end], &b[start..b.len() - end]);
+    let mut out: Vec<(Change, String)> = a[..start].iter().map(|l| (Change::Same, l.to_string())).collect();
+    if middle_a.len() > MAX_LINES || middle_b.len() > MAX_LINES {
+        out.extend(middle_a.iter().map(|l| (Change::Removed, l.to_string())));
+        out.extend(middle_b.iter().map(|l| (Change::Added, l.to_string())));
+    } else {
+        let (n, m) = (middle_a.len(), middle_b.len());
+        // longest[i][j]: common lines of middle_a[i..] and middle_b[j..]
+        let mut longest = vec![vec![0u16; m + 1]; n + 1];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                longest[i][j] = if middle_a[i] == middle_b[j] { longest[i + 1][j + 1] + 1 } else { longest[i + 1][j].max(longest[i][j + 1]) };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < n || j < m {
+            if i < n && j < m && middle_a[i] == middle_b[j] {
+                out.push((Change::Same, middle_a[i].to_string()));
+                (i, j) = (i + 1, j + 1);
+            } else if j == m || (i < n && longest[i + 1][j] >= longest[i][j + 1]) {
+                out.push((Change::Removed, middle_a[i].to_string()));
+                i += 1;
+            } else {
+                out.push((Change::Added, middle_b[j].to_string()));
+                j += 1;
+            }
+        }
+    }
+    out.extend(a[a.len() - end..].iter().map(|l| (Change::Same, l.to_string())));
+    out
+}
+
+/// Request and response of `a` against those of `b`
+pub fn entries(a: &HttpLog, b: &HttpLog) -> DiffView {
+    let text = |log: &HttpLog| format!("{}\n{}", log.request.text(), log.response_text());
+    DiffView { title: format!("{} → {}", a.url, b.url), lines: lines(&text(a), &text(b)), scroll: 0 }
+}
@@ -0,0 +1,158 @@
+// Name resolution for outbound connections: an async resolver with a TTL cache, asking the
+// system's nameservers unless --dns or :dns names others or a DNS-over-HTTPS server
+
+use std::io;
+use std::net::IpAddr;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use hickory_resolver::config::{LookupIpStrategy, NameServerConfigGroup, ResolverConfig};
+use hickory_resolver::name_server::TokioConnectionProvider;
+use hickory_resolver::TokioResolver;
+
+use crate::app::App;
+
+/// Answers kept until their TTL runs out
+const CACHE_SIZE: usize = 4096;
+
+/// `doh:NAME` shorthands: certificate name and addresses
+const DOH_PRESETS: [(&str, &str, &str); 3] = [
+    ("cloudflare", "cloudflare-dns.com", "1.1.1.1,1.0.0.1"),
+    ("google", "dns.google", "8.8.8.8,8.8.4.4"),
+    ("quad9", "dns.quad9.net", "9.9.9.9,149.112.112.112"),
+];
+
+#[derive(Clone, Default, PartialEq, Eq)]
+pub enum Servers {
+    /// From /etc/resolv.conf or the OS equivalent
+    #[default]
+    System,
+    /// Plain DNS on port 53
+    Plain(Vec<IpAddr>),
+    /// DNS over HTTPS on port 443, the certificate checked against `name`
+    Doh { name: String, ips: Vec<IpAddr> },
+}
+
+impl Servers {
+    /// `system`, `IP[,IP…]`, `doh:cloudflare|google|quad9` or `doh:NAME@IP[,IP…]`
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let ips = |list: &str| -> Result<Vec<IpAddr>, String> {
+            list.split(',').map(|ip| ip.trim().parse().map_err(|_| format!("`{}` is not an IP address", ip.trim()))).collect()
+        };
+        let Some(doh) = spec.strip_prefix("doh:") else {
+            return match spec {
+                "system" => Ok(Servers::System),
+                list => Ok(Servers::Plain(ips(list)?)),
+            };
+        };
+        let (name, list) = match (doh.split_once('@'), DOH_PRESETS.iter().find(|(preset, ..)| *preset == doh)) {
+            (Some((name, list)), _) => (name, list),
+            (None, Some((_, name, list))) => (*name, *list),
+            (None, None) => return Err(format!("`{}`: expected doh:cloudflare, doh:google, doh:quad9 or doh:NAME@IP", spec)),
+        };
+        Ok(Servers::Doh { name: name.to_string(), ips: ips(list)? })
+    }
+
+    pub fn label(&self) -> String {
+        let list = |ips: &[IpAddr]| ips.iter().map(IpAddr::to_string).collect::<Vec<_>>().join(", ");
+        match self {
+            Servers::System => "system resolver".to_string(),
+            Servers::Plain(ips) => list(ips),
+            Servers::Doh { name, ips } => format!("DoH {} ({})", name, list(ips)),
+        }
+    }
+
+    fn resolver(&self) -> Result<TokioResolver, String> {
+        let group = match self {
+            Servers::System => None,
+            Servers::Plain(ips) => Some(NameServerConfigGroup::from_ips_clear(ips, 53, true)),
+            Servers::Doh { name, ips } => Some(NameServerConfigGroup::from_ips_https(ips, 443, name.clone(), true)),
+        };
+        let mut builder = match group {
+            None => TokioResolver::builder_tokio().map_err(|e| format!("system DNS settings: {}", e))?,
+            Some(group) => TokioResolver::builder_with_config(ResolverConfig::from_parts(None, vec![], group), TokioConnectionProvider::default()),
+        };
+        builder.options_mut().cache_size = CACHE_SIZE;
+        // Both families, for dual-stack hosts to be raced
+        builder.options_mut().ip_strategy = LookupIpStrategy::Ipv4AndIpv6;
+        Ok(builder.build())
+    }
+}
+
+/// Where an entry's host resolved to
+#[derive(Clone, Copy)]
+pub struct Lookup {
+    /// Address the connection was made to
+    pub addr: IpAddr,
+    /// Zero for IP literals; near zero when answered from the cache
+    pub took: Duration,
+}
+
+impl Lookup {
+    pub fn label(&self) -> String {
+        let family = if self.addr.is_ipv6() { "IPv6" } else { "IPv4" };
+        format!("{} {} in {:.1} ms", family, self.addr, self.took.as_secs_f64() * 1000.0)
+    }
+}
+
+#[derive(Default)]
+struct State {
+    servers: Servers,
+    /// Built on first use and again after the servers change
+    resolver: Option<TokioResolver>,
+}
+
+static STATE: OnceLock<Mutex<State>> = OnceLock::new();
+
+fn state() -> &'static Mutex<State> {
+    STATE.get_or_init(Default::default)
+}
+
+pub fn set(servers: Servers) {
+    let mut state = state().lock().unwrap();
+    state.servers = servers;
+    state.resolver = None;
+}
+
+/// Addresses of `host`, both families, and how long finding them took; IP literals come back as they are
+pub async fn resolve(host: &str) -> io::Result<(Vec<IpAddr>, Duration)> {
+    let name = host.trim_start_matches('[').trim_end_matches(']');
+    if let Ok(ip) = name.parse() {
+        return Ok((vec![ip], Duration::ZERO));
+    }
+    let resolver = {
+        let mut state = state().lock().unwrap();
+        if state.resolver.is_none() {
+            state.resolver = Some(state.servers.resolver().map_err(io::Error::other)?);
+        }
+        state.resolver.clone().unwrap()
+    };
+    let started = Instant::now();
+    let found = resolver.lookup_ip(name).await.map_err(|e| io::Error::other(format!("DNS lookup of {} failed: {}", name, e)))?;
+    Ok((found.iter().collect(), started.elapsed()))
+}
+
+/// Note that a connection to `host` went to `lookup.addr`
+/// `:dns` shows the nameservers, `:dns system|IP[,IP…]|doh:…` changes them and `:dns flush`
+/// empties the cache
+pub fn command(app: &mut App, args: &[&str]) {
+    let mut state = state().lock().unwrap();
+    match args {
+        [] => app.set_status(format!("DNS: {} (:dns system | IP[,IP…] | doh:cloudflare|google|quad9|NAME@IP | flush)", state.servers.label())),
+        ["flush"] => {
+            if let Some(resolver) = &state.resolver {
+                resolver.clear_cache();
+            }
+            app.set_status("DNS cache flushed");
+        }
+        [spec] => match Servers::parse(spec) {
+            Ok(servers) => {
+                app.set_status(format!("DNS: {}", servers.label()));
+                state.servers = servers;
+                state.resolver = None;
+            }
+            Err(e) => app.set_status(e),
+        },
+        _ => app.set_status("usage: :dns [system | IP[,IP…] | doh:cloudflare|google|quad9|NAME@IP | flush]"),
+    }
+}
@@ -0,0 +1,162 @@
+// `belch docker`: the Docker CLI talks to a belch socket that forwards to the daemon's, and each
+// entry is tagged with its Engine API operation and shown with its JSON pretty-printed
+
+use std::env;
+use std::path::PathBuf;
+
+use crate::app::HttpLog;
+use crate::config;
+use crate::http;
+
+/// Where the daemon listens unless DOCKER_HOST or --docker-socket says otherwise
+const DEFAULT_SOCKET: &str = "/var/run/docker.sock";
+
+/// Engine API operation IDs by method and path after the version prefix. `*` is one path
+/// segment, `**` one or more (image names may hold slashes)
+const OPERATIONS: &[(&str, &str, &str)] = &[
+    ("GET", "_ping", "SystemPing"),
+    ("HEAD", "_ping", "SystemPingHead"),
+    ("GET", "version", "SystemVersion"),
+    ("GET", "info", "SystemInfo"),
+    ("GET", "events", "SystemEvents"),
+    ("GET", "system/df", "SystemDataUsage"),
+    ("POST", "auth", "SystemAuth"),
+    ("POST", "session", "Session"),
+    ("GET", "containers/json", "ContainerList"),
+    ("POST", "containers/create", "ContainerCreate"),
+    ("POST", "containers/prune", "ContainerPrune"),
+    ("GET", "containers/*/json", "ContainerInspect"),
+    ("GET", "containers/*/top", "ContainerTop"),
+    ("GET", "containers/*/logs", "ContainerLogs"),
+    ("GET", "containers/*/changes", "ContainerChanges"),
+    ("GET", "containers/*/export", "ContainerExport"),
+    ("GET", "containers/*/stats", "ContainerStats"),
+    ("POST", "containers/*/resize", "ContainerResize"),
+    ("POST", "containers/*/start", "ContainerStart"),
+    ("POST", "containers/*/stop", "ContainerStop"),
+    ("POST", "containers/*/restart", "ContainerRestart"),
+    ("POST", "containers/*/kill", "ContainerKill"),
+    ("POST", "containers/*/update", "ContainerUpdate"),
+    ("POST", "containers/*/rename", "ContainerRename"),
+    ("POST", "containers/*/pause", "ContainerPause"),
+    ("POST", "containers/*/unpause", "ContainerUnpause"),
+    ("POST", "containers/*/attach", "ContainerAttach"),
+    ("GET", "containers/*/attach/ws", "ContainerAttachWebsocket"),
+    ("POST", "containers/*/wait", "ContainerWait"),
+    ("HEAD", "containers/*/archive", "ContainerArchiveInfo"),
+    ("GET", "containers/*/archive", "ContainerArchive"),
+    ("PUT", "containers/*/archive", "PutContainerArchive"),
+    ("POST", "containers/*/exec", "ContainerExec"),
+    ("DELETE", "containers/*", "ContainerDelete"),
+    ("POST", "exec/*/start", "ExecStart"),
+    ("POST", "exec/*/resize", "ExecResize"),
+    ("GET", "exec/*/json", "ExecInspect"),
+    ("GET", "images/json", "ImageList"),
+    ("GET", "images/search", "ImageSearch"),
+    ("GET", "images/get", "ImageGetAll"),
+    ("POST", "images/create", "ImageCreate"),
+    ("POST", "images/load", "ImageLoad"),
+    ("POST", "images/prune", "ImagePrune"),
+    ("GET", "images/**/json", "ImageInspect"),
+    ("GET", "images/**/history", "ImageHistory"),
+    ("GET", "images/**/get", "ImageGet"),
+    ("POST", "images/**/push", "ImagePush"),
+    ("POST", "images/**/tag", "ImageTag"),
+    ("DELETE", "images/**", "ImageDelete"),
+    ("POST", "build", "ImageBuild"),
+    ("POST", "build/prune", "BuildPrune"),
+    ("POST", "commit", "ImageCommit"),
+    ("GET", "distribution/**/json", "DistributionInspect"),
+    ("GET", "networks", "NetworkList"),
+    ("POST", "networks/create", "NetworkCreate"),
+    ("POST", "networks/prune", "NetworkPrune"),
+    ("GET", "networks/*", "NetworkInspect"),
+    ("DELETE", "networks/*", "NetworkDelete"),
+    ("POST", "networks/*/connect", "NetworkConnect"),
+    ("POST", "networks/*/disconnect", "NetworkDisconnect"),
+    ("GET", "volumes", "VolumeList"),
+    ("POST", "volumes/create", "VolumeCreate"),
+    ("POST", "volumes/prune", "VolumePrune"),
+    ("GET", "volumes/*", "VolumeInspect"),
+    ("DELETE", "volumes/*", "VolumeDelete"),
+    ("GET", "plugins", "PluginList"),
+    ("GET", "swarm", "SwarmInspect"),
+    ("GET", "nodes", "NodeList"),
+    ("GET", "services", "ServiceList"),
+    ("GET", "tasks", "TaskList"),
+    ("GET", "secrets", "SecretList"),
+    ("GET", "configs", "ConfigList"),
+];
+
+/// Socket belch listens on for the Docker CLI
+pub fn listen_socket() -> PathBuf {
+    config::config_dir().join("docker.sock")
+}
+
+/// The daemon's socket: `--docker-socket`, else DOCKER_HOST when it is a Unix socket other than
+/// belch's own, else the default
+pub fn daemon_socket(flag: Option<String>) -> String {
+    let own = listen_socket().to_string_lossy().to_string();
+    flag.or_else(|| env::var("DOCKER_HOST").ok().and_then(|h| h.strip_prefix("unix://").map(str::to_string)))
+        .filter(|path| *path != own)
+        .unwrap_or_else(|| DEFAULT_SOCKET.to_string())
+}
+
+fn matches(pattern: &str, path: &[&str]) -> bool {
+    let pattern: Vec<&str> = pattern.split('/').collect();
+    match pattern.iter().position(|p| *p == "**") {
+        Some(i) => {
+            let (before, after) = (&pattern[..i], &pattern[i + 1..]);
+            path.len() > before.len() + after.len()
+                && before.iter().zip(path).all(|(p, s)| *p == "*" || p == s)
+                && after.iter().rev().zip(path.iter().rev()).all(|(p, s)| *p == "*" || p == s)
+        }
+        None => pattern.len() == path.len() && pattern.iter().zip(path).all(|(p, s)| *p == "*" || p == s),
+    }
+}
+
+/// Engine API operation ID of a request, e.g. `ContainerList` for `GET /v1.43/containers/json`
+pub fn operation(method: &str, target: &str) -> Option<&'static str> {
+    let path = http::origin_path(target).split('?').next().unwrap_or("");
+    let mut segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    if segments.first().is_some_and(|s| s.starts_with('v') && s[1..].contains('.') && s[1..].chars().all(|c| c.is_ascii_digit() || c == '.')) {
+        segments.remove(0);
+    }
+    OPERATIONS.iter().find(|(m, p, _)| method.eq_ignore_ascii_case(m) && matches(p, &segments)).map(|(_, _, op)| *op)
+}
+
+/// `body` pretty-printed when it is JSON or newline-delimited JSON, as pulls and event streams send
+fn pretty_json(body: &[u8]) -> Option<String> {
+    let text = std::str::from_utf8(body).ok()?;
+    if let Ok(value) = serde_json::from_str::<serde_json::Value>(text) {
+        return serde_json::to_string_pretty(&value).ok();
+    }
+    let values: Vec<serde_json::Value> = text.lines().filter(|l| !l.trim().is_empty()).map(serde_json::from_str).collect::<Result<_, _>>().ok()?;
+    (!values.is_empty()).then(|| values.iter().filter_map(|v| serde_json::to_string_pretty(v).ok()).collect::<Vec<_>>().join("\n"))
+}
+
+/// Message text with its decoded body in place of the raw one, pretty-printed when JSON
+fn pretty_message(text: &str, body: &[u8]) -> Option<String> {
+    let (head, _) = http::split_message(text);
+    http::header_value(head, "content-type").filter(|t| t.contains("json"))?;
+    Some(format!("{}\n\n{}", head, pretty_json(body)?))
+}
+
+/// Tag an entry from the Docker socket with its operation and pretty-print its JSON bodies
+pub fn label(entry: &mut HttpLog) {
+    let raw = entry.request_raw.bytes();
+    let Some(head) = http::RequestHead::parse(&raw) else { return };
+    if let Some(op) = operation(head.method, head.target) {
+        if !entry.tags.iter().any(|t| t == op) {
+            entry.tags.push(op.to_string());
+        }
+    }
+    let (raw_head, raw_body) = http::split_raw(&raw);
+    let body = http::decode_body(&String::from_utf8_lossy(raw_head), raw_body);
+    if let Some(text) = pretty_message(&entry.request.text(), &body) {
+        entry.request = text.into();
+    }
+    if let Some(text) = pretty_message(&entry.response_text(), &entry.response_body()) {
+        entry.response = text.into();
+    }
+}
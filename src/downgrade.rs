@@ -0,0 +1,126 @@
+// Unsafe plain-HTTP mode: for chosen hosts, `Location: https://` redirects are turned back into
+// http:// and Strict-Transport-Security is dropped, so legacy test setups stay reachable over plain
+// HTTP through the proxy. Once a host has redirected a plain request to HTTPS, belch fetches that
+// host over TLS itself while the client keeps talking plain HTTP to it.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use crate::app::App;
+use crate::config;
+use crate::http;
+
+#[derive(Default)]
+struct State {
+    /// Host patterns in unsafe mode
+    hosts: Vec<String>,
+    /// Plain `(host, port)` to the TLS port its redirect pointed at
+    upgraded: HashMap<(String, u16), u16>,
+}
+
+static STATE: OnceLock<Mutex<State>> = OnceLock::new();
+
+fn state() -> &'static Mutex<State> {
+    STATE.get_or_init(Default::default)
+}
+
+pub fn applies(host: &str) -> bool {
+    config::host_matches(&state().lock().unwrap().hosts, host)
+}
+
+/// TLS port to fetch plain requests for `host:port` from, once it redirected them to HTTPS
+pub fn upstream_tls(host: &str, port: u16) -> Option<u16> {
+    state().lock().unwrap().upgraded.get(&(host.to_lowercase(), port)).copied()
+}
+
+/// `https://host[:port]/…` as `http://…` when the host is in unsafe mode, noting where it pointed
+fn downgrade(location: &str) -> Option<String> {
+    let rest = location.get(..8).filter(|s| s.eq_ignore_ascii_case("https://")).map(|_| &location[8..])?;
+    let end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let (authority, path) = rest.split_at(end);
+    let (host, tls_port) = match http::split_authority(authority) {
+        (h, Some(p)) => (h, p.parse().ok()?),
+        (h, None) => (h, 443),
+    };
+    if !applies(host) {
+        return None;
+    }
+    let plain_port = if tls_port == 443 { 80 } else { tls_port };
+    state().lock().unwrap().upgraded.insert((host.to_lowercase(), plain_port), tls_port);
+    Some(match plain_port {
+        80 => format!("http://{}{}", http::url_host(host), path),
+        port => format!("http://{}:{}{}", http::url_host(host), port, path),
+    })
+}
+
+/// `response` without HSTS and with HTTPS redirects to unsafe-mode hosts made plain; true when
+/// anything changed
+pub fn response(response: Vec<u8>) -> (Vec<u8>, bool) {
+    let (head, body) = http::split_raw(&response);
+    let line_end = head.iter().position(|b| *b == b'\n').map_or(head.len(), |i| i + 1);
+    let mut out = head[..line_end].to_vec();
+    if !out.ends_with(b"\n") {
+        out.extend_from_slice(b"\r\n");
+    }
+    let mut changed = false;
+    for line in http::header_lines(head) {
+        let name = http::header_line_name(&line);
+        if name.eq_ignore_ascii_case(b"strict-transport-security") {
+            changed = true;
+            continue;
+        }
+        let text = String::from_utf8_lossy(&line);
+        let plain = name.eq_ignore_ascii_case(b"location").then(|| text.split_once(':').and_then(|(_, v)| downgrade(v.trim()))).flatten();
+        match plain {
+            Some(location) => {
+                out.extend_from_slice(format!("Location: {}", location).as_bytes());
+                changed = true;
+            }
+            None => out.extend_from_slice(&line),
+        }
+        out.extend_from_slice(b"\r\n");
+    }
+    if !changed {
+        return (response, false);
+    }
+    out.extend_from_slice(b"\r\n");
+    out.extend_from_slice(body);
+    (out, true)
+}
+
+/// Footer warning while any host is in unsafe mode
+pub fn indicator() -> Option<String> {
+    let count = state().lock().unwrap().hosts.len();
+    (count > 0).then(|| format!(" UNSAFE plain-http: {} host(s) ", count))
+}
+
+/// `:plain-http [HOST]` toggles unsafe mode for HOST or the selected entry's host,
+/// `:plain-http off` ends it for all and `:plain-http list` shows the hosts
+pub fn command(app: &mut App, args: &[&str]) {
+    let mut state = state().lock().unwrap();
+    let host = match args {
+        ["list"] if state.hosts.is_empty() => return app.set_status("No hosts in unsafe plain-HTTP mode"),
+        ["list"] => return app.set_status(format!("Unsafe plain-HTTP mode: {}", state.hosts.join(", "))),
+        ["off"] => {
+            state.hosts.clear();
+            state.upgraded.clear();
+            return app.set_status("Unsafe plain-HTTP mode off");
+        }
+        [host] => host.to_lowercase(),
+        [] => match app.selected_log().map(|l| l.host.to_lowercase()).filter(|h| !h.is_empty()) {
+            Some(host) => host,
+            None => return app.set_status("usage: :plain-http [HOST] | off | list"),
+        },
+        _ => return app.set_status("usage: :plain-http [HOST] | off | list"),
+    };
+    if let Some(i) = state.hosts.iter().position(|h| *h == host) {
+        state.hosts.remove(i);
+        state.upgraded.retain(|(h, _), _| *h != host);
+        return app.set_status(format!("{}: HTTPS redirects and HSTS pass through again", host));
+    }
+    if app.refuse("unsafe plain-HTTP mode") {
+        return;
+    }
+    app.set_status(format!("UNSAFE: {} is kept on plain HTTP (HTTPS redirects rewritten, HSTS dropped)", host));
+    state.hosts.push(host);
+}
@@ -0,0 +1,265 @@
+// Multi-line text editing for popups that hold a whole request
+
+/// Undo steps kept per editor
+const UNDO_DEPTH: usize = 200;
+
+/// Which buffer is handed to `$EDITOR` once the key handler returns
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum External {
+    Repeater,
+    /// The held request with this ID, head and body
+    Intercept(u64),
+    /// The body of the held request with this ID
+    InterceptBody(u64),
+    /// A captured entry's decoded request or response body, to read; edits are not kept
+    Entry { index: usize, response: bool },
+}
+
+/// Kinds of edit that run together into one undo step
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Typing,
+    Erasing,
+}
+
+struct Snapshot {
+    lines: Vec<String>,
+    row: usize,
+    col: usize,
+}
+
+#[derive(Default)]
+pub struct TextArea {
+    /// Never empty
+    pub lines: Vec<String>,
+    pub row: usize,
+    /// In characters, not bytes
+    pub col: usize,
+    /// First line shown
+    pub scroll: usize,
+    /// Find query being typed
+    pub search: Option<String>,
+    /// Last query searched for, which Ctrl+N looks for again
+    pub query: String,
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+    /// The last edit and where it left the cursor; more of the same there joins its undo step
+    last: Option<(Step, usize, usize)>,
+}
+
+impl TextArea {
+    /// `text` split at line breaks, CRLF or bare LF
+    pub fn new(text: &str) -> Self {
+        TextArea { lines: split(text), ..Default::default() }
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot { lines: self.lines.clone(), row: self.row, col: self.col }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        (self.lines, self.row, self.col) = (snapshot.lines, snapshot.row, snapshot.col);
+        self.last = None;
+    }
+
+    /// Record the state before an edit, unless it continues the previous one
+    fn save(&mut self, step: Option<Step>) {
+        let continues = step.is_some_and(|s| self.last == Some((s, self.row, self.col)));
+        if !continues {
+            self.undo.push(self.snapshot());
+            if self.undo.len() > UNDO_DEPTH {
+                self.undo.remove(0);
+            }
+        }
+        self.redo.clear();
+    }
+
+    fn after(&mut self, step: Option<Step>) {
+        self.last = step.map(|s| (s, self.row, self.col));
+    }
+
+    pub fn undo(&mut self) -> bool {
+        let Some(snapshot) = self.undo.pop() else { return false };
+        self.redo.push(self.snapshot());
+        self.restore(snapshot);
+        true
+    }
+
+    pub fn redo(&mut self) -> bool {
+        let Some(snapshot) = self.redo.pop() else { return false };
+        self.undo.push(self.snapshot());
+        self.restore(snapshot);
+        true
+    }
+
+    /// Replace the whole text as one undoable edit, keeping the cursor where it still fits
+    pub fn set_text(&mut self, text: &str) {
+        self.save(None);
+        self.lines = split(text);
+        self.row = self.row.min(self.lines.len() - 1);
+        self.col = self.col.min(self.line_len());
+        self.after(None);
+    }
+
+    /// Move to the next match of `query` after the cursor, wrapping around; false when there is none
+    pub fn find_next(&mut self) -> bool {
+        if self.query.is_empty() {
+            return false;
+        }
+        let query = self.query.to_ascii_lowercase();
+        let count = self.lines.len();
+        // The cursor's line comes round again last, for matches before the cursor
+        for i in 0..=count {
+            let row = (self.row + i) % count;
+            let line = self.lines[row].to_ascii_lowercase();
+            let from = if i == 0 { line.char_indices().nth(self.col + 1).map_or(line.len(), |(b, _)| b) } else { 0 };
+            if let Some(at) = line[from..].find(&query) {
+                self.row = row;
+                self.col = line[..from + at].chars().count();
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Lines joined with `sep`
+    pub fn text(&self, sep: &str) -> String {
+        self.lines.join(sep)
+    }
+
+    fn byte_at(&self, col: usize) -> usize {
+        let line = &self.lines[self.row];
+        line.char_indices().nth(col).map_or(line.len(), |(i, _)| i)
+    }
+
+    fn line_len(&self) -> usize {
+        self.lines[self.row].chars().count()
+    }
+
+    fn put(&mut self, c: char) {
+        let at = self.byte_at(self.col);
+        self.lines[self.row].insert(at, c);
+        self.col += 1;
+    }
+
+    fn split_line(&mut self) {
+        let at = self.byte_at(self.col);
+        let rest = self.lines[self.row].split_off(at);
+        self.row += 1;
+        self.lines.insert(self.row, rest);
+        self.col = 0;
+    }
+
+    /// A word typed in one go undoes together
+    pub fn insert(&mut self, c: char) {
+        let step = (!c.is_whitespace()).then_some(Step::Typing);
+        self.save(step);
+        self.put(c);
+        self.after(step);
+    }
+
+    /// Pasted text, line breaks included
+    pub fn insert_str(&mut self, text: &str) {
+        self.save(None);
+        for (i, part) in text.split('\n').enumerate() {
+            if i > 0 {
+                self.split_line();
+            }
+            part.strip_suffix('\r').unwrap_or(part).chars().for_each(|c| self.put(c));
+        }
+        self.after(None);
+    }
+
+    pub fn newline(&mut self) {
+        self.save(None);
+        self.split_line();
+        self.after(None);
+    }
+
+    pub fn backspace(&mut self) {
+        if self.col == 0 && self.row == 0 {
+            return;
+        }
+        self.save(Some(Step::Erasing));
+        if self.col > 0 {
+            self.col -= 1;
+            let at = self.byte_at(self.col);
+            self.lines[self.row].remove(at);
+        } else if self.row > 0 {
+            let line = self.lines.remove(self.row);
+            self.row -= 1;
+            self.col = self.line_len();
+            self.lines[self.row].push_str(&line);
+        }
+        self.after(Some(Step::Erasing));
+    }
+
+    pub fn delete(&mut self) {
+        if self.col == self.line_len() && self.row + 1 == self.lines.len() {
+            return;
+        }
+        self.save(Some(Step::Erasing));
+        if self.col < self.line_len() {
+            let at = self.byte_at(self.col);
+            self.lines[self.row].remove(at);
+        } else if self.row + 1 < self.lines.len() {
+            let next = self.lines.remove(self.row + 1);
+            self.lines[self.row].push_str(&next);
+        }
+        self.after(Some(Step::Erasing));
+    }
+
+    pub fn left(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+        } else if self.row > 0 {
+            self.row -= 1;
+            self.col = self.line_len();
+        }
+    }
+
+    pub fn right(&mut self) {
+        if self.col < self.line_len() {
+            self.col += 1;
+        } else if self.row + 1 < self.lines.len() {
+            self.row += 1;
+            self.col = 0;
+        }
+    }
+
+    /// Move `by` lines, keeping the column where the line is long enough
+    pub fn vertical(&mut self, by: isize) {
+        self.row = self.row.saturating_add_signed(by).min(self.lines.len() - 1);
+        self.col = self.col.min(self.line_len());
+    }
+
+    pub fn home(&mut self) {
+        self.col = 0;
+    }
+
+    pub fn end(&mut self) {
+        self.col = self.line_len();
+    }
+
+    pub fn top(&mut self) {
+        (self.row, self.col) = (0, 0);
+    }
+
+    pub fn bottom(&mut self) {
+        self.row = self.lines.len() - 1;
+        self.col = self.line_len();
+    }
+
+    /// Scroll so the cursor is within `height` lines
+    pub fn follow(&mut self, height: usize) {
+        if self.row < self.scroll {
+            self.scroll = self.row;
+        } else if height > 0 && self.row >= self.scroll + height {
+            self.scroll = self.row + 1 - height;
+        }
+    }
+}
+
+fn split(text: &str) -> Vec<String> {
+    text.split('\n').map(|l| l.strip_suffix('\r').unwrap_or(l).to_string()).collect()
+}
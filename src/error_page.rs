@@ -0,0 +1,61 @@
+// HTML error responses sent to the client when the proxy, not the site, fails
+
+use std::io;
+
+#[derive(Clone, Copy)]
+pub enum Failure {
+    Dns,
+    Refused,
+    Timeout,
+    Tls,
+    Other,
+}
+
+impl Failure {
+    pub fn from_io(e: &io::Error) -> Self {
+        let msg = e.to_string().to_lowercase();
+        match e.kind() {
+            io::ErrorKind::ConnectionRefused => Failure::Refused,
+            io::ErrorKind::TimedOut => Failure::Timeout,
+            _ if msg.contains("lookup") || msg.contains("not known") || msg.contains("nodename") => Failure::Dns,
+            _ => Failure::Other,
+        }
+    }
+
+    fn describe(self) -> (&'static str, &'static str) {
+        match self {
+            Failure::Dns => ("DNS resolution failed", "Check the hostname and belch's DNS settings (:dns)."),
+            Failure::Refused => ("Connection refused", "The host is reachable but nothing is listening on that port."),
+            Failure::Timeout => ("Connection timed out", "The host did not answer; it may be down or firewalled from the proxy's network."),
+            Failure::Tls => ("Upstream TLS handshake failed", "The server's certificate or TLS settings were rejected by belch. Add the host to the passthrough list to tunnel it untouched."),
+            Failure::Other => ("Upstream connection failed", "See the Events tab in belch for details."),
+        }
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Complete `502 Bad Gateway` response describing why `host` could not be reached
+pub fn render(host: &str, failure: Failure, detail: &str) -> Vec<u8> {
+    let (title, suggestion) = failure.describe();
+    let body = format!(
+        "<!doctype html><html><head><title>belch: {title}</title></head>\
+         <body style=\"font-family:sans-serif;max-width:40em;margin:3em auto\">\
+         <h1>{title}</h1>\
+         <p>The <b>belch proxy</b> could not reach <code>{host}</code>. This page comes from the proxy, not from the site.</p>\
+         <p><b>Error:</b> <code>{detail}</code></p>\
+         <p>{suggestion}</p></body></html>",
+        title = title,
+        host = escape(host),
+        detail = escape(detail),
+        suggestion = suggestion,
+    );
+    let mut resp = format!(
+        "HTTP/1.1 502 Bad Gateway\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\nX-Belch-Error: {}\r\n\r\n",
+        body.len(), title
+    ).into_bytes();
+    resp.extend_from_slice(body.as_bytes());
+    resp
+}
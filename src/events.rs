@@ -0,0 +1,82 @@
+// Internal proxy diagnostics: tracing events fanned out to the Events tab and an optional log file
+
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt::Write as _;
+use std::fs::OpenOptions;
+use std::sync::{Arc, Mutex};
+
+use time::OffsetDateTime;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+
+const MAX_EVENTS: usize = 1000;
+
+pub struct ProxyEvent {
+    pub time: String,
+    pub level: Level,
+    pub message: String,
+}
+
+pub type EventLog = Arc<Mutex<VecDeque<ProxyEvent>>>;
+
+/// Collects the `message` field plus any extra fields as `key=value`
+#[derive(Default)]
+struct MessageVisitor(String);
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.0, "{:?}", value);
+        } else {
+            let _ = write!(self.0, " {}={:?}", field.name(), value);
+        }
+    }
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.0.push_str(value);
+        } else {
+            let _ = write!(self.0, " {}={}", field.name(), value);
+        }
+    }
+}
+
+struct EventLayer(EventLog);
+
+impl<S: Subscriber> Layer<S> for EventLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        let t = OffsetDateTime::now_utc();
+        let mut events = self.0.lock().unwrap();
+        if events.len() == MAX_EVENTS {
+            events.pop_front();
+        }
+        events.push_back(ProxyEvent {
+            time: format!("{:02}:{:02}:{:02}", t.hour(), t.minute(), t.second()),
+            level: *event.metadata().level(),
+            message: visitor.0,
+        });
+    }
+}
+
+/// Install the global subscriber; events at `level` and above go to the returned buffer and `log_file`
+pub fn init(log_file: Option<&str>, level: LevelFilter) -> Result<EventLog, Box<dyn Error>> {
+    let events = EventLog::default();
+    let file_layer = match log_file {
+        Some(path) => {
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            Some(tracing_subscriber::fmt::layer().with_writer(Mutex::new(file)).with_ansi(false))
+        }
+        None => None,
+    };
+    tracing_subscriber::registry()
+        .with(level)
+        .with(EventLayer(events.clone()))
+        .with(file_layer)
+        .try_init()?;
+    Ok(events)
+}
@@ -0,0 +1,212 @@
+// One request's way through the proxy, the same for plain HTTP and intercepted TLS: the request
+// stages, sending it, the response stages, tagging and logging the entry, answering the client
+
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use bytes::Bytes;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tracing::warn;
+
+use crate::app::{self, App, HttpLog, Origin};
+use crate::faults::{self, Injected, Outcome, Stage};
+use crate::upstream::Upstream;
+use crate::{cache_bust, cookies, cors, downgrade, error_page, http, intercept, mirror, normalize, otel, plugins, rewrite, send, sse};
+
+pub struct Exchange {
+    /// What the entry knows before any message: client, connection, URL, host, port, TLS, SNI
+    entry: HttpLog,
+    /// The request as the client sent it
+    request: Vec<u8>,
+    method: String,
+    read_only: bool,
+    cors_dev: bool,
+    via: Option<Upstream>,
+    no_cache: bool,
+    pinned: bool,
+    rewrote_request: bool,
+    plain_http: bool,
+}
+
+/// Where a request stands once its request stages ran
+pub enum Prepared {
+    /// Send this upstream with `Exchange::complete`
+    Forward(Vec<u8>),
+    /// An event stream to hand to `sse::relay`, with `Exchange::stream_entry`
+    Stream(Vec<u8>),
+    /// Answered or dropped; the connection is done
+    Done,
+}
+
+impl Exchange {
+    pub fn new(app: &Arc<Mutex<App>>, entry: HttpLog, request: Vec<u8>, method: &str) -> Self {
+        let guard = app.lock().unwrap();
+        let read_only = guard.read_only_for(&entry.host);
+        Exchange {
+            via: guard.routes.route(&entry.host).cloned(),
+            cors_dev: guard.cors_dev.matches(&entry.host) && !read_only,
+            read_only,
+            entry,
+            request,
+            method: method.to_string(),
+            no_cache: false,
+            pinned: false,
+            rewrote_request: false,
+            plain_http: false,
+        }
+    }
+
+    /// Answer a `:cors-dev` preflight, or run `forward` (the request as it would go upstream)
+    /// through normalizing, plugins, cache busting, cookies, rewrites, the intercept hold and
+    /// trace propagation. Read-only mode skips the stages that change it.
+    pub async fn prepare<W: AsyncWrite + Unpin>(&mut self, app: &Arc<Mutex<App>>, forward: Vec<u8>, client: &mut W) -> Prepared {
+        let (host, conn) = (self.entry.host.clone(), self.entry.conn);
+        if let Some(answer) = self.cors_dev.then(|| cors::dev_preflight(&self.request)).flatten() {
+            let _ = client.write_all(&answer).await;
+            let _ = client.shutdown().await;
+            app::capture(app, HttpLog {
+                request: String::from_utf8_lossy(&self.request).replace("\r\n", "\n").into(),
+                request_raw: std::mem::take(&mut self.request).into(),
+                response_raw: answer.into(),
+                tags: vec!["cors-dev".into()],
+                ..self.entry.clone()
+            });
+            return Prepared::Done;
+        }
+        let forward = normalize::forward(http::remove_header(&forward, Origin::HEADER), &host);
+        let forward = if self.read_only { forward } else { plugins::process_request(forward) };
+        self.no_cache = cache_bust::applies(app, conn, &host);
+        let forward = if self.no_cache { cache_bust::request(forward) } else { forward };
+        let (forward, pinned) = cookies::apply(app, &host, forward);
+        let (forward, rewrote_request) = rewrite::request(app, &host, forward);
+        (self.pinned, self.rewrote_request) = (pinned, rewrote_request);
+        self.plain_http = !self.read_only && downgrade::applies(&host);
+        let Some(forward) = intercept::hold(app, conn, &host, forward).await else { return Prepared::Done };
+        let forward = otel::propagate(app, &host, forward);
+        match sse::wants(&forward) {
+            true => Prepared::Stream(forward),
+            false => Prepared::Forward(forward),
+        }
+    }
+
+    /// The entry an event stream starts from
+    pub fn stream_entry(self) -> HttpLog {
+        HttpLog { request_raw: self.request.into(), ..self.entry }
+    }
+
+    /// Send `forward` upstream (or fail it as the fault rules say), run the response through the
+    /// `:cors-dev`, cache busting, plain-HTTP, rewrite and plugin stages, log the entry and
+    /// answer the client. True when the client's connection stays open for another request.
+    pub async fn complete<W: AsyncWrite + Unpin>(self, app: &Arc<Mutex<App>>, forward: Vec<u8>, client: &mut W) -> bool {
+        let (host, port, tls) = (self.entry.host.clone(), self.entry.port, self.entry.tls);
+        let failed = format!("{} ✗", self.entry.url);
+        // Read-only traffic passes unchanged, faults included
+        let injected = if self.read_only { Injected::none() } else { faults::inject(&host, Stage::Request).await };
+        if injected.outcome == Outcome::Drop {
+            app::capture(app, HttpLog {
+                url: failed,
+                request: String::from_utf8_lossy(&forward).replace("\r\n", "\n").into(),
+                response: "[Dropped by fault injection]".to_string().into(),
+                request_raw: self.request.into(),
+                tags: injected.tags(),
+                ..self.entry
+            });
+            return false;
+        }
+        let started = Instant::now();
+        let sent = match injected.outcome {
+            // Nothing was looked up for a faked error
+            Outcome::Error => Ok((faults::ERROR_RESPONSE.to_vec(), None)),
+            // A host kept on plain HTTP that redirected to HTTPS is fetched over TLS
+            _ => match downgrade::upstream_tls(&host, port).filter(|_| self.plain_http && !tls) {
+                Some(tls_port) => send::send_resolved(&host, tls_port, true, &forward, self.via.as_ref()).await,
+                None => send::send_resolved(&host, port, tls, &forward, self.via.as_ref()).await,
+            },
+        };
+        let (resp_buf, resolved) = match sent {
+            Ok((resp, resolved)) => (Bytes::from(resp), resolved),
+            Err((failure, reason)) => {
+                warn!("{}:{}: {}", host, port, reason);
+                let page = error_page::render(&host, failure, &reason);
+                let _ = client.write_all(&page).await;
+                let _ = client.shutdown().await;
+                app::capture(app, HttpLog {
+                    url: failed,
+                    request: String::from_utf8_lossy(&forward).replace("\r\n", "\n").into(),
+                    response: format!("[{}]", reason).into(),
+                    request_raw: self.request.into(),
+                    response_raw: page.into(),
+                    duration: started.elapsed(),
+                    ..self.entry
+                });
+                return false;
+            }
+        };
+        let answered = if self.read_only { Injected::none() } else { faults::inject(&host, Stage::Response).await };
+        // The entry keeps the response as the server sent it
+        let to_client = match (answered.outcome, self.cors_dev) {
+            (Outcome::Error, _) => Bytes::from_static(faults::ERROR_RESPONSE),
+            (_, true) => Bytes::from(cors::dev_unblock(&self.request, &resp_buf)),
+            (_, false) => resp_buf.clone(),
+        };
+        let to_client = if self.no_cache { Bytes::from(cache_bust::response(to_client.to_vec())) } else { to_client };
+        let (to_client, downgraded) = match self.plain_http {
+            true => {
+                let (response, changed) = downgrade::response(to_client.to_vec());
+                (Bytes::from(response), changed)
+            }
+            false => (to_client, false),
+        };
+        let rewritten = rewrite::response(app, &host, &to_client);
+        let rewrote_response = rewritten.is_some();
+        let to_client = rewritten.map_or(to_client, Bytes::from);
+        let to_client = if self.read_only { to_client } else { plugins::process_response(to_client) };
+        let to_client = answered.mutate(to_client);
+        let tags = self.tags(downgraded, rewrote_response, &injected, &answered);
+        // The upstream was asked to close; the client's connection stays open if the response says where it ends
+        let kept = http::client_keeps_open(&self.request).then(|| http::keep_open(&to_client, &self.method)).flatten();
+        let (conn, request) = (self.entry.conn, self.request);
+        let primary = app::capture(app, HttpLog {
+            request: String::from_utf8_lossy(&forward).replace("\r\n", "\n").into(),
+            request_raw: request.into(),
+            response_raw: resp_buf.into(),
+            duration: started.elapsed(),
+            tags,
+            resolved,
+            ..self.entry
+        });
+        if mirror::applies(app, conn, &host) {
+            mirror::spawn(app.clone(), primary, &forward);
+        }
+        if answered.outcome == Outcome::Drop {
+            return false;
+        }
+        match kept {
+            Some(response) => answered.write(client, &response).await.is_ok(),
+            None => {
+                let _ = answered.write(client, &to_client).await;
+                let _ = client.shutdown().await;
+                false
+            }
+        }
+    }
+
+    /// Tags for what the stages did to the exchange
+    fn tags(&self, downgraded: bool, rewrote_response: bool, injected: &Injected, answered: &Injected) -> Vec<String> {
+        let mut tags: Vec<String> = if self.cors_dev { vec!["cors-dev".into()] } else { Vec::new() };
+        if self.no_cache {
+            tags.push("no-cache".into());
+        }
+        if self.pinned {
+            tags.push("cookie-override".into());
+        }
+        if downgraded {
+            tags.push("plain-http".into());
+        }
+        if self.rewrote_request || rewrote_response {
+            tags.push("rewrite".into());
+        }
+        tags.extend(injected.tags().into_iter().chain(answered.tags()));
+        tags
+    }
+}
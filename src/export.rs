@@ -0,0 +1,405 @@
+// Writing captured data to disk
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use base64::Engine;
+use serde_json::{json, Value};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::app::{App, HttpLog};
+use crate::http;
+use crate::markers;
+use crate::recon::Found;
+use crate::redact;
+use crate::regression;
+
+/// Path and query of a request target, without the scheme and host of an absolute one
+fn origin_form(target: &str) -> &str {
+    target.split_once("://").map_or(target, |(_, rest)| rest.find('/').map_or("/", |i| &rest[i..]))
+}
+
+/// Last path segment of the request target, without query or extension
+fn file_stem(log: &HttpLog) -> String {
+    let request = log.request.text();
+    let target = request.split_whitespace().nth(1).unwrap_or("/");
+    let path = origin_form(target).split(['?', '#']).next().unwrap_or("");
+    let last = path.rsplit('/').next().unwrap_or("");
+    let stem = last.rsplit_once('.').map_or(last, |(s, _)| s);
+    let stem: String = stem.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    if stem.is_empty() { log.host.replace(['.', ':'], "_") } else { stem }
+}
+
+/// Write the decoded response body into `dir`, picking the extension from Content-Type
+/// (falling back to the URL's) and never overwriting an existing file
+pub fn save_body(log: &HttpLog, dir: &Path) -> io::Result<PathBuf> {
+    if log.response_raw.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "entry has no response body"));
+    }
+    let head = log.response_head();
+    let request = log.request.text();
+    let target = request.split_whitespace().nth(1).unwrap_or("");
+    let url_ext = target.split(['?', '#']).next().unwrap_or("")
+        .rsplit('/').next()
+        .and_then(|s| s.rsplit_once('.'))
+        .map(|(_, e)| e.to_string());
+    let ext = http::header_value(&head, "content-type")
+        .and_then(http::extension_for)
+        .map(str::to_string)
+        .or(url_ext)
+        .unwrap_or_else(|| "bin".to_string());
+    let stem = file_stem(log);
+    let mut path = dir.join(format!("{}.{}", stem, ext));
+    let mut n = 1;
+    while path.exists() {
+        path = dir.join(format!("{}-{}.{}", stem, n, ext));
+        n += 1;
+    }
+    fs::write(&path, log.response_body())?;
+    Ok(path)
+}
+
+fn header_pairs(head: &str) -> Vec<Value> {
+    head.lines().skip(1)
+        .filter_map(|l| l.split_once(':'))
+        .map(|(k, v)| json!({ "name": k.trim(), "value": v.trim() }))
+        .collect()
+}
+
+fn timestamp(log: &HttpLog) -> String {
+    log.started.and_then(|t| t.format(&Rfc3339).ok()).unwrap_or_default()
+}
+
+/// Body as HAR content text, base64-encoded when it is not UTF-8
+fn content(body: Vec<u8>) -> (String, Option<&'static str>) {
+    match String::from_utf8(body) {
+        Ok(text) => (text, None),
+        Err(e) => (base64::engine::general_purpose::STANDARD.encode(e.as_bytes()), Some("base64")),
+    }
+}
+
+fn har_entry(log: &HttpLog) -> Value {
+    let raw = log.request_raw.bytes();
+    let (req_head, req_body) = http::split_raw(&raw);
+    let req_head = String::from_utf8_lossy(req_head);
+    let url = log.full_url();
+    let query = url.split_once('?').map_or(Vec::new(), |(_, q)| http::parse_query(q));
+    let mut request = json!({
+        "method": log.method(),
+        "url": url,
+        "httpVersion": "HTTP/1.1",
+        "headers": header_pairs(&req_head),
+        "queryString": query.iter().map(|(k, v)| json!({ "name": k, "value": v })).collect::<Vec<_>>(),
+        "cookies": [],
+        "headersSize": -1,
+        "bodySize": req_body.len(),
+    });
+    if !req_body.is_empty() {
+        request["postData"] = json!({
+            "mimeType": http::header_value(&req_head, "content-type").unwrap_or(""),
+            "text": String::from_utf8_lossy(req_body),
+        });
+    }
+    let resp_head = log.response_head();
+    let status = log.status().unwrap_or(0);
+    let (text, encoding) = content(log.response_body());
+    let mut body = json!({
+        "size": log.response_size,
+        "mimeType": http::header_value(&resp_head, "content-type").unwrap_or(""),
+        "text": text,
+    });
+    if let Some(encoding) = encoding {
+        body["encoding"] = json!(encoding);
+    }
+    let ms = log.duration.as_secs_f64() * 1000.0;
+    json!({
+        "startedDateTime": timestamp(log),
+        "time": ms,
+        "request": request,
+        "response": {
+            "status": status,
+            "statusText": http::reason_phrase(status),
+            "httpVersion": "HTTP/1.1",
+            "headers": header_pairs(&resp_head),
+            "cookies": [],
+            "content": body,
+            "redirectURL": http::header_value(&resp_head, "location").unwrap_or(""),
+            "headersSize": -1,
+            "bodySize": http::split_raw(&log.response_raw.bytes()).1.len(),
+        },
+        "cache": {},
+        "timings": { "send": 0, "wait": ms, "receive": 0 },
+    })
+}
+
+/// HAR 1.2 document; tunnels and other entries without a request are skipped
+pub fn har<'a>(logs: impl Iterator<Item = &'a HttpLog>) -> Value {
+    let entries: Vec<Value> = logs.filter(|l| !l.request_raw.is_empty()).map(har_entry).collect();
+    json!({
+        "log": {
+            "version": "1.2",
+            "creator": { "name": "belch", "version": env!("CARGO_PKG_VERSION") },
+            "entries": entries,
+        }
+    })
+}
+
+/// An exchange as one JSON object, the binary response body base64-encoded
+pub fn exchange(log: &HttpLog) -> Value {
+    json!({
+        "time": timestamp(log),
+        "method": log.method(),
+        "url": log.full_url(),
+        "status": log.status(),
+        "duration_ms": log.duration.as_millis() as u64,
+        "request": String::from_utf8_lossy(&log.request_raw.bytes()),
+        "response_head": log.response_head(),
+        "response_body": content(log.response_body()).0,
+        "response_sha256": log.response_sha256,
+        "listener": log.listener,
+        "correlation": log.correlation.iter().map(|(h, id)| (h.clone(), json!(id))).collect::<serde_json::Map<_, _>>(),
+    })
+}
+
+/// One JSON object per captured exchange
+pub fn jsonl<'a>(logs: impl Iterator<Item = &'a HttpLog>) -> String {
+    logs.filter(|l| !l.request_raw.is_empty())
+        .map(|log| exchange(log).to_string() + "\n")
+        .collect()
+}
+
+/// Burp Suite's "Save items" XML, requests and responses base64-encoded
+pub fn burp_xml<'a>(logs: impl Iterator<Item = &'a HttpLog>) -> String {
+    let b64 = |data: &[u8]| base64::engine::general_purpose::STANDARD.encode(data);
+    let mut out = format!("<?xml version=\"1.0\"?>\n<items burpVersion=\"belch {}\" exportTime=\"{}\">\n", env!("CARGO_PKG_VERSION"), now());
+    for log in logs.filter(|l| !l.request_raw.is_empty()) {
+        let raw = log.request_raw.bytes();
+        let url = log.full_url();
+        let path = http::RequestHead::parse(&raw).map_or("/".to_string(), |h| origin_form(h.target).to_string());
+        let extension = path.split(['?', '#']).next().unwrap_or("").rsplit('/').next()
+            .and_then(|s| s.rsplit_once('.')).map_or("null".to_string(), |(_, e)| e.to_string());
+        let head = log.response_head();
+        let port = match log.port {
+            0 => if log.tls { 443 } else { 80 },
+            port => port,
+        };
+        out.push_str("  <item>\n");
+        out.push_str(&format!("    <time>{}</time>\n", timestamp(log)));
+        out.push_str(&format!("    <url>{}</url>\n", cdata(&url)));
+        out.push_str(&format!("    <host ip=\"\">{}</host>\n", xml_escape(&log.host)));
+        out.push_str(&format!("    <port>{}</port>\n", port));
+        out.push_str(&format!("    <protocol>{}</protocol>\n", if log.tls { "https" } else { "http" }));
+        out.push_str(&format!("    <method>{}</method>\n", cdata(&log.method())));
+        out.push_str(&format!("    <path>{}</path>\n", cdata(&path)));
+        out.push_str(&format!("    <extension>{}</extension>\n", xml_escape(&extension)));
+        out.push_str(&format!("    <request base64=\"true\">{}</request>\n", cdata(&b64(&raw))));
+        out.push_str(&format!("    <status>{}</status>\n", log.status().unwrap_or(0)));
+        out.push_str(&format!("    <responselength>{}</responselength>\n", log.response_raw.len()));
+        out.push_str(&format!("    <mimetype>{}</mimetype>\n", xml_escape(http::header_value(&head, "content-type").unwrap_or(""))));
+        out.push_str(&format!("    <response base64=\"true\">{}</response>\n", cdata(&b64(&log.response_raw.bytes()))));
+        out.push_str(&format!("    <comment>{}</comment>\n", xml_escape(&log.tags.join(", "))));
+        out.push_str("  </item>\n");
+    }
+    out.push_str("</items>\n");
+    out
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// A CDATA section; a `]]>` inside is split across two
+fn cdata(text: &str) -> String {
+    format!("<![CDATA[{}]]>", text.replace("]]>", "]]]]><![CDATA[>"))
+}
+
+/// Response bodies longer than this are cut in Markdown reports
+const MARKDOWN_BODY: usize = 4000;
+
+/// A report appendix: one section per exchange with the request and response in fenced blocks
+pub fn markdown<'a>(logs: impl Iterator<Item = &'a HttpLog>) -> String {
+    let logs: Vec<&HttpLog> = logs.filter(|l| !l.request_raw.is_empty()).collect();
+    let mut out = format!("# Traffic evidence\n\n{} exchange(s), exported {} by belch.\n", logs.len(), now());
+    for (n, log) in logs.iter().enumerate() {
+        let status = log.status().map_or("no response".to_string(), |s| format!("{} {}", s, http::reason_phrase(s)));
+        out.push_str(&format!("\n## {}. {} {} → {}\n\n", n + 1, log.method(), log.full_url(), status));
+        if !timestamp(log).is_empty() {
+            out.push_str(&format!("- Time: {}\n", timestamp(log)));
+        }
+        if !log.tags.is_empty() {
+            out.push_str(&format!("- Tags: {}\n", log.tags.join(", ")));
+        }
+        out.push_str(&format!("- Duration: {} ms\n\n", log.duration.as_millis()));
+        out.push_str(&fenced(&log.request.text()));
+        let mut response = log.response_text();
+        if let Some((cut, _)) = response.char_indices().nth(MARKDOWN_BODY) {
+            let total = response.chars().count();
+            response.truncate(cut);
+            response.push_str(&format!("\n[… {} more characters]", total - MARKDOWN_BODY));
+        }
+        out.push('\n');
+        out.push_str(&fenced(&response));
+    }
+    out
+}
+
+/// An `http` code block whose fence is longer than any backtick run in `text`
+fn fenced(text: &str) -> String {
+    let longest = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+    format!("{}http\n{}\n{}\n", fence, text.trim_end(), fence)
+}
+
+fn now() -> String {
+    OffsetDateTime::now_utc().format(&Rfc3339).unwrap_or_default()
+}
+
+#[derive(Clone, Copy)]
+pub enum Format {
+    Har,
+    Jsonl,
+    BurpXml,
+    Markdown,
+    /// Rust `#[test]`s using reqwest
+    Reqwest,
+    /// pytest functions using httpx
+    Pytest,
+}
+
+impl Format {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "har" => Some(Format::Har),
+            "jsonl" => Some(Format::Jsonl),
+            "xml" | "burp" => Some(Format::BurpXml),
+            "md" | "markdown" => Some(Format::Markdown),
+            "reqwest" | "rs" => Some(Format::Reqwest),
+            "pytest" | "py" => Some(Format::Pytest),
+            _ => None,
+        }
+    }
+
+    pub fn ext(self) -> &'static str {
+        match self {
+            Format::Har => "har",
+            Format::Jsonl => "jsonl",
+            Format::BurpXml => "xml",
+            Format::Markdown => "md",
+            Format::Reqwest => "rs",
+            Format::Pytest => "py",
+        }
+    }
+
+    pub fn render<'a>(self, logs: impl Iterator<Item = &'a HttpLog>) -> io::Result<String> {
+        Ok(match self {
+            Format::Har => serde_json::to_string_pretty(&har(logs)).map_err(io::Error::other)?,
+            Format::Jsonl => jsonl(logs),
+            Format::BurpXml => burp_xml(logs),
+            Format::Markdown => markdown(logs),
+            Format::Reqwest => regression::reqwest(logs),
+            Format::Pytest => regression::pytest(logs),
+        })
+    }
+}
+
+/// Which entries an export covers, parsed from terms such as `scope tag:api since:login marked`;
+/// an entry must pass every term, and one of the tags when several are given
+#[derive(Clone, Default)]
+pub struct Filter {
+    scope: bool,
+    /// Only what the Requests list shows now
+    view: bool,
+    /// Only entries marked as evidence with `m`
+    marked: bool,
+    tags: Vec<String>,
+    /// Marker names or numbers, resolved when the export runs
+    since: Option<String>,
+    until: Option<String>,
+    last: Option<Duration>,
+    /// The Requests list's `:window`
+    window: bool,
+}
+
+impl Filter {
+    pub const USAGE: &'static str = "scope view marked window tag:NAME since:MARKER until:MARKER last:SPAN";
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut filter = Filter::default();
+        for term in text.split_whitespace() {
+            match term.split_once(':') {
+                None if term == "scope" => filter.scope = true,
+                None if term == "view" => filter.view = true,
+                None if term == "marked" => filter.marked = true,
+                None if term == "window" => filter.window = true,
+                Some(("tag", name)) if !name.is_empty() => filter.tags.push(name.to_string()),
+                Some(("since", marker)) if !marker.is_empty() => filter.since = Some(marker.to_string()),
+                Some(("until", marker)) if !marker.is_empty() => filter.until = Some(marker.to_string()),
+                Some(("last", span)) => filter.last = Some(markers::parse_span(span).ok_or(format!("`{}` is not a span of up to a year like 90s, 10m or 2h", span))?),
+                _ => return Err(format!("Unknown export term `{}`: {}", term, Filter::USAGE)),
+            }
+        }
+        Ok(filter)
+    }
+
+    /// Indices into `logs` of the entries it covers, in capture order
+    pub fn select(&self, app: &App) -> Result<Vec<usize>, String> {
+        let moment = |key: &Option<String>| key.as_ref().map(|k| markers::find(&app.markers, k).map(|i| app.markers[i].at)).transpose();
+        let (since, until) = (moment(&self.since)?, moment(&self.until)?);
+        let earliest = self.last.map(|span| {
+            time::Duration::try_from(span).ok().and_then(|span| OffsetDateTime::now_utc().checked_sub(span))
+                .ok_or_else(|| format!("last:{}s reaches back too far", span.as_secs()))
+        }).transpose()?;
+        let shown: Option<HashSet<usize>> = self.view.then(|| app.view().into_iter().collect());
+        Ok((0..app.logs.len())
+            .filter(|i| {
+                let log = &app.logs[*i];
+                let within = |bound: Option<OffsetDateTime>, after: bool| bound.is_none_or(|b| log.started.is_some_and(|t| if after { t >= b } else { t < b }));
+                (!self.scope || app.in_scope(&log.listener, &log.host))
+                    && shown.as_ref().is_none_or(|s| s.contains(i))
+                    && (!self.marked || app.evidence.contains(i))
+                    && (self.tags.is_empty() || self.tags.iter().any(|t| log.tags.contains(t)))
+                    && within(since, true)
+                    && within(until, false)
+                    && within(earliest, true)
+                    && (!self.window || app.window.as_ref().is_none_or(|w| w.contains(&app.markers, log.started)))
+            })
+            .collect())
+    }
+}
+
+/// Write the entries `filter` selects to a timestamped file in `dir`; returns it and how many went in
+pub fn session(app: &App, dir: &Path, format: Format, filter: &Filter) -> Result<(PathBuf, usize), String> {
+    let selected = filter.select(app)?;
+    let count = selected.iter().filter(|i| !app.logs[**i].request_raw.is_empty()).count();
+    let logs: Vec<_> = selected.iter().map(|i| redact::outgoing(app, &app.logs[*i])).collect();
+    let data = format.render(logs.iter().map(|l| l.as_ref())).map_err(|e| e.to_string())?;
+    let path = write_stamped(dir, "belch", format.ext(), data).map_err(|e| e.to_string())?;
+    Ok((path, count))
+}
+
+/// Write the recon list to a timestamped file in `dir`, one `kind<TAB>value<TAB>in-scope|out-of-scope` per line
+pub fn recon(found: &[Found], dir: &Path) -> io::Result<PathBuf> {
+    let data: String = found.iter()
+        .map(|f| format!("{}\t{}\t{}\n", f.kind.label(), f.value, if f.in_scope { "in-scope" } else { "out-of-scope" }))
+        .collect();
+    write_stamped(dir, "belch-recon", "tsv", data)
+}
+
+/// `dir/PREFIX-YYYYMMDD-HHMMSSZ.EXT`, UTC
+pub fn write_stamped(dir: &Path, prefix: &str, ext: &str, data: impl AsRef<[u8]>) -> io::Result<PathBuf> {
+    let t = OffsetDateTime::now_utc();
+    let name = format!(
+        "{}-{:04}{:02}{:02}-{:02}{:02}{:02}Z.{}",
+        prefix, t.year(), t.month() as u8, t.day(), t.hour(), t.minute(), t.second(), ext
+    );
+    fs::create_dir_all(dir)?;
+    let path = dir.join(name);
+    fs::write(&path, data)?;
+    Ok(path)
+}
@@ -0,0 +1,373 @@
+// Fault injection for resilience testing: delays, jitter, dropped connections and injected 500s
+// on matched hosts, applied before a request is forwarded or before its response is returned.
+// Response rules can also corrupt what the client gets. Rules come from `faults.txt` in the config
+// dir or from :fault, and a seed makes the random rolls repeat from run to run.
+
+use std::fs;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use bytes::Bytes;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use crate::app::App;
+use crate::{config, http, random};
+
+/// What the client gets instead of the server's answer
+pub const ERROR_RESPONSE: &[u8] = b"HTTP/1.1 500 Internal Server Error\r\nContent-Type: text/plain\r\nContent-Length: 30\r\nConnection: close\r\n\r\nFault injected by belch (500)\n";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Before the request goes upstream
+    Request,
+    /// After the response came back, before the client gets it
+    Response,
+}
+
+impl Stage {
+    fn name(self) -> &'static str {
+        match self {
+            Stage::Request => "request",
+            Stage::Response => "response",
+        }
+    }
+}
+
+/// A change to the response on its way to the client
+#[derive(Clone)]
+pub enum Mutation {
+    /// Cut the body in half and close, so the client reads less than Content-Length promised
+    Truncate,
+    /// Status line set to 500, the server's headers and body kept
+    Flip,
+    /// Remove a header
+    Strip(String),
+    /// Send so many bytes at a time, waiting in between
+    Drip { bytes: usize, every: Duration },
+}
+
+impl Mutation {
+    fn label(&self) -> String {
+        match self {
+            Mutation::Truncate => "truncate".into(),
+            Mutation::Flip => "flip to 500".into(),
+            Mutation::Strip(name) => format!("strip {}", name),
+            Mutation::Drip { bytes, every } => format!("drip {} B/{} ms", bytes, every.as_millis()),
+        }
+    }
+
+    fn tag(&self) -> &'static str {
+        match self {
+            Mutation::Truncate => "fault-truncate",
+            Mutation::Flip => "fault-flip",
+            Mutation::Strip(_) => "fault-strip",
+            Mutation::Drip { .. } => "fault-drip",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Rule {
+    /// Host pattern as in the scope list, or `*` for every host
+    host: String,
+    stage: Stage,
+    /// Shortest and longest delay; a random one in between is used each time
+    delay: Option<(Duration, Duration)>,
+    /// Percentages of matched messages dropped or answered with a 500
+    drop: f64,
+    error: f64,
+    /// Response changes, each with the percentage of passed responses it applies to
+    mutations: Vec<(Mutation, f64)>,
+}
+
+impl Rule {
+    /// `HOST [request|response] [delay=MS|MS-MS] [drop=P%] [error=P%]`, and for responses
+    /// `[truncate=P%] [flip=P%] [strip=NAME[:P%]] [drip=BYTES/MS[:P%]]`
+    pub fn parse(args: &[&str]) -> Result<Self, String> {
+        let Some((host, mut rest)) = args.split_first() else { return Err("no host".into()) };
+        let mut rule = Rule { host: host.to_lowercase(), stage: Stage::Request, delay: None, drop: 0.0, error: 0.0, mutations: Vec::new() };
+        match rest.first() {
+            Some(&"request") => rest = &rest[1..],
+            Some(&"response") => (rule.stage, rest) = (Stage::Response, &rest[1..]),
+            _ => {}
+        }
+        let ms = |t: &str| t.trim_end_matches("ms").parse::<u64>().map(Duration::from_millis).map_err(|_| format!("`{}` is not a number of milliseconds", t));
+        for arg in rest {
+            match arg.split_once('=') {
+                Some(("delay", v)) => {
+                    let (low, high) = v.split_once('-').unwrap_or((v, v));
+                    let (low, high) = (ms(low)?, ms(high)?);
+                    rule.delay = Some((low.min(high), low.max(high)));
+                }
+                Some(("drop", v)) => rule.drop = percent(v)?,
+                Some(("error", v)) => rule.error = percent(v)?,
+                Some(("truncate", v)) => rule.mutations.push((Mutation::Truncate, percent(v)?)),
+                Some(("flip", v)) => rule.mutations.push((Mutation::Flip, percent(v)?)),
+                Some(("strip", v)) => {
+                    let (name, p) = sometimes(v)?;
+                    if name.is_empty() {
+                        return Err("strip= takes a header name".into());
+                    }
+                    rule.mutations.push((Mutation::Strip(name.to_string()), p));
+                }
+                Some(("drip", v)) => {
+                    let (rate, p) = sometimes(v)?;
+                    let (bytes, every) = rate.split_once('/').ok_or_else(|| format!("`{}` is not BYTES/MS", rate))?;
+                    let bytes = bytes.parse::<usize>().ok().filter(|b| *b > 0).ok_or_else(|| format!("`{}` is not a number of bytes", bytes))?;
+                    rule.mutations.push((Mutation::Drip { bytes, every: ms(every)? }, p));
+                }
+                _ => return Err(format!("unknown setting `{}`: delay=MS[-MS], drop=P%, error=P%, truncate=P%, flip=P%, strip=NAME[:P%], drip=BYTES/MS[:P%]", arg)),
+            }
+        }
+        if rule.stage == Stage::Request && !rule.mutations.is_empty() {
+            return Err("truncate=, flip=, strip= and drip= change responses: give the response stage".into());
+        }
+        if rule.delay.is_none() && rule.drop == 0.0 && rule.error == 0.0 && rule.mutations.is_empty() {
+            return Err("nothing to inject: give delay=, drop=, error= or a response change".into());
+        }
+        Ok(rule)
+    }
+
+    pub fn label(&self) -> String {
+        let mut parts = vec![format!("{} {}", self.host, self.stage.name())];
+        match self.delay {
+            Some((low, high)) if low == high => parts.push(format!("delay {} ms", low.as_millis())),
+            Some((low, high)) => parts.push(format!("delay {}-{} ms", low.as_millis(), high.as_millis())),
+            None => {}
+        }
+        if self.drop > 0.0 {
+            parts.push(format!("drop {}%", self.drop));
+        }
+        if self.error > 0.0 {
+            parts.push(format!("500 {}%", self.error));
+        }
+        for (mutation, p) in &self.mutations {
+            parts.push(format!("{} {}%", mutation.label(), p));
+        }
+        parts.join(", ")
+    }
+
+    fn matches(&self, host: &str, stage: Stage) -> bool {
+        self.stage == stage && (self.host == "*" || config::host_matches(std::slice::from_ref(&self.host), host))
+    }
+}
+
+fn percent(v: &str) -> Result<f64, String> {
+    v.trim_end_matches('%').parse::<f64>().ok().filter(|p| (0.0..=100.0).contains(p)).ok_or_else(|| format!("`{}` is not a percentage", v))
+}
+
+/// `VALUE[:P%]`, always when no percentage is given
+fn sometimes(v: &str) -> Result<(&str, f64), String> {
+    match v.rsplit_once(':') {
+        Some((v, p)) => Ok((v, percent(p)?)),
+        None => Ok((v, 100.0)),
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Pass,
+    /// Close the client's connection without an answer
+    Drop,
+    /// Answer with `ERROR_RESPONSE`
+    Error,
+}
+
+pub struct Injected {
+    pub outcome: Outcome,
+    pub delayed: bool,
+    /// Response changes that came up in the roll
+    pub mutations: Vec<Mutation>,
+}
+
+impl Injected {
+    /// Nothing injected
+    pub fn none() -> Self {
+        Injected { outcome: Outcome::Pass, delayed: false, mutations: Vec::new() }
+    }
+
+    /// Tags for the entry, one per thing injected
+    pub fn tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = match (self.outcome, self.delayed) {
+            (Outcome::Drop, _) => vec!["fault-drop".into()],
+            (Outcome::Error, _) => vec!["fault-500".into()],
+            (Outcome::Pass, true) => vec!["fault-delay".into()],
+            (Outcome::Pass, false) => Vec::new(),
+        };
+        tags.extend(self.mutations.iter().map(|m| m.tag().to_string()));
+        tags
+    }
+
+    /// `response` with the rolled changes applied; dripping happens in `write`
+    pub fn mutate(&self, response: Bytes) -> Bytes {
+        if self.mutations.iter().all(|m| matches!(m, Mutation::Drip { .. })) {
+            return response;
+        }
+        let mut out = response.to_vec();
+        for mutation in &self.mutations {
+            match mutation {
+                Mutation::Strip(name) => out = http::remove_header(&out, name),
+                Mutation::Flip => {
+                    let line_end = out.iter().position(|b| *b == b'\n').map_or(out.len(), |i| i + 1);
+                    let version = out.split(|b| *b == b' ').next().unwrap_or(b"HTTP/1.1").to_vec();
+                    let mut flipped = version;
+                    flipped.extend_from_slice(b" 500 Internal Server Error\r\n");
+                    out.splice(..line_end, flipped);
+                }
+                Mutation::Truncate | Mutation::Drip { .. } => {}
+            }
+        }
+        // Last, so the head is whole for the changes above
+        if self.mutations.iter().any(|m| matches!(m, Mutation::Truncate)) {
+            let body = http::split_raw(&out).1.len();
+            out.truncate(out.len() - body + body / 2);
+        }
+        Bytes::from(out)
+    }
+
+    /// Write `data` to the client, a few bytes at a time when a drip came up
+    pub async fn write<W: AsyncWrite + Unpin>(&self, out: &mut W, data: &[u8]) -> std::io::Result<()> {
+        let drip = self.mutations.iter().find_map(|m| match m {
+            Mutation::Drip { bytes, every } => Some((*bytes, *every)),
+            _ => None,
+        });
+        let Some((bytes, every)) = drip else { return out.write_all(data).await };
+        for chunk in data.chunks(bytes) {
+            out.write_all(chunk).await?;
+            out.flush().await?;
+            tokio::time::sleep(every).await;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct State {
+    rules: Vec<Rule>,
+    /// Where the seeded sequence is; `None` rolls from the system's randomness
+    seed: Option<u64>,
+}
+
+static STATE: OnceLock<Mutex<State>> = OnceLock::new();
+
+fn state() -> &'static Mutex<State> {
+    STATE.get_or_init(Default::default)
+}
+
+/// A uniform random number in [0, 1), the next in the seeded sequence when there is one
+fn random() -> f64 {
+    let bits = match state().lock().unwrap().seed.as_mut() {
+        // splitmix64
+        Some(seed) => {
+            *seed = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let z = (*seed ^ (*seed >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            let z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^ (z >> 31)
+        }
+        None => u64::from_le_bytes(random::bytes().unwrap_or_default()),
+    };
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Apply the first rule matching `host` at `stage`: wait out its delay, roll for a drop or a 500,
+/// then for each response change
+pub async fn inject(host: &str, stage: Stage) -> Injected {
+    let rule = state().lock().unwrap().rules.iter().find(|r| r.matches(host, stage)).cloned();
+    let Some(rule) = rule else { return Injected::none() };
+    if let Some((low, high)) = rule.delay {
+        tokio::time::sleep(low + (high - low).mul_f64(random())).await;
+    }
+    let roll = random() * 100.0;
+    let outcome = if roll < rule.drop {
+        Outcome::Drop
+    } else if roll < rule.drop + rule.error {
+        Outcome::Error
+    } else {
+        Outcome::Pass
+    };
+    let mutations = match outcome {
+        Outcome::Pass => rule.mutations.into_iter().filter(|(_, p)| random() * 100.0 < *p).map(|(m, _)| m).collect(),
+        _ => Vec::new(),
+    };
+    Injected { outcome, delayed: rule.delay.is_some(), mutations }
+}
+
+/// Rules from `faults.txt` in the config dir, one `:fault` command's arguments per line, `#` comments
+pub fn load() -> Result<(), String> {
+    let path = config::config_dir().join("faults.txt");
+    let text = fs::read_to_string(&path).unwrap_or_default();
+    let mut state = state().lock().unwrap();
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let args: Vec<&str> = line.split_whitespace().collect();
+        match args.as_slice() {
+            ["seed", seed] => state.seed = Some(seed.parse().map_err(|_| format!("{}:{}: `{}` is not a number", path.display(), n + 1, seed))?),
+            _ => {
+                let rule = Rule::parse(&args).map_err(|e| format!("{}:{}: {}", path.display(), n + 1, e))?;
+                state.rules.retain(|r| !(r.host == rule.host && r.stage == rule.stage));
+                state.rules.push(rule);
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn clear() {
+    state().lock().unwrap().rules.clear();
+}
+
+/// Footer note while any fault rule is active
+pub fn indicator() -> Option<String> {
+    let count = state().lock().unwrap().rules.len();
+    (count > 0).then(|| format!(" faults: {} rule(s)", count))
+}
+
+/// `:fault HOST [request|response] SETTINGS…` adds a rule, replacing one for the same host and
+/// stage; `:fault HOST [STAGE] off` removes the host's rules, `:fault clear` removes all and `:fault` lists them.
+/// `:fault seed N|off` makes the rolls repeatable from N
+pub fn command(app: &mut App, args: &[&str]) {
+    let mut state = state().lock().unwrap();
+    let State { rules: list, seed } = &mut *state;
+    match args {
+        [] if list.is_empty() => app.set_status("No faults; :fault HOST|* [request|response] delay=MS[-MS] drop=P% error=P% truncate=P% flip=P% strip=NAME[:P%] drip=BYTES/MS[:P%]"),
+        [] => app.set_status(list.iter().map(Rule::label).collect::<Vec<_>>().join("   ")),
+        ["seed", "off"] => {
+            *seed = None;
+            app.set_status("Fault rolls random again");
+        }
+        ["seed", n] => match n.parse() {
+            Ok(n) => {
+                *seed = Some(n);
+                app.set_status(format!("Fault rolls repeat from seed {}", n));
+            }
+            Err(_) => app.set_status("usage: :fault seed N|off"),
+        },
+        ["clear"] => {
+            list.clear();
+            app.set_status("Fault injection off");
+        }
+        [host, stage @ .., "off"] => {
+            let stage = match stage {
+                [] => None,
+                ["request"] => Some(Stage::Request),
+                ["response"] => Some(Stage::Response),
+                _ => return app.set_status("usage: :fault HOST [request|response] off"),
+            };
+            let before = list.len();
+            list.retain(|r| !(r.host.eq_ignore_ascii_case(host) && stage.is_none_or(|s| r.stage == s)));
+            app.set_status(if list.len() < before { format!("No more faults for {}", host) } else { format!("No fault rule for {}", host) });
+        }
+        _ if app.refuse("fault injection") => {}
+        _ => match Rule::parse(args) {
+            Ok(rule) => {
+                list.retain(|r| !(r.host == rule.host && r.stage == rule.stage));
+                app.set_status(format!("Injecting faults: {}", rule.label()));
+                list.push(rule);
+            }
+            Err(e) => app.set_status(format!("fault: {}", e)),
+        },
+    }
+}
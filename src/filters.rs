@@ -0,0 +1,173 @@
+// Saved filters, and the Requests sidebar listing them and the tags with live counts
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::app::{App, HttpLog};
+use crate::config;
+use crate::tags::Rule;
+
+/// Used when `filters.txt` does not exist
+const DEFAULT_FILTERS: &str = "errors status 5xx\nclient-errors status 4xx\nposts method POST\n";
+
+pub struct Saved {
+    rule: Rule,
+    /// The `NAME FIELD PATTERN` line it was read from
+    line: String,
+    /// Indices into `logs` of the entries it matches, kept up to date on capture
+    hits: HashSet<usize>,
+}
+
+impl Saved {
+    pub fn name(&self) -> &str {
+        &self.rule.tag
+    }
+}
+
+#[derive(Default)]
+pub struct SavedFilters {
+    pub list: Vec<Saved>,
+    path: Option<PathBuf>,
+}
+
+impl SavedFilters {
+    /// Load `filters.txt` from the config dir: `NAME FIELD PATTERN` per line as in `tags.txt`, `#` comments
+    pub fn load() -> Result<Self, String> {
+        let path = config::config_dir().join("filters.txt");
+        let text = fs::read_to_string(&path).unwrap_or_else(|_| DEFAULT_FILTERS.to_string());
+        let mut list = Vec::new();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let rule = Rule::parse(line).map_err(|e| format!("{}:{}: {}", path.display(), n + 1, e))?;
+            list.push(Saved { rule, line: line.to_string(), hits: HashSet::new() });
+        }
+        Ok(SavedFilters { list, path: Some(path) })
+    }
+
+    fn write(&self) -> Result<(), String> {
+        let Some(path) = &self.path else { return Ok(()) };
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        let lines: Vec<&str> = self.list.iter().map(|s| s.line.as_str()).collect();
+        fs::write(path, lines.join("\n") + "\n").map_err(|e| format!("Could not write {}: {}", path.display(), e))
+    }
+
+    pub fn find(&self, name: &str) -> Option<&Saved> {
+        self.list.iter().find(|s| s.name() == name)
+    }
+
+    /// Whether the entry at `index` passes the filter called `name`
+    pub fn contains(&self, name: &str, index: usize) -> bool {
+        self.find(name).is_some_and(|s| s.hits.contains(&index))
+    }
+
+    /// Record a newly captured entry in every filter it matches
+    pub fn ingest(&mut self, index: usize, log: &HttpLog) {
+        for saved in &mut self.list {
+            if saved.rule.matches(log) {
+                saved.hits.insert(index);
+            }
+        }
+    }
+
+    pub fn clear_hits(&mut self) {
+        self.list.iter_mut().for_each(|s| s.hits.clear());
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub enum Item {
+    All,
+    Filter(String),
+    Tag(String),
+}
+
+/// Sidebar rows with their counts: everything, each saved filter, then each tag in use
+pub fn items(app: &App) -> Vec<(Item, usize)> {
+    let mut tags: BTreeMap<&str, usize> = BTreeMap::new();
+    for tag in app.logs.iter().flat_map(|l| &l.tags) {
+        *tags.entry(tag).or_default() += 1;
+    }
+    let mut items = vec![(Item::All, app.logs.len())];
+    items.extend(app.saved_filters.list.iter().map(|s| (Item::Filter(s.name().to_string()), s.hits.len())));
+    items.extend(tags.into_iter().map(|(t, n)| (Item::Tag(t.to_string()), n)));
+    items
+}
+
+/// The row the list is narrowed by now
+pub fn current(app: &App) -> Item {
+    match (&app.saved_filter, &app.tag_filter) {
+        (Some(name), _) => Item::Filter(name.clone()),
+        (None, Some(tag)) => Item::Tag(tag.clone()),
+        (None, None) => Item::All,
+    }
+}
+
+pub fn apply(app: &mut App, item: Item) {
+    app.keep_selection(|a| {
+        (a.saved_filter, a.tag_filter) = match item {
+            Item::All => (None, None),
+            Item::Filter(name) => (Some(name), None),
+            Item::Tag(tag) => (None, Some(tag)),
+        };
+    });
+}
+
+/// Apply the sidebar row `by` rows below the current one, or above when negative
+pub fn step(app: &mut App, by: isize) {
+    let items = items(app);
+    let at = items.iter().position(|(i, _)| *i == current(app)).unwrap_or(0);
+    let next = at.saturating_add_signed(by).min(items.len() - 1);
+    apply(app, items[next].0.clone());
+}
+
+/// `:filter NAME` narrows the list to a saved filter and `:filter` clears it;
+/// `:filter save NAME FIELD PATTERN` and `:filter delete NAME` edit `filters.txt`
+pub fn command(app: &mut App, args: &[&str]) {
+    match args {
+        [] => apply(app, Item::All),
+        ["save", rest @ ..] => {
+            let line = rest.join(" ");
+            let rule = match Rule::parse(&line) {
+                Ok(rule) => rule,
+                Err(e) => return app.set_status(format!("filter save: {} (fields: url, host, method, status, type, header, body)", e)),
+            };
+            let name = rule.tag.clone();
+            let mut saved = Saved { rule, line, hits: HashSet::new() };
+            for (i, log) in app.logs.iter().enumerate() {
+                if saved.rule.matches(log) {
+                    saved.hits.insert(i);
+                }
+            }
+            let count = saved.hits.len();
+            app.saved_filters.list.retain(|s| s.name() != name);
+            app.saved_filters.list.push(saved);
+            match app.saved_filters.write() {
+                Ok(()) => app.set_status(format!("Saved filter {} ({} entries)", name, count)),
+                Err(e) => app.set_status(e),
+            }
+        }
+        ["delete", name] => {
+            let before = app.saved_filters.list.len();
+            app.saved_filters.list.retain(|s| s.name() != *name);
+            if app.saved_filters.list.len() == before {
+                return app.set_status(format!("No saved filter {}", name));
+            }
+            if app.saved_filter.as_deref() == Some(*name) {
+                apply(app, Item::All);
+            }
+            match app.saved_filters.write() {
+                Ok(()) => app.set_status(format!("Deleted filter {}", name)),
+                Err(e) => app.set_status(e),
+            }
+        }
+        [name] if app.saved_filters.find(name).is_some() => apply(app, Item::Filter(name.to_string())),
+        [name] => app.set_status(format!("No saved filter {}; :filter save NAME FIELD PATTERN adds one", name)),
+        _ => app.set_status("usage: :filter [NAME] | :filter save NAME FIELD PATTERN | :filter delete NAME"),
+    }
+}
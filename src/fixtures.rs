@@ -0,0 +1,194 @@
+// `belch record` / `belch replay`: captured traffic as stub backends for integration tests
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+use tracing::{info, warn};
+
+use crate::app::{App, HttpLog};
+use crate::http;
+use crate::proxy::read_request;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Fixture {
+    pub method: String,
+    pub host: String,
+    pub path: String,
+    /// SHA-256 of the request body, hex
+    pub body_sha256: String,
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    /// UTF-8 body, or base64 when `binary` is set
+    pub body: String,
+    #[serde(default)]
+    pub binary: bool,
+    pub latency_ms: u64,
+}
+
+impl Fixture {
+    /// Build from a captured entry; None for tunnels and failed requests
+    pub fn from_log(log: &HttpLog) -> Option<Self> {
+        if log.request_raw.is_empty() || log.response_raw.is_empty() {
+            return None;
+        }
+        let request = log.request_raw.text();
+        let (req_head, req_body) = http::split_message(&request);
+        let mut first = req_head.lines().next()?.split_whitespace();
+        let method = first.next()?.to_string();
+        let path = http::origin_path(first.next()?).to_string();
+        let head = log.response_head();
+        let status = http::status_code(&head)?;
+        // Bodies are stored decoded, so drop the headers describing the wire encoding
+        let headers = head.lines().skip(1)
+            .filter_map(|l| l.split_once(':'))
+            .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+            .filter(|(k, _)| !matches!(
+                k.to_lowercase().as_str(),
+                "content-length" | "transfer-encoding" | "content-encoding" | "connection" | "keep-alive"
+            ))
+            .collect();
+        let body = log.response_body();
+        let (body, binary) = match String::from_utf8(body) {
+            Ok(s) => (s, false),
+            Err(e) => (base64::engine::general_purpose::STANDARD.encode(e.as_bytes()), true),
+        };
+        Some(Fixture {
+            method,
+            host: log.host.clone(),
+            path,
+            body_sha256: http::sha256_hex(req_body.as_bytes()),
+            status,
+            headers,
+            body,
+            binary,
+            latency_ms: log.duration.as_millis() as u64,
+        })
+    }
+
+    fn response(&self) -> Vec<u8> {
+        let body = if self.binary {
+            base64::engine::general_purpose::STANDARD.decode(&self.body).unwrap_or_default()
+        } else {
+            self.body.clone().into_bytes()
+        };
+        let mut out = format!("HTTP/1.1 {} {}\r\n", self.status, http::reason_phrase(self.status));
+        for (k, v) in &self.headers {
+            out.push_str(&format!("{}: {}\r\n", k, v));
+        }
+        out.push_str(&format!("Content-Length: {}\r\nConnection: close\r\n\r\n", body.len()));
+        let mut bytes = out.into_bytes();
+        bytes.extend_from_slice(&body);
+        bytes
+    }
+}
+
+/// Proxy without the TUI, writing every captured exchange to `--out` on Ctrl+C
+pub async fn record(app: Arc<Mutex<App>>, out: &str) -> Result<(), Box<dyn Error>> {
+    println!("Recording; press Ctrl+C to write {}", out);
+    let printer = {
+        let app = app.clone();
+        tokio::spawn(async move {
+            let mut seen = 0;
+            loop {
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                let guard = app.lock().unwrap();
+                for log in guard.logs.iter().skip(seen) {
+                    println!("  {}", log.url);
+                }
+                seen = guard.logs.len();
+            }
+        })
+    };
+    tokio::signal::ctrl_c().await?;
+    printer.abort();
+    let fixtures: Vec<Fixture> = app.lock().unwrap().logs.iter().filter_map(Fixture::from_log).collect();
+    fs::write(out, serde_json::to_string_pretty(&fixtures)?)?;
+    println!("Wrote {} fixtures to {}", fixtures.len(), out);
+    Ok(())
+}
+
+pub enum Latency {
+    None,
+    Recorded,
+    Fixed(u64),
+}
+
+/// Serve fixtures as a plain HTTP backend. Requests match on method + path + body hash,
+/// falling back to method + path; repeated matches are served in recorded order.
+pub async fn replay(file: &str, port: u16, latency: Latency) -> Result<(), Box<dyn Error>> {
+    let fixtures: Vec<Fixture> = serde_json::from_str(&fs::read_to_string(file)?)?;
+    let listener = TcpListener::bind(("127.0.0.1", port)).await?;
+    println!("Replaying {} fixtures from {} on http://127.0.0.1:{}", fixtures.len(), file, port);
+    let fixtures = Arc::new(fixtures);
+    // How many times each fixture key has been served, to step through sequences
+    let served: Arc<Mutex<HashMap<String, usize>>> = Arc::default();
+    loop {
+        let (mut client, _) = listener.accept().await?;
+        let fixtures = fixtures.clone();
+        let served = served.clone();
+        let delay = match latency {
+            Latency::None => None,
+            Latency::Fixed(ms) => Some(ms),
+            Latency::Recorded => Some(u64::MAX),
+        };
+        tokio::spawn(async move {
+            let Some(request) = read_request(&mut client).await else { return };
+            let text = String::from_utf8_lossy(&request);
+            let (head, body) = http::split_message(&text);
+            let mut first = head.lines().next().unwrap_or("").split_whitespace();
+            let method = first.next().unwrap_or("").to_string();
+            let path = http::origin_path(first.next().unwrap_or("/")).to_string();
+            let hash = http::sha256_hex(body.as_bytes());
+
+            let exact: Vec<&Fixture> = fixtures.iter()
+                .filter(|f| f.method == method && f.path == path && f.body_sha256 == hash)
+                .collect();
+            let candidates = if exact.is_empty() {
+                fixtures.iter().filter(|f| f.method == method && f.path == path).collect()
+            } else {
+                exact
+            };
+            let fixture = if candidates.is_empty() {
+                None
+            } else {
+                let key = format!("{} {} {}", method, path, hash);
+                let mut served = served.lock().unwrap();
+                let n = served.entry(key).or_insert(0);
+                let f = candidates[(*n).min(candidates.len() - 1)];
+                *n += 1;
+                Some(f.clone())
+            };
+
+            let response = match fixture {
+                Some(f) => {
+                    let wait = match delay {
+                        Some(u64::MAX) => f.latency_ms,
+                        Some(ms) => ms,
+                        None => 0,
+                    };
+                    if wait > 0 {
+                        tokio::time::sleep(Duration::from_millis(wait)).await;
+                    }
+                    info!("replay {} {} -> {}", method, path, f.status);
+                    f.response()
+                }
+                None => {
+                    warn!("replay {} {}: no fixture", method, path);
+                    let body = format!("{{\"error\":\"no fixture for {} {}\"}}", method, path.replace('"', "\\\""));
+                    format!(
+                        "HTTP/1.1 404 Not Found\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(), body
+                    ).into_bytes()
+                }
+            };
+            let _ = client.write_all(&response).await;
+        });
+    }
+}
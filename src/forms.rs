@@ -0,0 +1,230 @@
+// Forms on captured HTML pages, and submit requests built from them with their fields filled
+// in, for the Repeater
+
+use std::collections::HashMap;
+use std::fs;
+
+use crate::app::HttpLog;
+use crate::preview::{attr, tag_name};
+use crate::{config, http, preview, spider, transform};
+
+pub struct Field {
+    pub name: String,
+    /// `type` of an input, or `select`, `textarea` or `submit` for a button
+    pub kind: String,
+    /// Value the page gives it
+    pub value: String,
+    /// Checkboxes and radios only count when checked
+    pub checked: bool,
+}
+
+pub struct Form {
+    /// As written; empty submits to the page itself
+    pub action: String,
+    pub method: String,
+    pub enctype: String,
+    pub fields: Vec<Field>,
+}
+
+impl Form {
+    /// Method and action as shown in the inspector
+    pub fn label(&self) -> String {
+        format!("{} {}", self.method, if self.action.is_empty() { "(this page)" } else { &self.action })
+    }
+}
+
+/// Forms of an entry's HTML response
+pub fn of(log: &HttpLog) -> Vec<Form> {
+    let text = log.response_text();
+    let (head, body) = http::split_message(&text);
+    match http::header_value(head, "content-type").is_some_and(preview::is_html) {
+        true => find(body),
+        false => Vec::new(),
+    }
+}
+
+/// Forms of an HTML page in document order, with their named fields
+pub fn find(html: &str) -> Vec<Form> {
+    let lower = html.to_ascii_lowercase();
+    let mut forms = Vec::new();
+    let mut current: Option<Form> = None;
+    let mut pos = 0;
+    while let Some(start) = html[pos..].find('<').map(|i| i + pos) {
+        if lower[start..].starts_with("<!--") {
+            pos = lower[start..].find("-->").map_or(html.len(), |e| start + e + 3);
+            continue;
+        }
+        let Some(end) = html[start..].find('>').map(|i| i + start) else { break };
+        let tag = &html[start + 1..end];
+        pos = end + 1;
+        let (name, closing) = tag_name(tag);
+        // Content up to the element's closing tag
+        let content = |pos: &mut usize| {
+            let close = lower[*pos..].find(&format!("</{}", name)).map_or(html.len(), |e| *pos + e);
+            let text = &html[*pos..close];
+            *pos = close;
+            text
+        };
+        match (name.as_str(), closing) {
+            ("script" | "style" | "template", false) => {
+                content(&mut pos);
+            }
+            ("form", false) => {
+                forms.extend(current.take());
+                current = Some(Form {
+                    action: attr(tag, "action").unwrap_or_default().trim().to_string(),
+                    method: attr(tag, "method").unwrap_or("GET".into()).to_uppercase(),
+                    enctype: attr(tag, "enctype").unwrap_or("application/x-www-form-urlencoded".into()).to_lowercase(),
+                    fields: Vec::new(),
+                });
+            }
+            ("form", true) => forms.extend(current.take()),
+            (kind @ ("input" | "select" | "textarea" | "button"), false) => {
+                let Some(form) = current.as_mut() else { continue };
+                let Some(field_name) = attr(tag, "name").filter(|n| !n.is_empty()) else { continue };
+                let (kind, value) = match kind {
+                    "input" => (attr(tag, "type").unwrap_or("text".into()).to_lowercase(), attr(tag, "value").unwrap_or_default()),
+                    "textarea" => ("textarea".to_string(), transform::html_unescape(content(&mut pos))),
+                    "select" => ("select".to_string(), selected_option(content(&mut pos))),
+                    _ => match attr(tag, "type").unwrap_or("submit".into()).to_lowercase().as_str() {
+                        "submit" => ("submit".to_string(), attr(tag, "value").unwrap_or_default()),
+                        _ => continue,
+                    },
+                };
+                form.fields.push(Field { name: field_name, kind, value, checked: has_flag(tag, "checked") });
+            }
+            _ => {}
+        }
+    }
+    forms.extend(current);
+    forms
+}
+
+/// Whether a tag has a boolean attribute, bare or with a value
+fn has_flag(tag: &str, flag: &str) -> bool {
+    tag.to_ascii_lowercase().split(|c: char| c.is_whitespace() || c == '/')
+        .any(|w| w == flag || w.strip_prefix(flag).is_some_and(|rest| rest.starts_with('=')))
+}
+
+/// Value of the `selected` option of a select's content, or else of its first option
+fn selected_option(content: &str) -> String {
+    let lower = content.to_ascii_lowercase();
+    let mut options = Vec::new();
+    let mut pos = 0;
+    while let Some(start) = lower[pos..].find("<option").map(|i| i + pos) {
+        let Some(end) = content[start..].find('>').map(|i| i + start) else { break };
+        let tag = &content[start + 1..end];
+        let text_end = content[end + 1..].find('<').map_or(content.len(), |i| i + end + 1);
+        let value = attr(tag, "value").unwrap_or_else(|| transform::html_unescape(content[end + 1..text_end].trim()));
+        options.push((value, has_flag(tag, "selected")));
+        pos = end + 1;
+    }
+    options.iter().find(|(_, s)| *s).or(options.first()).map_or(String::new(), |(v, _)| v.clone())
+}
+
+/// `NAME VALUE` per line of `form_values.txt` in the config dir, `#` comments
+fn configured() -> HashMap<String, String> {
+    let text = fs::read_to_string(config::config_dir().join("form_values.txt")).unwrap_or_default();
+    text.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .filter_map(|l| l.split_once(char::is_whitespace).map(|(k, v)| (k.to_string(), v.trim().to_string())))
+        .collect()
+}
+
+/// Stand-in for an empty field, going by its type and name
+fn placeholder(field: &Field) -> &'static str {
+    let name = field.name.to_lowercase();
+    match field.kind.as_str() {
+        "email" => "test@example.com",
+        "password" => "Passw0rd!",
+        "number" | "range" => "1",
+        "tel" => "5555550100",
+        "url" => "https://example.com/",
+        "date" => "2024-01-01",
+        "checkbox" | "radio" => "on",
+        _ if name.contains("mail") => "test@example.com",
+        _ if name.contains("phone") || name.contains("tel") => "5555550100",
+        _ => "test",
+    }
+}
+
+/// Name and value of each field a browser would submit: configured values first, then the
+/// page's own, then placeholders; one radio per group and the first named submit button
+pub fn values(form: &Form) -> Vec<(String, String)> {
+    let configured = configured();
+    let mut out: Vec<(String, String)> = Vec::new();
+    let mut submitted = false;
+    for field in &form.fields {
+        match field.kind.as_str() {
+            "checkbox" if !field.checked => continue,
+            "radio" => {
+                let group: Vec<&Field> = form.fields.iter().filter(|f| f.kind == "radio" && f.name == field.name).collect();
+                let chosen = group.iter().find(|f| f.checked).unwrap_or(&group[0]);
+                if !std::ptr::eq(*chosen, field) {
+                    continue;
+                }
+            }
+            "submit" | "image" | "button" | "reset" | "file" => {
+                if submitted || !matches!(field.kind.as_str(), "submit" | "image") {
+                    continue;
+                }
+                submitted = true;
+            }
+            _ => {}
+        }
+        let value = match configured.get(&field.name) {
+            Some(v) => v.clone(),
+            None if !field.value.is_empty() || field.kind == "hidden" || field.kind == "submit" => field.value.clone(),
+            None => placeholder(field).to_string(),
+        };
+        out.push((field.name.clone(), value));
+    }
+    out
+}
+
+/// `host`, with the port unless it is the scheme's default
+fn authority(tls: bool, host: &str, port: u16) -> String {
+    if port == if tls { 443 } else { 80 } { host.to_string() } else { format!("{}:{}", host, port) }
+}
+
+/// The request submitting the form from `page`, and the entry it goes to: same cookies and
+/// user agent, fields filled in by `values`
+pub fn request(form: &Form, page: &HttpLog) -> Option<(HttpLog, Vec<u8>)> {
+    let url = spider::resolve(&page.full_url(), &form.action)?;
+    let (tls, host, port, target) = spider::target(&url)?;
+    let pairs = values(form);
+    let encoded: Vec<String> = pairs.iter().map(|(k, v)| format!("{}={}", http::url_encode(k), http::url_encode(v))).collect();
+    let (path, body, content_type) = match form.method.as_str() {
+        "GET" => (format!("{}?{}", target.split('?').next().unwrap_or("/"), encoded.join("&")), String::new(), None),
+        _ if form.enctype.contains("multipart") => {
+            let boundary = "----belchFormBoundary7MA4YWxk";
+            let mut body = String::new();
+            for (name, value) in &pairs {
+                body.push_str(&format!("--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n", boundary, name, value));
+            }
+            body.push_str(&format!("--{}--\r\n", boundary));
+            (target, body, Some(format!("multipart/form-data; boundary={}", boundary)))
+        }
+        _ => (target, encoded.join("&"), Some("application/x-www-form-urlencoded".to_string())),
+    };
+    let method = if form.method == "GET" { "GET" } else { "POST" };
+    let mut head = format!("{} {} HTTP/1.1\r\nHost: {}\r\n", method, path, authority(tls, &host, port));
+    let page_head = String::from_utf8_lossy(&page.request_raw.bytes()).split("\r\n\r\n").next().unwrap_or("").to_string();
+    for name in ["User-Agent", "Cookie", "Authorization"] {
+        if let Some(value) = http::header_value(&page_head, name) {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+    }
+    let page_url = page.full_url();
+    if let Some((tls, host, port, _)) = spider::target(&page_url).filter(|_| method == "POST") {
+        head.push_str(&format!("Origin: {}://{}\r\n", if tls { "https" } else { "http" }, authority(tls, &host, port)));
+    }
+    head.push_str(&format!("Referer: {}\r\n", page_url));
+    if let Some(content_type) = content_type {
+        head.push_str(&format!("Content-Type: {}\r\nContent-Length: {}\r\n", content_type, body.len()));
+    }
+    let mut log = page.clone();
+    (log.host, log.port, log.tls) = (host, port, tls);
+    Some((log, format!("{}\r\n{}", head, body).into_bytes()))
+}
@@ -0,0 +1,319 @@
+// Fuzzer: the Repeater's request sent once per payload, with the payload in every `§…§`
+// span, and the answers grouped by similarity so the odd one out stands out
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tracing::info;
+
+use crate::app::{App, HttpLog, Origin};
+use crate::{http, macros, payloads, repeater, transform};
+
+/// Marks an insertion point in the Repeater: `§default§`
+pub const MARKER: char = '§';
+/// Most payloads one run takes from a list
+const MAX_PAYLOADS: usize = 100_000;
+const DEFAULT_CONCURRENCY: usize = 4;
+/// Bits two body hashes may differ by and still count as the same page
+const MAX_DISTANCE: u32 = 4;
+/// Most answers a run keeps whole; the rest keep only status, length and hash
+const KEPT_BODIES: usize = 200;
+
+pub struct Attempt {
+    pub payload: String,
+    pub status: Option<u16>,
+    /// Body length with the payload's reflections taken out
+    pub len: usize,
+    pub elapsed: Duration,
+    /// The answer while it is kept, or why there was none
+    pub response: Result<Option<Vec<u8>>, String>,
+    hash: u64,
+}
+
+/// Attempts with the same status, a similar length and a similar body
+pub struct Cluster {
+    /// Indexes into `FuzzRun::attempts`, in arrival order
+    pub members: Vec<usize>,
+}
+
+pub struct FuzzRun {
+    pub url: String,
+    /// Where the payloads came from
+    pub source: String,
+    pub total: usize,
+    pub attempts: Vec<Attempt>,
+    pub clusters: Vec<Cluster>,
+    /// Attempts whose answer is still kept whole
+    kept: usize,
+    pub running: bool,
+    /// Rows are clusters, rarest first, instead of every attempt
+    pub grouped: bool,
+    pub selected: usize,
+    /// First response line shown below the table
+    pub scroll: usize,
+    stop: Arc<AtomicBool>,
+}
+
+impl FuzzRun {
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// Cluster indexes, smallest first, so anomalies come before the bulk of the answers
+    pub fn ranked(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.clusters.len()).collect();
+        order.sort_by_key(|&c| (self.clusters[c].members.len(), self.clusters[c].members[0]));
+        order
+    }
+
+    /// Few enough members to be worth a look: at most 5% of a run of at least 20
+    pub fn rare(&self, cluster: &Cluster) -> bool {
+        self.attempts.len() >= 20 && cluster.members.len() * 20 <= self.attempts.len()
+    }
+
+    /// The attempt behind the selected row
+    pub fn current(&self) -> Option<&Attempt> {
+        let index = if self.grouped {
+            let cluster = *self.ranked().get(self.selected)?;
+            self.clusters[cluster].members[0]
+        } else {
+            self.selected
+        };
+        self.attempts.get(index)
+    }
+
+    pub fn rows(&self) -> usize {
+        if self.grouped { self.clusters.len() } else { self.attempts.len() }
+    }
+
+    fn add(&mut self, mut attempt: Attempt) {
+        let index = self.attempts.len();
+        let similar = |c: &&mut Cluster| {
+            let first = &self.attempts[c.members[0]];
+            first.status == attempt.status
+                && similar_length(first.len, attempt.len)
+                && (first.hash ^ attempt.hash).count_ones() <= MAX_DISTANCE
+        };
+        match self.clusters.iter_mut().find(similar) {
+            Some(cluster) => {
+                // The first of its group is shown for it
+                cluster.members.push(index);
+                if let Ok(body) = &mut attempt.response {
+                    *body = None;
+                }
+            }
+            None => {
+                self.clusters.push(Cluster { members: vec![index] });
+                self.kept += matches!(attempt.response, Ok(Some(_))) as usize;
+            }
+        }
+        self.attempts.push(attempt);
+        if self.kept > KEPT_BODIES {
+            self.drop_commonest();
+        }
+    }
+
+    /// Let go of the kept answer of the biggest group, the least interesting one
+    fn drop_commonest(&mut self) {
+        let commonest = self.clusters.iter()
+            .filter(|c| matches!(self.attempts[c.members[0]].response, Ok(Some(_))))
+            .max_by_key(|c| c.members.len())
+            .map(|c| c.members[0]);
+        if let Some(first) = commonest {
+            self.attempts[first].response = Ok(None);
+            self.kept -= 1;
+        }
+    }
+}
+
+/// Lengths in the same bucket: within 5% of each other, or 16 bytes for short bodies
+fn similar_length(a: usize, b: usize) -> bool {
+    a.abs_diff(b) <= (a.max(b) / 20).max(16)
+}
+
+/// 64-bit simhash over pairs of adjacent words, each run of digits folded to one `0` so counters
+/// and timestamps don't count
+fn simhash(text: &str) -> u64 {
+    let words: Vec<String> = text.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty())
+        .map(|w| {
+            let mut folded = String::new();
+            for c in w.chars() {
+                if !c.is_ascii_digit() || !folded.ends_with('0') {
+                    folded.push(if c.is_ascii_digit() { '0' } else { c });
+                }
+            }
+            folded
+        })
+        .collect();
+    let mut weights = [0i32; 64];
+    // A one-word body still gets a hash
+    for pair in words.windows(if words.len() < 2 { 1 } else { 2 }) {
+        let mut hasher = DefaultHasher::new();
+        pair.hash(&mut hasher);
+        let hash = hasher.finish();
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            *weight += if hash >> bit & 1 == 1 { 1 } else { -1 };
+        }
+    }
+    weights.iter().enumerate().filter(|(_, w)| **w > 0).fold(0, |hash, (bit, _)| hash | 1 << bit)
+}
+
+/// Status, normalized length and body hash of an answer; reflections of the payload are taken
+/// out first, so a page echoing it doesn't look different for every payload
+fn fingerprint(raw: &[u8], payload: &str) -> (Option<u16>, usize, u64) {
+    let (head, body) = http::split_raw(raw);
+    let head = String::from_utf8_lossy(head);
+    let mut body = String::from_utf8_lossy(&http::decode_body(&head, body)).to_string();
+    // Shorter payloads would take out unrelated text
+    if payload.chars().count() >= 3 {
+        body = body.replace(payload, "").replace(&http::url_encode(payload), "");
+    }
+    (http::status_code(&head), body.len(), simhash(&body))
+}
+
+/// The template with every `§…§` span replaced by the payload
+fn fill(template: &str, payload: &str) -> String {
+    template.split(MARKER).enumerate().map(|(i, part)| if i % 2 == 1 { payload } else { part }).collect()
+}
+
+/// `request` with every query parameter value, and every field of a form-encoded body, marked as
+/// an insertion point, and how many were marked
+pub fn mark_parameters(request: &str) -> (String, usize) {
+    let mut count = 0;
+    let mut mark = |pairs: &str| -> String {
+        pairs.split('&').map(|pair| match pair.split_once('=') {
+            Some((name, value)) => {
+                count += 1;
+                format!("{}={m}{}{m}", name, value, m = MARKER)
+            }
+            None => pair.to_string(),
+        }).collect::<Vec<_>>().join("&")
+    };
+    let (head, body) = request.split_once("\n\n").unwrap_or((request, ""));
+    let (line, headers) = head.split_once('\n').unwrap_or((head, ""));
+    let line = match line.splitn(3, ' ').collect::<Vec<_>>().as_slice() {
+        [method, target, version] => match target.split_once('?') {
+            Some((path, query)) => format!("{} {}?{} {}", method, path, mark(query), version),
+            None => line.to_string(),
+        },
+        _ => line.to_string(),
+    };
+    let form = http::header_value(head, "Content-Type").is_some_and(|t| t.contains("x-www-form-urlencoded"));
+    let body = match body.split_once('\n') {
+        _ if !form || body.is_empty() => body.to_string(),
+        Some((fields, rest)) => format!("{}\n{}", mark(fields), rest),
+        None => mark(body),
+    };
+    let rest = if request.contains("\n\n") { format!("\n\n{}", body) } else { String::new() };
+    let headers = if head.contains('\n') { format!("\n{}", headers) } else { String::new() };
+    (format!("{}{}{}", line, headers, rest), count)
+}
+
+/// A payload list by name, `N-M` counting from N to M, or else a file with one payload per line
+fn payloads(source: &str) -> Result<Vec<String>, String> {
+    if let Some(mut list) = payloads::named(source) {
+        list.truncate(MAX_PAYLOADS);
+        return Ok(list);
+    }
+    if let Some((from, to)) = source.split_once('-').and_then(|(a, b)| Some((a.parse::<u64>().ok()?, b.parse::<u64>().ok()?))) {
+        return Ok((from..=to.max(from)).take(MAX_PAYLOADS).map(|n| n.to_string()).collect());
+    }
+    let text = std::fs::read_to_string(source).map_err(|e| format!("Can't read {}: {}", source, e))?;
+    Ok(text.lines().map(|l| l.trim_end_matches('\r')).filter(|l| !l.is_empty()).take(MAX_PAYLOADS).map(str::to_string).collect())
+}
+
+/// Send one payload and record the answer, unless a newer run replaced this one
+async fn attempt(app: &Arc<Mutex<App>>, target: &HttpLog, template: &str, payload: String, stop: &Arc<AtomicBool>) {
+    let sent = Instant::now();
+    let response = match transform::expand(fill(template, &payload).as_bytes()) {
+        Ok(filled) => macros::send_with_session(app, target, &repeater::frame(&filled), Origin::Fuzzer).await.map_err(|(_, reason)| reason),
+        Err(e) => Err(format!("Not sent: {}", e)),
+    };
+    let elapsed = sent.elapsed();
+    let (status, len, hash) = match &response {
+        Ok(raw) => fingerprint(raw, &payload),
+        Err(_) => (None, 0, 0),
+    };
+    let mut app = app.lock().unwrap();
+    if let Some(run) = app.fuzz.as_mut().filter(|r| Arc::ptr_eq(&r.stop, stop)) {
+        run.add(Attempt { payload, status, len, elapsed, response: response.map(Some), hash });
+    }
+}
+
+/// `:fuzz LIST|FILE|N-M [CONCURRENCY]` on the Repeater's request; `:fuzz` reopens the results,
+/// `:fuzz stop` ends the run
+pub fn command(guard: &mut App, app: Arc<Mutex<App>>, args: &[&str]) {
+    const USAGE: &str = "usage: :fuzz LIST|FILE|N-M [CONCURRENCY] | :fuzz stop";
+    let (source, workers) = match args {
+        [] => return guard.show_fuzz = guard.fuzz.is_some(),
+        ["stop"] => {
+            if let Some(run) = &guard.fuzz {
+                run.stop();
+            }
+            return;
+        }
+        [source] => (*source, DEFAULT_CONCURRENCY),
+        [source, n] => match n.parse::<usize>() {
+            Ok(n) if n > 0 => (*source, n.min(32)),
+            _ => return guard.set_status(USAGE),
+        },
+        _ => return guard.set_status(USAGE),
+    };
+    let Some(r) = guard.repeater.as_ref() else {
+        return guard.set_status("Open a request in the Repeater (R) and mark insertion points with Ctrl+F");
+    };
+    let template = r.editor.text("\r\n");
+    let markers = template.matches(MARKER).count();
+    if markers == 0 || markers % 2 == 1 {
+        return guard.set_status("Mark insertion points in the Repeater as §value§ (Ctrl+F types §)");
+    }
+    let target = r.target();
+    let list = match payloads(source) {
+        Ok(list) if !list.is_empty() => list,
+        Ok(_) => return guard.set_status(format!("No payloads in {}", source)),
+        Err(e) => return guard.set_status(e),
+    };
+    if let Some(old) = &guard.fuzz {
+        old.stop();
+    }
+    let stop = Arc::new(AtomicBool::new(false));
+    guard.fuzz = Some(FuzzRun {
+        url: target.full_url(),
+        source: source.to_string(),
+        total: list.len(),
+        attempts: Vec::new(),
+        clusters: Vec::new(),
+        kept: 0,
+        running: true,
+        grouped: true,
+        selected: 0,
+        scroll: 0,
+        stop: stop.clone(),
+    });
+    guard.show_fuzz = true;
+    guard.show_repeater = false;
+    tokio::spawn(async move {
+        let list = Arc::new(list);
+        let next = Arc::new(AtomicUsize::new(0));
+        let tasks: Vec<_> = (0..workers).map(|_| {
+            let (app, target, template, list, stop, next) = (app.clone(), target.clone(), template.clone(), list.clone(), stop.clone(), next.clone());
+            tokio::spawn(async move {
+                while !stop.load(Ordering::Relaxed) {
+                    let Some(payload) = list.get(next.fetch_add(1, Ordering::Relaxed)) else { break };
+                    attempt(&app, &target, &template, payload.clone(), &stop).await;
+                }
+            })
+        }).collect();
+        for task in tasks {
+            let _ = task.await;
+        }
+        let mut app = app.lock().unwrap();
+        if let Some(run) = app.fuzz.as_mut().filter(|r| Arc::ptr_eq(&r.stop, &stop)) {
+            run.running = false;
+            info!("fuzzing {} finished: {} answers in {} clusters", run.url, run.attempts.len(), run.clusters.len());
+        }
+    });
+}
@@ -0,0 +1,67 @@
+// Fuzzy matching for the Ctrl+P finder, in the style of fzf
+
+use std::collections::VecDeque;
+
+use crate::app::HttpLog;
+
+/// Most matches listed
+const MAX_RESULTS: usize = 200;
+
+/// Score of `query` as a case-insensitive subsequence of `text`; None when it is not one.
+/// Runs of consecutive characters and matches at word starts score higher, gaps lower.
+pub fn score(query: &str, text: &str) -> Option<i64> {
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    let mut at = 0;
+    let mut total = 0;
+    let mut last: Option<usize> = None;
+    for q in query.chars().flat_map(char::to_lowercase).filter(|c| !c.is_whitespace()) {
+        let i = at + text[at..].iter().position(|c| *c == q)?;
+        total += 1;
+        if last.is_some_and(|l| l + 1 == i) {
+            total += 5;
+        }
+        if i == 0 || !text[i - 1].is_alphanumeric() {
+            total += 3;
+        }
+        total -= last.map_or(i, |l| i - l - 1).min(10) as i64;
+        last = Some(i);
+        at = i + 1;
+    }
+    Some(total)
+}
+
+/// What the finder matches against: `METHOD path host status`
+pub fn line(log: &HttpLog) -> String {
+    let url = log.full_url();
+    let path = url.splitn(4, '/').nth(3).map_or("/".to_string(), |p| format!("/{}", p));
+    let status = log.status().map_or("---".to_string(), |s| s.to_string());
+    format!("{} {} {} {}", log.method(), path, log.host, status)
+}
+
+pub struct Finder {
+    pub query: String,
+    /// `line` of every entry, built once when the finder opens
+    pub lines: Vec<String>,
+    /// Entry indices, best first
+    pub results: Vec<usize>,
+    pub selected: usize,
+}
+
+impl Finder {
+    pub fn open(logs: &VecDeque<HttpLog>) -> Self {
+        let mut finder = Finder { query: String::new(), lines: logs.iter().map(line).collect(), results: Vec::new(), selected: 0 };
+        finder.update();
+        finder
+    }
+
+    /// Rank every entry against the query; newest first while it is empty
+    pub fn update(&mut self) {
+        let mut scored: Vec<(i64, usize)> = self.lines.iter().enumerate()
+            .filter_map(|(i, l)| score(&self.query, l).map(|s| (s, i)))
+            .collect();
+        // Ties go to the newer entry
+        scored.sort_by(|a, b| b.cmp(a));
+        self.results = scored.into_iter().take(MAX_RESULTS).map(|(_, i)| i).collect();
+        self.selected = 0;
+    }
+}
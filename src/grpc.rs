@@ -0,0 +1,289 @@
+// gRPC, gRPC-Web and Connect payloads: length-prefixed frames of schema-less protobuf
+
+use std::io::Read;
+
+use base64::Engine;
+
+use crate::app::HttpLog;
+use crate::http;
+
+/// Nested messages are not looked for deeper than this
+const MAX_DEPTH: usize = 12;
+/// Bytes shown of a field that is neither text nor a message
+const MAX_HEX: usize = 32;
+/// A compressed message is inflated no further than this
+const MAX_INFLATED: u64 = 16 << 20;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Protocol {
+    /// `application/grpc` and `application/grpc-web`: frames flagged 0x01 compressed, 0x80 trailers
+    Grpc,
+    /// `application/grpc-web-text`: the same frames, base64-encoded
+    GrpcWebText,
+    /// `application/connect+proto`: frames flagged 0x01 compressed, 0x02 end of stream (JSON)
+    ConnectStream,
+    /// `application/connect+json`: as above with JSON messages
+    ConnectJson,
+    /// `application/proto`: one unframed message, as Connect sends unary calls
+    Unary,
+}
+
+impl Protocol {
+    fn of(head: &str) -> Option<(Protocol, &'static str)> {
+        let content_type = http::header_value(head, "content-type")?.to_lowercase();
+        let content_type = content_type.split(';').next().unwrap_or("").trim();
+        let (base, _) = content_type.split_once('+').unwrap_or((content_type, ""));
+        Some(match (base, content_type) {
+            ("application/grpc", _) => (Protocol::Grpc, "gRPC"),
+            ("application/grpc-web", _) => (Protocol::Grpc, "gRPC-Web"),
+            ("application/grpc-web-text", _) => (Protocol::GrpcWebText, "gRPC-Web (text)"),
+            (_, "application/connect+proto") => (Protocol::ConnectStream, "Connect"),
+            (_, "application/connect+json") => (Protocol::ConnectJson, "Connect"),
+            (_, "application/proto" | "application/protobuf" | "application/x-protobuf") => (Protocol::Unary, "Protobuf"),
+            _ => return None,
+        })
+    }
+}
+
+/// One decoded message body, trailer block or end-of-stream message
+pub struct Decoded {
+    /// e.g. `gRPC-Web response: pkg.Service/Method`
+    pub label: String,
+    pub lines: Vec<String>,
+}
+
+pub enum Value {
+    Varint(u64),
+    Fixed64(u64),
+    Fixed32(u32),
+    Text(String),
+    Message(Vec<Field>),
+    Bytes(Vec<u8>),
+}
+
+pub struct Field {
+    pub number: u64,
+    pub value: Value,
+}
+
+fn varint(data: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let b = *data.get(*pos)?;
+        *pos += 1;
+        value |= u64::from(b & 0x7f) << shift;
+        if b & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+fn is_text(data: &[u8]) -> Option<String> {
+    let text = std::str::from_utf8(data).ok()?;
+    text.chars().all(|c| !c.is_control() || matches!(c, '\t' | '\n' | '\r')).then(|| text.to_string())
+}
+
+/// Parse `data` as protobuf wire format without a schema; None unless all of it parses
+pub fn decode(data: &[u8]) -> Option<Vec<Field>> {
+    decode_at(data, 0)
+}
+
+fn decode_at(data: &[u8], depth: usize) -> Option<Vec<Field>> {
+    let mut fields = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let key = varint(data, &mut pos)?;
+        let number = key >> 3;
+        if number == 0 || number >= 1 << 29 {
+            return None;
+        }
+        let value = match key & 7 {
+            0 => Value::Varint(varint(data, &mut pos)?),
+            1 => {
+                let bytes = data.get(pos..pos + 8)?;
+                pos += 8;
+                Value::Fixed64(u64::from_le_bytes(bytes.try_into().unwrap()))
+            }
+            5 => {
+                let bytes = data.get(pos..pos + 4)?;
+                pos += 4;
+                Value::Fixed32(u32::from_le_bytes(bytes.try_into().unwrap()))
+            }
+            2 => {
+                let len = usize::try_from(varint(data, &mut pos)?).ok()?;
+                let bytes = data.get(pos..pos.checked_add(len)?)?;
+                pos += len;
+                // Printable text is far more often a string than a message that happens to parse
+                if let Some(text) = is_text(bytes) {
+                    Value::Text(text)
+                } else if let Some(inner) = (depth < MAX_DEPTH).then(|| decode_at(bytes, depth + 1)).flatten() {
+                    Value::Message(inner)
+                } else {
+                    Value::Bytes(bytes.to_vec())
+                }
+            }
+            // Groups (3, 4) are long deprecated; treat them as not protobuf
+            _ => return None,
+        };
+        fields.push(Field { number, value });
+    }
+    Some(fields)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let shown: Vec<String> = bytes.iter().take(MAX_HEX).map(|b| format!("{:02x}", b)).collect();
+    let more = if bytes.len() > MAX_HEX { " …" } else { "" };
+    format!("{}{}", shown.join(" "), more)
+}
+
+/// Fields as an indented tree, `number: value`, with nested messages in braces
+pub fn render(fields: &[Field], indent: usize, out: &mut Vec<String>) {
+    let pad = "  ".repeat(indent);
+    for field in fields {
+        let n = field.number;
+        match &field.value {
+            Value::Varint(v) if *v > i64::MAX as u64 => out.push(format!("{}{}: {} (int64 {})", pad, n, v, *v as i64)),
+            Value::Varint(v) => out.push(format!("{}{}: {}", pad, n, v)),
+            Value::Fixed64(v) => out.push(format!("{}{}: 0x{:016x} (fixed64 {}, double {})", pad, n, v, v, f64::from_bits(*v))),
+            Value::Fixed32(v) => out.push(format!("{}{}: 0x{:08x} (fixed32 {}, float {})", pad, n, v, v, f32::from_bits(*v))),
+            Value::Text(t) => out.push(format!("{}{}: {:?}", pad, n, t)),
+            Value::Message(inner) => {
+                out.push(format!("{}{} {{", pad, n));
+                render(inner, indent + 1, out);
+                out.push(format!("{}}}", pad));
+            }
+            Value::Bytes(b) => out.push(format!("{}{}: <{} bytes> {}", pad, n, b.len(), hex(b))),
+        }
+    }
+}
+
+/// Lines for one message: the field tree, or a hex preview when it is not protobuf
+fn message_lines(data: &[u8]) -> Vec<String> {
+    let mut lines = Vec::new();
+    match decode(data) {
+        Some(fields) if fields.is_empty() => lines.push("(empty message)".to_string()),
+        Some(fields) => render(&fields, 0, &mut lines),
+        None => lines.push(format!("not protobuf: {}", hex(data))),
+    }
+    lines
+}
+
+fn json_lines(data: &[u8]) -> Vec<String> {
+    match serde_json::from_slice::<serde_json::Value>(data) {
+        Ok(v) => serde_json::to_string_pretty(&v).unwrap_or_default().lines().map(str::to_string).collect(),
+        Err(_) => String::from_utf8_lossy(data).lines().map(str::to_string).collect(),
+    }
+}
+
+/// The base64 text variant; each message may be padded on its own, so decode up to every `=` run
+fn decode_text(body: &[u8]) -> Option<Vec<u8>> {
+    let text: Vec<u8> = body.iter().copied().filter(|b| !b.is_ascii_whitespace()).collect();
+    let mut out = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    while i < text.len() {
+        if text[i] == b'=' {
+            while i < text.len() && text[i] == b'=' {
+                i += 1;
+            }
+            out.extend(base64::engine::general_purpose::STANDARD.decode(&text[start..i]).ok()?);
+            start = i;
+        } else {
+            i += 1;
+        }
+    }
+    if start < text.len() {
+        out.extend(base64::engine::general_purpose::STANDARD.decode(&text[start..]).ok()?);
+    }
+    Some(out)
+}
+
+/// Split a body into `(flags, payload)` frames; a short last frame is kept as far as it goes
+fn frames(body: &[u8]) -> Vec<(u8, &[u8])> {
+    let mut out = Vec::new();
+    let mut rest = body;
+    while rest.len() >= 5 {
+        let len = u32::from_be_bytes(rest[1..5].try_into().unwrap()) as usize;
+        let end = (5 + len).min(rest.len());
+        out.push((rest[0], &rest[5..end]));
+        rest = &rest[end..];
+    }
+    out
+}
+
+fn decompress(encoding: Option<&str>, data: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    let ok = match encoding.map(|e| e.trim().to_lowercase()).as_deref() {
+        Some("gzip") => flate2::read::GzDecoder::new(data).take(MAX_INFLATED).read_to_end(&mut out).is_ok(),
+        Some("deflate") => flate2::read::ZlibDecoder::new(data).take(MAX_INFLATED).read_to_end(&mut out).is_ok(),
+        _ => false,
+    };
+    ok.then_some(out)
+}
+
+/// Messages in one side of an exchange; empty when it is not an RPC payload
+fn decode_message(head: &str, body: &[u8], side: &str, method: &str) -> Vec<Decoded> {
+    let Some((protocol, name)) = Protocol::of(head) else { return Vec::new() };
+    if body.is_empty() {
+        return Vec::new();
+    }
+    let label = |what: String| format!("{} {}{}: {}", name, side, what, method);
+    if protocol == Protocol::Unary {
+        return vec![Decoded { label: label(String::new()), lines: message_lines(body) }];
+    }
+    let decoded;
+    let body = if protocol == Protocol::GrpcWebText {
+        match decode_text(body) {
+            Some(d) => { decoded = d; &decoded[..] }
+            None => return vec![Decoded { label: label(String::new()), lines: vec!["not valid base64".to_string()] }],
+        }
+    } else {
+        body
+    };
+    let encoding = http::header_value(head, "grpc-encoding").or_else(|| http::header_value(head, "connect-content-encoding"));
+    let connect = matches!(protocol, Protocol::ConnectStream | Protocol::ConnectJson);
+    let frames = frames(body);
+    let mut out = Vec::new();
+    for (i, (flags, payload)) in frames.iter().enumerate() {
+        let inflated;
+        let payload = if flags & 0x01 != 0 {
+            match decompress(encoding, payload) {
+                Some(d) => { inflated = d; &inflated[..] }
+                None => {
+                    let how = encoding.unwrap_or("an unknown encoding");
+                    out.push(Decoded { label: label(format!(" message {}", i + 1)), lines: vec![format!("compressed with {}, not decoded", how)] });
+                    continue;
+                }
+            }
+        } else {
+            payload
+        };
+        let (what, lines) = if !connect && flags & 0x80 != 0 {
+            (" trailers".to_string(), String::from_utf8_lossy(payload).lines().map(str::to_string).collect())
+        } else if connect && flags & 0x02 != 0 {
+            (" end of stream".to_string(), json_lines(payload))
+        } else if protocol == Protocol::ConnectJson {
+            (format!(" message {}", i + 1), json_lines(payload))
+        } else {
+            (format!(" message {}", i + 1), message_lines(payload))
+        };
+        let what = if frames.len() == 1 && what.starts_with(" message") { String::new() } else { what };
+        out.push(Decoded { label: label(what), lines });
+    }
+    out
+}
+
+/// Decoded messages of a gRPC, gRPC-Web or Connect exchange, request first
+pub fn find(log: &HttpLog) -> Vec<Decoded> {
+    let raw = log.request_raw.bytes();
+    let (head, body) = http::split_raw(&raw);
+    let head = String::from_utf8_lossy(head);
+    let target = head.split_whitespace().nth(1).unwrap_or("");
+    let path = target.split('?').next().unwrap_or("");
+    // `/package.Service/Method`, or the whole URL when it is absolute
+    let method = path.rsplitn(3, '/').take(2).collect::<Vec<_>>().into_iter().rev().collect::<Vec<_>>().join("/");
+    let mut out = decode_message(&head, &http::decode_body(&head, body), "request", &method);
+    out.extend(decode_message(&log.response_head(), &log.response_body(), "response", &method));
+    out
+}
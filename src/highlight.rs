@@ -0,0 +1,71 @@
+// Body syntax highlighting via syntect, picked by Content-Type
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use ratatui::style::{Color, Style};
+use ratatui::text::{Span, Spans};
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+/// Bodies longer than this are shown unhighlighted
+const MAX_LINES: usize = 5000;
+
+fn assets() -> &'static (SyntaxSet, Theme) {
+    static ASSETS: OnceLock<(SyntaxSet, Theme)> = OnceLock::new();
+    ASSETS.get_or_init(|| {
+        let mut themes = ThemeSet::load_defaults();
+        let theme = themes.themes.remove("base16-ocean.dark").unwrap_or_default();
+        (SyntaxSet::load_defaults_newlines(), theme)
+    })
+}
+
+/// syntect file extension for a Content-Type, if it is one we highlight
+pub fn syntax_for(content_type: &str) -> Option<&'static str> {
+    let ct = content_type.split(';').next().unwrap_or("").trim().to_lowercase();
+    match ct.as_str() {
+        "application/json" | "text/json" => Some("json"),
+        "text/html" | "application/xhtml+xml" => Some("html"),
+        "text/xml" | "application/xml" | "image/svg+xml" => Some("xml"),
+        "application/javascript" | "text/javascript" | "application/x-javascript" => Some("js"),
+        "text/css" => Some("css"),
+        _ if ct.ends_with("+json") => Some("json"),
+        _ if ct.ends_with("+xml") => Some("xml"),
+        _ => None,
+    }
+}
+
+/// Highlighted lines for `body`, or None when it has no syntax or is too long to highlight and
+/// is better shown as plain lines; recent results are cached since the pane redraws every tick
+pub fn highlight(body: &str, ext: &str) -> Option<Arc<Vec<Spans<'static>>>> {
+    static CACHE: Mutex<Vec<(u64, Arc<Vec<Spans<'static>>>)>> = Mutex::new(Vec::new());
+    let mut hasher = DefaultHasher::new();
+    (body, ext).hash(&mut hasher);
+    let key = hasher.finish();
+    let mut cache = CACHE.lock().unwrap();
+    if let Some((_, lines)) = cache.iter().find(|(k, _)| *k == key) {
+        return Some(lines.clone());
+    }
+
+    let (syntaxes, theme) = assets();
+    let syntax = syntaxes.find_syntax_by_extension(ext).filter(|_| body.lines().count() <= MAX_LINES)?;
+    let mut h = HighlightLines::new(syntax, theme);
+    let lines: Arc<Vec<Spans<'static>>> = Arc::new(LinesWithEndings::from(body).map(|line| {
+        let ranges = h.highlight_line(line, syntaxes).unwrap_or_default();
+        Spans::from(ranges.into_iter().map(|(style, text)| {
+            let fg = style.foreground;
+            Span::styled(
+                text.trim_end_matches(['\r', '\n']).to_string(),
+                Style::default().fg(Color::Rgb(fg.r, fg.g, fg.b)),
+            )
+        }).collect::<Vec<_>>())
+    }).collect());
+    if cache.len() >= 4 {
+        cache.remove(0);
+    }
+    cache.push((key, lines.clone()));
+    Some(lines)
+}
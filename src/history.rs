@@ -0,0 +1,91 @@
+// `:` command history, kept across sessions in `history.txt` and in project files
+
+use std::fs;
+use std::path::PathBuf;
+
+use crate::config;
+
+/// Oldest lines are dropped past this many
+const MAX_LINES: usize = 500;
+
+#[derive(Default)]
+pub struct History {
+    /// Oldest first
+    pub lines: Vec<String>,
+    /// Line being shown while browsing with Up/Down
+    cursor: Option<usize>,
+    /// What was typed before browsing; only lines starting with it are offered
+    draft: String,
+    path: Option<PathBuf>,
+}
+
+impl History {
+    pub fn load() -> Self {
+        let path = config::config_dir().join("history.txt");
+        let lines = fs::read_to_string(&path).unwrap_or_default().lines().map(str::to_string).collect();
+        History { lines, path: Some(path), ..Default::default() }
+    }
+
+    fn save(&self) {
+        let Some(path) = &self.path else { return };
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        let _ = fs::write(path, self.lines.join("\n") + "\n");
+    }
+
+    /// Record a line that was run, moving a repeat to the end
+    pub fn push(&mut self, line: &str) {
+        self.cursor = None;
+        let line = line.trim();
+        if line.is_empty() {
+            return;
+        }
+        self.lines.retain(|l| l != line);
+        self.lines.push(line.to_string());
+        let excess = self.lines.len().saturating_sub(MAX_LINES);
+        self.lines.drain(..excess);
+        self.save();
+    }
+
+    /// Add lines from a project, oldest first, without losing the ones already here
+    pub fn merge(&mut self, lines: &[String]) {
+        let mut merged: Vec<String> = lines.iter().filter(|l| !self.lines.contains(l)).cloned().collect();
+        merged.append(&mut self.lines);
+        self.lines = merged;
+        let excess = self.lines.len().saturating_sub(MAX_LINES);
+        self.lines.drain(..excess);
+        self.save();
+    }
+
+    /// Previous line starting with what was typed; None at the oldest
+    pub fn older(&mut self, typed: &str) -> Option<String> {
+        if self.cursor.is_none() {
+            self.draft = typed.to_string();
+        }
+        let end = self.cursor.unwrap_or(self.lines.len());
+        let i = self.lines[..end].iter().rposition(|l| l.starts_with(&self.draft))?;
+        self.cursor = Some(i);
+        Some(self.lines[i].clone())
+    }
+
+    /// Next line starting with what was typed, or the typed text itself past the newest
+    pub fn newer(&mut self) -> Option<String> {
+        let from = self.cursor? + 1;
+        match self.lines[from..].iter().position(|l| l.starts_with(&self.draft)) {
+            Some(i) => {
+                self.cursor = Some(from + i);
+                Some(self.lines[from + i].clone())
+            }
+            None => {
+                self.cursor = None;
+                Some(self.draft.clone())
+            }
+        }
+    }
+
+    /// Stop browsing, as when the line is run or abandoned
+    pub fn reset(&mut self) {
+        self.cursor = None;
+    }
+}
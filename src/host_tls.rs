@@ -0,0 +1,181 @@
+// Per-host settings for belch's TLS connections upstream: protocol version, ALPN, SNI, cipher
+// suites and browser-like suite order, changed at runtime with `:tls` to probe a server or get
+// along with a picky one
+
+use std::sync::{Arc, Mutex, OnceLock};
+
+use rustls::crypto::ring;
+use rustls::{ClientConfig, RootCertStore, SupportedProtocolVersion};
+
+use crate::app::App;
+use crate::config;
+
+/// Browser ClientHellos' cipher suite order, limited to the suites the TLS library has. Extension
+/// order, GREASE and the browsers' CBC suites stay the library's, so this only brings JA3/JA4
+/// closer to the browser's rather than matching it
+const PROFILES: &[(&str, &[&str])] = &[
+    ("chrome", &[
+        "TLS13_AES_128_GCM_SHA256", "TLS13_AES_256_GCM_SHA384", "TLS13_CHACHA20_POLY1305_SHA256",
+        "TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256", "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256",
+        "TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384", "TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384",
+        "TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256", "TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256",
+    ]),
+    ("firefox", &[
+        "TLS13_AES_128_GCM_SHA256", "TLS13_CHACHA20_POLY1305_SHA256", "TLS13_AES_256_GCM_SHA384",
+        "TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256", "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256",
+        "TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256", "TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256",
+        "TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384", "TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384",
+    ]),
+    ("safari", &[
+        "TLS13_AES_128_GCM_SHA256", "TLS13_AES_256_GCM_SHA384", "TLS13_CHACHA20_POLY1305_SHA256",
+        "TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384", "TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256",
+        "TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256", "TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384",
+        "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256", "TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256",
+    ]),
+];
+
+pub struct HostTls {
+    /// Host pattern, as in the scope list
+    pub host: String,
+    version: Option<&'static SupportedProtocolVersion>,
+    /// None offers `http/1.1` as usual; empty offers nothing
+    alpn: Option<Vec<String>>,
+    sni: bool,
+    /// Cipher suite names kept, e.g. `TLS13_AES_128_GCM_SHA256`; empty keeps them all
+    ciphers: Vec<String>,
+    /// The browser whose cipher suite order is offered
+    profile: Option<&'static str>,
+    config: Arc<ClientConfig>,
+}
+
+impl HostTls {
+    /// `HOST [1.2|1.3] [alpn=P,P|alpn=none] [nosni] [ciphers=PART+PART] [profile=BROWSER]`; a
+    /// cipher part keeps every suite whose name contains it, offered in the order of the parts
+    pub fn parse(host: &str, args: &[&str]) -> Result<Self, String> {
+        let mut version = None;
+        let mut alpn = None;
+        let mut sni = true;
+        let mut ciphers = Vec::new();
+        let mut profile = None;
+        let mut provider = ring::default_provider();
+        for arg in args {
+            match arg.split_once('=') {
+                None if *arg == "1.2" => version = Some(&rustls::version::TLS12),
+                None if *arg == "1.3" => version = Some(&rustls::version::TLS13),
+                None if matches!(*arg, "1.0" | "1.1") => return Err("TLS 1.0 and 1.1 aren't supported: the TLS library only speaks 1.2 and 1.3".into()),
+                None if *arg == "nosni" => sni = false,
+                Some(("alpn", "none")) => alpn = Some(Vec::new()),
+                Some(("alpn", list)) => alpn = Some(list.split(',').filter(|p| !p.is_empty()).map(str::to_string).collect()),
+                Some(("ciphers", parts)) => {
+                    let parts: Vec<String> = parts.split('+').map(str::to_uppercase).collect();
+                    ciphers.clear();
+                    for part in &parts {
+                        for name in provider.cipher_suites.iter().filter_map(|s| s.suite().as_str()) {
+                            if name.contains(part.as_str()) && !ciphers.iter().any(|c| c == name) {
+                                ciphers.push(name.to_string());
+                            }
+                        }
+                    }
+                    if ciphers.is_empty() {
+                        let names: Vec<&str> = provider.cipher_suites.iter().filter_map(|s| s.suite().as_str()).collect();
+                        return Err(format!("no cipher suite matches `{}`: {}", parts.join("+"), names.join(" ")));
+                    }
+                }
+                Some(("profile", name)) => match PROFILES.iter().find(|(p, _)| p.eq_ignore_ascii_case(name)) {
+                    Some((p, _)) => profile = Some(*p),
+                    None => return Err(format!("no browser profile `{}`: {}", name, PROFILES.iter().map(|(p, _)| *p).collect::<Vec<_>>().join(" "))),
+                },
+                _ => return Err(format!("unknown TLS setting `{}`: 1.2, 1.3, alpn=P,P, alpn=none, nosni, ciphers=PART+PART, profile=BROWSER", arg)),
+            }
+        }
+        if !ciphers.is_empty() {
+            provider.cipher_suites.retain(|s| s.suite().as_str().is_some_and(|n| ciphers.iter().any(|c| c == n)));
+        }
+        // The browser's order wins over the parts' order; suites it lacks keep their place after it
+        let order: Vec<&str> = match profile.and_then(|p| PROFILES.iter().find(|(name, _)| *name == p)) {
+            Some((_, suites)) => suites.to_vec(),
+            None => ciphers.iter().map(String::as_str).collect(),
+        };
+        provider.cipher_suites.sort_by_key(|s| s.suite().as_str().and_then(|n| order.iter().position(|o| *o == n)).unwrap_or(order.len()));
+        let versions: Vec<&'static SupportedProtocolVersion> = match version {
+            Some(v) => vec![v],
+            None => rustls::DEFAULT_VERSIONS.to_vec(),
+        };
+        let roots = RootCertStore { roots: webpki_roots::TLS_SERVER_ROOTS.to_vec() };
+        let mut config = ClientConfig::builder_with_provider(Arc::new(provider))
+            .with_protocol_versions(&versions)
+            .map_err(|e| format!("those settings can't work together: {}", e))?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        config.alpn_protocols = match &alpn {
+            Some(list) => list.iter().map(|p| p.as_bytes().to_vec()).collect(),
+            None => vec![b"http/1.1".to_vec()],
+        };
+        config.enable_sni = sni;
+        Ok(HostTls { host: host.to_lowercase(), version, alpn, sni, ciphers, profile, config: Arc::new(config) })
+    }
+
+    pub fn label(&self) -> String {
+        let mut parts = vec![self.host.clone()];
+        parts.push(match self.version {
+            Some(v) if v.version == rustls::ProtocolVersion::TLSv1_2 => "TLS 1.2 only".to_string(),
+            Some(_) => "TLS 1.3 only".to_string(),
+            None => "TLS 1.2-1.3".to_string(),
+        });
+        match &self.alpn {
+            Some(list) if list.is_empty() => parts.push("no ALPN".to_string()),
+            Some(list) => parts.push(format!("ALPN {}", list.join(","))),
+            None => {}
+        }
+        if !self.sni {
+            parts.push("no SNI".to_string());
+        }
+        if !self.ciphers.is_empty() {
+            parts.push(format!("{} cipher suite(s)", self.ciphers.len()));
+        }
+        if let Some(profile) = self.profile {
+            parts.push(format!("{}-like suite order", profile));
+        }
+        parts.join(", ")
+    }
+}
+
+static HOSTS: OnceLock<Mutex<Vec<HostTls>>> = OnceLock::new();
+
+fn hosts() -> &'static Mutex<Vec<HostTls>> {
+    HOSTS.get_or_init(Default::default)
+}
+
+/// The client config for connections to `host`, when it has settings of its own
+pub fn config_for(host: &str) -> Option<(Arc<ClientConfig>, String)> {
+    hosts().lock().unwrap().iter()
+        .find(|s| config::host_matches(std::slice::from_ref(&s.host), host))
+        .map(|s| (s.config.clone(), s.label()))
+}
+
+/// `:tls HOST SETTINGS…` sets how belch connects to HOST, `:tls HOST default` drops that,
+/// `:tls` lists the hosts with settings
+pub fn command(app: &mut App, args: &[&str]) {
+    let mut list = hosts().lock().unwrap();
+    match args {
+        [] if list.is_empty() => app.set_status("No TLS overrides; :tls HOST [1.2|1.3] [alpn=P,P|alpn=none] [nosni] [ciphers=PART+PART] [profile=BROWSER]"),
+        [] => app.set_status(list.iter().map(HostTls::label).collect::<Vec<_>>().join("   ")),
+        [host, "default"] => {
+            let before = list.len();
+            list.retain(|s| !s.host.eq_ignore_ascii_case(host));
+            app.set_status(if list.len() < before { format!("{} back to the default TLS settings", host) } else { format!("{} has no TLS overrides", host) });
+        }
+        [host, settings @ ..] if !settings.is_empty() => match HostTls::parse(host, settings) {
+            Ok(settings) => {
+                let label = settings.label();
+                let h2 = settings.alpn.as_ref().is_some_and(|a| a.iter().any(|p| p == "h2"));
+                list.retain(|s| s.host != settings.host);
+                list.push(settings);
+                let note = if h2 { " (belch still speaks HTTP/1.1 if the server picks h2)" } else { "" };
+                app.set_status(format!("TLS to {}{}", label, note));
+            }
+            Err(e) => app.set_status(format!("tls: {}", e)),
+        },
+        _ => app.set_status("usage: :tls HOST [1.2|1.3] [alpn=P,P|alpn=none] [nosni] [ciphers=PART+PART] [profile=BROWSER] | :tls HOST default"),
+    }
+}
@@ -0,0 +1,89 @@
+// Per-host traffic summary for the Hosts tab
+
+use std::collections::{BTreeMap, VecDeque};
+use std::time::Duration;
+
+use crate::app::HttpLog;
+
+pub struct HostStats {
+    pub host: String,
+    pub requests: usize,
+    /// Request bytes sent, as captured on the wire
+    pub bytes_out: usize,
+    pub bytes_in: usize,
+    /// Failed exchanges and 4xx/5xx answers
+    pub errors: usize,
+    pub median: Duration,
+}
+
+/// Column the Hosts tab is sorted by; numbers sort largest first, names A to Z
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum HostSort {
+    #[default]
+    Requests,
+    BytesOut,
+    BytesIn,
+    Errors,
+    Latency,
+    Host,
+}
+
+impl HostSort {
+    pub const ALL: [HostSort; 6] = [
+        HostSort::Requests,
+        HostSort::BytesOut,
+        HostSort::BytesIn,
+        HostSort::Errors,
+        HostSort::Latency,
+        HostSort::Host,
+    ];
+    pub fn title(self) -> &'static str {
+        match self {
+            HostSort::Requests => "requests",
+            HostSort::BytesOut => "bytes out",
+            HostSort::BytesIn => "bytes in",
+            HostSort::Errors => "errors",
+            HostSort::Latency => "latency",
+            HostSort::Host => "host",
+        }
+    }
+    pub fn next(self) -> HostSort {
+        let i = HostSort::ALL.iter().position(|c| *c == self).unwrap_or(0);
+        HostSort::ALL[(i + 1) % HostSort::ALL.len()]
+    }
+}
+
+/// One row per host, sorted by `sort`
+pub fn summarize(logs: &VecDeque<HttpLog>, sort: HostSort, reversed: bool) -> Vec<HostStats> {
+    let mut by_host: BTreeMap<&str, Vec<&HttpLog>> = BTreeMap::new();
+    for log in logs.iter().filter(|l| !l.host.is_empty()) {
+        by_host.entry(&log.host).or_default().push(log);
+    }
+    let mut stats: Vec<HostStats> = by_host.into_iter().map(|(host, logs)| {
+        // Tunnels have no request of their own to time or judge
+        let exchanges: Vec<&&HttpLog> = logs.iter().filter(|l| !l.request_raw.is_empty()).collect();
+        let mut durations: Vec<Duration> = exchanges.iter().map(|l| l.duration).collect();
+        durations.sort();
+        HostStats {
+            host: host.to_string(),
+            requests: logs.len(),
+            bytes_out: logs.iter().map(|l| l.request_raw.len()).sum(),
+            bytes_in: logs.iter().map(|l| l.response_raw.len()).sum(),
+            errors: exchanges.iter().filter(|l| l.status().is_none_or(|s| s >= 400)).count(),
+            median: durations.get(durations.len() / 2).copied().unwrap_or_default(),
+        }
+    }).collect();
+    // Stable sorts keep hosts in name order among equal keys
+    match sort {
+        HostSort::Requests => stats.sort_by_key(|s| std::cmp::Reverse(s.requests)),
+        HostSort::BytesOut => stats.sort_by_key(|s| std::cmp::Reverse(s.bytes_out)),
+        HostSort::BytesIn => stats.sort_by_key(|s| std::cmp::Reverse(s.bytes_in)),
+        HostSort::Errors => stats.sort_by_key(|s| std::cmp::Reverse(s.errors)),
+        HostSort::Latency => stats.sort_by_key(|s| std::cmp::Reverse(s.median)),
+        HostSort::Host => {}
+    }
+    if reversed {
+        stats.reverse();
+    }
+    stats
+}
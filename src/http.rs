@@ -0,0 +1,410 @@
+// Small helpers over captured HTTP message text
+
+use std::io::Read;
+
+use sha2::{Digest, Sha256};
+
+/// Most headers accepted in one message head
+const MAX_HEADERS: usize = 128;
+
+/// Request line and headers borrowed from a raw buffer
+pub struct RequestHead<'a> {
+    pub method: &'a str,
+    pub target: &'a str,
+    pub headers: Vec<(&'a str, &'a [u8])>,
+    /// Bytes up to and including the blank line; None while the head is incomplete
+    pub len: Option<usize>,
+}
+
+impl<'a> RequestHead<'a> {
+    /// Parse without copying; a partial head still yields its request line
+    pub fn parse(buf: &'a [u8]) -> Option<Self> {
+        let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+        let mut req = httparse::Request::new(&mut headers);
+        // Lines httparse can't read, like obs-fold or `Name : value`, are still forwarded as sent
+        let len = match httparse::ParserConfig::default().ignore_invalid_headers_in_requests(true).parse_request(&mut req, buf).ok()? {
+            httparse::Status::Complete(n) => Some(n),
+            httparse::Status::Partial => None,
+        };
+        let (method, target) = (req.method?, req.path?);
+        let headers = match len {
+            Some(_) => req.headers.iter().map(|h| (h.name, h.value)).collect(),
+            None => Vec::new(),
+        };
+        Some(RequestHead { method, target, headers, len })
+    }
+
+    /// First value of header `name`, case-insensitive
+    pub fn header(&self, name: &str) -> Option<&'a [u8]> {
+        self.headers.iter().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| *v)
+    }
+
+    pub fn header_str(&self, name: &str) -> Option<&'a str> {
+        self.header(name).and_then(|v| std::str::from_utf8(v).ok()).map(str::trim)
+    }
+
+    pub fn content_length(&self) -> usize {
+        self.header_str("content-length").and_then(|v| v.parse().ok()).unwrap_or(0)
+    }
+
+    /// Body bytes at the start of `body` that belong to this request; None until a chunked one has ended
+    pub fn body_len(&self, body: &[u8]) -> Option<usize> {
+        match self.header_str("transfer-encoding").is_some_and(|v| v.to_lowercase().contains("chunked")) {
+            true => chunked_len(body),
+            false => Some(self.content_length()),
+        }
+    }
+}
+
+/// Split a message into head and body at the first blank line
+pub fn split_message(text: &str) -> (&str, &str) {
+    match (text.find("\r\n\r\n"), text.find("\n\n")) {
+        (Some(a), Some(b)) if b < a => (&text[..b], &text[b + 2..]),
+        (Some(a), _) => (&text[..a], &text[a + 4..]),
+        (None, Some(b)) => (&text[..b], &text[b + 2..]),
+        (None, None) => (text, ""),
+    }
+}
+
+/// First value of header `name` (case-insensitive) in a message head
+pub fn header_value<'a>(head: &'a str, name: &str) -> Option<&'a str> {
+    head.lines().skip(1).find_map(|l| {
+        let (k, v) = l.split_once(':')?;
+        k.trim().eq_ignore_ascii_case(name).then(|| v.trim())
+    })
+}
+
+/// Split raw bytes into head and body at the first CRLFCRLF
+pub fn split_raw(raw: &[u8]) -> (&[u8], &[u8]) {
+    match raw.windows(4).position(|w| w == b"\r\n\r\n") {
+        Some(i) => (&raw[..i], &raw[i + 4..]),
+        None => (raw, &[]),
+    }
+}
+
+/// Whether a raw response holds its whole body, by Content-Length or the final chunk
+pub fn is_complete(raw: &[u8]) -> bool {
+    let (head, body) = split_raw(raw);
+    let head = String::from_utf8_lossy(head);
+    if header_value(&head, "transfer-encoding").is_some_and(|v| v.to_lowercase().contains("chunked")) {
+        return body.ends_with(b"0\r\n\r\n");
+    }
+    header_value(&head, "content-length").and_then(|v| v.parse::<usize>().ok()).is_some_and(|n| body.len() >= n)
+}
+
+/// An HTTP/1.1 client that did not ask to close will send its next request on the same connection
+pub fn client_keeps_open(request: &[u8]) -> bool {
+    let line_end = request.windows(2).position(|w| w == b"\r\n").unwrap_or(0);
+    let Some(head) = RequestHead::parse(request) else { return false };
+    let close = |name| head.header_str(name).is_some_and(|v| v.to_lowercase().contains("close"));
+    request[..line_end].ends_with(b"HTTP/1.1") && !close("connection") && !close("proxy-connection")
+}
+
+/// `response` as sent on a connection kept open: its `Connection` headers dropped, or None when its body runs to EOF
+pub fn keep_open(response: &[u8], method: &str) -> Option<Vec<u8>> {
+    let (head, body) = split_raw(response);
+    let text = String::from_utf8_lossy(head);
+    let status = status_code(&text)?;
+    let bodiless = method.eq_ignore_ascii_case("HEAD") || status < 200 || status == 204 || status == 304;
+    if !bodiless && !is_complete(response) {
+        return None;
+    }
+    let mut out = Vec::with_capacity(response.len());
+    // Bytes rather than text, so the rest of the head goes through untouched
+    for line in head.split(|b| *b == b'\n').map(|l| l.strip_suffix(b"\r").unwrap_or(l)) {
+        let name = line.split(|b| *b == b':').next().unwrap_or_default().trim_ascii();
+        if [&b"connection"[..], b"keep-alive", b"proxy-connection"].iter().any(|h| name.eq_ignore_ascii_case(h)) {
+            continue;
+        }
+        out.extend_from_slice(line);
+        out.extend_from_slice(b"\r\n");
+    }
+    out.extend_from_slice(b"\r\n");
+    out.extend_from_slice(body);
+    Some(out)
+}
+
+/// Length of the request at the start of `buf`, head and body; None until all of it has arrived
+/// or, for a Content-Length body, until the head has
+pub fn request_len(buf: &[u8]) -> Option<usize> {
+    let head = RequestHead::parse(buf)?;
+    let len = head.len?;
+    head.body_len(&buf[len..]).map(|body| len + body)
+}
+
+/// Length of a chunked body through its last chunk and trailers; None while more is to come
+pub fn chunked_len(body: &[u8]) -> Option<usize> {
+    let line_end = |at: usize| body[at..].windows(2).position(|w| w == b"\r\n").map(|i| at + i);
+    let mut at = 0;
+    loop {
+        let eol = line_end(at)?;
+        let size_line = String::from_utf8_lossy(&body[at..eol]);
+        let Ok(size) = usize::from_str_radix(size_line.split(';').next().unwrap_or("").trim(), 16) else {
+            // Not chunked after all: take what came and let the server judge it
+            return Some(body.len());
+        };
+        at = eol + 2;
+        if size == 0 {
+            // Trailer fields, then the blank line
+            loop {
+                let eol = line_end(at)?;
+                if eol == at {
+                    return Some(at + 2);
+                }
+                at = eol + 2;
+            }
+        }
+        at = at.checked_add(size)?.checked_add(2)?;
+        if at > body.len() {
+            return None;
+        }
+    }
+}
+
+/// Undo chunked transfer-encoding, stopping at the last complete chunk
+pub fn dechunk(mut body: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    while let Some(eol) = body.windows(2).position(|w| w == b"\r\n") {
+        let size_line = String::from_utf8_lossy(&body[..eol]);
+        let size = usize::from_str_radix(size_line.split(';').next().unwrap_or("").trim(), 16).unwrap_or(0);
+        body = &body[eol + 2..];
+        if size == 0 || body.len() < size {
+            out.extend_from_slice(&body[..size.min(body.len())]);
+            break;
+        }
+        out.extend_from_slice(&body[..size]);
+        body = body.get(size + 2..).unwrap_or_default();
+    }
+    out
+}
+
+/// Body with transfer- and content-encoding removed; falls back to the raw bytes on decode errors
+/// and when it would inflate past `MAX_DECODED`
+pub fn decode_body(head: &str, body: &[u8]) -> Vec<u8> {
+    decode_body_bounded(head, body).0
+}
+
+/// `decode_body`, and whether the body was kept raw for inflating past `MAX_DECODED`
+pub fn decode_body_bounded(head: &str, body: &[u8]) -> (Vec<u8>, bool) {
+    let chunked = header_value(head, "transfer-encoding").is_some_and(|v| v.to_lowercase().contains("chunked"));
+    let body = if chunked { dechunk(body) } else { body.to_vec() };
+    let mut out = Vec::new();
+    let ok = match header_value(head, "content-encoding").map(|v| v.trim().to_lowercase()).as_deref() {
+        Some("gzip") | Some("x-gzip") => inflate(flate2::read::MultiGzDecoder::new(&body[..]), &mut out),
+        // Servers disagree on zlib-wrapped vs raw deflate
+        Some("deflate") => inflate(flate2::read::ZlibDecoder::new(&body[..]), &mut out)
+            || inflate(flate2::read::DeflateDecoder::new(&body[..]), &mut out),
+        Some("br") => inflate(brotli_decompressor::Decompressor::new(&body[..], 4096), &mut out),
+        _ => return (body, false),
+    };
+    match ok {
+        _ if out.len() as u64 > MAX_DECODED => (body, true),
+        true => (out, false),
+        false => (body, false),
+    }
+}
+
+/// Most bytes a response body is inflated to; a small compression bomb would otherwise fill memory
+const MAX_DECODED: u64 = 64 << 20;
+
+/// Read `decoder` into `out` up to one byte past `MAX_DECODED`, so going over it shows
+fn inflate(decoder: impl Read, out: &mut Vec<u8>) -> bool {
+    out.clear();
+    decoder.take(MAX_DECODED + 1).read_to_end(out).is_ok()
+}
+
+/// File extension for a Content-Type
+pub fn extension_for(content_type: &str) -> Option<&'static str> {
+    let ct = content_type.split(';').next().unwrap_or("").trim().to_lowercase();
+    Some(match ct.as_str() {
+        "text/html" => "html",
+        "text/plain" => "txt",
+        "text/css" => "css",
+        "text/csv" => "csv",
+        "text/xml" | "application/xml" => "xml",
+        "application/json" => "json",
+        "application/javascript" | "text/javascript" | "application/x-javascript" => "js",
+        "application/pdf" => "pdf",
+        "application/zip" => "zip",
+        "application/gzip" => "gz",
+        "application/wasm" => "wasm",
+        "application/octet-stream" => "bin",
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        "image/x-icon" | "image/vnd.microsoft.icon" => "ico",
+        "image/avif" => "avif",
+        "font/woff" => "woff",
+        "font/woff2" => "woff2",
+        "font/ttf" => "ttf",
+        "audio/mpeg" => "mp3",
+        "video/mp4" => "mp4",
+        _ if ct.ends_with("+json") => "json",
+        _ if ct.ends_with("+xml") => "xml",
+        _ => return None,
+    })
+}
+
+/// Decode `%XX` escapes and `+` as space
+pub fn url_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() && bytes[i + 1].is_ascii_hexdigit() && bytes[i + 2].is_ascii_hexdigit() => {
+                let hex = |b: u8| (b as char).to_digit(16).unwrap_or(0) as u8;
+                out.push(hex(bytes[i + 1]) << 4 | hex(bytes[i + 2]));
+                i += 2;
+            }
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+/// `%XX` escapes for everything but unreserved characters
+pub fn url_encode(s: &str) -> String {
+    s.bytes().map(|b| match b {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+        b => format!("%{:02X}", b),
+    }).collect()
+}
+
+/// `a=1&b=2` pairs, decoded
+pub fn parse_query(s: &str) -> Vec<(String, String)> {
+    s.split('&')
+        .filter(|p| !p.is_empty())
+        .map(|p| {
+            let (k, v) = p.split_once('=').unwrap_or((p, ""));
+            (url_decode(k), url_decode(v))
+        })
+        .collect()
+}
+
+/// Origin-form path of a request target, dropping any `scheme://authority` prefix
+pub fn origin_path(target: &str) -> &str {
+    match target.split_once("://") {
+        Some((_, rest)) => rest.find('/').map_or("/", |i| &rest[i..]),
+        None => target,
+    }
+}
+
+/// Host and port text of `host[:port]` or `[IPv6][:port]`, the host without brackets
+pub fn split_authority(authority: &str) -> (&str, Option<&str>) {
+    if let Some((host, rest)) = authority.strip_prefix('[').and_then(|a| a.split_once(']')) {
+        return (host, rest.strip_prefix(':'));
+    }
+    match authority.rsplit_once(':') {
+        // More than one colon: a bare IPv6 address
+        Some((host, port)) if !host.contains(':') => (host, Some(port)),
+        _ => (authority, None),
+    }
+}
+
+/// `host` as written in a URL or Host header, IPv6 addresses in brackets
+pub fn url_host(host: &str) -> String {
+    match host.contains(':') && !host.starts_with('[') {
+        true => format!("[{}]", host),
+        false => host.to_string(),
+    }
+}
+
+/// Raw request with header `name` replaced, or added when missing
+pub fn set_header(request: &[u8], name: &str, value: &str) -> Vec<u8> {
+    let (head, body) = split_raw(request);
+    let head = String::from_utf8_lossy(head);
+    let prefix = format!("{}:", name.to_lowercase());
+    let mut out: String = head.split("\r\n")
+        .filter(|l| !l.to_lowercase().starts_with(&prefix))
+        .map(|l| format!("{}\r\n", l))
+        .collect();
+    out.push_str(&format!("{}: {}\r\n\r\n", name, value));
+    let mut bytes = out.into_bytes();
+    bytes.extend_from_slice(body);
+    bytes
+}
+
+/// Raw request without any header `name`; other lines are kept byte for byte
+pub fn remove_header(request: &[u8], name: &str) -> Vec<u8> {
+    let (head, body) = split_raw(request);
+    let line_end = head.iter().position(|b| *b == b'\n').map_or(head.len(), |i| i + 1);
+    let mut out = head[..line_end].to_vec();
+    if !out.ends_with(b"\n") {
+        out.extend_from_slice(b"\r\n");
+    }
+    for line in header_lines(head) {
+        if !header_line_name(&line).eq_ignore_ascii_case(name.as_bytes()) {
+            out.extend_from_slice(&line);
+            out.extend_from_slice(b"\r\n");
+        }
+    }
+    out.extend_from_slice(b"\r\n");
+    out.extend_from_slice(body);
+    out
+}
+
+/// The header lines of a raw request, continuation lines kept with the header they continue
+pub fn header_lines(head: &[u8]) -> Vec<Vec<u8>> {
+    let mut headers: Vec<Vec<u8>> = Vec::new();
+    for line in head.split(|b| *b == b'\n').skip(1) {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        match headers.last_mut() {
+            Some(last) if line.first().is_some_and(|b| matches!(b, b' ' | b'\t')) => {
+                last.extend_from_slice(b"\r\n");
+                last.extend_from_slice(line);
+            }
+            _ if line.is_empty() => {}
+            _ => headers.push(line.to_vec()),
+        }
+    }
+    headers
+}
+
+/// The name in a raw header line, without surrounding whitespace
+pub fn header_line_name(line: &[u8]) -> &[u8] {
+    let name = &line[..line.iter().position(|b| *b == b':').unwrap_or(line.len())];
+    let start = name.iter().position(|b| !b.is_ascii_whitespace()).unwrap_or(name.len());
+    let end = name.iter().rposition(|b| !b.is_ascii_whitespace()).map_or(start, |e| e + 1);
+    &name[start..end]
+}
+
+/// Status code from a response head
+pub fn status_code(head: &str) -> Option<u16> {
+    head.lines().next()?.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// Standard reason phrase for common status codes
+pub fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        301 => "Moved Permanently",
+        302 => "Found",
+        304 => "Not Modified",
+        307 => "Temporary Redirect",
+        308 => "Permanent Redirect",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "",
+    }
+}
+
+/// Lowercase hex SHA-256 of `data`
+pub fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|b| format!("{:02x}", b)).collect()
+}
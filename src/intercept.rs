@@ -0,0 +1,163 @@
+// Interception: requests held before forwarding, edited as a header table
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::oneshot;
+
+use crate::app::App;
+use crate::http;
+
+/// IDs for held requests, so an edit finishes on the one it started with
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// A held request and the channel its verdict goes back on
+pub struct Paused {
+    pub id: u64,
+    pub host: String,
+    pub table: HeaderTable,
+    /// Some(request) forwards it, None drops it; dropping the sender forwards the original
+    reply: oneshot::Sender<Option<Vec<u8>>>,
+}
+
+impl Paused {
+    pub fn forward(self) {
+        let _ = self.reply.send(Some(self.table.to_request()));
+    }
+
+    pub fn drop_request(self) {
+        let _ = self.reply.send(None);
+    }
+}
+
+/// Which part of a row is being typed into
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Name,
+    Value,
+}
+
+/// A request as its request line, one row per header, and the body
+pub struct HeaderTable {
+    pub request_line: String,
+    pub rows: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    /// 0 is the request line, then the rows
+    pub selected: usize,
+    pub editing: Option<(Field, String)>,
+}
+
+/// RFC 9110 token characters, as allowed in a header name
+fn is_token(name: &str) -> bool {
+    !name.is_empty() && name.bytes().all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+impl HeaderTable {
+    pub fn parse(request: &[u8]) -> Self {
+        let (head, body) = http::split_raw(request);
+        let head = String::from_utf8_lossy(head);
+        let mut lines = head.split("\r\n");
+        let request_line = lines.next().unwrap_or("").to_string();
+        let rows = lines
+            .filter_map(|l| l.split_once(':'))
+            .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+            .collect();
+        HeaderTable { request_line, rows, body: body.to_vec(), selected: 0, editing: None }
+    }
+
+    /// The edited request, with `Content-Length` matching the body unless it is chunked
+    pub fn to_request(&self) -> Vec<u8> {
+        let chunked = self.rows.iter()
+            .any(|(k, v)| k.eq_ignore_ascii_case("transfer-encoding") && v.to_lowercase().contains("chunked"));
+        let had_length = self.rows.iter().any(|(k, _)| k.eq_ignore_ascii_case("content-length"));
+        let mut head = format!("{}\r\n", self.request_line);
+        for (k, v) in self.rows.iter().filter(|(k, _)| !k.eq_ignore_ascii_case("content-length")) {
+            head.push_str(&format!("{}: {}\r\n", k, v));
+        }
+        if !chunked && (had_length || !self.body.is_empty()) {
+            head.push_str(&format!("Content-Length: {}\r\n", self.body.len()));
+        }
+        head.push_str("\r\n");
+        let mut out = head.into_bytes();
+        out.extend_from_slice(&self.body);
+        out
+    }
+
+    /// Start typing into the selected row's name or value, or the request line
+    pub fn edit(&mut self, field: Field) {
+        let text = match (self.selected, field) {
+            (0, _) => self.request_line.clone(),
+            (i, Field::Name) => self.rows[i - 1].0.clone(),
+            (i, Field::Value) => self.rows[i - 1].1.clone(),
+        };
+        self.editing = Some((if self.selected == 0 { Field::Value } else { field }, text));
+    }
+
+    /// Add an empty row under the selection and start typing its name
+    pub fn add(&mut self) {
+        let at = self.selected.min(self.rows.len());
+        self.rows.insert(at, (String::new(), String::new()));
+        self.selected = at + 1;
+        self.editing = Some((Field::Name, String::new()));
+    }
+
+    pub fn remove(&mut self) {
+        if self.selected > 0 {
+            self.rows.remove(self.selected - 1);
+            self.selected = self.selected.min(self.rows.len());
+        }
+    }
+
+    /// Store what was typed; an error leaves the edit open
+    pub fn commit(&mut self) -> Result<(), String> {
+        let Some((field, text)) = self.editing.take() else { return Ok(()) };
+        // Line breaks would start new headers or end the head
+        let text = text.replace(['\r', '\n'], "");
+        match (self.selected, field) {
+            (0, _) if text.split(' ').count() != 3 => {
+                self.editing = Some((field, text));
+                return Err("The request line is METHOD TARGET VERSION".into());
+            }
+            (0, _) => self.request_line = text,
+            (i, Field::Name) if !is_token(&text) => {
+                self.editing = Some((field, text));
+                return Err(format!("Not a valid header name (row {})", i));
+            }
+            (i, Field::Name) => {
+                let fresh = self.rows[i - 1].0.is_empty();
+                self.rows[i - 1].0 = text;
+                // A new row goes straight on to its value
+                if fresh {
+                    self.editing = Some((Field::Value, String::new()));
+                }
+            }
+            (i, Field::Value) => self.rows[i - 1].1 = text.trim().to_string(),
+        }
+        Ok(())
+    }
+
+    /// Abandon the edit, and the row too if it was just added
+    pub fn cancel(&mut self) {
+        self.editing = None;
+        if self.selected > 0 && self.rows[self.selected - 1].0.is_empty() {
+            self.remove();
+        }
+    }
+}
+
+/// Hold `request` for editing when interception is on and `host` is in scope for the client
+/// connection's listener; None means drop it
+pub async fn hold(app: &Arc<Mutex<App>>, conn: u64, host: &str, request: Vec<u8>) -> Option<Vec<u8>> {
+    let rx = {
+        let mut guard = app.lock().unwrap();
+        let listener = guard.conn_listeners.get(&conn).cloned().unwrap_or_default();
+        if !guard.intercepting || guard.read_only_for(host) || !guard.in_scope(&listener, host) {
+            return Some(request);
+        }
+        let (reply, rx) = oneshot::channel();
+        guard.paused.push_back(Paused { id: NEXT_ID.fetch_add(1, Ordering::Relaxed), host: host.to_string(), table: HeaderTable::parse(&request), reply });
+        rx
+    };
+    // Turning interception off drops the senders, which lets the originals through
+    rx.await.unwrap_or(Some(request))
+}
@@ -0,0 +1,217 @@
+// jq-like paths into JSON bodies: `.user.role`, `.items[0].id`, `.items[].id`, `..id`, with an
+// optional comparison such as `.user.role == "admin"`; JSONPath's leading `$` is accepted too
+
+use serde_json::Value;
+
+use crate::app::HttpLog;
+use crate::http;
+
+enum Step {
+    Key(String),
+    /// Negative counts from the end
+    Index(i64),
+    /// Every element or member
+    All,
+    /// A key at any depth
+    Descend(String),
+}
+
+#[derive(Clone, Copy)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// Substring of a string, element of an array
+    Contains,
+}
+
+/// Longest first, so `<=` isn't read as `<`
+const OPS: [(&str, Op); 7] = [("==", Op::Eq), ("!=", Op::Ne), ("<=", Op::Le), (">=", Op::Ge), ("<", Op::Lt), (">", Op::Gt), ("~", Op::Contains)];
+
+pub struct Query {
+    pub text: String,
+    steps: Vec<Step>,
+    test: Option<(Op, Value)>,
+}
+
+/// Byte offset and operator of the first comparison outside quotes and brackets
+fn find_op(text: &str) -> Option<(usize, &'static str, Op)> {
+    let (mut quote, mut depth) = (None, 0);
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '[') => depth += 1,
+            (None, ']') => depth -= 1,
+            (None, _) if depth == 0 => {
+                if let Some((symbol, op)) = OPS.iter().find(|(s, _)| text[i..].starts_with(s)) {
+                    return Some((i, symbol, *op));
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_steps(path: &str) -> Result<Vec<Step>, String> {
+    let path = path.trim();
+    let path = path.strip_prefix('$').unwrap_or(path);
+    let chars: Vec<char> = path.chars().collect();
+    let name_end = |from: usize| (from..chars.len()).find(|&j| matches!(chars[j], '.' | '[' | ' ')).unwrap_or(chars.len());
+    let mut steps = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '.' if chars.get(i + 1) == Some(&'.') => {
+                let end = name_end(i + 2);
+                let name: String = chars[i + 2..end].iter().collect();
+                if name.is_empty() {
+                    return Err("`..` needs a key after it, like ..id".into());
+                }
+                steps.push(Step::Descend(name));
+                i = end;
+            }
+            '.' => {
+                let end = name_end(i + 1);
+                match chars[i + 1..end].iter().collect::<String>().as_str() {
+                    "" => {}
+                    "*" => steps.push(Step::All),
+                    name => steps.push(Step::Key(name.to_string())),
+                }
+                i = end;
+            }
+            '[' => {
+                let end = (i..chars.len()).find(|&j| chars[j] == ']').ok_or("`[` is never closed")?;
+                let inner: String = chars[i + 1..end].iter().collect();
+                let inner = inner.trim();
+                // Quoted keys open and close with the same quote
+                let quoted = ['"', '\''].into_iter().find_map(|q| inner.strip_prefix(q)?.strip_suffix(q));
+                steps.push(match (inner, quoted) {
+                    ("" | "*", _) => Step::All,
+                    (_, Some(key)) => Step::Key(key.to_string()),
+                    _ => Step::Index(inner.parse().map_err(|_| format!("`[{}]` is not an index or a quoted key", inner))?),
+                });
+                i = end + 1;
+            }
+            c if i == 0 && (c.is_alphanumeric() || c == '_') => {
+                // A bare first key, as in `user.role`
+                let end = name_end(0);
+                steps.push(Step::Key(chars[..end].iter().collect()));
+                i = end;
+            }
+            c => return Err(format!("unexpected `{}` in the path", c)),
+        }
+    }
+    Ok(steps)
+}
+
+impl Query {
+    pub fn parse(text: &str) -> Result<Query, String> {
+        let (path, test) = match find_op(text) {
+            Some((at, symbol, op)) => {
+                let right = text[at + symbol.len()..].trim();
+                // Bare words are strings
+                let value = serde_json::from_str(right).unwrap_or_else(|_| Value::String(right.trim_matches('\'').to_string()));
+                (&text[..at], Some((op, value)))
+            }
+            None => (text, None),
+        };
+        Ok(Query { text: text.trim().to_string(), steps: parse_steps(path)?, test })
+    }
+
+    /// Values the path leads to
+    pub fn select<'a>(&self, root: &'a Value) -> Vec<&'a Value> {
+        let mut current = vec![root];
+        for step in &self.steps {
+            let mut next = Vec::new();
+            for value in current {
+                match (step, value) {
+                    (Step::Key(k), Value::Object(map)) => next.extend(map.get(k)),
+                    (Step::Index(n), Value::Array(items)) => {
+                        let index = if *n < 0 { items.len() as i64 + n } else { *n };
+                        next.extend(usize::try_from(index).ok().and_then(|i| items.get(i)));
+                    }
+                    (Step::All, Value::Array(items)) => next.extend(items),
+                    (Step::All, Value::Object(map)) => next.extend(map.values()),
+                    (Step::Descend(k), value) => descend(value, k, &mut next),
+                    _ => {}
+                }
+            }
+            current = next;
+        }
+        current
+    }
+
+    /// Whether a body passes as a filter: some value compares true, or without a comparison,
+    /// the path leads to something other than null or false
+    pub fn matches(&self, root: &Value) -> bool {
+        let selected = self.select(root);
+        match &self.test {
+            Some((op, expected)) => selected.iter().any(|v| compare(v, *op, expected)),
+            None => selected.iter().any(|v| !matches!(v, Value::Null | Value::Bool(false))),
+        }
+    }
+
+    /// What the detail pane shows: the selected values, or whether the comparison holds
+    pub fn results(&self, root: &Value) -> Vec<Value> {
+        match self.test {
+            Some(_) => vec![Value::Bool(self.matches(root))],
+            None => self.select(root).into_iter().cloned().collect(),
+        }
+    }
+}
+
+fn descend<'a>(value: &'a Value, key: &str, out: &mut Vec<&'a Value>) {
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map {
+                if k == key {
+                    out.push(v);
+                }
+                descend(v, key, out);
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|v| descend(v, key, out)),
+        _ => {}
+    }
+}
+
+fn compare(value: &Value, op: Op, expected: &Value) -> bool {
+    let numbers = value.as_f64().zip(expected.as_f64());
+    let order = match (numbers, value, expected) {
+        (Some((a, b)), _, _) => a.partial_cmp(&b),
+        (None, Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ => None,
+    };
+    match op {
+        Op::Eq => order.map_or(value == expected, |o| o.is_eq()),
+        Op::Ne => order.map_or(value != expected, |o| o.is_ne()),
+        Op::Lt => order.is_some_and(|o| o.is_lt()),
+        Op::Le => order.is_some_and(|o| o.is_le()),
+        Op::Gt => order.is_some_and(|o| o.is_gt()),
+        Op::Ge => order.is_some_and(|o| o.is_ge()),
+        Op::Contains => match (value, expected) {
+            (Value::String(a), Value::String(b)) => a.contains(b.as_str()),
+            (Value::Array(items), b) => items.contains(b),
+            _ => false,
+        },
+    }
+}
+
+/// The entry's response body, when it is JSON
+pub fn response_json(log: &HttpLog) -> Option<Value> {
+    let body = log.response_body();
+    let head = log.response_head();
+    let json_type = http::header_value(&head, "content-type").is_some_and(|t| t.to_lowercase().contains("json"));
+    let text = String::from_utf8_lossy(&body);
+    // Untyped bodies count when they look like JSON
+    if !json_type && !text.trim_start().starts_with(['{', '[']) {
+        return None;
+    }
+    serde_json::from_str(&text).ok()
+}
@@ -0,0 +1,101 @@
+// Rebinding the single-key actions of the main view from the `[keys]` table of config.toml: the
+// key pressed is translated to the built-in key of its action before the usual dispatch
+
+use std::collections::BTreeMap;
+use std::sync::{Mutex, OnceLock};
+
+use crossterm::event::KeyCode;
+
+/// Action names, their built-in keys and what they do
+pub const ACTIONS: &[(&str, char, &str)] = &[
+    ("quit", 'q', "Quit"),
+    ("command", ':', "Open the : command prompt"),
+    ("find", '/', "Show only entries containing some text"),
+    ("newest", 'G', "Jump to the newest entry and follow new ones"),
+    ("send-to", 's', "Send the selected entry to another tool"),
+    ("repeater", 'R', "Open the selected request in the Repeater"),
+    ("evidence", 'm', "Mark or unmark the selected entry as report evidence"),
+    ("intercept", 'I', "Turn interception on or off"),
+    ("zoom", 'z', "Zoom the detail pane"),
+    ("full-body", 'L', "Show the whole body, however long"),
+    ("compare-headers", 'h', "Show request and response headers side by side"),
+    ("html-preview", 'v', "Preview an HTML response as text"),
+    ("select-text", 'V', "Select text in the detail pane"),
+    ("save-body", 'w', "Save the selected body to a file"),
+    ("edit-body", 'E', "Open the selected body in $EDITOR"),
+    ("sort", 'o', "Sort by the next column"),
+    ("reverse-sort", 'O', "Reverse the sort"),
+    ("identical", 'i', "Show only entries with the same response"),
+    ("connection", 'c', "Show only entries on the same connection"),
+    ("chain", 'e', "Expand or fold a redirect chain"),
+    ("sidebar", 'b', "Show or hide the filter sidebar"),
+    ("shrink-list", '[', "Narrow the list"),
+    ("grow-list", ']', "Widen the list"),
+    ("cors-probe", 'X', "Probe the selected host's CORS policy"),
+    ("host-probe", 'H', "Probe the selected host with other Host headers"),
+    ("mine-params", 'M', "Mine hidden parameters of the selected request"),
+    ("passthrough", 'p', "Pass the selected host's TLS through untouched, or stop"),
+    ("passthrough-list", 'P', "Edit the passthrough list"),
+    ("scope", 'S', "Add or remove the selected host from the scope"),
+    ("ca", 'C', "Show the CA certificate and how to install it"),
+    ("cert-decoder", 'K', "Decode certificates found in the selected entry"),
+    ("recon", 'U', "List URLs and domains seen in the traffic"),
+    ("device-setup", 'D', "Set up a phone or emulator to use the proxy"),
+];
+
+/// Pressed key to built-in key, for rebound actions only
+static BINDINGS: OnceLock<Mutex<BTreeMap<char, char>>> = OnceLock::new();
+
+fn bindings() -> &'static Mutex<BTreeMap<char, char>> {
+    BINDINGS.get_or_init(Default::default)
+}
+
+/// Bind actions to keys from `action = "key"` pairs; keys are single characters. Built-in keys
+/// keep working, so taking another action's key needs that action bound elsewhere too.
+pub fn set(keys: &BTreeMap<String, String>) -> Result<(), String> {
+    let mut map = BTreeMap::new();
+    for (action, key) in keys {
+        let Some((_, builtin, _)) = ACTIONS.iter().find(|(name, _, _)| name == action) else {
+            return Err(format!("unknown action `{}` in [keys]: {}", action, ACTIONS.iter().map(|(n, _, _)| *n).collect::<Vec<_>>().join(", ")));
+        };
+        let mut chars = key.chars();
+        let (Some(c), None) = (chars.next(), chars.next()) else {
+            return Err(format!("[keys] {}: `{}` is not a single character", action, key));
+        };
+        if let Some(other) = map.insert(c, *builtin).filter(|b| b != builtin) {
+            let taken = ACTIONS.iter().find(|(_, k, _)| *k == other).map_or("", |(n, _, _)| *n);
+            return Err(format!("[keys]: `{}` is bound to both {} and {}", c, taken, action));
+        }
+    }
+    for (key, builtin) in &map {
+        let Some((shadowed, _, _)) = ACTIONS.iter().find(|(_, k, _)| k == key && k != builtin) else { continue };
+        if !map.values().any(|b| b == key) {
+            let action = ACTIONS.iter().find(|(_, k, _)| k == builtin).map_or("", |(n, _, _)| *n);
+            return Err(format!("[keys] {}: `{}` is {}'s key, which would be left without one; bind {} to another key too", action, key, shadowed, shadowed));
+        }
+    }
+    *bindings().lock().unwrap() = map;
+    Ok(())
+}
+
+/// The bindings that differ from the built-in keys, as `action = "key"` pairs
+pub fn get() -> BTreeMap<String, String> {
+    bindings().lock().unwrap().iter()
+        .filter(|(key, builtin)| key != builtin)
+        .filter_map(|(key, builtin)| ACTIONS.iter().find(|(_, k, _)| k == builtin).map(|(name, _, _)| (name.to_string(), key.to_string())))
+        .collect()
+}
+
+/// The key that runs the action built in on `builtin`; None when another action took it over
+pub fn key(builtin: char) -> Option<char> {
+    let map = bindings().lock().unwrap();
+    map.iter().find(|(_, b)| **b == builtin).map(|(k, _)| *k).or_else(|| (!map.contains_key(&builtin)).then_some(builtin))
+}
+
+/// The built-in key for what `code` is bound to
+pub fn translate(code: KeyCode) -> KeyCode {
+    match code {
+        KeyCode::Char(c) => KeyCode::Char(bindings().lock().unwrap().get(&c).copied().unwrap_or(c)),
+        other => other,
+    }
+}
@@ -0,0 +1,100 @@
+// Proxy listeners: one belch can listen on several addresses, each with a label its entries carry
+// and settings of its own, to serve several test setups at once
+
+use crate::app::App;
+use crate::config;
+
+pub struct Listener {
+    pub label: String,
+    pub addr: String,
+    /// Off: HTTPS is tunneled untouched, as for passthrough hosts
+    pub mitm: bool,
+    /// Hosts in scope for traffic on this listener; empty uses the global scope
+    pub scope: Vec<String>,
+}
+
+impl Listener {
+    /// `[LABEL=]ADDR[,nomitm][,scope=HOST+HOST…]`, ADDR being `HOST:PORT` or `unix:PATH`; the
+    /// label defaults to the port or the socket's file name
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut parts = spec.split(',');
+        let first = parts.next().unwrap_or("");
+        let (label, addr) = match first.split_once('=') {
+            Some((label, addr)) => (label.to_string(), addr.to_string()),
+            None => (first.rsplit([':', '/']).next().unwrap_or(first).to_string(), first.to_string()),
+        };
+        if label.is_empty() || !addr.contains(':') {
+            return Err(format!("`{}` is not [LABEL=]HOST:PORT or [LABEL=]unix:PATH", first));
+        }
+        let mut listener = Listener { label, addr, mitm: true, scope: Vec::new() };
+        for option in parts {
+            match option.split_once('=') {
+                None if option == "nomitm" => listener.mitm = false,
+                Some(("scope", hosts)) => listener.scope = hosts.split('+').filter(|h| !h.is_empty()).map(str::to_lowercase).collect(),
+                _ => return Err(format!("unknown listener option `{}`: nomitm, scope=HOST+HOST", option)),
+            }
+        }
+        Ok(listener)
+    }
+
+    /// Every spec in turn; labels must differ, since entries and `:listener` go by them
+    pub fn parse_all(specs: &[String]) -> Result<Vec<Self>, String> {
+        let mut out: Vec<Listener> = Vec::new();
+        for spec in specs {
+            let listener = Listener::parse(spec)?;
+            if let Some(other) = out.iter().find(|l| l.label == listener.label) {
+                return Err(format!("{} and {} share the label `{}`: name them with LABEL=ADDR", other.addr, listener.addr, listener.label));
+            }
+            out.push(listener);
+        }
+        Ok(out)
+    }
+
+    /// The spec `parse` reads back
+    pub fn spec(&self) -> String {
+        let mut spec = format!("{}={}", self.label, self.addr);
+        if !self.mitm {
+            spec.push_str(",nomitm");
+        }
+        if !self.scope.is_empty() {
+            spec.push_str(&format!(",scope={}", self.scope.join("+")));
+        }
+        spec
+    }
+
+    /// Whether `host` is in this listener's own scope, or None when it has none
+    pub fn covers(&self, host: &str) -> Option<bool> {
+        (!self.scope.is_empty()).then(|| config::host_matches(&self.scope, host))
+    }
+}
+
+/// `:listener LABEL` shows only entries that arrived on one listener, `:listener all` clears it;
+/// `:listener` lists them
+pub fn command(app: &mut App, args: &[&str]) {
+    match args {
+        [] => {
+            let text: Vec<String> = app.listeners.iter().map(|l| {
+                let count = app.logs.iter().filter(|e| e.listener == l.label).count();
+                let mut text = format!("{} {} ({})", l.label, l.addr, count);
+                if !l.mitm {
+                    text.push_str(" no MITM");
+                }
+                if !l.scope.is_empty() {
+                    text.push_str(&format!(" scope {}", l.scope.join("+")));
+                }
+                text
+            }).collect();
+            app.set_status(text.join("   "));
+        }
+        ["all"] => app.keep_selection(|a| a.listener_filter = None),
+        [label] if app.listeners.iter().any(|l| l.label == *label) || app.logs.iter().any(|e| e.listener == *label) => {
+            let label = label.to_string();
+            app.keep_selection(|a| a.listener_filter = Some(label));
+        }
+        [label] => {
+            let labels: Vec<&str> = app.listeners.iter().map(|l| l.label.as_str()).collect();
+            app.set_status(format!("No listener {}: {}|all", label, labels.join("|")));
+        }
+        _ => app.set_status("usage: :listener [LABEL|all]"),
+    }
+}
@@ -0,0 +1,369 @@
+// Recorded request sequences, such as a login, replayed to get a fresh session
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::app::{self, App, HttpLog, Origin};
+use crate::{config, csrf, throttle};
+use crate::error_page::Failure;
+use crate::http;
+use crate::send;
+
+/// Extensions of static files, left out while recording
+const STATIC: [&str; 10] = [".js", ".css", ".png", ".jpg", ".gif", ".svg", ".ico", ".woff", ".woff2", ".map"];
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Step {
+    pub host: String,
+    pub port: u16,
+    pub tls: bool,
+    /// Replayable request
+    pub request: String,
+    /// Response head and decoded body as recorded, for defining extractions
+    pub response: String,
+}
+
+impl Step {
+    /// A captured exchange worth replaying; None for tunnels, WebSockets and static files
+    pub fn from_log(log: &HttpLog) -> Option<Self> {
+        if log.request_raw.is_empty() || log.websocket {
+            return None;
+        }
+        let path = log.url.split('?').next().unwrap_or("").to_lowercase();
+        if STATIC.iter().any(|ext| path.ends_with(ext)) {
+            return None;
+        }
+        let port = send::target_port(log);
+        let response = format!("{}\r\n\r\n{}", log.response_head(), String::from_utf8_lossy(&log.response_body()));
+        Some(Step {
+            host: log.host.clone(),
+            port,
+            tls: log.tls,
+            request: String::from_utf8_lossy(&send::replayable(log)).to_string(),
+            response,
+        })
+    }
+
+    fn label(&self) -> String {
+        self.request.lines().next().unwrap_or("").to_string()
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Extraction {
+    pub name: String,
+    /// Step whose response holds the value, from 1
+    pub step: usize,
+    /// `regex` (first group, or the whole match) or `json` (a JSON pointer into the body)
+    pub kind: String,
+    pub pattern: String,
+    /// Value when recorded; later requests have it swapped for the fresh one
+    pub recorded: String,
+}
+
+impl Extraction {
+    fn find(&self, response: &str) -> Option<String> {
+        match self.kind.as_str() {
+            "regex" => {
+                let caps = Regex::new(&self.pattern).ok()?.captures(response)?;
+                Some(caps.get(1).or_else(|| caps.get(0))?.as_str().to_string())
+            }
+            "json" => {
+                let (_, body) = http::split_message(response);
+                match serde_json::from_str::<serde_json::Value>(body).ok()?.pointer(&self.pattern)? {
+                    serde_json::Value::String(s) => Some(s.clone()),
+                    other => Some(other.to_string()),
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Macro {
+    pub name: String,
+    pub steps: Vec<Step>,
+    #[serde(default)]
+    pub extractions: Vec<Extraction>,
+}
+
+fn path() -> PathBuf {
+    config::config_dir().join("macros.json")
+}
+
+/// Saved macros from `macros.json`
+pub fn load() -> Vec<Macro> {
+    fs::read_to_string(path()).ok().and_then(|s| serde_json::from_str(&s).ok()).unwrap_or_default()
+}
+
+fn save(macros: &[Macro]) -> Result<(), String> {
+    let path = path();
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    let json = serde_json::to_string_pretty(macros).map_err(|e| e.to_string())?;
+    fs::write(&path, json).map_err(|e| format!("Could not write {}: {}", path.display(), e))
+}
+
+/// What a macro run produced, applied to later requests
+#[derive(Clone, Default)]
+pub struct Session {
+    /// From `Set-Cookie`, latest value per name
+    pub cookies: Vec<(String, String)>,
+    /// Recorded values and their fresh replacements
+    pub replace: Vec<(String, String)>,
+}
+
+fn replace_bytes(data: &[u8], from: &[u8], to: &[u8]) -> Vec<u8> {
+    if from.is_empty() {
+        return data.to_vec();
+    }
+    let mut out = Vec::with_capacity(data.len());
+    let mut i = 0;
+    while i < data.len() {
+        if data[i..].starts_with(from) {
+            out.extend_from_slice(to);
+            i += from.len();
+        } else {
+            out.push(data[i]);
+            i += 1;
+        }
+    }
+    out
+}
+
+impl Session {
+    /// `request` with fresh values swapped in, the session's cookies set and `Content-Length` fixed up
+    pub fn apply(&self, request: &[u8]) -> Vec<u8> {
+        let mut out = request.to_vec();
+        for (from, to) in &self.replace {
+            out = replace_bytes(&out, from.as_bytes(), to.as_bytes());
+        }
+        if !self.cookies.is_empty() {
+            let existing = http::RequestHead::parse(&out).and_then(|h| h.header_str("cookie")).unwrap_or("").to_string();
+            let mut pairs: Vec<(String, String)> = existing.split(';')
+                .filter_map(|p| p.trim().split_once('='))
+                .filter(|(k, _)| !self.cookies.iter().any(|(n, _)| n == k))
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            pairs.extend(self.cookies.iter().cloned());
+            let header: Vec<String> = pairs.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+            out = http::set_header(&out, "Cookie", &header.join("; "));
+        }
+        let len = http::split_raw(&out).1.len();
+        if len > 0 || http::RequestHead::parse(&out).is_some_and(|h| h.header("content-length").is_some()) {
+            out = http::set_header(&out, "Content-Length", &len.to_string());
+        }
+        out
+    }
+
+    fn absorb_cookies(&mut self, head: &str) {
+        for line in head.lines().skip(1) {
+            let Some((name, value)) = line.split_once(':') else { continue };
+            if !name.eq_ignore_ascii_case("set-cookie") {
+                continue;
+            }
+            let Some((k, v)) = value.trim().split(';').next().and_then(|p| p.split_once('=')) else { continue };
+            self.cookies.retain(|(n, _)| n != k.trim());
+            self.cookies.push((k.trim().to_string(), v.trim().to_string()));
+        }
+    }
+}
+
+/// The server no longer accepts the session: 401, 419 or 440, or a redirect to a login page
+pub fn expired(response: &[u8]) -> bool {
+    let head = String::from_utf8_lossy(http::split_raw(response).0);
+    match http::status_code(&head) {
+        Some(401 | 419 | 440) => true,
+        Some(300..=399) => http::header_value(&head, "location")
+            .is_some_and(|l| ["login", "signin", "sign-in", "logon"].iter().any(|w| l.to_lowercase().contains(w))),
+        _ => false,
+    }
+}
+
+/// Replay every step, carrying cookies and extracted values forward; returns the session and step statuses
+pub async fn run(app: &Arc<Mutex<App>>, m: &Macro) -> Result<(Session, Vec<u16>), String> {
+    let mut session = Session::default();
+    let mut statuses = Vec::new();
+    for (n, step) in m.steps.iter().enumerate().map(|(n, s)| (n + 1, s)) {
+        let via = app.lock().unwrap().routes.route(&step.host).cloned();
+        let request = session.apply(step.request.as_bytes());
+        let raw = send::send(&step.host, step.port, step.tls, &request, via.as_ref()).await
+            .map_err(|(_, e)| format!("step {} ({}): {}", n, step.label(), e))?;
+        let (head, body) = http::split_raw(&raw);
+        let head = String::from_utf8_lossy(head).to_string();
+        let response = format!("{}\r\n\r\n{}", head, String::from_utf8_lossy(&http::decode_body(&head, body)));
+        statuses.push(http::status_code(&head).unwrap_or(0));
+        session.absorb_cookies(&head);
+        for x in m.extractions.iter().filter(|x| x.step == n) {
+            let fresh = x.find(&response).ok_or_else(|| format!("step {}: `{}` not found in the response", n, x.name))?;
+            session.replace.retain(|(from, _)| *from != x.recorded);
+            session.replace.push((x.recorded.clone(), fresh));
+        }
+    }
+    info!("macro {} replayed: {:?}", m.name, statuses);
+    Ok((session, statuses))
+}
+
+/// Send a captured request with the auto macro's session and a fresh CSRF token, replaying the macro
+/// once if the session has expired; the exchange is logged as coming from `origin`
+pub async fn send_with_session(app: &Arc<Mutex<App>>, log: &HttpLog, request: &[u8], origin: Origin) -> Result<Vec<u8>, (Failure, String)> {
+    let _permit = throttle::acquire(&log.host).await;
+    let started = Instant::now();
+    let (sent, result) = exchange(app, log, request).await;
+    let entry = HttpLog::sent(log, &sent, result.as_deref().map_err(|(_, e)| e.as_str()), started.elapsed(), origin);
+    app::capture(app, entry);
+    result
+}
+
+/// The request as finally sent, and its answer
+async fn exchange(app: &Arc<Mutex<App>>, log: &HttpLog, request: &[u8]) -> (Vec<u8>, Result<Vec<u8>, (Failure, String)>) {
+    let (via, auto, session) = {
+        let guard = app.lock().unwrap();
+        (guard.routes.route(&log.host).cloned(), guard.auto_macro.clone(), guard.macro_session.clone())
+    };
+    let port = send::target_port(log);
+    let prepared = match (&auto, &session) {
+        (Some(_), Some(s)) => s.apply(request),
+        _ => request.to_vec(),
+    };
+    let prepared = csrf::refresh(app, log, port, prepared, via.as_ref()).await;
+    let raw = match send::send(&log.host, port, log.tls, &prepared, via.as_ref()).await {
+        Ok(raw) => raw,
+        Err(e) => return (prepared, Err(e)),
+    };
+    let Some(m) = auto.filter(|_| expired(&raw)).and_then(|name| load().into_iter().find(|m| m.name == name)) else { return (prepared, Ok(raw)) };
+    // Concurrent senders that also saw the expiry wait for the one replaying
+    if std::mem::replace(&mut app.lock().unwrap().macro_running, true) {
+        return (prepared, Ok(raw));
+    }
+    let result = run(app, &m).await;
+    let fresh = {
+        let mut guard = app.lock().unwrap();
+        guard.macro_running = false;
+        match result {
+            Ok((fresh, statuses)) => {
+                guard.set_status(format!("Session expired; macro {} replayed ({} steps)", m.name, statuses.len()));
+                guard.macro_session = Some(fresh.clone());
+                fresh
+            }
+            Err(e) => {
+                guard.set_status(format!("Session expired; macro {} failed: {}", m.name, e));
+                return (prepared, Ok(raw));
+            }
+        }
+    };
+    let prepared = csrf::refresh(app, log, port, fresh.apply(request), via.as_ref()).await;
+    let result = send::send(&log.host, port, log.tls, &prepared, via.as_ref()).await;
+    (prepared, result)
+}
+
+/// `:macro …`: record, edit, run and pick the macro used when a session expires
+pub fn command(guard: &mut App, app: Arc<Mutex<App>>, args: &[&str]) {
+    const USAGE: &str = "usage: :macro list|record NAME|stop|add NAME|extract NAME STEP VAR regex|json PATTERN|run NAME|auto NAME|off|delete NAME";
+    let mut macros = load();
+    let find = |macros: &mut Vec<Macro>, name: &str| macros.iter().position(|m| m.name == name);
+    let result: Result<String, String> = match args {
+        [] | ["list"] => Ok(match macros.is_empty() {
+            true => "No macros; :macro record NAME, then log in through the proxy".to_string(),
+            false => macros.iter().map(|m| format!("{} ({} steps)", m.name, m.steps.len())).collect::<Vec<_>>().join(", "),
+        }),
+        ["record", name] => {
+            guard.macro_recording = Some(Macro { name: name.to_string(), steps: Vec::new(), extractions: Vec::new() });
+            Ok(format!("Recording macro {}; :macro stop when done", name))
+        }
+        ["stop"] => match guard.macro_recording.take() {
+            Some(m) if m.steps.is_empty() => Err("Nothing recorded".to_string()),
+            Some(m) => {
+                let msg = format!("Saved macro {} ({} steps)", m.name, m.steps.len());
+                match find(&mut macros, &m.name) {
+                    Some(i) => macros[i] = m,
+                    None => macros.push(m),
+                }
+                save(&macros).map(|_| msg)
+            }
+            None => Err("Not recording".to_string()),
+        },
+        ["add", name] => match guard.selected_log().and_then(Step::from_log) {
+            Some(step) => {
+                let i = find(&mut macros, name).unwrap_or_else(|| {
+                    macros.push(Macro { name: name.to_string(), steps: Vec::new(), extractions: Vec::new() });
+                    macros.len() - 1
+                });
+                macros[i].steps.push(step);
+                let msg = format!("Added step {} to macro {}", macros[i].steps.len(), name);
+                save(&macros).map(|_| msg)
+            }
+            None => Err("Select a captured request to add".to_string()),
+        },
+        ["extract", name, step, var, kind @ ("regex" | "json"), pattern @ ..] if !pattern.is_empty() => {
+            match (find(&mut macros, name), step.parse::<usize>()) {
+                (Some(i), Ok(step)) if (1..=macros[i].steps.len()).contains(&step) => {
+                    let mut x = Extraction {
+                        name: var.to_string(), step, kind: kind.to_string(), pattern: pattern.join(" "), recorded: String::new(),
+                    };
+                    match x.find(&macros[i].steps[step - 1].response) {
+                        Some(recorded) => {
+                            let msg = format!("{} = {} in the recording; later steps get the fresh value", var, recorded);
+                            x.recorded = recorded;
+                            macros[i].extractions.retain(|e| e.name != x.name);
+                            macros[i].extractions.push(x);
+                            save(&macros).map(|_| msg)
+                        }
+                        None => Err(format!("Nothing matches in the recorded response of step {}", step)),
+                    }
+                }
+                (Some(_), _) => Err(format!("No step {} in macro {}", step, name)),
+                (None, _) => Err(format!("No macro {}", name)),
+            }
+        }
+        ["run", name] => match find(&mut macros, name) {
+            Some(i) => {
+                let m = macros.swap_remove(i);
+                tokio::spawn(async move {
+                    let result = run(&app, &m).await;
+                    let mut guard = app.lock().unwrap();
+                    match result {
+                        Ok((session, statuses)) => {
+                            let chain: Vec<String> = statuses.iter().map(u16::to_string).collect();
+                            guard.set_status(format!("Macro {}: {}; {} value(s) refreshed", m.name, chain.join(" → "), session.replace.len()));
+                            guard.macro_session = Some(session);
+                        }
+                        Err(e) => guard.set_status(format!("Macro {} failed at {}", m.name, e)),
+                    }
+                });
+                Ok(format!("Running macro {}", name))
+            }
+            None => Err(format!("No macro {}", name)),
+        },
+        ["auto", "off"] => {
+            guard.auto_macro = None;
+            Ok("Repeat sends no longer refresh the session".to_string())
+        }
+        ["auto", name] => match find(&mut macros, name) {
+            Some(_) => {
+                guard.auto_macro = Some(name.to_string());
+                Ok(format!("Repeat sends replay macro {} when the session has expired", name))
+            }
+            None => Err(format!("No macro {}", name)),
+        },
+        ["delete", name] => match find(&mut macros, name) {
+            Some(i) => {
+                macros.remove(i);
+                save(&macros).map(|_| format!("Deleted macro {}", name))
+            }
+            None => Err(format!("No macro {}", name)),
+        },
+        _ => Err(USAGE.to_string()),
+    };
+    match result {
+        Ok(msg) | Err(msg) => guard.set_status(msg),
+    }
+}
@@ -1,243 +1,282 @@
-// Belch Proxy TUI – Passive HTTP/HTTPS Observer
+// Belch Proxy TUI – HTTP/HTTPS Intercepting Observer
+
+mod access_log;
+mod api;
+mod app;
+mod authz;
+mod ca;
+mod cache_bust;
+mod certs;
+mod compare;
+mod config;
+mod config_file;
+mod cookies;
+mod correlation;
+mod cors;
+mod creds;
+mod csrf;
+mod csp;
+mod detail;
+mod diagnose;
+mod diff;
+mod dns;
+mod docker;
+mod downgrade;
+mod editor;
+mod error_page;
+mod events;
+mod exchange;
+mod export;
+mod faults;
+mod filters;
+mod fixtures;
+mod forms;
+mod fuzzer;
+mod fuzzy;
+mod grpc;
+mod highlight;
+mod history;
+mod host_tls;
+mod hosts;
+mod http;
+mod intercept;
+mod keys;
+mod issues;
+mod jsonpath;
+mod listeners;
+mod macros;
+mod markers;
+mod miner;
+mod mirror;
+mod mitm;
+mod negotiate;
+mod normalize;
+mod ntlm;
+mod onboard;
+mod otel;
+mod packed;
+mod palette;
+mod payloads;
+mod pinning;
+mod plugins;
+mod preview;
+mod project;
+mod proxy;
+mod random;
+mod recon;
+mod redact;
+mod redirects;
+mod reflect;
+mod regression;
+mod repeat;
+mod repeater;
+mod rewrite;
+mod saml;
+mod selection;
+mod send;
+mod send_to;
+mod serialized;
+mod sse;
+mod sitemap;
+mod smuggle;
+mod sniff;
+mod spider;
+mod store;
+mod tags;
+mod theme;
+mod throttle;
+mod timeouts;
+mod trace;
+mod transform;
+mod triage;
+mod ui;
+mod upstream;
+mod vhost;
+mod websocket;
 
-use std::collections::VecDeque;
 use std::error::Error;
 use std::io;
+use std::path::Path;
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
 
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use ratatui::{
-    backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout},
-    style::{Color, Modifier, Style},
-    text::{Span, Spans},
-    widgets::{Block, Borders, Paragraph, Wrap},
-    Terminal,
-};
-use tokio::{
-    io::{AsyncReadExt, AsyncWriteExt, split},
-    net::{TcpListener, TcpStream},
-};
+use ratatui::{backend::CrosstermBackend, Terminal};
+use tracing::info;
+use tracing_subscriber::filter::LevelFilter;
 
-#[derive(Clone)]
-struct HttpLog {
-    url: String,
-    request: String,
-    response: String,
-}
+use app::App;
+use ca::CertAuthority;
+use config::{CorsDev, Passthrough, Scope};
+use history::History;
 
-struct App {
-    logs: VecDeque<HttpLog>,
-    selected: usize,
-}
-
-impl App {
-    fn new() -> Self {
-        Self { logs: VecDeque::new(), selected: 0 }
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("ca") {
+        return ca::command(&config::config_dir(), &args[1..]);
     }
-    fn next(&mut self) {
-        if self.selected + 1 < self.logs.len() {
-            self.selected += 1;
-        }
+    let level = flag(&args, "--log-level").and_then(|l| l.parse().ok()).unwrap_or(LevelFilter::INFO);
+    if args.first().map(String::as_str) == Some("replay") {
+        let file = args.get(1).filter(|a| !a.starts_with("--")).ok_or("usage: belch replay FILE [--port N] [--latency recorded|MS]")?;
+        let port = flag(&args, "--port").and_then(|p| p.parse().ok()).unwrap_or(8080);
+        let latency = match flag(&args, "--latency").as_deref() {
+            None | Some("0") => fixtures::Latency::None,
+            Some("recorded") => fixtures::Latency::Recorded,
+            Some(ms) => fixtures::Latency::Fixed(ms.parse().map_err(|_| "--latency takes `recorded` or milliseconds")?),
+        };
+        events::init(flag(&args, "--log-file").as_deref(), level)?;
+        return fixtures::replay(file, port, latency).await;
     }
-    fn previous(&mut self) {
-        if self.selected > 0 {
-            self.selected -= 1;
-        }
+    let limits = flag(&args, "--timeouts").map(|t| timeouts::Timeouts::parse(&t)).transpose()
+        .map_err(|e| format!("--timeouts: {}", e))?;
+    timeouts::init(limits.unwrap_or_default());
+    if let Some(spec) = flag(&args, "--dns") {
+        dns::set(dns::Servers::parse(&spec).map_err(|e| format!("--dns: {}", e))?);
     }
-    fn selected_log(&self) -> Option<&HttpLog> {
-        self.logs.get(self.selected)
+    if let Some(spec) = flag(&args, "--throttle") {
+        throttle::set(throttle::Limits::parse(&spec).map_err(|e| format!("--throttle: {}", e))?);
     }
-}
+    let (config_path, settings) = config_file::ConfigFile::load(flag(&args, "--config").as_deref().map(Path::new))?;
+    theme::set(settings.theme()?);
+    keys::set(&settings.keys)?;
+    let rewrites = settings.rules()?;
+    let scope = || match settings.scope.hosts.is_empty() {
+        true => Scope::load(),
+        false => Scope::with_hosts(&settings.scope.hosts),
+    };
+    let docker = args.first().map(String::as_str) == Some("docker");
+    let mut specs = flags(&args, "--listen");
+    // config.toml's listeners stand in for --listen, but not for the docker preset's socket
+    if specs.is_empty() && !docker {
+        specs = settings.listeners.listen.clone();
+    }
+    if specs.is_empty() {
+        specs.push(match docker {
+            true => format!("docker=unix:{}", docker::listen_socket().display()),
+            false => "127.0.0.1:1337".to_string(),
+        });
+    }
+    let listeners = listeners::Listener::parse_all(&specs).map_err(|e| format!("--listen: {}", e))?;
+    let listen = listeners[0].addr.clone();
+    let daemon = docker.then(|| format!("unix:{}", docker::daemon_socket(flag(&args, "--docker-socket"))));
+    let default_upstream = daemon.clone().or_else(|| flag(&args, "--upstream")).or_else(|| flag(&args, "--upstream-proxy"))
+        .map(|s| upstream::Upstream::parse(&s)).transpose()?;
+    let routes = upstream::Routes::load(default_upstream)?;
+    let tag_rules = tags::TagRules::load()?;
+    let saved_filters = filters::SavedFilters::load()?;
+    let redactions = redact::Redactions::load()?;
+    let csrf_rules = csrf::CsrfRules::load()?;
+    let cookie_overrides = cookies::CookieOverrides::load()?;
+    // Read-only sessions inject nothing
+    if !args.iter().any(|a| a == "--read-only") {
+        faults::load()?;
+    }
+    let non_http = match flag(&args, "--non-http") {
+        Some(p) => sniff::NonHttp::parse(&p).ok_or("--non-http takes `reject`, `sni` or HOST:PORT")?,
+        None => sniff::NonHttp::Reject,
+    };
+    let access_log = flag(&args, "--access-log")
+        .map(|f| access_log::AccessLog::open(Path::new(&f)).map_err(|e| format!("--access-log {}: {}", f, e)))
+        .transpose()?;
+    let auto_export = flag(&args, "--auto-export").or_else(|| settings.export.dir.clone());
+    let export_format = match flag(&args, "--auto-export-format").or_else(|| settings.export.format.clone()) {
+        Some(f) => export::Format::parse(&f).ok_or("--auto-export-format takes `har`, `jsonl`, `xml`, `md`, `reqwest` or `pytest`")?,
+        None => export::Format::Har,
+    };
+    let export_select = flag(&args, "--auto-export-select").or_else(|| settings.export.select.clone());
+    let export_filter = export::Filter::parse(export_select.as_deref().unwrap_or("scope"))
+        .map_err(|e| format!("--auto-export-select: {}", e))?;
+    let events = events::init(flag(&args, "--log-file").as_deref(), level)?;
+    plugins::init();
+    let ca = Arc::new(CertAuthority::load_or_create(&config::config_dir())?);
 
-/// Async HTTP/HTTPS proxy listener
-async fn spawn_proxy_listener(app: Arc<Mutex<App>>) {
-    let listener = TcpListener::bind("127.0.0.1:1337").await.unwrap();
-    println!("🔌 Proxy listening on http://127.0.0.1:1337");
-    loop {
-        let (mut client, _) = listener.accept().await.unwrap();
-        let app = Arc::clone(&app);
-        tokio::spawn(async move {
-            // Read initial frame
-            let mut buf = [0u8; 8192];
-            let n = match client.read(&mut buf).await {
-                Ok(n) if n > 0 => n,
-                _ => return,
-            };
-            let header = String::from_utf8_lossy(&buf[..n]).to_string();
-            let mut lines = header.lines();
-            let start = lines.next().unwrap_or_default();
-            let mut parts = start.split_whitespace();
-            let method = parts.next().unwrap_or("");
-            let target = parts.next().unwrap_or("");
+    if args.first().map(String::as_str) == Some("record") {
+        let out = flag(&args, "--out").unwrap_or_else(|| "fixtures.json".to_string());
+        let mut app = App::new(Passthrough::load(), scope(), listen.clone(), events);
+        app.routes = routes;
+        app.access_log = access_log;
+        app.listeners = listeners;
+        let app = Arc::new(Mutex::new(app));
+        println!("Proxy listening on {}", listen);
+        spawn_listeners(&app, &ca);
+        return fixtures::record(app, &out).await;
+    }
 
-            // Split client into reader/writer
-            let (mut client_r, mut client_w) = split(client);
+    let mut app = App::new(Passthrough::load(), scope(), listen.clone(), events);
+    app.routes = routes;
+    app.minimal_forward = args.iter().any(|a| a == "--minimal-forward");
+    app.read_only = args.iter().any(|a| a == "--read-only");
+    app.non_http = non_http;
+    app.access_log = access_log;
+    app.otel = flag(&args, "--otlp").map(|url| otel::Exporter::start(&url)).transpose().map_err(|e| format!("--otlp: {}", e))?;
+    app.tag_rules = tag_rules;
+    app.saved_filters = saved_filters;
+    app.redactions = redactions;
+    app.redact = args.iter().any(|a| a == "--redact");
+    app.csrf = csrf_rules;
+    app.cookie_overrides = cookie_overrides;
+    app.cors_dev = CorsDev::load();
+    app.history = History::load();
+    app.listeners = listeners;
+    app.docker = docker;
+    app.rewrites = rewrites;
+    app.config_path = config_path;
+    app.export_defaults = settings.export;
+    if let Some(daemon) = &daemon {
+        info!("docker: forwarding {} to {}", listen, daemon);
+        app.set_status(format!("export DOCKER_HOST={}", listen.replacen("unix:", "unix://", 1)));
+    }
+    let app = Arc::new(Mutex::new(app));
+    spawn_listeners(&app, &ca);
 
-            if method.eq_ignore_ascii_case("CONNECT") {
-                // Acknowledge
-                let _ = client_w.write_all(b"HTTP/1.1 200 Connection Established\r\n\r\n").await;
-                { let mut guard = app.lock().unwrap();
-                    guard.logs.push_back(HttpLog {
-                        url: format!("CONNECT {}", target),
-                        request: start.to_string(),
-                        response: "[Tunnel established]".to_string(),
-                    });
-                }
-                // Connect upstream
-                if let Ok(upstream) = TcpStream::connect(target).await {
-                    let (mut up_r, mut up_w) = split(upstream);
-                    let mut cbuf = [0u8;8192];
-                    let mut ubuf = [0u8;8192];
-                    loop {
-                        // client->upstream
-                        let cm = match client_r.read(&mut cbuf).await {
-                            Ok(0) | Err(_) => break,
-                            Ok(m) => m,
-                        };
-                        let creq = String::from_utf8_lossy(&cbuf[..cm]).to_string();
-                        if up_w.write_all(&cbuf[..cm]).await.is_err() { break; }
-                        // upstream->client
-                        let um = match up_r.read(&mut ubuf).await {
-                            Ok(0) | Err(_) => break,
-                            Ok(m) => m,
-                        };
-                        let uresp = String::from_utf8_lossy(&ubuf[..um]).to_string();
-                        let _ = client_w.write_all(&ubuf[..um]).await;
-                        // Combined log
-                        let mut guard = app.lock().unwrap();
-                        guard.logs.push_back(HttpLog {
-                            url: format!("Tunnel {}", target),
-                            request: creq,
-                            response: uresp,
-                        });
-                    }
-                }
-            } else {
-                // Plain HTTP
-                let request = header.clone();
-                let mut lines = request.lines();
-                let first = lines.next().unwrap_or_default();
-                let parts: Vec<&str> = first.split_whitespace().collect();
-                let meth = parts.get(0).copied().unwrap_or("");
-                let path = parts.get(1).copied().unwrap_or("/");
-                let host_hdr = request.lines()
-                    .find(|l| l.to_lowercase().starts_with("host:"))
-                    .and_then(|l| l.splitn(2, ' ').nth(1))
-                    .unwrap_or("127.0.0.1");
-                let mut hp = host_hdr.split(':');
-                let host = hp.next().unwrap_or("127.0.0.1");
-                let port = hp.next().and_then(|x| x.parse().ok()).unwrap_or(80);
-                let forward = format!("{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n", meth, path, host);
-                if let Ok(mut upstream) = TcpStream::connect((host, port)).await {
-                    let _ = upstream.write_all(forward.as_bytes()).await;
-                    let mut resp_buf = Vec::new();
-                    let _ = upstream.read_to_end(&mut resp_buf).await;
-                    let resp_string = String::from_utf8_lossy(&resp_buf).to_string().replace("\r\n","\n");
-                    { let mut guard = app.lock().unwrap();
-                        guard.logs.push_back(HttpLog {
-                            url: format!("{} {} [Host: {}]", meth, path, host),
-                            request: forward.clone(),
-                            response: resp_string.clone(),
-                        });
-                    }
-                    let _ = client_w.write_all(&resp_buf).await;
-                }
-            }
-        });
+    let result = if args.iter().any(|a| a == "--headless") {
+        println!("Proxy listening on {}", listen);
+        api::run(app.clone(), flag(&args, "--api").as_deref().unwrap_or(api::DEFAULT_ADDR)).await
+    } else {
+        tui(app.clone(), ca)
+    };
+
+    if let Some(dir) = auto_export {
+        let (path, count) = export::session(&app.lock().unwrap(), Path::new(&dir), export_format, &export_filter)?;
+        println!("Session exported to {} ({} entries)", path.display(), count);
     }
+    result
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
+/// Run the TUI in the current thread until it quits
+fn tui(app: Arc<Mutex<App>>, ca: Arc<CertAuthority>) -> Result<(), Box<dyn Error>> {
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture, EnableBracketedPaste)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let app = Arc::new(Mutex::new(App::new()));
-    // Spawn one runtime-based listener
-    tokio::spawn(spawn_proxy_listener(app.clone()));
-
-    // Run TUI in the current thread
-    run_app(&mut terminal, app)?;
+    let result = ui::run_app(&mut terminal, app, ca);
 
     disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen, DisableMouseCapture)?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen, DisableMouseCapture, DisableBracketedPaste)?;
     terminal.show_cursor()?;
-    Ok(())
+    Ok(result?)
 }
 
-fn run_app(
-    terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>,
-    app: Arc<Mutex<App>>,
-) -> std::io::Result<()> {
-    loop {
-        terminal.draw(|f| {
-            let guard = app.lock().unwrap();
-            let size = f.size();
-            let chunks = Layout::default()
-                .direction(Direction::Vertical)
-                .constraints([Constraint::Min(0), Constraint::Length(1)])
-                .split(size);
-
-            let panels = Layout::default()
-                .direction(Direction::Horizontal)
-                .constraints([Constraint::Length(30), Constraint::Min(50)])
-                .split(chunks[0]);
-
-            let list = guard.logs.iter().enumerate().map(|(i, log)| {
-                let style = if i == guard.selected { Style::default().fg(Color::Black).bg(Color::White) } else { Style::default() };
-                Spans::from(Span::styled(log.url.clone(), style))
-            }).collect::<Vec<_>>();
-            f.render_widget(
-                Paragraph::new(list)
-                    .block(Block::default().borders(Borders::ALL).title("Requests")),
-                panels[0],
-            );
-
-            let mut detail = vec![Spans::from(Span::styled(
-                "Request:", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
-            ))];
-            if let Some(log) = guard.selected_log() {
-                detail.extend(log.request.lines().map(|l| Spans::from(Span::raw(l))));
-                detail.push(Spans::from(Span::styled(
-                    "Response:", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD),
-                )));
-                detail.extend(log.response.lines().map(|l| Spans::from(Span::raw(l))));
-            } else {
-                detail.push(Spans::from("No requests yet"));
-            }
-            f.render_widget(
-                Paragraph::new(detail)
-                    .block(Block::default().borders(Borders::ALL).title("Raw"))
-                    .wrap(Wrap { trim: false }),
-                panels[1],
-            );
+/// One runtime-based task per listener
+fn spawn_listeners(app: &Arc<Mutex<App>>, ca: &Arc<CertAuthority>) {
+    let listeners: Vec<(String, String)> = app.lock().unwrap().listeners.iter().map(|l| (l.addr.clone(), l.label.clone())).collect();
+    for (addr, label) in listeners {
+        tokio::spawn(proxy::spawn_proxy_listener(app.clone(), ca.clone(), addr, label));
+    }
+}
 
-            f.render_widget(
-                Paragraph::new("↑↓: Navigate   Q: Quit")
-                    .style(Style::default().fg(Color::DarkGray)),
-                chunks[1],
-            );
-        })?;
+/// Every value following `name` on the command line, for flags that may repeat
+fn flags(args: &[String], name: &str) -> Vec<String> {
+    args.windows(2).filter(|w| w[0] == name).map(|w| w[1].clone()).collect()
+}
 
-        if event::poll(Duration::from_millis(50))? {
-            if let Event::Key(key) = event::read()? {
-                match key.code {
-                    KeyCode::Char('q') => break,
-                    KeyCode::Up => app.lock().unwrap().previous(),
-                    KeyCode::Down => app.lock().unwrap().next(),
-                    _ => {}
-                }
-            }
-        }
-    }
-    Ok(())
+/// Value following `name` on the command line
+fn flag(args: &[String], name: &str) -> Option<String> {
+    args.iter().position(|a| a == name).and_then(|i| args.get(i + 1)).cloned()
 }
@@ -0,0 +1,132 @@
+// Named time markers dropped during capture, and the time window the Requests list is limited to
+
+use std::time::Duration;
+
+use time::OffsetDateTime;
+
+use crate::app::App;
+
+/// Longest span `last` takes: a year
+const MAX_SPAN: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+pub struct Marker {
+    pub name: String,
+    pub at: OffsetDateTime,
+}
+
+impl Marker {
+    /// `HH:MM:SS` in UTC, as in the Events tab
+    pub fn time(&self) -> String {
+        format!("{:02}:{:02}:{:02}", self.at.hour(), self.at.minute(), self.at.second())
+    }
+}
+
+#[derive(Clone)]
+pub enum Window {
+    /// From one marker up to the next one given, or to now
+    Between(usize, Option<usize>),
+    /// The last stretch of time, moving along as time passes
+    Last(Duration),
+}
+
+impl Window {
+    pub fn contains(&self, markers: &[Marker], started: Option<OffsetDateTime>) -> bool {
+        let Some(started) = started else { return false };
+        match self {
+            Window::Between(from, to) => {
+                markers.get(*from).is_some_and(|m| started >= m.at)
+                    && to.and_then(|t| markers.get(t)).is_none_or(|m| started < m.at)
+            }
+            // A span reaching past the earliest time covers everything
+            Window::Last(span) => time::Duration::try_from(*span).ok()
+                .and_then(|span| OffsetDateTime::now_utc().checked_sub(span))
+                .is_none_or(|from| started >= from),
+        }
+    }
+
+    pub fn label(&self, markers: &[Marker]) -> String {
+        let name = |i: usize| markers.get(i).map_or("?".to_string(), |m| format!("\"{}\"", m.name));
+        match self {
+            Window::Between(from, None) => format!("since {}", name(*from)),
+            Window::Between(from, Some(to)) => format!("between {} and {}", name(*from), name(*to)),
+            Window::Last(span) => format!("in the last {}", span_label(*span)),
+        }
+    }
+}
+
+/// `90s`, `2m` or `1h`; a bare number is minutes. At most `MAX_SPAN`.
+pub fn parse_span(text: &str) -> Option<Duration> {
+    let (number, unit) = text.find(|c: char| !c.is_ascii_digit() && c != '.').map_or((text, "m"), |i| text.split_at(i));
+    let n: f64 = number.parse().ok().filter(|n| *n > 0.0)?;
+    let secs = match unit {
+        "s" => n,
+        "m" | "min" => n * 60.0,
+        "h" => n * 3600.0,
+        _ => return None,
+    };
+    Duration::try_from_secs_f64(secs).ok().filter(|span| *span <= MAX_SPAN)
+}
+
+fn span_label(span: Duration) -> String {
+    match span.as_secs() {
+        s if s % 3600 == 0 => format!("{}h", s / 3600),
+        s if s % 60 == 0 => format!("{}m", s / 60),
+        s => format!("{}s", s),
+    }
+}
+
+/// A marker by number, counting from 1, or by name
+pub fn find(markers: &[Marker], key: &str) -> Result<usize, String> {
+    if let Ok(n) = key.parse::<usize>() {
+        return (1..=markers.len()).contains(&n).then(|| n - 1).ok_or(format!("No marker {}", n));
+    }
+    markers.iter().position(|m| m.name.eq_ignore_ascii_case(key)).ok_or(format!("No marker named {}", key))
+}
+
+/// `:mark [NAME…]` drops a marker now; `:mark delete N` removes one
+pub fn mark(app: &mut App, args: &[&str]) {
+    if let ["delete", key] = args {
+        return match find(&app.markers, key) {
+            Ok(i) => {
+                let marker = app.markers.remove(i);
+                // Windows point at markers by position
+                app.keep_selection(|a| a.window = None);
+                app.set_status(format!("Deleted marker \"{}\"", marker.name));
+            }
+            Err(e) => app.set_status(e),
+        };
+    }
+    let name = if args.is_empty() { format!("Marker {}", app.markers.len() + 1) } else { args.join(" ") };
+    let marker = Marker { name, at: OffsetDateTime::now_utc() };
+    app.set_status(format!("Marker {} \"{}\" at {} (:window {} shows what follows)", app.markers.len() + 1, marker.name, marker.time(), app.markers.len() + 1));
+    app.markers.push(marker);
+}
+
+/// `:marks` lists the markers
+pub fn list(app: &mut App) {
+    let text = match app.markers.is_empty() {
+        true => "No markers; :mark NAME drops one".to_string(),
+        false => app.markers.iter().enumerate().map(|(i, m)| format!("{}: {} {}", i + 1, m.time(), m.name)).collect::<Vec<_>>().join("   "),
+    };
+    app.set_status(text);
+}
+
+/// `:window FROM [TO]` between markers, `:window last SPAN`, or `:window` to show everything
+pub fn window(app: &mut App, args: &[&str]) {
+    const USAGE: &str = "usage: :window MARKER [MARKER] | last 2m | (nothing, to clear)";
+    let window = match args {
+        [] => None,
+        ["last", span] | [span] if parse_span(span).is_some() && find(&app.markers, span).is_err() => parse_span(span).map(Window::Last),
+        ["last", span] if find(&app.markers, "last").is_err() => return app.set_status(format!("`{}` is not a span of up to a year like 90s, 10m or 2h", span)),
+        [from] => match find(&app.markers, from) {
+            Ok(from) => Some(Window::Between(from, None)),
+            Err(e) => return app.set_status(e),
+        },
+        [from, to] => match (find(&app.markers, from), find(&app.markers, to)) {
+            (Ok(from), Ok(to)) => Some(Window::Between(from.min(to), Some(from.max(to)))),
+            (Err(e), _) | (_, Err(e)) => return app.set_status(e),
+        },
+        _ => return app.set_status(USAGE),
+    };
+    app.keep_selection(|a| a.window = window);
+}
@@ -0,0 +1,129 @@
+// Hidden parameter discovery: resend a request with guessed names and diff the responses
+
+use std::fs;
+use std::sync::{Arc, Mutex};
+
+use tracing::info;
+
+use crate::app::{App, HttpLog};
+use crate::config;
+use crate::http;
+use crate::issues::{Issue, Severity};
+use crate::send;
+use crate::throttle;
+
+pub const WORDLIST: &[&str] = &[
+    "debug", "test", "admin", "id", "user", "username", "email", "callback", "cb", "jsonp", "redirect",
+    "redirect_uri", "return", "returnUrl", "next", "url", "uri", "dest", "continue", "format", "type",
+    "lang", "locale", "page", "limit", "offset", "size", "sort", "order", "q", "query", "search",
+    "filter", "fields", "expand", "include", "token", "key", "api_key", "apikey", "access_token",
+    "secret", "auth", "session", "verbose", "dev", "env", "mode", "preview", "draft", "view",
+    "template", "theme", "file", "path", "dir", "folder", "cmd", "exec", "command", "action", "do",
+    "method", "source", "src", "ref", "referer", "origin", "host", "port", "proxy", "json", "xml",
+    "raw", "pretty", "output", "version", "v", "beta", "internal", "role", "roles", "is_admin",
+    "isAdmin", "group", "account", "tenant", "org", "uid", "user_id", "pid", "cache", "nocache",
+    "timestamp", "ts", "signature", "sig", "hash", "state", "code", "scope", "client_id",
+];
+
+/// `params.txt` in the config dir if present, else the built-in list
+fn wordlist() -> Vec<String> {
+    let custom = fs::read_to_string(config::config_dir().join("params.txt")).unwrap_or_default();
+    let words: Vec<String> = custom.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')).map(String::from).collect();
+    if words.is_empty() { WORDLIST.iter().map(|w| w.to_string()).collect() } else { words }
+}
+
+/// Add `name=value` to the query string, and to the body as well when it is form-encoded
+fn with_param(request: &[u8], name: &str, value: &str) -> Vec<u8> {
+    let (head, body) = http::split_raw(request);
+    let head = String::from_utf8_lossy(head);
+    let mut lines = head.split("\r\n");
+    let first = lines.next().unwrap_or("");
+    let mut parts = first.splitn(3, ' ');
+    let (method, target, version) = (parts.next().unwrap_or(""), parts.next().unwrap_or("/"), parts.next().unwrap_or("HTTP/1.1"));
+    let sep = if target.contains('?') { '&' } else { '?' };
+    let form = http::header_value(&head, "content-type").is_some_and(|c| c.contains("x-www-form-urlencoded"));
+    let mut body = body.to_vec();
+    if form {
+        if !body.is_empty() {
+            body.push(b'&');
+        }
+        body.extend_from_slice(format!("{}={}", name, value).as_bytes());
+    }
+    let mut out = format!("{} {}{}{}={} {}\r\n", method, target, sep, name, value, version);
+    for line in lines.filter(|l| !l.is_empty()) {
+        if line.to_lowercase().starts_with("content-length:") {
+            continue;
+        }
+        out.push_str(line);
+        out.push_str("\r\n");
+    }
+    if !body.is_empty() {
+        out.push_str(&format!("Content-Length: {}\r\n", body.len()));
+    }
+    out.push_str("\r\n");
+    let mut bytes = out.into_bytes();
+    bytes.extend_from_slice(&body);
+    bytes
+}
+
+/// Status and decoded body of a raw response
+fn summarize(response: &[u8]) -> (u16, Vec<u8>) {
+    let (head, body) = http::split_raw(response);
+    let head = String::from_utf8_lossy(head);
+    (http::status_code(&head).unwrap_or(0), http::decode_body(&head, body))
+}
+
+/// Mine the entry in the background, reporting candidates as issues
+pub fn start(app: Arc<Mutex<App>>, log: HttpLog) {
+    tokio::spawn(async move {
+        let base = send::replayable(&log);
+        let via = app.lock().unwrap().routes.route(&log.host).cloned();
+        let port = send::target_port(&log);
+        let fetch = |req: Vec<u8>| {
+            let (host, via) = (log.host.clone(), via.clone());
+            async move {
+                let _permit = throttle::acquire(&host).await;
+                send::send(&host, port, log.tls, &req, via.as_ref()).await.ok().map(|r| summarize(&r))
+            }
+        };
+        // A nonsense parameter tells us how much responses vary on their own
+        let Some((control_status, control_body)) = fetch(with_param(&base, "belchctl", "belchctl0")).await else {
+            return app.lock().unwrap().set_status("Param mining: request failed");
+        };
+        let noise = match fetch(with_param(&base, "belchctl", "belchctl1")).await {
+            Some((_, body)) => body.len().abs_diff(control_body.len()),
+            None => 0,
+        };
+        let words = wordlist();
+        let mut found = 0;
+        for (n, name) in words.iter().enumerate() {
+            let canary = format!("belch{}z", n);
+            let Some((status, body)) = fetch(with_param(&base, name, &canary)).await else { continue };
+            let mut reasons = Vec::new();
+            if status != control_status {
+                reasons.push(format!("status {} → {}", control_status, status));
+            }
+            let diff = body.len().abs_diff(control_body.len());
+            if diff > noise + 16 && diff * 100 > control_body.len() * 2 {
+                reasons.push(format!("length {} → {}", control_body.len(), body.len()));
+            }
+            let reflected = body.windows(canary.len()).any(|w| w == canary.as_bytes());
+            if reflected {
+                reasons.push("value reflected".to_string());
+            }
+            let mut app = app.lock().unwrap();
+            if !reasons.is_empty() {
+                found += 1;
+                app.add_issue(Issue {
+                    severity: if reflected { Severity::Low } else { Severity::Info },
+                    host: log.host.clone(),
+                    url: log.full_url(),
+                    title: format!("Hidden parameter `{}`", name),
+                    detail: reasons.join(", "),
+                });
+            }
+            app.set_status(format!("Param mining {}: {}/{} tried, {} found", log.host, n + 1, words.len(), found));
+        }
+        info!("param mining on {} finished: {} candidates", log.full_url(), found);
+    });
+}
@@ -0,0 +1,117 @@
+// Shadow traffic: chosen requests also go to a second upstream, such as staging, in the background
+// while the client gets the primary's answer. Mirror answers are logged next to the originals and
+// tagged with whether they match; `s`, `d` on one diffs it against its original.
+
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::app::{App, HttpLog, Origin};
+use crate::{config, http, proxy, send, spider, throttle};
+
+pub struct Mirror {
+    host: String,
+    port: u16,
+    tls: bool,
+    /// Host patterns mirrored; empty mirrors the in-scope hosts
+    pub hosts: Vec<String>,
+}
+
+impl Mirror {
+    /// `http[s]://HOST[:PORT]`, mirroring `hosts`
+    pub fn parse(url: &str, hosts: &[&str]) -> Result<Self, String> {
+        let (tls, rest) = match url.split_once("://") {
+            Some(("http", rest)) => (false, rest),
+            Some(("https", rest)) => (true, rest),
+            _ => return Err(format!("`{}` is not an http or https URL", url)),
+        };
+        let (host, port) = match http::split_authority(rest.split(['/', '?']).next().unwrap_or("")) {
+            ("", _) => return Err(format!("`{}` has no host", url)),
+            (host, Some(port)) => (host, port.parse().map_err(|_| format!("bad port in `{}`", url))?),
+            (host, None) => (host, if tls { 443 } else { 80 }),
+        };
+        let hosts = hosts.iter().map(|h| h.to_lowercase()).collect();
+        Ok(Mirror { host: host.to_lowercase(), port, tls, hosts })
+    }
+
+    pub fn label(&self) -> String {
+        format!("{}://{}", if self.tls { "https" } else { "http" }, spider::host_header(&self.host, self.port))
+    }
+}
+
+/// Whether requests to `host` on connection `conn` are mirrored
+pub fn applies(app: &Arc<Mutex<App>>, conn: u64, host: &str) -> bool {
+    let guard = app.lock().unwrap();
+    let Some(mirror) = guard.mirror.as_ref().filter(|m| !guard.read_only_for(host) && !m.host.eq_ignore_ascii_case(host)) else { return false };
+    match mirror.hosts.is_empty() {
+        true => guard.in_scope(&guard.conn_listeners.get(&conn).cloned().unwrap_or_default(), host),
+        false => config::host_matches(&mirror.hosts, host),
+    }
+}
+
+/// Same status and decoded body; headers such as Date differ on every answer
+fn same(a: &HttpLog, b: &HttpLog) -> bool {
+    a.status() == b.status() && a.response_body() == b.response_body()
+}
+
+/// Send `request`, as forwarded for entry `primary` (generation and index), to the mirror and log
+/// its answer
+pub fn spawn(app: Arc<Mutex<App>>, (generation, primary): (u64, usize), request: &[u8]) {
+    let (host, port, tls, via) = {
+        let guard = app.lock().unwrap();
+        let Some(m) = &guard.mirror else { return };
+        (m.host.clone(), m.port, m.tls, guard.routes.route(&m.host).cloned())
+    };
+    let request = http::set_header(&proxy::to_origin(request), "Host", &spider::host_header(&host, port));
+    tokio::spawn(async move {
+        let _permit = throttle::acquire(&host).await;
+        let started = Instant::now();
+        let sent = send::send(&host, port, tls, &request, via.as_ref()).await;
+        let target = HttpLog { host, port, tls, ..Default::default() };
+        let mut entry = HttpLog::sent(&target, &request, sent.as_deref().map_err(|(_, reason)| reason.as_str()), started.elapsed(), Origin::Mirror);
+        let mut guard = app.lock().unwrap();
+        // The original went with a clear or `:open`; the index now means another entry
+        if guard.generation != generation {
+            return;
+        }
+        entry.mirror_of = Some(primary);
+        if let Some(original) = guard.logs.get(primary) {
+            entry.tags.push(if same(original, &entry) { "mirror-same" } else { "mirror-diff" }.to_string());
+        }
+        guard.push_log(entry);
+    });
+}
+
+/// `:mirror URL [HOST…]` mirrors in-scope requests, or those to HOST patterns, to URL;
+/// `:mirror off` stops and `:mirror` shows where they go
+pub fn command(app: &mut App, args: &[&str]) {
+    match args {
+        [] => match &app.mirror {
+            Some(m) => {
+                let what = if m.hosts.is_empty() { "in-scope requests".to_string() } else { m.hosts.join(", ") };
+                let (same, differ) = app.logs.iter().filter(|l| l.mirror_of.is_some()).fold((0, 0), |(s, d), l| match l.tags.iter().any(|t| t == "mirror-same") {
+                    true => (s + 1, d),
+                    false => (s, d + 1),
+                });
+                app.set_status(format!("Mirroring {} to {}: {} matched, {} differ", what, m.label(), same, differ));
+            }
+            None => app.set_status("Not mirroring (:mirror URL [HOST…])"),
+        },
+        ["off"] => {
+            app.mirror = None;
+            app.set_status("Mirroring off");
+        }
+        [url, hosts @ ..] => {
+            if app.refuse("mirroring") {
+                return;
+            }
+            match Mirror::parse(url, hosts) {
+                Ok(m) => {
+                    let what = if m.hosts.is_empty() { "in-scope requests".to_string() } else { m.hosts.join(", ") };
+                    app.set_status(format!("Mirroring {} to {}; answers still come from the primary", what, m.label()));
+                    app.mirror = Some(m);
+                }
+                Err(e) => app.set_status(e),
+            }
+        }
+    }
+}
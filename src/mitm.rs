@@ -0,0 +1,88 @@
+// TLS interception of CONNECT tunnels
+
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_rustls::TlsAcceptor;
+use tracing::warn;
+
+use crate::app::{self, App, HttpLog};
+use crate::ca::CertAuthority;
+use crate::exchange::{Exchange, Prepared};
+use crate::http::{self, RequestHead};
+use crate::proxy::{force_close, read_next_request};
+use crate::{pinning, sse, timeouts, websocket};
+
+fn log(app: &Arc<Mutex<App>>, entry: HttpLog) {
+    app::capture(app, entry);
+}
+
+/// Terminate the client's TLS with a minted leaf cert, relay its requests upstream over TLS
+pub async fn intercept<C>(client: C, client_ip: IpAddr, conn: u64, target: &str, host: &str, app: Arc<Mutex<App>>, ca: Arc<CertAuthority>)
+where
+    C: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let fail = |reason: String| {
+        warn!("{}: {}", target, reason.lines().next().unwrap_or_default().trim_matches(['[', ']']));
+        HttpLog {
+            client: Some(client_ip),
+            conn,
+            url: format!("TLS FAIL {}", target),
+            host: host.to_string(),
+            request: format!("CONNECT {}", target).into(),
+            response: reason.into(),
+            ..Default::default()
+        }
+    };
+    let trial = pinning::begin(&app, host);
+    let server_cfg = match trial.map_or_else(|| ca.server_config(host), |t| ca.trial_config(host, t)) {
+        Ok(cfg) => cfg,
+        Err(e) => return log(&app, fail(format!("[Could not mint certificate: {}]", e))),
+    };
+    let limit = timeouts::get().tls;
+    let accepted = tokio::time::timeout(limit, TlsAcceptor::from(server_cfg).accept(client)).await;
+    match (trial, &accepted) {
+        (Some(trial), Ok(Err(e))) => pinning::finish(&app, host, trial, Err(format!("handshake failed: {}", e))),
+        (Some(trial), Err(_)) => pinning::finish(&app, host, trial, Err("handshake timed out".to_string())),
+        _ => {}
+    }
+    let mut tls = match accepted {
+        Ok(Ok(s)) => s,
+        Err(_) => return log(&app, fail(format!("[Client {}]", timeouts::expired("tls", limit)))),
+        // Typically a pinned client rejecting our certificate
+        Ok(Err(e)) => return log(&app, fail(format!(
+            "[Client TLS handshake failed: {}]\nIf this client pins certificates, press p to add {} to the passthrough list.",
+            e, host
+        ))),
+    };
+    let sni = tls.get_ref().1.server_name().map(str::to_string);
+    // Requests after the first arrive on the same tunnel while the client keeps it open
+    let mut pending = Vec::new();
+    // A client that checks pins after the handshake closes before its first request
+    let mut trial = trial;
+    loop {
+        let request = read_next_request(&mut tls, &mut pending).await;
+        if let Some(trial) = trial.take() {
+            pinning::finish(&app, host, trial, request.as_ref().map(|_| ()).ok_or("closed without sending a request".to_string()));
+        }
+        let Some(request) = request else { return };
+        let Some(head) = RequestHead::parse(&request) else { return };
+        let (meth, path) = (head.method.to_string(), head.target.to_string());
+        let port = http::split_authority(target).1.and_then(|p| p.parse().ok()).unwrap_or(443);
+        let entry = HttpLog { client: Some(client_ip), conn, url: format!("{} {} [TLS: {}]", meth, path, host), host: host.to_string(), port, tls: true, sni: sni.clone(), ..Default::default() };
+        if websocket::is_upgrade(&head) {
+            return websocket::relay(tls, request, host, port, true, entry, app).await;
+        }
+        let forward = force_close(&request);
+        let mut exchange = Exchange::new(&app, entry, request, &meth);
+        let forward = match exchange.prepare(&app, forward, &mut tls).await {
+            Prepared::Forward(forward) => forward,
+            Prepared::Stream(forward) => return sse::relay(tls, forward, host, port, true, exchange.stream_entry(), app).await,
+            Prepared::Done => return,
+        };
+        if !exchange.complete(&app, forward, &mut tls).await {
+            return;
+        }
+    }
+}
@@ -0,0 +1,188 @@
+// SPNEGO (Kerberos) tokens for upstream proxy authentication, made by the system's GSSAPI library
+// with the ticket from `kinit`; the library is loaded the first time a proxy needs it, so builds
+// and hosts without Kerberos never link against it
+
+use std::ffi::c_void;
+
+type Status = u32;
+
+#[repr(C)]
+struct Buffer {
+    length: usize,
+    value: *mut c_void,
+}
+
+#[repr(C)]
+struct Oid {
+    length: u32,
+    elements: *const c_void,
+}
+
+// SAFETY (Sync): both OIDs point at constant bytes the library only reads
+struct ConstOid(Oid);
+unsafe impl Sync for ConstOid {}
+
+/// 1.2.840.113554.1.2.1.4, a `service@host` name
+static HOSTBASED_SERVICE: ConstOid = ConstOid(Oid { length: 10, elements: b"\x2a\x86\x48\x86\xf7\x12\x01\x02\x01\x04".as_ptr() as *const c_void });
+/// 1.3.6.1.5.5.2, SPNEGO
+static SPNEGO: ConstOid = ConstOid(Oid { length: 6, elements: b"\x2b\x06\x01\x05\x05\x02".as_ptr() as *const c_void });
+
+const GSS_C_GSS_CODE: i32 = 1;
+const GSS_C_MECH_CODE: i32 = 2;
+const GSS_S_CONTINUE_NEEDED: Status = 1;
+
+type ImportNameFn = unsafe extern "C" fn(*mut Status, *const Buffer, *const Oid, *mut *mut c_void) -> Status;
+type InitSecContextFn = unsafe extern "C" fn(
+    *mut Status, *mut c_void, *mut *mut c_void, *mut c_void, *const Oid, u32, u32,
+    *mut c_void, *const Buffer, *mut *const Oid, *mut Buffer, *mut u32, *mut u32,
+) -> Status;
+type ReleaseBufferFn = unsafe extern "C" fn(*mut Status, *mut Buffer) -> Status;
+type ReleaseNameFn = unsafe extern "C" fn(*mut Status, *mut *mut c_void) -> Status;
+type DeleteSecContextFn = unsafe extern "C" fn(*mut Status, *mut *mut c_void, *mut Buffer) -> Status;
+type DisplayStatusFn = unsafe extern "C" fn(*mut Status, Status, i32, *const Oid, *mut u32, *mut Buffer) -> Status;
+
+struct Gss {
+    import_name: ImportNameFn,
+    init_sec_context: InitSecContextFn,
+    release_buffer: ReleaseBufferFn,
+    release_name: ReleaseNameFn,
+    delete_sec_context: DeleteSecContextFn,
+    display_status: DisplayStatusFn,
+}
+
+#[cfg(unix)]
+fn load() -> Result<Gss, String> {
+    use std::ffi::CString;
+    const LIBRARIES: &[&str] = &[
+        "libgssapi_krb5.so.2",
+        "libgssapi.so.3",
+        "/System/Library/Frameworks/GSS.framework/GSS",
+        "libgssapi_krb5.dylib",
+    ];
+    let lib = LIBRARIES.iter().find_map(|name| {
+        let name = CString::new(*name).unwrap();
+        // SAFETY: dlopen runs the system GSSAPI library's initializers
+        Some(unsafe { libc::dlopen(name.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) }).filter(|l| !l.is_null())
+    }).ok_or_else(|| format!("no GSSAPI library found (tried {})", LIBRARIES.join(", ")))?;
+    let symbol = |name: &str| -> Result<*mut c_void, String> {
+        let c_name = CString::new(name).unwrap();
+        // SAFETY: `lib` is a live handle from dlopen, never closed
+        let ptr = unsafe { libc::dlsym(lib, c_name.as_ptr()) };
+        if ptr.is_null() { Err(format!("the GSSAPI library has no {}", name)) } else { Ok(ptr) }
+    };
+    // SAFETY: each symbol is cast to its signature in RFC 2744's C bindings
+    unsafe {
+        Ok(Gss {
+            import_name: std::mem::transmute::<*mut c_void, ImportNameFn>(symbol("gss_import_name")?),
+            init_sec_context: std::mem::transmute::<*mut c_void, InitSecContextFn>(symbol("gss_init_sec_context")?),
+            release_buffer: std::mem::transmute::<*mut c_void, ReleaseBufferFn>(symbol("gss_release_buffer")?),
+            release_name: std::mem::transmute::<*mut c_void, ReleaseNameFn>(symbol("gss_release_name")?),
+            delete_sec_context: std::mem::transmute::<*mut c_void, DeleteSecContextFn>(symbol("gss_delete_sec_context")?),
+            display_status: std::mem::transmute::<*mut c_void, DisplayStatusFn>(symbol("gss_display_status")?),
+        })
+    }
+}
+
+#[cfg(not(unix))]
+fn load() -> Result<Gss, String> {
+    Err("Kerberos proxy authentication is only supported on Unix".to_string())
+}
+
+fn gss() -> Result<&'static Gss, String> {
+    static GSS: std::sync::OnceLock<Result<Gss, String>> = std::sync::OnceLock::new();
+    GSS.get_or_init(load).as_ref().map_err(String::clone)
+}
+
+impl Gss {
+    /// The library's words for a failed call's major and minor status
+    fn describe(&self, major: Status, minor: Status) -> String {
+        let mut words = Vec::new();
+        for (code, kind) in [(major, GSS_C_GSS_CODE), (minor, GSS_C_MECH_CODE)] {
+            let mut context = 0u32;
+            loop {
+                let mut ignored = 0;
+                let mut text = Buffer { length: 0, value: std::ptr::null_mut() };
+                // SAFETY: `text` is filled by the library and released right after copying
+                unsafe {
+                    if (self.display_status)(&mut ignored, code, kind, std::ptr::null(), &mut context, &mut text) != 0 {
+                        break;
+                    }
+                    let bytes = std::slice::from_raw_parts(text.value as *const u8, text.length);
+                    words.push(String::from_utf8_lossy(bytes).trim().to_string());
+                    (self.release_buffer)(&mut ignored, &mut text);
+                }
+                if context == 0 {
+                    break;
+                }
+            }
+        }
+        words.retain(|w| !w.is_empty());
+        words.join(": ")
+    }
+}
+
+/// One SPNEGO exchange with a proxy, started with the ticket cache's credentials
+pub struct Context {
+    gss: &'static Gss,
+    name: *mut c_void,
+    context: *mut c_void,
+}
+
+// SAFETY: a context is only ever used by one task at a time
+unsafe impl Send for Context {}
+
+impl Context {
+    /// For the proxy at `host`, authenticated as its `HTTP@host` service
+    pub fn new(host: &str) -> Result<Self, String> {
+        let gss = gss()?;
+        let service = format!("HTTP@{}", host);
+        let input = Buffer { length: service.len(), value: service.as_ptr() as *mut c_void };
+        let (mut minor, mut name) = (0, std::ptr::null_mut());
+        // SAFETY: `input` borrows `service`, which the library copies into `name`
+        let major = unsafe { (gss.import_name)(&mut minor, &input, &HOSTBASED_SERVICE.0, &mut name) };
+        if major != 0 {
+            return Err(format!("Kerberos name {}: {}", service, gss.describe(major, minor)));
+        }
+        Ok(Context { gss, name, context: std::ptr::null_mut() })
+    }
+
+    /// The token for the next `Proxy-Authorization: Negotiate`, given the proxy's last one
+    /// (none at first); blocks while the library asks the KDC for a service ticket
+    pub fn step(&mut self, input: Option<&[u8]>) -> Result<Vec<u8>, String> {
+        let input = input.map(|i| Buffer { length: i.len(), value: i.as_ptr() as *mut c_void });
+        let mut output = Buffer { length: 0, value: std::ptr::null_mut() };
+        let mut minor = 0;
+        // SAFETY: the default credential and no channel bindings are null pointers in RFC 2744;
+        // `output` is copied out and released before returning
+        unsafe {
+            let major = (self.gss.init_sec_context)(
+                &mut minor, std::ptr::null_mut(), &mut self.context, self.name, &SPNEGO.0, 0, 0,
+                std::ptr::null_mut(), input.as_ref().map_or(std::ptr::null(), |i| i as *const Buffer),
+                std::ptr::null_mut(), &mut output, std::ptr::null_mut(), std::ptr::null_mut(),
+            );
+            let token = match output.value.is_null() {
+                true => Vec::new(),
+                false => std::slice::from_raw_parts(output.value as *const u8, output.length).to_vec(),
+            };
+            let mut ignored = 0;
+            (self.gss.release_buffer)(&mut ignored, &mut output);
+            match major {
+                0 | GSS_S_CONTINUE_NEEDED => Ok(token),
+                _ => Err(format!("Kerberos: {}", self.gss.describe(major, minor))),
+            }
+        }
+    }
+}
+
+impl Drop for Context {
+    fn drop(&mut self) {
+        let mut ignored = 0;
+        // SAFETY: both handles came from the library and are released once
+        unsafe {
+            if !self.context.is_null() {
+                (self.gss.delete_sec_context)(&mut ignored, &mut self.context, std::ptr::null_mut());
+            }
+            (self.gss.release_name)(&mut ignored, &mut self.name);
+        }
+    }
+}
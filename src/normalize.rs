@@ -0,0 +1,224 @@
+// What belch changes in a request's header lines when forwarding it. By default nothing: lines go
+// out byte for byte, odd casing, repeats, obs-fold and whitespace included, so parser differentials
+// reach the server. Each rule can be turned on for everything or for one host.
+
+use std::sync::{Mutex, OnceLock};
+
+use crate::app::App;
+use crate::config;
+use crate::http;
+
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum Casing {
+    #[default]
+    Keep,
+    Lower,
+    /// `Content-Type`
+    Title,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rules {
+    pub casing: Casing,
+    /// Join repeated headers into one comma-separated line
+    pub merge: bool,
+    /// Join obs-fold continuation lines onto their header with a space
+    pub unfold: bool,
+    /// No space before the colon, one after it and none at the end of the line
+    pub trim: bool,
+}
+
+impl Rules {
+    /// These rules with `name=value` settings applied on top
+    fn with(mut self, args: &[&str]) -> Result<Self, String> {
+        for arg in args {
+            let flag = |v: &str| match v {
+                "on" => Ok(true),
+                "off" => Ok(false),
+                _ => Err(format!("`{}` takes on or off", arg)),
+            };
+            match arg.split_once('=') {
+                Some(("casing", "keep")) => self.casing = Casing::Keep,
+                Some(("casing", "lower")) => self.casing = Casing::Lower,
+                Some(("casing", "title")) => self.casing = Casing::Title,
+                Some(("merge", v)) => self.merge = flag(v)?,
+                Some(("unfold", v)) => self.unfold = flag(v)?,
+                Some(("trim", v)) => self.trim = flag(v)?,
+                _ => return Err(format!("unknown rule `{}`: casing=keep|lower|title, merge=on|off, unfold=on|off, trim=on|off", arg)),
+            }
+        }
+        Ok(self)
+    }
+
+    pub fn label(&self) -> String {
+        let mut on = Vec::new();
+        match self.casing {
+            Casing::Keep => {}
+            Casing::Lower => on.push("lowercase names"),
+            Casing::Title => on.push("title-case names"),
+        }
+        if self.merge {
+            on.push("merge repeats");
+        }
+        if self.unfold {
+            on.push("unfold obs-fold");
+        }
+        if self.trim {
+            on.push("trim whitespace");
+        }
+        if on.is_empty() { "preserve exactly".to_string() } else { on.join(", ") }
+    }
+
+    /// Header lines after the request line, each with its continuation lines, rewritten
+    fn apply(&self, headers: Vec<Vec<u8>>) -> Vec<Vec<u8>> {
+        let mut out: Vec<Vec<u8>> = Vec::with_capacity(headers.len());
+        for header in headers {
+            let header = if self.unfold { unfold(&header) } else { header };
+            let Some(colon) = header.iter().position(|b| *b == b':') else {
+                out.push(header);
+                continue;
+            };
+            let (name, value) = header.split_at(colon);
+            let name = if self.trim { trim(name) } else { name };
+            let name = match self.casing {
+                Casing::Keep => name.to_vec(),
+                Casing::Lower => name.to_ascii_lowercase(),
+                Casing::Title => title_case(name),
+            };
+            let value = &value[1..];
+            let value = if self.trim { trim(value).to_vec() } else { value.to_vec() };
+            let repeat = self.merge.then(|| out.iter().position(|o| http::header_line_name(o).eq_ignore_ascii_case(http::header_line_name(&name)))).flatten();
+            match repeat {
+                Some(first) => {
+                    out[first].push(b',');
+                    if self.trim {
+                        out[first].push(b' ');
+                    }
+                    out[first].extend_from_slice(&value);
+                }
+                None => {
+                    let mut line = name;
+                    line.push(b':');
+                    if self.trim {
+                        line.push(b' ');
+                    }
+                    line.extend_from_slice(&value);
+                    out.push(line);
+                }
+            }
+        }
+        out
+    }
+}
+
+fn trim(bytes: &[u8]) -> &[u8] {
+    let start = bytes.iter().position(|b| !matches!(b, b' ' | b'\t')).unwrap_or(bytes.len());
+    let end = bytes.iter().rposition(|b| !matches!(b, b' ' | b'\t')).map_or(start, |e| e + 1);
+    &bytes[start..end]
+}
+
+/// `line\r\n  more` as `line more`
+fn unfold(header: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(header.len());
+    for (i, part) in header.split(|b| *b == b'\n').enumerate() {
+        let part = part.strip_suffix(b"\r").unwrap_or(part);
+        if i > 0 {
+            out.push(b' ');
+            out.extend_from_slice(trim(part));
+        } else {
+            out.extend_from_slice(part);
+        }
+    }
+    out
+}
+
+fn title_case(name: &[u8]) -> Vec<u8> {
+    let mut upper = true;
+    name.iter().map(|b| {
+        let c = if upper { b.to_ascii_uppercase() } else { b.to_ascii_lowercase() };
+        upper = *b == b'-';
+        c
+    }).collect()
+}
+
+#[derive(Default)]
+struct State {
+    rules: Rules,
+    /// Host patterns with rules of their own, first match wins
+    hosts: Vec<(String, Rules)>,
+}
+
+static STATE: OnceLock<Mutex<State>> = OnceLock::new();
+
+fn state() -> &'static Mutex<State> {
+    STATE.get_or_init(Default::default)
+}
+
+fn rules_for(host: &str) -> Rules {
+    let state = state().lock().unwrap();
+    state.hosts.iter().find(|(p, _)| config::host_matches(std::slice::from_ref(p), host)).map_or(state.rules, |(_, r)| *r)
+}
+
+/// `request` as forwarded to `host`, untouched unless some rule is on for it
+pub fn forward(request: Vec<u8>, host: &str) -> Vec<u8> {
+    let rules = rules_for(host);
+    if rules == Rules::default() {
+        return request;
+    }
+    let (head, body) = http::split_raw(&request);
+    let line_end = head.iter().position(|b| *b == b'\n').map_or(head.len(), |i| i + 1);
+    let mut out = head[..line_end].to_vec();
+    if !out.ends_with(b"\n") {
+        out.extend_from_slice(b"\r\n");
+    }
+    for line in rules.apply(http::header_lines(head)) {
+        out.extend_from_slice(&line);
+        out.extend_from_slice(b"\r\n");
+    }
+    out.extend_from_slice(b"\r\n");
+    out.extend_from_slice(body);
+    out
+}
+
+/// `:normalize [HOST] RULE=VALUE…` turns rules on or off, for everything or for HOST;
+/// `:normalize [HOST] preserve` forwards exactly again and `:normalize` shows what is on
+pub fn command(app: &mut App, args: &[&str]) {
+    const USAGE: &str = "usage: :normalize [HOST] casing=keep|lower|title merge=on|off unfold=on|off trim=on|off | :normalize [HOST] preserve";
+    let mut state = state().lock().unwrap();
+    let (host, settings) = match args {
+        [] => {
+            let mut text = format!("Forwarding: {}", state.rules.label());
+            for (host, rules) in &state.hosts {
+                text.push_str(&format!("   {}: {}", host, rules.label()));
+            }
+            return app.set_status(text);
+        }
+        [first, rest @ ..] if !first.contains('=') && *first != "preserve" => (Some(first.to_lowercase()), rest),
+        _ => (None, args),
+    };
+    let result = match (&host, settings) {
+        (_, []) => Err(USAGE.to_string()),
+        (None, ["preserve"]) => Ok(Rules::default()),
+        (Some(h), ["preserve"]) => {
+            state.hosts.retain(|(p, _)| p != h);
+            return app.set_status(format!("{}: back to the global rules ({})", h, state.rules.label()));
+        }
+        (None, _) => state.rules.with(settings),
+        (Some(h), _) => state.hosts.iter().find(|(p, _)| p == h).map_or(state.rules, |(_, r)| *r).with(settings),
+    };
+    let rules = match result {
+        Ok(rules) => rules,
+        Err(e) => return app.set_status(e),
+    };
+    match host {
+        Some(h) => {
+            state.hosts.retain(|(p, _)| *p != h);
+            app.set_status(format!("Forwarding to {}: {}", h, rules.label()));
+            state.hosts.push((h, rules));
+        }
+        None => {
+            state.rules = rules;
+            app.set_status(format!("Forwarding: {}", rules.label()));
+        }
+    }
+}
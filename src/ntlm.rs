@@ -0,0 +1,82 @@
+// NTLMv2 message construction for upstream proxy authentication (MS-NLMP)
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, Mac};
+use md4::{Digest, Md4};
+use md5::Md5;
+
+use crate::random;
+
+const SIGNATURE: &[u8; 8] = b"NTLMSSP\0";
+// UNICODE | OEM | REQUEST_TARGET | NTLM | ALWAYS_SIGN | EXTENDED_SESSIONSECURITY
+const FLAGS: u32 = 0x0000_0001 | 0x0000_0002 | 0x0000_0004 | 0x0000_0200 | 0x0000_8000 | 0x0008_0000;
+/// Seconds between 1601-01-01 and the Unix epoch
+const EPOCH_DELTA: u64 = 11_644_473_600;
+
+fn utf16(s: &str) -> Vec<u8> {
+    s.encode_utf16().flat_map(u16::to_le_bytes).collect()
+}
+
+fn hmac_md5(key: &[u8], parts: &[&[u8]]) -> [u8; 16] {
+    let mut mac = Hmac::<Md5>::new_from_slice(key).expect("HMAC accepts any key length");
+    for p in parts {
+        mac.update(p);
+    }
+    mac.finalize().into_bytes().into()
+}
+
+/// Type 1 message
+pub fn negotiate() -> Vec<u8> {
+    let mut msg = SIGNATURE.to_vec();
+    msg.extend_from_slice(&1u32.to_le_bytes());
+    msg.extend_from_slice(&FLAGS.to_le_bytes());
+    // Empty domain and workstation buffers
+    msg.extend_from_slice(&[0; 16]);
+    msg
+}
+
+/// Type 3 message answering the type 2 `challenge`; None if the challenge is malformed
+pub fn authenticate(challenge: &[u8], domain: &str, user: &str, pass: &str) -> Option<Vec<u8>> {
+    if challenge.get(..8)? != SIGNATURE || challenge.get(8..12)? != 2u32.to_le_bytes() {
+        return None;
+    }
+    let server_challenge = challenge.get(24..32)?;
+    let info_len = u16::from_le_bytes(challenge.get(40..42)?.try_into().ok()?) as usize;
+    let info_off = u32::from_le_bytes(challenge.get(44..48)?.try_into().ok()?) as usize;
+    let target_info = challenge.get(info_off..info_off + info_len)?;
+
+    let nt_hash = Md4::digest(utf16(pass));
+    let v2_hash = hmac_md5(&nt_hash, &[&utf16(&(user.to_uppercase() + domain))]);
+
+    let client_challenge: [u8; 8] = random::bytes()?;
+    let since_1601 = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs() + EPOCH_DELTA;
+    let timestamp = since_1601 * 10_000_000;
+
+    let mut blob = vec![1, 1, 0, 0, 0, 0, 0, 0];
+    blob.extend_from_slice(&timestamp.to_le_bytes());
+    blob.extend_from_slice(&client_challenge);
+    blob.extend_from_slice(&[0; 4]);
+    blob.extend_from_slice(target_info);
+    blob.extend_from_slice(&[0; 4]);
+
+    let proof = hmac_md5(&v2_hash, &[server_challenge, &blob]);
+    let nt_response = [&proof[..], &blob].concat();
+    let lm_response = [&hmac_md5(&v2_hash, &[server_challenge, &client_challenge])[..], &client_challenge].concat();
+
+    let fields = [lm_response, nt_response, utf16(domain), utf16(user), utf16("BELCH"), Vec::new()];
+    let mut msg = SIGNATURE.to_vec();
+    msg.extend_from_slice(&3u32.to_le_bytes());
+    let mut offset = 64u32;
+    for field in &fields {
+        msg.extend_from_slice(&(field.len() as u16).to_le_bytes());
+        msg.extend_from_slice(&(field.len() as u16).to_le_bytes());
+        msg.extend_from_slice(&offset.to_le_bytes());
+        offset += field.len() as u32;
+    }
+    msg.extend_from_slice(&FLAGS.to_le_bytes());
+    for field in fields {
+        msg.extend_from_slice(&field);
+    }
+    Some(msg)
+}
@@ -0,0 +1,105 @@
+// Device setup: serves the root CA over plain HTTP for phones and tablets
+
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::Arc;
+
+use qrcode::render::unicode::Dense1x2;
+use qrcode::QrCode;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tracing::{info, warn};
+
+use crate::ca::CertAuthority;
+
+pub const ANDROID_STEPS: &[&str] = &[
+    "Wi-Fi settings → long-press the network → Modify → Advanced",
+    "Proxy: Manual, hostname {proxy_host}, port {proxy_port}",
+    "Scan the QR code (or open {url}) and download belch-ca.crt",
+    "Settings → Security → Encryption & credentials → Install a certificate → CA certificate",
+    "Pick belch-ca.crt and confirm",
+    "Note: apps targeting Android 7+ ignore user CAs unless their network_security_config trusts them; browsers work",
+];
+
+pub const IOS_STEPS: &[&str] = &[
+    "Settings → Wi-Fi → (i) next to the network → Configure Proxy → Manual",
+    "Server {proxy_host}, port {proxy_port}",
+    "Open {url} in Safari (scan the QR code with the camera) and allow the profile download",
+    "Settings → General → VPN & Device Management → Belch Proxy CA → Install",
+    "Settings → General → About → Certificate Trust Settings → enable full trust for Belch Proxy CA",
+];
+
+/// Best-effort LAN address of this machine (no packets are sent)
+pub fn lan_ip() -> Option<std::net::IpAddr> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("8.8.8.8:80").ok()?;
+    socket.local_addr().ok().map(|a| a.ip())
+}
+
+/// QR code for `data` as lines of half-block characters (light modules on a dark terminal)
+pub fn qr_lines(data: &str) -> Vec<String> {
+    match QrCode::new(data) {
+        Ok(code) => code.render::<Dense1x2>()
+            .dark_color(Dense1x2::Light)
+            .light_color(Dense1x2::Dark)
+            .quiet_zone(true)
+            .build()
+            .lines()
+            .map(str::to_string)
+            .collect(),
+        Err(_) => vec!["[QR code unavailable]".to_string()],
+    }
+}
+
+/// Bind the setup server on all interfaces (port 1338, else any free port) and serve in the background
+pub fn start(ca: Arc<CertAuthority>) -> std::io::Result<SocketAddr> {
+    let std_listener = std::net::TcpListener::bind("0.0.0.0:1338")
+        .or_else(|_| std::net::TcpListener::bind("0.0.0.0:0"))?;
+    std_listener.set_nonblocking(true)?;
+    let addr = std_listener.local_addr()?;
+    let listener = TcpListener::from_std(std_listener)?;
+    info!("device setup server listening on {}", addr);
+    tokio::spawn(async move {
+        loop {
+            let mut client = match listener.accept().await {
+                Ok((client, _)) => client,
+                Err(e) => {
+                    warn!("setup server accept failed: {}", e);
+                    continue;
+                }
+            };
+            let ca = Arc::clone(&ca);
+            tokio::spawn(async move {
+                let mut buf = [0u8; 4096];
+                let n = match client.read(&mut buf).await {
+                    Ok(n) if n > 0 => n,
+                    _ => return,
+                };
+                let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                let path = request.split_whitespace().nth(1).unwrap_or("/");
+                let (content_type, disposition, body) = match path {
+                    "/ca.crt" => ("application/x-x509-ca-cert", "attachment; filename=\"belch-ca.crt\"", ca.cert_der.clone()),
+                    "/ca.pem" => ("application/x-pem-file", "attachment; filename=\"belch-ca.pem\"", ca.cert_pem.clone().into_bytes()),
+                    _ => ("text/html; charset=utf-8", "inline", landing_page(&ca).into_bytes()),
+                };
+                let head = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Disposition: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    content_type, disposition, body.len()
+                );
+                let _ = client.write_all(head.as_bytes()).await;
+                let _ = client.write_all(&body).await;
+            });
+        }
+    });
+    Ok(addr)
+}
+
+fn landing_page(ca: &CertAuthority) -> String {
+    format!(
+        "<!doctype html><html><head><meta name=\"viewport\" content=\"width=device-width\"><title>Belch CA</title></head>\
+         <body style=\"font-family:sans-serif\"><h1>Belch Proxy CA</h1>\
+         <p><a href=\"/ca.crt\">Download belch-ca.crt</a> (Android, iOS, Windows)</p>\
+         <p><a href=\"/ca.pem\">Download belch-ca.pem</a></p>\
+         <p>SHA-256<br><code style=\"word-break:break-all\">{}</code></p></body></html>",
+        ca.fingerprint()
+    )
+}
@@ -0,0 +1,183 @@
+// OpenTelemetry: one span per exchange, exported as OTLP/HTTP JSON, with the W3C trace
+// context passed upstream so backend spans nest under the proxy's
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+use crate::app::{App, HttpLog};
+use crate::http;
+use crate::random;
+use crate::send;
+
+/// Spans are collected this long before each export
+const BATCH_EVERY: Duration = Duration::from_secs(2);
+/// Most spans in one export
+const MAX_BATCH: usize = 512;
+/// Spans waiting for export; more are dropped while the collector is slow or down
+const QUEUE: usize = 8192;
+
+/// Where spans go, as given to `--otlp`
+struct Endpoint {
+    host: String,
+    port: u16,
+    tls: bool,
+    path: String,
+}
+
+impl Endpoint {
+    /// `http[s]://host[:port][/path]`; the path defaults to `/v1/traces`
+    fn parse(url: &str) -> Result<Self, String> {
+        let (tls, rest) = match url.split_once("://") {
+            Some(("http", rest)) => (false, rest),
+            Some(("https", rest)) => (true, rest),
+            _ => return Err(format!("`{}` is not an http or https URL", url)),
+        };
+        let (authority, path) = rest.split_once('/').map_or((rest, ""), |(a, p)| (a, p));
+        let (host, port) = match http::split_authority(authority) {
+            (h, Some(p)) => (h, p.parse().map_err(|_| format!("bad port in `{}`", url))?),
+            (h, None) => (h, if tls { 443 } else { 4318 }),
+        };
+        let path = if path.trim_matches('/').is_empty() { "/v1/traces".to_string() } else { format!("/{}", path) };
+        Ok(Endpoint { host: host.to_string(), port, tls, path })
+    }
+}
+
+pub struct Exporter {
+    spans: mpsc::Sender<Value>,
+}
+
+impl Exporter {
+    /// Parse the endpoint and start exporting in the background
+    pub fn start(url: &str) -> Result<Self, String> {
+        let endpoint = Endpoint::parse(url)?;
+        let (spans, rx) = mpsc::channel(QUEUE);
+        info!("exporting spans to {}://{}:{}{}", if endpoint.tls { "https" } else { "http" }, endpoint.host, endpoint.port, endpoint.path);
+        tokio::spawn(export(endpoint, rx));
+        Ok(Exporter { spans })
+    }
+
+    /// Queue the span of a captured exchange
+    pub fn record(&self, log: &HttpLog) {
+        if let Some(span) = span(log) {
+            if self.spans.try_send(span).is_err() {
+                debug!("otel: export queue full, span dropped");
+            }
+        }
+    }
+}
+
+/// `(trace ID, span ID)` of a `traceparent` value
+fn parse_traceparent(value: &str) -> Option<(String, String)> {
+    let parts: Vec<&str> = value.trim().split('-').collect();
+    match parts[..] {
+        [_, trace, span, _] if trace.len() == 32 && span.len() == 16 => Some((trace.to_lowercase(), span.to_lowercase())),
+        _ => None,
+    }
+}
+
+/// The request with a `traceparent` naming a fresh proxy span: the client's trace continued, or a new one
+pub fn propagate(app: &Arc<Mutex<App>>, host: &str, request: Vec<u8>) -> Vec<u8> {
+    let guard = app.lock().unwrap();
+    if guard.otel.is_none() || guard.read_only_for(host) {
+        return request;
+    }
+    let head = String::from_utf8_lossy(http::split_raw(&request).0).to_string();
+    let trace = http::header_value(&head, "traceparent").and_then(parse_traceparent).map_or_else(|| random::hex(16), |(t, _)| t);
+    http::set_header(&request, "traceparent", &format!("00-{}-{}-01", trace, random::hex(8)))
+}
+
+fn attribute(key: &str, value: Value) -> Value {
+    json!({ "key": key, "value": value })
+}
+
+/// The exchange as an OTLP span; its IDs come from the forwarded `traceparent`, its parent from the client's
+fn span(log: &HttpLog) -> Option<Value> {
+    let started = log.started?;
+    let forwarded = log.request.text();
+    let sent = http::split_message(&forwarded).0;
+    let (trace, id) = http::header_value(sent, "traceparent").and_then(parse_traceparent)
+        .unwrap_or_else(|| (random::hex(16), random::hex(8)));
+    let raw = log.request_raw.bytes();
+    let original = String::from_utf8_lossy(http::split_raw(&raw).0).to_string();
+    let parent = http::header_value(&original, "traceparent").and_then(parse_traceparent)
+        .filter(|(t, s)| *t == trace && *s != id)
+        .map(|(_, s)| s);
+    let start = started.unix_timestamp_nanos();
+    let end = start + log.duration.as_nanos() as i128;
+    let method = log.method();
+    let mut attributes = vec![
+        attribute("http.request.method", json!({ "stringValue": method })),
+        attribute("url.full", json!({ "stringValue": log.full_url() })),
+        attribute("server.address", json!({ "stringValue": log.host })),
+        attribute("belch.connection", json!({ "intValue": log.conn.to_string() })),
+    ];
+    if log.port != 0 {
+        attributes.push(attribute("server.port", json!({ "intValue": log.port.to_string() })));
+    }
+    if let Some(client) = log.client {
+        attributes.push(attribute("client.address", json!({ "stringValue": client.to_string() })));
+    }
+    if let Some(status) = log.status() {
+        attributes.push(attribute("http.response.status_code", json!({ "intValue": status.to_string() })));
+    }
+    // OTLP status codes: 1 ok, 2 error
+    let code = if log.status().is_none_or(|s| s >= 500) { 2 } else { 1 };
+    let mut span = json!({
+        "traceId": trace,
+        "spanId": id,
+        "name": format!("{} {}", method, log.host),
+        // Client: the proxy calling the upstream
+        "kind": 3,
+        "startTimeUnixNano": start.to_string(),
+        "endTimeUnixNano": end.to_string(),
+        "attributes": attributes,
+        "status": { "code": code },
+    });
+    if let Some(parent) = parent {
+        span["parentSpanId"] = json!(parent);
+    }
+    Some(span)
+}
+
+async fn export(endpoint: Endpoint, mut rx: mpsc::Receiver<Value>) {
+    let mut failing = false;
+    while let Some(first) = rx.recv().await {
+        tokio::time::sleep(BATCH_EVERY).await;
+        let mut spans = vec![first];
+        while spans.len() < MAX_BATCH {
+            let Ok(span) = rx.try_recv() else { break };
+            spans.push(span);
+        }
+        let body = json!({
+            "resourceSpans": [{
+                "resource": { "attributes": [attribute("service.name", json!({ "stringValue": "belch" }))] },
+                "scopeSpans": [{ "scope": { "name": "belch" }, "spans": spans }],
+            }],
+        }).to_string();
+        let request = format!(
+            "POST {} HTTP/1.1\r\nHost: {}:{}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            endpoint.path, http::url_host(&endpoint.host), endpoint.port, body.len(), body,
+        );
+        let result = send::send(&endpoint.host, endpoint.port, endpoint.tls, request.as_bytes(), None).await.map_err(|(_, reason)| reason)
+            .and_then(|raw| match http::status_code(&String::from_utf8_lossy(http::split_raw(&raw).0)) {
+                Some(200..=299) => Ok(()),
+                status => Err(format!("collector answered {}", status.map_or("nothing".to_string(), |s| s.to_string()))),
+            });
+        // Reported when exporting starts or stops failing, not for every batch
+        match result {
+            Err(e) if !failing => {
+                warn!("OTLP export to {}:{} failed: {}", endpoint.host, endpoint.port, e);
+                failing = true;
+            }
+            Ok(()) if failing => {
+                info!("OTLP export to {}:{} working again", endpoint.host, endpoint.port);
+                failing = false;
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,666 @@
+// MessagePack, CBOR and AMF bodies, common in mobile and IoT APIs, decoded into JSON-like trees
+// for the detail pane
+
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::app::HttpLog;
+use crate::http;
+
+/// Containers are not followed deeper than this
+const MAX_DEPTH: usize = 64;
+/// Bytes shown of a binary value
+const MAX_HEX: usize = 48;
+/// Concatenated top-level values listed
+const MAX_VALUES: usize = 100;
+
+enum Node {
+    Null,
+    Undefined,
+    Bool(bool),
+    Int(i128),
+    Float(f64),
+    Str(String),
+    Bytes(Vec<u8>),
+    Array(Vec<Node>),
+    Map(Vec<(Node, Node)>),
+    /// A value with a type the tree has no form for: a CBOR tag, a MessagePack extension, an AMF
+    /// class name, a reference to an earlier AMF object
+    Tagged(String, Box<Node>),
+}
+
+impl Node {
+    fn tagged(label: impl Into<String>, node: Node) -> Node {
+        Node::Tagged(label.into(), Box::new(node))
+    }
+
+    /// One line per scalar and per container boundary, indented two spaces a level
+    fn write(&self, indent: usize, out: &mut String) {
+        let pad = |n: usize| "  ".repeat(n);
+        match self {
+            Node::Null => out.push_str("null"),
+            Node::Undefined => out.push_str("undefined"),
+            Node::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+            Node::Int(n) => out.push_str(&n.to_string()),
+            Node::Float(f) => out.push_str(&format!("{:?}", f)),
+            Node::Str(s) => out.push_str(&serde_json::to_string(s).unwrap_or_default()),
+            Node::Bytes(b) => {
+                let hex: String = b.iter().take(MAX_HEX).map(|b| format!("{:02x}", b)).collect();
+                let more = if b.len() > MAX_HEX { format!("… {} bytes", b.len()) } else { String::new() };
+                out.push_str(&format!("h'{}'{}", hex, more));
+            }
+            Node::Array(items) if items.is_empty() => out.push_str("[]"),
+            Node::Array(items) => {
+                out.push_str("[\n");
+                for (i, item) in items.iter().enumerate() {
+                    out.push_str(&pad(indent + 1));
+                    item.write(indent + 1, out);
+                    out.push_str(if i + 1 < items.len() { ",\n" } else { "\n" });
+                }
+                out.push_str(&format!("{}]", pad(indent)));
+            }
+            Node::Map(entries) if entries.is_empty() => out.push_str("{}"),
+            Node::Map(entries) => {
+                out.push_str("{\n");
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    out.push_str(&pad(indent + 1));
+                    key.write(indent + 1, out);
+                    out.push_str(": ");
+                    value.write(indent + 1, out);
+                    out.push_str(if i + 1 < entries.len() { ",\n" } else { "\n" });
+                }
+                out.push_str(&format!("{}}}", pad(indent)));
+            }
+            Node::Tagged(label, node) => {
+                out.push_str(label);
+                out.push(' ');
+                node.write(indent, out);
+            }
+        }
+    }
+}
+
+fn date(secs: i128) -> Node {
+    match i64::try_from(secs).ok().and_then(|s| OffsetDateTime::from_unix_timestamp(s).ok()).and_then(|t| t.format(&Rfc3339).ok()) {
+        Some(text) => Node::Str(text),
+        None => Node::Int(secs),
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader { data, pos: 0 }
+    }
+
+    fn done(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8], String> {
+        let data = self.data;
+        let slice = data.get(self.pos..self.pos.saturating_add(n)).ok_or_else(|| format!("cut short at byte {}", self.pos))?;
+        self.pos += n;
+        Ok(slice)
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    /// Big-endian unsigned of `n` bytes
+    fn uint(&mut self, n: usize) -> Result<u64, String> {
+        Ok(self.bytes(n)?.iter().fold(0, |acc, b| acc << 8 | *b as u64))
+    }
+
+    /// A count from the data, refused when there aren't that many bytes left for its items
+    fn count(&self, n: u64) -> Result<usize, String> {
+        match usize::try_from(n) {
+            Ok(n) if n <= self.data.len() - self.pos => Ok(n),
+            _ => Err(format!("length {} at byte {} runs past the end", n, self.pos)),
+        }
+    }
+
+    fn text(&mut self, n: usize) -> Result<String, String> {
+        Ok(String::from_utf8_lossy(self.bytes(n)?).into_owned())
+    }
+
+    fn f64(&mut self) -> Result<f64, String> {
+        Ok(f64::from_bits(self.uint(8)?))
+    }
+
+    fn deeper(depth: usize) -> Result<usize, String> {
+        (depth < MAX_DEPTH).then_some(depth + 1).ok_or_else(|| "nested too deep".to_string())
+    }
+}
+
+// MessagePack
+
+fn msgpack(r: &mut Reader, depth: usize) -> Result<Node, String> {
+    let depth = Reader::deeper(depth)?;
+    let b = r.byte()?;
+    let array = |r: &mut Reader, n: u64| -> Result<Node, String> {
+        let n = r.count(n)?;
+        (0..n).map(|_| msgpack(r, depth)).collect::<Result<_, _>>().map(Node::Array)
+    };
+    let map = |r: &mut Reader, n: u64| -> Result<Node, String> {
+        let n = r.count(n)?;
+        (0..n).map(|_| Ok((msgpack(r, depth)?, msgpack(r, depth)?))).collect::<Result<_, String>>().map(Node::Map)
+    };
+    let ext = |r: &mut Reader, n: usize| -> Result<Node, String> {
+        let kind = r.byte()? as i8;
+        let data = r.bytes(n)?;
+        // Type -1 is the timestamp extension
+        Ok(match (kind, n) {
+            (-1, 4) => Node::tagged("timestamp", date(u32::from_be_bytes(data.try_into().unwrap()) as i128)),
+            (-1, 8) => Node::tagged("timestamp", date((u64::from_be_bytes(data.try_into().unwrap()) & 0x3_ffff_ffff) as i128)),
+            (-1, 12) => Node::tagged("timestamp", date(i64::from_be_bytes(data[4..].try_into().unwrap()) as i128)),
+            _ => Node::tagged(format!("ext({})", kind), Node::Bytes(data.to_vec())),
+        })
+    };
+    Ok(match b {
+        0x00..=0x7f => Node::Int(b as i128),
+        0x80..=0x8f => map(r, (b & 0x0f) as u64)?,
+        0x90..=0x9f => array(r, (b & 0x0f) as u64)?,
+        0xa0..=0xbf => Node::Str(r.text((b & 0x1f) as usize)?),
+        0xc0 => Node::Null,
+        0xc2 => Node::Bool(false),
+        0xc3 => Node::Bool(true),
+        0xc4..=0xc6 => {
+            let n = r.uint(1 << (b - 0xc4))?;
+            let n = r.count(n)?;
+            Node::Bytes(r.bytes(n)?.to_vec())
+        }
+        0xc7..=0xc9 => {
+            let n = r.uint(1 << (b - 0xc7))?;
+            let n = r.count(n)?;
+            ext(r, n)?
+        }
+        0xca => Node::Float(f32::from_bits(r.uint(4)? as u32) as f64),
+        0xcb => Node::Float(r.f64()?),
+        0xcc..=0xcf => Node::Int(r.uint(1 << (b - 0xcc))? as i128),
+        0xd0 => Node::Int(r.uint(1)? as i8 as i128),
+        0xd1 => Node::Int(r.uint(2)? as i16 as i128),
+        0xd2 => Node::Int(r.uint(4)? as i32 as i128),
+        0xd3 => Node::Int(r.uint(8)? as i64 as i128),
+        0xd4..=0xd8 => ext(r, 1 << (b - 0xd4))?,
+        0xd9..=0xdb => {
+            let n = r.uint(1 << (b - 0xd9))?;
+            let n = r.count(n)?;
+            Node::Str(r.text(n)?)
+        }
+        0xdc | 0xdd => {
+            let n = r.uint(if b == 0xdc { 2 } else { 4 })?;
+            array(r, n)?
+        }
+        0xde | 0xdf => {
+            let n = r.uint(if b == 0xde { 2 } else { 4 })?;
+            map(r, n)?
+        }
+        0xe0..=0xff => Node::Int(b as i8 as i128),
+        0xc1 => return Err(format!("reserved byte 0xc1 at {}", r.pos - 1)),
+    })
+}
+
+// CBOR
+
+/// IEEE half precision
+fn half(bits: u16) -> f64 {
+    let exp = (bits >> 10) & 0x1f;
+    let mant = (bits & 0x3ff) as f64;
+    let value = match exp {
+        0 => mant * 2f64.powi(-24),
+        31 if mant == 0.0 => f64::INFINITY,
+        31 => f64::NAN,
+        e => (mant + 1024.0) * 2f64.powi(e as i32 - 25),
+    };
+    if bits & 0x8000 != 0 { -value } else { value }
+}
+
+/// `None` for the break that ends an indefinite-length item
+fn cbor(r: &mut Reader, depth: usize) -> Result<Option<Node>, String> {
+    let depth = Reader::deeper(depth)?;
+    let b = r.byte()?;
+    let (major, info) = (b >> 5, b & 0x1f);
+    if b == 0xff {
+        return Ok(None);
+    }
+    let arg = match info {
+        0..=23 => Some(info as u64),
+        24..=27 => Some(r.uint(1 << (info - 24))?),
+        31 if matches!(major, 2..=5) => None,
+        _ => return Err(format!("invalid additional info {} at byte {}", info, r.pos - 1)),
+    };
+    let value = |r: &mut Reader| cbor(r, depth)?.ok_or_else(|| "unexpected break".to_string());
+    // Items of an indefinite-length container, up to its break
+    let until_break = |r: &mut Reader, pairs: bool| -> Result<Vec<(Node, Option<Node>)>, String> {
+        let mut items = Vec::new();
+        while let Some(item) = cbor(r, depth)? {
+            let second = if pairs { Some(value(r)?) } else { None };
+            items.push((item, second));
+        }
+        Ok(items)
+    };
+    Ok(Some(match (major, arg) {
+        (0, Some(n)) => Node::Int(n as i128),
+        (1, Some(n)) => Node::Int(-1 - n as i128),
+        (2, Some(n)) => {
+            let n = r.count(n)?;
+            Node::Bytes(r.bytes(n)?.to_vec())
+        }
+        (3, Some(n)) => {
+            let n = r.count(n)?;
+            Node::Str(r.text(n)?)
+        }
+        (2 | 3, None) => {
+            let mut joined = Vec::new();
+            for (chunk, _) in until_break(r, false)? {
+                match chunk {
+                    Node::Bytes(b) => joined.extend(b),
+                    Node::Str(s) => joined.extend(s.into_bytes()),
+                    _ => return Err("non-string chunk in an indefinite string".into()),
+                }
+            }
+            if major == 2 { Node::Bytes(joined) } else { Node::Str(String::from_utf8_lossy(&joined).into_owned()) }
+        }
+        (4, Some(n)) => {
+            let n = r.count(n)?;
+            Node::Array((0..n).map(|_| value(r)).collect::<Result<_, _>>()?)
+        }
+        (4, None) => Node::Array(until_break(r, false)?.into_iter().map(|(v, _)| v).collect()),
+        (5, Some(n)) => {
+            let n = r.count(n)?;
+            Node::Map((0..n).map(|_| Ok((value(r)?, value(r)?))).collect::<Result<_, String>>()?)
+        }
+        (5, None) => Node::Map(until_break(r, true)?.into_iter().map(|(k, v)| (k, v.unwrap_or(Node::Null))).collect()),
+        // Self-described CBOR says nothing about the value inside
+        (6, Some(55799)) => value(r)?,
+        (6, Some(1)) => match value(r)? {
+            Node::Int(secs) => Node::tagged("epoch", date(secs)),
+            other => Node::tagged("tag(1)", other),
+        },
+        (6, Some(tag)) => Node::tagged(format!("tag({})", tag), value(r)?),
+        (7, Some(20)) => Node::Bool(false),
+        (7, Some(21)) => Node::Bool(true),
+        (7, Some(22)) => Node::Null,
+        (7, Some(23)) => Node::Undefined,
+        (7, Some(n)) if info == 25 => Node::Float(half(n as u16)),
+        (7, Some(n)) if info == 26 => Node::Float(f32::from_bits(n as u32) as f64),
+        (7, Some(n)) if info == 27 => Node::Float(f64::from_bits(n)),
+        (7, Some(n)) => Node::tagged("simple", Node::Int(n as i128)),
+        _ => return Err(format!("unexpected major type {} at byte {}", major, r.pos - 1)),
+    }))
+}
+
+// AMF, Flash Remoting's format: a packet of AMF0 headers and messages, AMF3 inside
+
+#[derive(Default)]
+struct Amf {
+    /// AMF3 strings and traits seen so far, which later ones refer to by index
+    strings: Vec<String>,
+    traits: Vec<Traits>,
+    /// AMF0 and AMF3 complex values seen so far; references to them are shown as such
+    objects0: usize,
+    objects3: usize,
+}
+
+#[derive(Clone)]
+struct Traits {
+    class: String,
+    sealed: Vec<String>,
+    dynamic: bool,
+    externalizable: bool,
+}
+
+impl Amf {
+    fn u16_string(r: &mut Reader) -> Result<String, String> {
+        let n = r.uint(2)? as usize;
+        r.text(n)
+    }
+
+    /// AMF0 object properties, up to the empty name and end marker
+    fn properties0(&mut self, r: &mut Reader, depth: usize) -> Result<Vec<(Node, Node)>, String> {
+        let mut entries = Vec::new();
+        loop {
+            let name = Amf::u16_string(r)?;
+            if name.is_empty() && r.data.get(r.pos) == Some(&0x09) {
+                r.pos += 1;
+                return Ok(entries);
+            }
+            entries.push((Node::Str(name), self.value0(r, depth)?));
+        }
+    }
+
+    fn value0(&mut self, r: &mut Reader, depth: usize) -> Result<Node, String> {
+        let depth = Reader::deeper(depth)?;
+        let marker = r.byte()?;
+        Ok(match marker {
+            0x00 => Node::Float(r.f64()?),
+            0x01 => Node::Bool(r.byte()? != 0),
+            0x02 => Node::Str(Amf::u16_string(r)?),
+            0x03 => {
+                self.objects0 += 1;
+                Node::Map(self.properties0(r, depth)?)
+            }
+            0x05 => Node::Null,
+            0x06 | 0x0d => Node::Undefined,
+            0x07 => Node::tagged("ref", Node::Int(r.uint(2)? as i128)),
+            0x08 => {
+                self.objects0 += 1;
+                r.uint(4)?;
+                Node::Map(self.properties0(r, depth)?)
+            }
+            0x0a => {
+                self.objects0 += 1;
+                let n = r.uint(4)?;
+                let n = r.count(n)?;
+                Node::Array((0..n).map(|_| self.value0(r, depth)).collect::<Result<_, _>>()?)
+            }
+            0x0b => {
+                let ms = r.f64()?;
+                r.bytes(2)?;
+                Node::tagged("date", date((ms / 1000.0) as i128))
+            }
+            0x0c | 0x0f => {
+                let n = r.uint(4)?;
+                let n = r.count(n)?;
+                Node::Str(r.text(n)?)
+            }
+            0x10 => {
+                self.objects0 += 1;
+                let class = Amf::u16_string(r)?;
+                Node::tagged(class, Node::Map(self.properties0(r, depth)?))
+            }
+            0x11 => self.value3(r, depth)?,
+            m => return Err(format!("unknown AMF0 marker 0x{:02x} at byte {}", m, r.pos - 1)),
+        })
+    }
+
+    /// AMF3's variable-length 29-bit integer
+    fn u29(r: &mut Reader) -> Result<u32, String> {
+        let mut value = 0u32;
+        for i in 0..4 {
+            let b = r.byte()? as u32;
+            if i == 3 {
+                return Ok(value << 8 | b);
+            }
+            value = value << 7 | (b & 0x7f);
+            if b & 0x80 == 0 {
+                break;
+            }
+        }
+        Ok(value)
+    }
+
+    fn string3(&mut self, r: &mut Reader) -> Result<String, String> {
+        let header = Amf::u29(r)?;
+        if header & 1 == 0 {
+            let index = (header >> 1) as usize;
+            return self.strings.get(index).cloned().ok_or_else(|| format!("bad string reference {}", index));
+        }
+        let n = r.count((header >> 1) as u64)?;
+        let s = r.text(n)?;
+        if !s.is_empty() {
+            self.strings.push(s.clone());
+        }
+        Ok(s)
+    }
+
+    /// The U29 header of a complex value: `Err` with the node for a reference, else the inline bits
+    fn reference(&mut self, r: &mut Reader) -> Result<Result<u32, Node>, String> {
+        let header = Amf::u29(r)?;
+        if header & 1 == 0 {
+            return Ok(Err(Node::tagged("ref", Node::Int((header >> 1) as i128))));
+        }
+        self.objects3 += 1;
+        Ok(Ok(header >> 1))
+    }
+
+    fn value3(&mut self, r: &mut Reader, depth: usize) -> Result<Node, String> {
+        let depth = Reader::deeper(depth)?;
+        let marker = r.byte()?;
+        Ok(match marker {
+            0x00 => Node::Undefined,
+            0x01 => Node::Null,
+            0x02 => Node::Bool(false),
+            0x03 => Node::Bool(true),
+            // Sign-extended from 29 bits
+            0x04 => Node::Int(((Amf::u29(r)? << 3) as i32 >> 3) as i128),
+            0x05 => Node::Float(r.f64()?),
+            0x06 => Node::Str(self.string3(r)?),
+            0x07 | 0x0b => match self.reference(r)? {
+                Ok(n) => {
+                    let n = r.count(n as u64)?;
+                    Node::tagged("xml", Node::Str(r.text(n)?))
+                }
+                Err(node) => node,
+            },
+            0x08 => match self.reference(r)? {
+                Ok(_) => Node::tagged("date", date((r.f64()? / 1000.0) as i128)),
+                Err(node) => node,
+            },
+            0x09 => match self.reference(r)? {
+                Ok(n) => {
+                    let mut entries = Vec::new();
+                    loop {
+                        let key = self.string3(r)?;
+                        if key.is_empty() {
+                            break;
+                        }
+                        entries.push((Node::Str(key), self.value3(r, depth)?));
+                    }
+                    let n = r.count(n as u64)?;
+                    let dense: Vec<Node> = (0..n).map(|_| self.value3(r, depth)).collect::<Result<_, _>>()?;
+                    match entries.is_empty() {
+                        true => Node::Array(dense),
+                        false => {
+                            entries.extend(dense.into_iter().enumerate().map(|(i, v)| (Node::Int(i as i128), v)));
+                            Node::Map(entries)
+                        }
+                    }
+                }
+                Err(node) => node,
+            },
+            0x0a => match self.reference(r)? {
+                Ok(bits) => self.object3(r, depth, bits)?,
+                Err(node) => node,
+            },
+            0x0c => match self.reference(r)? {
+                Ok(n) => {
+                    let n = r.count(n as u64)?;
+                    Node::Bytes(r.bytes(n)?.to_vec())
+                }
+                Err(node) => node,
+            },
+            0x0d..=0x10 => match self.reference(r)? {
+                Ok(n) => {
+                    let n = r.count(n as u64)?;
+                    r.byte()?;
+                    let items = match marker {
+                        0x0d => (0..n).map(|_| Ok(Node::Int(r.uint(4)? as i32 as i128))).collect::<Result<_, String>>()?,
+                        0x0e => (0..n).map(|_| Ok(Node::Int(r.uint(4)? as i128))).collect::<Result<_, String>>()?,
+                        0x0f => (0..n).map(|_| Ok(Node::Float(r.f64()?))).collect::<Result<_, String>>()?,
+                        _ => {
+                            let class = self.string3(r)?;
+                            let items = (0..n).map(|_| self.value3(r, depth)).collect::<Result<_, _>>()?;
+                            return Ok(Node::tagged(format!("Vector.<{}>", if class.is_empty() { "*" } else { &class }), Node::Array(items)));
+                        }
+                    };
+                    Node::tagged("vector", Node::Array(items))
+                }
+                Err(node) => node,
+            },
+            0x11 => match self.reference(r)? {
+                Ok(n) => {
+                    r.byte()?;
+                    let n = r.count(n as u64)?;
+                    Node::tagged("dictionary", Node::Map((0..n).map(|_| Ok((self.value3(r, depth)?, self.value3(r, depth)?))).collect::<Result<_, String>>()?))
+                }
+                Err(node) => node,
+            },
+            m => return Err(format!("unknown AMF3 marker 0x{:02x} at byte {}", m, r.pos - 1)),
+        })
+    }
+
+    /// An object after its header bits: its traits inline or by reference, then its members
+    fn object3(&mut self, r: &mut Reader, depth: usize, bits: u32) -> Result<Node, String> {
+        let traits = if bits & 1 == 0 {
+            let index = (bits >> 1) as usize;
+            self.traits.get(index).cloned().ok_or_else(|| format!("bad traits reference {}", index))?
+        } else {
+            let externalizable = bits & 2 != 0;
+            let dynamic = bits & 4 != 0;
+            let count = bits >> 3;
+            let class = self.string3(r)?;
+            let sealed = (0..r.count(count as u64)?).map(|_| self.string3(r)).collect::<Result<_, _>>()?;
+            let traits = Traits { class, sealed, dynamic, externalizable };
+            self.traits.push(traits.clone());
+            traits
+        };
+        let label = if traits.class.is_empty() { "object".to_string() } else { traits.class.clone() };
+        if traits.externalizable {
+            // Flex's collection wrappers write just the value they wrap; others are opaque
+            return match traits.class.as_str() {
+                "flex.messaging.io.ArrayCollection" | "flex.messaging.io.ObjectProxy" | "mx.collections.ArrayCollection" => {
+                    Ok(Node::tagged(label, self.value3(r, depth)?))
+                }
+                _ => Err(format!("{} is externalizable; its own code reads the rest", label)),
+            };
+        }
+        let mut entries = Vec::new();
+        for name in &traits.sealed {
+            entries.push((Node::Str(name.clone()), self.value3(r, depth)?));
+        }
+        if traits.dynamic {
+            loop {
+                let name = self.string3(r)?;
+                if name.is_empty() {
+                    break;
+                }
+                entries.push((Node::Str(name), self.value3(r, depth)?));
+            }
+        }
+        Ok(Node::tagged(label, Node::Map(entries)))
+    }
+
+    /// A whole packet: version, headers and messages
+    fn packet(r: &mut Reader) -> Result<Node, String> {
+        let mut amf = Amf::default();
+        let version = r.uint(2)?;
+        let mut headers = Vec::new();
+        for _ in 0..r.uint(2)? {
+            let name = Amf::u16_string(r)?;
+            let must = r.byte()? != 0;
+            r.uint(4)?;
+            let value = amf.value0(r, 0)?;
+            headers.push(Node::Map(vec![
+                (Node::Str("name".into()), Node::Str(name)),
+                (Node::Str("mustUnderstand".into()), Node::Bool(must)),
+                (Node::Str("value".into()), value),
+            ]));
+        }
+        let mut messages = Vec::new();
+        for _ in 0..r.uint(2)? {
+            let target = Amf::u16_string(r)?;
+            let response = Amf::u16_string(r)?;
+            r.uint(4)?;
+            // Each message starts its reference tables over
+            amf = Amf::default();
+            let body = amf.value0(r, 0)?;
+            messages.push(Node::Map(vec![
+                (Node::Str("target".into()), Node::Str(target)),
+                (Node::Str("response".into()), Node::Str(response)),
+                (Node::Str("body".into()), body),
+            ]));
+        }
+        Ok(Node::Map(vec![
+            (Node::Str("version".into()), Node::Int(version as i128)),
+            (Node::Str("headers".into()), Node::Array(headers)),
+            (Node::Str("messages".into()), Node::Array(messages)),
+        ]))
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Format {
+    MessagePack,
+    Cbor,
+    Amf,
+}
+
+impl Format {
+    /// By Content-Type, or CBOR's self-describe tag
+    fn of(head: &str, body: &[u8]) -> Option<Format> {
+        let content_type = http::header_value(head, "content-type").unwrap_or("").to_lowercase();
+        match content_type.as_str() {
+            t if t.contains("msgpack") || t.contains("messagepack") => Some(Format::MessagePack),
+            t if t.contains("cbor") => Some(Format::Cbor),
+            t if t.contains("x-amf") => Some(Format::Amf),
+            _ if body.starts_with(&[0xd9, 0xd9, 0xf7]) => Some(Format::Cbor),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Format::MessagePack => "MessagePack",
+            Format::Cbor => "CBOR",
+            Format::Amf => "AMF",
+        }
+    }
+}
+
+/// The tree as text; values that follow one another are listed in turn, and where the data
+/// stops making sense is said at the end
+fn decode(format: Format, body: &[u8]) -> String {
+    let mut r = Reader::new(body);
+    let mut out = String::new();
+    let mut count = 0;
+    while !r.done() && count < MAX_VALUES {
+        let node = match format {
+            Format::MessagePack => msgpack(&mut r, 0),
+            Format::Cbor => cbor(&mut r, 0).and_then(|n| n.ok_or_else(|| "unexpected break".to_string())),
+            Format::Amf => Amf::packet(&mut r),
+        };
+        if count > 0 {
+            out.push('\n');
+        }
+        match node {
+            Ok(node) => node.write(0, &mut out),
+            Err(e) => {
+                out.push_str(&format!("// not decoded further: {}", e));
+                break;
+            }
+        }
+        count += 1;
+    }
+    out
+}
+
+/// A decoded body
+pub struct Decoded {
+    /// e.g. `MessagePack response body`
+    pub label: String,
+    pub text: String,
+}
+
+/// Decoded request and response bodies of the formats above
+pub fn find(log: &HttpLog) -> Vec<Decoded> {
+    let raw = log.request_raw.bytes();
+    let (head, body) = http::split_raw(&raw);
+    let head = String::from_utf8_lossy(head);
+    let request = http::decode_body(&head, body);
+    let response = log.response_body();
+    [("request", head.into_owned(), request), ("response", log.response_head(), response)]
+        .into_iter()
+        .filter(|(_, _, body)| !body.is_empty())
+        .filter_map(|(side, head, body)| {
+            let format = Format::of(&head, &body)?;
+            Some(Decoded { label: format!("{} {} body", format.name(), side), text: decode(format, &body) })
+        })
+        .collect()
+}
@@ -0,0 +1,105 @@
+// The command palette: every key action and `:` command by name, fuzzy-searched with the
+// finder's scoring. Actions run as if their key was pressed; commands open the prompt with their
+// name typed, ready for arguments.
+
+use crate::fuzzy;
+use crate::keys;
+
+/// `:` commands and what they do
+pub const COMMANDS: &[(&str, &str)] = &[
+    ("sort", "Sort the list by a column, ascending or descending"),
+    ("find", "Show only entries containing some text"),
+    ("tag", "Show only entries with a tag"),
+    ("filter", "Save, apply or remove a named filter"),
+    ("where", "Show only entries whose JSON response passes an expression"),
+    ("jq", "Pick values out of the selected JSON response"),
+    ("origin", "Show only traffic from the proxy or one of belch's tools"),
+    ("listener", "Show only one listener's traffic"),
+    ("corr", "Show only entries with a correlation ID"),
+    ("mark", "Drop a named time marker"),
+    ("marks", "List the time markers"),
+    ("window", "Show only entries between two markers"),
+    ("trace", "Show where the selected request's tokens came from"),
+    ("ws", "Narrow the WebSocket message list"),
+    ("sse", "Narrow the server-sent event list"),
+    ("save", "Save the session to a project file"),
+    ("open", "Open a project file"),
+    ("export", "Export entries as HAR, JSON lines, Burp XML, Markdown or tests"),
+    ("note", "Note the selected entry"),
+    ("triage", "Set the selected entry's triage status, or export and import the sheet"),
+    ("redact", "Mask credentials in exports and projects"),
+    ("read-only", "Only capture, everywhere or for a host: no interception, rewriting or active tools"),
+    ("repeater", "Bring the Repeater back"),
+    ("repeat", "Replay the selected request on a schedule or in a burst"),
+    ("fuzz", "Send the Repeater's request once per payload"),
+    ("payloads", "List or import payload lists for the fuzzer"),
+    ("authz", "Replay the listed requests as each identity"),
+    ("smuggle", "Probe the selected entry's host for request smuggling"),
+    ("macro", "Record or replay a request sequence such as a login"),
+    ("discover", "Fetch robots.txt and sitemap.xml of in-scope hosts"),
+    ("spider", "Follow in-scope links from the captured pages"),
+    ("form", "Open a form of the selected page in the Repeater"),
+    ("diagnose", "Compare a URL fetched directly and through belch"),
+    ("pin", "Check whether a client pins certificates"),
+    ("cors-dev", "Answer CORS preflights for a host"),
+    ("csrf", "Refresh a CSRF token before each replay"),
+    ("cookie", "Set, unset or list cookies sent to a domain"),
+    ("cookies", "Open the cookie jar"),
+    ("edit", "Edit the intercepted request's body in $EDITOR"),
+    ("decode", "Set the Decoder tab's transform chain"),
+    ("tls", "Change how belch connects upstream to a host"),
+    ("normalize", "Rewrite header casing and folding on the way out"),
+    ("fault", "Inject delays, drops and errors for a host"),
+    ("throttle", "Limit the request rate per host"),
+    ("plain-http", "Talk plain HTTP to a host that must stay off TLS"),
+    ("no-cache", "Strip caching headers so every request reaches the server"),
+    ("dns", "Show or flush the DNS cache"),
+    ("mirror", "Copy requests to a second upstream and compare the answers"),
+    ("rewrite", "List, add or delete match/replace rules"),
+    ("config", "Show or save config.toml"),
+];
+
+#[derive(Clone, Copy)]
+pub enum Target {
+    /// Built-in key of a `keys::ACTIONS` entry
+    Key(char),
+    Command(&'static str),
+}
+
+pub struct Palette {
+    pub query: String,
+    /// What each line runs, and the line as shown and matched
+    pub items: Vec<(Target, String)>,
+    /// Indices into `items`, best first
+    pub results: Vec<usize>,
+    pub selected: usize,
+}
+
+impl Palette {
+    pub fn open() -> Self {
+        // An action a rebinding left without a key still runs from here
+        let actions = keys::ACTIONS.iter().map(|(name, builtin, about)| {
+            let key = keys::key(*builtin).map_or("-".to_string(), |k| k.to_string());
+            (Target::Key(*builtin), format!("{:<18} {:<3} {}", name, key, about))
+        });
+        let commands = COMMANDS.iter().map(|(name, about)| (Target::Command(name), format!(":{:<21} {}", name, about)));
+        let mut palette = Palette { query: String::new(), items: actions.chain(commands).collect(), results: Vec::new(), selected: 0 };
+        palette.update();
+        palette
+    }
+
+    /// Rank the items against the query; listed in order while it is empty
+    pub fn update(&mut self) {
+        let mut scored: Vec<(i64, usize)> = self.items.iter().enumerate()
+            .filter_map(|(i, (_, line))| fuzzy::score(&self.query, line).map(|s| (s, i)))
+            .collect();
+        // Stable, so ties keep the listed order
+        scored.sort_by_key(|(s, _)| -s);
+        self.results = scored.into_iter().map(|(_, i)| i).collect();
+        self.selected = 0;
+    }
+
+    pub fn chosen(&self) -> Option<Target> {
+        self.results.get(self.selected).map(|i| self.items[*i].0)
+    }
+}
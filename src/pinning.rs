@@ -0,0 +1,166 @@
+// Certificate pinning check: a client's next TLS connections to one host are each served a
+// certificate wrong in a different way, and whether the client goes on to send a request tells
+// which ones it accepts
+
+use std::sync::{Arc, Mutex};
+
+use tracing::info;
+
+use crate::app::App;
+use crate::issues::{Issue, Severity};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Trial {
+    /// Signed by nobody
+    SelfSigned,
+    /// Signed by the belch CA for another name
+    WrongHost,
+    /// Signed by the belch CA, expired yesterday
+    Expired,
+    /// The leaf belch always serves
+    Valid,
+}
+
+impl Trial {
+    /// Bad certificates first, so the client is left working with the last one
+    pub const ALL: [Trial; 4] = [Trial::SelfSigned, Trial::WrongHost, Trial::Expired, Trial::Valid];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Trial::SelfSigned => "self-signed",
+            Trial::WrongHost => "wrong host",
+            Trial::Expired => "expired",
+            Trial::Valid => "belch CA",
+        }
+    }
+}
+
+enum State {
+    Waiting,
+    /// Served to a connection that hasn't finished its handshake and first request yet
+    Running,
+    Accepted,
+    Rejected(String),
+}
+
+pub struct PinCheck {
+    pub host: String,
+    trials: Vec<(Trial, State)>,
+}
+
+impl PinCheck {
+    fn done(&self) -> bool {
+        self.trials.iter().all(|(_, s)| matches!(s, State::Accepted | State::Rejected(_)))
+    }
+
+    fn accepted(&self, trial: Trial) -> Option<bool> {
+        self.trials.iter().find(|(t, _)| *t == trial).and_then(|(_, s)| match s {
+            State::Accepted => Some(true),
+            State::Rejected(_) => Some(false),
+            _ => None,
+        })
+    }
+
+    /// Each trial's outcome so far, then what they add up to once all are in
+    pub fn summary(&self) -> String {
+        let states: Vec<String> = self.trials.iter().map(|(t, s)| {
+            let state = match s {
+                State::Waiting => "waiting",
+                State::Running => "connecting",
+                State::Accepted => "accepted",
+                State::Rejected(_) => "rejected",
+            };
+            format!("{} {}", t.label(), state)
+        }).collect();
+        let mut text = format!("Pinning check {}: {}", self.host, states.join(", "));
+        if !self.done() {
+            let left = self.trials.iter().filter(|(_, s)| matches!(s, State::Waiting)).count();
+            if left > 0 {
+                text.push_str(&format!(" — make the app connect {} more time(s)", left));
+            }
+            return text;
+        }
+        let broken: Vec<&str> = Trial::ALL[..3].iter().filter(|t| self.accepted(**t) == Some(true)).map(|t| t.label()).collect();
+        let verdict = match (broken.is_empty(), self.accepted(Trial::Valid)) {
+            (false, _) => format!("certificate validation is broken (accepts {})", broken.join(", ")),
+            (true, Some(true)) => "validates certificates but doesn't pin: the belch CA is accepted".to_string(),
+            _ => "pins its certificates, or doesn't trust the belch CA".to_string(),
+        };
+        text.push_str(&format!(" — {}", verdict));
+        text
+    }
+
+    fn issues(&self) -> Vec<Issue> {
+        let issue = |severity, title: String, detail: String| Issue { severity, host: self.host.clone(), url: format!("https://{}/", self.host), title, detail };
+        let mut out: Vec<Issue> = Trial::ALL[..3].iter()
+            .filter(|t| self.accepted(**t) == Some(true))
+            .map(|t| issue(Severity::High, format!("Client accepts {} certificates", t.label()), "found by the pinning check".to_string()))
+            .collect();
+        if out.is_empty() && self.accepted(Trial::Valid) == Some(true) {
+            out.push(issue(Severity::Low, "No certificate pinning".to_string(), "the client accepted a certificate from the belch CA".to_string()));
+        }
+        out
+    }
+}
+
+/// The trial for a new TLS connection to `host`, if a check on it still has one waiting
+pub fn begin(app: &Arc<Mutex<App>>, host: &str) -> Option<Trial> {
+    let mut app = app.lock().unwrap();
+    let check = app.pin_check.as_mut().filter(|c| c.host.eq_ignore_ascii_case(host))?;
+    let (trial, state) = check.trials.iter_mut().find(|(_, s)| matches!(s, State::Waiting))?;
+    *state = State::Running;
+    Some(*trial)
+}
+
+/// Whether the client went on to send a request over the trial's connection
+pub fn finish(app: &Arc<Mutex<App>>, host: &str, trial: Trial, outcome: Result<(), String>) {
+    let mut app = app.lock().unwrap();
+    let Some(check) = app.pin_check.as_mut().filter(|c| c.host.eq_ignore_ascii_case(host)) else { return };
+    if let Some((_, state)) = check.trials.iter_mut().find(|(t, _)| *t == trial) {
+        *state = match outcome {
+            Ok(()) => State::Accepted,
+            Err(e) => State::Rejected(e),
+        };
+    }
+    let summary = check.summary();
+    let issues = if check.done() { check.issues() } else { Vec::new() };
+    if let Some((_, State::Rejected(reason))) = check.trials.iter().find(|(t, _)| *t == trial) {
+        info!("pinning check {}: {} certificate rejected: {}", host, trial.label(), reason);
+    }
+    if check.done() {
+        info!("{}", summary);
+    }
+    for issue in issues {
+        app.add_issue(issue);
+    }
+    app.set_status(summary);
+}
+
+/// `:pin [HOST]` checks the selected entry's host or HOST; `:pin` again shows progress, `:pin stop` ends it
+pub fn command(app: &mut App, args: &[&str]) {
+    let host = match args {
+        ["stop"] => {
+            app.pin_check = None;
+            return app.set_status("Pinning check stopped");
+        }
+        [] if app.pin_check.is_some() => {
+            let summary = app.pin_check.as_ref().map(PinCheck::summary).unwrap_or_default();
+            return app.set_status(summary);
+        }
+        [] => match app.selected_log().filter(|l| l.tls) {
+            Some(log) => log.host.clone(),
+            None => return app.set_status("usage: :pin HOST, or select an HTTPS entry | :pin stop"),
+        },
+        [host] => host.to_lowercase(),
+        _ => return app.set_status("usage: :pin [HOST] | :pin stop"),
+    };
+    if app.refuse("the pinning check") {
+        return;
+    }
+    if app.passthrough.matches(&host) {
+        return app.set_status(format!("{} is on the passthrough list, so belch never serves it a certificate", host));
+    }
+    let check = PinCheck { host, trials: Trial::ALL.iter().map(|t| (*t, State::Waiting)).collect() };
+    app.set_status(check.summary());
+    app.pin_check = Some(check);
+}
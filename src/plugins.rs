@@ -0,0 +1,154 @@
+// Plugins: shared libraries in the config dir's `plugins/`, loaded at startup through a small C ABI
+//
+// Every plugin exports `const char *belch_plugin_name(void)` and, if it returns memory,
+// `void belch_free(unsigned char *ptr, size_t len)`. Each hook is optional and returns
+// NULL for "nothing to do", or a buffer it allocated with its length in `*out_len`:
+//
+//   unsigned char *belch_request(const unsigned char *req, size_t len, size_t *out_len)
+//       rewrite a request before it is forwarded
+//   unsigned char *belch_response(const unsigned char *resp, size_t len, size_t *out_len)
+//       rewrite a response before it goes back to the client; the entry keeps the original
+//   unsigned char *belch_describe(const unsigned char *req, size_t req_len,
+//                                 const unsigned char *resp, size_t resp_len, size_t *out_len)
+//       UTF-8 text shown in its own section of the detail pane, e.g. a decoded token; asked
+//       once per entry when it is selected
+//   unsigned char *belch_export(const unsigned char *jsonl, size_t len, size_t *out_len)
+//       turn the listed entries, as `jsonl` export lines, into a file for `:export NAME`
+
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use bytes::Bytes;
+use tracing::{info, warn};
+
+use crate::config;
+
+type NameFn = unsafe extern "C" fn() -> *const c_char;
+type FreeFn = unsafe extern "C" fn(*mut u8, usize);
+type TransformFn = unsafe extern "C" fn(*const u8, usize, *mut usize) -> *mut u8;
+type DescribeFn = unsafe extern "C" fn(*const u8, usize, *const u8, usize, *mut usize) -> *mut u8;
+
+pub struct Plugin {
+    pub name: String,
+    free: Option<FreeFn>,
+    request: Option<TransformFn>,
+    response: Option<TransformFn>,
+    describe: Option<DescribeFn>,
+    export: Option<TransformFn>,
+}
+
+// The library stays loaded for the life of the process, and hooks are plain functions
+unsafe impl Send for Plugin {}
+unsafe impl Sync for Plugin {}
+
+#[cfg(unix)]
+fn open(path: &Path) -> Result<Plugin, String> {
+    let c_path = CString::new(path.to_string_lossy().as_bytes()).map_err(|e| e.to_string())?;
+    // SAFETY: dlopen runs the library's initializers; plugins are code the user chose to install
+    let lib = unsafe { libc::dlopen(c_path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+    if lib.is_null() {
+        // SAFETY: dlerror returns a thread-local message or null
+        let err = unsafe { libc::dlerror() };
+        return Err(if err.is_null() { "dlopen failed".to_string() } else { unsafe { CStr::from_ptr(err) }.to_string_lossy().to_string() });
+    }
+    let symbol = |name: &str| -> *mut c_void {
+        let name = CString::new(name).unwrap();
+        // SAFETY: `lib` is a live handle from dlopen
+        unsafe { libc::dlsym(lib, name.as_ptr()) }
+    };
+    let name = symbol("belch_plugin_name");
+    if name.is_null() {
+        return Err("no belch_plugin_name symbol".to_string());
+    }
+    // SAFETY: each symbol is cast to the signature the ABI above documents for it
+    unsafe {
+        let name = CStr::from_ptr(std::mem::transmute::<*mut c_void, NameFn>(name)()).to_string_lossy().to_string();
+        let free = symbol("belch_free");
+        let hook = |s: &str| Some(symbol(s)).filter(|p| !p.is_null());
+        Ok(Plugin {
+            name,
+            free: (!free.is_null()).then(|| std::mem::transmute::<*mut c_void, FreeFn>(free)),
+            request: hook("belch_request").map(|p| std::mem::transmute::<*mut c_void, TransformFn>(p)),
+            response: hook("belch_response").map(|p| std::mem::transmute::<*mut c_void, TransformFn>(p)),
+            describe: hook("belch_describe").map(|p| std::mem::transmute::<*mut c_void, DescribeFn>(p)),
+            export: hook("belch_export").map(|p| std::mem::transmute::<*mut c_void, TransformFn>(p)),
+        })
+    }
+}
+
+#[cfg(not(unix))]
+fn open(_path: &Path) -> Result<Plugin, String> {
+    Err("plugins are only supported on Unix".to_string())
+}
+
+static PLUGINS: OnceLock<Vec<Plugin>> = OnceLock::new();
+
+/// Load every `.so`/`.dylib` in `plugins/`; once, at startup
+pub fn init() {
+    let dir = config::config_dir().join("plugins");
+    let mut paths: Vec<_> = fs::read_dir(&dir).into_iter().flatten().flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e == "so" || e == "dylib"))
+        .collect();
+    paths.sort();
+    let mut loaded = Vec::new();
+    for path in paths {
+        match open(&path) {
+            Ok(p) => {
+                info!("plugin {} loaded from {}", p.name, path.display());
+                loaded.push(p);
+            }
+            Err(e) => warn!("plugin {} not loaded: {}", path.display(), e),
+        }
+    }
+    let _ = PLUGINS.set(loaded);
+}
+
+pub fn all() -> &'static [Plugin] {
+    PLUGINS.get().map_or(&[], Vec::as_slice)
+}
+
+impl Plugin {
+    /// Copy out a buffer the plugin returned and hand it back to be freed
+    fn take(&self, ptr: *mut u8, len: usize) -> Option<Vec<u8>> {
+        if ptr.is_null() {
+            return None;
+        }
+        // SAFETY: the ABI promises `len` readable bytes at `ptr`, owned by the plugin until freed
+        let out = unsafe { std::slice::from_raw_parts(ptr, len) }.to_vec();
+        if let Some(free) = self.free {
+            unsafe { free(ptr, len) };
+        }
+        Some(out)
+    }
+
+    fn transform(&self, hook: Option<TransformFn>, data: &[u8]) -> Option<Vec<u8>> {
+        let mut len = 0;
+        // SAFETY: `data` is valid for the call and `len` is a live out-parameter
+        let ptr = unsafe { hook?(data.as_ptr(), data.len(), &mut len) };
+        self.take(ptr, len)
+    }
+
+    pub fn describe(&self, request: &[u8], response: &[u8]) -> Option<String> {
+        let mut len = 0;
+        // SAFETY: as for `transform`
+        let ptr = unsafe { self.describe?(request.as_ptr(), request.len(), response.as_ptr(), response.len(), &mut len) };
+        self.take(ptr, len).map(|t| String::from_utf8_lossy(&t).to_string())
+    }
+
+    pub fn export(&self, jsonl: &[u8]) -> Option<Vec<u8>> {
+        self.transform(self.export, jsonl)
+    }
+}
+
+/// `request` after every plugin's request hook, in load order
+pub fn process_request(request: Vec<u8>) -> Vec<u8> {
+    all().iter().fold(request, |req, p| p.transform(p.request, &req).unwrap_or(req))
+}
+
+/// `response` after every plugin's response hook, in load order
+pub fn process_response(response: Bytes) -> Bytes {
+    all().iter().filter(|p| p.response.is_some()).fold(response, |resp, p| p.transform(p.response, &resp).map_or(resp, Bytes::from))
+}
@@ -0,0 +1,264 @@
+// Readable text of an HTML page for the detail pane: scripts and styles dropped, headings,
+// links, images and forms kept as short markers
+
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use crate::transform;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Text,
+    Title,
+    Heading,
+    Link,
+    /// Where a link goes
+    Href,
+    Form,
+    Field,
+}
+
+pub type Line = Vec<(Kind, String)>;
+
+/// Elements whose content is never shown
+const SKIPPED: [&str; 7] = ["script", "style", "template", "svg", "noscript", "select", "textarea"];
+/// Elements that start on a new line
+const BLOCKS: [&str; 22] = [
+    "address", "article", "aside", "blockquote", "dd", "div", "dl", "dt", "fieldset", "figure", "footer",
+    "header", "hr", "main", "nav", "ol", "p", "pre", "section", "table", "tr", "ul",
+];
+
+fn attrs() -> &'static Regex {
+    static ATTRS: OnceLock<Regex> = OnceLock::new();
+    ATTRS.get_or_init(|| Regex::new(r#"([A-Za-z_:][-A-Za-z0-9_:.]*)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'=<>`]+))"#).unwrap())
+}
+
+/// Value of an attribute in a tag's source, entities decoded
+pub fn attr(tag: &str, name: &str) -> Option<String> {
+    attrs().captures_iter(tag)
+        .find(|c| c[1].eq_ignore_ascii_case(name))
+        .map(|c| transform::html_unescape(c.get(2).or(c.get(3)).or(c.get(4)).map_or("", |m| m.as_str())))
+}
+
+/// Lowercase name of a tag from its source between `<` and `>`, and whether it closes
+pub fn tag_name(tag: &str) -> (String, bool) {
+    let closing = tag.starts_with('/');
+    let name = tag.trim_start_matches('/').split(|c: char| c.is_whitespace() || c == '/').next().unwrap_or("");
+    (name.to_ascii_lowercase(), closing)
+}
+
+pub fn is_html(content_type: &str) -> bool {
+    content_type.to_ascii_lowercase().contains("html")
+}
+
+#[derive(Default)]
+struct Render {
+    lines: Vec<Line>,
+    line: Line,
+    /// A space is owed before the next text on this line
+    space: bool,
+    heading: bool,
+    title: bool,
+    pre: bool,
+    /// Inside a form, where each field gets a line of its own
+    form: bool,
+    /// Target of the link being read
+    link: Option<String>,
+}
+
+impl Render {
+    fn push(&mut self, kind: Kind, text: &str) {
+        let kind = match kind {
+            Kind::Text if self.title => Kind::Title,
+            Kind::Text if self.link.is_some() => Kind::Link,
+            Kind::Text if self.heading => Kind::Heading,
+            k => k,
+        };
+        if self.pre {
+            let mut parts = text.split('\n');
+            if let Some(first) = parts.next() {
+                self.piece(kind, first.to_string());
+            }
+            for part in parts {
+                self.newline();
+                self.piece(kind, part.to_string());
+            }
+            return;
+        }
+        let words: Vec<&str> = text.split_whitespace().collect();
+        if words.is_empty() {
+            self.space |= !text.is_empty();
+            return;
+        }
+        if text.starts_with(char::is_whitespace) {
+            self.space = true;
+        }
+        let leading = if self.space && !self.line.is_empty() { " " } else { "" };
+        self.piece(kind, format!("{}{}", leading, words.join(" ")));
+        self.space = text.ends_with(char::is_whitespace);
+    }
+
+    fn piece(&mut self, kind: Kind, text: String) {
+        match self.line.last_mut() {
+            Some((k, last)) if *k == kind => last.push_str(&text),
+            _ => self.line.push((kind, text)),
+        }
+    }
+
+    fn newline(&mut self) {
+        if !self.line.is_empty() {
+            self.lines.push(std::mem::take(&mut self.line));
+        }
+        self.space = false;
+    }
+
+    /// A new line with an empty one before it, unless there already is one
+    fn paragraph(&mut self) {
+        self.newline();
+        if self.lines.last().is_some_and(|l| !l.is_empty()) {
+            self.lines.push(Vec::new());
+        }
+    }
+
+    fn field(&mut self, text: String) {
+        let leading = if self.line.is_empty() { "" } else { " " };
+        self.piece(Kind::Field, format!("{}[{}", leading, text));
+        self.field_end();
+    }
+
+    /// Closes a field's bracket; fields of a form end their line
+    fn field_end(&mut self) {
+        self.piece(Kind::Field, "]".to_string());
+        self.space = true;
+        if self.form {
+            self.newline();
+        }
+    }
+
+    fn open(&mut self, name: &str, tag: &str) {
+        match name {
+            "title" => {
+                self.paragraph();
+                self.title = true;
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.paragraph();
+                self.piece(Kind::Heading, format!("{} ", "#".repeat(name[1..].parse().unwrap_or(1))));
+                self.heading = true;
+            }
+            "br" => self.newline(),
+            "li" => {
+                self.newline();
+                self.piece(Kind::Text, "• ".to_string());
+            }
+            "td" | "th" if !self.line.is_empty() => self.piece(Kind::Text, "  │ ".to_string()),
+            "a" => self.link = Some(attr(tag, "href").unwrap_or_default()),
+            "img" => {
+                if let Some(alt) = attr(tag, "alt").filter(|a| !a.trim().is_empty()) {
+                    self.field(format!("image: {}", alt.trim()));
+                }
+            }
+            "form" => {
+                self.paragraph();
+                let method = attr(tag, "method").unwrap_or("GET".into()).to_uppercase();
+                let action = attr(tag, "action").filter(|a| !a.is_empty()).unwrap_or("(this page)".into());
+                self.piece(Kind::Form, format!("Form: {} {}", method, action));
+                self.newline();
+                self.form = true;
+            }
+            "input" => {
+                let kind = attr(tag, "type").unwrap_or("text".into()).to_lowercase();
+                let name = attr(tag, "name").unwrap_or_default();
+                let value = attr(tag, "value").unwrap_or_default();
+                match kind.as_str() {
+                    "hidden" => self.field(format!("hidden {}={}", name, value)),
+                    "submit" | "button" | "reset" | "image" => self.field(if value.is_empty() { kind } else { value }),
+                    _ => {
+                        let hint = attr(tag, "placeholder").or((!value.is_empty()).then_some(value)).unwrap_or_default();
+                        self.field(format!("{}: {}{}", name, kind, if hint.is_empty() { String::new() } else { format!(" \"{}\"", hint) }));
+                    }
+                }
+            }
+            "textarea" => self.field(format!("{}: textarea", attr(tag, "name").unwrap_or_default())),
+            "select" => self.field(format!("{}: select", attr(tag, "name").unwrap_or_default())),
+            "button" => {
+                self.piece(Kind::Field, format!("{}[", if self.line.is_empty() { "" } else { " " }));
+                self.space = false;
+            }
+            "pre" => {
+                self.paragraph();
+                self.pre = true;
+            }
+            "p" | "ul" | "ol" | "table" | "blockquote" => self.paragraph(),
+            _ if BLOCKS.contains(&name) => self.newline(),
+            _ => {}
+        }
+    }
+
+    fn close(&mut self, name: &str) {
+        match name {
+            "title" => {
+                self.title = false;
+                self.paragraph();
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.heading = false;
+                self.paragraph();
+            }
+            "a" => {
+                if let Some(href) = self.link.take().filter(|h| !h.is_empty() && !h.starts_with('#') && !h.starts_with("javascript:")) {
+                    self.piece(Kind::Href, format!(" <{}>", href));
+                }
+            }
+            "button" => self.field_end(),
+            "pre" => {
+                self.pre = false;
+                self.paragraph();
+            }
+            "form" => {
+                self.form = false;
+                self.paragraph();
+            }
+            "p" | "ul" | "ol" | "table" | "blockquote" => self.paragraph(),
+            "li" | "td" | "th" => {}
+            _ if BLOCKS.contains(&name) => self.newline(),
+            _ => {}
+        }
+    }
+}
+
+/// Lines of styled pieces: the title, then the text of the page as it would read
+pub fn render(html: &str) -> Vec<Line> {
+    let lower = html.to_ascii_lowercase();
+    let mut out = Render::default();
+    let mut pos = 0;
+    while pos < html.len() {
+        let Some(start) = html[pos..].find('<').map(|i| i + pos) else {
+            out.push(Kind::Text, &transform::html_unescape(&html[pos..]));
+            break;
+        };
+        out.push(Kind::Text, &transform::html_unescape(&html[pos..start]));
+        if lower[start..].starts_with("<!--") {
+            pos = lower[start..].find("-->").map_or(html.len(), |e| start + e + 3);
+            continue;
+        }
+        let Some(end) = html[start..].find('>').map(|i| i + start) else { break };
+        let tag = &html[start + 1..end];
+        pos = end + 1;
+        let (name, closing) = tag_name(tag);
+        if closing {
+            out.close(&name);
+            continue;
+        }
+        out.open(&name, tag);
+        if SKIPPED.contains(&name.as_str()) && !tag.ends_with('/') {
+            pos = lower[pos..].find(&format!("</{}", name)).map_or(html.len(), |e| pos + e);
+        }
+    }
+    out.newline();
+    while out.lines.last().is_some_and(|l| l.is_empty()) {
+        out.lines.pop();
+    }
+    out.lines
+}
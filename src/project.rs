@@ -0,0 +1,317 @@
+// `.belch` project files: a whole session in one compressed, checksummed file
+//
+// Layout: MAGIC, format version (u16 LE), sections, a JSON index of the sections,
+// then the index offset (u64 LE) and MAGIC again. Each section is zstd-compressed JSON
+// (gzip in version 1) with the SHA-256 of its uncompressed bytes in the index. Readers skip section kinds
+// they do not know, so adding one needs no version bump; the version only changes
+// when an existing section changes incompatibly.
+
+use std::fs;
+use std::io::Read;
+use std::net::IpAddr;
+use std::path::Path;
+use std::time::Duration;
+
+use base64::Engine;
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::app::{App, HttpLog, Origin, SortColumn};
+use crate::dns;
+use crate::http;
+use crate::issues::Issue;
+use crate::markers::Marker;
+use crate::redact;
+use crate::triage;
+use crate::websocket::WsMessage;
+
+const MAGIC: &[u8; 8] = b"BELCHPRJ";
+const VERSION: u16 = 2;
+
+#[derive(Serialize, Deserialize)]
+struct Section {
+    kind: String,
+    offset: u64,
+    len: u64,
+    sha256: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    url: String,
+    host: String,
+    port: u16,
+    tls: bool,
+    #[serde(default)]
+    sni: Option<String>,
+    #[serde(default)]
+    client: Option<IpAddr>,
+    /// Unix time in milliseconds
+    started: Option<i64>,
+    duration_us: u64,
+    /// Detail pane text
+    request: String,
+    response: String,
+    /// Base64 of the bytes on the wire
+    request_raw: String,
+    response_raw: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    websocket: bool,
+    #[serde(default)]
+    conn: u64,
+    /// Empty for live traffic
+    #[serde(default)]
+    origin: String,
+    #[serde(default)]
+    listener: String,
+    #[serde(default)]
+    resolved: Option<IpAddr>,
+    #[serde(default)]
+    dns_us: u64,
+    #[serde(default)]
+    note: String,
+    /// Triage status name, empty for none
+    #[serde(default)]
+    triage: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct WsRecord {
+    /// Position in the entries section
+    entry: usize,
+    at_us: u64,
+    to_server: bool,
+    opcode: u8,
+    /// Base64
+    payload: String,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Settings {
+    scope: Vec<String>,
+    sort: String,
+    sort_desc: bool,
+    tag_filter: Option<String>,
+    #[serde(default)]
+    origin_filter: Option<String>,
+    /// Name and unix time in milliseconds
+    #[serde(default)]
+    markers: Vec<(String, i64)>,
+}
+
+impl Entry {
+    fn from_log(log: &HttpLog) -> Self {
+        let b64 = base64::engine::general_purpose::STANDARD;
+        Entry {
+            url: log.url.clone(),
+            host: log.host.clone(),
+            port: log.port,
+            tls: log.tls,
+            sni: log.sni.clone(),
+            client: log.client,
+            started: log.started.map(|t| (t.unix_timestamp_nanos() / 1_000_000) as i64),
+            duration_us: log.duration.as_micros() as u64,
+            request: log.request.text(),
+            response: log.response_text(),
+            request_raw: b64.encode(log.request_raw.bytes()),
+            response_raw: b64.encode(log.response_raw.bytes()),
+            tags: log.tags.clone(),
+            websocket: log.websocket,
+            conn: log.conn,
+            origin: if log.origin == Origin::Live { String::new() } else { log.origin.name().to_string() },
+            listener: log.listener.clone(),
+            resolved: log.resolved.map(|l| l.addr),
+            dns_us: log.resolved.map_or(0, |l| l.took.as_micros() as u64),
+            note: log.note.clone(),
+            triage: log.triage.map_or("", triage::Status::name).to_string(),
+        }
+    }
+
+    fn into_log(self) -> HttpLog {
+        let b64 = base64::engine::general_purpose::STANDARD;
+        HttpLog {
+            url: self.url,
+            host: self.host,
+            port: self.port,
+            tls: self.tls,
+            sni: self.sni,
+            client: self.client,
+            started: self.started.and_then(|ms| OffsetDateTime::from_unix_timestamp_nanos(ms as i128 * 1_000_000).ok()),
+            duration: Duration::from_micros(self.duration_us),
+            request: self.request.into(),
+            response: self.response.into(),
+            request_raw: b64.decode(self.request_raw).unwrap_or_default().into(),
+            response_raw: b64.decode(self.response_raw).unwrap_or_default().into(),
+            tags: self.tags,
+            websocket: self.websocket,
+            conn: self.conn,
+            origin: Origin::parse(&self.origin).unwrap_or_default(),
+            listener: self.listener,
+            resolved: self.resolved.map(|addr| dns::Lookup { addr, took: Duration::from_micros(self.dns_us) }),
+            note: self.note,
+            triage: triage::Status::parse(&self.triage),
+            ..Default::default()
+        }
+    }
+}
+
+fn compress(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    zstd::stream::encode_all(data, zstd::DEFAULT_COMPRESSION_LEVEL)
+}
+
+/// A section as written by format `version`
+fn decompress(version: u16, packed: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    match version {
+        1 => GzDecoder::new(packed).read_to_end(&mut out)?,
+        _ => zstd::stream::Decoder::new(packed)?.read_to_end(&mut out)?,
+    };
+    Ok(out)
+}
+
+/// Serialize the session: entries, WebSocket messages, findings, command history and view settings
+pub fn to_bytes(app: &App) -> Result<Vec<u8>, String> {
+    let entries: Vec<Entry> = app.logs.iter().map(|l| Entry::from_log(&redact::outgoing(app, l))).collect();
+    let b64 = base64::engine::general_purpose::STANDARD;
+    let messages: Vec<WsRecord> = app.ws_messages.iter().map(|m| WsRecord {
+        entry: m.entry,
+        at_us: m.at.as_micros() as u64,
+        to_server: m.to_server,
+        opcode: m.opcode,
+        payload: b64.encode(if app.redact { app.redactions.bytes(&m.payload) } else { m.payload.to_vec() }),
+    }).collect();
+    let issues: Vec<Issue> = app.issues.iter().map(|i| match app.redact {
+        true => Issue { url: app.redactions.text(&i.url), detail: app.redactions.text(&i.detail), ..i.clone() },
+        false => i.clone(),
+    }).collect();
+    let settings = Settings {
+        scope: app.scope.hosts.clone(),
+        sort: app.sort.title().to_string(),
+        sort_desc: app.sort_desc,
+        tag_filter: app.tag_filter.clone(),
+        origin_filter: app.origin_filter.map(|o| o.name().to_string()),
+        markers: app.markers.iter().map(|m| (m.name.clone(), (m.at.unix_timestamp_nanos() / 1_000_000) as i64)).collect(),
+    };
+    let sections = [
+        ("entries", serde_json::to_vec(&entries)),
+        ("findings", serde_json::to_vec(&issues)),
+        ("history", serde_json::to_vec(&app.history.lines)),
+        ("settings", serde_json::to_vec(&settings)),
+        ("websocket", serde_json::to_vec(&messages)),
+    ];
+    let mut out = MAGIC.to_vec();
+    out.extend_from_slice(&VERSION.to_le_bytes());
+    let mut index = Vec::new();
+    for (kind, json) in sections {
+        let json = json.map_err(|e| e.to_string())?;
+        let packed = compress(&json).map_err(|e| e.to_string())?;
+        index.push(Section { kind: kind.to_string(), offset: out.len() as u64, len: packed.len() as u64, sha256: http::sha256_hex(&json) });
+        out.extend_from_slice(&packed);
+    }
+    let index_offset = out.len() as u64;
+    out.extend_from_slice(&serde_json::to_vec(&index).map_err(|e| e.to_string())?);
+    out.extend_from_slice(&index_offset.to_le_bytes());
+    out.extend_from_slice(MAGIC);
+    Ok(out)
+}
+
+/// Write the session to `path`, replacing it only once the new file is complete
+pub fn save(app: &App, path: &Path) -> Result<(), String> {
+    let data = to_bytes(app)?;
+    let tmp = path.with_extension("belch.tmp");
+    fs::write(&tmp, data).and_then(|_| fs::rename(&tmp, path)).map_err(|e| e.to_string())
+}
+
+/// Sections of a project file by kind, decompressed and verified
+fn read_sections(data: &[u8]) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let header = MAGIC.len() + 2;
+    let trailer = 8 + MAGIC.len();
+    if data.len() < header + trailer || &data[..MAGIC.len()] != MAGIC || &data[data.len() - MAGIC.len()..] != MAGIC {
+        return Err("not a belch project file, or truncated".into());
+    }
+    let version = u16::from_le_bytes([data[MAGIC.len()], data[MAGIC.len() + 1]]);
+    if version > VERSION {
+        return Err(format!("project format {} needs a newer belch (this one reads up to {})", version, VERSION));
+    }
+    let end = data.len() - trailer;
+    let mut offset = [0u8; 8];
+    offset.copy_from_slice(&data[end..end + 8]);
+    let index_offset = u64::from_le_bytes(offset) as usize;
+    let index: Vec<Section> = data.get(index_offset..end)
+        .and_then(|i| serde_json::from_slice(i).ok())
+        .ok_or("project index is damaged")?;
+    let mut out = Vec::new();
+    for section in index {
+        let packed = section.offset.checked_add(section.len)
+            .and_then(|end| data.get(usize::try_from(section.offset).ok()?..usize::try_from(end).ok()?))
+            .ok_or_else(|| format!("section `{}` lies outside the file", section.kind))?;
+        let json = decompress(version, packed).map_err(|e| format!("section `{}` does not decompress: {}", section.kind, e))?;
+        if http::sha256_hex(&json) != section.sha256 {
+            return Err(format!("section `{}` fails its checksum", section.kind));
+        }
+        out.push((section.kind, json));
+    }
+    Ok(out)
+}
+
+/// Replace the session with the one saved in `path`; returns the number of entries
+pub fn open(app: &mut App, path: &Path) -> Result<usize, String> {
+    let data = fs::read(path).map_err(|e| e.to_string())?;
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut issues: Vec<Issue> = Vec::new();
+    let mut settings = Settings::default();
+    let mut messages: Vec<WsRecord> = Vec::new();
+    let mut history: Vec<String> = Vec::new();
+    for (kind, json) in read_sections(&data)? {
+        let bad = |e: serde_json::Error| format!("section `{}`: {}", kind, e);
+        match kind.as_str() {
+            "entries" => entries = serde_json::from_slice(&json).map_err(bad)?,
+            "findings" => issues = serde_json::from_slice(&json).map_err(bad)?,
+            "history" => history = serde_json::from_slice(&json).map_err(bad)?,
+            "settings" => settings = serde_json::from_slice(&json).map_err(bad)?,
+            "websocket" => messages = serde_json::from_slice(&json).map_err(bad)?,
+            _ => {}
+        }
+    }
+    let count = entries.len();
+    app.clear_logs();
+    for entry in entries {
+        // Live connections are numbered after the loaded ones
+        app.connections_seen = app.connections_seen.max(entry.conn);
+        let mut log = entry.into_log();
+        let found = log.analyze();
+        app.ingest(log, found);
+    }
+    let b64 = base64::engine::general_purpose::STANDARD;
+    for m in messages {
+        app.push_ws(WsMessage {
+            entry: m.entry,
+            at: Duration::from_micros(m.at_us),
+            to_server: m.to_server,
+            opcode: m.opcode,
+            payload: b64.decode(m.payload).unwrap_or_default().into(),
+        });
+    }
+    for issue in issues {
+        app.add_issue(issue);
+    }
+    // Hosts added to scope before opening stay in it
+    for host in settings.scope {
+        if !app.scope.hosts.contains(&host) {
+            app.scope.hosts.push(host);
+        }
+    }
+    app.sort = SortColumn::ALL.iter().copied().find(|c| c.title() == settings.sort).unwrap_or_default();
+    app.sort_desc = settings.sort_desc;
+    app.tag_filter = settings.tag_filter;
+    app.origin_filter = settings.origin_filter.as_deref().and_then(Origin::parse);
+    app.markers = settings.markers.into_iter()
+        .filter_map(|(name, ms)| Some(Marker { name, at: OffsetDateTime::from_unix_timestamp_nanos(ms as i128 * 1_000_000).ok()? }))
+        .collect();
+    app.window = None;
+    app.history.merge(&history);
+    Ok(count)
+}
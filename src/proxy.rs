@@ -0,0 +1,344 @@
+#[cfg(unix)]
+use std::fs;
+use std::net::SocketAddr;
+#[cfg(unix)]
+use std::os::unix::fs::FileTypeExt;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+#[cfg(unix)]
+use tokio::net::UnixListener;
+use tokio::{
+    io::{copy_bidirectional, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::TcpListener,
+};
+
+use tracing::{debug, error, info, warn};
+
+use crate::app::{App, HttpLog};
+use crate::ca::CertAuthority;
+use crate::exchange::{Exchange, Prepared};
+use crate::sniff::{self, NonHttp};
+use crate::{http, mitm, sse, timeouts, upstream, websocket};
+
+/// Counts a client connection as active for as long as it is alive, and numbers it
+struct ConnGuard {
+    app: Arc<Mutex<App>>,
+    id: u64,
+}
+
+impl ConnGuard {
+    fn new(app: &Arc<Mutex<App>>, listener: &str) -> Self {
+        let mut guard = app.lock().unwrap();
+        guard.active_connections += 1;
+        guard.connections_seen += 1;
+        let id = guard.connections_seen;
+        guard.conn_listeners.insert(id, listener.to_string());
+        Self { app: Arc::clone(app), id }
+    }
+}
+
+impl Drop for ConnGuard {
+    fn drop(&mut self) {
+        let mut guard = self.app.lock().unwrap();
+        guard.active_connections -= 1;
+        guard.conn_listeners.remove(&self.id);
+    }
+}
+
+/// Async HTTP/HTTPS proxy listener; entries arriving on it carry `label`
+pub async fn spawn_proxy_listener(app: Arc<Mutex<App>>, ca: Arc<CertAuthority>, addr: String, label: String) {
+    if let Some(path) = addr.strip_prefix("unix:") {
+        return unix_listener(app, ca, path.to_string(), label).await;
+    }
+    let listener = match TcpListener::bind(&addr).await {
+        Ok(l) => l,
+        Err(e) => return error!("could not bind proxy listener {} on {}: {}", label, addr, e),
+    };
+    info!("proxy listener {} on http://{}", label, addr);
+    loop {
+        let (client, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                warn!("accept failed: {}", e);
+                continue;
+            }
+        };
+        tokio::spawn(serve(client, peer, Arc::clone(&app), Arc::clone(&ca), label.clone()));
+    }
+}
+
+/// Listener on a Unix socket, for clients of local daemons; they count as 127.0.0.1
+#[cfg(unix)]
+async fn unix_listener(app: Arc<Mutex<App>>, ca: Arc<CertAuthority>, path: String, label: String) {
+    // A socket left behind by an earlier run would fail the bind
+    if fs::symlink_metadata(&path).is_ok_and(|m| m.file_type().is_socket()) {
+        let _ = fs::remove_file(&path);
+    }
+    let listener = match UnixListener::bind(&path) {
+        Ok(l) => l,
+        Err(e) => return error!("could not bind proxy listener {} on unix:{}: {}", label, path, e),
+    };
+    info!("proxy listener {} on unix:{}", label, path);
+    let peer = SocketAddr::from(([127, 0, 0, 1], 0));
+    loop {
+        let client = match listener.accept().await {
+            Ok((client, _)) => client,
+            Err(e) => {
+                warn!("accept failed: {}", e);
+                continue;
+            }
+        };
+        tokio::spawn(serve(client, peer, Arc::clone(&app), Arc::clone(&ca), label.clone()));
+    }
+}
+
+#[cfg(not(unix))]
+async fn unix_listener(_app: Arc<Mutex<App>>, _ca: Arc<CertAuthority>, path: String, label: String) {
+    error!("could not bind proxy listener {} on unix:{}: Unix sockets are only supported on Unix", label, path);
+}
+
+/// One client connection: a CONNECT tunnel, plain HTTP requests or another protocol
+async fn serve<C>(mut client: C, peer: SocketAddr, app: Arc<Mutex<App>>, ca: Arc<CertAuthority>, label: String)
+where
+    C: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let conn = ConnGuard::new(&app, &label);
+    // Read initial frame
+    let mut buf = [0u8; 8192];
+    let limit = timeouts::get().header;
+    let n = match tokio::time::timeout(limit, client.read(&mut buf)).await {
+        Ok(Ok(n)) if n > 0 => n,
+        Ok(_) => return,
+        Err(_) => return debug!("{}: {}", peer, timeouts::expired("header", limit)),
+    };
+    let Some(head) = http::RequestHead::parse(&buf[..n]) else {
+        return non_http(client, &buf[..n], peer, conn.id, app).await;
+    };
+    let (method, target) = (head.method, head.target);
+
+    if method.eq_ignore_ascii_case("CONNECT") {
+        // Acknowledge
+        let _ = client.write_all(b"HTTP/1.1 200 Connection Established\r\n\r\n").await;
+        let (host, port) = http::split_authority(target);
+        let port = port.and_then(|p| p.parse().ok()).unwrap_or(443);
+        let passthrough = {
+            let guard = app.lock().unwrap();
+            guard.passthrough.matches(host) || guard.listeners.iter().any(|l| l.label == label && !l.mitm)
+        };
+        if !passthrough {
+            mitm::intercept(client, peer.ip(), conn.id, target, host, app, ca).await;
+            return;
+        }
+        { let mut guard = app.lock().unwrap();
+            guard.push_log(HttpLog {
+                client: Some(peer.ip()),
+                url: format!("CONNECT {}", target),
+                host: host.to_string(),
+                conn: conn.id,
+                request: format!("{} {} HTTP/1.1", method, target).into(),
+                response: "[Passthrough tunnel, not intercepted]".to_string().into(),
+                ..Default::default()
+            });
+        }
+        // Blind tunnel
+        let via = app.lock().unwrap().routes.route(host).cloned();
+        match upstream::connect(via.as_ref(), host, port).await {
+            Ok(mut upstream) => {
+                if let Err(e) = copy_bidirectional(&mut client, &mut upstream).await {
+                    debug!("passthrough tunnel to {} ended: {}", target, e);
+                }
+            }
+            Err(e) => warn!("passthrough connect to {} failed: {}", target, e),
+        }
+    } else {
+        plain(client, buf[..n].to_vec(), peer, conn.id, app).await;
+    }
+}
+
+/// Plain HTTP requests on one client connection, relayed in turn for as long as the client keeps it open
+async fn plain<C>(mut client: C, mut pending: Vec<u8>, peer: SocketAddr, conn: u64, app: Arc<Mutex<App>>)
+where
+    C: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let mut first = true;
+    loop {
+        // The rest of the request may still be in flight
+        let Some(request) = read_next_request(&mut client, &mut pending).await else {
+            if first {
+                debug!("{}: request closed early or hit the header timeout", peer);
+            }
+            return;
+        };
+        first = false;
+        let Some(head) = http::RequestHead::parse(&request) else { return };
+        let (meth, path) = (head.method.to_string(), head.target.to_string());
+        let host_hdr = head.header_str("host").unwrap_or("127.0.0.1");
+        let (host, port) = match http::split_authority(host_hdr) {
+            (h, Some(p)) => (h.to_string(), p.parse().unwrap_or(80)),
+            (h, None) => (h.to_string(), 80),
+        };
+        let host = host.as_str();
+        let entry = HttpLog { client: Some(peer.ip()), conn, url: format!("{} {} [Host: {}]", meth, path, host), host: host.to_string(), port, ..Default::default() };
+        if websocket::is_upgrade(&head) {
+            return websocket::relay(client, request, host, port, false, entry, app).await;
+        }
+        let minimal = {
+            let guard = app.lock().unwrap();
+            guard.minimal_forward && !guard.read_only_for(host)
+        };
+        let forward = match minimal {
+            true => format!("{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n", meth, path, host).into_bytes(),
+            false => to_origin(&request),
+        };
+        let mut exchange = Exchange::new(&app, entry, request, &meth);
+        let forward = match exchange.prepare(&app, forward, &mut client).await {
+            Prepared::Forward(forward) => forward,
+            Prepared::Stream(forward) => return sse::relay(client, forward, host, port, false, exchange.stream_entry(), app).await,
+            Prepared::Done => return,
+        };
+        if !exchange.complete(&app, forward, &mut client).await {
+            return;
+        }
+    }
+}
+
+/// Log a connection whose first bytes are not HTTP, then tunnel or close it as `--non-http` says
+async fn non_http<C>(mut client: C, first: &[u8], peer: SocketAddr, conn: u64, app: Arc<Mutex<App>>)
+where
+    C: AsyncRead + AsyncWrite + Unpin,
+{
+    let found = sniff::detect(first);
+    let (policy, via) = {
+        let guard = app.lock().unwrap();
+        let host = found.sni.as_deref().unwrap_or_default();
+        (guard.non_http.clone(), guard.routes.route(host).cloned())
+    };
+    let target = match (policy, &found.sni) {
+        (NonHttp::Forward(target), _) => Some(target),
+        (NonHttp::Sni, Some(sni)) => Some(format!("{}:443", sni)),
+        _ => None,
+    };
+    let named = found.sni.as_ref().map_or(String::new(), |s| format!(" for {}", s));
+    warn!("non-HTTP connection from {}: {}{}", peer, found.label, named);
+    let (host, port) = match target.as_deref().map(http::split_authority) {
+        Some((h, Some(p))) => (h.to_string(), p.parse().unwrap_or(443)),
+        _ => (found.sni.clone().unwrap_or_default(), 0),
+    };
+    let response = match &target {
+        Some(t) => format!("[Tunneling to {}]", t),
+        None => "[Rejected: not HTTP; --non-http sni or --non-http HOST:PORT tunnels these]".to_string(),
+    };
+    let url = format!("{}{} from {}", found.label, named, peer);
+    let index = {
+        let mut guard = app.lock().unwrap();
+        guard.push_log(HttpLog {
+            client: Some(peer.ip()),
+            url: url.clone(),
+            host: host.clone(),
+            port,
+            conn,
+            sni: found.sni.clone(),
+            request: format!("[{} bytes before detection]\n{}", first.len(), sniff::preview(first)).into(),
+            response: response.into(),
+            ..Default::default()
+        });
+        guard.logs.len() - 1
+    };
+    let Some(target) = target else { return };
+    let started = Instant::now();
+    let (sent, received) = match upstream::connect(via.as_ref(), &host, port).await {
+        Ok(mut upstream) => {
+            let _ = upstream.write_all(first).await;
+            let counts = copy_bidirectional(&mut client, &mut upstream).await.unwrap_or_default();
+            (counts.0 + first.len() as u64, counts.1)
+        }
+        Err(e) => {
+            warn!("non-HTTP tunnel to {} failed: {}", target, e);
+            (0, 0)
+        }
+    };
+    let mut guard = app.lock().unwrap();
+    // Unless `:open` replaced the session meanwhile
+    if let Some(log) = guard.logs.get_mut(index).filter(|l| l.url == url) {
+        log.response = format!("[Tunneled to {}: {} bytes sent, {} bytes received]", target, sent, received).into();
+        log.duration = started.elapsed();
+    }
+}
+
+/// Read one request (head plus Content-Length or chunked body) off a client stream, within the header time limit
+pub async fn read_request<R: AsyncRead + Unpin>(r: &mut R) -> Option<Vec<u8>> {
+    tokio::time::timeout(timeouts::get().header, read_whole_request(r)).await.ok().flatten()
+}
+
+/// Read the next request on a connection kept open; bytes past its end stay in `pending` for the one after
+pub async fn read_next_request<R: AsyncRead + Unpin>(r: &mut R, pending: &mut Vec<u8>) -> Option<Vec<u8>> {
+    let buffered = std::mem::take(pending);
+    let mut request = read_request(&mut (&buffered[..]).chain(&mut *r)).await?;
+    if let Some(end) = http::request_len(&request).filter(|e| *e < request.len()) {
+        *pending = request.split_off(end);
+    }
+    Some(request)
+}
+
+async fn read_whole_request<R: AsyncRead + Unpin>(r: &mut R) -> Option<Vec<u8>> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 8192];
+    let total = loop {
+        // A chunked body is read here to its end, a Content-Length one below
+        if let Some(total) = http::request_len(&buf) {
+            break total;
+        }
+        match r.read(&mut chunk).await {
+            Ok(0) | Err(_) => return None,
+            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+        }
+    };
+    while buf.len() < total {
+        match r.read(&mut chunk).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+        }
+    }
+    Some(buf)
+}
+
+/// Headers that only apply to one connection and are not passed along
+pub const HOP_BY_HOP: [&str; 7] = ["connection", "proxy-connection", "keep-alive", "proxy-authorization", "proxy-authenticate", "te", "trailer"];
+
+/// Drop hop-by-hop headers, including any named in `Connection`, and add `Connection: close` so the
+/// upstream response can be read to EOF; every other line goes out exactly as the client sent it
+pub fn force_close(request: &[u8]) -> Vec<u8> {
+    let Some(head) = http::RequestHead::parse(request).filter(|h| h.len.is_some()) else {
+        return request.to_vec();
+    };
+    let listed: Vec<String> = head.headers.iter()
+        .filter(|(k, _)| k.eq_ignore_ascii_case("connection"))
+        .flat_map(|(_, v)| String::from_utf8_lossy(v).split(',').map(|t| t.trim().to_lowercase()).collect::<Vec<_>>())
+        .collect();
+    let (raw_head, body) = http::split_raw(request);
+    let mut out = Vec::with_capacity(request.len() + 19);
+    let line_end = raw_head.iter().position(|b| *b == b'\n').map_or(raw_head.len(), |i| i + 1);
+    out.extend_from_slice(&raw_head[..line_end]);
+    for line in http::header_lines(raw_head) {
+        let lower = String::from_utf8_lossy(http::header_line_name(&line)).to_lowercase();
+        if HOP_BY_HOP.contains(&lower.as_str()) || listed.contains(&lower) {
+            continue;
+        }
+        out.extend_from_slice(&line);
+        out.extend_from_slice(b"\r\n");
+    }
+    out.extend_from_slice(b"Connection: close\r\n\r\n");
+    out.extend_from_slice(body);
+    out
+}
+
+/// A client's request as an origin server expects it: origin-form target, hop-by-hop headers dropped
+pub fn to_origin(request: &[u8]) -> Vec<u8> {
+    let Some(head) = http::RequestHead::parse(request) else { return request.to_vec() };
+    let line_end = request.windows(2).position(|w| w == b"\r\n").unwrap_or(0);
+    let version = std::str::from_utf8(&request[..line_end]).ok().and_then(|l| l.rsplit(' ').next()).unwrap_or("HTTP/1.1");
+    let mut out = format!("{} {} {}", head.method, http::origin_path(head.target), version).into_bytes();
+    out.extend_from_slice(&request[line_end..]);
+    force_close(&out)
+}
@@ -0,0 +1,17 @@
+// Random bytes from the system's secure generator, for IDs, challenges and tokens
+
+use ring::rand::{SecureRandom, SystemRandom};
+
+/// `N` random bytes, or None if the system generator fails
+pub fn bytes<const N: usize>() -> Option<[u8; N]> {
+    let mut buf = [0u8; N];
+    SystemRandom::new().fill(&mut buf).ok()?;
+    Some(buf)
+}
+
+/// `len` random bytes as lowercase hex; zeros if the system generator fails
+pub fn hex(len: usize) -> String {
+    let mut buf = vec![0u8; len];
+    let _ = SystemRandom::new().fill(&mut buf);
+    buf.iter().map(|b| format!("{:02x}", b)).collect()
+}
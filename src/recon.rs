@@ -0,0 +1,138 @@
+// Session-wide extraction of URLs, domains and IPs referenced in response bodies
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use regex::Regex;
+
+use crate::app::{App, HttpLog, ReconView};
+use crate::http;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Kind {
+    Url,
+    Domain,
+    Ip,
+}
+
+impl Kind {
+    pub fn label(self) -> &'static str {
+        match self {
+            Kind::Url => "url",
+            Kind::Domain => "domain",
+            Kind::Ip => "ip",
+        }
+    }
+}
+
+pub struct Found {
+    pub kind: Kind,
+    pub value: String,
+    pub in_scope: bool,
+    /// Number of responses mentioning it
+    pub count: usize,
+}
+
+struct Patterns {
+    absolute: Regex,
+    /// `href`/`src`/`action` attributes and quoted root-relative paths
+    relative: Regex,
+    ip: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| Patterns {
+        absolute: Regex::new(r#"(?i)\b(?:https?|wss?)://[^\s"'<>()\\`{}|^]+"#).unwrap(),
+        relative: Regex::new(r#"(?i)(?:(?:href|src|action)\s*=\s*["']|["'])(/[A-Za-z0-9_\-.~/%]+(?:\?[^"'\s<>]*)?)["']"#).unwrap(),
+        ip: Regex::new(r"\b(?:(?:25[0-5]|2[0-4]\d|1?\d?\d)\.){3}(?:25[0-5]|2[0-4]\d|1?\d?\d)\b").unwrap(),
+    })
+}
+
+/// Host part of an absolute URL, without credentials or port
+fn url_host(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, r)| r);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+    let authority = authority.rsplit_once('@').map_or(authority, |(_, a)| a);
+    authority.split(':').next().unwrap_or("")
+}
+
+/// Everything one response body references, deduplicated
+fn from_log(log: &HttpLog) -> Vec<(Kind, String)> {
+    let head = log.response_head();
+    let binary = http::header_value(&head, "content-type")
+        .is_some_and(|t| ["image/", "font/", "audio/", "video/"].iter().any(|p| t.starts_with(p)));
+    if binary || log.response_raw.is_empty() {
+        return Vec::new();
+    }
+    let body = log.response_body();
+    let text = String::from_utf8_lossy(&body);
+    let p = patterns();
+    let mut urls: Vec<String> = p.absolute.find_iter(&text)
+        .map(|m| m.as_str().trim_end_matches(['.', ',', ';', ':']).to_string())
+        .collect();
+    let base = log.full_url();
+    let origin = base.find("://")
+        .and_then(|i| base[i + 3..].find('/').map(|j| &base[..i + 3 + j]))
+        .unwrap_or(&base);
+    urls.extend(p.relative.captures_iter(&text)
+        .filter(|c| !c[1].starts_with("//"))
+        .map(|c| format!("{}{}", origin, &c[1])));
+    // Protocol-relative references keep the page's scheme
+    let scheme = if log.tls { "https:" } else { "http:" };
+    urls.extend(p.relative.captures_iter(&text)
+        .filter(|c| c[1].starts_with("//") && c[1].len() > 2)
+        .map(|c| format!("{}{}", scheme, &c[1])));
+
+    let mut found: Vec<(Kind, String)> = Vec::new();
+    for url in urls {
+        let host = url_host(&url).to_lowercase();
+        if !host.is_empty() && !p.ip.is_match(&host) {
+            found.push((Kind::Domain, host));
+        }
+        found.push((Kind::Url, url));
+    }
+    found.extend(p.ip.find_iter(&text).map(|m| (Kind::Ip, m.as_str().to_string())));
+    found.sort();
+    found.dedup();
+    found
+}
+
+/// Absolute URLs one response body links to or references
+pub fn links(log: &HttpLog) -> Vec<String> {
+    from_log(log).into_iter().filter(|(k, _)| *k == Kind::Url).map(|(_, url)| url).collect()
+}
+
+/// Open the recon list of every URL, domain and IP in the session's response bodies, grouped by
+/// kind; the bodies are searched on a blocking thread, not under the lock
+pub fn open(guard: &mut App, app: Arc<Mutex<App>>) {
+    let logs: VecDeque<HttpLog> = guard.logs.clone();
+    let generation = guard.generation;
+    guard.set_status(format!("Searching {} responses", logs.len()));
+    tokio::spawn(async move {
+        let Ok(counts) = tokio::task::spawn_blocking(move || count(&logs)).await else { return };
+        let mut guard = app.lock().unwrap();
+        // A clear or `:open` replaced the session meanwhile
+        if guard.generation != generation {
+            return;
+        }
+        let found = counts.into_iter().map(|((kind, value), count)| {
+            let host = match kind {
+                Kind::Url => url_host(&value).to_string(),
+                _ => value.clone(),
+            };
+            Found { kind, in_scope: guard.scope.contains(&host), value, count }
+        }).collect();
+        guard.recon = Some(ReconView { found, selected: 0 });
+    });
+}
+
+fn count(logs: &VecDeque<HttpLog>) -> BTreeMap<(Kind, String), usize> {
+    let mut counts = BTreeMap::new();
+    for log in logs {
+        for key in from_log(log) {
+            *counts.entry(key).or_default() += 1;
+        }
+    }
+    counts
+}
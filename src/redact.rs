@@ -0,0 +1,164 @@
+// Redaction of credentials and personal data in exports and saved projects, so a capture can be
+// shared: header values, cookie values and regex matches become `[REDACTED]`
+
+use std::borrow::Cow;
+use std::fs;
+
+use regex::bytes::Regex;
+
+use crate::app::{App, HttpLog};
+use crate::config;
+use crate::http;
+
+pub const MASK: &str = "[REDACTED]";
+
+/// Used when `redact.txt` does not exist
+const DEFAULT_RULES: &str = "header Authorization\nheader Proxy-Authorization\nheader X-Api-Key\nregex eyJ[A-Za-z0-9_-]+\\.[A-Za-z0-9_-]+\\.[A-Za-z0-9_-]*\nregex (?i)(?:password|passwd|token|secret)=([^&\\s]+)\n";
+
+#[derive(Default)]
+pub struct Redactions {
+    /// Lowercase header names whose values are masked
+    headers: Vec<String>,
+    /// Cookies masked in `Cookie` and `Set-Cookie`
+    cookies: Vec<String>,
+    /// The whole match is masked, or only the first group when there is one
+    patterns: Vec<Regex>,
+}
+
+impl Redactions {
+    /// Load `redact.txt` from the config dir: `header NAME`, `cookie NAME` or `regex PATTERN` per line, `#` comments
+    pub fn load() -> Result<Self, String> {
+        let path = config::config_dir().join("redact.txt");
+        let text = fs::read_to_string(&path).unwrap_or_else(|_| DEFAULT_RULES.to_string());
+        let mut rules = Redactions::default();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (kind, value) = line.split_once(char::is_whitespace).map(|(k, v)| (k, v.trim())).unwrap_or((line, ""));
+            match (kind, value) {
+                (_, "") => return Err(format!("{}:{}: expected `header NAME`, `cookie NAME` or `regex PATTERN`", path.display(), n + 1)),
+                ("header", name) => rules.headers.push(name.to_lowercase()),
+                ("cookie", name) => rules.cookies.push(name.to_string()),
+                ("regex", pattern) => rules.patterns.push(Regex::new(pattern).map_err(|e| format!("{}:{}: bad regex: {}", path.display(), n + 1, e))?),
+                _ => return Err(format!("{}:{}: unknown rule `{}`", path.display(), n + 1, kind)),
+            }
+        }
+        Ok(rules)
+    }
+
+    pub fn count(&self) -> usize {
+        self.headers.len() + self.cookies.len() + self.patterns.len()
+    }
+
+    /// `data` with every pattern's matches masked
+    pub fn bytes(&self, data: &[u8]) -> Vec<u8> {
+        let mut out = data.to_vec();
+        for pattern in &self.patterns {
+            let mut masked = Vec::with_capacity(out.len());
+            let mut last = 0;
+            for caps in pattern.captures_iter(&out) {
+                let Some(m) = caps.get(1).or_else(|| caps.get(0)) else { continue };
+                masked.extend_from_slice(&out[last..m.start()]);
+                masked.extend_from_slice(MASK.as_bytes());
+                last = m.end();
+            }
+            masked.extend_from_slice(&out[last..]);
+            out = masked;
+        }
+        out
+    }
+
+    pub fn text(&self, text: &str) -> String {
+        String::from_utf8_lossy(&self.bytes(text.as_bytes())).into_owned()
+    }
+
+    /// A message head, `\r\n` or `\n` separated, with header and cookie rules applied
+    fn head(&self, head: &str) -> String {
+        let newline = if head.contains("\r\n") { "\r\n" } else { "\n" };
+        let lines: Vec<String> = head.split(newline).enumerate().map(|(i, line)| {
+            let Some((name, value)) = line.split_once(':').filter(|_| i > 0) else { return line.to_string() };
+            let lower = name.trim().to_lowercase();
+            if self.headers.contains(&lower) {
+                format!("{}: {}", name, MASK)
+            } else if lower == "cookie" {
+                format!("{}:{}", name, value.split(';').map(|c| self.cookie(c)).collect::<Vec<_>>().join(";"))
+            } else if lower == "set-cookie" {
+                let (pair, attributes) = value.split_once(';').map_or((value, None), |(p, a)| (p, Some(a)));
+                format!("{}:{}{}", name, self.cookie(pair), attributes.map_or(String::new(), |a| format!(";{}", a)))
+            } else {
+                line.to_string()
+            }
+        }).collect();
+        self.text(&lines.join(newline))
+    }
+
+    /// One ` name=value` pair, its spacing kept
+    fn cookie(&self, pair: &str) -> String {
+        match pair.split_once('=') {
+            Some((name, _)) if self.cookies.iter().any(|c| c == name.trim()) => format!("{}={}", name, MASK),
+            _ => pair.to_string(),
+        }
+    }
+
+    /// Raw message bytes; an encoded body is decoded first so nothing hides in it, and its
+    /// encoding headers go with it
+    fn raw(&self, raw: &[u8]) -> Vec<u8> {
+        let (head, body) = http::split_raw(raw);
+        if head.len() == raw.len() {
+            return self.head(&String::from_utf8_lossy(raw)).into_bytes();
+        }
+        let head = String::from_utf8_lossy(head);
+        let encoded = ["transfer-encoding", "content-encoding"].iter().any(|h| http::header_value(&head, h).is_some());
+        let sized = encoded || http::header_value(&head, "content-length").is_some();
+        let body = self.bytes(&if encoded { http::decode_body(&head, body) } else { body.to_vec() });
+        let mut lines: Vec<String> = head.split("\r\n").filter(|l| {
+            let name = l.split(':').next().unwrap_or("").trim().to_lowercase();
+            name != "content-length" && !(encoded && matches!(name.as_str(), "transfer-encoding" | "content-encoding"))
+        }).map(str::to_string).collect();
+        // Masks change the body's length
+        if sized {
+            lines.push(format!("Content-Length: {}", body.len()));
+        }
+        let head = lines.join("\r\n");
+        let mut out = self.head(&head).into_bytes();
+        out.extend_from_slice(b"\r\n\r\n");
+        out.extend_from_slice(&body);
+        out
+    }
+
+    /// A copy of the entry with every rule applied to its URL, messages and correlation IDs
+    pub fn log(&self, log: &HttpLog) -> HttpLog {
+        let shown = |text: String| match text.split_once("\n\n") {
+            Some((head, body)) => format!("{}\n\n{}", self.head(head), self.text(body)),
+            None => self.head(&text),
+        };
+        HttpLog {
+            url: self.text(&log.url),
+            request: shown(log.request.text()).into(),
+            response: shown(log.response_text()).into(),
+            request_raw: self.raw(&log.request_raw.bytes()).into(),
+            response_raw: self.raw(&log.response_raw.bytes()).into(),
+            correlation: log.correlation.iter().map(|(h, id)| (h.clone(), if self.headers.contains(&h.to_lowercase()) { MASK.to_string() } else { id.clone() })).collect(),
+            ..log.clone()
+        }
+    }
+}
+
+/// The entry as it leaves belch in an export or a saved project: redacted while `:redact` is on
+pub fn outgoing<'a>(app: &App, log: &'a HttpLog) -> Cow<'a, HttpLog> {
+    if app.redact { Cow::Owned(app.redactions.log(log)) } else { Cow::Borrowed(log) }
+}
+
+/// `:redact [on|off]` switches redaction of exports and project saves
+pub fn command(app: &mut App, args: &[&str]) {
+    app.redact = match args {
+        [] => !app.redact,
+        ["on"] => true,
+        ["off"] => false,
+        _ => return app.set_status("usage: :redact [on|off]"),
+    };
+    let state = if app.redact { "on" } else { "off" };
+    app.set_status(format!("Redaction {}: exports and saves mask {} rule(s) from redact.txt", state, app.redactions.count()));
+}
@@ -0,0 +1,103 @@
+// Redirect chains: a 3xx answer linked to the request that followed its Location
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::app::HttpLog;
+use crate::http;
+
+/// Unfollowed redirects remembered for matching
+const LOOKBACK: usize = 200;
+
+/// `location` resolved against `base`, both absolute URLs
+pub fn resolve(base: &str, location: &str) -> String {
+    if location.contains("://") {
+        return location.to_string();
+    }
+    let (scheme, rest) = base.split_once("://").unwrap_or(("http", base));
+    let authority = rest.split('/').next().unwrap_or(rest);
+    if let Some(rest) = location.strip_prefix("//") {
+        return format!("{}://{}", scheme, rest);
+    }
+    if location.starts_with('/') {
+        return format!("{}://{}{}", scheme, authority, location);
+    }
+    // Relative to the base path's directory
+    let path = rest[authority.len()..].split(['?', '#']).next().unwrap_or("");
+    let dir = path.rsplit_once('/').map_or("", |(d, _)| d);
+    format!("{}://{}{}/{}", scheme, authority, dir, location)
+}
+
+/// Comparable form: lowercase scheme and host, no default port, no fragment
+fn normalize(url: &str) -> String {
+    let url = url.split('#').next().unwrap_or(url);
+    let Some((scheme, rest)) = url.split_once("://") else { return url.to_string() };
+    let scheme = scheme.to_lowercase();
+    let (authority, path) = rest.find(['/', '?']).map_or((rest, "/"), |i| rest.split_at(i));
+    let mut authority = authority.to_lowercase();
+    let default = if scheme == "https" { ":443" } else { ":80" };
+    if authority.ends_with(default) {
+        authority.truncate(authority.len() - default.len());
+    }
+    let path = if path.starts_with('?') { format!("/{}", path) } else { path.to_string() };
+    format!("{}://{}{}", scheme, authority, path)
+}
+
+/// Where a 3xx entry sends the client, normalized
+fn target(log: &HttpLog) -> Option<String> {
+    if !log.status().is_some_and(|s| (300..400).contains(&s) && s != 304) {
+        return None;
+    }
+    let head = log.response_head();
+    http::header_value(&head, "location").map(|l| normalize(&resolve(&log.full_url(), l.trim())))
+}
+
+/// Links between captured entries, by index into `App::logs`
+#[derive(Default)]
+pub struct Chains {
+    /// Redirects nothing has followed yet, with their normalized targets
+    pending: VecDeque<(usize, String)>,
+    next: HashMap<usize, usize>,
+    prev: HashMap<usize, usize>,
+}
+
+impl Chains {
+    /// Link a newly captured entry to the redirect it follows, and remember it if it redirects itself
+    pub fn add(&mut self, index: usize, log: &HttpLog) {
+        if !log.request_raw.is_empty() {
+            let url = normalize(&log.full_url());
+            // Latest first: a client retrying the same redirect follows the newest
+            if let Some(p) = self.pending.iter().rposition(|(_, t)| *t == url) {
+                let (from, _) = self.pending.remove(p).unwrap_or_default();
+                self.next.insert(from, index);
+                self.prev.insert(index, from);
+            }
+        }
+        if let Some(t) = target(log) {
+            self.pending.push_back((index, t));
+            if self.pending.len() > LOOKBACK {
+                self.pending.pop_front();
+            }
+        }
+    }
+
+    pub fn prev(&self, index: usize) -> Option<usize> {
+        self.prev.get(&index).copied()
+    }
+
+    /// First request of the chain `index` belongs to
+    pub fn head(&self, mut index: usize) -> usize {
+        while let Some(p) = self.prev(index) {
+            index = p;
+        }
+        index
+    }
+
+    /// Entries of the chain starting at `head`, in order; just `head` when it did not redirect
+    pub fn chain(&self, head: usize) -> Vec<usize> {
+        let mut out = vec![head];
+        while let Some(n) = self.next.get(out.last().unwrap_or(&head)) {
+            out.push(*n);
+        }
+        out
+    }
+}
@@ -0,0 +1,92 @@
+// Passive detection of request values echoed back verbatim in the response
+
+use serde_json::Value;
+
+use crate::app::HttpLog;
+use crate::http;
+use crate::issues::{Issue, Severity};
+
+/// Shorter values match by coincidence too often
+const MIN_LEN: usize = 4;
+/// Request headers whose values are worth tracking
+const HEADERS: &[&str] = &["user-agent", "referer", "x-forwarded-for", "x-forwarded-host", "origin"];
+
+fn json_strings(value: &Value, out: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map {
+                match v {
+                    Value::String(s) => out.push((k.clone(), s.clone())),
+                    _ => json_strings(v, out),
+                }
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|v| json_strings(v, out)),
+        _ => {}
+    }
+}
+
+/// Named inputs of a request: query and body parameters, selected headers
+fn inputs(request: &[u8]) -> Vec<(String, String)> {
+    let text = String::from_utf8_lossy(request);
+    let (head, body) = http::split_message(&text);
+    let target = head.split_whitespace().nth(1).unwrap_or("");
+    let mut out = target.split_once('?').map_or(Vec::new(), |(_, q)| http::parse_query(q));
+    let ctype = http::header_value(head, "content-type").unwrap_or("").to_lowercase();
+    if ctype.contains("x-www-form-urlencoded") {
+        out.extend(http::parse_query(body));
+    } else if ctype.contains("json") {
+        if let Ok(v) = serde_json::from_str::<Value>(body) {
+            json_strings(&v, &mut out);
+        }
+    }
+    for name in HEADERS {
+        if let Some(v) = http::header_value(head, name) {
+            out.push((name.to_string(), v.to_string()));
+        }
+    }
+    out.retain(|(_, v)| v.len() >= MIN_LEN && !v.chars().all(|c| c.is_ascii_digit()));
+    out
+}
+
+/// Where in an HTML document the byte at `pos` sits
+fn html_context(body: &str, pos: usize) -> &'static str {
+    let before = body[..pos].to_lowercase();
+    let script_open = before.rfind("<script");
+    if script_open.is_some() && script_open > before.rfind("</script") {
+        return "script block";
+    }
+    if before.rfind('<') > before.rfind('>') {
+        return "HTML attribute";
+    }
+    "HTML body"
+}
+
+/// Issues for values of `log`'s request that appear in its decoded response `body`
+pub fn check(log: &HttpLog, body: &[u8]) -> Vec<Issue> {
+    if body.is_empty() {
+        return Vec::new();
+    }
+    let head = log.response_head();
+    let ctype = http::header_value(&head, "content-type").unwrap_or("").to_lowercase();
+    let body = String::from_utf8_lossy(body);
+    let mut issues = Vec::new();
+    for (name, value) in inputs(&log.request_raw.bytes()) {
+        let Some(pos) = body.find(value.as_str()) else { continue };
+        let (context, severity) = if ctype.contains("json") {
+            ("JSON", Severity::Low)
+        } else if ctype.contains("html") {
+            (html_context(&body, pos), Severity::Medium)
+        } else {
+            ("response body", Severity::Info)
+        };
+        issues.push(Issue {
+            severity,
+            host: log.host.clone(),
+            url: log.full_url(),
+            title: format!("Reflected input `{}`", name),
+            detail: format!("{} in {}", value.chars().take(40).collect::<String>(), context),
+        });
+    }
+    issues
+}
@@ -0,0 +1,234 @@
+// Regression tests from captured traffic: each exchange becomes a Rust `#[test]` using reqwest,
+// or a pytest function using httpx, that sends the request again and checks the status, the
+// Content-Type and a few stable fields of the answer
+
+use serde_json::Value;
+
+use crate::app::HttpLog;
+use crate::http;
+
+/// Left out of generated requests: the client library works them out or they tie the
+/// request to the original connection
+const SKIPPED_HEADERS: &[&str] = &["host", "content-length", "connection", "proxy-connection", "keep-alive", "transfer-encoding", "te", "accept-encoding", "content-encoding"];
+
+/// JSON fields whose names suggest they change on every answer
+const VOLATILE: &[&str] = &["time", "date", "token", "nonce", "csrf", "session", "expires", "trace", "request_id", "requestid", "uuid"];
+
+/// Fields checked per JSON object
+const MAX_FIELDS: usize = 5;
+
+/// What a test sends and expects, taken from one entry
+struct Case {
+    name: String,
+    method: String,
+    url: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+    status: u16,
+    /// Media type without parameters
+    content_type: Option<String>,
+    check: Check,
+}
+
+enum Check {
+    Nothing,
+    /// Top-level scalar fields and their values
+    Fields(Vec<(String, Value)>),
+    Array,
+    /// Text inside `<title>`
+    Title(String),
+}
+
+/// `get_api_users_3` for `GET /api/users?page=2` as the third case; anything but letters and
+/// digits, in the method too, becomes one `_`
+fn test_name(method: &str, url: &str, n: usize) -> String {
+    let path = http::origin_path(url).split('?').next().unwrap_or("");
+    let mut name = String::new();
+    for c in method.chars().chain(['/']).chain(path.chars()) {
+        match c.is_ascii_alphanumeric() {
+            true => name.push(c.to_ascii_lowercase()),
+            false if !name.is_empty() && !name.ends_with('_') => name.push('_'),
+            false => {}
+        }
+    }
+    name.truncate(40);
+    // An identifier can't start with a digit
+    let lead = if name.starts_with(|c: char| c.is_ascii_digit()) { "_" } else { "" };
+    format!("{}{}_{}", lead, name.trim_end_matches('_'), n)
+}
+
+fn title(html: &str) -> Option<String> {
+    let lower = html.to_lowercase();
+    let open = lower.find("<title")?;
+    let start = open + lower[open..].find('>')? + 1;
+    let end = start + lower[start..].find("</title")?;
+    let text = html.get(start..end)?.trim();
+    (!text.is_empty() && text.len() <= 120 && !text.contains('\n')).then(|| text.to_string())
+}
+
+fn check(content_type: Option<&str>, body: &[u8]) -> Check {
+    let text = String::from_utf8_lossy(body);
+    match content_type {
+        Some(t) if t.contains("json") => match serde_json::from_str::<Value>(&text) {
+            Ok(Value::Object(map)) => Check::Fields(
+                map.into_iter()
+                    .filter(|(k, v)| {
+                        let key = k.to_lowercase();
+                        !VOLATILE.iter().any(|w| key.contains(w))
+                            && (v.is_boolean() || v.is_number() || v.is_null() || v.as_str().is_some_and(|s| s.len() <= 80))
+                    })
+                    .take(MAX_FIELDS)
+                    .collect(),
+            ),
+            Ok(Value::Array(_)) => Check::Array,
+            _ => Check::Nothing,
+        },
+        Some("text/html") => title(&text).map_or(Check::Nothing, Check::Title),
+        _ => Check::Nothing,
+    }
+}
+
+/// Entries with a complete request and an answer, as cases
+fn cases<'a>(logs: impl Iterator<Item = &'a HttpLog>) -> Vec<Case> {
+    logs.filter_map(|log| {
+        let raw = log.request_raw.bytes();
+        let head = http::RequestHead::parse(&raw).filter(|h| h.len.is_some())?;
+        let status = log.status()?;
+        let (raw_head, raw_body) = http::split_raw(&raw);
+        let headers = head.headers.iter()
+            .filter(|(n, _)| !SKIPPED_HEADERS.iter().any(|s| n.eq_ignore_ascii_case(s)))
+            .map(|(n, v)| (n.to_string(), String::from_utf8_lossy(v).into_owned()))
+            .collect();
+        let response_head = log.response_head();
+        let content_type = http::header_value(&response_head, "content-type")
+            .map(|t| t.split(';').next().unwrap_or("").trim().to_lowercase())
+            .filter(|t| !t.is_empty() && head.method != "HEAD" && status != 204 && status != 304);
+        let check = if head.method == "HEAD" { Check::Nothing } else { check(content_type.as_deref(), &log.response_body()) };
+        Some(Case {
+            name: String::new(),
+            method: head.method.to_string(),
+            url: log.full_url(),
+            headers,
+            body: http::decode_body(&String::from_utf8_lossy(raw_head), raw_body),
+            status,
+            content_type,
+            check,
+        })
+    })
+    .enumerate()
+    .map(|(n, case)| Case { name: test_name(&case.method, &case.url, n + 1), ..case })
+    .collect()
+}
+
+/// A Rust string literal
+fn rust_str(text: &str) -> String {
+    format!("{:?}", text)
+}
+
+/// A Python string literal; JSON escapes are valid Python
+fn python_str(text: &str) -> String {
+    serde_json::to_string(text).unwrap_or_default()
+}
+
+fn python_value(value: &Value) -> String {
+    match value {
+        Value::Null => "None".to_string(),
+        Value::Bool(true) => "True".to_string(),
+        Value::Bool(false) => "False".to_string(),
+        Value::String(s) => python_str(s),
+        other => other.to_string(),
+    }
+}
+
+/// A tests/ file for a crate with `reqwest` (blocking feature) and `serde_json` as dev-dependencies
+pub fn reqwest<'a>(logs: impl Iterator<Item = &'a HttpLog>) -> String {
+    let cases = cases(logs);
+    let mut out = format!(
+        "// {} regression test(s) generated by belch from captured traffic.\n\
+         // Needs reqwest (with the `blocking` feature) and serde_json as dev-dependencies.\n\n\
+         use reqwest::blocking::Client;\n\
+         use reqwest::Method;\n\
+         use serde_json::{{json, Value}};\n\n\
+         fn client() -> Client {{\n    \
+             Client::builder().redirect(reqwest::redirect::Policy::none()).build().unwrap()\n\
+         }}\n",
+        cases.len()
+    );
+    for case in &cases {
+        out.push_str(&format!("\n#[test]\nfn {}() {{\n    let response = client()\n", case.name));
+        out.push_str(&format!("        .request(Method::from_bytes(b{}).unwrap(), {})\n", rust_str(&case.method), rust_str(&case.url)));
+        for (name, value) in &case.headers {
+            out.push_str(&format!("        .header({}, {})\n", rust_str(name), rust_str(value)));
+        }
+        if !case.body.is_empty() {
+            match std::str::from_utf8(&case.body) {
+                Ok(text) => out.push_str(&format!("        .body({})\n", rust_str(text))),
+                Err(_) => out.push_str(&format!("        .body(&b\"{}\"[..])\n", case.body.escape_ascii())),
+            }
+        }
+        out.push_str("        .send()\n        .unwrap();\n");
+        out.push_str(&format!("    assert_eq!(response.status().as_u16(), {});\n", case.status));
+        if let Some(t) = &case.content_type {
+            out.push_str(&format!("    assert!(response.headers()[\"content-type\"].to_str().unwrap().starts_with({}));\n", rust_str(t)));
+        }
+        match &case.check {
+            Check::Fields(fields) if !fields.is_empty() => {
+                out.push_str("    let body: Value = serde_json::from_str(&response.text().unwrap()).unwrap();\n");
+                for (key, value) in fields {
+                    let value = match value {
+                        // JSON escapes such as `\u001f` are not Rust's
+                        Value::String(s) => rust_str(s),
+                        other => other.to_string(),
+                    };
+                    out.push_str(&format!("    assert_eq!(body[{}], json!({}));\n", rust_str(key), value));
+                }
+            }
+            Check::Array => out.push_str("    let body: Value = serde_json::from_str(&response.text().unwrap()).unwrap();\n    assert!(body.is_array());\n"),
+            Check::Title(title) => out.push_str(&format!("    assert!(response.text().unwrap().contains({}));\n", rust_str(title))),
+            _ => {}
+        }
+        out.push_str("}\n");
+    }
+    out
+}
+
+/// A pytest module using httpx
+pub fn pytest<'a>(logs: impl Iterator<Item = &'a HttpLog>) -> String {
+    let cases = cases(logs);
+    let mut out = format!("# {} regression test(s) generated by belch from captured traffic.\n# Run with pytest; needs httpx.\n\nimport httpx\n", cases.len());
+    for case in &cases {
+        out.push_str(&format!("\n\ndef test_{}():\n    response = httpx.request(\n", case.name));
+        out.push_str(&format!("        {},\n        {},\n", python_str(&case.method), python_str(&case.url)));
+        if !case.headers.is_empty() {
+            out.push_str("        headers=[\n");
+            for (name, value) in &case.headers {
+                out.push_str(&format!("            ({}, {}),\n", python_str(name), python_str(value)));
+            }
+            out.push_str("        ],\n");
+        }
+        if !case.body.is_empty() {
+            match std::str::from_utf8(&case.body) {
+                Ok(text) => out.push_str(&format!("        content={},\n", python_str(text))),
+                Err(_) => out.push_str(&format!("        content=b\"{}\",\n", case.body.escape_ascii())),
+            }
+        }
+        out.push_str("    )\n");
+        out.push_str(&format!("    assert response.status_code == {}\n", case.status));
+        if let Some(t) = &case.content_type {
+            out.push_str(&format!("    assert response.headers[\"content-type\"].startswith({})\n", python_str(t)));
+        }
+        match &case.check {
+            Check::Fields(fields) if !fields.is_empty() => {
+                out.push_str("    body = response.json()\n");
+                for (key, value) in fields {
+                    let op = if value.is_boolean() || value.is_null() { "is" } else { "==" };
+                    out.push_str(&format!("    assert body[{}] {} {}\n", python_str(key), op, python_value(value)));
+                }
+            }
+            Check::Array => out.push_str("    assert isinstance(response.json(), list)\n"),
+            Check::Title(title) => out.push_str(&format!("    assert {} in response.text\n", python_str(title))),
+            _ => {}
+        }
+    }
+    out
+}
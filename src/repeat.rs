@@ -0,0 +1,151 @@
+// Repeated replay of one request, as an availability or load probe
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tracing::info;
+
+use crate::app::{App, HttpLog, Origin};
+use crate::http;
+use crate::macros;
+use crate::send;
+
+pub struct Sample {
+    /// Since the run started
+    pub at: Duration,
+    /// None when the request failed outright
+    pub status: Option<u16>,
+    pub latency: Duration,
+}
+
+pub struct RepeatRun {
+    pub url: String,
+    /// What was asked for, as shown in the popup title
+    pub plan: String,
+    pub samples: Vec<Sample>,
+    pub running: bool,
+    stop: Arc<AtomicBool>,
+}
+
+impl RepeatRun {
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// Latency at quantile `q` (0.0–1.0) of the answered requests
+    pub fn latency(&self, q: f64) -> Option<Duration> {
+        let mut l: Vec<Duration> = self.samples.iter().filter(|s| s.status.is_some()).map(|s| s.latency).collect();
+        l.sort();
+        l.get(((l.len() as f64 - 1.0) * q).round() as usize).copied()
+    }
+}
+
+enum Mode {
+    /// One request per interval, optionally a fixed number of times
+    Every(Duration, Option<usize>),
+    /// A fixed number of requests spread over concurrent workers
+    Burst(usize, usize),
+}
+
+fn parse(args: &[&str]) -> Result<Mode, String> {
+    const USAGE: &str = "usage: :repeat every SECS [COUNT] | :repeat COUNT [CONCURRENCY] | :repeat stop";
+    let num = |s: &str| s.parse::<usize>().ok().filter(|n| *n > 0).ok_or(USAGE);
+    match args {
+        ["every", secs, rest @ ..] => {
+            // A tenth of a second to a day
+            let interval = secs.trim_end_matches('s').parse().ok().filter(|s: &f64| *s >= 0.1)
+                .and_then(|s| Duration::try_from_secs_f64(s).ok())
+                .filter(|i| i.as_secs() <= 24 * 60 * 60)
+                .ok_or(USAGE)?;
+            let count = rest.first().map(|c| num(c)).transpose()?;
+            Ok(Mode::Every(interval, count))
+        }
+        [count] => Ok(Mode::Burst(num(count)?, 1)),
+        [count, workers] => Ok(Mode::Burst(num(count)?, num(workers)?.min(64))),
+        _ => Err(USAGE.into()),
+    }
+}
+
+/// Send once and record the result, unless a newer run replaced this one
+async fn sample(app: &Arc<Mutex<App>>, log: &HttpLog, request: &[u8], started: Instant, stop: &Arc<AtomicBool>) {
+    let at = started.elapsed();
+    let sent = Instant::now();
+    let status = macros::send_with_session(app, log, request, Origin::Replay).await.ok()
+        .and_then(|r| http::status_code(&String::from_utf8_lossy(http::split_raw(&r).0)));
+    let latency = sent.elapsed();
+    let mut app = app.lock().unwrap();
+    if let Some(run) = app.repeat.as_mut().filter(|r| Arc::ptr_eq(&r.stop, stop)) {
+        run.samples.push(Sample { at, status, latency });
+    }
+}
+
+/// `:repeat …` on the selected entry: start, stop or reopen a run
+pub fn command(guard: &mut App, app: Arc<Mutex<App>>, args: &[&str]) {
+    match args {
+        [] => {
+            guard.show_repeat = guard.repeat.is_some();
+            return;
+        }
+        ["stop"] => {
+            if let Some(run) = &guard.repeat {
+                run.stop();
+            }
+            return;
+        }
+        _ => {}
+    }
+    let mode = match parse(args) {
+        Ok(mode) => mode,
+        Err(e) => return guard.set_status(e),
+    };
+    let Some(log) = guard.selected_log().filter(|l| !l.request_raw.is_empty()).cloned() else {
+        return guard.set_status("Select a captured request to repeat");
+    };
+    if let Some(old) = &guard.repeat {
+        old.stop();
+    }
+    let stop = Arc::new(AtomicBool::new(false));
+    let plan = match mode {
+        Mode::Every(interval, Some(count)) => format!("every {:.1}s, {} times", interval.as_secs_f64(), count),
+        Mode::Every(interval, None) => format!("every {:.1}s", interval.as_secs_f64()),
+        Mode::Burst(count, workers) => format!("{} requests, {} at a time", count, workers),
+    };
+    guard.repeat = Some(RepeatRun { url: log.full_url(), plan, samples: Vec::new(), running: true, stop: stop.clone() });
+    guard.show_repeat = true;
+    tokio::spawn(async move {
+        let request = send::replayable(&log);
+        let started = Instant::now();
+        match mode {
+            Mode::Every(interval, count) => {
+                let mut ticks = tokio::time::interval(interval);
+                for _ in 0..count.unwrap_or(usize::MAX) {
+                    ticks.tick().await;
+                    if stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    sample(&app, &log, &request, started, &stop).await;
+                }
+            }
+            Mode::Burst(count, workers) => {
+                let next = Arc::new(AtomicUsize::new(0));
+                let tasks: Vec<_> = (0..workers).map(|_| {
+                    let (app, log, request, stop, next) = (app.clone(), log.clone(), request.clone(), stop.clone(), next.clone());
+                    tokio::spawn(async move {
+                        while next.fetch_add(1, Ordering::Relaxed) < count && !stop.load(Ordering::Relaxed) {
+                            sample(&app, &log, &request, started, &stop).await;
+                        }
+                    })
+                }).collect();
+                for task in tasks {
+                    let _ = task.await;
+                }
+            }
+        }
+        let mut app = app.lock().unwrap();
+        if let Some(run) = app.repeat.as_mut().filter(|r| Arc::ptr_eq(&r.stop, &stop)) {
+            run.running = false;
+            info!("repeat of {} finished after {} requests", run.url, run.samples.len());
+        }
+    });
+}
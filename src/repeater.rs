@@ -0,0 +1,198 @@
+// Repeater: edit a captured request and send it again, normalized or as exact bytes
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tracing::info;
+
+use crate::app::{self, App, HttpLog, Origin};
+use crate::editor::TextArea;
+use crate::{http, macros, send, transform};
+
+pub struct Repeater {
+    pub host: String,
+    pub port: u16,
+    pub tls: bool,
+    /// Send exactly what is in the editor: escapes decoded, nothing fixed up
+    pub raw: bool,
+    pub editor: TextArea,
+    /// The last answer and how long it took, or why there was none
+    pub response: Option<Result<(Vec<u8>, Duration), String>>,
+    pub sending: bool,
+    /// First response line shown
+    pub scroll: usize,
+    /// The entry it was opened from, for its route and the macro session
+    log: HttpLog,
+    /// Identifies the send in flight, so a reopened Repeater ignores an older answer
+    run: Arc<()>,
+}
+
+/// `\r`, `\n`, `\t`, `\0`, `\xNN` and `\\` as the bytes they name; anything else is kept as typed
+fn unescape(text: &str) -> Vec<u8> {
+    let bytes = text.as_bytes();
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let (b, used) = match (bytes[i], bytes.get(i + 1)) {
+            (b'\\', Some(b'r')) => (b'\r', 2),
+            (b'\\', Some(b'n')) => (b'\n', 2),
+            (b'\\', Some(b't')) => (b'\t', 2),
+            (b'\\', Some(b'0')) => (0, 2),
+            (b'\\', Some(b'\\')) => (b'\\', 2),
+            (b'\\', Some(b'x')) => match text.get(i + 2..i + 4).filter(|h| h.bytes().all(|c| c.is_ascii_hexdigit())) {
+                Some(hex) => (u8::from_str_radix(hex, 16).unwrap(), 4),
+                None => (b'\\', 1),
+            },
+            (b, _) => (b, 1),
+        };
+        out.push(b);
+        i += used;
+    }
+    out
+}
+
+/// Editor text that `unescape` turns back into exactly `bytes`, one line per CRLF
+fn escape(bytes: &[u8]) -> String {
+    let mut text = String::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let used = match (bytes[i], bytes.get(i + 1)) {
+            (b'\r', Some(b'\n')) => {
+                text.push('\n');
+                2
+            }
+            (b, _) => {
+                match b {
+                    b'\\' => text.push_str("\\\\"),
+                    b'\r' => text.push_str("\\r"),
+                    b'\n' => text.push_str("\\n"),
+                    b'\t' => text.push_str("\\t"),
+                    b' ' | b'!'..=b'~' => text.push(b as char),
+                    _ => text.push_str(&format!("\\x{:02x}", b)),
+                }
+                1
+            }
+        };
+        i += used;
+    }
+    text
+}
+
+/// A CRLF-separated request with a blank line after the head and, unless it is chunked,
+/// a `Content-Length` matching the body
+pub fn frame(raw: &[u8]) -> Vec<u8> {
+    let (head, body) = match raw.windows(4).position(|w| w == b"\r\n\r\n") {
+        Some(i) => (String::from_utf8_lossy(&raw[..i]).to_string(), &raw[i + 4..]),
+        None => (String::from_utf8_lossy(raw).trim_end_matches("\r\n").to_string(), &b""[..]),
+    };
+    let head = head.as_str();
+    let mut request = [format!("{}\r\n\r\n", head).as_bytes(), body].concat();
+    let chunked = http::header_value(head, "transfer-encoding").is_some_and(|v| v.to_lowercase().contains("chunked"));
+    if !chunked && (!body.is_empty() || http::header_value(head, "content-length").is_some()) {
+        request = http::set_header(&request, "Content-Length", &body.len().to_string());
+    }
+    request
+}
+
+impl Repeater {
+    pub fn open(log: &HttpLog) -> Self {
+        let port = send::target_port(log);
+        Repeater {
+            host: log.host.clone(),
+            port,
+            tls: log.tls,
+            raw: false,
+            editor: TextArea::new(&String::from_utf8_lossy(&send::replayable(log))),
+            response: None,
+            sending: false,
+            scroll: 0,
+            log: log.clone(),
+            run: Arc::new(()),
+        }
+    }
+
+    /// In raw mode on exactly `request`, sent to the entry's server
+    pub fn raw(log: &HttpLog, request: &[u8]) -> Self {
+        Repeater { raw: true, editor: TextArea::new(&escape(request)), ..Repeater::open(log) }
+    }
+
+    /// Switch between plaintext and TLS, moving a default port along
+    pub fn toggle_tls(&mut self) {
+        self.tls = !self.tls;
+        self.port = match (self.port, self.tls) {
+            (80, true) => 443,
+            (443, false) => 80,
+            (p, _) => p,
+        };
+    }
+
+    /// The bytes a send writes. Raw: each line break is CRLF, a line ending in a lone `\` runs
+    /// on without one, and escapes are decoded. Otherwise the head ends in a blank line and
+    /// `Content-Length` matches the body unless it is chunked. `<%name(…)%>` tags are evaluated
+    /// in both, after escapes and before framing.
+    pub fn request(&self) -> Result<Vec<u8>, String> {
+        if self.raw {
+            let mut text = String::new();
+            for (i, line) in self.editor.lines.iter().enumerate() {
+                let continued = line.ends_with('\\') && !line.ends_with("\\\\");
+                text.push_str(if continued { &line[..line.len() - 1] } else { line });
+                if !continued && i + 1 < self.editor.lines.len() {
+                    text.push_str("\r\n");
+                }
+            }
+            return transform::expand(&unescape(&text));
+        }
+        Ok(frame(&transform::expand(self.editor.text("\r\n").as_bytes())?))
+    }
+
+    /// The entry it was opened from, pointed at the Repeater's host, port and scheme
+    pub fn target(&self) -> HttpLog {
+        let mut target = self.log.clone();
+        (target.host, target.port, target.tls) = (self.host.clone(), self.port, self.tls);
+        target
+    }
+}
+
+/// Send the editor's request in the background; the answer lands in `repeater.response`
+pub fn send(guard: &mut App, app: Arc<Mutex<App>>) {
+    let host = guard.repeater.as_ref().map_or(String::new(), |r| r.host.clone());
+    if guard.refuse_for("sending", &host) {
+        return;
+    }
+    let Some(r) = guard.repeater.as_mut() else { return };
+    if r.sending {
+        return;
+    }
+    let request = match r.request() {
+        Ok(request) => request,
+        Err(e) => {
+            r.response = Some(Err(format!("Not sent: {}", e)));
+            return;
+        }
+    };
+    let target = r.target();
+    let raw = r.raw;
+    r.sending = true;
+    r.run = Arc::new(());
+    let run = r.run.clone();
+    tokio::spawn(async move {
+        let started = Instant::now();
+        let result = if raw {
+            // Untouched: no session applied, no framing fixed
+            let via = app.lock().unwrap().routes.route(&target.host).cloned();
+            let result = send::send(&target.host, target.port, target.tls, &request, via.as_ref()).await;
+            let entry = HttpLog::sent(&target, &request, result.as_deref().map_err(|(_, e)| e.as_str()), started.elapsed(), Origin::Repeater);
+            app::capture(&app, entry);
+            result
+        } else {
+            macros::send_with_session(&app, &target, &request, Origin::Repeater).await
+        };
+        let elapsed = started.elapsed();
+        info!("repeater sent {} bytes to {}:{} ({})", request.len(), target.host, target.port, if raw { "raw" } else { "http" });
+        let mut guard = app.lock().unwrap();
+        let Some(r) = guard.repeater.as_mut().filter(|r| Arc::ptr_eq(&r.run, &run)) else { return };
+        r.sending = false;
+        r.scroll = 0;
+        r.response = Some(result.map(|raw| (raw, elapsed)).map_err(|(_, reason)| reason));
+    });
+}
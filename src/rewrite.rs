@@ -0,0 +1,171 @@
+// Match/replace rules: a regex replaced in the head or body of requests on their way out, or of
+// responses on their way back to the client, for every host or chosen ones. Entries keep the
+// response as the server sent it and are tagged `rewrite` when a rule changed anything.
+
+use std::sync::{Arc, Mutex};
+
+use regex::bytes::Regex;
+
+use crate::app::App;
+use crate::config;
+use crate::http;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Part {
+    RequestHeader,
+    RequestBody,
+    ResponseHeader,
+    ResponseBody,
+}
+
+impl Part {
+    pub const ALL: [Part; 4] = [Part::RequestHeader, Part::RequestBody, Part::ResponseHeader, Part::ResponseBody];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Part::RequestHeader => "request-header",
+            Part::RequestBody => "request-body",
+            Part::ResponseHeader => "response-header",
+            Part::ResponseBody => "response-body",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Part::ALL.into_iter().find(|p| p.name() == s)
+    }
+
+    fn request(self) -> bool {
+        matches!(self, Part::RequestHeader | Part::RequestBody)
+    }
+
+    fn body(self) -> bool {
+        matches!(self, Part::RequestBody | Part::ResponseBody)
+    }
+}
+
+#[derive(Clone)]
+pub struct Rule {
+    pub part: Part,
+    pub pattern: Regex,
+    /// `$1`/`${name}` refer to capture groups
+    pub replace: String,
+    /// Host patterns the rule is limited to; empty for all
+    pub hosts: Vec<String>,
+}
+
+impl Rule {
+    pub fn new(part: &str, pattern: &str, replace: &str, hosts: Vec<String>) -> Result<Self, String> {
+        let part = Part::parse(part).ok_or_else(|| format!("`{}` is not {}", part, Part::ALL.map(Part::name).join(", ")))?;
+        // Multi-line with CRLF line ends, so `^` and `$` work per header line
+        let pattern = Regex::new(&format!("(?mR){}", pattern)).map_err(|e| format!("bad pattern `{}`: {}", pattern, e))?;
+        Ok(Rule { part, pattern, replace: replace.to_string(), hosts })
+    }
+
+    /// The pattern as written
+    pub fn source(&self) -> &str {
+        self.pattern.as_str().trim_start_matches("(?mR)")
+    }
+
+    pub fn label(&self) -> String {
+        let hosts = if self.hosts.is_empty() { String::new() } else { format!(" on {}", self.hosts.join(", ")) };
+        format!("{} /{}/ → \"{}\"{}", self.part.name(), self.source(), self.replace, hosts)
+    }
+}
+
+/// `message` with the request or response rules for `host` applied; header rules see the head
+/// (request or status line included), body rules the decoded body. None when nothing changed.
+fn apply(rules: &[Rule], host: &str, request: bool, message: &[u8]) -> Option<Vec<u8>> {
+    let rules: Vec<&Rule> = rules.iter().filter(|r| r.part.request() == request && (r.hosts.is_empty() || config::host_matches(&r.hosts, host))).collect();
+    if rules.is_empty() {
+        return None;
+    }
+    let (raw_head, raw_body) = http::split_raw(message);
+    let decoded = rules.iter().any(|r| r.part.body()).then(|| http::decode_body(&String::from_utf8_lossy(raw_head), raw_body));
+    let (mut head, mut body) = (raw_head.to_vec(), decoded.clone());
+    for rule in &rules {
+        let text = match (rule.part.body(), body.as_mut()) {
+            (true, Some(body)) => body,
+            _ => &mut head,
+        };
+        if rule.pattern.is_match(text) {
+            *text = rule.pattern.replace_all(text, rule.replace.as_bytes()).into_owned();
+        }
+    }
+    if head == raw_head && body == decoded {
+        return None;
+    }
+    head.extend_from_slice(b"\r\n\r\n");
+    match body.filter(|b| Some(b) != decoded.as_ref()) {
+        // The body goes out decoded, so its length and encoding headers change with it
+        Some(body) => {
+            let head = http::remove_header(&http::remove_header(&head, "Transfer-Encoding"), "Content-Encoding");
+            let mut out = http::set_header(&head, "Content-Length", &body.len().to_string());
+            out.extend_from_slice(&body);
+            Some(out)
+        }
+        None => {
+            head.extend_from_slice(raw_body);
+            Some(head)
+        }
+    }
+}
+
+/// `request` after the request rules for `host`; true when any changed it
+pub fn request(app: &Arc<Mutex<App>>, host: &str, request: Vec<u8>) -> (Vec<u8>, bool) {
+    let rules = {
+        let guard = app.lock().unwrap();
+        if guard.read_only_for(host) || guard.rewrites.is_empty() {
+            return (request, false);
+        }
+        guard.rewrites.clone()
+    };
+    match apply(&rules, host, true, &request) {
+        Some(changed) => (changed, true),
+        None => (request, false),
+    }
+}
+
+/// `response` after the response rules for `host`, or None when none changed it
+pub fn response(app: &Arc<Mutex<App>>, host: &str, response: &[u8]) -> Option<Vec<u8>> {
+    let rules = {
+        let guard = app.lock().unwrap();
+        if guard.read_only_for(host) || guard.rewrites.is_empty() {
+            return None;
+        }
+        guard.rewrites.clone()
+    };
+    apply(&rules, host, false, response)
+}
+
+/// `:rewrite` lists the rules, `:rewrite add PART REGEX [REPLACEMENT…]` adds one for every host and
+/// `:rewrite delete N` removes one; `:config save` keeps them
+pub fn command(app: &mut App, args: &[&str]) {
+    const USAGE: &str = "usage: :rewrite | add request-header|request-body|response-header|response-body REGEX [REPLACEMENT…] | delete N";
+    match args {
+        [] if app.rewrites.is_empty() => app.set_status(format!("No match/replace rules ({})", USAGE)),
+        [] => {
+            let list: Vec<String> = app.rewrites.iter().enumerate().map(|(i, r)| format!("{}. {}", i + 1, r.label())).collect();
+            app.set_status(list.join("   "));
+        }
+        ["add", part, pattern, replace @ ..] => {
+            if app.refuse("match/replace rules") {
+                return;
+            }
+            match Rule::new(part, pattern, &replace.join(" "), Vec::new()) {
+                Ok(rule) => {
+                    app.set_status(format!("Rule {} added: {}", app.rewrites.len() + 1, rule.label()));
+                    app.rewrites.push(rule);
+                }
+                Err(e) => app.set_status(e),
+            }
+        }
+        ["delete", n] => match n.parse::<usize>().ok().filter(|n| (1..=app.rewrites.len()).contains(n)) {
+            Some(n) => {
+                let rule = app.rewrites.remove(n - 1);
+                app.set_status(format!("Removed {}", rule.label()));
+            }
+            None => app.set_status(format!("No rule {}", n)),
+        },
+        _ => app.set_status(USAGE),
+    }
+}
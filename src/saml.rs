@@ -0,0 +1,209 @@
+// SAMLRequest/SAMLResponse decoding for SSO debugging
+
+use std::io::Read;
+use std::sync::OnceLock;
+
+use base64::Engine;
+use flate2::read::DeflateDecoder;
+use regex::Regex;
+
+use crate::app::HttpLog;
+use crate::http;
+
+pub struct Saml {
+    /// `SAMLRequest` or `SAMLResponse`
+    pub param: String,
+    /// Where it was found: query, form body or response form
+    pub source: &'static str,
+    pub xml: String,
+    /// Key fields; a `None` value is a notable absence, such as no signature
+    pub fields: Vec<(&'static str, Option<String>)>,
+}
+
+struct Patterns {
+    /// Hidden inputs of auto-submitting forms, with `name` before or after `value`
+    form: Regex,
+    form_reversed: Regex,
+    issuer: Regex,
+    audience: Regex,
+    name_id: Regex,
+    conditions: Regex,
+    status: Regex,
+    signature: Regex,
+    encrypted: Regex,
+    root: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| Patterns {
+        form: Regex::new(r#"(?i)name\s*=\s*["'](SAMLRequest|SAMLResponse)["'][^>]*?value\s*=\s*["']([^"']+)"#).unwrap(),
+        form_reversed: Regex::new(r#"(?i)value\s*=\s*["']([^"']+)["'][^>]*?name\s*=\s*["'](SAMLRequest|SAMLResponse)["']"#).unwrap(),
+        issuer: Regex::new(r"<(?:\w+:)?Issuer[^>]*>\s*([^<]+?)\s*<").unwrap(),
+        audience: Regex::new(r"<(?:\w+:)?Audience>\s*([^<]+?)\s*<").unwrap(),
+        name_id: Regex::new(r"<(?:\w+:)?NameID([^>]*)>\s*([^<]+?)\s*<").unwrap(),
+        conditions: Regex::new(r"<(?:\w+:)?Conditions([^>]*)>").unwrap(),
+        status: Regex::new(r#"<(?:\w+:)?StatusCode[^>]*Value="([^"]+)""#).unwrap(),
+        signature: Regex::new(r"<(?:\w+:)?Signature[\s>]").unwrap(),
+        encrypted: Regex::new(r"<(?:\w+:)?EncryptedAssertion[\s>]").unwrap(),
+        root: Regex::new(r"<(?:\w+:)?(\w+)((?:\s+[^>]*?)?)/?>").unwrap(),
+    })
+}
+
+/// Value of `name="..."` in a tag's attribute text
+fn attr(attrs: &str, name: &str) -> Option<String> {
+    let start = attrs.find(&format!("{}=\"", name))? + name.len() + 2;
+    attrs[start..].split('"').next().map(str::to_string)
+}
+
+fn unescape_html(s: &str) -> String {
+    s.replace("&#x2b;", "+").replace("&#x2B;", "+").replace("&#43;", "+")
+        .replace("&#x3d;", "=").replace("&#x3D;", "=").replace("&#61;", "=")
+        .replace("&#x2f;", "/").replace("&#x2F;", "/").replace("&#47;", "/")
+        .replace("&amp;", "&")
+}
+
+/// Most bytes a redirect-binding message is inflated to; real ones are a few KB
+const MAX_INFLATED: u64 = 1 << 20;
+
+/// Base64, then raw DEFLATE for the redirect binding; the POST binding is not compressed
+fn decode(value: &str) -> Option<String> {
+    // `+` may have been turned into a space by form decoding
+    let cleaned: String = value.chars().filter(|c| !matches!(c, '\r' | '\n')).map(|c| if c == ' ' { '+' } else { c }).collect();
+    let raw = base64::engine::general_purpose::STANDARD.decode(cleaned.trim()).ok()?;
+    let mut inflated = String::new();
+    let bounded = DeflateDecoder::new(&raw[..]).take(MAX_INFLATED).read_to_string(&mut inflated).is_ok();
+    if bounded && inflated.trim_start().starts_with('<') {
+        return Some(inflated);
+    }
+    let text = String::from_utf8(raw).ok()?;
+    text.trim_start().starts_with('<').then_some(text)
+}
+
+/// XML re-indented one element per line, short text content kept inline
+fn pretty_xml(xml: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut depth: usize = 0;
+    // The last line ends with an opening tag, or an opening tag and its text
+    let mut open = false;
+    let mut rest = xml.trim();
+    while !rest.is_empty() {
+        let end = if rest.starts_with('<') {
+            rest.find('>').map_or(rest.len(), |i| i + 1)
+        } else {
+            rest.find('<').unwrap_or(rest.len())
+        };
+        let (token, next) = rest.split_at(end);
+        rest = next;
+        let token = token.trim();
+        if token.is_empty() {
+            continue;
+        }
+        if !token.starts_with('<') {
+            match lines.last_mut() {
+                Some(line) if open && rest.starts_with("</") => line.push_str(token),
+                _ => {
+                    lines.push(format!("{}{}", "  ".repeat(depth), token));
+                    open = false;
+                }
+            }
+            continue;
+        }
+        if token.starts_with("</") {
+            depth = depth.saturating_sub(1);
+            match lines.last_mut() {
+                Some(line) if open => line.push_str(token),
+                _ => lines.push(format!("{}{}", "  ".repeat(depth), token)),
+            }
+            open = false;
+            continue;
+        }
+        lines.push(format!("{}{}", "  ".repeat(depth), token));
+        open = !token.starts_with("<?") && !token.starts_with("<!") && !token.ends_with("/>");
+        if open {
+            depth += 1;
+        }
+    }
+    lines.join("\n")
+}
+
+fn fields(xml: &str) -> Vec<(&'static str, Option<String>)> {
+    let p = patterns();
+    let mut out = Vec::new();
+    if let Some(root) = p.root.captures_iter(xml).find(|c| !c[0].starts_with("<?")) {
+        out.push(("Type", Some(root[1].to_string())));
+        for name in ["ID", "InResponseTo", "Destination", "AssertionConsumerServiceURL", "IssueInstant"] {
+            if let Some(v) = attr(&root[2], name) {
+                out.push((name, Some(v)));
+            }
+        }
+    }
+    if let Some(c) = p.status.captures(xml) {
+        out.push(("Status", Some(c[1].rsplit(':').next().unwrap_or("").to_string())));
+    }
+    if let Some(c) = p.issuer.captures(xml) {
+        out.push(("Issuer", Some(c[1].to_string())));
+    }
+    if let Some(c) = p.name_id.captures(xml) {
+        let format = attr(&c[1], "Format").map(|f| format!(" ({})", f.rsplit(':').next().unwrap_or(""))).unwrap_or_default();
+        out.push(("NameID", Some(format!("{}{}", &c[2], format))));
+    }
+    let audiences: Vec<&str> = p.audience.captures_iter(xml).filter_map(|c| c.get(1)).map(|m| m.as_str()).collect();
+    if !audiences.is_empty() {
+        out.push(("Audience", Some(audiences.join(", "))));
+    }
+    if let Some(c) = p.conditions.captures(xml) {
+        let window = format!(
+            "{} → {}",
+            attr(&c[1], "NotBefore").unwrap_or_else(|| "?".into()),
+            attr(&c[1], "NotOnOrAfter").unwrap_or_else(|| "?".into()),
+        );
+        out.push(("Conditions", Some(window)));
+    }
+    if p.encrypted.is_match(xml) {
+        out.push(("Assertion", Some("encrypted".into())));
+    }
+    let signatures = p.signature.find_iter(xml).count();
+    out.push(("Signature", (signatures > 0).then(|| format!("{} present", signatures))));
+    out
+}
+
+/// SAML messages in the request's query or form body, or in a response's auto-post form
+pub fn find(log: &HttpLog) -> Vec<Saml> {
+    let request = log.request_raw.text();
+    let (head, body) = http::split_message(&request);
+    let target = head.split_whitespace().nth(1).unwrap_or("");
+    let mut values: Vec<(String, String, &'static str)> = Vec::new();
+    if let Some((_, query)) = target.split_once('?') {
+        values.extend(http::parse_query(query).into_iter().map(|(k, v)| (k, v, "query")));
+    }
+    if http::header_value(head, "content-type").is_some_and(|t| t.to_lowercase().contains("x-www-form-urlencoded")) {
+        values.extend(http::parse_query(body).into_iter().map(|(k, v)| (k, v, "form body")));
+    }
+    let response = String::from_utf8_lossy(&log.response_body()).into_owned();
+    if response.contains("SAML") {
+        let p = patterns();
+        values.extend(p.form.captures_iter(&response).map(|c| (c[1].to_string(), unescape_html(&c[2]), "response form")));
+        values.extend(p.form_reversed.captures_iter(&response).map(|c| (c[2].to_string(), unescape_html(&c[1]), "response form")));
+    }
+    // The redirect binding signs the query string rather than the XML
+    let query_signature = values.iter()
+        .find(|(k, _, s)| k == "SigAlg" && *s == "query")
+        .filter(|_| values.iter().any(|(k, _, s)| k == "Signature" && *s == "query"))
+        .map(|(_, alg, _)| format!("in query string ({})", alg.rsplit('#').next().unwrap_or("")));
+    values.iter()
+        .filter(|(k, _, _)| k == "SAMLRequest" || k == "SAMLResponse")
+        .filter_map(|(param, value, source)| {
+            let xml = decode(value)?;
+            let mut fields = fields(&xml);
+            if *source == "query" {
+                for (name, value) in fields.iter_mut() {
+                    if *name == "Signature" && value.is_none() {
+                        *value = query_signature.clone();
+                    }
+                }
+            }
+            Some(Saml { fields, xml: pretty_xml(&xml), param: param.clone(), source })
+        })
+        .collect()
+}
@@ -0,0 +1,113 @@
+// Visual selection in the detail pane: a range of its text to copy, hand to the Decoder or mark
+// as a fuzzing insertion point
+
+use std::io::Write;
+
+use base64::Engine;
+
+use crate::app::{App, Tab};
+use crate::editor::TextArea;
+use crate::fuzzer;
+
+/// Line and column of the detail pane's text, in characters
+pub type Point = (usize, usize);
+
+pub struct Selection {
+    /// Where the selection started; the cursor is its other end
+    pub anchor: Point,
+    pub cursor: Point,
+}
+
+fn line_len(lines: &[String], line: usize) -> usize {
+    lines.get(line).map_or(0, |l| l.chars().count())
+}
+
+impl Selection {
+    pub fn at(point: Point) -> Self {
+        Selection { anchor: point, cursor: point }
+    }
+
+    /// Both ends in reading order; the last character is included
+    fn bounds(&self) -> (Point, Point) {
+        (self.anchor.min(self.cursor), self.anchor.max(self.cursor))
+    }
+
+    /// Selected columns of a line, end exclusive; past the line's end when the cursor sits there
+    pub fn columns(&self, line: usize, len: usize) -> Option<(usize, usize)> {
+        let (start, end) = self.bounds();
+        if line < start.0 || line > end.0 {
+            return None;
+        }
+        let from = if line == start.0 { start.1.min(len) } else { 0 };
+        let to = if line == end.0 { end.1 + 1 } else { len };
+        Some((from, to))
+    }
+
+    /// The selected text, lines joined with `\n`
+    pub fn text(&self, lines: &[String]) -> String {
+        let (start, end) = self.bounds();
+        (start.0..=end.0.min(lines.len().saturating_sub(1)))
+            .filter_map(|i| {
+                let line = lines.get(i)?;
+                let (from, to) = self.columns(i, line.chars().count())?;
+                Some(line.chars().skip(from).take(to.saturating_sub(from)).collect::<String>())
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Moves the cursor, kept on the text: a line down or up, a column right or left
+    pub fn shift(&mut self, lines: &[String], down: isize, right: isize) {
+        let last = lines.len().saturating_sub(1);
+        let line = self.cursor.0.saturating_add_signed(down).min(last);
+        let col = self.cursor.1.min(line_len(lines, self.cursor.0)).saturating_add_signed(right);
+        self.cursor = (line, col.min(line_len(lines, line)));
+    }
+
+    pub fn line_start(&mut self) {
+        self.cursor.1 = 0;
+    }
+
+    pub fn line_end(&mut self, lines: &[String]) {
+        self.cursor.1 = line_len(lines, self.cursor.0);
+    }
+}
+
+/// Puts `text` on the clipboard of the terminal, even over SSH, with an OSC 52 escape
+pub fn copy(app: &mut App, text: &str) {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+    let mut out = std::io::stdout();
+    let _ = write!(out, "\x1b]52;c;{}\x07", encoded);
+    let _ = out.flush();
+    app.set_status(format!("Copied {} characters to the clipboard", text.chars().count()));
+}
+
+/// Opens the Decoder tab with `text` as its input
+pub fn decode(app: &mut App, text: &str) {
+    app.pipeline.input = TextArea::new(text);
+    app.pipeline.scroll = 0;
+    app.selection = None;
+    app.tab = Tab::Decoder;
+}
+
+/// Opens the selected request in the Repeater with the first occurrence of `text` marked
+/// `§text§`, ready for `:fuzz`
+pub fn fuzz(app: &mut App, text: &str) {
+    if text.is_empty() || text.contains(fuzzer::MARKER) {
+        return app.set_status("Select some text of the request to fuzz");
+    }
+    app.open_repeater();
+    if !app.show_repeater {
+        return;
+    }
+    let Some(repeater) = app.repeater.as_mut() else { return };
+    let request = repeater.editor.text("\n");
+    let Some(at) = request.find(text) else {
+        app.show_repeater = false;
+        return app.set_status("The selection isn't part of the request");
+    };
+    let marked = format!("{}{m}{}{m}{}", &request[..at], text, &request[at + text.len()..], m = fuzzer::MARKER);
+    repeater.editor = TextArea::new(&marked);
+    app.selection = None;
+    app.set_status("Insertion point marked; :fuzz LIST runs payloads through it");
+}
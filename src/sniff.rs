@@ -0,0 +1,84 @@
+// Best guess at what a client is speaking when its first bytes are not HTTP
+
+/// What to do with connections that are not HTTP
+#[derive(Clone, Default, PartialEq, Eq)]
+pub enum NonHttp {
+    /// Close them
+    #[default]
+    Reject,
+    /// Tunnel TLS to port 443 of the server it names; reject the rest
+    Sni,
+    /// Tunnel everything to one `host:port`
+    Forward(String),
+}
+
+impl NonHttp {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "reject" => Some(NonHttp::Reject),
+            "sni" => Some(NonHttp::Sni),
+            target if target.rsplit_once(':').is_some_and(|(h, p)| !h.is_empty() && p.parse::<u16>().is_ok()) => {
+                Some(NonHttp::Forward(target.to_string()))
+            }
+            _ => None,
+        }
+    }
+}
+
+pub struct Detected {
+    pub label: &'static str,
+    /// Server name from a TLS ClientHello
+    pub sni: Option<String>,
+}
+
+/// Label the first bytes of a connection
+pub fn detect(data: &[u8]) -> Detected {
+    let label = match data {
+        [0x16, 0x03, ..] => "TLS ClientHello (no CONNECT)",
+        [0x80, _, 0x01, ..] => "SSLv2 ClientHello",
+        _ if data.starts_with(b"SSH-") => "SSH",
+        _ if data.starts_with(b"PRI * HTTP/2.0") => "HTTP/2 prior knowledge",
+        [0x05, n, ..] if *n > 0 && data.len() == 2 + *n as usize => "SOCKS5",
+        [0x04, 0x01 | 0x02, ..] => "SOCKS4",
+        _ if data.iter().all(|b| b.is_ascii_graphic() || b.is_ascii_whitespace()) => "Unknown text protocol",
+        _ => "Unknown binary protocol",
+    };
+    let sni = if data.starts_with(&[0x16, 0x03]) { client_hello_sni(data) } else { None };
+    Detected { label, sni }
+}
+
+/// Server name indication from a TLS ClientHello record
+fn client_hello_sni(data: &[u8]) -> Option<String> {
+    // Record header (5), handshake header (4), version (2), random (32)
+    let mut at = 5 + 4 + 2 + 32;
+    let session = *data.get(at)? as usize;
+    at += 1 + session;
+    let suites = u16::from_be_bytes([*data.get(at)?, *data.get(at + 1)?]) as usize;
+    at += 2 + suites;
+    let compression = *data.get(at)? as usize;
+    at += 1 + compression;
+    let end = (at + 2 + u16::from_be_bytes([*data.get(at)?, *data.get(at + 1)?]) as usize).min(data.len());
+    at += 2;
+    while at + 4 <= end {
+        let kind = u16::from_be_bytes([data[at], data[at + 1]]);
+        let len = u16::from_be_bytes([data[at + 2], data[at + 3]]) as usize;
+        at += 4;
+        if kind == 0 {
+            // List length (2), name type (1), name length (2), name
+            let name_len = u16::from_be_bytes([*data.get(at + 3)?, *data.get(at + 4)?]) as usize;
+            let name = data.get(at + 5..at + 5 + name_len)?;
+            return String::from_utf8(name.to_vec()).ok();
+        }
+        at += len;
+    }
+    None
+}
+
+/// Hex and ASCII columns for the first bytes, as shown in the detail pane
+pub fn preview(data: &[u8]) -> String {
+    data.chunks(16).take(8).map(|row| {
+        let hex: Vec<String> = row.iter().map(|b| format!("{:02x}", b)).collect();
+        let ascii: String = row.iter().map(|b| if b.is_ascii_graphic() { *b as char } else { '.' }).collect();
+        format!("{:<48}{}", hex.join(" "), ascii)
+    }).collect::<Vec<_>>().join("\n")
+}
@@ -0,0 +1,247 @@
+// Spider: follows in-scope links from captured pages. Its requests go through belch's own
+// listener, so every page it reaches is logged and scanned like browser traffic.
+
+use std::collections::{HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use rustls::pki_types::{CertificateDer, ServerName};
+use regex::Regex;
+use rustls::{ClientConfig, RootCertStore};
+use tokio::io::AsyncWriteExt;
+use tokio_rustls::TlsConnector;
+use tracing::info;
+
+use crate::app::{App, HttpLog, Origin};
+use crate::config::Scope;
+use crate::error_page::Failure;
+use crate::store::Stored;
+use crate::upstream::{self, Upstream};
+use crate::{http, recon, send, throttle};
+
+/// Link hops followed from the captured pages when `:spider` is given no depth
+const DEFAULT_DEPTH: usize = 2;
+/// Most pages one run requests
+const MAX_PAGES: usize = 500;
+/// Pause between requests, to go easy on the target
+const DELAY: Duration = Duration::from_millis(100);
+/// Not worth a request: nothing to follow in them
+const SKIPPED: [&str; 14] = ["png", "jpg", "jpeg", "gif", "svg", "ico", "webp", "css", "woff", "woff2", "ttf", "mp4", "pdf", "zip"];
+
+/// `(tls, host, port, origin-form target)` of an absolute http(s) URL, fragment dropped
+pub fn target(url: &str) -> Option<(bool, String, u16, String)> {
+    let (scheme, rest) = url.split_once("://")?;
+    let tls = match scheme.to_lowercase().as_str() {
+        "http" => false,
+        "https" => true,
+        _ => return None,
+    };
+    let rest = rest.split('#').next().unwrap_or("");
+    let end = rest.find(['/', '?']).unwrap_or(rest.len());
+    let authority = rest[..end].rsplit_once('@').map_or(&rest[..end], |(_, a)| a);
+    let (host, port) = match http::split_authority(authority) {
+        (h, Some(p)) => (h, p.parse().ok()?),
+        (h, None) => (h, if tls { 443 } else { 80 }),
+    };
+    let path = match &rest[end..] {
+        "" => "/".to_string(),
+        p if p.starts_with('?') => format!("/{}", p),
+        p => p.to_string(),
+    };
+    (!host.is_empty()).then(|| (tls, host.to_lowercase(), port, path))
+}
+
+/// Worth following: not a static file, and not a link that would end the session
+fn followable(url: &str) -> bool {
+    let path = url.split(['?', '#']).next().unwrap_or("").to_lowercase();
+    let ext = path.rsplit_once('.').map(|(_, e)| e).filter(|e| !e.contains('/'));
+    !ext.is_some_and(|e| SKIPPED.contains(&e)) && !["logout", "logoff", "signout", "sign-out"].iter().any(|w| path.contains(w))
+}
+
+fn hrefs() -> &'static Regex {
+    static HREF: OnceLock<Regex> = OnceLock::new();
+    HREF.get_or_init(|| Regex::new(r#"(?i)\b(?:href|src|action)\s*=\s*["']([^"'#]*)"#).unwrap())
+}
+
+/// `href` resolved against the page at `base`, dot segments removed; None for other schemes
+pub fn resolve(base: &str, href: &str) -> Option<String> {
+    let href = href.trim().replace("&amp;", "&");
+    if href.contains("://") {
+        return Some(href);
+    }
+    // javascript:, mailto:, data: and the like
+    if href.split(['/', '?']).next().is_some_and(|s| s.contains(':')) {
+        return None;
+    }
+    let (scheme, rest) = base.split_once("://")?;
+    if let Some(rest) = href.strip_prefix("//") {
+        return Some(format!("{}://{}", scheme, rest));
+    }
+    let end = rest.find(['/', '?']).unwrap_or(rest.len());
+    let (authority, base_path) = (&rest[..end], rest[end..].split('?').next().unwrap_or(""));
+    let path = if href.is_empty() {
+        return Some(base.to_string());
+    } else if href.starts_with('?') {
+        format!("{}{}", if base_path.is_empty() { "/" } else { base_path }, href)
+    } else if href.starts_with('/') {
+        href
+    } else {
+        let dir = base_path.rfind('/').map_or("/", |i| &base_path[..=i]);
+        format!("{}{}", dir, href)
+    };
+    let (path, query) = path.split_once('?').map_or((path.as_str(), None), |(p, q)| (p, Some(q)));
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in path.split('/').skip(1) {
+        match segment {
+            "." => {}
+            ".." => { segments.pop(); }
+            s => segments.push(s),
+        }
+    }
+    let query = query.map_or(String::new(), |q| format!("?{}", q));
+    Some(format!("{}://{}/{}{}", scheme, authority, segments.join("/"), query))
+}
+
+/// Links on a page: attribute values resolved against its URL, and the absolute URLs recon finds
+fn links(url: &str, raw: &[u8]) -> Vec<String> {
+    let (head, body) = http::split_raw(raw);
+    let body = http::decode_body(&String::from_utf8_lossy(head), body);
+    let text = String::from_utf8_lossy(&body);
+    let mut found: Vec<String> = hrefs().captures_iter(&text).filter_map(|c| resolve(url, &c[1])).collect();
+    // Just enough of an entry for the extractor
+    let page = HttpLog { request_raw: format!("GET {} HTTP/1.1\r\n\r\n", url).into_bytes().into(), response_raw: raw.to_vec().into(), ..Default::default() };
+    found.extend(recon::links(&page));
+    found
+}
+
+/// TLS to the proxy's interception, which presents certificates from belch's CA
+pub fn client_config(ca_der: &[u8]) -> Arc<ClientConfig> {
+    let mut roots = RootCertStore { roots: webpki_roots::TLS_SERVER_ROOTS.to_vec() };
+    // Passthrough hosts are tunneled untouched and show their real certificates
+    let _ = roots.add(CertificateDer::from(ca_der.to_vec()));
+    Arc::new(ClientConfig::builder().with_root_certificates(roots).with_no_client_auth())
+}
+
+/// The response to `GET url` with `headers` (after the request line), sent through the proxy
+/// listening on `listen`
+pub async fn through_proxy(url: &str, headers: &str, listen: &str, tls_config: &Arc<ClientConfig>) -> Result<Vec<u8>, (Failure, String)> {
+    let (tls, host, port, path) = target(url).ok_or((Failure::Other, format!("not an http(s) URL: {}", url)))?;
+    let (listen_host, listen_port) = listen.rsplit_once(':').and_then(|(h, p)| Some((h, p.parse().ok()?)))
+        .ok_or((Failure::Other, format!("bad listen address {}", listen)))?;
+    let listen_host = if listen_host == "0.0.0.0" { "127.0.0.1" } else { listen_host };
+    if !tls {
+        // Absolute-form, as a browser configured with the proxy would send it
+        let request = format!("GET {} HTTP/1.1\r\n{}", url.split('#').next().unwrap_or(url), headers);
+        return send::send(listen_host, listen_port, false, request.as_bytes(), None).await;
+    }
+    let own = Upstream::parse(&format!("http://{}:{}", listen_host, listen_port)).map_err(|e| (Failure::Other, e))?;
+    let stream = upstream::connect(Some(&own), &host, port).await
+        .map_err(|e| (Failure::from_io(&e), format!("CONNECT through the proxy failed: {}", e)))?;
+    let name = ServerName::try_from(host.clone()).map_err(|e| (Failure::Other, format!("Invalid server name: {}", e)))?;
+    let mut stream = TlsConnector::from(tls_config.clone()).connect(name, stream).await
+        .map_err(|e| (Failure::Tls, format!("TLS through the proxy failed: {}", e)))?;
+    let _ = stream.write_all(format!("GET {} HTTP/1.1\r\n{}", path, headers).as_bytes()).await;
+    send::read_response(&mut stream).await
+}
+
+/// `Host` header value for a target: the port only when it isn't the scheme's default
+pub fn host_header(host: &str, port: u16) -> String {
+    let host = http::url_host(host);
+    if port == 80 || port == 443 { host } else { format!("{}:{}", host, port) }
+}
+
+async fn fetch(url: &str, listen: &str, tls_config: &Arc<ClientConfig>) -> Result<Vec<u8>, (Failure, String)> {
+    let (_, host, port, _) = target(url).ok_or((Failure::Other, format!("not an http(s) URL: {}", url)))?;
+    let headers = format!("Host: {}\r\nUser-Agent: belch spider\r\n{}: {}\r\nAccept: text/html,*/*\r\nConnection: close\r\n\r\n", host_header(&host, port), Origin::HEADER, Origin::Spider.mark());
+    through_proxy(url, &headers, listen, tls_config).await
+}
+
+/// `:spider [DEPTH|stop]`: follow in-scope links from the captured pages, DEPTH hops deep
+pub fn command(guard: &mut App, app: Arc<Mutex<App>>, ca_der: Vec<u8>, args: &[&str]) {
+    let depth = match args {
+        [] => DEFAULT_DEPTH,
+        ["stop"] => {
+            if let Some(stop) = guard.spider.take() {
+                stop.store(true, Ordering::Relaxed);
+                guard.set_status("Spider stopping");
+            }
+            return;
+        }
+        [n] => match n.parse() {
+            Ok(n) if n > 0 => n,
+            _ => return guard.set_status("usage: :spider [DEPTH|stop]"),
+        },
+        _ => return guard.set_status("usage: :spider [DEPTH|stop]"),
+    };
+    if guard.spider.is_some() {
+        return guard.set_status("The spider is already running (:spider stop)");
+    }
+    // An empty scope list covers everything; keep to the hosts already visited then
+    let seen: HashSet<String> = guard.logs.iter().map(|l| l.host.to_lowercase()).collect();
+    let scoped = !guard.scope.hosts.is_empty();
+    let in_scope = move |scope: &Scope, host: &str| scope.contains(host) && (scoped || seen.contains(host));
+    let mut visited: HashSet<String> = guard.logs.iter().map(|l| l.full_url().split('#').next().unwrap_or("").to_string()).collect();
+    // Parsed on the task: the lock is only held to pick the pages
+    let pages: Vec<(String, Stored)> = guard.logs.iter().filter(|l| {
+        let html = http::header_value(&l.response_head(), "content-type").is_some_and(|t| t.contains("html"));
+        html && guard.in_scope(&l.listener, &l.host)
+    }).map(|l| (l.full_url(), l.response_raw.clone())).collect();
+    let stop = Arc::new(AtomicBool::new(false));
+    guard.spider = Some(stop.clone());
+    let listen = guard.listen.clone();
+    guard.set_status(format!("Spider: reading {} captured page(s)", pages.len()));
+    tokio::spawn(async move {
+        let found = tokio::task::spawn_blocking(move || {
+            pages.iter().flat_map(|(url, raw)| links(url, &raw.bytes())).collect::<Vec<_>>()
+        }).await.unwrap_or_default();
+        let mut queue = VecDeque::new();
+        {
+            let mut guard = app.lock().unwrap();
+            for url in found {
+                let host = target(&url).map(|t| t.1).unwrap_or_default();
+                if followable(&url) && in_scope(&guard.scope, &host) && visited.insert(url.clone()) {
+                    queue.push_back((url, 1));
+                }
+            }
+            if queue.is_empty() {
+                if guard.spider.as_ref().is_some_and(|s| Arc::ptr_eq(s, &stop)) {
+                    guard.spider = None;
+                }
+                return guard.set_status("No unvisited in-scope links in the captured pages");
+            }
+            guard.set_status(format!("Spider: {} link(s) queued, depth {}", queue.len(), depth));
+        }
+        let config = client_config(&ca_der);
+        let mut fetched = 0;
+        while let Some((url, hops)) = queue.pop_front() {
+            if stop.load(Ordering::Relaxed) || fetched >= MAX_PAGES {
+                break;
+            }
+            let permit = throttle::acquire(&target(&url).map(|t| t.1).unwrap_or_default()).await;
+            let fetched_page = fetch(&url, &listen, &config).await;
+            drop(permit);
+            let Ok(raw) = fetched_page else { continue };
+            fetched += 1;
+            if hops < depth {
+                let links = links(&url, &raw);
+                let guard = app.lock().unwrap();
+                for link in links {
+                    let host = target(&link).map(|t| t.1).unwrap_or_default();
+                    if followable(&link) && in_scope(&guard.scope, &host) && visited.insert(link.clone()) {
+                        queue.push_back((link, hops + 1));
+                    }
+                }
+            }
+            app.lock().unwrap().set_status(format!("Spider: {} fetched, {} queued (depth {} of {})", fetched, queue.len(), hops, depth));
+            tokio::time::sleep(DELAY).await;
+        }
+        let mut guard = app.lock().unwrap();
+        // A newer run may have started after a stop
+        if guard.spider.as_ref().is_some_and(|s| Arc::ptr_eq(s, &stop)) {
+            guard.spider = None;
+        }
+        info!("spider finished: {} page(s) fetched, {} left queued", fetched, queue.len());
+        guard.set_status(format!("Spider done: {} page(s) fetched", fetched));
+    });
+}
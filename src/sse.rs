@@ -0,0 +1,150 @@
+// Server-sent events: streamed through as they arrive, shown as a list of events
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tracing::warn;
+
+use crate::app::{App, HttpLog};
+use crate::http::{self, RequestHead};
+use crate::{error_page, send, upstream};
+
+/// Least time between updates of a streaming entry
+const UPDATE_EVERY: Duration = Duration::from_millis(250);
+
+pub struct Event {
+    /// Last event ID in effect when it was dispatched
+    pub id: Option<String>,
+    /// `message` unless the stream named it
+    pub event: String,
+    pub data: String,
+}
+
+/// Requests from `EventSource` and the like, which expect a stream back
+pub fn wants(request: &[u8]) -> bool {
+    RequestHead::parse(request)
+        .and_then(|h| h.header_str("accept").map(|a| a.to_lowercase().contains("text/event-stream")))
+        .unwrap_or(false)
+}
+
+pub fn is_stream(head: &str) -> bool {
+    http::header_value(head, "content-type").is_some_and(|t| t.to_lowercase().starts_with("text/event-stream"))
+}
+
+/// Events completed so far, as the HTML spec parses them; a trailing partial event waits for its blank line
+pub fn parse(body: &str) -> Vec<Event> {
+    let mut events = Vec::new();
+    let (mut id, mut event, mut data): (Option<String>, Option<String>, Option<String>) = (None, None, None);
+    for line in body.split('\n').map(|l| l.strip_suffix('\r').unwrap_or(l)) {
+        if line.is_empty() {
+            if let Some(data) = data.take() {
+                events.push(Event { id: id.clone(), event: event.take().unwrap_or_else(|| "message".to_string()), data });
+            }
+            event = None;
+            continue;
+        }
+        if line.starts_with(':') {
+            continue;
+        }
+        let (field, value) = line.split_once(':').unwrap_or((line, ""));
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match field {
+            "event" => event = Some(value.to_string()),
+            "data" => match data.as_mut() {
+                Some(d) => {
+                    d.push('\n');
+                    d.push_str(value);
+                }
+                None => data = Some(value.to_string()),
+            },
+            "id" if !value.contains('\0') => id = Some(value.to_string()).filter(|v| !v.is_empty()),
+            _ => {}
+        }
+    }
+    events
+}
+
+/// Forward the response to `client` as it arrives, updating the entry along the way
+async fn relay_with<C, U>(mut client: C, mut upstream: U, forward: Vec<u8>, mut entry: HttpLog, app: Arc<Mutex<App>>)
+where
+    C: AsyncRead + AsyncWrite + Unpin,
+    U: AsyncRead + AsyncWrite + Unpin,
+{
+    let started = Instant::now();
+    let _ = upstream.write_all(&forward).await;
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 8192];
+    let head_len = loop {
+        if let Some(i) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break i + 4;
+        }
+        match upstream.read(&mut chunk).await {
+            Ok(0) | Err(_) => break buf.len(),
+            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+        }
+    };
+    let _ = client.write_all(&buf).await;
+    let head = String::from_utf8_lossy(&buf[..head_len]).to_string();
+    if is_stream(&head) {
+        entry.url.push_str(" [SSE]");
+    }
+    let url = entry.url.clone();
+    entry.request = String::from_utf8_lossy(&forward).replace("\r\n", "\n").into();
+    entry.response_raw = buf.into();
+    entry.duration = started.elapsed();
+    let index = {
+        let mut app = app.lock().unwrap();
+        app.push_log(entry);
+        app.logs.len() - 1
+    };
+    // No idle limit: streams may be quiet for minutes between events
+    let mut updated = Instant::now();
+    // Received since the entry was last updated
+    let mut pending = Vec::new();
+    loop {
+        let n = match upstream.read(&mut chunk).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        if client.write_all(&chunk[..n]).await.is_err() {
+            break;
+        }
+        pending.extend_from_slice(&chunk[..n]);
+        if updated.elapsed() >= UPDATE_EVERY {
+            app.lock().unwrap().extend_response(index, &url, &pending, started.elapsed(), false);
+            pending.clear();
+            updated = Instant::now();
+        }
+    }
+    app.lock().unwrap().extend_response(index, &url, &pending, started.elapsed(), true);
+    let _ = client.shutdown().await;
+}
+
+/// Send `forward` to `host:port` and stream the response back to `client`; `entry` carries the original request
+pub async fn relay<C>(mut client: C, forward: Vec<u8>, host: &str, port: u16, tls: bool, entry: HttpLog, app: Arc<Mutex<App>>)
+where
+    C: AsyncRead + AsyncWrite + Unpin,
+{
+    let via = app.lock().unwrap().routes.route(host).cloned();
+    let stream = match upstream::connect(via.as_ref(), host, port).await {
+        Ok(s) => s,
+        Err(e) => {
+            let reason = format!("Upstream connect failed: {}", e);
+            warn!("{}:{}: {}", host, port, reason);
+            let _ = client.write_all(&error_page::render(host, error_page::Failure::from_io(&e), &reason)).await;
+            return;
+        }
+    };
+    if tls {
+        match send::tls_connect(host, stream).await {
+            Ok(s) => relay_with(client, s, forward, entry, app).await,
+            Err((failure, reason)) => {
+                warn!("{}:{}: {}", host, port, reason);
+                let _ = client.write_all(&error_page::render(host, failure, &reason)).await;
+            }
+        }
+    } else {
+        relay_with(client, stream, forward, entry, app).await;
+    }
+}
@@ -0,0 +1,108 @@
+// Captured messages kept zstd-compressed in memory once they pass a size threshold; the head
+// stays as-is, so listing and sorting never decompress
+
+use bytes::{Bytes, BytesMut};
+
+/// Messages up to this size are kept as-is
+const THRESHOLD: usize = 16 * 1024;
+/// Fastest zstd level: capture must not wait on compression
+const LEVEL: i32 = 1;
+
+#[derive(Clone, Default)]
+pub struct Stored {
+    /// Everything up to and including the blank line after the head; empty when there is none
+    head: Bytes,
+    /// The rest, compressed when `compressed`
+    body: Bytes,
+    /// Size before compression
+    len: usize,
+    compressed: bool,
+}
+
+impl Stored {
+    pub fn new(raw: Bytes) -> Self {
+        let len = raw.len();
+        let end = raw.windows(4).position(|w| w == b"\r\n\r\n").map_or(0, |i| i + 4);
+        let (head, body) = (raw.slice(..end), raw.slice(end..));
+        if body.len() > THRESHOLD {
+            // Already-compressed payloads are not worth the decompression on every access
+            if let Some(packed) = zstd::bulk::compress(&body, LEVEL).ok().filter(|p| p.len() < body.len() * 9 / 10) {
+                return Stored { head, body: packed.into(), len, compressed: true };
+            }
+        }
+        Stored { head, body, len, compressed: false }
+    }
+
+    /// Add `more` to the end, left uncompressed; growing in place when nothing else shares the
+    /// buffer, so a stream appended to bit by bit is copied only as it outgrows it
+    pub fn append(&mut self, more: &[u8]) {
+        let body = match self.compressed {
+            true => self.bytes().slice(self.head.len()..),
+            false => std::mem::take(&mut self.body),
+        };
+        let mut body = body.try_into_mut().unwrap_or_else(|shared| BytesMut::from(&shared[..]));
+        body.extend_from_slice(more);
+        self.body = body.freeze();
+        self.len += more.len();
+        self.compressed = false;
+    }
+
+    /// The original bytes, decompressed if needed
+    pub fn bytes(&self) -> Bytes {
+        if !self.compressed && self.head.is_empty() {
+            return self.body.clone();
+        }
+        let mut out = Vec::with_capacity(self.len);
+        out.extend_from_slice(&self.head);
+        match self.compressed {
+            true => out.extend(zstd::bulk::decompress(&self.body, self.len - self.head.len()).unwrap_or_default()),
+            false => out.extend_from_slice(&self.body),
+        }
+        out.into()
+    }
+
+    /// The head with its blank line, without touching the body; everything when there is no head
+    pub fn head(&self) -> Bytes {
+        match self.head.is_empty() {
+            true => self.bytes(),
+            false => self.head.clone(),
+        }
+    }
+
+    /// The original bytes as text
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.bytes()).into_owned()
+    }
+
+    /// Size of the original bytes
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Bytes saved by compression
+    pub fn saved(&self) -> usize {
+        self.len - self.head.len() - self.body.len()
+    }
+}
+
+impl From<Bytes> for Stored {
+    fn from(raw: Bytes) -> Self {
+        Stored::new(raw)
+    }
+}
+
+impl From<Vec<u8>> for Stored {
+    fn from(raw: Vec<u8>) -> Self {
+        Stored::new(raw.into())
+    }
+}
+
+impl From<String> for Stored {
+    fn from(text: String) -> Self {
+        Stored::new(text.into())
+    }
+}
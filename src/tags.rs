@@ -0,0 +1,117 @@
+// Rules that tag entries as they are captured
+
+use std::fs;
+
+use crate::app::HttpLog;
+use crate::config;
+use crate::http;
+
+/// Used when `tags.txt` does not exist
+const DEFAULT_RULES: &str = "api type json\nadmin url /admin\nerror status 5xx\n";
+
+#[derive(Clone, Copy)]
+enum Field {
+    Url,
+    Host,
+    Method,
+    Status,
+    /// Response content type
+    Type,
+    /// Header present in the request or response
+    Header,
+    /// Decoded response body
+    Body,
+}
+
+impl Field {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name.to_lowercase().as_str() {
+            "url" => Field::Url,
+            "host" => Field::Host,
+            "method" => Field::Method,
+            "status" => Field::Status,
+            "type" | "content-type" => Field::Type,
+            "header" => Field::Header,
+            "body" => Field::Body,
+            _ => return None,
+        })
+    }
+}
+
+pub struct Rule {
+    /// Tag given, or the saved filter's name
+    pub tag: String,
+    field: Field,
+    pattern: String,
+}
+
+impl Rule {
+    /// `TAG FIELD PATTERN`
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let mut parts = line.trim().splitn(3, char::is_whitespace);
+        let (Some(tag), Some(field), Some(pattern)) = (parts.next(), parts.next(), parts.next()) else {
+            return Err("expected `TAG FIELD PATTERN`".into());
+        };
+        let field = Field::parse(field).ok_or_else(|| format!("unknown field `{}`", field))?;
+        Ok(Rule { tag: tag.to_string(), field, pattern: pattern.trim().to_string() })
+    }
+
+    pub fn matches(&self, log: &HttpLog) -> bool {
+        let pattern = self.pattern.to_lowercase();
+        match self.field {
+            Field::Url => log.full_url().to_lowercase().contains(&pattern),
+            Field::Host => config::host_matches(std::slice::from_ref(&pattern), &log.host),
+            Field::Method => log.method().eq_ignore_ascii_case(&pattern),
+            // `5xx` matches the whole class
+            Field::Status => log.status().is_some_and(|s| match pattern.strip_suffix("xx") {
+                Some(class) => s.to_string().starts_with(class),
+                None => s.to_string() == pattern,
+            }),
+            Field::Type => http::header_value(&log.response_head(), "content-type")
+                .is_some_and(|t| t.to_lowercase().contains(&pattern)),
+            Field::Header => {
+                let raw = log.request_raw.bytes();
+                let request = String::from_utf8_lossy(http::split_raw(&raw).0).to_string();
+                http::header_value(&request, &pattern).is_some() || http::header_value(&log.response_head(), &pattern).is_some()
+            }
+            Field::Body => {
+                let body = log.response_body();
+                body.windows(self.pattern.len()).any(|w| w == self.pattern.as_bytes())
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct TagRules {
+    rules: Vec<Rule>,
+}
+
+impl TagRules {
+    /// Load `tags.txt` from the config dir: `TAG FIELD PATTERN` per line, `#` comments.
+    /// Fields are url, host, method, status (`500` or `5xx`), type, header and body.
+    pub fn load() -> Result<Self, String> {
+        let path = config::config_dir().join("tags.txt");
+        let text = fs::read_to_string(&path).unwrap_or_else(|_| DEFAULT_RULES.to_string());
+        let mut rules = Vec::new();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            rules.push(Rule::parse(line).map_err(|e| format!("{}:{}: {}", path.display(), n + 1, e))?);
+        }
+        Ok(TagRules { rules })
+    }
+
+    /// Tags of every matching rule, each once
+    pub fn apply(&self, log: &HttpLog) -> Vec<String> {
+        let mut tags: Vec<String> = Vec::new();
+        for rule in &self.rules {
+            if !tags.contains(&rule.tag) && rule.matches(log) {
+                tags.push(rule.tag.clone());
+            }
+        }
+        tags
+    }
+}
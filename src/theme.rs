@@ -0,0 +1,76 @@
+// Colours from the `[theme]` table of config.toml: the highlighted row or tab, tags and the status line
+
+use std::sync::{Mutex, OnceLock};
+
+use ratatui::style::{Color, Style};
+
+#[derive(Clone, Copy)]
+pub struct Theme {
+    pub selection_fg: Color,
+    pub selection_bg: Color,
+    pub tag: Color,
+    pub status: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme { selection_fg: Color::Black, selection_bg: Color::White, tag: Color::Yellow, status: Color::Yellow }
+    }
+}
+
+const NAMES: [(&str, Color); 17] = [
+    ("reset", Color::Reset),
+    ("black", Color::Black),
+    ("red", Color::Red),
+    ("green", Color::Green),
+    ("yellow", Color::Yellow),
+    ("blue", Color::Blue),
+    ("magenta", Color::Magenta),
+    ("cyan", Color::Cyan),
+    ("gray", Color::Gray),
+    ("dark-gray", Color::DarkGray),
+    ("light-red", Color::LightRed),
+    ("light-green", Color::LightGreen),
+    ("light-yellow", Color::LightYellow),
+    ("light-blue", Color::LightBlue),
+    ("light-magenta", Color::LightMagenta),
+    ("light-cyan", Color::LightCyan),
+    ("white", Color::White),
+];
+
+/// A colour name such as `light-blue`, or `#rrggbb`
+pub fn parse_color(s: &str) -> Result<Color, String> {
+    let name = s.trim().to_lowercase().replace('_', "-");
+    if let Some(hex) = name.strip_prefix('#').filter(|h| h.len() == 6) {
+        let n = u32::from_str_radix(hex, 16).map_err(|_| format!("`{}` is not #rrggbb", s))?;
+        return Ok(Color::Rgb((n >> 16) as u8, (n >> 8) as u8, n as u8));
+    }
+    NAMES.iter().find(|(n, _)| *n == name).map(|(_, c)| *c).ok_or_else(|| format!("unknown colour `{}`: a name like light-blue, or #rrggbb", s))
+}
+
+pub fn color_name(color: Color) -> String {
+    match color {
+        Color::Rgb(r, g, b) => format!("#{:02x}{:02x}{:02x}", r, g, b),
+        other => NAMES.iter().find(|(_, c)| *c == other).map_or("reset", |(n, _)| n).to_string(),
+    }
+}
+
+static THEME: OnceLock<Mutex<Theme>> = OnceLock::new();
+
+fn theme() -> &'static Mutex<Theme> {
+    THEME.get_or_init(Default::default)
+}
+
+pub fn set(t: Theme) {
+    *theme().lock().unwrap() = t;
+}
+
+pub fn get() -> Theme {
+    *theme().lock().unwrap()
+}
+
+/// The highlighted row of a list or the current tab
+pub fn selection() -> Style {
+    let t = get();
+    Style::default().fg(t.selection_fg).bg(t.selection_bg)
+}
@@ -0,0 +1,160 @@
+// Per-host limits on belch's own requests (fuzzer, spider, scanners, resends), so an active
+// run can't flood a target or trip its rate limiting
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use tokio::sync::Notify;
+
+/// Longest gap a rate may put between two requests
+const MAX_GAP: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Clone, Copy, Default)]
+pub struct Limits {
+    /// Requests started per second, per host
+    pub rate: Option<f64>,
+    /// Requests in flight at once, per host
+    pub concurrency: Option<usize>,
+}
+
+impl Limits {
+    /// `RATE[,CONCURRENCY]`, either part `-` for no limit, e.g. `5`, `2.5,4` or `-,2`
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (rate, concurrency) = spec.split_once(',').map_or((spec, None), |(r, c)| (r, Some(c)));
+        let rate = match rate.trim_end_matches("/s") {
+            "-" => None,
+            r => {
+                let rate = r.parse::<f64>().ok().filter(|r| r.is_finite() && *r > 0.0)
+                    .ok_or_else(|| format!("`{}` is not a number of requests per second", r))?;
+                if gap(rate).is_none_or(|g| g > MAX_GAP) {
+                    return Err(format!("`{}` is too slow: allow at least one request a day", r));
+                }
+                Some(rate)
+            }
+        };
+        let concurrency = match concurrency {
+            None | Some("-") => None,
+            Some(c) => Some(c.parse::<usize>().ok().filter(|c| *c > 0).ok_or_else(|| format!("`{}` is not a number of requests", c))?),
+        };
+        Ok(Limits { rate, concurrency })
+    }
+
+    pub fn label(&self) -> String {
+        match (self.rate, self.concurrency) {
+            (None, None) => "off".to_string(),
+            (Some(r), None) => format!("{}/s per host", r),
+            (None, Some(c)) => format!("{} at a time per host", c),
+            (Some(r), Some(c)) => format!("{}/s, {} at a time per host", r, c),
+        }
+    }
+}
+
+/// Time between request starts at `rate` per second
+fn gap(rate: f64) -> Option<Duration> {
+    Duration::try_from_secs_f64(1.0 / rate).ok()
+}
+
+#[derive(Default)]
+struct Host {
+    in_flight: usize,
+    waiting: usize,
+    /// Earliest start the rate allows for the next request
+    next: Option<Instant>,
+}
+
+#[derive(Default)]
+struct State {
+    limits: Limits,
+    hosts: HashMap<String, Host>,
+}
+
+static STATE: OnceLock<Mutex<State>> = OnceLock::new();
+static FREED: OnceLock<Notify> = OnceLock::new();
+
+fn state() -> &'static Mutex<State> {
+    STATE.get_or_init(Mutex::default)
+}
+
+fn freed() -> &'static Notify {
+    FREED.get_or_init(Notify::new)
+}
+
+pub fn set(limits: Limits) {
+    state().lock().unwrap().limits = limits;
+    // Waiters recheck against the new limits
+    freed().notify_waiters();
+}
+
+pub fn limits() -> Limits {
+    state().lock().unwrap().limits
+}
+
+/// Held while a request is in flight; dropping it lets the next one for the host go
+pub struct Permit {
+    host: String,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let mut state = state().lock().unwrap();
+        if let Some(host) = state.hosts.get_mut(&self.host) {
+            host.in_flight -= 1;
+        }
+        freed().notify_waiters();
+    }
+}
+
+/// Counts a caller as queued for its host until dropped, even if the wait is abandoned
+struct Queued<'a>(&'a str);
+
+impl Drop for Queued<'_> {
+    fn drop(&mut self) {
+        if let Some(host) = state().lock().unwrap().hosts.get_mut(self.0) {
+            host.waiting -= 1;
+        }
+    }
+}
+
+/// Wait until the host's limits allow another request
+pub async fn acquire(host: &str) -> Permit {
+    let key = host.to_lowercase();
+    state().lock().unwrap().hosts.entry(key.clone()).or_default().waiting += 1;
+    let queued = Queued(&key);
+    let wait = loop {
+        let notified = freed().notified();
+        {
+            let mut state = state().lock().unwrap();
+            let limits = state.limits;
+            let entry = state.hosts.entry(key.clone()).or_default();
+            if limits.concurrency.is_none_or(|c| entry.in_flight < c) {
+                entry.in_flight += 1;
+                let now = Instant::now();
+                // The start slot is reserved now, so concurrent callers queue up behind it
+                let start = entry.next.filter(|n| *n > now).unwrap_or(now);
+                entry.next = limits.rate.and_then(gap).and_then(|g| start.checked_add(g));
+                break start - now;
+            }
+        }
+        notified.await;
+    };
+    drop(queued);
+    let permit = Permit { host: key };
+    if !wait.is_zero() {
+        tokio::time::sleep(wait).await;
+    }
+    permit
+}
+
+/// Footer indicator while limits are set: requests in flight and queued, over all hosts
+pub fn indicator() -> Option<String> {
+    let state = state().lock().unwrap();
+    if state.limits.rate.is_none() && state.limits.concurrency.is_none() {
+        return None;
+    }
+    let busy: usize = state.hosts.values().map(|h| h.in_flight).sum();
+    let queued: usize = state.hosts.values().map(|h| h.waiting).sum();
+    let rate = state.limits.rate.map_or("-".to_string(), |r| format!("{}/s", r));
+    let conc = state.limits.concurrency.map_or("-".to_string(), |c| c.to_string());
+    Some(format!(" throttle {} ×{}: {} busy {} queued", rate, conc, busy, queued))
+}
@@ -0,0 +1,69 @@
+// Per-stage time limits, so hung peers do not hold tasks and sockets forever
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+#[derive(Clone, Copy)]
+pub struct Timeouts {
+    /// TCP connect to the server or upstream proxy, including the proxy's CONNECT or SOCKS reply
+    pub connect: Duration,
+    /// TLS handshake, with the server or with the client
+    pub tls: Duration,
+    /// From sending a request to the end of the response head, or for a client to send its request
+    pub header: Duration,
+    /// Longest silence while a response body is arriving
+    pub idle: Duration,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Timeouts {
+            connect: Duration::from_secs(10),
+            tls: Duration::from_secs(10),
+            header: Duration::from_secs(30),
+            idle: Duration::from_secs(60),
+        }
+    }
+}
+
+impl Timeouts {
+    /// Defaults overridden by `STAGE=SECS` pairs, e.g. `connect=5,idle=120`
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut t = Timeouts::default();
+        for pair in spec.split(',').filter(|p| !p.is_empty()) {
+            let (stage, secs) = pair.split_once('=').ok_or_else(|| format!("`{}` is not STAGE=SECS", pair))?;
+            let limit = secs.parse().ok().filter(|s: &f64| *s > 0.0)
+                .and_then(|s| Duration::try_from_secs_f64(s).ok())
+                .filter(|l| *l <= MAX)
+                .ok_or_else(|| format!("`{}` is not a number of seconds up to a day", secs))?;
+            let slot = match stage {
+                "connect" => &mut t.connect,
+                "tls" => &mut t.tls,
+                "header" => &mut t.header,
+                "idle" => &mut t.idle,
+                other => return Err(format!("unknown timeout `{}`: connect, tls, header, idle", other)),
+            };
+            *slot = limit;
+        }
+        Ok(t)
+    }
+}
+
+/// Longest any stage may be given
+const MAX: Duration = Duration::from_secs(24 * 60 * 60);
+
+static TIMEOUTS: OnceLock<Timeouts> = OnceLock::new();
+
+/// Set once at startup; later calls are ignored
+pub fn init(t: Timeouts) {
+    let _ = TIMEOUTS.set(t);
+}
+
+pub fn get() -> Timeouts {
+    TIMEOUTS.get().copied().unwrap_or_default()
+}
+
+/// Message naming the stage (`connect`, `tls`, `header` or `idle`) that ran out of time
+pub fn expired(stage: &str, limit: Duration) -> String {
+    format!("{} timeout after {}s (raise with --timeouts {}=SECS)", stage, limit.as_secs_f64(), stage)
+}
@@ -0,0 +1,150 @@
+// Where a request's tokens and IDs came from: earlier responses that first contained them
+
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use crate::app::{App, HttpLog};
+use crate::creds;
+use crate::http;
+
+/// How many requests back a chain is followed
+const MAX_DEPTH: usize = 4;
+
+/// Header names that usually carry a token
+const TOKEN_HEADERS: [&str; 5] = ["token", "csrf", "xsrf", "key", "session"];
+
+pub struct Dependency {
+    /// 0 for the traced request's own values, 1 for what its sources sent, and so on
+    pub depth: usize,
+    pub name: String,
+    /// `query`, `path`, `cookie`, `header`, `form` or `json`
+    pub location: &'static str,
+    pub value: String,
+    /// Earliest earlier entry whose response held the value, and where in it
+    pub source: Option<(usize, &'static str)>,
+}
+
+/// Worth tracing: IDs with digits, or long enough to be a token
+fn interesting(value: &str) -> bool {
+    (value.len() >= 3 && value.bytes().any(|b| b.is_ascii_digit())) || value.len() >= 16
+}
+
+/// Named values a request sends, once each
+fn values(log: &HttpLog) -> Vec<(String, &'static str, String)> {
+    let raw = log.request_raw.bytes();
+    let Some(head) = http::RequestHead::parse(&raw) else { return Vec::new() };
+    let mut out = Vec::new();
+    let target = http::origin_path(head.target);
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    for (n, segment) in path.split('/').filter(|s| !s.is_empty()).enumerate() {
+        out.push((format!("segment {}", n + 1), "path", http::url_decode(segment)));
+    }
+    out.extend(http::parse_query(query).into_iter().map(|(k, v)| (k, "query", v)));
+    for pair in head.header_str("cookie").unwrap_or("").split(';') {
+        if let Some((k, v)) = pair.trim().split_once('=') {
+            out.push((k.to_string(), "cookie", v.to_string()));
+        }
+    }
+    if let Some(auth) = head.header_str("authorization") {
+        // The credential after the scheme name
+        out.push(("authorization".to_string(), "header", auth.split_once(' ').map_or(auth, |(_, c)| c).to_string()));
+    }
+    let text = String::from_utf8_lossy(&raw);
+    let (head_text, body) = http::split_message(&text);
+    for line in head_text.lines().skip(1) {
+        if let Some((k, v)) = line.split_once(':') {
+            let lower = k.to_lowercase();
+            if lower != "cookie" && TOKEN_HEADERS.iter().any(|t| lower.contains(t)) {
+                out.push((k.to_string(), "header", v.trim().to_string()));
+            }
+        }
+    }
+    let ctype = head.header_str("content-type").unwrap_or("").to_lowercase();
+    if ctype.contains("x-www-form-urlencoded") {
+        out.extend(http::parse_query(body).into_iter().map(|(k, v)| (k, "form", v)));
+    } else if ctype.contains("json") {
+        if let Ok(json) = serde_json::from_str(body) {
+            let mut pairs = Vec::new();
+            creds::json_pairs(&json, &mut pairs);
+            out.extend(pairs.into_iter().map(|(k, v)| (k, "json", v)));
+        }
+    }
+    let mut seen = HashSet::new();
+    out.retain(|(_, _, v)| interesting(v) && seen.insert(v.clone()));
+    out
+}
+
+/// Where in a response `value` appears
+fn found_in(head: &str, body: &str, value: &str) -> Option<&'static str> {
+    for line in head.lines().skip(1) {
+        if line.contains(value) {
+            let name = line.split(':').next().unwrap_or("").to_lowercase();
+            return Some(match name.as_str() {
+                "set-cookie" => "Set-Cookie",
+                "location" => "Location",
+                _ => "header",
+            });
+        }
+    }
+    // JSON may escape slashes
+    (body.contains(value) || body.contains(&value.replace('/', "\\/"))).then_some("body")
+}
+
+/// The values `index` sends and, for each, the request chain that produced it
+pub fn trace(logs: &VecDeque<HttpLog>, index: usize) -> Vec<Dependency> {
+    // Decoded responses, filled in as the search reaches them
+    let mut responses: Vec<Option<(String, String)>> = vec![None; index];
+    let mut visited = HashSet::from([index]);
+    let mut out = Vec::new();
+    walk(logs, index, 0, &mut responses, &mut visited, &mut out);
+    out
+}
+
+fn walk(
+    logs: &VecDeque<HttpLog>,
+    index: usize,
+    depth: usize,
+    responses: &mut [Option<(String, String)>],
+    visited: &mut HashSet<usize>,
+    out: &mut Vec<Dependency>,
+) {
+    for (name, location, value) in values(&logs[index]) {
+        let source = (0..index).find_map(|j| {
+            let (head, body) = responses[j].get_or_insert_with(|| {
+                let log = &logs[j];
+                (log.response_head(), String::from_utf8_lossy(&log.response_body()).to_string())
+            });
+            found_in(head, body, &value).map(|w| (j, w))
+        });
+        // Deeper down only the values that lead somewhere are worth a line
+        if depth > 0 && source.is_none() {
+            continue;
+        }
+        out.push(Dependency { depth, name, location, value, source });
+        if let Some((j, _)) = source {
+            if depth + 1 < MAX_DEPTH && visited.insert(j) {
+                walk(logs, j, depth + 1, responses, visited, out);
+            }
+        }
+    }
+}
+
+/// `:trace`: trace the selected request off the UI thread, over a copy of the entries up to it
+pub fn command(guard: &mut App, app: Arc<Mutex<App>>) {
+    let Some(index) = guard.view().get(guard.selected).copied() else { return guard.set_status("No request selected") };
+    let logs: VecDeque<HttpLog> = guard.logs.range(..=index).cloned().collect();
+    let url = logs[index].url.clone();
+    guard.set_status(format!("Tracing {}", url));
+    tokio::spawn(async move {
+        let Ok(deps) = tokio::task::spawn_blocking(move || trace(&logs, index)).await else { return };
+        let mut guard = app.lock().unwrap();
+        // A clear or `:open` may have replaced the entry meanwhile
+        if guard.logs.get(index).map(|l| &l.url) != Some(&url) {
+            return;
+        }
+        let found = deps.iter().filter(|d| d.depth == 0 && d.source.is_some()).count();
+        let sent = deps.iter().filter(|d| d.depth == 0).count();
+        guard.set_status(format!("{} of {} sent value(s) came from earlier responses", found, sent));
+        guard.trace = Some((index, deps));
+    });
+}
@@ -0,0 +1,308 @@
+// Encodings, decodings and hashes for the Decoder tab, applied one after another
+
+use std::io::{Read, Write};
+
+use base64::Engine;
+use md5::{Digest, Md5};
+use sha2::Sha256;
+
+use crate::editor::TextArea;
+use crate::http;
+
+/// Gunzip stops with an error rather than inflate past this
+const MAX_GUNZIPPED: u64 = 64 << 20;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Transform {
+    UrlDecode,
+    UrlEncode,
+    Base64Decode,
+    Base64Encode,
+    HexDecode,
+    HexEncode,
+    Gunzip,
+    Gzip,
+    HtmlUnescape,
+    HtmlEscape,
+    Jwt,
+    Md5,
+    Sha1,
+    Sha256,
+}
+
+impl Transform {
+    pub const ALL: [Transform; 14] = [
+        Transform::UrlDecode, Transform::UrlEncode, Transform::Base64Decode, Transform::Base64Encode,
+        Transform::HexDecode, Transform::HexEncode, Transform::Gunzip, Transform::Gzip,
+        Transform::HtmlUnescape, Transform::HtmlEscape, Transform::Jwt, Transform::Md5,
+        Transform::Sha1, Transform::Sha256,
+    ];
+
+    /// Short name, as typed in commands
+    pub fn name(self) -> &'static str {
+        match self {
+            Transform::UrlDecode => "unurl",
+            Transform::UrlEncode => "url",
+            Transform::Base64Decode => "unb64",
+            Transform::Base64Encode => "b64",
+            Transform::HexDecode => "unhex",
+            Transform::HexEncode => "hex",
+            Transform::Gunzip => "gunzip",
+            Transform::Gzip => "gzip",
+            Transform::HtmlUnescape => "unhtml",
+            Transform::HtmlEscape => "html",
+            Transform::Jwt => "jwt",
+            Transform::Md5 => "md5",
+            Transform::Sha1 => "sha1",
+            Transform::Sha256 => "sha256",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Transform> {
+        Transform::ALL.into_iter().find(|t| t.name() == name)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Transform::UrlDecode => "URL decode",
+            Transform::UrlEncode => "URL encode",
+            Transform::Base64Decode => "Base64 decode",
+            Transform::Base64Encode => "Base64 encode",
+            Transform::HexDecode => "Hex decode",
+            Transform::HexEncode => "Hex encode",
+            Transform::Gunzip => "Gunzip",
+            Transform::Gzip => "Gzip",
+            Transform::HtmlUnescape => "HTML entities decode",
+            Transform::HtmlEscape => "HTML entities encode",
+            Transform::Jwt => "JWT decode",
+            Transform::Md5 => "MD5",
+            Transform::Sha1 => "SHA-1",
+            Transform::Sha256 => "SHA-256",
+        }
+    }
+
+    /// Key that adds it in the Decoder tab: lowercase decodes, uppercase encodes
+    pub fn key(self) -> char {
+        match self {
+            Transform::UrlDecode => 'u',
+            Transform::UrlEncode => 'U',
+            Transform::Base64Decode => 'b',
+            Transform::Base64Encode => 'B',
+            Transform::HexDecode => 'x',
+            Transform::HexEncode => 'X',
+            Transform::Gunzip => 'z',
+            Transform::Gzip => 'Z',
+            Transform::HtmlUnescape => 'h',
+            Transform::HtmlEscape => 'H',
+            Transform::Jwt => 'j',
+            Transform::Md5 => '5',
+            Transform::Sha1 => '1',
+            Transform::Sha256 => '2',
+        }
+    }
+
+    pub fn apply(self, input: &[u8]) -> Result<Vec<u8>, String> {
+        let text = || String::from_utf8_lossy(input).to_string();
+        let hex = |bytes: &[u8]| bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>().into_bytes();
+        match self {
+            Transform::UrlDecode => Ok(http::url_decode(&text()).into_bytes()),
+            Transform::UrlEncode => Ok(http::url_encode(&text()).into_bytes()),
+            Transform::Base64Decode => base64_decode(&text()),
+            Transform::Base64Encode => Ok(base64::engine::general_purpose::STANDARD.encode(input).into_bytes()),
+            Transform::HexDecode => hex_decode(&text()),
+            Transform::HexEncode => Ok(hex(input)),
+            Transform::Gunzip => {
+                let mut out = Vec::new();
+                flate2::read::MultiGzDecoder::new(input).take(MAX_GUNZIPPED + 1).read_to_end(&mut out).map_err(|e| format!("not gzip: {}", e))?;
+                match out.len() as u64 > MAX_GUNZIPPED {
+                    true => Err(format!("inflates past {} MiB", MAX_GUNZIPPED >> 20)),
+                    false => Ok(out),
+                }
+            }
+            Transform::Gzip => {
+                let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(input).and_then(|_| encoder.finish()).map_err(|e| e.to_string())
+            }
+            Transform::HtmlUnescape => Ok(html_unescape(&text()).into_bytes()),
+            Transform::HtmlEscape => Ok(text().replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+                .replace('"', "&quot;").replace('\'', "&#39;").into_bytes()),
+            Transform::Jwt => jwt(&text()),
+            Transform::Md5 => Ok(hex(&Md5::digest(input))),
+            Transform::Sha1 => Ok(hex(ring::digest::digest(&ring::digest::SHA1_FOR_LEGACY_USE_ONLY, input).as_ref())),
+            Transform::Sha256 => Ok(hex(&Sha256::digest(input))),
+        }
+    }
+}
+
+/// Standard or URL-safe alphabet, padded or not, whitespace ignored
+fn base64_decode(text: &str) -> Result<Vec<u8>, String> {
+    let cleaned: String = text.chars().filter(|c| !c.is_whitespace() && *c != '=')
+        .map(|c| match c {
+            '-' => '+',
+            '_' => '/',
+            c => c,
+        })
+        .collect();
+    base64::engine::general_purpose::STANDARD_NO_PAD.decode(cleaned).map_err(|e| format!("not base64: {}", e))
+}
+
+/// Hex digits, with any `0x` prefix, whitespace, `:` or `-` separators ignored
+fn hex_decode(text: &str) -> Result<Vec<u8>, String> {
+    let text = text.trim();
+    let digits: Vec<u8> = text.strip_prefix("0x").unwrap_or(text).bytes()
+        .filter(|b| !b.is_ascii_whitespace() && !b":-".contains(b))
+        .collect();
+    if digits.len() % 2 == 1 || !digits.iter().all(u8::is_ascii_hexdigit) {
+        return Err("not hex".into());
+    }
+    Ok(digits.chunks(2).map(|pair| u8::from_str_radix(std::str::from_utf8(pair).unwrap_or("00"), 16).unwrap_or(0)).collect())
+}
+
+/// Named entities for markup characters and spaces, plus every numeric one
+pub fn html_unescape(text: &str) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest.find(';').filter(|end| *end <= 10).map(|end| &rest[1..end]);
+        let decoded = entity.and_then(|e| match e {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            _ => {
+                let code = match e.strip_prefix("#x").or_else(|| e.strip_prefix("#X")) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => e.strip_prefix('#')?.parse().ok()?,
+                };
+                char::from_u32(code)
+            }
+        });
+        match (decoded, entity) {
+            (Some(c), Some(e)) => {
+                out.push(c);
+                rest = &rest[e.len() + 2..];
+            }
+            _ => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Header and claims of a JWT, pretty-printed; the signature is not checked
+fn jwt(text: &str) -> Result<Vec<u8>, String> {
+    let parts: Vec<&str> = text.trim().split('.').collect();
+    if parts.len() < 2 {
+        return Err("not a JWT: expected header.payload.signature".into());
+    }
+    let mut out = String::new();
+    for (label, part) in [("Header", parts[0]), ("Payload", parts[1])] {
+        let json: serde_json::Value = base64_decode(part).ok().and_then(|b| serde_json::from_slice(&b).ok())
+            .ok_or(format!("not a JWT: the {} is not base64url JSON", label.to_lowercase()))?;
+        out.push_str(&format!("{}:\n{}\n", label, serde_json::to_string_pretty(&json).unwrap_or_default()));
+    }
+    Ok(out.into_bytes())
+}
+
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack.get(from..)?.windows(needle.len()).position(|w| w == needle).map(|i| i + from)
+}
+
+/// Start of the innermost `<%name(` tag, with the name's end
+fn last_tag(data: &[u8]) -> Option<(usize, usize)> {
+    let mut found = None;
+    let mut from = 0;
+    while let Some(start) = find(data, b"<%", from) {
+        let name_end = start + 2 + data[start + 2..].iter().take_while(|b| b.is_ascii_alphanumeric()).count();
+        if name_end > start + 2 && data.get(name_end) == Some(&b'(') {
+            found = Some((start, name_end));
+        }
+        from = start + 2;
+    }
+    found
+}
+
+/// `<%name(…)%>` tags replaced by that transform of what they enclose, innermost first,
+/// so `<%b64(<%md5(x)%>)%>` works
+pub fn expand(input: &[u8]) -> Result<Vec<u8>, String> {
+    let mut data = input.to_vec();
+    while let Some((start, name_end)) = last_tag(&data) {
+        let name = String::from_utf8_lossy(&data[start + 2..name_end]).to_string();
+        let transform = Transform::parse(&name).ok_or(format!("unknown tag <%{}(…)%>", name))?;
+        let close = find(&data, b")%>", name_end + 1).ok_or(format!("<%{}( is never closed with )%>", name))?;
+        let out = transform.apply(&data[name_end + 1..close]).map_err(|e| format!("<%{}(…)%>: {}", name, e))?;
+        data.splice(start..close + 3, out);
+    }
+    Ok(data)
+}
+
+/// Text as is when it is printable UTF-8, a hex dump otherwise
+pub fn display(bytes: &[u8]) -> String {
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        if !text.chars().any(|c| c.is_control() && !"\n\r\t".contains(c)) {
+            return text.to_string();
+        }
+    }
+    bytes.chunks(16).enumerate().map(|(i, row)| {
+        let hex: Vec<String> = row.iter().map(|b| format!("{:02x}", b)).collect();
+        let ascii: String = row.iter().map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' }).collect();
+        format!("{:08x}  {:<48} {}", i * 16, hex.join(" "), ascii)
+    }).collect::<Vec<_>>().join("\n")
+}
+
+/// Each stage's output, or why it failed
+type Outputs = Vec<Result<Vec<u8>, String>>;
+
+/// State of the Decoder tab: the input and the transforms applied to it in turn
+pub struct Pipeline {
+    pub input: TextArea,
+    pub stages: Vec<Transform>,
+    /// Keys go to the input instead of adding stages
+    pub editing: bool,
+    pub scroll: usize,
+    /// The input and stages `run` last saw, and what they gave
+    last: Option<((String, Vec<Transform>), Outputs)>,
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Pipeline { input: TextArea::new(""), stages: Vec::new(), editing: false, scroll: 0, last: None }
+    }
+}
+
+impl Pipeline {
+    /// Output of each stage in turn, up to and including the first that fails; worked out again
+    /// only when the input or the stages change
+    pub fn run(&mut self) -> &[Result<Vec<u8>, String>] {
+        let key = (self.input.text("\n"), self.stages.clone());
+        if self.last.as_ref().map(|(k, _)| k) != Some(&key) {
+            let out = Self::apply(key.0.as_bytes(), &key.1);
+            self.last = Some((key, out));
+        }
+        &self.last.as_ref().unwrap().1
+    }
+
+    fn apply(input: &[u8], stages: &[Transform]) -> Outputs {
+        let mut data = input.to_vec();
+        let mut out = Vec::new();
+        for stage in stages {
+            let result = stage.apply(&data);
+            let failed = result.is_err();
+            if let Ok(next) = &result {
+                data = next.clone();
+            }
+            out.push(result);
+            if failed {
+                break;
+            }
+        }
+        out
+    }
+}
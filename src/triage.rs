@@ -0,0 +1,248 @@
+// Findings triage: each entry can carry a note and an investigation status, saved with the
+// project. The sheet goes out as CSV to be worked on in a spreadsheet and comes back in, matched
+// on entry ID and URL, while the evidence itself stays in belch.
+
+use std::fs;
+use std::path::Path;
+
+use crate::app::{App, HttpLog};
+use crate::export;
+use crate::redact;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Open,
+    Investigating,
+    Confirmed,
+    FalsePositive,
+    Resolved,
+}
+
+impl Status {
+    pub const ALL: [Status; 5] = [Status::Open, Status::Investigating, Status::Confirmed, Status::FalsePositive, Status::Resolved];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Status::Open => "open",
+            Status::Investigating => "investigating",
+            Status::Confirmed => "confirmed",
+            Status::FalsePositive => "false-positive",
+            Status::Resolved => "resolved",
+        }
+    }
+
+    /// A name, case and separators ignored so spreadsheet edits like `False positive` still read
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim().to_lowercase().replace([' ', '_'], "-");
+        Status::ALL.into_iter().find(|t| t.name() == s)
+    }
+}
+
+const HEADER: [&str; 5] = ["id", "url", "tags", "note", "status"];
+
+/// First characters that make a spreadsheet read a cell as a formula
+const FORMULA: [char; 6] = ['=', '+', '-', '@', '\t', '\r'];
+
+/// A CSV field, quoted when needed; cells a spreadsheet would run as a formula get a leading `'`
+fn field(text: &str) -> String {
+    let text = match text.starts_with(FORMULA) {
+        true => format!("'{}", text),
+        false => text.to_string(),
+    };
+    match text.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", text.replace('"', "\"\"")),
+        false => text,
+    }
+}
+
+/// `field` undone: the `'` belch put before a formula-like cell dropped
+fn unfield(text: &str) -> String {
+    match text.strip_prefix('\'').filter(|t| t.starts_with(FORMULA)) {
+        Some(t) => t.to_string(),
+        None => text.to_string(),
+    }
+}
+
+/// Rows of RFC 4180 CSV; quoted fields may hold commas, quotes and line breaks
+fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let (mut row, mut cell, mut quoted) = (Vec::new(), String::new(), false);
+    let mut chars = text.trim_start_matches('\u{feff}').chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                cell.push('"');
+            }
+            ('"', true) => quoted = false,
+            ('"', false) if cell.is_empty() => quoted = true,
+            (',', false) => row.push(std::mem::take(&mut cell)),
+            ('\r', false) => {}
+            ('\n', false) => {
+                row.push(std::mem::take(&mut cell));
+                rows.push(std::mem::take(&mut row));
+            }
+            (c, _) => cell.push(c),
+        }
+    }
+    if !cell.is_empty() || !row.is_empty() {
+        row.push(cell);
+        rows.push(row);
+    }
+    rows.retain(|r| r.iter().any(|c| !c.is_empty()));
+    rows
+}
+
+/// The sheet for entries `indices`: ID (position in capture order, from 1), URL, tags, note and status
+pub fn csv(app: &App, indices: &[usize]) -> String {
+    let mut out = HEADER.join(",") + "\r\n";
+    for i in indices {
+        let log = redact::outgoing(app, &app.logs[*i]);
+        let cells = [(i + 1).to_string(), log.full_url(), log.tags.join(" "), log.note.clone(), log.triage.map_or("", Status::name).to_string()];
+        out.push_str(&cells.iter().map(|c| field(c)).collect::<Vec<_>>().join(","));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+/// Apply an edited sheet: notes and statuses replace belch's, tags are added. Rows whose URL no
+/// longer matches their ID's entry are skipped. Every row is checked before any is applied, so a
+/// bad status changes nothing. Returns the entries changed and the rows skipped.
+pub fn import(app: &mut App, text: &str) -> Result<(usize, usize), String> {
+    let mut rows = parse_csv(text).into_iter();
+    let header: Vec<String> = rows.next().ok_or("The file is empty")?.iter().map(|h| h.trim().to_lowercase()).collect();
+    let column = |name: &str| header.iter().position(|h| h == name);
+    let (Some(id), Some(url)) = (column("id"), column("url")) else {
+        return Err(format!("Expected a header row with {}", HEADER.join(",")));
+    };
+    let (tags, note, status) = (column("tags"), column("note"), column("status"));
+    let mut updates = Vec::new();
+    let mut skipped = 0;
+    for row in rows {
+        let cell = |i: Option<usize>| i.and_then(|i| row.get(i)).map(|c| unfield(c.trim()));
+        // Exported URLs went through redaction, so compare with the redacted one
+        let index = cell(Some(id)).and_then(|n| n.parse::<usize>().ok()).filter(|n| *n > 0 && *n <= app.logs.len()).map(|n| n - 1)
+            .filter(|i| cell(Some(url)).is_some_and(|u| u == redact::outgoing(app, &app.logs[*i]).full_url()));
+        let Some(index) = index else {
+            skipped += 1;
+            continue;
+        };
+        // None leaves the status as it is, Some(None) clears it
+        let triage = match cell(status).as_deref() {
+            Some("") => Some(None),
+            Some(name) => match Status::parse(name) {
+                Some(s) => Some(Some(s)),
+                None => return Err(format!("Unknown status `{}` for ID {}: {}", name, index + 1, Status::ALL.map(Status::name).join(", "))),
+            },
+            None => None,
+        };
+        updates.push((index, cell(note), triage, cell(tags).unwrap_or_default()));
+    }
+    let mut changed = 0;
+    for (index, note, triage, tags) in updates {
+        let log = &mut app.logs[index];
+        let before = (log.note.clone(), log.triage, log.tags.len());
+        if let Some(text) = note {
+            log.note = text;
+        }
+        if let Some(triage) = triage {
+            log.triage = triage;
+        }
+        for tag in tags.split_whitespace() {
+            if !log.tags.iter().any(|t| t == tag) {
+                log.tags.push(tag.to_string());
+            }
+        }
+        if before != (log.note.clone(), log.triage, log.tags.len()) {
+            changed += 1;
+        }
+    }
+    Ok((changed, skipped))
+}
+
+/// Detail pane line for an entry with a status or note
+pub fn label(log: &HttpLog) -> Option<String> {
+    // Notes edited in a spreadsheet may span lines
+    let note = log.note.lines().map(str::trim).collect::<Vec<_>>().join(" ");
+    match (log.triage, note.is_empty()) {
+        (None, true) => None,
+        (Some(s), true) => Some(format!("Triage: {}", s.name())),
+        (None, false) => Some(format!("Note: {}", note)),
+        (Some(s), false) => Some(format!("Triage: {}: {}", s.name(), note)),
+    }
+}
+
+/// `:note TEXT` notes the selected entry; `:note -` removes it
+pub fn note(app: &mut App, text: &str) {
+    let Some(index) = app.view().get(app.selected).copied() else { return app.set_status("No entry selected") };
+    match text.trim() {
+        "" => match app.logs[index].note.is_empty() {
+            true => app.set_status("usage: :note TEXT | :note - (removes it)"),
+            false => app.set_status(format!("Note: {}", app.logs[index].note)),
+        },
+        "-" => {
+            app.logs[index].note.clear();
+            app.set_status("Note removed");
+        }
+        text => {
+            app.logs[index].note = text.to_string();
+            app.set_status("Noted (:triage export writes the sheet)");
+        }
+    }
+}
+
+/// `:triage STATUS|none` sets the selected entry's status, `:triage export [TERMS]` writes the
+/// sheet, `:triage import FILE` reads it back and `:triage` counts the statuses
+pub fn command(app: &mut App, args: &[&str]) {
+    let usage = format!("usage: :triage {}|none | export [{}] | import FILE", Status::ALL.map(Status::name).join("|"), export::Filter::USAGE);
+    match args {
+        [] => {
+            let counts: Vec<String> = Status::ALL.iter()
+                .map(|s| (s, app.logs.iter().filter(|l| l.triage == Some(*s)).count()))
+                .filter(|(_, n)| *n > 0)
+                .map(|(s, n)| format!("{} {}", n, s.name()))
+                .collect();
+            match counts.is_empty() {
+                true => app.set_status(usage),
+                false => app.set_status(format!("Triage: {}", counts.join(", "))),
+            }
+        }
+        ["export", terms @ ..] => {
+            // Without terms, entries with a status or note
+            let filter = export::Filter::parse(&terms.join(" "));
+            let result = filter.and_then(|f| f.select(app)).and_then(|mut selected| {
+                if terms.is_empty() {
+                    selected.retain(|i| app.logs[*i].triage.is_some() || !app.logs[*i].note.is_empty());
+                }
+                let data = csv(app, &selected);
+                export::write_stamped(Path::new("."), "belch-triage", "csv", data).map(|path| (path, selected.len())).map_err(|e| e.to_string())
+            });
+            match result {
+                Ok((path, n)) => app.set_status(format!("Wrote {} row(s) to {}", n, path.display())),
+                Err(e) => app.set_status(format!("Triage export failed: {}", e)),
+            }
+        }
+        ["import", file] => {
+            let result = fs::read_to_string(file).map_err(|e| format!("{}: {}", file, e)).and_then(|text| import(app, &text));
+            match result {
+                Ok((changed, 0)) => app.set_status(format!("Updated {} entries from {}", changed, file)),
+                Ok((changed, skipped)) => app.set_status(format!("Updated {} entries; {} row(s) skipped, ID and URL not matching", changed, skipped)),
+                Err(e) => app.set_status(format!("Triage import failed: {}", e)),
+            }
+        }
+        ["none"] | ["-"] => {
+            let Some(index) = app.view().get(app.selected).copied() else { return app.set_status("No entry selected") };
+            app.logs[index].triage = None;
+            app.set_status("Triage status cleared");
+        }
+        [name] => match (Status::parse(name), app.view().get(app.selected).copied()) {
+            (Some(status), Some(index)) => {
+                app.logs[index].triage = Some(status);
+                app.set_status(format!("Marked {}", status.name()));
+            }
+            (Some(_), None) => app.set_status("No entry selected"),
+            (None, _) => app.set_status(usage),
+        },
+        _ => app.set_status(usage),
+    }
+}
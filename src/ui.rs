@@ -0,0 +1,2806 @@
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crossterm::event::{self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use crossterm::execute;
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Cell, Clear, Paragraph, Row, Sparkline, Table, TableState, Tabs, Wrap},
+    Frame, Terminal,
+};
+
+use tracing::{info, Level};
+
+use crate::app::{App, DecoderView, DeviceSetup, MAX_SPLIT, MIN_SPLIT, Origin, PassthroughEditor, PayloadsView, ReconView, Tab};
+use crate::ca::CertAuthority;
+use crate::authz::AuthMatrix;
+use crate::fuzzer::FuzzRun;
+use crate::repeat::RepeatRun;
+use crate::repeater::Repeater;
+use crate::selection::{self, Selection};
+use crate::smuggle::{Outcome, SmuggleRun};
+use crate::fuzzy::Finder;
+use crate::palette::{Palette, Target};
+use crate::intercept::{Field, HeaderTable, Paused};
+use crate::cookies::CookieJar;
+use crate::diagnose::{Diagnosis, Kind};
+use crate::diff::{Change, DiffView};
+use crate::filters::{self, Item};
+use crate::issues::Severity;
+use crate::trace::{self, Dependency};
+use crate::editor::{External, TextArea};
+use crate::send_to::{self, Action, Menu};
+use crate::transform::{self, Pipeline, Transform};
+use crate::{authz, certs, compare, cors, detail, diagnose, downgrade, export, faults, forms, fuzzer, grpc, highlight, hosts, http, jsonpath, keys, macros, miner, packed, payloads, preview, random, recon, repeat, repeater, saml, serialized, sitemap, smuggle, spider, sse, theme, throttle, triage, vhost, websocket};
+use crate::onboard;
+
+type Backend = CrosstermBackend<std::io::Stdout>;
+
+/// Where the last frame put things, for mouse hit-testing
+#[derive(Default)]
+struct Hits {
+    tab_row: u16,
+    /// Each tab with the columns its title spans
+    tabs: Vec<(Tab, u16, u16)>,
+    /// Everything between the tab bar and the footer
+    body: Rect,
+    /// The current tab's table, and the position of its first visible row
+    list: Rect,
+    list_offset: usize,
+    detail: Rect,
+    /// The Requests sidebar, its first row the first item
+    sidebar: Rect,
+    /// The list/detail divider is being dragged
+    dragging: bool,
+    /// Text of every detail pane line and the pane's horizontal scroll, while selecting
+    detail_lines: Vec<String>,
+    detail_hscroll: usize,
+    /// A selection is being dragged out in the detail pane
+    selecting: bool,
+}
+
+pub fn run_app(
+    terminal: &mut Terminal<Backend>,
+    app: Arc<Mutex<App>>,
+    ca: Arc<CertAuthority>,
+) -> std::io::Result<()> {
+    let mut hits = Hits::default();
+    loop {
+        let external = app.lock().unwrap().external_edit.take();
+        if let Some(target) = external {
+            run_external_edit(terminal, &app, target);
+        }
+        terminal.draw(|f| {
+            let mut guard = app.lock().unwrap();
+            let size = f.size();
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(1), Constraint::Min(0), Constraint::Length(1)])
+                .split(size);
+
+            hits.tab_row = chunks[0].y;
+            hits.tabs = draw_tabs(f, &guard, chunks[0]);
+            hits.body = chunks[1];
+            hits.list = Rect::default();
+            hits.detail = Rect::default();
+            hits.sidebar = Rect::default();
+            match guard.tab {
+                Tab::Requests => {
+                    let view = guard.view();
+                    let unseen = guard.follow(&view);
+                    let selected = view.get(guard.selected).copied();
+                    detail::update(&mut guard, selected);
+                    let max_scroll = draw_requests(f, &guard, &view, chunks[1], &mut hits, unseen);
+                    guard.detail_scroll = guard.detail_scroll.min(max_scroll);
+                }
+                Tab::Hosts => {
+                    hits.list_offset = draw_hosts(f, &guard, chunks[1]);
+                    hits.list = chunks[1];
+                }
+                Tab::Sitemap => {
+                    sitemap::refresh(&mut guard);
+                    hits.list_offset = draw_sitemap(f, &guard, chunks[1]);
+                    hits.list = chunks[1];
+                }
+                Tab::Credentials => {
+                    draw_credentials(f, &guard, chunks[1]);
+                    (hits.list, hits.list_offset) = (chunks[1], 0);
+                }
+                Tab::Issues => {
+                    hits.list_offset = draw_issues(f, &guard, chunks[1]);
+                    hits.list = chunks[1];
+                }
+                Tab::Events => draw_events(f, &guard, chunks[1]),
+                Tab::Decoder => draw_pipeline(f, &mut guard.pipeline, chunks[1]),
+            }
+
+            let throttled = [throttle::indicator(), faults::indicator(), guard.no_cache.then(|| " no-cache".to_string()), guard.mirror.as_ref().map(|m| format!(" mirror {}", m.label()))].into_iter().flatten().reduce(|a, b| a + &b);
+            let unsafe_mode = downgrade::indicator();
+            let footer = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([
+                    Constraint::Min(0),
+                    Constraint::Length(60),
+                    Constraint::Length(36),
+                    Constraint::Length(throttled.as_ref().map_or(0, |t| t.chars().count() as u16 + 1)),
+                    Constraint::Length(unsafe_mode.as_ref().map_or(0, |t| t.chars().count() as u16)),
+                ])
+                .split(chunks[2]);
+            let help = match &guard.status {
+                _ if guard.command.is_some() => {
+                    Paragraph::new(format!(":{}_", guard.command.as_deref().unwrap_or("")))
+                }
+                Some((msg, at)) if at.elapsed() < Duration::from_secs(5) => {
+                    Paragraph::new(msg.as_str()).style(Style::default().fg(theme::get().status))
+                }
+                _ => Paragraph::new("↑↓: Navigate   G: Newest   PgUp/PgDn: Scroll detail   [/]: Resize   z: Zoom detail   v: HTML preview   V: Select text   Tab: Switch tab   o/O/:sort: Sort/reverse   i: Identical responses   c: Same connection   e: Expand redirects   m: Mark as evidence   b: Filter sidebar   /: Find   s: Send to…   R: Repeater   M: Mine params   X: CORS probe   H: Host probe   U: URLs/domains   K: Cert decoder   w: Save body   E: Body in $EDITOR   P: Passthrough list   p: Toggle host passthrough   S: Toggle host scope   C: CA   D: Device setup   Q: Quit")
+                    .style(Style::default().fg(Color::DarkGray)),
+            };
+            f.render_widget(help, footer[0]);
+            // Requests/second over the last minute; the label shows the last full second
+            let rate = guard.request_rate(60);
+            f.render_widget(
+                Sparkline::default().data(&rate).style(Style::default().fg(Color::Green)),
+                footer[1],
+            );
+            f.render_widget(
+                Paragraph::new(format!(
+                    " {:>3} req/s {:>3} conn {:>7} saved",
+                    rate[58], guard.active_connections, human_size(guard.stored_saved),
+                ))
+                    .style(Style::default().fg(Color::DarkGray)),
+                footer[2],
+            );
+            if let Some(throttled) = throttled {
+                f.render_widget(Paragraph::new(throttled).style(Style::default().fg(Color::Magenta)), footer[3]);
+            }
+            if let Some(warning) = unsafe_mode {
+                f.render_widget(Paragraph::new(warning).style(Style::default().fg(Color::White).bg(Color::Red).add_modifier(Modifier::BOLD)), footer[4]);
+            }
+
+            if let Some(editor) = &guard.passthrough_editor {
+                draw_passthrough_editor(f, &guard, editor, size);
+            }
+            if guard.show_ca {
+                draw_ca_info(f, &ca, size);
+            }
+            if let Some(view) = &guard.recon {
+                draw_recon(f, view, size);
+            }
+            if let Some(view) = &guard.decoder {
+                draw_decoder(f, view, size);
+            }
+            if let Some(run) = guard.repeat.as_ref().filter(|_| guard.show_repeat) {
+                draw_repeat(f, run, size);
+            }
+            if let Some(matrix) = guard.authz.as_ref().filter(|_| guard.show_authz) {
+                draw_authz(f, matrix, size);
+            }
+            if let Some(run) = guard.smuggle.as_ref().filter(|_| guard.show_smuggle) {
+                draw_smuggle(f, run, size);
+            }
+            if guard.show_repeater {
+                if let Some(repeater) = guard.repeater.as_mut() {
+                    draw_repeater(f, repeater, size);
+                }
+            }
+            if let Some(run) = guard.fuzz.as_ref().filter(|_| guard.show_fuzz) {
+                draw_fuzz(f, run, size);
+            }
+            if let Some(diagnosis) = &guard.diagnosis {
+                draw_diagnosis(f, diagnosis, size);
+            }
+            if let Some(view) = &guard.diff {
+                draw_diff(f, view, size);
+            }
+            if let Some(jar) = &guard.cookie_jar {
+                draw_cookie_jar(f, jar, size);
+            }
+            if let Some(menu) = &guard.send_to {
+                draw_send_to(f, &guard, menu, size);
+            }
+            if let Some(view) = &guard.payloads {
+                draw_payloads(f, view, size);
+            }
+            if let Some(setup) = &guard.device_setup {
+                draw_device_setup(f, &guard, setup, size);
+            }
+            if let Some(finder) = &guard.finder {
+                draw_finder(f, &guard, finder, size);
+            }
+            if let Some(palette) = &guard.palette {
+                draw_palette(f, palette, size);
+            }
+            if let Some(paused) = guard.paused.front() {
+                draw_intercept(f, paused, guard.paused.len(), size);
+            }
+        })?;
+
+        if event::poll(Duration::from_millis(50))? {
+            let event = event::read()?;
+            if let Event::Mouse(mouse) = event {
+                let mut guard = app.lock().unwrap();
+                // Popups are keyboard-only
+                if guard.passthrough_editor.is_none() && !guard.show_ca && guard.recon.is_none() && guard.decoder.is_none() && !guard.show_repeat && !guard.show_authz && !guard.show_smuggle && !guard.show_repeater && !guard.show_fuzz && guard.diagnosis.is_none() && guard.diff.is_none() && guard.cookie_jar.is_none() && guard.send_to.is_none() && guard.payloads.is_none() && guard.device_setup.is_none() && guard.paused.is_empty() && guard.finder.is_none() && guard.palette.is_none() {
+                    handle_mouse(&mut guard, &mut hits, mouse);
+                }
+            }
+            if let Event::Paste(text) = &event {
+                let mut guard = app.lock().unwrap();
+                let shown = guard.show_repeater;
+                if let Some(repeater) = guard.repeater.as_mut().filter(|_| shown) {
+                    repeater.editor.insert_str(text);
+                } else if let Some(view) = guard.decoder.as_mut() {
+                    *view = DecoderView { source: "pasted text".into(), decoded: certs::find(text), scroll: 0 };
+                } else if guard.tab == Tab::Decoder {
+                    guard.pipeline.input = TextArea::new(text);
+                    guard.pipeline.scroll = 0;
+                }
+            }
+            if let Event::Key(mut key) = event {
+                let mut guard = app.lock().unwrap();
+                // An action run from the palette comes back as its built-in key
+                let mut chosen = false;
+                if guard.palette.is_some() {
+                    match handle_palette_key(&mut guard, key.code) {
+                        Some(builtin) => (key, chosen) = (KeyEvent::new(KeyCode::Char(builtin), KeyModifiers::NONE), true),
+                        None => continue,
+                    }
+                }
+                if guard.passthrough_editor.is_some() {
+                    handle_passthrough_key(&mut guard, key.code);
+                    continue;
+                }
+                if guard.finder.is_some() {
+                    handle_finder_key(&mut guard, key.code);
+                    continue;
+                }
+                if guard.send_to.is_some() {
+                    handle_send_to_key(&mut guard, key.code);
+                    continue;
+                }
+                if !guard.paused.is_empty() && guard.command.is_none() {
+                    handle_intercept_key(&mut guard, key.code);
+                    continue;
+                }
+                if guard.show_repeater && guard.command.is_none() {
+                    handle_repeater_key(&mut guard, app.clone(), key);
+                    continue;
+                }
+                if guard.selection.is_some() && guard.command.is_none() {
+                    handle_selection_key(&mut guard, &hits, key.code);
+                    continue;
+                }
+                if let Some(command) = guard.command.as_mut() {
+                    match key.code {
+                        KeyCode::Char(c) => command.push(c),
+                        KeyCode::Backspace => { command.pop(); }
+                        KeyCode::Up => {
+                            let typed = command.clone();
+                            if let Some(line) = guard.history.older(&typed) {
+                                guard.command = Some(line);
+                            }
+                        }
+                        KeyCode::Down => {
+                            if let Some(line) = guard.history.newer() {
+                                guard.command = Some(line);
+                            }
+                        }
+                        KeyCode::Esc => {
+                            guard.command = None;
+                            guard.history.reset();
+                        }
+                        KeyCode::Enter => {
+                            let line = guard.command.take().unwrap_or_default();
+                            guard.history.push(&line);
+                            let words: Vec<&str> = line.split_whitespace().collect();
+                            // Runs that outlive the command need the shared handle
+                            match words.split_first() {
+                                // Reopening results and stopping runs stay allowed
+                                Some((&tool @ ("repeat" | "authz" | "smuggle" | "fuzz" | "macro" | "discover" | "spider" | "diagnose"), args))
+                                    if !matches!(args, [] | ["stop"]) && guard.refuse_selected(&format!(":{}", tool)) => {}
+                                Some((&"repeat", args)) => repeat::command(&mut guard, app.clone(), args),
+                                Some((&"authz", args)) => authz::command(&mut guard, app.clone(), args),
+                                Some((&"smuggle", args)) => smuggle::command(&mut guard, app.clone(), args),
+                                Some((&"fuzz", args)) => fuzzer::command(&mut guard, app.clone(), args),
+                                Some((&"macro", args)) => macros::command(&mut guard, app.clone(), args),
+                                Some((&"discover", _)) => sitemap::discover(&mut guard, app.clone()),
+                                Some((&"trace", _)) => trace::command(&mut guard, app.clone()),
+                                Some((&"spider", args)) => spider::command(&mut guard, app.clone(), ca.cert_der.clone(), args),
+                                Some((&"diagnose", args)) => diagnose::command(&mut guard, app.clone(), ca.cert_der.clone(), args),
+                                _ => guard.run_command(&line),
+                            }
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
+                if guard.show_ca {
+                    if matches!(key.code, KeyCode::Esc | KeyCode::Char('C')) {
+                        guard.show_ca = false;
+                    }
+                    continue;
+                }
+                if guard.recon.is_some() {
+                    handle_recon_key(&mut guard, key.code);
+                    continue;
+                }
+                if guard.show_repeat {
+                    match key.code {
+                        KeyCode::Esc => guard.show_repeat = false,
+                        KeyCode::Char('s') => {
+                            if let Some(run) = &guard.repeat {
+                                run.stop();
+                            }
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
+                if guard.show_authz {
+                    if let Some(matrix) = guard.authz.as_mut() {
+                        match key.code {
+                            KeyCode::Esc => guard.show_authz = false,
+                            KeyCode::Char('s') => matrix.running = false,
+                            KeyCode::Up => matrix.scroll = matrix.scroll.saturating_sub(1),
+                            KeyCode::Down => matrix.scroll = (matrix.scroll + 1).min(matrix.rows.len().saturating_sub(1)),
+                            _ => {}
+                        }
+                    }
+                    continue;
+                }
+                if guard.show_smuggle {
+                    if let Some(run) = guard.smuggle.as_mut() {
+                        match key.code {
+                            KeyCode::Esc => guard.show_smuggle = false,
+                            KeyCode::Char('s') => run.running = false,
+                            KeyCode::Up => run.selected = run.selected.saturating_sub(1),
+                            KeyCode::Down => run.selected = (run.selected + 1).min(run.probes.len().saturating_sub(1)),
+                            KeyCode::Char('r') => {
+                                let repeater = run.repeater();
+                                let host = repeater.as_ref().map_or(String::new(), |r| r.host.clone());
+                                if !guard.refuse_for("the Repeater", &host) {
+                                    guard.repeater = repeater;
+                                    guard.show_smuggle = false;
+                                    guard.show_repeater = true;
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                    continue;
+                }
+                if let Some(diagnosis) = guard.diagnosis.as_mut() {
+                    match key.code {
+                        KeyCode::Esc => guard.diagnosis = None,
+                        KeyCode::Up => diagnosis.scroll = diagnosis.scroll.saturating_sub(1),
+                        KeyCode::Down => diagnosis.scroll = (diagnosis.scroll + 1).min(diagnosis.lines.len().saturating_sub(1)),
+                        _ => {}
+                    }
+                    continue;
+                }
+                if let Some(view) = guard.diff.as_mut() {
+                    match key.code {
+                        KeyCode::Esc => guard.diff = None,
+                        KeyCode::Up => view.scroll = view.scroll.saturating_sub(1),
+                        KeyCode::Down => view.scroll = (view.scroll + 1).min(view.lines.len().saturating_sub(1)),
+                        KeyCode::PageUp => view.scroll = view.scroll.saturating_sub(20),
+                        KeyCode::PageDown => view.scroll = (view.scroll + 20).min(view.lines.len().saturating_sub(1)),
+                        _ => {}
+                    }
+                    continue;
+                }
+                if guard.show_fuzz {
+                    if let Some(run) = guard.fuzz.as_mut() {
+                        match key.code {
+                            KeyCode::Esc => guard.show_fuzz = false,
+                            KeyCode::Char('s') => run.stop(),
+                            KeyCode::Char('g') => {
+                                run.grouped = !run.grouped;
+                                run.selected = 0;
+                            }
+                            KeyCode::Up => {
+                                run.selected = run.selected.saturating_sub(1);
+                                run.scroll = 0;
+                            }
+                            KeyCode::Down => {
+                                run.selected = (run.selected + 1).min(run.rows().saturating_sub(1));
+                                run.scroll = 0;
+                            }
+                            KeyCode::PageUp => run.scroll = run.scroll.saturating_sub(20),
+                            KeyCode::PageDown => run.scroll += 20,
+                            _ => {}
+                        }
+                    }
+                    continue;
+                }
+                if guard.payloads.is_some() {
+                    handle_payloads_key(&mut guard, key.code);
+                    continue;
+                }
+                if guard.cookie_jar.is_some() {
+                    handle_cookie_jar_key(&mut guard, key.code);
+                    continue;
+                }
+                if let Some(view) = guard.decoder.as_mut() {
+                    match key.code {
+                        KeyCode::Esc | KeyCode::Char('K') => guard.decoder = None,
+                        KeyCode::Up => view.scroll = view.scroll.saturating_sub(1),
+                        KeyCode::Down => view.scroll += 1,
+                        KeyCode::PageUp => view.scroll = view.scroll.saturating_sub(20),
+                        KeyCode::PageDown => view.scroll += 20,
+                        _ => {}
+                    }
+                    continue;
+                }
+                if let Some(setup) = guard.device_setup.as_mut() {
+                    match key.code {
+                        KeyCode::Esc | KeyCode::Char('D') => guard.device_setup = None,
+                        KeyCode::Tab => setup.ios = !setup.ios,
+                        _ => {}
+                    }
+                    continue;
+                }
+                if guard.tab == Tab::Decoder && handle_pipeline_key(&mut guard, key) {
+                    continue;
+                }
+                // Keys rebound in config.toml act as the built-in ones
+                match if chosen { key.code } else { keys::translate(key.code) } {
+                    // Most terminals send Ctrl+Shift+P as Ctrl+P, hence F1
+                    KeyCode::F(1) => guard.palette = Some(Palette::open()),
+                    KeyCode::Char('p' | 'P') if key.modifiers.contains(KeyModifiers::CONTROL | KeyModifiers::SHIFT) => {
+                        guard.palette = Some(Palette::open());
+                    }
+                    KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        guard.finder = Some(Finder::open(&guard.logs));
+                    }
+                    KeyCode::Char('q') => break,
+                    KeyCode::Tab => guard.tab = guard.tab.next(),
+                    KeyCode::Up => guard.previous(),
+                    KeyCode::Down => guard.next(),
+                    KeyCode::PageDown => guard.detail_scroll += 20,
+                    KeyCode::PageUp => guard.detail_scroll = guard.detail_scroll.saturating_sub(20),
+                    KeyCode::Home => guard.detail_scroll = 0,
+                    KeyCode::Char('L') => guard.full_body = true,
+                    KeyCode::Char('[') => guard.resize_split(-5),
+                    KeyCode::Char(']') => guard.resize_split(5),
+                    KeyCode::Char('z') => guard.zoomed = !guard.zoomed,
+                    KeyCode::Char('h') => guard.compare_headers = !guard.compare_headers,
+                    KeyCode::Char('v') => guard.html_preview = !guard.html_preview,
+                    KeyCode::Char('V') if guard.tab == Tab::Requests && guard.selected_log().is_some() => {
+                        let top = guard.detail_scroll;
+                        guard.selection = Some(Selection::at((top, 0)));
+                    }
+                    KeyCode::Char('I') => guard.toggle_intercept(),
+                    KeyCode::Char('r') if guard.tab == Tab::Credentials => guard.toggle_reveal(),
+                    KeyCode::Char('w') => save_selected_body(&mut guard),
+                    KeyCode::Char('E') if guard.tab == Tab::Requests => guard.edit_body(true),
+                    KeyCode::Char('s') if guard.tab == Tab::Requests => send_to::open(&mut guard),
+                    KeyCode::Char(':') => guard.command = Some(String::new()),
+                    KeyCode::Char('/') => guard.command = Some("find ".into()),
+                    KeyCode::Char('o') if guard.tab == Tab::Hosts => guard.host_sort = guard.host_sort.next(),
+                    KeyCode::Char('O') if guard.tab == Tab::Hosts => guard.host_sort_reversed = !guard.host_sort_reversed,
+                    KeyCode::Enter if guard.tab == Tab::Sitemap => guard.open_sitemap_node(),
+                    KeyCode::Char('o') => guard.cycle_sort(),
+                    KeyCode::Char('O') => guard.reverse_sort(),
+                    KeyCode::Char('i') => guard.toggle_identical(),
+                    KeyCode::Char('c') => guard.toggle_connection(),
+                    KeyCode::Char('e') => guard.toggle_chain(),
+                    KeyCode::Char('m') if guard.tab == Tab::Requests => guard.toggle_evidence(),
+                    KeyCode::Char('G') if guard.tab == Tab::Requests => guard.jump_newest(),
+                    KeyCode::Char('b') => guard.sidebar = !guard.sidebar,
+                    KeyCode::Char('{') if guard.sidebar => filters::step(&mut guard, -1),
+                    KeyCode::Char('}') if guard.sidebar => filters::step(&mut guard, 1),
+                    KeyCode::Char('X') if guard.refuse_selected("the CORS probe") => {}
+                    KeyCode::Char('X') => {
+                        if let Some(log) = guard.selected_log().filter(|l| !l.request_raw.is_empty()).cloned() {
+                            guard.set_status(format!("CORS probe {}…", log.host));
+                            cors::probe(app.clone(), log);
+                        }
+                    }
+                    KeyCode::Char('H') if guard.refuse_selected("the Host probe") => {}
+                    KeyCode::Char('H') => {
+                        if let Some(log) = guard.selected_log().filter(|l| !l.request_raw.is_empty()).cloned() {
+                            guard.set_status(format!("Host probe {}…", log.host));
+                            vhost::probe(app.clone(), log);
+                        }
+                    }
+                    KeyCode::Char('M') if guard.refuse_selected("param mining") => {}
+                    KeyCode::Char('M') => {
+                        if let Some(log) = guard.selected_log().filter(|l| !l.request_raw.is_empty()).cloned() {
+                            guard.set_status(format!("Param mining {}…", log.host));
+                            miner::start(app.clone(), log);
+                        }
+                    }
+                    KeyCode::Char('p') => guard.toggle_selected_passthrough(),
+                    KeyCode::Char('S') => guard.toggle_selected_scope(),
+                    KeyCode::Char('P') => guard.passthrough_editor = Some(PassthroughEditor::default()),
+                    KeyCode::Char('C') => guard.show_ca = true,
+                    KeyCode::Char('K') => {
+                        let view = match guard.selected_log() {
+                            Some(log) => {
+                                let text = format!("{}\n{}", log.request.text(), String::from_utf8_lossy(&log.response_body()));
+                                DecoderView { source: log.url.clone(), decoded: certs::find(&text), scroll: 0 }
+                            }
+                            None => DecoderView { source: "no entry selected".into(), ..Default::default() },
+                        };
+                        guard.decoder = Some(view);
+                    }
+                    KeyCode::Char('U') => recon::open(&mut guard, app.clone()),
+                    KeyCode::Char('D') => open_device_setup(&mut guard, &ca),
+                    KeyCode::Char('R') => guard.open_repeater(),
+                    _ => {}
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the columns each tab title covers
+fn draw_tabs(f: &mut Frame<Backend>, app: &App, area: Rect) -> Vec<(Tab, u16, u16)> {
+    let events = app.events.lock().unwrap();
+    let problems = events.iter().filter(|e| e.level <= Level::WARN).count();
+    let titles = Tab::ALL.iter().map(|t| {
+        let title = match t {
+            Tab::Issues if !app.issues.is_empty() => format!("{} ({})", t.title(), app.issues.len()),
+            Tab::Events if problems > 0 => format!("{} ({})", t.title(), problems),
+            _ => t.title().to_string(),
+        };
+        Spans::from(title)
+    }).collect::<Vec<_>>();
+    // Tabs pads each title with a space either side and puts a one-column divider between them
+    let mut x = area.x;
+    let spans = Tab::ALL.iter().zip(&titles).map(|(t, title)| {
+        let start = x;
+        x += title.width() as u16 + 2;
+        let span = (*t, start, x);
+        x += 1;
+        span
+    }).collect();
+    let selected = Tab::ALL.iter().position(|t| *t == app.tab).unwrap_or(0);
+    f.render_widget(
+        Tabs::new(titles)
+            .select(selected)
+            .highlight_style(theme::selection()),
+        area,
+    );
+    spans
+}
+
+/// Byte count with a binary unit suffix
+fn human_size(n: usize) -> String {
+    match n {
+        0..=1023 => format!("{}B", n),
+        1024..=1_048_575 => format!("{:.1}K", n as f64 / 1024.0),
+        _ => format!("{:.1}M", n as f64 / 1_048_576.0),
+    }
+}
+
+/// Saved filters and tags with how many entries each has; the one applied is highlighted
+fn draw_sidebar(f: &mut Frame<Backend>, app: &App, area: Rect) {
+    let current = filters::current(app);
+    let lines: Vec<Spans> = filters::items(app).into_iter().map(|(item, count)| {
+        let (label, color) = match &item {
+            Item::All => ("All".to_string(), Color::White),
+            Item::Filter(name) => (name.clone(), Color::Cyan),
+            Item::Tag(tag) => (format!("[{}]", tag), theme::get().tag),
+        };
+        let style = if item == current { Style::default().fg(Color::Black).bg(color) } else { Style::default().fg(color) };
+        Spans::from(Span::styled(format!("{} ({})", label, count), style))
+    }).collect();
+    f.render_widget(Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("Filters {/}")), area);
+}
+
+/// Returns the largest useful detail scroll offset
+fn draw_requests(f: &mut Frame<Backend>, app: &App, view: &[usize], area: Rect, hits: &mut Hits, unseen: usize) -> usize {
+    let area = if app.sidebar && !app.zoomed {
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(24), Constraint::Min(0)])
+            .split(area);
+        draw_sidebar(f, app, columns[0]);
+        hits.sidebar = columns[0];
+        columns[1]
+    } else {
+        area
+    };
+    let panels = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(app.split), Constraint::Percentage(100u16.saturating_sub(app.split))])
+        .split(area);
+    let panels = if app.zoomed { vec![Rect { width: 0, ..area }, area] } else { panels.to_vec() };
+
+    // Entries sharing a response body, for the duplicate column
+    let mut copies: HashMap<&str, usize> = HashMap::new();
+    for log in app.logs.iter().filter(|l| !l.response_sha256.is_empty()) {
+        *copies.entry(&log.response_sha256).or_default() += 1;
+    }
+    let listed: HashSet<usize> = view.iter().copied().collect();
+    // Each marker shows on the first entry started after it
+    let mut marked: HashMap<usize, Vec<&str>> = HashMap::new();
+    for marker in &app.markers {
+        if let Some(i) = app.logs.iter().position(|l| l.started.is_some_and(|t| t >= marker.at)) {
+            marked.entry(i).or_default().push(&marker.name);
+        }
+    }
+    let rows = view.iter().map(|i| {
+        let log = &app.logs[*i];
+        let dup = copies.get(log.response_sha256.as_str()).copied().unwrap_or(0);
+        let mut request = Vec::new();
+        // Hops of an expanded chain sit under its first request
+        let hop = app.redirects.prev(*i).is_some_and(|p| listed.contains(&p));
+        if hop {
+            request.push(Span::styled("  ↳ ", Style::default().fg(Color::Magenta)));
+        }
+        let chain = app.redirects.chain(*i);
+        if chain.len() > 1 && !hop && !app.expanded.contains(i) {
+            let last = chain.last().and_then(|l| app.logs[*l].status()).map_or("?".to_string(), |s| s.to_string());
+            request.push(Span::styled(format!("[↪{} → {}] ", chain.len() - 1, last), Style::default().fg(Color::Magenta)));
+        }
+        if app.evidence.contains(i) {
+            request.push(Span::styled("★ ", Style::default().fg(Color::Green)));
+        }
+        if let Some(status) = log.triage {
+            request.push(Span::styled(format!("<{}> ", status.name()), Style::default().fg(Color::Green)));
+        }
+        for name in marked.get(i).into_iter().flatten() {
+            request.push(Span::styled(format!("▸{} ", name), Style::default().fg(Color::Black).bg(Color::Magenta)));
+        }
+        if app.listeners.len() > 1 && !log.listener.is_empty() {
+            request.push(Span::styled(format!("@{} ", log.listener), Style::default().fg(Color::Blue)));
+        }
+        if log.origin != Origin::Live {
+            request.push(Span::styled(format!("{{{}}} ", log.origin.name()), Style::default().fg(Color::Cyan)));
+        }
+        request.extend(log.tags.iter().map(|t| Span::styled(format!("[{}] ", t), Style::default().fg(theme::get().tag))));
+        request.push(Span::raw(log.url.clone()));
+        Row::new(vec![
+            Cell::from(Spans::from(request)),
+            Cell::from(human_size(log.request_size)),
+            Cell::from(human_size(log.response_size)),
+            Cell::from(log.response_sha256.get(..12).unwrap_or("").to_string()),
+            Cell::from(if dup > 1 { format!("×{}", dup) } else { String::new() }),
+            Cell::from(log.correlation.first().map_or(String::new(), |(_, id)| id.chars().take(12).collect())),
+        ])
+    }).collect::<Vec<_>>();
+    let mut title = format!("Requests [{} {}]", app.sort.title(), if app.sort_desc { "↓" } else { "↑" });
+    if app.intercepting {
+        title.push_str(" [intercept]");
+    }
+    if app.read_only {
+        title.push_str(" [read-only]");
+    } else if !app.read_only_hosts.is_empty() {
+        title.push_str(&format!(" [read-only: {}]", app.read_only_hosts.join(" ")));
+    }
+    if let Some(hash) = &app.identical_to {
+        title.push_str(&format!(" identical to {}… ({})", &hash[..12], view.len()));
+    }
+    if let Some(conn) = app.conn_filter {
+        title.push_str(&format!(" on connection #{} ({})", conn, view.len()));
+    }
+    if let Some(id) = &app.corr_filter {
+        title.push_str(&format!(" with ID {} ({})", id, view.len()));
+    }
+    if let Some(tag) = &app.tag_filter {
+        title.push_str(&format!(" tagged {} ({})", tag, view.len()));
+    }
+    if let Some(name) = &app.saved_filter {
+        title.push_str(&format!(" in {} ({})", name, view.len()));
+    }
+    if let Some(window) = &app.window {
+        title.push_str(&format!(" {} ({})", window.label(&app.markers), view.len()));
+    }
+    if let Some(origin) = app.origin_filter {
+        title.push_str(&format!(" from {} ({})", origin.name(), view.len()));
+    }
+    if let Some(listener) = &app.listener_filter {
+        title.push_str(&format!(" on listener {} ({})", listener, view.len()));
+    }
+    if let Some(query) = &app.where_filter {
+        title.push_str(&format!(" where {} ({})", query.text, view.len()));
+    }
+    if let Some(search) = &app.search {
+        title.push_str(&format!(" matching \"{}\" ({})", search, view.len()));
+    }
+    if app.following {
+        title.push_str(" following");
+    }
+    let mut title = vec![Span::raw(title)];
+    if unseen > 0 {
+        // Newest entries sit at the bottom unless the sort is reversed
+        let arrow = if app.sort_desc { "▲" } else { "▼" };
+        title.push(Span::styled(format!(" {} {} new (G) ", arrow, unseen), Style::default().fg(Color::Black).bg(theme::get().tag)));
+    }
+    let mut state = TableState::default();
+    state.select((!view.is_empty()).then_some(app.selected));
+    f.render_stateful_widget(
+        Table::new(rows)
+            .header(Row::new(["Request", "Req", "Resp", "SHA-256", "Dup", "Corr ID"])
+                .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)))
+            .block(Block::default().borders(Borders::ALL).title(Spans::from(title)))
+            .highlight_style(theme::selection())
+            .widths(&[
+                // Whatever the fixed columns and their spacing leave
+                Constraint::Length(panels[0].width.saturating_sub(49).max(20)),
+                Constraint::Length(7),
+                Constraint::Length(7),
+                Constraint::Length(12),
+                Constraint::Length(4),
+                Constraint::Length(12),
+            ]),
+        panels[0],
+        &mut state,
+    );
+    (hits.list, hits.list_offset, hits.detail) = (panels[0], state.offset(), panels[1]);
+
+    let index = view.get(app.selected).copied();
+    let selected = index.map(|i| &app.logs[i]);
+
+    let mut segments = index.map_or(Vec::new(), |i| chain_segments(app, i));
+    if let Some((index, deps)) = app.trace.as_ref().filter(|(i, _)| index == Some(*i)) {
+        segments.extend(trace_segments(app, *index, deps));
+    }
+    if let Some(log) = selected.filter(|l| l.conn != 0) {
+        let shared: Vec<usize> = (0..app.logs.len()).filter(|i| app.logs[*i].conn == log.conn).collect();
+        if shared.len() > 1 {
+            let nth = shared.iter().position(|i| Some(*i) == index).map_or(0, |p| p + 1);
+            segments.push(Segment::Styled(Spans::from(Span::styled(
+                format!("Connection #{}: request {} of {} on it (c: show only these)", log.conn, nth, shared.len()),
+                Style::default().fg(Color::DarkGray),
+            ))));
+        }
+    }
+    if let Some(log) = selected.filter(|l| !l.correlation.is_empty()) {
+        let ids: Vec<String> = log.correlation.iter().map(|(header, id)| format!("{} {}", header, id)).collect();
+        segments.push(Segment::Styled(Spans::from(Span::styled(
+            format!("Correlation: {} (:corr to show only these)", ids.join(", ")),
+            Style::default().fg(Color::DarkGray),
+        ))));
+    }
+    if let Some(label) = selected.and_then(triage::label) {
+        segments.push(Segment::Styled(Spans::from(Span::styled(label, Style::default().fg(Color::Green)))));
+    }
+    if let Some(primary) = selected.and_then(|l| l.mirror_of).and_then(|i| app.logs.get(i)) {
+        let verdict = if selected.is_some_and(|l| l.tags.iter().any(|t| t == "mirror-same")) { "same answer" } else { "answer differs" };
+        segments.push(Segment::Styled(Spans::from(Span::styled(
+            format!("Mirror of {}: {} (s, d to diff)", primary.url, verdict),
+            Style::default().fg(Color::DarkGray),
+        ))));
+    }
+    if let Some(lookup) = selected.and_then(|l| l.resolved) {
+        segments.push(Segment::Styled(Spans::from(Span::styled(format!("Resolved: {}", lookup.label()), Style::default().fg(Color::DarkGray)))));
+    }
+    // Decompressed once per entry by `detail::update`
+    let texts = selected.map(|_| (app.detail.request.as_str(), app.detail.response.as_str()));
+    let whole = Some(app.detail.response_body_len);
+    if let (Some((request, response)), Some(log), true) = (texts, selected, app.compare_headers) {
+        let width = panels[1].width.saturating_sub(2) as usize;
+        segments.extend(compare_segments(request, response, log.tls, width));
+        for (label, text, whole) in [("Request body:", request, None), ("Response body:", response, whole)] {
+            if !http::split_message(text).1.is_empty() {
+                segments.push(Segment::Styled(Spans::from("")));
+                segments.push(Segment::Styled(Spans::from(Span::styled(label, Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)))));
+                // Skip the head, already in the columns
+                segments.extend(message_segments(text, whole).into_iter().skip(2));
+            }
+        }
+    } else if let Some((request, response)) = texts {
+        segments.push(Segment::Styled(Spans::from(Span::styled(
+            "Request:", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        ))));
+        segments.extend(message_segments(request, None));
+        segments.push(Segment::Styled(Spans::from(Span::styled(
+            "Response:", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD),
+        ))));
+        let html = http::header_value(http::split_message(response).0, "content-type").is_some_and(preview::is_html);
+        let response_segments = match app.html_preview && html {
+            true => preview_segments(response),
+            false => message_segments(response, whole),
+        };
+        // A stream's body is listed as events below instead
+        let keep = if sse::is_stream(http::split_message(response).0) { 1 } else { response_segments.len() };
+        segments.extend(response_segments.into_iter().take(keep));
+    }
+    if let Some((index, log)) = index.zip(selected) {
+        if let Some(query) = &app.body_query {
+            segments.extend(query_segments(query, app.detail.json(log)));
+        }
+        for (i, form) in app.detail.forms.iter().enumerate() {
+            segments.extend(form_segments(i + 1, form));
+        }
+        for found in &app.detail.saml {
+            segments.extend(saml_segments(found));
+        }
+        for blob in &app.detail.serialized {
+            segments.extend(serialized_segments(blob));
+        }
+        for decoded in &app.detail.grpc {
+            segments.extend(grpc_segments(decoded));
+        }
+        for decoded in &app.detail.packed {
+            segments.extend(packed_segments(decoded));
+        }
+        for (name, text) in &app.detail.plugins {
+            segments.push(Segment::Styled(Spans::from("")));
+            segments.push(Segment::Styled(Spans::from(Span::styled(
+                format!("{} (plugin):", name),
+                Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD),
+            ))));
+            segments.extend(text.lines().map(|l| Segment::Styled(Spans::from(l.to_string()))));
+        }
+        if log.websocket {
+            segments.extend(ws_segments(app, index));
+        }
+        if sse::is_stream(&log.response_head()) {
+            segments.extend(sse_segments(app));
+        }
+    }
+    if texts.is_none() {
+        segments.push(Segment::Styled(Spans::from("No requests yet")));
+    }
+    let total: usize = segments.iter().map(Segment::len).sum();
+    let height = panels[1].height.saturating_sub(2) as usize;
+    let max_scroll = total.saturating_sub(height);
+    let offset = app.detail_scroll.min(max_scroll);
+    let mut lines = visible_lines(&segments, offset, height);
+    let title = format!("Raw [{}/{}]", offset + 1, total);
+    let Some(selection) = &app.selection else {
+        hits.detail_lines.clear();
+        f.render_widget(
+            Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title)).wrap(Wrap { trim: false }),
+            panels[1],
+        );
+        return max_scroll;
+    };
+    // Unwrapped while selecting, so each row is one line and columns map straight to characters
+    hits.detail_lines = segments.iter().flat_map(Segment::texts).collect();
+    for (i, line) in lines.iter_mut().enumerate() {
+        let len = line.0.iter().map(|s| s.content.chars().count()).sum();
+        if let Some((from, to)) = selection.columns(offset + i, len) {
+            *line = mark_columns(std::mem::take(line), from, to);
+        }
+    }
+    let width = panels[1].width.saturating_sub(2) as usize;
+    hits.detail_hscroll = selection.cursor.1.saturating_sub(width.saturating_sub(1));
+    f.render_widget(
+        Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title(format!("{} selecting — y: copy  d: decoder  f: fuzz here  v: restart  Esc: done", title)))
+            .scroll((0, hits.detail_hscroll as u16)),
+        panels[1],
+    );
+    max_scroll
+}
+
+/// `line` with the characters from `from` to `to` shown reversed, and a reversed space past its
+/// end when the selection runs beyond it
+fn mark_columns(line: Spans<'static>, from: usize, to: usize) -> Spans<'static> {
+    let mut out = Vec::new();
+    let mut at = 0;
+    for span in line.0 {
+        let chars: Vec<char> = span.content.chars().collect();
+        let (start, end) = (at, at + chars.len());
+        at = end;
+        let (a, b) = (from.clamp(start, end) - start, to.clamp(start, end) - start);
+        for (range, style) in [(0..a, span.style), (a..b, span.style.add_modifier(Modifier::REVERSED)), (b..chars.len(), span.style)] {
+            if !range.is_empty() {
+                out.push(Span::styled(chars[range].iter().collect::<String>(), style));
+            }
+        }
+    }
+    if to > at {
+        out.push(Span::styled(" ", Style::default().add_modifier(Modifier::REVERSED)));
+    }
+    Spans::from(out)
+}
+
+/// A run of detail pane lines; Spans are only built for the lines on screen
+enum Segment<'a> {
+    Styled(Spans<'static>),
+    Plain(Vec<&'a str>),
+    Highlighted(Arc<Vec<Spans<'static>>>),
+}
+
+impl Segment<'_> {
+    fn len(&self) -> usize {
+        match self {
+            Segment::Styled(_) => 1,
+            Segment::Plain(lines) => lines.len(),
+            Segment::Highlighted(lines) => lines.len(),
+        }
+    }
+
+    /// Unstyled text of each line
+    fn texts(&self) -> Vec<String> {
+        let text = |line: &Spans| line.0.iter().map(|s| s.content.as_ref()).collect();
+        match self {
+            Segment::Styled(line) => vec![text(line)],
+            Segment::Plain(lines) => lines.iter().map(|l| l.to_string()).collect(),
+            Segment::Highlighted(lines) => lines.iter().map(text).collect(),
+        }
+    }
+}
+
+fn visible_lines(segments: &[Segment], mut offset: usize, height: usize) -> Vec<Spans<'static>> {
+    let mut out = Vec::with_capacity(height);
+    for segment in segments {
+        if out.len() >= height {
+            break;
+        }
+        let len = segment.len();
+        if offset >= len {
+            offset -= len;
+            continue;
+        }
+        let want = height - out.len();
+        match segment {
+            Segment::Styled(line) => out.push(line.clone()),
+            Segment::Plain(lines) => out.extend(lines[offset..].iter().take(want).map(|l| Spans::from(l.to_string()))),
+            Segment::Highlighted(lines) => out.extend(lines[offset..].iter().take(want).cloned()),
+        }
+        offset = 0;
+    }
+    out
+}
+
+/// Head lines as-is, body highlighted according to its Content-Type and cut at `limit` bytes
+fn message_segments(text: &str, whole: Option<usize>) -> Vec<Segment<'_>> {
+    let (head, body) = http::split_message(text);
+    let mut segments = vec![Segment::Plain(head.lines().collect())];
+    if body.is_empty() {
+        return segments;
+    }
+    segments.push(Segment::Styled(Spans::from("")));
+    let highlighted = http::header_value(head, "content-type").and_then(highlight::syntax_for).and_then(|ext| highlight::highlight(body, ext));
+    match highlighted {
+        Some(lines) => segments.push(Segment::Highlighted(lines)),
+        None => segments.push(Segment::Plain(body.lines().collect())),
+    }
+    if let Some(whole) = whole.filter(|w| *w > body.len()) {
+        segments.push(Segment::Styled(Spans::from(Span::styled(
+            format!("[truncated — {} of {} bytes shown — press L to load full body]", body.len(), whole),
+            Style::default().fg(Color::Yellow),
+        ))));
+    }
+    segments
+}
+
+/// The response head, then its HTML body as readable text
+fn preview_segments(text: &str) -> Vec<Segment<'_>> {
+    let (head, body) = http::split_message(text);
+    let mut segments = vec![
+        Segment::Plain(head.lines().collect()),
+        Segment::Styled(Spans::from("")),
+        Segment::Styled(Spans::from(Span::styled("[HTML preview — v: source]", Style::default().fg(Color::DarkGray)))),
+    ];
+    for line in preview::render(body) {
+        segments.push(Segment::Styled(Spans::from(line.into_iter().map(|(kind, text)| {
+            let style = match kind {
+                preview::Kind::Text => Style::default(),
+                preview::Kind::Title => Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+                preview::Kind::Heading => Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+                preview::Kind::Link => Style::default().fg(Color::Blue).add_modifier(Modifier::UNDERLINED),
+                preview::Kind::Href => Style::default().fg(Color::DarkGray),
+                preview::Kind::Form => Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD),
+                preview::Kind::Field => Style::default().fg(Color::Magenta),
+            };
+            Span::styled(text, style)
+        }).collect::<Vec<_>>())));
+    }
+    segments
+}
+
+/// Request headers on the left and response headers on the right, related ones on one row
+fn compare_segments(request: &str, response: &str, tls: bool, width: usize) -> Vec<Segment<'static>> {
+    let (req_head, resp_head) = (http::split_message(request).0, http::split_message(response).0);
+    let column = width.saturating_sub(3) / 2;
+    let cell = |h: &Option<(String, String)>| {
+        let text = h.as_ref().map_or(String::new(), |(k, v)| format!("{}: {}", k, v));
+        let mut text: String = text.chars().take(column).collect();
+        let pad = column - text.chars().count();
+        text.push_str(&" ".repeat(pad));
+        text
+    };
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let mut segments = vec![
+        Segment::Styled(Spans::from(vec![
+            Span::styled(format!("{:<column$}", req_head.lines().next().unwrap_or(""), column = column), bold.fg(Color::Cyan)),
+            Span::raw(" │ "),
+            Span::styled(resp_head.lines().next().unwrap_or("").to_string(), bold.fg(Color::Green)),
+        ])),
+    ];
+    for row in compare::rows(req_head, resp_head, tls) {
+        let right = match (&row.response, row.note) {
+            (None, Some((note, why))) => {
+                let color = if note == compare::Note::Missing { Color::Red } else { Color::Yellow };
+                Span::styled(why.chars().take(column).collect::<String>(), Style::default().fg(color))
+            }
+            _ => Span::raw(cell(&row.response).trim_end().to_string()),
+        };
+        segments.push(Segment::Styled(Spans::from(vec![
+            Span::styled(cell(&row.request), Style::default().fg(if row.request.is_some() { Color::Reset } else { Color::DarkGray })),
+            Span::styled(" │ ", Style::default().fg(Color::DarkGray)),
+            right,
+        ])));
+    }
+    segments
+}
+
+/// Decoded SAML message: key fields, then the re-indented XML
+fn saml_segments(found: &saml::Saml) -> Vec<Segment<'_>> {
+    let mut segments = vec![
+        Segment::Styled(Spans::from("")),
+        Segment::Styled(Spans::from(Span::styled(
+            format!("{} ({}):", found.param, found.source),
+            Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD),
+        ))),
+    ];
+    for (name, value) in &found.fields {
+        let value = match value {
+            Some(v) => Span::styled(v.clone(), Style::default().fg(Color::Yellow)),
+            None => Span::styled("absent", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+        };
+        segments.push(Segment::Styled(Spans::from(vec![
+            Span::styled(format!("  {:<30}", name), Style::default().fg(Color::DarkGray)),
+            value,
+        ])));
+    }
+    segments.push(Segment::Styled(Spans::from("")));
+    segments.push(highlight::highlight(&found.xml, "xml").map_or_else(|| Segment::Plain(found.xml.lines().collect()), Segment::Highlighted));
+    segments
+}
+
+fn serialized_segments(blob: &serialized::Blob) -> Vec<Segment<'static>> {
+    let mut segments = vec![
+        Segment::Styled(Spans::from("")),
+        Segment::Styled(Spans::from(Span::styled(
+            format!("{}:", blob.label),
+            Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD),
+        ))),
+    ];
+    for (name, value) in &blob.fields {
+        let style = match (*name, value.as_str()) {
+            ("MAC", "absent") | ("Gadget classes", _) => Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            _ => Style::default().fg(Color::Yellow),
+        };
+        segments.push(Segment::Styled(Spans::from(vec![
+            Span::styled(format!("  {:<30}", name), Style::default().fg(Color::DarkGray)),
+            Span::styled(value.clone(), style),
+        ])));
+    }
+    if !blob.lines.is_empty() {
+        segments.push(Segment::Styled(Spans::from("")));
+    }
+    segments.extend(blob.lines.iter().map(|l| Segment::Styled(Spans::from(l.clone()))));
+    segments
+}
+
+/// What the `:jq` query picks out of the response
+fn query_segments(query: &jsonpath::Query, json: Option<&serde_json::Value>) -> Vec<Segment<'static>> {
+    let mut segments = vec![
+        Segment::Styled(Spans::from("")),
+        Segment::Styled(Spans::from(vec![
+            Span::styled(format!("jq {}", query.text), Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD)),
+            Span::styled("  (:jq clears)", Style::default().fg(Color::DarkGray)),
+        ])),
+    ];
+    let note = |text: &str| Segment::Styled(Spans::from(Span::styled(text.to_string(), Style::default().fg(Color::DarkGray))));
+    match json {
+        None => segments.push(note("  the response is not JSON")),
+        Some(json) => {
+            let results = query.results(json);
+            if results.is_empty() {
+                segments.push(note("  no match"));
+            }
+            for value in results {
+                let text = serde_json::to_string_pretty(&value).unwrap_or_default();
+                segments.extend(text.lines().map(|l| Segment::Styled(Spans::from(Span::styled(format!("  {}", l), Style::default().fg(Color::Yellow))))));
+            }
+        }
+    }
+    segments
+}
+
+/// A form's action and fields, with how to submit it from the Repeater
+fn form_segments(n: usize, form: &forms::Form) -> Vec<Segment<'static>> {
+    let mut segments = vec![
+        Segment::Styled(Spans::from("")),
+        Segment::Styled(Spans::from(vec![
+            Span::styled(format!("Form {}: {}", n, form.label()), Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD)),
+            Span::styled(format!("  (:form {} fills it in the Repeater)", n), Style::default().fg(Color::DarkGray)),
+        ])),
+    ];
+    for field in &form.fields {
+        segments.push(Segment::Styled(Spans::from(vec![
+            Span::styled(format!("  {:<24}", field.name), Style::default().fg(Color::DarkGray)),
+            Span::styled(format!("{:<10}", field.kind), Style::default().fg(Color::Cyan)),
+            Span::styled(field.value.clone(), Style::default().fg(Color::Yellow)),
+        ])));
+    }
+    segments
+}
+
+/// One decoded RPC message as its field tree
+fn grpc_segments(decoded: &grpc::Decoded) -> Vec<Segment<'static>> {
+    let mut segments = vec![
+        Segment::Styled(Spans::from("")),
+        Segment::Styled(Spans::from(Span::styled(
+            format!("{}:", decoded.label),
+            Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD),
+        ))),
+    ];
+    segments.extend(decoded.lines.iter().map(|l| Segment::Styled(Spans::from(format!("  {}", l)))));
+    segments
+}
+
+fn packed_segments(decoded: &packed::Decoded) -> Vec<Segment<'_>> {
+    vec![
+        Segment::Styled(Spans::from("")),
+        Segment::Styled(Spans::from(Span::styled(
+            format!("{}:", decoded.label),
+            Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD),
+        ))),
+        highlight::highlight(&decoded.text, "json").map_or_else(|| Segment::Plain(decoded.text.lines().collect()), Segment::Highlighted),
+    ]
+}
+
+/// Every hop of the redirect chain `index` is part of, with the selected one marked
+fn chain_segments(app: &App, index: usize) -> Vec<Segment<'static>> {
+    let chain = app.redirects.chain(app.redirects.head(index));
+    if chain.len() < 2 {
+        return Vec::new();
+    }
+    let mut segments = vec![Segment::Styled(Spans::from(Span::styled(
+        format!("Redirect chain ({} hops, e: expand/fold):", chain.len() - 1),
+        Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD),
+    )))];
+    for i in chain {
+        let log = &app.logs[i];
+        let status = log.status().map_or("---".to_string(), |s| s.to_string());
+        let color = if log.status().is_some_and(|s| (300..400).contains(&s)) { Color::Yellow } else { Color::Green };
+        let marker = if i == index { "▶ " } else { "  " };
+        segments.push(Segment::Styled(Spans::from(vec![
+            Span::raw(marker),
+            Span::styled(format!("{} ", status), Style::default().fg(color)),
+            Span::raw(format!("{} {}", log.method(), log.full_url())),
+        ])));
+    }
+    segments.push(Segment::Styled(Spans::from("")));
+    segments
+}
+
+/// Values the traced request sends, each under the earlier response it first appeared in
+fn trace_segments(app: &App, index: usize, deps: &[Dependency]) -> Vec<Segment<'static>> {
+    let mut segments = vec![Segment::Styled(Spans::from(Span::styled(
+        format!("Dependencies of {} {}:", app.logs[index].method(), app.logs[index].full_url()),
+        Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD),
+    )))];
+    if deps.is_empty() {
+        segments.push(Segment::Styled(Spans::from("  No IDs or tokens sent")));
+    }
+    for dep in deps {
+        let mut value = dep.value.clone();
+        if value.chars().count() > 40 {
+            value = format!("{}…", value.chars().take(40).collect::<String>());
+        }
+        let mut spans = vec![
+            Span::raw("  ".repeat(dep.depth + 1)),
+            Span::styled(format!("{} ({})", dep.name, dep.location), Style::default().fg(Color::Cyan)),
+            Span::raw(format!(" = {}", value)),
+        ];
+        match dep.source {
+            Some((j, place)) => {
+                let log = &app.logs[j];
+                let status = log.status().map_or("---".to_string(), |s| s.to_string());
+                spans.push(Span::styled(format!("  ← {} in ", place), Style::default().fg(Color::Green)));
+                spans.push(Span::raw(format!("{} {} {}", status, log.method(), log.full_url())));
+            }
+            None => spans.push(Span::styled("  not in an earlier response", Style::default().fg(Color::DarkGray))),
+        }
+        segments.push(Segment::Styled(Spans::from(spans)));
+    }
+    segments.push(Segment::Styled(Spans::from("")));
+    segments
+}
+
+/// Messages of a WebSocket connection, one line each, as narrowed by `:ws` and `:find`
+fn ws_segments(app: &App, index: usize) -> Vec<Segment<'static>> {
+    let shown = app.ws_shown(index);
+    let total = app.ws_messages.iter().filter(|m| m.entry == index).count();
+    let mut segments = vec![
+        Segment::Styled(Spans::from("")),
+        Segment::Styled(Spans::from(Span::styled(
+            format!("WebSocket messages [{}] ({} of {}):", app.ws_filter.label(), shown.len(), total),
+            Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD),
+        ))),
+    ];
+    for m in shown {
+        let (arrow, color) = if m.to_server { ("→", Color::Cyan) } else { ("←", Color::Green) };
+        let payload = match m.opcode {
+            2 => format!("{} bytes", m.payload.len()),
+            8 if m.payload.len() >= 2 => format!("{} {}", u16::from_be_bytes([m.payload[0], m.payload[1]]), String::from_utf8_lossy(&m.payload[2..])),
+            _ => String::from_utf8_lossy(&m.payload).chars().take(200).map(|c| if c.is_control() { '.' } else { c }).collect(),
+        };
+        segments.push(Segment::Styled(Spans::from(vec![
+            Span::styled(format!("{:>9.3}s ", m.at.as_secs_f64()), Style::default().fg(Color::DarkGray)),
+            Span::styled(format!("{} {:<6} ", arrow, websocket::opcode_name(m.opcode)), Style::default().fg(color)),
+            Span::raw(payload),
+        ])));
+    }
+    segments
+}
+
+/// Events of a server-sent event stream so far, one line each, as narrowed by `:sse`
+fn sse_segments(app: &App) -> Vec<Segment<'static>> {
+    let events = &app.detail.events;
+    let mut types: Vec<(String, usize)> = Vec::new();
+    for e in events {
+        match types.iter_mut().find(|(t, _)| *t == e.event) {
+            Some((_, n)) => *n += 1,
+            None => types.push((e.event.clone(), 1)),
+        }
+    }
+    let shown: Vec<&sse::Event> = events.iter().filter(|e| app.sse_filter.as_ref().is_none_or(|f| *f == e.event)).collect();
+    let mut segments = vec![
+        Segment::Styled(Spans::from("")),
+        Segment::Styled(Spans::from(Span::styled(
+            format!("Server-sent events [{}] ({} of {}):", app.sse_filter.as_deref().unwrap_or("all"), shown.len(), events.len()),
+            Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD),
+        ))),
+    ];
+    if !types.is_empty() {
+        let list: Vec<String> = types.iter().map(|(t, n)| format!("{} ×{}", t, n)).collect();
+        segments.push(Segment::Styled(Spans::from(Span::styled(
+            format!("  types: {}", list.join(", ")), Style::default().fg(Color::DarkGray),
+        ))));
+    }
+    for e in shown {
+        let data: String = e.data.replace('\n', " ⏎ ").chars().take(200).map(|c| if c.is_control() { '.' } else { c }).collect();
+        segments.push(Segment::Styled(Spans::from(vec![
+            Span::styled(format!("  {:<10} ", e.id.as_deref().unwrap_or("-")), Style::default().fg(Color::DarkGray)),
+            Span::styled(format!("{:<12} ", e.event), Style::default().fg(Color::Green)),
+            Span::raw(data),
+        ])));
+    }
+    segments
+}
+
+/// Captured credentials grouped by host, passwords masked unless revealed
+fn draw_credentials(f: &mut Frame<Backend>, app: &App, area: Rect) {
+    let header = Row::new(["Host", "Source", "Username", "Password", "URL"])
+        .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD));
+    let rows = app.credentials.iter().enumerate().map(|(i, c)| {
+        let password = if app.cred_revealed.contains(&i) { c.password.clone() } else { "•".repeat(8) };
+        let style = if i == app.cred_selected { Style::default().fg(Color::Black).bg(Color::White) } else { Style::default() };
+        Row::new(vec![
+            Cell::from(c.host.clone()),
+            Cell::from(c.source),
+            Cell::from(c.username.clone()),
+            Cell::from(password),
+            Cell::from(c.url.clone()),
+        ]).style(style)
+    }).collect::<Vec<_>>();
+    f.render_widget(
+        Table::new(rows)
+            .header(header)
+            .block(Block::default().borders(Borders::ALL).title("Credentials (r: reveal/mask)"))
+            .widths(&[
+                Constraint::Length(28),
+                Constraint::Length(7),
+                Constraint::Length(24),
+                Constraint::Length(24),
+                Constraint::Min(10),
+            ]),
+        area,
+    );
+}
+
+/// Findings, most severe first; returns the position of the first visible row
+fn draw_issues(f: &mut Frame<Backend>, app: &App, area: Rect) -> usize {
+    let header = Row::new(["Severity", "Host", "Issue", "Detail", "URL"])
+        .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD));
+    let rows = app.issues.iter().map(|i| {
+        let color = match i.severity {
+            Severity::High => Color::Red,
+            Severity::Medium => Color::LightRed,
+            Severity::Low => Color::Yellow,
+            Severity::Info => Color::Blue,
+        };
+        Row::new(vec![
+            Cell::from(i.severity.label()).style(Style::default().fg(color)),
+            Cell::from(i.host.clone()),
+            Cell::from(i.title.clone()),
+            Cell::from(i.detail.lines().next().unwrap_or("").to_string()),
+            Cell::from(i.url.clone()),
+        ])
+    }).collect::<Vec<_>>();
+    // Multi-line details, like a CSP report, get a pane under the table
+    let area = match app.issues.get(app.issue_selected).filter(|i| i.detail.contains('\n')) {
+        Some(issue) => {
+            let height = (issue.detail.lines().count() as u16 + 2).min(area.height / 2);
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(3), Constraint::Length(height)])
+                .split(area);
+            f.render_widget(
+                Paragraph::new(issue.detail.clone())
+                    .block(Block::default().borders(Borders::ALL).title(issue.title.clone()))
+                    .wrap(Wrap { trim: false }),
+                chunks[1],
+            );
+            chunks[0]
+        }
+        None => area,
+    };
+    let mut state = TableState::default();
+    state.select((!app.issues.is_empty()).then_some(app.issue_selected));
+    f.render_stateful_widget(
+        Table::new(rows)
+            .header(header)
+            .block(Block::default().borders(Borders::ALL).title("Issues"))
+            .highlight_style(theme::selection())
+            .widths(&[
+                Constraint::Length(8),
+                Constraint::Length(24),
+                Constraint::Length(32),
+                Constraint::Length(36),
+                Constraint::Length(area.width.saturating_sub(106).max(10)),
+            ]),
+        area,
+        &mut state,
+    );
+    state.offset()
+}
+
+/// Traffic per host; returns the position of the first visible row
+fn draw_hosts(f: &mut Frame<Backend>, app: &App, area: Rect) -> usize {
+    let stats = hosts::summarize(&app.logs, app.host_sort, app.host_sort_reversed);
+    let header = Row::new(["Host", "Requests", "Out", "In", "Errors", "Median"])
+        .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD));
+    let rows = stats.iter().map(|s| {
+        let errors = Cell::from(s.errors.to_string())
+            .style(Style::default().fg(if s.errors > 0 { Color::Red } else { Color::Reset }));
+        Row::new(vec![
+            Cell::from(s.host.clone()),
+            Cell::from(s.requests.to_string()),
+            Cell::from(human_size(s.bytes_out)),
+            Cell::from(human_size(s.bytes_in)),
+            errors,
+            Cell::from(format!("{}ms", s.median.as_millis())),
+        ])
+    }).collect::<Vec<_>>();
+    let descending = (app.host_sort != hosts::HostSort::Host) != app.host_sort_reversed;
+    let title = format!("Hosts [{} {}] (o/O: sort)", app.host_sort.title(), if descending { "↓" } else { "↑" });
+    let mut state = TableState::default();
+    state.select((!stats.is_empty()).then_some(app.host_selected.min(stats.len() - 1)));
+    f.render_stateful_widget(
+        Table::new(rows)
+            .header(header)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(theme::selection())
+            .widths(&[
+                Constraint::Length(area.width.saturating_sub(52).max(20)),
+                Constraint::Length(8),
+                Constraint::Length(8),
+                Constraint::Length(8),
+                Constraint::Length(6),
+                Constraint::Length(8),
+            ]),
+        area,
+        &mut state,
+    );
+    state.offset()
+}
+
+fn draw_sitemap(f: &mut Frame<Backend>, app: &App, area: Rect) -> usize {
+    let tree = &app.sitemap_tree.nodes;
+    let rows = tree.iter().map(|node| {
+        let label = format!("{}{}", "  ".repeat(node.depth), node.label);
+        if node.unvisited() {
+            let source = node.seeded.map_or("", |s| s.label());
+            return Row::new(vec![Cell::from(label), Cell::from(String::new()), Cell::from(String::new()), Cell::from(format!("unvisited ({})", source))])
+                .style(Style::default().fg(Color::DarkGray));
+        }
+        let status = node.entries.last().and_then(|i| app.logs[*i].status());
+        let color = match status {
+            Some(200..=299) => Color::Green,
+            Some(300..=399) => Color::Yellow,
+            Some(_) => Color::Red,
+            None => Color::Reset,
+        };
+        let count = if node.entries.is_empty() { String::new() } else { node.entries.len().to_string() };
+        Row::new(vec![
+            Cell::from(label),
+            Cell::from(count),
+            Cell::from(status.map_or(String::new(), |s| s.to_string())).style(Style::default().fg(color)),
+            Cell::from(node.seeded.map_or(String::new(), |s| format!("also in {}", s.label()))),
+        ])
+    }).collect::<Vec<_>>();
+    let unvisited = tree.iter().filter(|n| n.unvisited()).count();
+    let title = format!("Sitemap ({} paths, {} unvisited)   Enter: Newest entry   :discover: robots.txt and sitemaps", tree.len(), unvisited);
+    let mut state = TableState::default();
+    state.select((!tree.is_empty()).then_some(app.sitemap_selected.min(tree.len().saturating_sub(1))));
+    f.render_stateful_widget(
+        Table::new(rows)
+            .header(Row::new(["Path", "Requests", "Status", "Source"])
+                .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)))
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(theme::selection())
+            .widths(&[
+                Constraint::Length(area.width.saturating_sub(48).max(20)),
+                Constraint::Length(8),
+                Constraint::Length(6),
+                Constraint::Length(26),
+            ]),
+        area,
+        &mut state,
+    );
+    state.offset()
+}
+
+/// Proxy diagnostics, newest at the bottom
+fn draw_events(f: &mut Frame<Backend>, app: &App, area: Rect) {
+    let events = app.events.lock().unwrap();
+    let visible = area.height.saturating_sub(2) as usize;
+    let lines = events.iter().skip(events.len().saturating_sub(visible)).map(|e| {
+        let color = match e.level {
+            Level::ERROR => Color::Red,
+            Level::WARN => Color::Yellow,
+            Level::INFO => Color::Green,
+            _ => Color::DarkGray,
+        };
+        Spans::from(vec![
+            Span::styled(format!("{} ", e.time), Style::default().fg(Color::DarkGray)),
+            Span::styled(format!("{:<5} ", e.level), Style::default().fg(color)),
+            Span::raw(e.message.clone()),
+        ])
+    }).collect::<Vec<_>>();
+    f.render_widget(
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("Events")),
+        area,
+    );
+}
+
+/// Tab clicks, row clicks, wheel scrolling and dragging the list/detail divider
+fn handle_mouse(app: &mut App, hits: &mut Hits, mouse: MouseEvent) {
+    let (col, row) = (mouse.column, mouse.row);
+    let inside = |r: Rect| col >= r.x && col < r.right() && row >= r.y && row < r.bottom();
+    match mouse.kind {
+        MouseEventKind::Down(MouseButton::Left) if row == hits.tab_row => {
+            if let Some((tab, _, _)) = hits.tabs.iter().find(|(_, start, end)| (*start..*end).contains(&col)) {
+                app.tab = *tab;
+            }
+        }
+        // Either border of the divider can be grabbed
+        MouseEventKind::Down(MouseButton::Left)
+            if hits.list.width > 0 && hits.detail.width > 0 && inside(hits.body) && (col + 1 == hits.detail.x || col == hits.detail.x) =>
+        {
+            hits.dragging = true;
+        }
+        MouseEventKind::Down(MouseButton::Left) if inside(hits.sidebar) && row > hits.sidebar.y => {
+            if let Some((item, _)) = filters::items(app).into_iter().nth((row - hits.sidebar.y - 1) as usize) {
+                filters::apply(app, item);
+            }
+        }
+        // Below the top border and the header row, above the bottom border
+        MouseEventKind::Down(MouseButton::Left)
+            if inside(hits.list) && row >= hits.list.y + 2 && row + 1 < hits.list.bottom() =>
+        {
+            app.selection = None;
+            app.select(hits.list_offset + (row - hits.list.y - 2) as usize);
+        }
+        // Inside the borders; the pane stops wrapping once selecting, so rows are lines
+        MouseEventKind::Down(MouseButton::Left)
+            if inside(hits.detail) && app.tab == Tab::Requests && row > hits.detail.y && row + 1 < hits.detail.bottom() && col > hits.detail.x =>
+        {
+            app.selection = Some(Selection::at(detail_point(app, hits, col, row)));
+            hits.selecting = true;
+        }
+        MouseEventKind::Drag(MouseButton::Left) if hits.selecting => {
+            let point = detail_point(app, hits, col, row);
+            if let Some(selection) = app.selection.as_mut() {
+                selection.cursor = point;
+            }
+        }
+        MouseEventKind::Up(MouseButton::Left) if hits.selecting => hits.selecting = false,
+        MouseEventKind::Drag(MouseButton::Left) if hits.dragging && hits.body.width > 0 => {
+            let pct = (col.saturating_sub(hits.body.x) + 1) as u32 * 100 / hits.body.width as u32;
+            app.split = (pct as u16).clamp(MIN_SPLIT, MAX_SPLIT);
+        }
+        // Saved once the drag ends rather than on every step
+        MouseEventKind::Up(MouseButton::Left) if hits.dragging => {
+            hits.dragging = false;
+            app.set_split(app.split);
+        }
+        MouseEventKind::ScrollDown if inside(hits.detail) => app.detail_scroll += 3,
+        MouseEventKind::ScrollUp if inside(hits.detail) => app.detail_scroll = app.detail_scroll.saturating_sub(3),
+        MouseEventKind::ScrollDown if inside(hits.list) => app.next(),
+        MouseEventKind::ScrollUp if inside(hits.list) => app.previous(),
+        _ => {}
+    }
+}
+
+/// Detail pane line and column under a screen position
+fn detail_point(app: &App, hits: &Hits, col: u16, row: u16) -> (usize, usize) {
+    let line = app.detail_scroll + row.saturating_sub(hits.detail.y + 1) as usize;
+    let col = hits.detail_hscroll + col.saturating_sub(hits.detail.x + 1) as usize;
+    (line, col)
+}
+
+/// Moves the detail pane's selection, or acts on what it covers
+fn handle_selection_key(app: &mut App, hits: &Hits, code: KeyCode) {
+    let lines = &hits.detail_lines;
+    let page = hits.detail.height.saturating_sub(2) as isize;
+    let Some(selection) = app.selection.as_mut() else { return };
+    match code {
+        KeyCode::Esc | KeyCode::Char('V') => app.selection = None,
+        KeyCode::Up => selection.shift(lines, -1, 0),
+        KeyCode::Down => selection.shift(lines, 1, 0),
+        KeyCode::Left => selection.shift(lines, 0, -1),
+        KeyCode::Right => selection.shift(lines, 0, 1),
+        KeyCode::PageUp => selection.shift(lines, -page, 0),
+        KeyCode::PageDown => selection.shift(lines, page, 0),
+        KeyCode::Home => selection.line_start(),
+        KeyCode::End => selection.line_end(lines),
+        KeyCode::Char('v') => selection.anchor = selection.cursor,
+        KeyCode::Char('y') | KeyCode::Enter => {
+            let text = selection.text(lines);
+            selection::copy(app, &text);
+        }
+        KeyCode::Char('d') => {
+            let text = selection.text(lines);
+            selection::decode(app, &text);
+        }
+        KeyCode::Char('f') => {
+            let text = selection.text(lines);
+            selection::fuzz(app, &text);
+        }
+        _ => {}
+    }
+    // Keep the cursor on screen
+    if let Some(selection) = &app.selection {
+        let line = selection.cursor.0;
+        let height = page.max(1) as usize;
+        app.detail_scroll = app.detail_scroll.min(line).max((line + 1).saturating_sub(height));
+    }
+}
+
+fn save_selected_body(app: &mut App) {
+    let Some(log) = app.selected_log() else { return };
+    match export::save_body(log, std::path::Path::new(".")) {
+        Ok(path) => {
+            info!("saved response body to {}", path.display());
+            app.set_status(format!("Saved {}", path.display()));
+        }
+        Err(e) => app.set_status(format!("Save failed: {}", e)),
+    }
+}
+
+/// Centered rect taking the given percentage of `area`
+pub fn centered(area: Rect, pct_x: u16, pct_y: u16) -> Rect {
+    let w = area.width * pct_x / 100;
+    let h = area.height * pct_y / 100;
+    Rect::new(area.x + (area.width - w) / 2, area.y + (area.height - h) / 2, w, h)
+}
+
+fn draw_finder(f: &mut Frame<Backend>, app: &App, finder: &Finder, size: Rect) {
+    let area = centered(size, 80, 70);
+    let rows = finder.results.iter().map(|i| {
+        let log = &app.logs[*i];
+        let color = match log.status() {
+            Some(200..=299) => Color::Green,
+            Some(300..=399) => Color::Yellow,
+            Some(_) => Color::Red,
+            None => Color::DarkGray,
+        };
+        Row::new(vec![Cell::from(finder.lines[*i].clone()).style(Style::default().fg(color))])
+    }).collect::<Vec<_>>();
+    let mut state = TableState::default();
+    state.select((!finder.results.is_empty()).then_some(finder.selected));
+    let title = format!(
+        "Find entry: {}_   ({} of {})   ↑↓: Select   Enter: Jump   Esc: Close",
+        finder.query, finder.results.len(), finder.lines.len(),
+    );
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(
+        Table::new(rows)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(theme::selection())
+            .widths(&[Constraint::Length(area.width.saturating_sub(2))]),
+        area,
+        &mut state,
+    );
+}
+
+fn draw_palette(f: &mut Frame<Backend>, palette: &Palette, size: Rect) {
+    let area = centered(size, 70, 70);
+    let rows = palette.results.iter().map(|i| {
+        let color = match palette.items[*i].0 {
+            Target::Key(_) => Color::Reset,
+            Target::Command(_) => Color::Cyan,
+        };
+        Row::new(vec![Cell::from(palette.items[*i].1.clone()).style(Style::default().fg(color))])
+    }).collect::<Vec<_>>();
+    let mut state = TableState::default();
+    state.select((!palette.results.is_empty()).then_some(palette.selected));
+    let title = format!(
+        "Run: {}_   ({} of {})   ↑↓: Select   Enter: Run   Esc: Close",
+        palette.query, palette.results.len(), palette.items.len(),
+    );
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(
+        Table::new(rows)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(theme::selection())
+            .widths(&[Constraint::Length(area.width.saturating_sub(2))]),
+        area,
+        &mut state,
+    );
+}
+
+fn draw_intercept(f: &mut Frame<Backend>, paused: &Paused, held: usize, size: Rect) {
+    let area = centered(size, 80, 80);
+    let table = &paused.table;
+    let width = table.rows.iter().map(|(k, _)| k.chars().count()).max().unwrap_or(0).max(12);
+    let selected = Style::default().fg(Color::Black).bg(Color::White);
+    let typing = |text: &str| Span::styled(format!("{}_", text), Style::default().fg(Color::Yellow));
+    let mut lines = vec![match (&table.editing, table.selected) {
+        (Some((_, input)), 0) => Spans::from(typing(input)),
+        (_, 0) => Spans::from(Span::styled(table.request_line.clone(), selected.add_modifier(Modifier::BOLD))),
+        _ => Spans::from(Span::styled(table.request_line.clone(), Style::default().add_modifier(Modifier::BOLD))),
+    }];
+    for (i, (name, value)) in table.rows.iter().enumerate().map(|(i, r)| (i + 1, r)) {
+        let name_cell = format!("{:<width$}  ", name, width = width);
+        lines.push(match (&table.editing, i == table.selected) {
+            (Some((Field::Name, input)), true) => Spans::from(typing(input)),
+            (Some((Field::Value, input)), true) => Spans::from(vec![Span::styled(name_cell, Style::default().fg(Color::Cyan)), typing(input)]),
+            (None, true) => Spans::from(Span::styled(format!("{}{}", name_cell, value), selected)),
+            _ => Spans::from(vec![Span::styled(name_cell, Style::default().fg(Color::Cyan)), Span::raw(value.clone())]),
+        });
+    }
+    lines.push(Spans::from(""));
+    let chunked = table.rows.iter().any(|(k, v)| k.eq_ignore_ascii_case("transfer-encoding") && v.to_lowercase().contains("chunked"));
+    let note = if chunked { "chunked, sent as is" } else { "Content-Length is set on forward" };
+    lines.push(Spans::from(Span::styled(
+        format!("Body: {} bytes ({})", table.body.len(), note), Style::default().fg(Color::DarkGray),
+    )));
+    let body = String::from_utf8_lossy(&table.body);
+    lines.extend(body.lines().take(area.height.saturating_sub(lines.len() as u16 + 3) as usize).map(|l| Spans::from(l.to_string())));
+    let keys = match table.editing {
+        Some(_) => "Enter: Save   Esc: Cancel",
+        None => "Enter: Edit value   n: Edit name   a: Add   d: Delete   E/B: Request/body in $EDITOR   f: Forward   x: Drop   I: Intercept off",
+    };
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title(format!("Intercepted: {} ({} held)   {}", paused.host, held, keys))),
+        area,
+    );
+}
+
+fn draw_passthrough_editor(f: &mut Frame<Backend>, app: &App, editor: &PassthroughEditor, size: Rect) {
+    let area = centered(size, 50, 60);
+    let mut lines = app.passthrough.hosts.iter().enumerate().map(|(i, h)| {
+        let style = if i == editor.selected { Style::default().fg(Color::Black).bg(Color::White) } else { Style::default() };
+        Spans::from(Span::styled(h.clone(), style))
+    }).collect::<Vec<_>>();
+    if lines.is_empty() {
+        lines.push(Spans::from(Span::styled("(no passthrough hosts)", Style::default().fg(Color::DarkGray))));
+    }
+    lines.push(Spans::from(""));
+    match &editor.input {
+        Some(input) => lines.push(Spans::from(vec![
+            Span::styled("Add host: ", Style::default().fg(Color::Cyan)),
+            Span::raw(format!("{}_", input)),
+        ])),
+        None => lines.push(Spans::from(Span::styled(
+            "a: Add   d: Remove   Esc: Close", Style::default().fg(Color::DarkGray),
+        ))),
+    }
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title("Passthrough hosts (not intercepted)")),
+        area,
+    );
+}
+
+fn draw_recon(f: &mut Frame<Backend>, view: &ReconView, size: Rect) {
+    let area = centered(size, 80, 80);
+    let rows = view.found.iter().map(|found| {
+        let scope = if found.in_scope { Cell::from("in") } else { Cell::from("out").style(Style::default().fg(Color::DarkGray)) };
+        Row::new(vec![
+            Cell::from(found.kind.label()),
+            scope,
+            Cell::from(found.count.to_string()),
+            Cell::from(found.value.clone()),
+        ])
+    }).collect::<Vec<_>>();
+    let domains = view.found.iter().filter(|f| f.kind == recon::Kind::Domain).count();
+    let title = format!(
+        "URLs, domains and IPs in responses ({} found, {} domains)   w: Export   Esc: Close",
+        view.found.len(), domains,
+    );
+    let mut state = TableState::default();
+    state.select((!view.found.is_empty()).then_some(view.selected));
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(
+        Table::new(rows)
+            .header(Row::new(["Kind", "Scope", "Seen", "Value"])
+                .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)))
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(theme::selection())
+            .widths(&[
+                Constraint::Length(6),
+                Constraint::Length(5),
+                Constraint::Length(5),
+                Constraint::Length(area.width.saturating_sub(21).max(10)),
+            ]),
+        area,
+        &mut state,
+    );
+}
+
+/// Repeat run results: totals, status per request and a latency sparkline
+fn draw_repeat(f: &mut Frame<Backend>, run: &RepeatRun, size: Rect) {
+    let area = centered(size, 80, 60);
+    let state = if run.running { "running" } else { "finished" };
+    let block = Block::default().borders(Borders::ALL)
+        .title(format!("Repeat {} ({}, {})   s: Stop   Esc: Hide", run.url, run.plan, state));
+    let inner = block.inner(area);
+    f.render_widget(Clear, area);
+    f.render_widget(block, area);
+    let parts = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(4), Constraint::Min(3)])
+        .split(inner);
+
+    let mut counts: Vec<(Option<u16>, usize)> = Vec::new();
+    for s in &run.samples {
+        match counts.iter_mut().find(|(status, _)| *status == s.status) {
+            Some((_, n)) => *n += 1,
+            None => counts.push((s.status, 1)),
+        }
+    }
+    counts.sort();
+    let color = |status: Option<u16>| match status {
+        Some(200..=399) => Color::Green,
+        Some(400..=499) => Color::Yellow,
+        _ => Color::Red,
+    };
+    let mut summary: Vec<Span> = vec![Span::raw(format!("{} sent   ", run.samples.len()))];
+    for (status, n) in &counts {
+        let label = status.map_or("failed".to_string(), |s| s.to_string());
+        summary.push(Span::styled(format!("{} ×{}   ", label, n), Style::default().fg(color(*status))));
+    }
+    let ms = |q: f64| run.latency(q).map_or("-".to_string(), |d| format!("{}ms", d.as_millis()));
+    let latency = format!("latency min {}   median {}   p95 {}   max {}", ms(0.0), ms(0.5), ms(0.95), ms(1.0));
+    let width = inner.width as usize;
+    let strip: Vec<Span> = run.samples.iter().skip(run.samples.len().saturating_sub(width))
+        .map(|s| Span::styled("▮", Style::default().fg(color(s.status))))
+        .collect();
+    let elapsed = run.samples.iter().map(|s| s.at + s.latency).max().unwrap_or_default().as_secs_f64();
+    f.render_widget(
+        Paragraph::new(vec![
+            Spans::from(summary),
+            Spans::from(format!("{}   over {:.1}s", latency, elapsed)),
+            Spans::from(Span::styled("Status per request, oldest first:", Style::default().fg(Color::DarkGray))),
+            Spans::from(strip),
+        ]),
+        parts[0],
+    );
+    let data: Vec<u64> = run.samples.iter().skip(run.samples.len().saturating_sub(width))
+        .map(|s| s.latency.as_millis() as u64)
+        .collect();
+    f.render_widget(
+        Sparkline::default()
+            .block(Block::default().borders(Borders::TOP).title(format!("Latency per request (peak {}ms)", data.iter().max().unwrap_or(&0))))
+            .data(&data)
+            .style(Style::default().fg(Color::Cyan)),
+        parts[1],
+    );
+}
+
+fn draw_authz(f: &mut Frame<Backend>, matrix: &AuthMatrix, size: Rect) {
+    let area = centered(size, 90, 80);
+    let state = if matrix.running { "running" } else { "done" };
+    let header = Row::new(std::iter::once("Request".to_string()).chain(matrix.columns.iter().cloned()))
+        .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD));
+    let rows = matrix.rows.iter().skip(matrix.scroll).map(|row| {
+        let mut cells = vec![Cell::from(format!("{} {}", row.method, row.url))];
+        for (column, cell) in row.cells.iter().enumerate() {
+            let (text, style) = match cell {
+                None => ("…".to_string(), Style::default().fg(Color::DarkGray)),
+                Some(c) => {
+                    let text = format!("{} {}", c.status.map_or("failed".to_string(), |s| s.to_string()), human_size(c.len));
+                    let style = match c.status {
+                        _ if matrix.suspicious(row, column) => Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                        Some(401 | 403) => Style::default().fg(Color::Green),
+                        None => Style::default().fg(Color::DarkGray),
+                        _ => Style::default(),
+                    };
+                    (text, style)
+                }
+            };
+            cells.push(Cell::from(text).style(style));
+        }
+        Row::new(cells)
+    }).collect::<Vec<_>>();
+    let mut widths = vec![Constraint::Length(area.width.saturating_sub(2 + 14 * matrix.columns.len() as u16).max(20))];
+    widths.extend(matrix.columns.iter().map(|_| Constraint::Length(13)));
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Table::new(rows)
+            .header(header)
+            .block(Block::default().borders(Borders::ALL).title(format!(
+                "Auth matrix: {} requests ({}; red = same answer as the captured session)   ↑↓: Scroll   s: Stop   Esc: Hide",
+                matrix.rows.len(), state
+            )))
+            .widths(&widths),
+        area,
+    );
+}
+
+fn draw_smuggle(f: &mut Frame<Backend>, run: &SmuggleRun, size: Rect) {
+    let area = centered(size, 80, 70);
+    let state = if run.running { "running" } else { "done" };
+    let outcome = |o: &Outcome| {
+        let answer = match (o.status, o.timed_out) {
+            (_, true) => "no answer".to_string(),
+            (None, false) => "failed".to_string(),
+            (Some(s), false) => format!("{} {}", s, human_size(o.len)),
+        };
+        (answer, format!("{} ms", o.elapsed.as_millis()))
+    };
+    let mut rows = Vec::new();
+    if let Some(base) = &run.baseline {
+        let (answer, time) = outcome(base);
+        rows.push(Row::new(vec!["baseline".to_string(), "as captured".to_string(), answer, time]).style(Style::default().fg(Color::DarkGray)));
+    }
+    rows.extend(run.probes.iter().map(|p| {
+        let (answer, time, style) = match (&p.outcome, p.skipped) {
+            (_, true) => ("skipped".to_string(), String::new(), Style::default().fg(Color::DarkGray)),
+            (None, false) => ("…".to_string(), String::new(), Style::default().fg(Color::DarkGray)),
+            (Some(o), false) => {
+                let (answer, time) = outcome(o);
+                let style = if run.suspicious(p) { Style::default().fg(Color::Red).add_modifier(Modifier::BOLD) } else { Style::default() };
+                (answer, time, style)
+            }
+        };
+        let confirm = match &p.confirm {
+            Some((_, o)) if run.confirmed(p) => format!("{}: desync", outcome(o).0),
+            Some((_, o)) => format!("{}: as usual", outcome(o).0),
+            None if run.confirming && run.suspicious(p) => "…".to_string(),
+            None => String::new(),
+        };
+        Row::new(vec![p.technique.label().to_string(), p.form.to_string(), answer, time, confirm]).style(style)
+    }));
+    let mut table = TableState::default();
+    table.select(Some(run.selected + run.baseline.is_some() as usize));
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(
+        Table::new(rows)
+            .header(Row::new(["Probe", "Transfer-Encoding", "Answer", "Time", "Next request"])
+                .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)))
+            .block(Block::default().borders(Borders::ALL).title(format!(
+                "Smuggling probes: {} ({}; red = no answer, a likely desync)   ↑↓: Select   r: Repeater   s: Stop   Esc: Hide",
+                run.url, state
+            )))
+            .highlight_style(theme::selection())
+            .widths(&[Constraint::Length(8), Constraint::Length(22), Constraint::Length(16), Constraint::Length(10), Constraint::Length(24)]),
+        area,
+        &mut table,
+    );
+}
+
+fn draw_diagnosis(f: &mut Frame<Backend>, diagnosis: &Diagnosis, size: Rect) {
+    let area = centered(size, 80, 60);
+    let lines: Vec<Spans> = match diagnosis.running {
+        true => vec![Spans::from(Span::styled("Fetching directly, through belch, then directly again…", Style::default().fg(Color::DarkGray)))],
+        false => diagnosis.lines.iter().skip(diagnosis.scroll).map(|(kind, text)| {
+            let style = match kind {
+                Kind::Info => Style::default(),
+                Kind::Same => Style::default().fg(Color::Green),
+                Kind::Noise => Style::default().fg(Color::DarkGray),
+                Kind::Changed => Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            };
+            Spans::from(Span::styled(text.clone(), style))
+        }).collect(),
+    };
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title(format!("Direct vs through belch: {}   ↑↓: Scroll   Esc: Close", diagnosis.url)))
+            .wrap(Wrap { trim: false }),
+        area,
+    );
+}
+
+fn draw_diff(f: &mut Frame<Backend>, view: &DiffView, size: Rect) {
+    let area = centered(size, 90, 80);
+    let lines: Vec<Spans> = view.lines.iter().skip(view.scroll).map(|(change, text)| match change {
+        Change::Same => Spans::from(Span::styled(format!("  {}", text), Style::default().fg(Color::DarkGray))),
+        Change::Removed => Spans::from(Span::styled(format!("- {}", text), Style::default().fg(Color::Red))),
+        Change::Added => Spans::from(Span::styled(format!("+ {}", text), Style::default().fg(Color::Green))),
+    }).collect();
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title(format!("Diff {} ({} line(s) differ)   ↑↓/PgUp/PgDn: Scroll   Esc: Close", view.title, view.changes()))),
+        area,
+    );
+}
+
+fn draw_cookie_jar(f: &mut Frame<Backend>, jar: &CookieJar, size: Rect) {
+    let area = centered(size, 80, 70);
+    let rows = jar.rows.iter().map(|row| {
+        let pinned = match &row.pinned {
+            Some(value) => Cell::from(value.clone()).style(Style::default().fg(Color::Yellow)),
+            None => Cell::from(""),
+        };
+        Row::new(vec![Cell::from(row.domain.clone()), Cell::from(row.name.clone()), Cell::from(row.value.clone()), pinned])
+    }).collect::<Vec<_>>();
+    let pins = jar.rows.iter().filter(|r| r.pinned.is_some()).count();
+    let title = format!("Cookie jar ({} cookies, {} pinned)   p: Pin seen value   e: Edit pin   d: Unpin   Esc: Close", jar.rows.len(), pins);
+    let value_width = area.width.saturating_sub(50).max(20) / 2;
+    let mut state = TableState::default();
+    state.select((!jar.rows.is_empty()).then_some(jar.selected));
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(
+        Table::new(rows)
+            .header(Row::new(["Domain", "Name", "Seen", "Pinned"])
+                .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)))
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(theme::selection())
+            .widths(&[Constraint::Length(24), Constraint::Length(20), Constraint::Length(value_width), Constraint::Length(value_width)]),
+        area,
+        &mut state,
+    );
+}
+
+fn handle_cookie_jar_key(app: &mut App, code: KeyCode) {
+    let Some(jar) = app.cookie_jar.as_mut() else { return };
+    let last = jar.rows.len().saturating_sub(1);
+    match code {
+        KeyCode::Esc => return app.cookie_jar = None,
+        KeyCode::Up => jar.selected = jar.selected.saturating_sub(1),
+        KeyCode::Down => jar.selected = (jar.selected + 1).min(last),
+        KeyCode::PageUp => jar.selected = jar.selected.saturating_sub(20),
+        KeyCode::PageDown => jar.selected = (jar.selected + 20).min(last),
+        _ => {}
+    }
+    let Some(row) = jar.rows.get(jar.selected) else { return };
+    let (domain, name, value) = (row.domain.clone(), row.name.clone(), row.pinned.clone().unwrap_or_else(|| row.value.clone()));
+    let result = match code {
+        KeyCode::Char('p') if value.is_empty() => Err(format!("No value of {} seen to pin", name)),
+        KeyCode::Char('p') => app.cookie_overrides.set(&domain, &name, &value).map(|_| format!("Requests to {} send {}={}", domain, name, value)),
+        KeyCode::Char('d') => app.cookie_overrides.remove(&domain, &name).map(|removed| match removed {
+            true => format!("{} on {} is the browser's again", name, domain),
+            false => format!("{} on {} isn't pinned", name, domain),
+        }),
+        KeyCode::Char('e') => {
+            app.cookie_jar = None;
+            app.command = Some(format!("cookie set {} {} {}", domain, name, value));
+            return;
+        }
+        _ => return,
+    };
+    let selected = jar.selected;
+    let mut refreshed = CookieJar::collect(app.logs.iter(), &app.cookie_overrides);
+    refreshed.selected = selected.min(refreshed.rows.len().saturating_sub(1));
+    app.cookie_jar = Some(refreshed);
+    match result {
+        Ok(msg) | Err(msg) => app.set_status(msg),
+    }
+}
+
+fn draw_send_to(f: &mut Frame<Backend>, app: &App, menu: &Menu, size: Rect) {
+    let width = (size.width * 6 / 10).clamp(40.min(size.width), size.width);
+    let height = (Action::ALL.len() as u16 + 2).min(size.height);
+    let area = Rect::new(size.x + (size.width - width) / 2, size.y + (size.height - height) / 2, width, height);
+    let lines: Vec<Spans> = Action::ALL.iter().enumerate().map(|(i, action)| {
+        let style = if i == menu.selected { Style::default().fg(Color::Black).bg(Color::White) } else { Style::default() };
+        Spans::from(vec![
+            Span::styled(format!(" {}  ", action.key()), style.fg(Color::Cyan)),
+            Span::styled(action.label(app, menu.index), style),
+        ])
+    }).collect();
+    let url = app.logs.get(menu.index).map_or("", |l| l.url.as_str());
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(format!("Send {}   ↑↓ Enter or key   Esc: Close", url))),
+        area,
+    );
+}
+
+fn handle_send_to_key(app: &mut App, code: KeyCode) {
+    let Some(menu) = app.send_to.as_mut() else { return };
+    let action = match code {
+        KeyCode::Esc => return app.send_to = None,
+        KeyCode::Up => return menu.selected = menu.selected.saturating_sub(1),
+        KeyCode::Down => return menu.selected = (menu.selected + 1).min(Action::ALL.len() - 1),
+        KeyCode::Enter => Action::ALL[menu.selected],
+        KeyCode::Char(c) => match Action::ALL.iter().find(|a| a.key() == c) {
+            Some(action) => *action,
+            None => return,
+        },
+        _ => return,
+    };
+    let index = menu.index;
+    send_to::run(app, index, action);
+}
+
+fn draw_decoder(f: &mut Frame<Backend>, view: &DecoderView, size: Rect) {
+    let area = centered(size, 80, 80);
+    let mut lines = Vec::new();
+    for d in &view.decoded {
+        lines.push(Spans::from(Span::styled(d.label.clone(), Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))));
+        for (name, value) in &d.fields {
+            lines.push(Spans::from(vec![
+                Span::styled(format!("  {:<16}", name), Style::default().fg(Color::DarkGray)),
+                Span::raw(value.clone()),
+            ]));
+        }
+        lines.push(Spans::from(""));
+    }
+    if lines.is_empty() {
+        lines.push(Spans::from(Span::styled(
+            "No certificates, certificate requests or public keys found. Paste PEM text to decode it.",
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+    let scroll = view.scroll.min(lines.len().saturating_sub(1)) as u16;
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title(format!(
+                "Certificates & keys in {}   paste PEM to decode   Esc: Close",
+                view.source,
+            )))
+            .wrap(Wrap { trim: false })
+            .scroll((scroll, 0)),
+        area,
+    );
+}
+
+/// Decoder tab: the input, then each stage's output in turn
+fn draw_pipeline(f: &mut Frame<Backend>, pipeline: &mut Pipeline, area: Rect) {
+    let height = (pipeline.input.lines.len() as u16 + 2).clamp(3, area.height / 3);
+    let parts = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(height), Constraint::Min(0)])
+        .split(area);
+    pipeline.input.follow(height.saturating_sub(2) as usize);
+    let title = if pipeline.editing {
+        editor_title(&pipeline.input, "Input (editing)   Ctrl+Z/Y: Undo/Redo   Ctrl+W: Find   Esc: Done".to_string())
+    } else {
+        "Input   Enter: Edit   paste to replace".to_string()
+    };
+    f.render_widget(
+        Paragraph::new(editor_lines(&pipeline.input, pipeline.editing)).block(Block::default().borders(Borders::ALL).title(title)),
+        parts[0],
+    );
+    let mut lines = Vec::new();
+    let stages = pipeline.stages.clone();
+    for (i, (stage, result)) in stages.iter().zip(pipeline.run()).enumerate() {
+        match result {
+            Ok(out) => {
+                lines.push(Spans::from(Span::styled(
+                    format!("{}. {} ({})", i + 1, stage.label(), human_size(out.len())),
+                    Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+                )));
+                lines.extend(transform::display(out).lines().map(|l| Spans::from(l.to_string())));
+            }
+            Err(e) => {
+                lines.push(Spans::from(Span::styled(format!("{}. {}", i + 1, stage.label()), Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))));
+                lines.push(Spans::from(Span::styled(format!("failed: {}; later stages skipped", e), Style::default().fg(Color::Red))));
+            }
+        }
+        lines.push(Spans::from(""));
+    }
+    if pipeline.stages.is_empty() {
+        lines.push(Spans::from(Span::styled("No stages yet: press a key below to add one", Style::default().fg(Color::DarkGray))));
+    }
+    pipeline.scroll = pipeline.scroll.min(lines.len().saturating_sub(1));
+    let keys: Vec<String> = Transform::ALL.iter().map(|t| format!("{} {}", t.key(), t.name())).collect();
+    f.render_widget(
+        Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title(format!(
+                "Stages   Backspace: Drop last   Delete: Clear   {}   PgUp/PgDn: Scroll",
+                keys.join("  "),
+            )))
+            .wrap(Wrap { trim: false })
+            .scroll((pipeline.scroll as u16, 0)),
+        parts[1],
+    );
+}
+
+/// Decoder tab keys; false for keys the tab leaves to the rest of the app
+fn handle_pipeline_key(app: &mut App, key: KeyEvent) -> bool {
+    let pipeline = &mut app.pipeline;
+    if pipeline.editing {
+        match key.code {
+            KeyCode::Esc if pipeline.input.search.is_none() => pipeline.editing = false,
+            _ => {
+                if let Some(note) = edit_text(&mut pipeline.input, key) {
+                    app.set_status(note);
+                }
+            }
+        }
+        return true;
+    }
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        return false;
+    }
+    match key.code {
+        KeyCode::Enter => pipeline.editing = true,
+        KeyCode::Backspace => {
+            pipeline.stages.pop();
+        }
+        KeyCode::Delete => pipeline.stages.clear(),
+        KeyCode::PageUp => pipeline.scroll = pipeline.scroll.saturating_sub(20),
+        KeyCode::PageDown => pipeline.scroll += 20,
+        KeyCode::Char(c) => match Transform::ALL.into_iter().find(|t| t.key() == c) {
+            Some(stage) => pipeline.stages.push(stage),
+            None => return false,
+        },
+        _ => return false,
+    }
+    true
+}
+
+/// Visible lines of a text area, with the cursor cell highlighted when it has focus
+fn editor_lines(editor: &TextArea, focused: bool) -> Vec<Spans<'static>> {
+    let cursor = Style::default().fg(Color::Black).bg(Color::White);
+    editor.lines.iter().enumerate().skip(editor.scroll).map(|(i, line)| {
+        if i != editor.row || !focused {
+            return Spans::from(line.clone());
+        }
+        let chars: Vec<char> = line.chars().collect();
+        let at = chars.get(editor.col).map_or(" ".to_string(), char::to_string);
+        Spans::from(vec![
+            Span::raw(chars[..editor.col].iter().collect::<String>()),
+            Span::styled(at, cursor),
+            Span::raw(chars.get(editor.col + 1..).unwrap_or_default().iter().collect::<String>()),
+        ])
+    }).collect()
+}
+
+fn draw_repeater(f: &mut Frame<Backend>, repeater: &mut Repeater, size: Rect) {
+    let area = centered(size, 90, 85);
+    let halves = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+        .split(area);
+    repeater.editor.follow(halves[0].height.saturating_sub(2) as usize);
+    let lines = editor_lines(&repeater.editor, true);
+    let scheme = if repeater.tls { "https" } else { "http" };
+    let mode = if repeater.raw { "raw bytes: \\r \\n \\xNN escapes, trailing \\ joins lines" } else { "HTTP: Content-Length fixed on send" };
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(editor_title(&repeater.editor, format!(
+            "Repeater: {}://{}:{} ({})   Ctrl+S: Send   Ctrl+T: TLS   Ctrl+R: Raw   Ctrl+F: § fuzz marker   Ctrl+Z/Y: Undo/Redo   Ctrl+W: Find   Ctrl+E: $EDITOR   Esc: Hide",
+            scheme, repeater.host, repeater.port, mode,
+        )))),
+        halves[0],
+    );
+    let (title, text, style) = match &repeater.response {
+        _ if repeater.sending => ("Response: sending…".to_string(), String::new(), Style::default()),
+        None => ("Response".to_string(), String::new(), Style::default()),
+        Some(Err(reason)) => ("Response: failed".to_string(), reason.clone(), Style::default().fg(Color::Red)),
+        Some(Ok((raw, elapsed))) => {
+            let (head, _) = http::split_raw(raw);
+            let status = http::status_code(&String::from_utf8_lossy(head)).map_or("no status".to_string(), |s| s.to_string());
+            (
+                format!("Response: {}, {}, {} ms   PgUp/PgDn: Scroll", status, human_size(raw.len()), elapsed.as_millis()),
+                String::from_utf8_lossy(raw).replace("\r\n", "\n"),
+                Style::default(),
+            )
+        }
+    };
+    f.render_widget(
+        Paragraph::new(text)
+            .style(style)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .wrap(Wrap { trim: false })
+            .scroll((repeater.scroll as u16, 0)),
+        halves[1],
+    );
+}
+
+/// Fuzz results, grouped by similar answers or one row each, and the selected answer
+fn draw_fuzz(f: &mut Frame<Backend>, run: &FuzzRun, size: Rect) {
+    let area = centered(size, 90, 85);
+    let halves = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+        .split(area);
+    let mut cluster_of = vec![0; run.attempts.len()];
+    for (c, cluster) in run.clusters.iter().enumerate() {
+        for &m in &cluster.members {
+            cluster_of[m] = c;
+        }
+    }
+    let row = |count: String, a: &fuzzer::Attempt, payloads: String, rare: bool| {
+        let color = match a.status {
+            Some(200..=399) => Color::Green,
+            Some(400..=499) => Color::Yellow,
+            _ => Color::Red,
+        };
+        let style = if rare { Style::default().add_modifier(Modifier::BOLD) } else { Style::default() };
+        Row::new(vec![
+            Cell::from(count),
+            Cell::from(a.status.map_or("failed".to_string(), |s| s.to_string())).style(Style::default().fg(color)),
+            Cell::from(human_size(a.len)),
+            Cell::from(format!("{} ms", a.elapsed.as_millis())),
+            Cell::from(payloads),
+        ]).style(style)
+    };
+    let rows: Vec<Row> = if run.grouped {
+        run.ranked().into_iter().map(|c| {
+            let cluster = &run.clusters[c];
+            let mut payloads: Vec<&str> = cluster.members.iter().take(5).map(|&m| run.attempts[m].payload.as_str()).collect();
+            if cluster.members.len() > 5 {
+                payloads.push("…");
+            }
+            row(format!("×{}", cluster.members.len()), &run.attempts[cluster.members[0]], payloads.join(", "), run.rare(cluster))
+        }).collect()
+    } else {
+        run.attempts.iter().enumerate().map(|(i, a)| {
+            row(format!("#{}", i + 1), a, a.payload.clone(), run.rare(&run.clusters[cluster_of[i]]))
+        }).collect()
+    };
+    let state_label = if run.running { "running" } else { "done" };
+    let title = format!(
+        "Fuzz {} with {}: {}/{} sent, {} kinds of answer ({}; bold = rare)   g: {}   ↑↓: Select   s: Stop   Esc: Hide",
+        run.url, run.source, run.attempts.len(), run.total, run.clusters.len(), state_label,
+        if run.grouped { "Every answer" } else { "Group similar" },
+    );
+    let mut state = TableState::default();
+    state.select((run.rows() > 0).then_some(run.selected.min(run.rows().saturating_sub(1))));
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(
+        Table::new(rows)
+            .header(Row::new([if run.grouped { "Count" } else { "#" }, "Status", "Length", "Time", "Payload"])
+                .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)))
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(theme::selection())
+            .widths(&[
+                Constraint::Length(7),
+                Constraint::Length(6),
+                Constraint::Length(9),
+                Constraint::Length(9),
+                Constraint::Length(halves[0].width.saturating_sub(37).max(10)),
+            ]),
+        halves[0],
+        &mut state,
+    );
+    let (title, text, style) = match run.current() {
+        None => ("Response".to_string(), String::new(), Style::default()),
+        Some(a) => match &a.response {
+            Err(reason) => (format!("Response to {}: failed", a.payload), reason.clone(), Style::default().fg(Color::Red)),
+            Ok(Some(raw)) => (
+                format!("Response to {}   PgUp/PgDn: Scroll", a.payload),
+                String::from_utf8_lossy(raw).replace("\r\n", "\n"),
+                Style::default(),
+            ),
+            Ok(None) => (
+                format!("Response to {}", a.payload),
+                "Only the first answer of each kind is kept here; this one is in the request list (:origin fuzzer)".to_string(),
+                Style::default().fg(Color::DarkGray),
+            ),
+        },
+    };
+    f.render_widget(
+        Paragraph::new(text)
+            .style(style)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .wrap(Wrap { trim: false })
+            .scroll((run.scroll as u16, 0)),
+        halves[1],
+    );
+}
+
+/// Payload lists on the left, the selected one's payloads on the right
+fn draw_payloads(f: &mut Frame<Backend>, view: &PayloadsView, size: Rect) {
+    let area = centered(size, 80, 70);
+    let halves = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(area);
+    let rows = view.lists.iter().map(|l| {
+        let style = if l.builtin { Style::default() } else { Style::default().fg(Color::Cyan) };
+        Row::new(vec![l.name.clone(), l.items.len().to_string(), l.about.clone()]).style(style)
+    }).collect::<Vec<_>>();
+    let mut state = TableState::default();
+    state.select((!view.lists.is_empty()).then_some(view.selected));
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(
+        Table::new(rows)
+            .header(Row::new(["List", "Items", "About"])
+                .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)))
+            .block(Block::default().borders(Borders::ALL).title("Payload lists   d: Delete imported   Esc: Close"))
+            .highlight_style(theme::selection())
+            .widths(&[Constraint::Length(14), Constraint::Length(6), Constraint::Length(halves[0].width.saturating_sub(24).max(10))]),
+        halves[0],
+        &mut state,
+    );
+    let items: Vec<Spans> = view.lists.get(view.selected).map_or(Vec::new(), |l| {
+        l.items.iter().enumerate().skip(view.scroll).map(|(i, item)| Spans::from(vec![
+            Span::styled(format!("{:>5} ", i + 1), Style::default().fg(Color::DarkGray)),
+            Span::raw(item.clone()),
+        ])).collect()
+    });
+    f.render_widget(
+        Paragraph::new(items).block(Block::default().borders(Borders::ALL).title("Preview (:fuzz NAME sends these)   PgUp/PgDn: Scroll")),
+        halves[1],
+    );
+}
+
+fn handle_payloads_key(app: &mut App, code: KeyCode) {
+    let Some(view) = app.payloads.as_mut() else { return };
+    match code {
+        KeyCode::Esc => app.payloads = None,
+        KeyCode::Up => {
+            view.selected = view.selected.saturating_sub(1);
+            view.scroll = 0;
+        }
+        KeyCode::Down => {
+            view.selected = (view.selected + 1).min(view.lists.len().saturating_sub(1));
+            view.scroll = 0;
+        }
+        KeyCode::PageUp => view.scroll = view.scroll.saturating_sub(20),
+        KeyCode::PageDown => {
+            let len = view.lists.get(view.selected).map_or(0, |l| l.items.len());
+            view.scroll = (view.scroll + 20).min(len.saturating_sub(1));
+        }
+        KeyCode::Char('d') => {
+            let Some(list) = view.lists.get(view.selected).filter(|l| !l.builtin) else {
+                return app.set_status("Built-in lists can't be deleted");
+            };
+            let name = list.name.clone();
+            match payloads::remove(&name) {
+                Ok(()) => {
+                    *view = PayloadsView::load(None);
+                    app.set_status(format!("Deleted payload list `{}`", name));
+                }
+                Err(e) => app.set_status(e),
+            }
+        }
+        _ => {}
+    }
+}
+
+fn handle_repeater_key(app: &mut App, shared: Arc<Mutex<App>>, key: KeyEvent) {
+    let Some(repeater) = app.repeater.as_mut() else { return };
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    match key.code {
+        _ if repeater.editor.search.is_some() => {}
+        KeyCode::Char('s') if ctrl => return repeater::send(app, shared),
+        KeyCode::Char('t') if ctrl => return repeater.toggle_tls(),
+        KeyCode::Char('r') if ctrl => return repeater.raw = !repeater.raw,
+        KeyCode::Char('f') if ctrl => return repeater.editor.insert(fuzzer::MARKER),
+        KeyCode::Char('e') if ctrl => return app.external_edit = Some(External::Repeater),
+        KeyCode::Esc => return app.show_repeater = false,
+        KeyCode::PageUp => return repeater.scroll = repeater.scroll.saturating_sub(20),
+        KeyCode::PageDown => return repeater.scroll += 20,
+        _ => {}
+    }
+    if let Some(note) = edit_text(&mut repeater.editor, key) {
+        app.set_status(note);
+    }
+}
+
+/// Keys shared by the multi-line editors: typing, movement, Ctrl+Z/Y undo and redo, Ctrl+W find
+/// and Ctrl+N next match; returns a note for the status line
+fn edit_text(editor: &mut TextArea, key: KeyEvent) -> Option<String> {
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    if let Some(search) = editor.search.as_mut() {
+        match key.code {
+            KeyCode::Char(c) => search.push(c),
+            KeyCode::Backspace => { search.pop(); }
+            KeyCode::Esc => editor.search = None,
+            KeyCode::Enter => {
+                editor.query = editor.search.take().unwrap_or_default();
+                if !editor.find_next() {
+                    return Some(format!("No match for `{}`", editor.query));
+                }
+            }
+            _ => {}
+        }
+        return None;
+    }
+    match key.code {
+        KeyCode::Char('z') if ctrl => return (!editor.undo()).then(|| "Nothing to undo".to_string()),
+        KeyCode::Char('y') if ctrl => return (!editor.redo()).then(|| "Nothing to redo".to_string()),
+        KeyCode::Char('w') if ctrl => editor.search = Some(String::new()),
+        KeyCode::Char('n') if ctrl && editor.query.is_empty() => return Some("Nothing searched for yet (Ctrl+W)".to_string()),
+        KeyCode::Char('n') if ctrl => return (!editor.find_next()).then(|| format!("No match for `{}`", editor.query)),
+        KeyCode::Home if ctrl => editor.top(),
+        KeyCode::End if ctrl => editor.bottom(),
+        _ if ctrl => {}
+        KeyCode::Char(c) => editor.insert(c),
+        KeyCode::Enter => editor.newline(),
+        KeyCode::Backspace => editor.backspace(),
+        KeyCode::Delete => editor.delete(),
+        KeyCode::Left => editor.left(),
+        KeyCode::Right => editor.right(),
+        KeyCode::Up => editor.vertical(-1),
+        KeyCode::Down => editor.vertical(1),
+        KeyCode::Home => editor.home(),
+        KeyCode::End => editor.end(),
+        _ => {}
+    }
+    None
+}
+
+/// Block title for an editor: the find prompt while one is typed, else `title`
+fn editor_title(editor: &TextArea, title: String) -> String {
+    match &editor.search {
+        Some(query) => format!("Find: {}_   Enter: Go   Esc: Cancel", query),
+        None => title,
+    }
+}
+
+/// A new directory under the system temp dir that only this user can enter
+fn private_dir() -> std::io::Result<PathBuf> {
+    let dir = std::env::temp_dir().join(format!("belch-{}-{}", std::process::id(), random::hex(8)));
+    let mut builder = std::fs::DirBuilder::new();
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(&dir)?;
+    Ok(dir)
+}
+
+/// Write `data` to `path`, which must not exist yet, readable by this user only
+fn write_new(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(data)
+}
+
+/// Hand `data` to `$EDITOR` (vi if unset) in a temporary `.ext` file, with the TUI suspended until
+/// it exits. The file is new, in a private directory, so other users can't read or replace it.
+fn edit_externally(terminal: &mut Terminal<Backend>, data: &[u8], ext: &str) -> Result<Vec<u8>, String> {
+    let dir = private_dir().map_err(|e| format!("Could not create a temporary directory: {}", e))?;
+    let path = dir.join(format!("edit.{}", ext));
+    if let Err(e) = write_new(&path, data) {
+        let _ = std::fs::remove_dir_all(&dir);
+        return Err(format!("Could not write {}: {}", path.display(), e));
+    }
+    let editor = std::env::var("EDITOR").ok().filter(|e| !e.trim().is_empty()).unwrap_or_else(|| "vi".to_string());
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+    let _ = disable_raw_mode();
+    let _ = execute!(terminal.backend_mut(), LeaveAlternateScreen, DisableMouseCapture, DisableBracketedPaste);
+    let status = std::process::Command::new(program).args(words).arg(&path).status();
+    let _ = enable_raw_mode();
+    let _ = execute!(terminal.backend_mut(), EnterAlternateScreen, EnableMouseCapture, EnableBracketedPaste);
+    let _ = terminal.clear();
+    let edited = std::fs::read(&path);
+    // Editors may leave swap or backup files next to it
+    let _ = std::fs::remove_dir_all(&dir);
+    match status {
+        Ok(s) if s.success() => {}
+        Ok(s) => return Err(format!("{} exited with {}; edits discarded", program, s)),
+        Err(e) => return Err(format!("Could not run {}: {}", program, e)),
+    }
+    let mut edited = edited.map_err(|e| format!("Could not read back {}: {}", path.display(), e))?;
+    // Editors end the file with a newline the buffer didn't have
+    if !data.ends_with(b"\n") && edited.ends_with(b"\n") {
+        edited.pop();
+        if edited.ends_with(b"\r") {
+            edited.pop();
+        }
+    }
+    Ok(edited)
+}
+
+/// File extension for a body, from the `Content-Type` in `head`
+fn body_extension(head: &str) -> &'static str {
+    http::header_value(head, "content-type").and_then(http::extension_for).unwrap_or("txt")
+}
+
+/// Run `$EDITOR` on the buffer a key asked for and put the result back
+fn run_external_edit(terminal: &mut Terminal<Backend>, app: &Arc<Mutex<App>>, target: External) {
+    let opened = {
+        let guard = app.lock().unwrap();
+        match target {
+            External::Repeater => guard.repeater.as_ref().map(|r| (r.editor.text("\n").into_bytes(), "http")),
+            External::Intercept(id) => guard.paused.iter().find(|p| p.id == id).map(|p| {
+                let request = p.table.to_request();
+                let (head, body) = http::split_raw(&request);
+                let mut text = String::from_utf8_lossy(head).replace("\r\n", "\n").into_bytes();
+                text.extend_from_slice(b"\n\n");
+                text.extend_from_slice(body);
+                (text, "http")
+            }),
+            External::InterceptBody(id) => guard.paused.iter().find(|p| p.id == id).map(|p| {
+                let head: String = p.table.rows.iter().map(|(k, v)| format!("\n{}: {}", k, v)).collect();
+                (p.table.body.clone(), body_extension(&head))
+            }),
+            External::Entry { index, response } => guard.logs.get(index).map(|log| match response {
+                true => (log.response_body(), body_extension(&log.response_head())),
+                false => {
+                    let raw = log.request_raw.bytes();
+                    let (head, body) = http::split_raw(&raw);
+                    let head = String::from_utf8_lossy(head);
+                    (http::decode_body(&head, body), body_extension(&head))
+                }
+            }),
+        }
+    };
+    let Some((data, ext)) = opened else { return };
+    let edited = edit_externally(terminal, &data, ext);
+    let mut guard = app.lock().unwrap();
+    let edited = match edited {
+        Ok(edited) if edited == data => return guard.set_status("No changes from the editor"),
+        Ok(edited) => edited,
+        Err(e) => return guard.set_status(e),
+    };
+    match target {
+        External::Repeater => {
+            if let Some(repeater) = guard.repeater.as_mut() {
+                repeater.editor.set_text(&String::from_utf8_lossy(&edited));
+            }
+        }
+        External::Intercept(id) => {
+            // The head goes back to CRLF; the body is kept as written
+            let at = edited.windows(2).position(|w| w == b"\n\n");
+            let (head, body) = at.map_or((&edited[..], &b""[..]), |i| (&edited[..i], &edited[i + 2..]));
+            let head = String::from_utf8_lossy(head).replace("\r\n", "\n").replace('\n', "\r\n");
+            let request = [head.as_bytes(), b"\r\n\r\n", body].concat();
+            // The request may have been let through, or dropped, while the editor was open
+            match guard.paused.iter_mut().find(|p| p.id == id) {
+                Some(paused) => paused.table = HeaderTable::parse(&request),
+                None => return guard.set_status("The request was released while editing; edits discarded"),
+            }
+        }
+        External::InterceptBody(id) => match guard.paused.iter_mut().find(|p| p.id == id) {
+            Some(paused) => paused.table.body = edited,
+            None => return guard.set_status("The request was released while editing; edits discarded"),
+        },
+        External::Entry { .. } => return guard.set_status("Captured entries don't change; edit a copy in the Repeater (R)"),
+    }
+    guard.set_status("Edits from the editor applied");
+}
+
+fn handle_recon_key(app: &mut App, code: KeyCode) {
+    let Some(view) = app.recon.as_mut() else { return };
+    match code {
+        KeyCode::Esc | KeyCode::Char('U') => app.recon = None,
+        KeyCode::Up => view.selected = view.selected.saturating_sub(1),
+        KeyCode::Down if view.selected + 1 < view.found.len() => view.selected += 1,
+        KeyCode::PageUp => view.selected = view.selected.saturating_sub(20),
+        KeyCode::PageDown => view.selected = (view.selected + 20).min(view.found.len().saturating_sub(1)),
+        KeyCode::Char('w') => {
+            let msg = match export::recon(&view.found, std::path::Path::new(".")) {
+                Ok(path) => format!("Saved {}", path.display()),
+                Err(e) => format!("Save failed: {}", e),
+            };
+            app.set_status(msg);
+        }
+        _ => {}
+    }
+}
+
+fn draw_ca_info(f: &mut Frame<Backend>, ca: &CertAuthority, size: Rect) {
+    let area = centered(size, 70, 40);
+    let label = |s: &'static str| Span::styled(format!("{:<12}", s), Style::default().fg(Color::Cyan));
+    let days = ca.days_left();
+    let expiry_style = if days < 30 { Style::default().fg(Color::Red) } else { Style::default() };
+    let lines = vec![
+        Spans::from(vec![label("Path"), Span::raw(ca.cert_path.display().to_string())]),
+        Spans::from(vec![label("SHA-256"), Span::raw(ca.fingerprint())]),
+        Spans::from(vec![label("Not before"), Span::raw(ca.not_before.date().to_string())]),
+        Spans::from(vec![
+            label("Not after"),
+            Span::styled(format!("{} ({} days left)", ca.not_after.date(), days), expiry_style),
+        ]),
+        Spans::from(""),
+        Spans::from(Span::styled(
+            "belch ca export | regenerate | install      Esc: Close",
+            Style::default().fg(Color::DarkGray),
+        )),
+    ];
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title("Root CA"))
+            .wrap(Wrap { trim: false }),
+        area,
+    );
+}
+
+/// Start the CA download server on first use and show the onboarding popup
+fn open_device_setup(app: &mut App, ca: &Arc<CertAuthority>) {
+    let ip = onboard::lan_ip().map_or("127.0.0.1".to_string(), |ip| ip.to_string());
+    if app.setup_url.is_none() {
+        app.setup_url = Some(match onboard::start(ca.clone()) {
+            Ok(addr) => format!("http://{}:{}/", ip, addr.port()),
+            Err(e) => format!("[setup server failed: {}]", e),
+        });
+    }
+    let url = app.setup_url.clone().unwrap_or_default();
+    app.device_setup = Some(DeviceSetup { qr: onboard::qr_lines(&url), url, ios: false });
+}
+
+fn draw_device_setup(f: &mut Frame<Backend>, app: &App, setup: &DeviceSetup, size: Rect) {
+    let area = centered(size, 90, 90);
+    f.render_widget(Clear, area);
+    let title = if setup.ios { "Device setup: iOS (Tab: Android)" } else { "Device setup: Android (Tab: iOS)" };
+    f.render_widget(Block::default().borders(Borders::ALL).title(title), area);
+    let inner = Rect::new(area.x + 1, area.y + 1, area.width.saturating_sub(2), area.height.saturating_sub(2));
+    let qr_width = setup.qr.first().map_or(0, |l| l.chars().count() as u16);
+    let cols = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Length(qr_width + 1), Constraint::Min(20)])
+        .split(inner);
+    f.render_widget(
+        Paragraph::new(setup.qr.iter().map(|l| Spans::from(l.as_str())).collect::<Vec<_>>()),
+        cols[0],
+    );
+
+    let (proxy_host, proxy_port) = app.listen.rsplit_once(':').unwrap_or((&app.listen, "1337"));
+    let lan = setup.url.trim_start_matches("http://").split(':').next().unwrap_or("");
+    let proxy_host = if proxy_host == "0.0.0.0" || proxy_host == "::" { lan } else { proxy_host };
+    let mut lines = vec![
+        Spans::from(vec![Span::styled("CA download: ", Style::default().fg(Color::Cyan)), Span::raw(setup.url.clone())]),
+        Spans::from(vec![Span::styled("Proxy:       ", Style::default().fg(Color::Cyan)), Span::raw(format!("{}:{}", proxy_host, proxy_port))]),
+        Spans::from(""),
+    ];
+    if proxy_host.starts_with("127.") || proxy_host == "localhost" {
+        lines.push(Spans::from(Span::styled(
+            "The proxy only listens on loopback; restart with --listen 0.0.0.0:1337 so devices can reach it.",
+            Style::default().fg(Color::Yellow),
+        )));
+        lines.push(Spans::from(""));
+    }
+    let steps = if setup.ios { onboard::IOS_STEPS } else { onboard::ANDROID_STEPS };
+    for (i, step) in steps.iter().enumerate() {
+        let step = step.replace("{proxy_host}", proxy_host)
+            .replace("{proxy_port}", proxy_port)
+            .replace("{url}", &setup.url);
+        lines.push(Spans::from(format!("{}. {}", i + 1, step)));
+    }
+    lines.push(Spans::from(""));
+    lines.push(Spans::from(Span::styled("Tab: Switch OS   Esc: Close", Style::default().fg(Color::DarkGray))));
+    f.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }), cols[1]);
+}
+
+fn handle_finder_key(app: &mut App, code: KeyCode) {
+    let Some(finder) = app.finder.as_mut() else { return };
+    match code {
+        KeyCode::Esc => app.finder = None,
+        KeyCode::Up => finder.selected = finder.selected.saturating_sub(1),
+        KeyCode::Down if finder.selected + 1 < finder.results.len() => finder.selected += 1,
+        KeyCode::Char(c) => {
+            finder.query.push(c);
+            finder.update();
+        }
+        KeyCode::Backspace => {
+            finder.query.pop();
+            finder.update();
+        }
+        KeyCode::Enter => {
+            let target = finder.results.get(finder.selected).copied();
+            app.finder = None;
+            if let Some(index) = target {
+                app.jump_to(index);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The built-in key of the action chosen, to be handled as if pressed
+fn handle_palette_key(app: &mut App, code: KeyCode) -> Option<char> {
+    let palette = app.palette.as_mut()?;
+    match code {
+        KeyCode::Esc | KeyCode::F(1) => app.palette = None,
+        KeyCode::Up => palette.selected = palette.selected.saturating_sub(1),
+        KeyCode::Down if palette.selected + 1 < palette.results.len() => palette.selected += 1,
+        KeyCode::Char(c) => {
+            palette.query.push(c);
+            palette.update();
+        }
+        KeyCode::Backspace => {
+            palette.query.pop();
+            palette.update();
+        }
+        KeyCode::Enter => {
+            let chosen = palette.chosen();
+            app.palette = None;
+            match chosen {
+                Some(Target::Key(builtin)) => return Some(builtin),
+                Some(Target::Command(name)) => app.command = Some(format!("{} ", name)),
+                None => {}
+            }
+        }
+        _ => {}
+    }
+    None
+}
+
+fn handle_intercept_key(app: &mut App, code: KeyCode) {
+    let Some(table) = app.paused.front_mut().map(|p| &mut p.table) else { return };
+    if let Some((_, input)) = table.editing.as_mut() {
+        match code {
+            KeyCode::Char(c) => input.push(c),
+            KeyCode::Backspace => { input.pop(); }
+            KeyCode::Esc => table.cancel(),
+            KeyCode::Enter => {
+                if let Err(e) = table.commit() {
+                    app.set_status(e);
+                }
+            }
+            _ => {}
+        }
+        return;
+    }
+    match code {
+        KeyCode::Up => table.selected = table.selected.saturating_sub(1),
+        KeyCode::Down if table.selected < table.rows.len() => table.selected += 1,
+        KeyCode::Enter => table.edit(Field::Value),
+        KeyCode::Char('n') => table.edit(Field::Name),
+        KeyCode::Char('a') => table.add(),
+        KeyCode::Char('d') | KeyCode::Delete => table.remove(),
+        KeyCode::Char('f') => {
+            if let Some(paused) = app.paused.pop_front() {
+                paused.forward();
+            }
+        }
+        KeyCode::Char('x') => {
+            if let Some(paused) = app.paused.pop_front() {
+                app.set_status(format!("Dropped a request to {}", paused.host));
+                paused.drop_request();
+            }
+        }
+        KeyCode::Char('E') => app.external_edit = app.paused.front().map(|p| External::Intercept(p.id)),
+        KeyCode::Char('B') => app.external_edit = app.paused.front().map(|p| External::InterceptBody(p.id)),
+        KeyCode::Char('I') => app.toggle_intercept(),
+        KeyCode::Char(':') => app.command = Some(String::new()),
+        _ => {}
+    }
+}
+
+fn handle_passthrough_key(app: &mut App, code: KeyCode) {
+    let count = app.passthrough.hosts.len();
+    let Some(editor) = app.passthrough_editor.as_mut() else { return };
+    if let Some(input) = editor.input.as_mut() {
+        match code {
+            KeyCode::Char(c) => input.push(c),
+            KeyCode::Backspace => { input.pop(); }
+            KeyCode::Esc => editor.input = None,
+            KeyCode::Enter => {
+                let host = editor.input.take().unwrap_or_default();
+                app.passthrough.add(&host);
+            }
+            _ => {}
+        }
+        return;
+    }
+    match code {
+        KeyCode::Esc | KeyCode::Char('P') => app.passthrough_editor = None,
+        KeyCode::Up => editor.selected = editor.selected.saturating_sub(1),
+        KeyCode::Down if editor.selected + 1 < count => editor.selected += 1,
+        KeyCode::Char('a') => editor.input = Some(String::new()),
+        KeyCode::Char('d') | KeyCode::Delete => {
+            let i = editor.selected;
+            app.passthrough.remove(i);
+            if let Some(editor) = app.passthrough_editor.as_mut() {
+                editor.selected = i.min(app.passthrough.hosts.len().saturating_sub(1));
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,138 @@
+// Host header, SNI and CONNECT target disagreements, and Host-header probes on resend
+
+use std::sync::{Arc, Mutex};
+
+use tracing::info;
+
+use crate::app::{App, HttpLog};
+use crate::http;
+use crate::issues::{Issue, Severity};
+use crate::send;
+use crate::throttle;
+
+/// Host name no upstream should know, so seeing it come back means it was used
+const CANARY: &str = "belch-probe.example";
+
+/// Host name without port, brackets, trailing dot or case
+fn normalize(host: &str) -> String {
+    let host = match host.strip_prefix('[') {
+        Some(rest) => rest.split(']').next().unwrap_or(""),
+        // A single colon separates the port; more means a bare IPv6 address
+        None if host.matches(':').count() == 1 => host.split(':').next().unwrap_or(""),
+        None => host,
+    };
+    host.trim_end_matches('.').to_lowercase()
+}
+
+/// Issue when the Host header, SNI and CONNECT target or absolute URL name different hosts
+pub fn passive(log: &HttpLog) -> Vec<Issue> {
+    let raw = log.request_raw.bytes();
+    let Some(head) = http::RequestHead::parse(&raw) else { return Vec::new() };
+    let mut names: Vec<(&str, &str)> = Vec::new();
+    if log.tls {
+        names.push(("CONNECT", &log.host));
+    }
+    if let Some(sni) = &log.sni {
+        names.push(("SNI", sni));
+    }
+    if let Some(host) = head.header_str("host") {
+        names.push(("Host", host));
+    }
+    if let Some((_, rest)) = head.target.split_once("://") {
+        names.push(("URL", rest.split(['/', '?', '#']).next().unwrap_or("")));
+    }
+    let first = names.first().map(|(_, h)| normalize(h));
+    if names.iter().all(|(_, h)| Some(normalize(h)) == first) {
+        return Vec::new();
+    }
+    let detail: Vec<String> = names.iter().map(|(label, h)| format!("{} {}", label, h)).collect();
+    vec![Issue {
+        severity: Severity::Low,
+        host: log.host.clone(),
+        url: log.full_url(),
+        title: "Host mismatch".to_string(),
+        detail: detail.join(", "),
+    }]
+}
+
+/// Request with a second Host header ahead of the original
+fn duplicate_host(request: &[u8]) -> Vec<u8> {
+    let line_end = request.windows(2).position(|w| w == b"\r\n").map_or(0, |i| i + 2);
+    let mut out = request[..line_end].to_vec();
+    out.extend_from_slice(format!("Host: {}\r\n", CANARY).as_bytes());
+    out.extend_from_slice(&request[line_end..]);
+    out
+}
+
+/// Request with an absolute-form target naming another host
+fn absolute_target(request: &[u8], tls: bool) -> Vec<u8> {
+    let Some(head) = http::RequestHead::parse(request) else { return request.to_vec() };
+    let scheme = if tls { "https" } else { "http" };
+    let line_end = request.windows(2).position(|w| w == b"\r\n").unwrap_or(0);
+    let version = std::str::from_utf8(&request[..line_end]).ok().and_then(|l| l.rsplit(' ').next()).unwrap_or("HTTP/1.1");
+    let mut out = format!("{} {}://{}{} {}", head.method, scheme, CANARY, http::origin_path(head.target), version).into_bytes();
+    out.extend_from_slice(&request[line_end..]);
+    out
+}
+
+/// Status, head and decoded body of a raw response
+fn summarize(response: &[u8]) -> (u16, String, Vec<u8>) {
+    let (head, body) = http::split_raw(response);
+    let head = String::from_utf8_lossy(head).to_string();
+    (http::status_code(&head).unwrap_or(0), head.clone(), http::decode_body(&head, body))
+}
+
+/// Resend the entry to the same connection target with desynchronized Host values in the background
+pub fn probe(app: Arc<Mutex<App>>, log: HttpLog) {
+    tokio::spawn(async move {
+        let base = send::replayable(&log);
+        let via = app.lock().unwrap().routes.route(&log.host).cloned();
+        let port = send::target_port(&log);
+        let fetch = |req: Vec<u8>| {
+            let (host, via) = (log.host.clone(), via.clone());
+            async move {
+                let _permit = throttle::acquire(&host).await;
+                send::send(&host, port, log.tls, &req, via.as_ref()).await.ok().map(|r| summarize(&r))
+            }
+        };
+        let Some((base_status, _, base_body)) = fetch(base.clone()).await else {
+            return app.lock().unwrap().set_status("Host probe: request failed");
+        };
+        let variants = [
+            ("Host", http::set_header(&base, "Host", CANARY)),
+            ("Host localhost", http::set_header(&base, "Host", "localhost")),
+            ("X-Forwarded-Host", http::set_header(&base, "X-Forwarded-Host", CANARY)),
+            ("duplicate Host", duplicate_host(&base)),
+            ("absolute URL", absolute_target(&base, log.tls)),
+        ];
+        let mut found = 0;
+        for (label, request) in variants {
+            let Some((status, head, body)) = fetch(request).await else { continue };
+            let location = http::header_value(&head, "location").is_some_and(|l| l.contains(CANARY));
+            let in_body = body.windows(CANARY.len()).any(|w| w == CANARY.as_bytes());
+            let diff = body.len().abs_diff(base_body.len());
+            let finding = if location || in_body {
+                let place = if location { "Location header" } else { "response body" };
+                Some((Severity::Medium, "Host header injection", format!("{} reflected in {}", CANARY, place)))
+            } else if status != base_status {
+                Some((Severity::Info, "Host routing differs", format!("status {} → {}", base_status, status)))
+            } else if diff > 64 && diff * 10 > base_body.len() {
+                Some((Severity::Info, "Host routing differs", format!("length {} → {}", base_body.len(), body.len())))
+            } else {
+                None
+            };
+            if let Some((severity, title, detail)) = finding {
+                found += 1;
+                app.lock().unwrap().add_issue(Issue {
+                    severity,
+                    host: log.host.clone(),
+                    url: log.full_url(),
+                    title: format!("{} ({})", title, label),
+                    detail,
+                });
+            }
+        }
+        info!("Host probe of {} finished: {} findings", log.full_url(), found);
+        app.lock().unwrap().set_status(format!("Host probe {}: {} finding(s)", log.host, found));
+    });
+}
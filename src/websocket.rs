@@ -0,0 +1,305 @@
+// WebSocket upgrades: relayed frame by frame so each message is recorded
+
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use base64::Engine;
+use bytes::Bytes;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tracing::{debug, warn};
+
+use crate::app::{App, HttpLog};
+use crate::http::{self, RequestHead};
+use crate::proxy::HOP_BY_HOP;
+use crate::{send, upstream};
+
+/// Largest frame payload accepted; anything bigger ends the relay
+const MAX_FRAME: u64 = 64 * 1024 * 1024;
+
+pub struct WsMessage {
+    /// Index of the handshake entry in `App::logs`
+    pub entry: usize,
+    /// Since the upgrade completed
+    pub at: Duration,
+    pub to_server: bool,
+    pub opcode: u8,
+    pub payload: Bytes,
+}
+
+/// Name of a frame opcode
+pub fn opcode_name(opcode: u8) -> &'static str {
+    match opcode {
+        1 => "text",
+        2 => "binary",
+        8 => "close",
+        9 => "ping",
+        10 => "pong",
+        _ => "other",
+    }
+}
+
+/// Messages shown for a connection: by direction, opcode and search text
+#[derive(Clone, Copy, Default)]
+pub struct WsFilter {
+    /// `Some(true)` for client → server only
+    pub to_server: Option<bool>,
+    pub opcode: Option<u8>,
+}
+
+impl WsFilter {
+    /// Apply `:ws` words such as `out text` or `both all`
+    pub fn update(&mut self, words: &[&str]) -> Result<(), String> {
+        for word in words {
+            match *word {
+                "out" => self.to_server = Some(true),
+                "in" => self.to_server = Some(false),
+                "both" => self.to_server = None,
+                "all" => self.opcode = None,
+                name => {
+                    let opcode = (0..=10).find(|o| opcode_name(*o) == name && name != "other")
+                        .ok_or_else(|| format!("Unknown `:ws` word `{}`: in, out, both, text, binary, ping, pong, close, all", name))?;
+                    self.opcode = Some(opcode);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn matches(&self, m: &WsMessage) -> bool {
+        self.to_server.is_none_or(|d| d == m.to_server) && self.opcode.is_none_or(|o| o == m.opcode)
+    }
+
+    pub fn label(&self) -> String {
+        let direction = match self.to_server {
+            Some(true) => "out",
+            Some(false) => "in",
+            None => "both",
+        };
+        format!("{}, {}", direction, self.opcode.map_or("all", opcode_name))
+    }
+}
+
+pub fn is_upgrade(head: &RequestHead) -> bool {
+    head.header_str("upgrade").is_some_and(|u| u.eq_ignore_ascii_case("websocket"))
+}
+
+/// The upgrade request for the origin server. Extensions are not offered,
+/// so permessage-deflate never hides payloads from the log.
+fn upgrade_request(request: &[u8]) -> Vec<u8> {
+    let Some(head) = RequestHead::parse(request) else { return request.to_vec() };
+    let line_end = request.windows(2).position(|w| w == b"\r\n").unwrap_or(0);
+    let version = std::str::from_utf8(&request[..line_end]).ok().and_then(|l| l.rsplit(' ').next()).unwrap_or("HTTP/1.1");
+    let mut out = format!("{} {} {}\r\n", head.method, http::origin_path(head.target), version).into_bytes();
+    for (name, value) in &head.headers {
+        let lower = name.to_lowercase();
+        if HOP_BY_HOP.contains(&lower.as_str()) || lower == "upgrade" || lower == "sec-websocket-extensions" {
+            continue;
+        }
+        out.extend_from_slice(name.as_bytes());
+        out.extend_from_slice(b": ");
+        out.extend_from_slice(value);
+        out.extend_from_slice(b"\r\n");
+    }
+    out.extend_from_slice(b"Connection: Upgrade\r\nUpgrade: websocket\r\n\r\n");
+    out
+}
+
+struct Frame {
+    /// As received, for forwarding untouched
+    raw: Vec<u8>,
+    fin: bool,
+    opcode: u8,
+    /// Unmasked
+    payload: Vec<u8>,
+}
+
+async fn read_frame<R: AsyncRead + Unpin>(r: &mut R) -> io::Result<Frame> {
+    let mut head = [0u8; 2];
+    r.read_exact(&mut head).await?;
+    let mut raw = head.to_vec();
+    let masked = head[1] & 0x80 != 0;
+    let len = match head[1] & 0x7f {
+        126 => {
+            let mut ext = [0u8; 2];
+            r.read_exact(&mut ext).await?;
+            raw.extend_from_slice(&ext);
+            u16::from_be_bytes(ext) as u64
+        }
+        127 => {
+            let mut ext = [0u8; 8];
+            r.read_exact(&mut ext).await?;
+            raw.extend_from_slice(&ext);
+            u64::from_be_bytes(ext)
+        }
+        n => n as u64,
+    };
+    if len > MAX_FRAME {
+        return Err(io::Error::other(format!("{} byte frame", len)));
+    }
+    let mut mask = [0u8; 4];
+    if masked {
+        r.read_exact(&mut mask).await?;
+        raw.extend_from_slice(&mask);
+    }
+    let mut payload = vec![0u8; len as usize];
+    r.read_exact(&mut payload).await?;
+    raw.extend_from_slice(&payload);
+    if masked {
+        for (i, b) in payload.iter_mut().enumerate() {
+            *b ^= mask[i % 4];
+        }
+    }
+    Ok(Frame { raw, fin: head[0] & 0x80 != 0, opcode: head[0] & 0x0f, payload })
+}
+
+/// Opcode and unmasked payload of the next frame
+pub async fn read_message<R: AsyncRead + Unpin>(r: &mut R) -> io::Result<(u8, Vec<u8>)> {
+    read_frame(r).await.map(|f| (f.opcode, f.payload))
+}
+
+/// A final, unmasked frame as a server sends it
+pub fn frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x80 | opcode];
+    match payload.len() {
+        n if n < 126 => out.push(n as u8),
+        n if n <= u16::MAX as usize => {
+            out.push(126);
+            out.extend_from_slice(&(n as u16).to_be_bytes());
+        }
+        n => {
+            out.push(127);
+            out.extend_from_slice(&(n as u64).to_be_bytes());
+        }
+    }
+    out.extend_from_slice(payload);
+    out
+}
+
+/// `Sec-WebSocket-Accept` for a handshake's `Sec-WebSocket-Key`
+pub fn accept_key(key: &str) -> String {
+    let digest = ring::digest::digest(&ring::digest::SHA1_FOR_LEGACY_USE_ONLY, format!("{}258EAFA5-E914-47DA-95CA-C5AB0DC85B11", key.trim()).as_bytes());
+    base64::engine::general_purpose::STANDARD.encode(digest.as_ref())
+}
+
+/// Forward frames one way, recording each complete message
+async fn pump<R, W>(mut from: R, mut to: W, app: &Arc<Mutex<App>>, entry: usize, to_server: bool, started: Instant)
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    // Opcode and payload of a fragmented message so far
+    let mut partial: Option<(u8, Vec<u8>)> = None;
+    loop {
+        let frame = match read_frame(&mut from).await {
+            Ok(f) => f,
+            Err(e) => {
+                debug!("websocket relay ended: {}", e);
+                break;
+            }
+        };
+        if to.write_all(&frame.raw).await.is_err() {
+            break;
+        }
+        let message = match (frame.opcode, partial.as_mut()) {
+            (0, Some((_, payload))) => {
+                payload.extend_from_slice(&frame.payload);
+                if frame.fin { partial.take() } else { None }
+            }
+            // Control frames may arrive between fragments
+            (op, _) if op >= 8 => Some((op, frame.payload)),
+            (op, _) if frame.fin => Some((op, frame.payload)),
+            (op, _) => {
+                partial = Some((op, frame.payload));
+                None
+            }
+        };
+        if let Some((opcode, payload)) = message {
+            app.lock().unwrap().push_ws(WsMessage { entry, at: started.elapsed(), to_server, opcode, payload: payload.into() });
+            if opcode == 8 {
+                break;
+            }
+        }
+    }
+    let _ = to.shutdown().await;
+}
+
+/// Complete the upgrade with the origin server, then relay and record both directions
+async fn relay_with<C, U>(client: C, mut upstream: U, mut request: Vec<u8>, mut entry: HttpLog, app: Arc<Mutex<App>>)
+where
+    C: AsyncRead + AsyncWrite + Unpin,
+    U: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut client = client;
+    let started = Instant::now();
+    // Clients that do not wait for the 101 may send frames right behind the request
+    let head_end = RequestHead::parse(&request).and_then(|h| h.len).unwrap_or(request.len());
+    let early = request.split_off(head_end);
+    let forward = upgrade_request(&request);
+    let _ = upstream.write_all(&forward).await;
+    // Response head; frames may follow in the same read
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 8192];
+    let head_len = loop {
+        if let Some(i) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break i + 4;
+        }
+        match upstream.read(&mut chunk).await {
+            Ok(0) | Err(_) => break buf.len(),
+            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+        }
+    };
+    let _ = client.write_all(&buf[..head_len]).await;
+    let head = String::from_utf8_lossy(&buf[..head_len]).to_string();
+    let switched = http::status_code(&head) == Some(101);
+    entry.request = String::from_utf8_lossy(&forward).replace("\r\n", "\n").into();
+    entry.request_raw = request.into();
+    entry.response = head.replace("\r\n", "\n").into();
+    entry.response_raw = Bytes::copy_from_slice(&buf[..head_len]).into();
+    entry.duration = started.elapsed();
+    entry.websocket = switched;
+    if switched {
+        entry.url.push_str(" [WebSocket]");
+    }
+    let index = {
+        let mut app = app.lock().unwrap();
+        app.push_log(entry);
+        app.logs.len() - 1
+    };
+    if !switched {
+        // Pass the refusal through as is
+        warn!("websocket upgrade refused: {}", head.lines().next().unwrap_or(""));
+        let _ = client.write_all(&buf[head_len..]).await;
+        let _ = tokio::io::copy_bidirectional(&mut client, &mut upstream).await;
+        return;
+    }
+    let started = Instant::now();
+    let leftover = buf[head_len..].to_vec();
+    let (client_read, client_write) = tokio::io::split(client);
+    let (upstream_read, upstream_write) = tokio::io::split(upstream);
+    tokio::join!(
+        pump((&early[..]).chain(client_read), upstream_write, &app, index, true, started),
+        // Likewise frames in the same read as the response head
+        pump((&leftover[..]).chain(upstream_read), client_write, &app, index, false, started),
+    );
+}
+
+/// Relay an upgrade request from `client` to `host:port`
+pub async fn relay<C>(client: C, request: Vec<u8>, host: &str, port: u16, tls: bool, entry: HttpLog, app: Arc<Mutex<App>>)
+where
+    C: AsyncRead + AsyncWrite + Unpin,
+{
+    let via = app.lock().unwrap().routes.route(host).cloned();
+    let stream = match upstream::connect(via.as_ref(), host, port).await {
+        Ok(s) => s,
+        Err(e) => return warn!("websocket connect to {}:{} failed: {}", host, port, e),
+    };
+    if tls {
+        match send::tls_connect(host, stream).await {
+            Ok(s) => relay_with(client, s, request, entry, app).await,
+            Err((_, reason)) => warn!("websocket to {}: {}", host, reason),
+        }
+    } else {
+        relay_with(client, stream, request, entry, app).await;
+    }
+}